@@ -0,0 +1,107 @@
+//! Benchmarks of the kernels every step goes through, at several system
+//! sizes: `cargo bench`, or `cargo bench -- exchange` for some of them.
+
+use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+use nalgebra::Vector3;
+use std::hint::black_box;
+
+use nez::{
+    Mesh, Params, Simulation, VectorField, ZarrOutput, config::OutputConfig,
+    field::add_exchange_field, llg::llg_rhs,
+};
+
+/// Films of one layer and a cube, from a few thousand cells to a million
+const SIZES: [[usize; 3]; 4] = [[64, 64, 1], [256, 256, 1], [1024, 1024, 1], [64, 64, 64]];
+
+fn mesh(n: [usize; 3]) -> Mesh {
+    Mesh::new(n, [2e-9; 3])
+}
+
+/// A smoothly varying magnetization, so that no field vanishes
+fn texture(mesh: &Mesh) -> VectorField {
+    VectorField::from_fn(mesh.len(), |i| {
+        let [x, y, z] = mesh.coords(i).map(|c| c as f64 * 0.1);
+        Vector3::new(x.sin(), y.cos(), 1.0 + z.sin()).normalize()
+    })
+}
+
+fn name(n: [usize; 3]) -> String {
+    format!("{}x{}x{}", n[0], n[1], n[2])
+}
+
+fn bench_llg_rhs(c: &mut Criterion) {
+    let p = Params {
+        alpha: 0.02,
+        ..Params::default()
+    };
+    let m = Vector3::new(0.6, 0.0, 0.8);
+    let h = Vector3::new(0.0, 0.1, 1.0);
+    c.bench_function("llg_rhs", |b| {
+        b.iter(|| llg_rhs(black_box(&m), black_box(&h), black_box(&p)))
+    });
+}
+
+fn bench_exchange(c: &mut Criterion) {
+    let mut group = c.benchmark_group("exchange_field");
+    let p = Params::default();
+    for n in SIZES {
+        let mesh = mesh(n);
+        let m = texture(&mesh);
+        let mut h = VectorField::zeros(mesh.len());
+        group.throughput(Throughput::Elements(mesh.len() as u64));
+        group.bench_function(BenchmarkId::from_parameter(name(n)), |b| {
+            b.iter(|| add_exchange_field(&m, &mesh, &p, &mut h))
+        });
+    }
+    group.finish();
+}
+
+fn bench_rk4_step(c: &mut Criterion) {
+    let mut group = c.benchmark_group("rk4_step");
+    group.sample_size(20);
+    for n in SIZES {
+        let mesh = mesh(n);
+        // the default solver: rk4 with exchange and the applied field
+        let mut sim = Simulation::new(mesh, Params::default(), Vector3::z());
+        sim.m = texture(&mesh);
+        group.throughput(Throughput::Elements(mesh.len() as u64));
+        group.bench_function(BenchmarkId::from_parameter(name(n)), |b| {
+            b.iter(|| sim.step())
+        });
+    }
+    group.finish();
+}
+
+fn bench_zarr_write(c: &mut Criterion) {
+    let mut group = c.benchmark_group("zarr_write");
+    group.sample_size(20);
+    for n in SIZES {
+        let mesh = mesh(n);
+        let m = texture(&mesh);
+        let config = OutputConfig {
+            path: format!("memory://{}", name(n)).into(),
+            ..OutputConfig::default()
+        };
+        let mut output = ZarrOutput::create(&config, &mesh).unwrap();
+        let mut frame = 0;
+        group.throughput(Throughput::Bytes(3 * 8 * mesh.len() as u64));
+        group.bench_function(BenchmarkId::from_parameter(name(n)), |b| {
+            b.iter(|| {
+                output.write(frame, frame as f64 * 1e-12, &m).unwrap();
+                // over and over the same few frames, so that the store held
+                // in memory stops growing
+                frame = (frame + 1) % 8;
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(
+    kernels,
+    bench_llg_rhs,
+    bench_exchange,
+    bench_rk4_step,
+    bench_zarr_write
+);
+criterion_main!(kernels);
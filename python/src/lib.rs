@@ -0,0 +1,285 @@
+//! Python bindings: the `nez` module.
+//!
+//! ```python
+//! import nez
+//!
+//! sim = nez.Simulation.load("config.toml")   # or nez.Simulation(toml_text)
+//! sim.alpha = 0.02
+//! sim.h_ext = (0.0, 0.0, 0.1)
+//! sim.run(1000)
+//! m = sim.m                                   # numpy array (nz, ny, nx, 3)
+//! m[..., 2] *= -1                             # changes the copy only
+//! sim.m = m                                   # sets the state
+//! ```
+//!
+//! `m` is a copy, not a view of the state: the steppers swap the buffers of
+//! the magnetization at every step, so no array could keep pointing at it,
+//! and writing to the array leaves the simulation alone until it is
+//! assigned back to `sim.m`.
+//!
+//! Simulations built here write no output unless `output=True` is passed,
+//! in which case they create the Zarr store of `output.path` as `nez run`.
+
+use nalgebra::Vector3;
+use numpy::{PyArray1, PyArrayMethods, PyReadonlyArrayDyn};
+use pyo3::{exceptions::PyValueError, prelude::*, types::PyDict};
+
+use nez as core;
+
+fn err(e: core::Error) -> PyErr {
+    PyValueError::new_err(e.to_string())
+}
+
+/// A magnetic sample with its parameters and state, built from a TOML config
+#[pyclass(module = "nez")]
+struct Simulation {
+    inner: core::Simulation,
+    config: core::Config,
+}
+
+impl Simulation {
+    fn build(config: core::Config, output: bool) -> PyResult<Self> {
+        let inner = if output {
+            core::Simulation::from_config(&config)
+        } else {
+            core::Simulation::build(&config)
+        }
+        .map_err(err)?;
+        Ok(Self { inner, config })
+    }
+}
+
+#[pymethods]
+impl Simulation {
+    /// Simulation described by TOML text (all keys optional)
+    #[new]
+    #[pyo3(signature = (toml = "", output = false))]
+    fn new(toml: &str, output: bool) -> PyResult<Self> {
+        Self::build(core::Config::parse(toml).map_err(err)?, output)
+    }
+
+    /// Simulation described by a TOML file
+    #[staticmethod]
+    #[pyo3(signature = (path, output = false))]
+    fn load(path: &str, output: bool) -> PyResult<Self> {
+        Self::build(core::Config::load(path).map_err(err)?, output)
+    }
+
+    /// Advance by `n` steps, writing output as configured
+    fn run(&mut self, py: Python<'_>, n: u64) -> PyResult<()> {
+        let sim = &mut self.inner;
+        py.detach(|| sim.run(n)).map_err(err)
+    }
+
+    /// Relax with damping-only dynamics; returns (converged, iterations, max torque)
+    #[pyo3(signature = (tolerance = None))]
+    fn relax(&mut self, py: Python<'_>, tolerance: Option<f64>) -> (bool, u64, f64) {
+        let mut config = self.config.relax.clone();
+        if let Some(t) = tolerance {
+            config.tolerance = t;
+        }
+        let sim = &mut self.inner;
+        let r = py.detach(|| sim.relax(&config));
+        (r.converged, r.iterations, r.torque)
+    }
+
+    /// Minimize the energy; returns (converged, iterations, max torque)
+    #[pyo3(signature = (tolerance = None))]
+    fn minimize(&mut self, py: Python<'_>, tolerance: Option<f64>) -> (bool, u64, f64) {
+        let mut config = self.config.minimize.clone();
+        if let Some(t) = tolerance {
+            config.tolerance = t;
+        }
+        let sim = &mut self.inner;
+        let r = py.detach(|| sim.minimize(&config));
+        (r.converged, r.iterations, r.torque)
+    }
+
+    /// Write the current state to the store (no-op without output)
+    fn save(&mut self) -> PyResult<()> {
+        self.inner.save().map_err(err)
+    }
+
+    /// Copy of the magnetization, an array of shape (nz, ny, nx, 3), or
+    /// (2, nz, ny, nx, 3) with a second sublattice; writing to it does not
+    /// change the state, assigning it to `m` does
+    #[getter]
+    fn m<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        let mesh = &self.inner.mesh;
+        let flat: Vec<f64> = self.inner.m.iter().flat_map(|v| [v.x, v.y, v.z]).collect();
+        let mut shape = vec![mesh.nz, mesh.ny, mesh.nx, 3];
+        if self.inner.sublattice.is_some() {
+            shape.insert(0, 2);
+        }
+        let array = PyArray1::from_vec(py, flat).reshape(shape)?;
+        Ok(array.into_any())
+    }
+
+    /// Replace the magnetization by an array of the shape of `m`, or
+    /// (ncells, 3); vectors are normalised, and ignored outside the
+    /// geometry
+    #[setter]
+    fn set_m(&mut self, m: PyReadonlyArrayDyn<'_, f64>) -> PyResult<()> {
+        let cells = self.inner.mesh.len();
+        let view = m.as_array();
+        let n = self.inner.m.len();
+        if view.len() != 3 * n || view.shape().last() != Some(&3) {
+            return Err(PyValueError::new_err(format!(
+                "expected {n} vectors of 3 components, got shape {:?}",
+                view.shape()
+            )));
+        }
+        let values: Vec<f64> = view.iter().copied().collect();
+        let geometry = self.inner.params.geometry.as_deref();
+        let m: Option<core::VectorField> = values
+            .chunks_exact(3)
+            .enumerate()
+            .map(|(i, v)| match geometry {
+                // no moment outside the sample
+                Some(g) if !g.contains(i % cells) => Some(Vector3::zeros()),
+                _ => Vector3::new(v[0], v[1], v[2]).try_normalize(0.0),
+            })
+            .collect();
+        self.inner.m = m.ok_or_else(|| PyValueError::new_err("`m` has zero vectors"))?;
+        Ok(())
+    }
+
+    /// Spatially averaged magnetization, of the first sublattice if there
+    /// are two
+    fn average(&self) -> (f64, f64, f64) {
+        let m = self.inner.average();
+        (m.x, m.y, m.z)
+    }
+
+    /// Total energy (J)
+    fn energy(&self) -> f64 {
+        self.inner.energy()
+    }
+
+    /// Energy of every term (J)
+    fn energies<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        let dict = PyDict::new(py);
+        for (name, e) in self.inner.energies() {
+            dict.set_item(name, e)?;
+        }
+        Ok(dict)
+    }
+
+    /// The columns of the table for the current state
+    fn observables<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        let dict = PyDict::new(py);
+        for (name, v) in self.inner.observables() {
+            dict.set_item(name, v)?;
+        }
+        Ok(dict)
+    }
+
+    /// Simulated time (s)
+    #[getter]
+    fn t(&self) -> f64 {
+        self.inner.t
+    }
+
+    /// Steps taken so far
+    #[getter]
+    fn step(&self) -> u64 {
+        self.inner.step
+    }
+
+    /// Current time step (s)
+    #[getter]
+    fn dt(&self) -> f64 {
+        self.inner.dt
+    }
+
+    #[setter]
+    fn set_dt(&mut self, dt: f64) {
+        self.inner.params.dt = dt;
+        self.inner.dt = dt;
+    }
+
+    /// Gilbert damping
+    #[getter]
+    fn alpha(&self) -> f64 {
+        self.inner.params.alpha
+    }
+
+    #[setter]
+    fn set_alpha(&mut self, alpha: f64) {
+        self.inner.params.alpha = alpha;
+    }
+
+    /// Gyromagnetic ratio (rad s⁻¹ T⁻¹)
+    #[getter]
+    fn gamma(&self) -> f64 {
+        self.inner.params.gamma
+    }
+
+    #[setter]
+    fn set_gamma(&mut self, gamma: f64) {
+        self.inner.params.gamma = gamma;
+    }
+
+    /// Exchange stiffness (J m⁻¹)
+    #[getter]
+    fn a_ex(&self) -> f64 {
+        self.inner.params.a_ex
+    }
+
+    #[setter]
+    fn set_a_ex(&mut self, a_ex: f64) {
+        self.inner.params.a_ex = a_ex;
+    }
+
+    /// μ₀Mₛ (T)
+    #[getter]
+    fn mu0_ms(&self) -> f64 {
+        self.inner.params.mu0_ms
+    }
+
+    #[setter]
+    fn set_mu0_ms(&mut self, mu0_ms: f64) {
+        self.inner.params.mu0_ms = mu0_ms;
+    }
+
+    /// Uniform applied field (T)
+    #[getter]
+    fn h_ext(&self) -> (f64, f64, f64) {
+        let h = self.inner.params.h_ext;
+        (h.x, h.y, h.z)
+    }
+
+    #[setter]
+    fn set_h_ext(&mut self, h: (f64, f64, f64)) {
+        self.inner.params.h_ext = Vector3::new(h.0, h.1, h.2);
+    }
+
+    /// Cell counts (nx, ny, nz)
+    #[getter]
+    fn size(&self) -> (usize, usize, usize) {
+        let mesh = &self.inner.mesh;
+        (mesh.nx, mesh.ny, mesh.nz)
+    }
+
+    /// Cell size (dx, dy, dz) in m
+    #[getter]
+    fn cell(&self) -> (f64, f64, f64) {
+        let [dx, dy, dz] = self.inner.mesh.cell();
+        (dx, dy, dz)
+    }
+
+    fn __repr__(&self) -> String {
+        let mesh = &self.inner.mesh;
+        format!(
+            "Simulation({}x{}x{} cells, step {}, t = {:e} s)",
+            mesh.nx, mesh.ny, mesh.nz, self.inner.step, self.inner.t
+        )
+    }
+}
+
+#[pymodule]
+#[pyo3(name = "nez")]
+fn init(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<Simulation>()?;
+    Ok(())
+}
@@ -0,0 +1,104 @@
+//! Absorbing boundary layers: damping rising smoothly towards chosen faces
+//! of the mesh, so that spin waves die out there instead of reflecting back
+//! into the sample.
+//!
+//! Within `width` of a face, the damping gains `alpha · (d / width)^power`,
+//! with d how deep the cell centre lies in the layer; corners add the layers
+//! of both faces. A ramp over many wavelengths absorbs with little
+//! reflection, since no part of it changes the damping abruptly; a sharp
+//! step would reflect like the edge it replaces. The ramp comes on top of
+//! the damping of every cell, from `[params]`, regions or maps.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use crate::Mesh;
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AbsorbingConfig {
+    /// faces of the mesh with a layer
+    pub boundaries: Vec<Boundary>,
+    /// thickness of each layer (m)
+    pub width: f64,
+    /// damping added at the faces themselves
+    pub alpha: f64,
+    /// exponent of the ramp, 2 rising quadratically from the inner edge
+    pub power: f64,
+}
+
+impl Default for AbsorbingConfig {
+    /// both ends of a chain or strip along x
+    fn default() -> Self {
+        Self {
+            boundaries: vec![Boundary::XMin, Boundary::XMax],
+            width: 100e-9,
+            alpha: 0.5,
+            power: 2.0,
+        }
+    }
+}
+
+/// A face of the mesh, as its axis and side
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub enum Boundary {
+    #[serde(rename = "-x")]
+    XMin,
+    #[serde(rename = "+x")]
+    XMax,
+    #[serde(rename = "-y")]
+    YMin,
+    #[serde(rename = "+y")]
+    YMax,
+    #[serde(rename = "-z")]
+    ZMin,
+    #[serde(rename = "+z")]
+    ZMax,
+}
+
+impl Boundary {
+    pub fn axis(self) -> usize {
+        self as usize / 2
+    }
+
+    /// Whether the face lies at the far end of its axis
+    fn far(self) -> bool {
+        self as usize % 2 == 1
+    }
+}
+
+impl fmt::Display for Boundary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let side = if self.far() { '+' } else { '-' };
+        write!(f, "{side}{}", ['x', 'y', 'z'][self.axis()])
+    }
+}
+
+impl AbsorbingConfig {
+    /// Damping added in every cell of `mesh`
+    pub fn damping(&self, mesh: &Mesh) -> Vec<f64> {
+        (0..mesh.len())
+            .map(|i| {
+                let coords = mesh.coords(i);
+                self.boundaries
+                    .iter()
+                    .map(|&b| {
+                        let a = b.axis();
+                        let n = mesh.size()[a];
+                        let k = if b.far() {
+                            n - 1 - coords[a]
+                        } else {
+                            coords[a]
+                        };
+                        let depth = self.width - (k as f64 + 0.5) * mesh.cell()[a];
+                        if depth > 0.0 {
+                            self.alpha * (depth / self.width).powf(self.power)
+                        } else {
+                            0.0
+                        }
+                    })
+                    .sum()
+            })
+            .collect()
+    }
+}
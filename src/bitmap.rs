@@ -0,0 +1,125 @@
+//! Bitmap images read as grey values, for sample shapes and region maps
+//! drawn in an image editor or exported from a lithography layout.
+//!
+//! PNG files (any bit depth, palette images included) and TIFF files (8 to
+//! 64 bit integer or float samples, uncompressed, LZW or Deflate) are
+//! supported. The pages of a multi-page TIFF form a stack.
+
+use std::{fs::File, io::BufReader, path::Path};
+
+use tiff::decoder::{Decoder, DecodingResult};
+
+use crate::Result;
+
+/// Grey value of every pixel of one or more pages
+#[derive(Debug, Clone, PartialEq)]
+pub struct Bitmap {
+    pub width: usize,
+    pub height: usize,
+    pub pages: usize,
+    /// row by row from the top, page by page
+    values: Vec<f64>,
+}
+
+impl Bitmap {
+    /// Grey value of the pixel in column `x` and row `y` (from the top) of
+    /// page `page`: the raw sample of a grey image, the mean of the colour
+    /// channels otherwise. Alpha is ignored.
+    #[inline(always)]
+    pub fn at(&self, x: usize, y: usize, page: usize) -> f64 {
+        self.values[x + self.width * (y + self.height * page)]
+    }
+}
+
+/// Read a `.png`, `.tif` or `.tiff` file
+pub fn read(path: impl AsRef<Path>) -> Result<Bitmap> {
+    let path = path.as_ref();
+    let ext = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(str::to_lowercase);
+    let file = File::open(path).map_err(|e| format!("cannot read {}: {e}", path.display()))?;
+    let reader = BufReader::new(file);
+    match ext.as_deref() {
+        Some("png") => read_png(reader),
+        Some("tif" | "tiff") => read_tiff(reader),
+        _ => Err("expected a .png, .tif or .tiff image".into()),
+    }
+    .map_err(|e| format!("{}: {e}", path.display()).into())
+}
+
+/// Mean of the first `colours` of every `samples` values
+fn grey(values: &[f64], samples: usize, colours: usize) -> Vec<f64> {
+    values
+        .chunks_exact(samples)
+        .map(|p| p[..colours].iter().sum::<f64>() / colours as f64)
+        .collect()
+}
+
+fn read_png(reader: BufReader<File>) -> Result<Bitmap> {
+    let mut decoder = png::Decoder::new(reader);
+    // palette to RGB, grey below 8 bits to 8 bits
+    decoder.set_transformations(png::Transformations::EXPAND);
+    let mut reader = decoder.read_info()?;
+    let mut buf = vec![0; reader.output_buffer_size().ok_or("image too large")?];
+    let info = reader.next_frame(&mut buf)?;
+    let buf = &buf[..info.buffer_size()];
+    let values: Vec<f64> = match info.bit_depth {
+        png::BitDepth::Sixteen => buf
+            .chunks_exact(2)
+            .map(|b| u16::from_be_bytes([b[0], b[1]]) as f64)
+            .collect(),
+        _ => buf.iter().map(|&b| b as f64).collect(),
+    };
+    let samples = info.color_type.samples();
+    let colours = if samples >= 3 { 3 } else { 1 };
+    Ok(Bitmap {
+        width: info.width as usize,
+        height: info.height as usize,
+        pages: 1,
+        values: grey(&values, samples, colours),
+    })
+}
+
+fn read_tiff(reader: BufReader<File>) -> Result<Bitmap> {
+    let mut decoder = Decoder::new(reader)?;
+    let (width, height) = decoder.dimensions()?;
+    let (mut values, mut pages) = (Vec::new(), 0);
+    loop {
+        if decoder.dimensions()? != (width, height) {
+            return Err("all pages must have the same size".into());
+        }
+        let (samples, colours) = match decoder.colortype()? {
+            tiff::ColorType::Gray(_) => (1, 1),
+            tiff::ColorType::GrayA(_) => (2, 1),
+            tiff::ColorType::RGB(_) => (3, 3),
+            tiff::ColorType::RGBA(_) => (4, 3),
+            other => return Err(format!("unsupported colour type {other:?}").into()),
+        };
+        let page: Vec<f64> = match decoder.read_image()? {
+            DecodingResult::U8(v) => v.into_iter().map(f64::from).collect(),
+            DecodingResult::U16(v) => v.into_iter().map(f64::from).collect(),
+            DecodingResult::U32(v) => v.into_iter().map(f64::from).collect(),
+            DecodingResult::U64(v) => v.into_iter().map(|v| v as f64).collect(),
+            DecodingResult::F16(v) => v.into_iter().map(|v| v.to_f64()).collect(),
+            DecodingResult::F32(v) => v.into_iter().map(f64::from).collect(),
+            DecodingResult::F64(v) => v,
+            DecodingResult::I8(v) => v.into_iter().map(f64::from).collect(),
+            DecodingResult::I16(v) => v.into_iter().map(f64::from).collect(),
+            DecodingResult::I32(v) => v.into_iter().map(f64::from).collect(),
+            DecodingResult::I64(v) => v.into_iter().map(|v| v as f64).collect(),
+        };
+        values.extend(grey(&page, samples, colours));
+        pages += 1;
+        if !decoder.more_images() {
+            break;
+        }
+        decoder.next_image()?;
+    }
+    Ok(Bitmap {
+        width: width as usize,
+        height: height as usize,
+        pages,
+        values,
+    })
+}
@@ -0,0 +1,1493 @@
+use nalgebra::Vector3;
+use serde::{Deserialize, Serialize};
+use std::{fs, path::Path, path::PathBuf};
+
+use crate::{
+    Mesh, Params, Result, VectorField, Waveform,
+    absorbing::AbsorbingConfig,
+    ensemble::EnsembleConfig,
+    field::{DmiKind, HeisenbergConfig, Profile, RkkyConfig, StrainSource, ZeemanSource},
+    geometry::Shape,
+    gneb::GnebConfig,
+    grains::GrainsConfig,
+    hysteresis::HysteresisConfig,
+    langevin::LangevinConfig,
+    llb::LlbConfig,
+    maps::MapsConfig,
+    minimize::MinimizeConfig,
+    monte_carlo::MonteCarloConfig,
+    mtj::MtjConfig,
+    output::{Codec, Layout, Precision, ZarrOutput, is_remote},
+    parallel::ParallelConfig,
+    probe::ProbeConfig,
+    regions::{ExchangeScale, MAX_REGIONS, RegionConfig},
+    snapshot,
+    spin_pumping::SpinPumpingConfig,
+    stages::{Stage, StageKind},
+    stepper::{Device, Method, Stepper},
+    sublattice::SublatticeConfig,
+    sweep::SweepConfig,
+    table::TableConfig,
+    temperature::TemperatureConfig,
+    texture::Texture,
+    wall::WallConfig,
+};
+
+/// Full description of a run, usually read from a TOML file.
+///
+/// Every section and key is optional and falls back to the defaults of the
+/// original hard-coded chain:
+///
+/// ```toml
+/// [mesh]
+/// nx = 128
+/// dx = 2.5e-9
+///
+/// [params]
+/// alpha = 0.2
+/// dt = 1e-14
+/// h_ext = [0.0, 0.0, 1.0]
+///
+/// [run]
+/// steps = 50
+///
+/// [initial]
+/// m = [0.17, 0.0, 0.98]
+///
+/// [output]
+/// path = "magnetization.zarr"
+/// ```
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub mesh: Mesh,
+    pub params: Params,
+    /// shape of the sample; cells outside carry no moment. The whole mesh
+    /// when absent, see [`crate::geometry`]
+    pub geometry: Option<Shape>,
+    /// groups of cells whose material parameters differ from the main
+    /// sections, see [`crate::regions`]
+    pub regions: Vec<RegionConfig>,
+    /// factors on the exchange between regions, see [`crate::regions`]
+    pub exchange_scale: Vec<ExchangeScale>,
+    /// material parameters given cell by cell, see [`crate::maps`]
+    pub maps: MapsConfig,
+    /// Voronoi grains with their own anisotropy axes, see [`crate::grains`]
+    pub grains: Option<GrainsConfig>,
+    /// temperature of the sample, scaling Mₛ and `a_ex`, see
+    /// [`crate::temperature`]
+    pub temperature: Option<TemperatureConfig>,
+    /// damping ramps at the faces of the mesh that absorb spin waves, see
+    /// [`crate::absorbing`]
+    pub absorbing: Option<AbsorbingConfig>,
+    /// time- and space-dependent applied fields (T), summed on top of
+    /// `params.h_ext`
+    pub zeeman: Vec<ZeemanSource>,
+    /// magnetostatic interaction, enabled by an (empty) `[demag]` table
+    pub demag: Option<DemagConfig>,
+    /// magnetostatic interaction of point dipoles summed pair by pair, for
+    /// chains and small systems
+    pub dipolar: Option<DipolarConfig>,
+    /// uniaxial magnetocrystalline anisotropy
+    pub uniaxial: Option<UniaxialConfig>,
+    /// cubic magnetocrystalline anisotropy
+    pub cubic: Option<CubicConfig>,
+    /// Dzyaloshinskii–Moriya interaction
+    pub dmi: Option<DmiConfig>,
+    /// interlayer exchange across spacers, one entry per coupled pair
+    pub rkky: Vec<RkkyConfig>,
+    /// exchange of the cells as atoms, shell by shell of neighbours
+    pub heisenberg: Option<HeisenbergConfig>,
+    /// magnetoelastic coupling to a prescribed strain
+    pub magnetoelastic: Option<MagnetoelasticConfig>,
+    /// Oersted field of a current through the sample
+    pub oersted: Option<OerstedConfig>,
+    /// voltage-controlled magnetic anisotropy
+    pub vcma: Option<VcmaConfig>,
+    /// effective-field terms left out of the field and the energies, by
+    /// name: `"zeeman"`, `"exchange"`, or that of an optional section
+    pub disable: Vec<String>,
+    /// Zhang–Li spin-transfer torque from an in-plane current
+    pub zhang_li: Option<ZhangLiConfig>,
+    /// Slonczewski spin-transfer torque from a perpendicular current
+    pub slonczewski: Option<SlonczewskiConfig>,
+    /// spin-orbit torque from an in-plane current in an adjacent heavy metal
+    pub sot: Option<SotConfig>,
+    /// second sublattice of an antiferromagnet or ferrimagnet, see
+    /// [`crate::sublattice`]
+    pub sublattice: Option<SublatticeConfig>,
+    /// the Landau–Lifshitz–Bloch equation of motion instead of the LLG
+    /// equation, see [`crate::llb`]
+    pub llb: Option<LlbConfig>,
+    /// thermal fluctuations of a temperature that may vary across the
+    /// sample, with the spin-Seebeck torque of its gradient, see
+    /// [`crate::langevin`]
+    pub langevin: Option<LangevinConfig>,
+    pub solver: SolverConfig,
+    /// settings of `nez minimize`
+    pub minimize: MinimizeConfig,
+    /// settings of `nez relax`
+    pub relax: RelaxConfig,
+    /// settings of `nez gneb`
+    pub gneb: GnebConfig,
+    /// settings of `nez monte-carlo`
+    pub monte_carlo: MonteCarloConfig,
+    /// settings of `nez hysteresis`
+    pub hysteresis: HysteresisConfig,
+    /// settings of `nez sweep`, see [`crate::sweep`]
+    pub sweep: SweepConfig,
+    /// settings of `nez ensemble`, see [`crate::ensemble`]
+    pub ensemble: EnsembleConfig,
+    pub run: RunConfig,
+    /// worker threads of the command line, see [`crate::parallel`]
+    pub parallel: ParallelConfig,
+    /// stages of `nez run`, carried out in order instead of `run.steps`,
+    /// see [`crate::stages`]
+    pub stages: Vec<Stage>,
+    pub initial: InitialConfig,
+    pub output: OutputConfig,
+    /// per-step observables of `nez run`
+    pub table: TableConfig,
+    /// domain wall whose position and velocity join the observables, and
+    /// which the window may follow, see [`crate::wall`]
+    pub wall: Option<WallConfig>,
+    /// groups of cells whose average magnetization joins the observables,
+    /// see [`crate::probe`]
+    pub probes: Vec<ProbeConfig>,
+    /// spin current pumped into a normal metal and its ISHE voltage, which
+    /// join the observables, see [`crate::spin_pumping`]
+    pub spin_pumping: Option<SpinPumpingConfig>,
+    /// resistance of a tunnel junction over the `[slonczewski]` polarizer,
+    /// which joins the observables, see [`crate::mtj`]
+    pub mtj: Option<MtjConfig>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DemagConfig {}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DipolarConfig {
+    /// longest distance between interacting dipoles (m), unlimited when
+    /// absent
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cutoff: Option<f64>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct UniaxialConfig {
+    /// first-order constant Ku1 (J m⁻³)
+    pub k1: f64,
+    /// second-order constant Ku2 (J m⁻³)
+    pub k2: f64,
+    /// easy axis (normalised on load)
+    pub axis: Vector3<f64>,
+}
+
+impl Default for UniaxialConfig {
+    fn default() -> Self {
+        Self {
+            k1: 0.0,
+            k2: 0.0,
+            axis: Vector3::z(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CubicConfig {
+    /// Kc1, Kc2, Kc3 (J m⁻³)
+    pub k1: f64,
+    pub k2: f64,
+    pub k3: f64,
+    /// first cubic axis
+    pub c1: Vector3<f64>,
+    /// second cubic axis, orthogonalised against `c1`; the third is c1 × c2
+    pub c2: Vector3<f64>,
+}
+
+impl Default for CubicConfig {
+    fn default() -> Self {
+        Self {
+            k1: 0.0,
+            k2: 0.0,
+            k3: 0.0,
+            c1: Vector3::x(),
+            c2: Vector3::y(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DmiConfig {
+    /// DMI constant (J m⁻²)
+    pub d: f64,
+    /// `"interfacial"` or `"bulk"`
+    pub kind: DmiKind,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MagnetoelasticConfig {
+    /// magnetoelastic constants B1, B2 (J m⁻³)
+    pub b1: f64,
+    pub b2: f64,
+    /// strain, the sum of every source; none leaves the sample unstrained
+    pub strain: Vec<StrainSource>,
+}
+
+/// A current flowing through the sample, `scale(t) × profile(r)` times a
+/// fixed density:
+///
+/// ```toml
+/// [oersted]
+/// density = [1e12, 0.0, 0.0]   # or file = "j.ovf"
+/// scale = { kind = "sine", amplitude = 1.0, frequency = 1e9 }
+/// ```
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct OerstedConfig {
+    /// current density (A m⁻²), the same in every cell
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub density: Option<Vector3<f64>>,
+    /// OVF file with the current density of every cell (A m⁻²) instead,
+    /// interpolated onto the mesh if the grids differ
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file: Option<PathBuf>,
+    /// spatial weight of the density; outside `[geometry]` it is always 0
+    #[serde(skip_serializing_if = "Profile::is_uniform")]
+    pub profile: Profile,
+    /// time dependence, a dimensionless factor (1 by default)
+    pub scale: Waveform<f64>,
+}
+
+impl Default for OerstedConfig {
+    fn default() -> Self {
+        Self {
+            density: None,
+            file: None,
+            profile: Profile::Uniform,
+            scale: Waveform::Constant { value: 1.0 },
+        }
+    }
+}
+
+/// Anisotropy of the film changed by a voltage across the oxide barrier on
+/// top of it, e.g. a 1 GHz drive of a perpendicular film:
+///
+/// ```toml
+/// [vcma]
+/// coefficient = 100e-15   # ξ (J V⁻¹ m⁻¹), i.e. 100 fJ V⁻¹ m⁻¹
+/// oxide = 1e-9
+/// voltage = { kind = "sine", amplitude = 1.0, frequency = 1e9 }
+/// ```
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct VcmaConfig {
+    /// VCMA coefficient ξ (J V⁻¹ m⁻¹), the change of the interface
+    /// anisotropy (J m⁻²) per electric field in the barrier (V m⁻¹);
+    /// positive when a positive voltage strengthens the easy axis
+    pub coefficient: f64,
+    /// barrier thickness (m)
+    pub oxide: f64,
+    /// ferromagnet thickness (m), the mesh thickness when absent
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub thickness: Option<f64>,
+    /// axis of the anisotropy, normal to the interface
+    pub axis: Vector3<f64>,
+    /// spatial weight, e.g. the footprint of the electrode
+    #[serde(skip_serializing_if = "Profile::is_uniform")]
+    pub profile: Profile,
+    /// voltage across the barrier (V)
+    pub voltage: Waveform<f64>,
+}
+
+impl Default for VcmaConfig {
+    fn default() -> Self {
+        Self {
+            coefficient: 0.0,
+            oxide: 1e-9,
+            thickness: None,
+            axis: Vector3::z(),
+            profile: Profile::Uniform,
+            voltage: Waveform::Constant { value: 0.0 },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ZhangLiConfig {
+    /// spin polarization P
+    pub polarization: f64,
+    /// non-adiabaticity ξ
+    pub xi: f64,
+    /// current density (A m⁻²), e.g. `{ kind = "constant", value = [1e12, 0.0, 0.0] }`
+    pub current: Waveform<Vector3<f64>>,
+}
+
+impl Default for ZhangLiConfig {
+    fn default() -> Self {
+        Self {
+            polarization: 1.0,
+            xi: 0.0,
+            current: Waveform::Constant {
+                value: Vector3::zeros(),
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SlonczewskiConfig {
+    /// spin polarization P
+    pub polarization: f64,
+    /// asymmetry parameter Λ (1 gives the angle-independent torque)
+    pub lambda: f64,
+    /// secondary spin-torque efficiency ε′
+    pub epsilon_prime: f64,
+    /// fixed-layer magnetization (normalised on load)
+    pub fixed: Vector3<f64>,
+    /// free-layer thickness (m), the mesh thickness when absent
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub thickness: Option<f64>,
+    /// current density along z (A m⁻²)
+    pub current: Waveform<f64>,
+}
+
+impl Default for SlonczewskiConfig {
+    fn default() -> Self {
+        Self {
+            polarization: 1.0,
+            lambda: 1.0,
+            epsilon_prime: 0.0,
+            fixed: Vector3::z(),
+            thickness: None,
+            current: Waveform::Constant { value: 0.0 },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SotConfig {
+    /// spin Hall angle θ_SH
+    pub theta_sh: f64,
+    /// damping-like efficiency, relative to θ_SH
+    pub damping_like: f64,
+    /// field-like efficiency, relative to θ_SH
+    pub field_like: f64,
+    /// ferromagnet thickness (m), the mesh thickness when absent
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub thickness: Option<f64>,
+    /// in-plane charge current density in the heavy metal (A m⁻²)
+    pub current: Waveform<Vector3<f64>>,
+}
+
+impl Default for SotConfig {
+    fn default() -> Self {
+        Self {
+            theta_sh: 0.1,
+            damping_like: 1.0,
+            field_like: 0.0,
+            thickness: None,
+            current: Waveform::Constant {
+                value: Vector3::zeros(),
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SolverConfig {
+    /// `"heun"`, `"rk4"`, `"lsrk4"`, `"cayley"`, `"implicit"` (fixed
+    /// `params.dt`), `"rk23"` or `"rk45"` (adaptive, starting from
+    /// `params.dt`)
+    pub method: Method,
+    /// largest accepted error in |dm| per step (adaptive methods)
+    pub tolerance: f64,
+    /// smallest step (s); steps this small are accepted regardless of error
+    pub dt_min: f64,
+    /// largest step (s), unbounded when absent
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dt_max: Option<f64>,
+    /// `"cpu"` or `"gpu"` (fixed-step `"heun"` and `"rk4"` with exchange,
+    /// static fields and uniaxial anisotropy; anything else falls back to
+    /// the CPU)
+    pub device: Device,
+}
+
+impl Default for SolverConfig {
+    fn default() -> Self {
+        Self {
+            method: Method::Rk4,
+            tolerance: 1e-5,
+            dt_min: 1e-18,
+            dt_max: None,
+            device: Device::Cpu,
+        }
+    }
+}
+
+impl SolverConfig {
+    /// The scheme of `method` on `device`
+    pub fn stepper(&self) -> Box<dyn Stepper> {
+        #[cfg(feature = "gpu")]
+        if self.device == Device::Gpu {
+            return crate::gpu::stepper(self.method);
+        }
+        self.method.stepper()
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RelaxConfig {
+    /// stop once max|m × B_eff| drops below this (T)
+    pub tolerance: f64,
+    /// give up after this many time steps
+    pub max_steps: u64,
+    /// evaluate the torque every this many steps
+    pub check_every: u64,
+}
+
+impl Default for RelaxConfig {
+    fn default() -> Self {
+        Self {
+            tolerance: 1e-4,
+            max_steps: 1_000_000,
+            check_every: 10,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RunConfig {
+    /// number of time steps
+    pub steps: u64,
+    /// print the observables every this many steps
+    pub print_every: u64,
+    /// write a checkpoint to the store every this many steps, besides the
+    /// one at the end of the run (0: only at the end)
+    pub checkpoint_every: u64,
+    /// end the run early once max|m × B_eff| is below this (T), checked
+    /// when it starts and every `print_every` steps
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stop_torque: Option<f64>,
+    /// end it early once max|dm/dt| is below this (s⁻¹), likewise
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stop_dmdt: Option<f64>,
+    /// end it once the simulated time reaches this (s), landing on it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stop_time: Option<f64>,
+    /// end it once it has taken this long (s of wall-clock time), likewise
+    /// checked every `print_every` steps
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stop_wall: Option<f64>,
+    /// end it once a column of the table passes a value
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub stop_when: Vec<StopWhen>,
+}
+
+/// A column of the table passing a value, e.g. ⟨mz⟩ changing sign on
+/// switching
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StopWhen {
+    /// name of the column, as in the table: "mz", "E_total", "max_torque"...
+    pub column: String,
+    /// met once the column is below this
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub below: Option<f64>,
+    /// met once the column is above this
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub above: Option<f64>,
+}
+
+impl Default for RunConfig {
+    fn default() -> Self {
+        Self {
+            steps: 50,
+            print_every: 50,
+            checkpoint_every: 1000,
+            stop_torque: None,
+            stop_dmdt: None,
+            stop_time: None,
+            stop_wall: None,
+            stop_when: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct InitialConfig {
+    /// uniform initial direction (normalised on load)
+    pub m: Vector3<f64>,
+    /// OVF file (OOMMF, mumax3) to start from instead, interpolated onto the
+    /// mesh if the grids differ; `m` fills cells where it vanishes
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file: Option<PathBuf>,
+    /// vortex, domain wall, skyrmion, helix, random or expression state
+    /// instead, see [`crate::texture`]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub texture: Option<Texture>,
+    /// store of an earlier run to start from instead: centred on the
+    /// sample, cropped or padded with `m` where its size differs, and
+    /// interpolated onto the mesh if the cells differ; `m` also fills cells
+    /// where it vanishes
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub store: Option<PathBuf>,
+    /// snapshot of `store` to start from (default: the last one)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub frame: Option<u64>,
+}
+
+impl InitialConfig {
+    /// Initial magnetization on `mesh`
+    pub fn state(&self, mesh: &Mesh) -> Result<VectorField> {
+        if let Some(file) = &self.file {
+            Ok(crate::ovf::read(file)?.resample(mesh, self.m))
+        } else if let Some(texture) = &self.texture {
+            texture
+                .state(mesh)
+                .map_err(|e| format!("`initial.texture`: {e}").into())
+        } else if let Some(store) = &self.store {
+            self.stored(store, mesh)
+                .map_err(|e| format!("`initial.store` {}: {e}", store.display()).into())
+        } else {
+            Ok(VectorField::uniform(mesh.len(), self.m.normalize()))
+        }
+    }
+
+    /// Snapshot `frame` of the store at `path`, of its first sublattice if
+    /// it has two, on `mesh`
+    fn stored(&self, path: &Path, mesh: &Mesh) -> Result<VectorField> {
+        let store = ZarrOutput::open(path)?;
+        let frames = store.frames()?;
+        let frame = match self.frame {
+            Some(f) if f < frames => f,
+            Some(f) => return Err(format!("no frame {f} among its {frames}").into()),
+            None => frames.checked_sub(1).ok_or("it holds no snapshots")?,
+        };
+        let m = store.read(frame)?;
+        let recorded = store.config()?.mesh;
+        // the stored cells over the sample of the mesh, centred, cropped
+        // where it is smaller and padded with zeros, i.e. with `m`, where
+        // it is larger; then interpolated onto the cells of the mesh
+        let from = [recorded.nx, recorded.ny, recorded.nz];
+        let step = [recorded.dx, recorded.dy, recorded.dz];
+        let extent = [
+            mesh.dx * mesh.nx as f64,
+            mesh.dy * mesh.ny as f64,
+            mesh.dz * mesh.nz as f64,
+        ];
+        let nodes: [usize; 3] =
+            std::array::from_fn(|k| ((extent[k] / step[k]).round() as usize).max(1));
+        let shift: [isize; 3] = std::array::from_fn(|k| (from[k] as isize - nodes[k] as isize) / 2);
+        // index along axis k of the stored cell under node c, if any
+        let cell = |k: usize, c: usize| {
+            usize::try_from(c as isize + shift[k])
+                .ok()
+                .filter(|&c| c < from[k])
+        };
+        let padded = Mesh::new(nodes, step);
+        let data = (0..padded.len())
+            .map(|i| {
+                let [x, y, z] = padded.coords(i);
+                match (cell(0, x), cell(1, y), cell(2, z)) {
+                    (Some(x), Some(y), Some(z)) => m.get(recorded.idx(x, y, z)),
+                    _ => Vector3::zeros(),
+                }
+            })
+            .collect();
+        let source = crate::ovf::Ovf { nodes, step, data };
+        Ok(source.resample(mesh, self.m))
+    }
+}
+
+impl Default for InitialConfig {
+    fn default() -> Self {
+        // small tilt away from +z
+        let tilt = 10f64.to_radians();
+        Self {
+            m: Vector3::new(tilt.sin(), 0.0, tilt.cos()),
+            file: None,
+            texture: None,
+            store: None,
+            frame: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct OutputConfig {
+    /// Zarr store location
+    pub path: PathBuf,
+    /// steps between frames of `m` written by `nez run`
+    pub every: u64,
+    /// compression of `m`
+    pub codec: Codec,
+    /// floating-point type of `m`
+    pub precision: Precision,
+    /// compression level, the usual one of `codec` when absent
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub level: Option<i32>,
+    /// cells (z, y, x) per compressed chunk of `m`, each dividing the mesh
+    /// size; a whole frame when absent
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub chunk: Option<[u64; 3]>,
+    /// frames per shard, the file holding a run of chunks
+    pub shard: u64,
+    /// `"nez"`, or `"amumax"` for pipelines written for amumax output
+    pub layout: Layout,
+    /// files written besides the store, enabled by an `[output.snapshots]`
+    /// table
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub snapshots: Option<SnapshotConfig>,
+    /// fields written to the store besides `m`, enabled by an
+    /// `[output.fields]` table
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fields: Option<FieldsConfig>,
+    /// also write the log to the store path with extension `.log`, see
+    /// [`crate::logging`]
+    pub log: bool,
+}
+
+impl Default for OutputConfig {
+    fn default() -> Self {
+        Self {
+            path: "magnetization.zarr".into(),
+            every: 1,
+            codec: Codec::default(),
+            precision: Precision::default(),
+            level: None,
+            chunk: None,
+            shard: 1,
+            layout: Layout::default(),
+            snapshots: None,
+            fields: None,
+            log: false,
+        }
+    }
+}
+
+impl OutputConfig {
+    /// Directory of the snapshot files: `snapshots.dir`, or the store path
+    /// without extension
+    pub fn snapshot_dir(&self) -> PathBuf {
+        match self.snapshots.as_ref().and_then(|s| s.dir.clone()) {
+            Some(dir) => dir,
+            None => snapshot::default_dir(&self.path),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SnapshotConfig {
+    /// steps between snapshot files
+    pub every: u64,
+    pub format: snapshot::Format,
+    pub data: snapshot::Encoding,
+    /// directory of the files, the store path without extension when absent
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dir: Option<PathBuf>,
+}
+
+/// Fields written to the group `fields` of the store, every `every` steps:
+///
+/// ```toml
+/// [output.fields]
+/// every = 100
+/// b_eff = true                     # B_eff (T)
+/// terms = ["demag", "exchange"]    # B_demag, B_exchange (T)
+/// energy_density = true            # of all terms (J/m³)
+/// torque = true                    # m × B_eff (T)
+/// ```
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FieldsConfig {
+    /// steps between frames of the fields
+    pub every: u64,
+    /// the effective field, without thermal noise or spin torques
+    pub b_eff: bool,
+    /// terms whose field alone is written, by name
+    pub terms: Vec<String>,
+    pub energy_density: bool,
+    pub torque: bool,
+}
+
+impl Default for FieldsConfig {
+    fn default() -> Self {
+        Self {
+            every: 1000,
+            b_eff: false,
+            terms: Vec::new(),
+            energy_density: false,
+            torque: false,
+        }
+    }
+}
+
+impl FieldsConfig {
+    /// Name of every array written, with whether it holds vectors
+    pub fn arrays(&self) -> Vec<(String, bool)> {
+        let mut arrays = Vec::new();
+        if self.b_eff {
+            arrays.push(("B_eff".to_string(), true));
+        }
+        arrays.extend(self.terms.iter().map(|t| (format!("B_{t}"), true)));
+        if self.energy_density {
+            arrays.push(("energy_density".to_string(), false));
+        }
+        if self.torque {
+            arrays.push(("torque".to_string(), true));
+        }
+        arrays
+    }
+}
+
+impl Default for SnapshotConfig {
+    fn default() -> Self {
+        Self {
+            every: 1000,
+            format: snapshot::Format::default(),
+            data: snapshot::Encoding::default(),
+            dir: None,
+        }
+    }
+}
+
+impl Config {
+    /// Read and validate a TOML file
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let text =
+            fs::read_to_string(path).map_err(|e| format!("cannot read {}: {e}", path.display()))?;
+        let dir = path.parent().unwrap_or(Path::new("."));
+        Self::parse_in(&text, dir).map_err(|e| format!("{}: {e}", path.display()).into())
+    }
+
+    /// Parse and validate TOML text; files it refers to are looked up
+    /// relative to the working directory
+    pub fn parse(text: &str) -> Result<Self> {
+        Self::parse_in(text, Path::new("."))
+    }
+
+    pub(crate) fn parse_in(text: &str, dir: &Path) -> Result<Self> {
+        let mut config: Self = toml::from_str(text)?;
+        config.validate()?;
+        for s in &mut config.zeeman {
+            s.waveform.load_file(dir)?;
+        }
+        for stage in &mut config.stages {
+            for s in stage.zeeman.iter_mut().flatten() {
+                s.waveform.load_file(dir)?;
+            }
+        }
+        if let Some(me) = &mut config.magnetoelastic {
+            for s in &mut me.strain {
+                s.waveform.load_file(dir)?;
+            }
+        }
+        if let Some(o) = &mut config.oersted {
+            o.scale.load_file(dir)?;
+            if let Some(file) = &mut o.file {
+                *file = dir.join(&file);
+            }
+        }
+        if let Some(t) = &mut config.temperature {
+            t.ms.load_file(dir)?;
+            t.a_ex.load_file(dir)?;
+            if let Some(crate::maps::Map::File(file)) = &mut t.map {
+                *file = dir.join(&file);
+            }
+        }
+        if let Some(l) = &mut config.llb {
+            l.temperature.load_file(dir)?;
+        }
+        if let Some(crate::maps::Map::File(file)) =
+            config.langevin.as_mut().and_then(|l| l.map.as_mut())
+        {
+            *file = dir.join(&file);
+        }
+        if let Some(v) = &mut config.vcma {
+            v.voltage.load_file(dir)?;
+        }
+        if let Some(z) = &mut config.zhang_li {
+            z.current.load_file(dir)?;
+        }
+        if let Some(s) = &mut config.slonczewski {
+            s.current.load_file(dir)?;
+        }
+        if let Some(s) = &mut config.sot {
+            s.current.load_file(dir)?;
+        }
+        if let Some(file) = &mut config.initial.file {
+            *file = dir.join(&file);
+        }
+        if let Some(store) = config.initial.store.as_mut().filter(|s| !is_remote(s)) {
+            *store = dir.join(&store);
+        }
+        config.maps.resolve_files(dir);
+        if let Some(g) = &mut config.geometry {
+            g.resolve_files(dir);
+        }
+        for r in &mut config.regions {
+            r.shape.resolve_files(dir);
+        }
+        for p in &mut config.probes {
+            if let Some(s) = &mut p.shape {
+                s.resolve_files(dir);
+            }
+        }
+        Ok(config)
+    }
+
+    /// Check values that deserialize fine but make no physical sense
+    pub fn validate(&self) -> Result<()> {
+        fn positive(key: &str, v: f64) -> Result<()> {
+            if v.is_finite() && v > 0.0 {
+                Ok(())
+            } else {
+                Err(format!("`{key}` must be positive and finite, got {v}").into())
+            }
+        }
+        fn finite(key: &str, v: f64) -> Result<()> {
+            if v.is_finite() {
+                Ok(())
+            } else {
+                Err(format!("`{key}` must be finite, got {v}").into())
+            }
+        }
+        fn direction(key: &str, v: Vector3<f64>) -> Result<()> {
+            if v.iter().all(|c| c.is_finite()) && v.norm() > 0.0 {
+                Ok(())
+            } else {
+                Err(format!("`{key}` must be a finite, non-zero vector").into())
+            }
+        }
+        fn non_negative(key: &str, v: f64) -> Result<()> {
+            if v.is_finite() && v >= 0.0 {
+                Ok(())
+            } else {
+                Err(format!("`{key}` must be non-negative and finite, got {v}").into())
+            }
+        }
+        fn solver(key: &str, s: &SolverConfig) -> Result<()> {
+            positive(&format!("{key}.tolerance"), s.tolerance)?;
+            non_negative(&format!("{key}.dt_min"), s.dt_min)?;
+            if s.device == Device::Gpu && !cfg!(feature = "gpu") {
+                return Err(format!(
+                    "`{key}.device = \"gpu\"` needs nez built with the `gpu` feature"
+                )
+                .into());
+            }
+            if let Some(dt_max) = s.dt_max {
+                positive(&format!("{key}.dt_max"), dt_max)?;
+                if dt_max < s.dt_min {
+                    return Err(format!("`{key}.dt_max` is below `{key}.dt_min`").into());
+                }
+            }
+            Ok(())
+        }
+        fn profile(key: &str, p: &Profile) -> Result<()> {
+            if let Profile::Expr { expr } = p {
+                crate::expr::Expr::parse(expr, &["x", "y", "z"])
+                    .map_err(|e| format!("`{key}`: {e}"))?;
+            }
+            Ok(())
+        }
+
+        fn shape(key: &str, s: &Shape, nz: usize) -> Result<()> {
+            let point = |name: &str, v: &Vector3<f64>| -> Result<()> {
+                if v.iter().all(|c| c.is_finite()) {
+                    Ok(())
+                } else {
+                    Err(format!("`{key}.{name}` must be finite").into())
+                }
+            };
+            match s {
+                Shape::Cuboid { min, max } => {
+                    point("min", min)?;
+                    point("max", max)?;
+                }
+                Shape::Cylinder {
+                    centre,
+                    radius,
+                    axis,
+                    height,
+                } => {
+                    point("centre", centre)?;
+                    positive(&format!("{key}.radius"), *radius)?;
+                    direction(&format!("{key}.axis"), *axis)?;
+                    if let Some(h) = height {
+                        positive(&format!("{key}.height"), *h)?;
+                    }
+                }
+                Shape::Ellipsoid { centre, radii } => {
+                    point("centre", centre)?;
+                    for r in radii.iter() {
+                        positive(&format!("{key}.radii"), *r)?;
+                    }
+                }
+                Shape::Layers { first, last } => {
+                    if first > last || *last >= nz {
+                        return Err(format!(
+                            "`{key}` layers {first}..={last} must lie within 0..{nz}"
+                        )
+                        .into());
+                    }
+                }
+                Shape::Expr { expr } => {
+                    crate::expr::Expr::parse(expr, &["x", "y", "z"])
+                        .map_err(|e| format!("`{key}`: {e}"))?;
+                }
+                Shape::Image { min, max, .. } => {
+                    if min.is_none() && max.is_none() {
+                        return Err(format!("`{key}` needs a `min` or `max` grey value").into());
+                    }
+                    if let (Some(min), Some(max)) = (min, max)
+                        && min > max
+                    {
+                        return Err(format!("`{key}.min` must not exceed `max`").into());
+                    }
+                }
+                Shape::Union { shapes }
+                | Shape::Intersection { shapes }
+                | Shape::Difference { shapes } => {
+                    if shapes.is_empty() {
+                        return Err(format!("`{key}.shapes` must not be empty").into());
+                    }
+                    for (k, s) in shapes.iter().enumerate() {
+                        shape(&format!("{key}.shapes[{k}]"), s, nz)?;
+                    }
+                }
+            }
+            Ok(())
+        }
+
+        for (key, n) in [
+            ("nx", self.mesh.nx),
+            ("ny", self.mesh.ny),
+            ("nz", self.mesh.nz),
+        ] {
+            if n == 0 {
+                return Err(format!("`mesh.{key}` must be at least 1").into());
+            }
+        }
+        positive("mesh.dx", self.mesh.dx)?;
+        positive("mesh.dy", self.mesh.dy)?;
+        positive("mesh.dz", self.mesh.dz)?;
+        positive("params.gamma", self.params.gamma)?;
+        positive("params.mu0_ms", self.params.mu0_ms)?;
+        positive("params.dt", self.params.dt)?;
+        non_negative("params.alpha", self.params.alpha)?;
+        non_negative("params.a_ex", self.params.a_ex)?;
+        if self.params.h_ext.iter().any(|v| !v.is_finite()) {
+            return Err("`params.h_ext` must be finite".into());
+        }
+        for (k, s) in self.zeeman.iter().enumerate() {
+            let key = format!("zeeman[{k}]");
+            s.waveform.validate(&key)?;
+            profile(&format!("{key}.profile"), &s.profile)?;
+        }
+        if let Some(g) = &self.geometry {
+            shape("geometry", g, self.mesh.nz)?;
+        }
+        if let Some(s) = &self.spin_pumping {
+            s.validate()?;
+        }
+        if let Some(mtj) = &self.mtj {
+            mtj.validate()?;
+            if self.slonczewski.is_none() {
+                return Err("`mtj` needs the polarizer of a `[slonczewski]` section".into());
+            }
+        }
+        for (k, probe) in self.probes.iter().enumerate() {
+            probe.validate(k, &self.mesh, self.regions.len())?;
+            if let Some(s) = &probe.shape {
+                shape(&format!("probes[{k}].shape"), s, self.mesh.nz)?;
+            }
+            if self.probes[..k].iter().any(|p| p.name == probe.name) {
+                return Err(
+                    format!("`probes[{k}].name`: two probes named {:?}", probe.name).into(),
+                );
+            }
+        }
+        if let Some(wall) = &self.wall {
+            let n = self.mesh.size()[wall.axis as usize];
+            if n < 2 {
+                return Err(format!("`wall.axis` needs at least 2 cells, the mesh has {n}").into());
+            }
+            if wall.center && self.geometry.is_some() {
+                return Err(
+                    "`wall.center` shifts m through the whole mesh and cannot be \
+                            combined with `geometry`"
+                        .into(),
+                );
+            }
+        }
+        if self.regions.len() > MAX_REGIONS {
+            return Err(format!("at most {MAX_REGIONS} `regions` are supported").into());
+        }
+        let cubic = self.cubic.clone().unwrap_or_default();
+        for (k, r) in self.regions.iter().enumerate() {
+            let key = format!("regions[{k}]");
+            shape(&format!("{key}.shape"), &r.shape, self.mesh.nz)?;
+            let o = &r.params;
+            if let Some(v) = o.gamma {
+                positive(&format!("{key}.params.gamma"), v)?;
+            }
+            if let Some(v) = o.alpha {
+                non_negative(&format!("{key}.params.alpha"), v)?;
+            }
+            if let Some(v) = o.a_ex {
+                non_negative(&format!("{key}.params.a_ex"), v)?;
+            }
+            if let Some(v) = o.mu0_ms {
+                positive(&format!("{key}.params.mu0_ms"), v)?;
+            }
+            let u = &r.uniaxial;
+            for (name, v) in [("k1", u.k1), ("k2", u.k2)] {
+                if let Some(v) = v {
+                    finite(&format!("{key}.uniaxial.{name}"), v)?;
+                }
+            }
+            if let Some(axis) = u.axis {
+                direction(&format!("{key}.uniaxial.axis"), axis)?;
+            }
+            let c = &r.cubic;
+            for (name, v) in [("k1", c.k1), ("k2", c.k2), ("k3", c.k3)] {
+                if let Some(v) = v {
+                    finite(&format!("{key}.cubic.{name}"), v)?;
+                }
+            }
+            for (name, v) in [("c1", c.c1), ("c2", c.c2)] {
+                if let Some(v) = v {
+                    direction(&format!("{key}.cubic.{name}"), v)?;
+                }
+            }
+            let (c1, c2) = (c.c1.unwrap_or(cubic.c1), c.c2.unwrap_or(cubic.c2));
+            if (c.c1.is_some() || c.c2.is_some())
+                && c1.normalize().cross(&c2.normalize()).norm() < 1e-6
+            {
+                return Err(format!("`{key}.cubic.c2` must not be parallel to `c1`").into());
+            }
+            if let Some(d) = r.dmi.d {
+                finite(&format!("{key}.dmi.d"), d)?;
+            }
+        }
+        for (k, s) in self.exchange_scale.iter().enumerate() {
+            let key = format!("exchange_scale[{k}]");
+            finite(&format!("{key}.scale"), s.scale)?;
+            let regions = self.regions.len();
+            if let Some(r) = s.regions.iter().find(|&&r| r > regions) {
+                return Err(format!(
+                    "`{key}.regions` names region {r}, but there are only regions 0 to {regions}"
+                )
+                .into());
+            }
+        }
+        for (key, map) in self.maps.entries() {
+            if let Some(crate::maps::Map::Expr(expr)) = map {
+                crate::expr::Expr::parse(expr, &["x", "y", "z"])
+                    .map_err(|e| format!("`maps.{key}`: {e}"))?;
+            }
+        }
+        if let Some(g) = &self.grains {
+            positive("grains.size", g.size)?;
+            if !(0.0..=180.0).contains(&g.cone) {
+                return Err(format!(
+                    "`grains.cone` must lie between 0 and 180 degrees, got {}",
+                    g.cone
+                )
+                .into());
+            }
+            non_negative("grains.exchange", g.exchange)?;
+        }
+        if let Some(t) = &self.temperature {
+            non_negative("temperature.value", t.value)?;
+            if let Some(crate::maps::Map::Expr(expr)) = &t.map {
+                crate::expr::Expr::parse(expr, &["x", "y", "z"])
+                    .map_err(|e| format!("`temperature.map`: {e}"))?;
+            }
+            t.ms.validate("temperature.ms", true)?;
+            t.a_ex.validate("temperature.a_ex", false)?;
+        }
+        if let Some(a) = &self.absorbing {
+            positive("absorbing.width", a.width)?;
+            non_negative("absorbing.alpha", a.alpha)?;
+            positive("absorbing.power", a.power)?;
+            for (k, b) in a.boundaries.iter().enumerate() {
+                if a.boundaries[..k].contains(b) {
+                    return Err(format!("`absorbing.boundaries` lists `{b}` twice").into());
+                }
+                if self.mesh.periodic(b.axis()) {
+                    return Err(format!(
+                        "`absorbing.boundaries`: the mesh is periodic across `{b}`"
+                    )
+                    .into());
+                }
+            }
+        }
+        if let Some(s) = &self.sublattice {
+            positive("sublattice.gamma", s.gamma)?;
+            positive("sublattice.mu0_ms", s.mu0_ms)?;
+            non_negative("sublattice.alpha", s.alpha)?;
+            if let Some(a) = s.a_ex {
+                non_negative("sublattice.a_ex", a)?;
+            }
+            finite("sublattice.exchange", s.exchange)?;
+            if let Some(m) = s.m {
+                direction("sublattice.m", m)?;
+            }
+        }
+        if let Some(u) = &self.uniaxial {
+            finite("uniaxial.k1", u.k1)?;
+            finite("uniaxial.k2", u.k2)?;
+            direction("uniaxial.axis", u.axis)?;
+        }
+        if let Some(c) = &self.cubic {
+            finite("cubic.k1", c.k1)?;
+            finite("cubic.k2", c.k2)?;
+            finite("cubic.k3", c.k3)?;
+            direction("cubic.c1", c.c1)?;
+            direction("cubic.c2", c.c2)?;
+            if c.c1.normalize().cross(&c.c2.normalize()).norm() < 1e-6 {
+                return Err("`cubic.c2` must not be parallel to `cubic.c1`".into());
+            }
+        }
+        if let Some(d) = &self.dipolar {
+            if self.demag.is_some() {
+                return Err(
+                    "`dipolar` and `demag` describe the same interaction, enable only one".into(),
+                );
+            }
+            if self.sublattice.is_some() {
+                return Err("`dipolar` does not support a `[sublattice]`".into());
+            }
+            if let Some(c) = d.cutoff {
+                positive("dipolar.cutoff", c)?;
+            }
+        }
+        if let Some(d) = &self.dmi {
+            finite("dmi.d", d.d)?;
+        }
+        if let Some(me) = &self.magnetoelastic {
+            finite("magnetoelastic.b1", me.b1)?;
+            finite("magnetoelastic.b2", me.b2)?;
+            for (k, s) in me.strain.iter().enumerate() {
+                let key = format!("magnetoelastic.strain[{k}]");
+                s.waveform.validate(&key)?;
+                profile(&format!("{key}.profile"), &s.profile)?;
+            }
+        }
+        for (k, r) in self.rkky.iter().enumerate() {
+            let key = format!("rkky[{k}]");
+            finite(&format!("{key}.j1"), r.j1)?;
+            finite(&format!("{key}.j2"), r.j2)?;
+            let (pair, bound, name) = match (r.layers, r.regions) {
+                (Some(l), None) => (l, self.mesh.nz, "layers"),
+                (None, Some(r)) => (r, self.regions.len() + 1, "regions"),
+                _ => {
+                    return Err(
+                        format!("`{key}` needs exactly one of `layers` and `regions`").into(),
+                    );
+                }
+            };
+            if pair[0] == pair[1] || pair.iter().any(|&v| v >= bound) {
+                return Err(format!(
+                    "`{key}.{name}` must name two different {name} below {bound}, got {pair:?}"
+                )
+                .into());
+            }
+        }
+        if let Some(h) = &self.heisenberg {
+            if h.j.is_empty() {
+                return Err("`heisenberg.j` needs the constant of at least one shell".into());
+            }
+            for (k, &j) in h.j.iter().enumerate() {
+                finite(&format!("heisenberg.j[{k}]"), j)?;
+            }
+        }
+        if let Some(o) = &self.oersted {
+            match (o.density, &o.file) {
+                (Some(d), None) => {
+                    if !d.iter().all(|c| c.is_finite()) {
+                        return Err("`oersted.density` must be finite".into());
+                    }
+                }
+                (None, Some(_)) => {}
+                _ => return Err("`oersted` needs exactly one of `density` and `file`".into()),
+            }
+            profile("oersted.profile", &o.profile)?;
+            o.scale.validate("oersted.scale")?;
+        }
+        if let Some(v) = &self.vcma {
+            finite("vcma.coefficient", v.coefficient)?;
+            positive("vcma.oxide", v.oxide)?;
+            if let Some(t) = v.thickness {
+                positive("vcma.thickness", t)?;
+            }
+            direction("vcma.axis", v.axis)?;
+            profile("vcma.profile", &v.profile)?;
+            v.voltage.validate("vcma.voltage")?;
+        }
+        if let Some(z) = &self.zhang_li {
+            finite("zhang_li.polarization", z.polarization)?;
+            finite("zhang_li.xi", z.xi)?;
+            z.current.validate("zhang_li.current")?;
+        }
+        if let Some(s) = &self.slonczewski {
+            finite("slonczewski.polarization", s.polarization)?;
+            positive("slonczewski.lambda", s.lambda)?;
+            finite("slonczewski.epsilon_prime", s.epsilon_prime)?;
+            direction("slonczewski.fixed", s.fixed)?;
+            if let Some(t) = s.thickness {
+                positive("slonczewski.thickness", t)?;
+            }
+            s.current.validate("slonczewski.current")?;
+        }
+        if let Some(s) = &self.sot {
+            finite("sot.theta_sh", s.theta_sh)?;
+            finite("sot.damping_like", s.damping_like)?;
+            finite("sot.field_like", s.field_like)?;
+            if let Some(t) = s.thickness {
+                positive("sot.thickness", t)?;
+            }
+            s.current.validate("sot.current")?;
+        }
+        solver("solver", &self.solver)?;
+        if let Some(l) = &self.llb {
+            positive("llb.curie", l.curie)?;
+            positive("llb.susceptibility", l.susceptibility)?;
+            l.temperature.validate("llb.temperature")?;
+            if self.sublattice.is_some() {
+                return Err("`llb` does not support a `[sublattice]`".into());
+            }
+            if self.temperature.is_some() {
+                return Err(
+                    "`llb` has a temperature of its own and cannot be combined with \
+                     `[temperature]`"
+                        .into(),
+                );
+            }
+            let mut solvers = std::iter::once(&self.solver)
+                .chain(self.stages.iter().filter_map(|s| s.solver.as_ref()));
+            if solvers.any(|s| s.method == Method::Cayley) {
+                return Err(
+                    "`llb` changes the length of m, which the `cayley` method keeps fixed".into(),
+                );
+            }
+        }
+        if let Some(l) = &self.langevin {
+            non_negative("langevin.temperature", l.temperature)?;
+            if let Some(crate::maps::Map::Expr(expr)) = &l.map {
+                crate::expr::Expr::parse(expr, &["x", "y", "z"])
+                    .map_err(|e| format!("`langevin.map`: {e}"))?;
+            }
+            finite("langevin.seebeck", l.seebeck)?;
+            finite("langevin.beta", l.beta)?;
+            if self.sublattice.is_some() {
+                return Err("`langevin` does not support a `[sublattice]`".into());
+            }
+            if self.llb.is_some() {
+                return Err("`langevin` does not support the LLB equation of `[llb]`".into());
+            }
+            let mut solvers = std::iter::once(&self.solver)
+                .chain(self.stages.iter().filter_map(|s| s.solver.as_ref()));
+            if let Some(s) = solvers.find(|s| matches!(s.method, Method::Rk23 | Method::Rk45)) {
+                return Err(format!(
+                    "`langevin` needs a fixed time step, which `{}` adapts",
+                    s.method.stepper().name()
+                )
+                .into());
+            }
+        }
+        positive("minimize.tolerance", self.minimize.tolerance)?;
+        positive("relax.tolerance", self.relax.tolerance)?;
+        if self.relax.check_every == 0 {
+            return Err("`relax.check_every` must be at least 1".into());
+        }
+        if self.gneb.images < 3 {
+            return Err("`gneb.images` must be at least 3".into());
+        }
+        non_negative("gneb.spring", self.gneb.spring)?;
+        positive("gneb.step", self.gneb.step)?;
+        positive("gneb.tolerance", self.gneb.tolerance)?;
+        non_negative("gneb.noise", self.gneb.noise)?;
+        let mc = &self.monte_carlo;
+        if mc.temperatures.is_empty() {
+            return Err("`monte_carlo.temperatures` must not be empty".into());
+        }
+        for (k, &t) in mc.temperatures.iter().enumerate() {
+            non_negative(&format!("monte_carlo.temperatures[{k}]"), t)?;
+        }
+        if mc.sweeps == 0 {
+            return Err("`monte_carlo.sweeps` must be at least 1".into());
+        }
+        positive("monte_carlo.cone", mc.cone)?;
+        if !(mc.acceptance > 0.0 && mc.acceptance < 1.0) {
+            return Err(format!(
+                "`monte_carlo.acceptance` must lie strictly between 0 and 1, got {}",
+                mc.acceptance
+            )
+            .into());
+        }
+        direction("hysteresis.direction", self.hysteresis.direction)?;
+        finite("hysteresis.max", self.hysteresis.max)?;
+        finite("hysteresis.min", self.hysteresis.min)?;
+        if self.hysteresis.steps == 0 {
+            return Err("`hysteresis.steps` must be at least 1".into());
+        }
+        if self.sweep.parallel == 0 {
+            return Err("`sweep.parallel` must be at least 1".into());
+        }
+        for (k, p) in self.sweep.parameters.iter().enumerate() {
+            p.validate(k)?;
+        }
+        if self.ensemble.realizations == 0 {
+            return Err("`ensemble.realizations` must be at least 1".into());
+        }
+        if self.ensemble.parallel == 0 {
+            return Err("`ensemble.parallel` must be at least 1".into());
+        }
+        for (k, stage) in self.stages.iter().enumerate() {
+            let key = format!("stages[{k}]");
+            match (stage.kind, stage.duration, stage.steps) {
+                (StageKind::Run, Some(d), None) => positive(&format!("{key}.duration"), d)?,
+                (StageKind::Run, None, Some(_)) => {}
+                (StageKind::Run, _, _) => {
+                    return Err(
+                        format!("`{key}` needs exactly one of `duration` and `steps`").into(),
+                    );
+                }
+                (_, None, None) => {}
+                (kind, _, _) => {
+                    return Err(format!(
+                        "`{key}`: a `{}` stage takes no `duration` or `steps`",
+                        kind.name()
+                    )
+                    .into());
+                }
+            }
+            if let Some(h) = stage.h_ext
+                && h.iter().any(|v| !v.is_finite())
+            {
+                return Err(format!("`{key}.h_ext` must be finite").into());
+            }
+            for (j, s) in stage.zeeman.iter().flatten().enumerate() {
+                let key = format!("{key}.zeeman[{j}]");
+                s.waveform.validate(&key)?;
+                profile(&format!("{key}.profile"), &s.profile)?;
+            }
+            if let Some(s) = &stage.solver {
+                solver(&format!("{key}.solver"), s)?;
+            }
+            if stage.every == Some(0) {
+                return Err(format!("`{key}.every` must be at least 1").into());
+            }
+        }
+        direction("initial.m", self.initial.m)?;
+        let starts = [
+            self.initial.file.is_some(),
+            self.initial.texture.is_some(),
+            self.initial.store.is_some(),
+        ];
+        if starts.iter().filter(|&&s| s).count() > 1 {
+            return Err("`initial` takes at most one of `file`, `texture` and `store`".into());
+        }
+        if let Some(texture) = &self.initial.texture {
+            texture.validate("initial.texture")?;
+        }
+        if self.initial.frame.is_some() && self.initial.store.is_none() {
+            return Err("`initial.frame` needs an `initial.store`".into());
+        }
+        if self.run.print_every == 0 {
+            return Err("`run.print_every` must be at least 1".into());
+        }
+        if let Some(torque) = self.run.stop_torque {
+            positive("run.stop_torque", torque)?;
+        }
+        if let Some(dmdt) = self.run.stop_dmdt {
+            positive("run.stop_dmdt", dmdt)?;
+        }
+        if let Some(t) = self.run.stop_time {
+            finite("run.stop_time", t)?;
+        }
+        if let Some(wall) = self.run.stop_wall {
+            positive("run.stop_wall", wall)?;
+        }
+        for (k, stop) in self.run.stop_when.iter().enumerate() {
+            let key = format!("run.stop_when[{k}]");
+            match (stop.below, stop.above) {
+                (Some(v), None) => finite(&format!("{key}.below"), v)?,
+                (None, Some(v)) => finite(&format!("{key}.above"), v)?,
+                _ => {
+                    return Err(format!("`{key}` needs exactly one of `below` and `above`").into());
+                }
+            }
+        }
+        if self.parallel.chunk == 0 {
+            return Err("`parallel.chunk` must be at least 1".into());
+        }
+        for (key, every) in [
+            ("output.every", self.output.every),
+            ("table.every", self.table.every),
+        ] {
+            if every == 0 {
+                return Err(format!("`{key}` must be at least 1").into());
+            }
+        }
+        let out = &self.output;
+        out.codec
+            .check(out.level.unwrap_or(out.codec.default_level()))
+            .map_err(|e| format!("`output.codec`: {e}"))?;
+        if out.shard == 0 {
+            return Err("`output.shard` must be at least 1".into());
+        }
+        if let Some(chunk) = out.chunk {
+            let size = [self.mesh.nz, self.mesh.ny, self.mesh.nx];
+            if chunk
+                .iter()
+                .zip(size)
+                .any(|(&c, n)| c == 0 || !(n as u64).is_multiple_of(c))
+            {
+                return Err(format!(
+                    "`output.chunk` {chunk:?} must divide the mesh size {size:?} (z, y, x)"
+                )
+                .into());
+            }
+        }
+        if self.output.snapshots.as_ref().is_some_and(|s| s.every == 0) {
+            return Err("`output.snapshots.every` must be at least 1".into());
+        }
+        if let Some(fields) = &self.output.fields {
+            if fields.every == 0 {
+                return Err("`output.fields.every` must be at least 1".into());
+            }
+            if fields.arrays().is_empty() {
+                return Err("`output.fields` selects no field: set `b_eff`, `terms`, \
+                            `energy_density` or `torque`"
+                    .into());
+            }
+            if self.sublattice.is_some() {
+                return Err("`output.fields` does not support a `[sublattice]`".into());
+            }
+        }
+        if out.log && is_remote(&out.path) {
+            return Err("`output.log` needs a local `output.path`".into());
+        }
+        Ok(())
+    }
+}
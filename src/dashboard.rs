@@ -0,0 +1,224 @@
+//! Full-screen terminal view of a run, `nez run --dashboard`: the averaged
+//! magnetization and the total energy plotted against time as the run goes,
+//! below the progress of the run and above the last printed rows.
+//!
+//! The screen is drawn with ratatui on the alternate screen, which is left
+//! when the dashboard is dropped, and the last rows are printed again.
+//! Input is never read, so the terminal stays in cooked mode and Ctrl-C
+//! reaches [`crate::interrupt`] as in a plain run.
+
+use ratatui::{
+    Frame, Terminal,
+    backend::CrosstermBackend,
+    crossterm::{
+        cursor::{Hide, Show},
+        execute,
+        terminal::{EnterAlternateScreen, LeaveAlternateScreen},
+    },
+    layout::{Constraint, Layout},
+    style::{Color, Style},
+    symbols::Marker,
+    text::Line,
+    widgets::{Axis, Block, Chart, Dataset, GraphType, LineGauge, Paragraph},
+};
+use std::{
+    collections::VecDeque,
+    io::{IsTerminal, Stdout, Write},
+    time::{Duration, Instant},
+};
+
+use crate::{Result, Simulation};
+
+/// Printed rows kept below the plots
+const ROWS: usize = 6;
+/// Samples kept; every other one is dropped when there are more
+const SAMPLES: usize = 4096;
+/// Shortest time between two redraws
+const REDRAW: Duration = Duration::from_millis(100);
+
+/// The dashboard of the current run, owning the terminal while it lives
+pub struct Dashboard {
+    terminal: Terminal<CrosstermBackend<Stdout>>,
+    /// t, mx, my, mz and E_total of every sample, oldest first
+    samples: Vec<[f64; 5]>,
+    /// seconds of simulated time between kept samples
+    spacing: f64,
+    rows: VecDeque<String>,
+    /// fraction of the run done, and the line drawn over it
+    done: f64,
+    status: String,
+    drawn: Option<Instant>,
+}
+
+impl Dashboard {
+    /// Switch stdout, which must be a terminal, to the dashboard
+    pub fn open() -> Result<Self> {
+        if !std::io::stdout().is_terminal() {
+            return Err("the dashboard needs a terminal".into());
+        }
+        execute!(std::io::stdout(), EnterAlternateScreen, Hide)?;
+        Ok(Self {
+            terminal: Terminal::new(CrosstermBackend::new(std::io::stdout()))?,
+            samples: Vec::new(),
+            spacing: 0.0,
+            rows: VecDeque::with_capacity(ROWS + 1),
+            done: 0.0,
+            status: String::new(),
+            drawn: None,
+        })
+    }
+
+    /// Keep `line` among the rows shown
+    pub fn row(&mut self, line: String) {
+        self.rows.push_back(line);
+        if self.rows.len() > ROWS {
+            self.rows.pop_front();
+        }
+    }
+
+    /// Add the state of `sim` to the plots
+    pub fn sample(&mut self, sim: &Simulation) {
+        if let Some(last) = self.samples.last()
+            && sim.t - last[0] < self.spacing
+        {
+            return;
+        }
+        let m = sim.average();
+        self.samples.push([sim.t, m.x, m.y, m.z, sim.energy()]);
+        if self.samples.len() > SAMPLES {
+            let mut k = 0;
+            self.samples.retain(|_| {
+                k += 1;
+                k % 2 == 1
+            });
+            let span = self.samples[self.samples.len() - 1][0] - self.samples[0][0];
+            self.spacing = span / (SAMPLES / 2) as f64;
+        }
+    }
+
+    /// Show the fraction `done` of the run and `status` above the plots,
+    /// redrawing unless drawn very recently
+    pub fn update(&mut self, done: f64, status: String) {
+        self.done = done;
+        self.status = status;
+        if self.drawn.is_none_or(|d| d.elapsed() >= REDRAW) {
+            self.draw();
+        }
+    }
+
+    /// Redraw the screen
+    pub fn draw(&mut self) {
+        self.drawn = Some(Instant::now());
+        let Self {
+            terminal,
+            samples,
+            rows,
+            done,
+            status,
+            ..
+        } = self;
+        let drawn = terminal.draw(|frame| render(frame, samples, rows, *done, status));
+        if let Err(e) = drawn {
+            tracing::debug!("dashboard not drawn: {e}");
+        }
+    }
+}
+
+impl Drop for Dashboard {
+    fn drop(&mut self) {
+        // main screen, visible cursor, and the rows for the record
+        let _ = execute!(std::io::stdout(), Show, LeaveAlternateScreen);
+        for row in &self.rows {
+            println!("{row}");
+        }
+        let _ = std::io::stdout().flush();
+    }
+}
+
+/// Lay out the progress line, the plots of ⟨m⟩ and the energy against time
+/// and the `rows` on `frame`
+fn render(
+    frame: &mut Frame,
+    samples: &[[f64; 5]],
+    rows: &VecDeque<String>,
+    done: f64,
+    status: &str,
+) {
+    let [progress, m, energy, printed] = Layout::vertical([
+        Constraint::Length(1),
+        Constraint::Fill(1),
+        Constraint::Fill(1),
+        Constraint::Length(ROWS as u16),
+    ])
+    .areas(frame.area());
+    frame.render_widget(
+        LineGauge::default()
+            .ratio(done.clamp(0.0, 1.0))
+            .label(status)
+            .filled_style(Style::new().fg(Color::Green)),
+        progress,
+    );
+    let series = |c: usize| -> Vec<(f64, f64)> {
+        samples
+            .iter()
+            .filter(|s| s[c].is_finite())
+            .map(|s| (s[0], s[c]))
+            .collect()
+    };
+    let (t0, t1) = match (samples.first(), samples.last()) {
+        (Some(a), Some(b)) if b[0] > a[0] => (a[0], b[0]),
+        _ => (0.0, 1.0),
+    };
+    let time = || {
+        Axis::default()
+            .bounds([t0, t1])
+            .labels([format!("{t0:.3e} s"), format!("{t1:.3e} s")])
+    };
+    let (mx, my, mz) = (series(1), series(2), series(3));
+    let datasets = [
+        ("mx", &mx, Color::Red),
+        ("my", &my, Color::Green),
+        ("mz", &mz, Color::Blue),
+    ]
+    .into_iter()
+    .map(|(name, data, colour)| line(data, colour).name(name))
+    .collect();
+    frame.render_widget(
+        Chart::new(datasets)
+            .block(Block::bordered().title("⟨m⟩"))
+            .x_axis(time())
+            .y_axis(Axis::default().bounds([-1.0, 1.0]).labels(["-1", "0", "1"])),
+        m,
+    );
+    let e = series(4);
+    let lo = e.iter().map(|p| p.1).fold(f64::INFINITY, f64::min);
+    let hi = e.iter().map(|p| p.1).fold(f64::NEG_INFINITY, f64::max);
+    let (lo, hi) = match (lo, hi) {
+        _ if lo < hi => (lo, hi),
+        _ if lo.is_finite() => (lo - 1.0, hi + 1.0),
+        // no samples yet
+        _ => (-1.0, 1.0),
+    };
+    frame.render_widget(
+        Chart::new(vec![line(&e, Color::Yellow)])
+            .block(Block::bordered().title("E_total (J)"))
+            .x_axis(time())
+            .y_axis(
+                Axis::default()
+                    .bounds([lo, hi])
+                    .labels([format!("{lo:.2e}"), format!("{hi:.2e}")]),
+            ),
+        energy,
+    );
+    let text: Vec<Line> = rows.iter().map(|r| Line::raw(r.as_str())).collect();
+    frame.render_widget(Paragraph::new(text), printed);
+}
+
+/// A line plot of `data` in `colour`, in braille dots
+fn line(data: &[(f64, f64)], colour: Color) -> Dataset<'_> {
+    Dataset::default()
+        .marker(Marker::Braille)
+        .graph_type(GraphType::Line)
+        .style(Style::new().fg(colour))
+        .data(data)
+}
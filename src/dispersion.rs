@@ -0,0 +1,157 @@
+//! Spin-wave dispersion from the snapshots of a store, `nez analyze
+//! dispersion`. One component of `m`, averaged across the sample, gives
+//! m(t, x) along the chosen axis; its static part is removed and the power
+//! |m(k, f)|² of its Fourier transform over time and position is the
+//! dispersion map, with k from -π/d to π/d and f from 0 to the Nyquist
+//! frequency of the snapshots. Waves travelling towards +x (or +y), as
+//! `cos(kx - 2πft)`, have k > 0.
+//!
+//! Snapshots written every so many steps of an adaptive solver are not
+//! evenly spaced in time; they are interpolated onto an even grid first, see
+//! [`crate::spectrum::resample`].
+
+use clap::ValueEnum;
+use rustfft::num_complex::Complex64;
+use std::{f64::consts::PI, fs::File, io::BufWriter, path::Path};
+
+use crate::{Result, ZarrOutput, fft::Fft3, spectrum::resample};
+
+/// Axis along which the waves travel
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum Axis {
+    #[default]
+    X,
+    Y,
+}
+
+/// Component of `m` analysed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum Component {
+    X,
+    Y,
+    #[default]
+    Z,
+}
+
+/// A dispersion map
+pub struct Dispersion {
+    /// wave numbers (rad/m), increasing
+    pub k: Vec<f64>,
+    /// frequencies (Hz), from 0
+    pub f: Vec<f64>,
+    /// |m(k, f)|², one row of all `k` per frequency
+    pub power: Vec<f64>,
+}
+
+/// Dispersion of `component` of the snapshots of `out` along `axis`
+pub fn compute(out: &ZarrOutput, axis: Axis, component: Component) -> Result<Dispersion> {
+    let mesh = out.config()?.mesh;
+    let frames = out.frames()?;
+    if frames < 4 {
+        return Err(format!("{frames} snapshots are too few for a dispersion").into());
+    }
+    let (n, d) = match axis {
+        Axis::X => (mesh.nx, mesh.dx),
+        Axis::Y => (mesh.ny, mesh.dy),
+    };
+    let c = component as usize;
+    // m(t, x) averaged across the sample, with the times of the snapshots
+    let mut times = Vec::with_capacity(frames as usize);
+    let mut lines = Vec::with_capacity(frames as usize);
+    for frame in 0..frames {
+        let t = out
+            .time(frame)?
+            .ok_or("the store records no times of its snapshots")?;
+        let m = out.read(frame)?;
+        let mut line = vec![0.0; n];
+        for i in 0..mesh.len() {
+            let [x, y, _] = mesh.coords(i);
+            line[if axis == Axis::X { x } else { y }] += m.get(i)[c];
+        }
+        let across = (mesh.len() / n) as f64;
+        line.iter_mut().for_each(|v| *v /= across);
+        times.push(t);
+        lines.push(line);
+    }
+    let (dt, lines) = resample(&times, &lines)?;
+    let nt = lines.len();
+    // evenly spaced samples, position fastest
+    let mut buf: Vec<Complex64> = lines
+        .into_iter()
+        .flatten()
+        .map(|v| Complex64::new(v, 0.0))
+        .collect();
+    // the static part would only add a peak at f = 0
+    for x in 0..n {
+        let mean = (0..nt).map(|s| buf[x + n * s].re).sum::<f64>() / nt as f64;
+        (0..nt).for_each(|s| buf[x + n * s].re -= mean);
+    }
+    Fft3::new([n, nt, 1]).forward(&mut buf);
+
+    let nf = nt / 2 + 1;
+    let k = (0..n)
+        .map(|i| 2.0 * PI * (i as f64 - (n / 2) as f64) / (n as f64 * d))
+        .collect();
+    let f = (0..nf).map(|j| j as f64 / (nt as f64 * dt)).collect();
+    let mut power = Vec::with_capacity(nf * n);
+    for j in 0..nf {
+        for i in 0..n {
+            // wave number index before the shift putting k = 0 in the middle,
+            // mirrored: the forward transform over both position and time
+            // finds exp(i(kx - ωt)) at -k
+            let x = (n - (i + n - n / 2) % n) % n;
+            power.push(buf[x + n * j].norm_sqr());
+        }
+    }
+    Ok(Dispersion { k, f, power })
+}
+
+impl Dispersion {
+    /// Write the map to the group `/dispersion` of `out`: `power` (f, k),
+    /// `k` and `f`
+    pub fn store(&self, out: &ZarrOutput, axis: Axis, component: Component) -> Result<()> {
+        let (nk, nf) = (self.k.len() as u64, self.f.len() as u64);
+        let about = |units: &str| {
+            let mut attributes = serde_json::Map::new();
+            attributes.insert("units".into(), units.into());
+            attributes.insert("axis".into(), format!("{axis:?}").to_lowercase().into());
+            attributes.insert(
+                "component".into(),
+                format!("m{component:?}").to_lowercase().into(),
+            );
+            attributes
+        };
+        out.write_dataset("/dispersion/k", &[nk], &["k"], &self.k, about("rad/m"))?;
+        out.write_dataset("/dispersion/f", &[nf], &["f"], &self.f, about("Hz"))?;
+        out.write_dataset(
+            "/dispersion/power",
+            &[nf, nk],
+            &["f", "k"],
+            &self.power,
+            about("1"),
+        )
+    }
+
+    /// Write the map as a grey-scale PNG image, k to the right and f
+    /// upwards, over the six decades of power below the maximum
+    pub fn write_png(&self, path: &Path) -> Result<()> {
+        let (nk, nf) = (self.k.len(), self.f.len());
+        let max = self.power.iter().cloned().fold(0.0, f64::max);
+        let mut pixels = Vec::with_capacity(nk * nf);
+        for row in self.power.chunks(nk).rev() {
+            pixels.extend(row.iter().map(|&p| {
+                let decades = if p > 0.0 { (p / max).log10() } else { -6.0 };
+                (255.0 * (1.0 + decades / 6.0).clamp(0.0, 1.0)).round() as u8
+            }));
+        }
+        let file =
+            File::create(path).map_err(|e| format!("cannot write {}: {e}", path.display()))?;
+        let mut encoder = png::Encoder::new(BufWriter::new(file), nk as u32, nf as u32);
+        encoder.set_color(png::ColorType::Grayscale);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(&pixels)?;
+        writer.finish()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,139 @@
+//! Ensembles of stochastic runs, `nez ensemble config.toml`: the config,
+//! with the thermal noise of `[langevin]`, is run once per realization with
+//! the seeds `langevin.seed`, `langevin.seed + 1`...
+//!
+//! ```toml
+//! [ensemble]
+//! realizations = 100
+//! parallel = 4            # realizations run at the same time
+//!
+//! [[run.stop_when]]       # a realization ends once it has switched
+//! column = "mz"
+//! below = 0.0
+//! ```
+//!
+//! Every realization is run as `nez run` would into the group `run_0000`,
+//! `run_0001`... of the store at `output.path`, table included. The root
+//! group gets the statistics of these tables row by row, over the
+//! realizations that reached the row: `mean`, `std`, the sample standard
+//! deviation, and `count`; the last row of every realization as `final`;
+//! and as attributes the seeds, the condition on the state (`stop_when`,
+//! `stop_torque` or `stop_dmdt` of `[run]`) that ended each realization
+//! early if one did, and the fraction of them so ended, `stopped_fraction`:
+//! with the condition above, the probability to switch within `run.steps`.
+
+use serde::{Deserialize, Serialize};
+
+use crate::{Config, Result, sweep};
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct EnsembleConfig {
+    /// independent runs, each with its own seed
+    pub realizations: usize,
+    /// realizations run at the same time, sharing the worker threads
+    pub parallel: usize,
+}
+
+impl Default for EnsembleConfig {
+    fn default() -> Self {
+        Self {
+            realizations: 16,
+            parallel: 1,
+        }
+    }
+}
+
+/// One run of an ensemble
+#[derive(Debug, Clone)]
+pub struct Realization {
+    /// name of its group in the store, e.g. `run_0003`
+    pub name: String,
+    /// `langevin.seed` of its config
+    pub seed: u64,
+    pub config: Config,
+}
+
+/// The realizations of the ensemble of `config`, writing to the store at
+/// `output.path`
+pub fn realizations(config: &Config) -> Result<Vec<Realization>> {
+    let Some(langevin) = &config.langevin else {
+        return Err("`nez ensemble` needs `[langevin]`: without thermal noise \
+                    every realization is the same"
+            .into());
+    };
+    if !config.table.zarr {
+        return Err(
+            "`nez ensemble` averages the tables of the stores and needs `table.zarr`".into(),
+        );
+    }
+    let root = &config.output.path;
+    Ok((0..config.ensemble.realizations)
+        .map(|k| {
+            let name = format!("run_{k:04}");
+            let seed = langevin.seed.wrapping_add(k as u64);
+            let mut run = config.clone();
+            if let Some(langevin) = &mut run.langevin {
+                langevin.seed = seed;
+            }
+            sweep::place(&mut run, config, root, &name);
+            Realization {
+                name,
+                seed,
+                config: run,
+            }
+        })
+        .collect())
+}
+
+/// Statistics of the tables of the realizations of an ensemble, row by row
+#[derive(Debug, Clone, PartialEq)]
+pub struct Statistics {
+    /// rows of the longest table
+    pub rows: usize,
+    pub columns: usize,
+    /// mean of every row and column, row after row
+    pub mean: Vec<f64>,
+    /// sample standard deviation, NaN where fewer than two realizations
+    /// reached the row
+    pub std: Vec<f64>,
+    /// realizations that reached every row
+    pub count: Vec<f64>,
+}
+
+impl Statistics {
+    /// Of `tables`, every realization's as a list of `columns` columns,
+    /// which may be shorter for realizations stopped early
+    pub fn of(tables: &[Vec<Vec<f64>>], columns: usize) -> Self {
+        let rows = tables
+            .iter()
+            .map(|t| t.first().map_or(0, Vec::len))
+            .max()
+            .unwrap_or(0);
+        let mut mean = vec![0.0; rows * columns];
+        let mut std = vec![0.0; rows * columns];
+        let mut count = vec![0.0; rows];
+        for r in 0..rows {
+            let reached: Vec<_> = tables
+                .iter()
+                .filter(|t| t.first().is_some_and(|c| c.len() > r))
+                .collect();
+            let n = reached.len() as f64;
+            count[r] = n;
+            for c in 0..columns {
+                let values = || reached.iter().map(|t| t[c][r]);
+                let m = values().sum::<f64>() / n;
+                let var = values().map(|v| (v - m).powi(2)).sum::<f64>() / (n - 1.0);
+                mean[r * columns + c] = m;
+                std[r * columns + c] = if n > 1.0 { var.sqrt() } else { f64::NAN };
+            }
+        }
+        Self {
+            rows,
+            columns,
+            mean,
+            std,
+            count,
+        }
+    }
+}
@@ -0,0 +1,320 @@
+//! A small arithmetic expression language, e.g. `0.01 * exp(-(x - 50e-9)^2 / w^2)`.
+//!
+//! Expressions are parsed once into a tree and evaluated per cell with the
+//! values of a fixed list of variables. Supported: numbers, `+ - * / % ^`,
+//! the comparisons `< <= > >= == !=` (1 if true, 0 otherwise), parentheses,
+//! the constants `pi` and `e`, and the functions `sin cos tan asin acos atan
+//! atan2 sinh cosh tanh exp ln log10 sqrt abs sign step sinc floor ceil round
+//! min max pow hypot`.
+
+use std::fmt;
+
+use crate::Result;
+
+/// A parsed expression
+#[derive(Debug, Clone)]
+pub struct Expr {
+    root: Node,
+    source: String,
+}
+
+#[derive(Debug, Clone)]
+enum Node {
+    Num(f64),
+    Var(usize),
+    Neg(Box<Node>),
+    Bin(char, Box<Node>, Box<Node>),
+    Call(Func, Vec<Node>),
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Func {
+    One(fn(f64) -> f64),
+    Two(fn(f64, f64) -> f64),
+}
+
+fn function(name: &str) -> Option<Func> {
+    use Func::*;
+    Some(match name {
+        "sin" => One(f64::sin),
+        "cos" => One(f64::cos),
+        "tan" => One(f64::tan),
+        "asin" => One(f64::asin),
+        "acos" => One(f64::acos),
+        "atan" => One(f64::atan),
+        "sinh" => One(f64::sinh),
+        "cosh" => One(f64::cosh),
+        "tanh" => One(f64::tanh),
+        "exp" => One(f64::exp),
+        "ln" => One(f64::ln),
+        "log10" => One(f64::log10),
+        "sqrt" => One(f64::sqrt),
+        "abs" => One(f64::abs),
+        "sign" => One(|x| if x == 0.0 { 0.0 } else { x.signum() }),
+        "step" => One(|x| if x >= 0.0 { 1.0 } else { 0.0 }),
+        "sinc" => One(|x| if x == 0.0 { 1.0 } else { x.sin() / x }),
+        "floor" => One(f64::floor),
+        "ceil" => One(f64::ceil),
+        "round" => One(f64::round),
+        "atan2" => Two(f64::atan2),
+        "min" => Two(f64::min),
+        "max" => Two(f64::max),
+        "pow" => Two(f64::powf),
+        "hypot" => Two(f64::hypot),
+        _ => return None,
+    })
+}
+
+impl Expr {
+    /// Parse `source`, where identifiers may refer to `vars` by name
+    pub fn parse(source: &str, vars: &[&str]) -> Result<Self> {
+        let mut p = Parser {
+            src: source.as_bytes(),
+            pos: 0,
+            vars,
+        };
+        let root = p.comparison()?;
+        p.skip_ws();
+        if p.pos < p.src.len() {
+            return Err(p.error("unexpected character"));
+        }
+        Ok(Self {
+            root,
+            source: source.to_string(),
+        })
+    }
+
+    /// Evaluate with `values[k]` bound to the k-th variable given to `parse`
+    pub fn eval(&self, values: &[f64]) -> f64 {
+        eval(&self.root, values)
+    }
+
+    /// Whether the k-th variable given to `parse` appears in the expression
+    pub fn uses(&self, k: usize) -> bool {
+        fn uses(node: &Node, k: usize) -> bool {
+            match node {
+                Node::Num(_) => false,
+                Node::Var(j) => *j == k,
+                Node::Neg(a) => uses(a, k),
+                Node::Bin(_, a, b) => uses(a, k) || uses(b, k),
+                Node::Call(_, args) => args.iter().any(|a| uses(a, k)),
+            }
+        }
+        uses(&self.root, k)
+    }
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+fn eval(node: &Node, v: &[f64]) -> f64 {
+    match node {
+        Node::Num(x) => *x,
+        Node::Var(k) => v[*k],
+        Node::Neg(a) => -eval(a, v),
+        Node::Bin(op, a, b) => {
+            let (a, b) = (eval(a, v), eval(b, v));
+            match op {
+                '+' => a + b,
+                '-' => a - b,
+                '*' => a * b,
+                '/' => a / b,
+                '%' => a % b,
+                '^' => a.powf(b),
+                op => {
+                    let holds = match op {
+                        '<' => a < b,
+                        '≤' => a <= b,
+                        '>' => a > b,
+                        '≥' => a >= b,
+                        '=' => a == b,
+                        _ => a != b,
+                    };
+                    if holds { 1.0 } else { 0.0 }
+                }
+            }
+        }
+        Node::Call(Func::One(f), args) => f(eval(&args[0], v)),
+        Node::Call(Func::Two(f), args) => f(eval(&args[0], v), eval(&args[1], v)),
+    }
+}
+
+struct Parser<'a> {
+    src: &'a [u8],
+    pos: usize,
+    vars: &'a [&'a str],
+}
+
+impl Parser<'_> {
+    fn error(&self, what: &str) -> crate::Error {
+        format!(
+            "{what} at position {} in `{}`",
+            self.pos + 1,
+            String::from_utf8_lossy(self.src)
+        )
+        .into()
+    }
+
+    fn skip_ws(&mut self) {
+        while self.pos < self.src.len() && self.src[self.pos].is_ascii_whitespace() {
+            self.pos += 1;
+        }
+    }
+
+    fn peek(&mut self) -> Option<u8> {
+        self.skip_ws();
+        self.src.get(self.pos).copied()
+    }
+
+    fn eat(&mut self, c: u8) -> bool {
+        if self.peek() == Some(c) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    // comparison := expr (('<' | '<=' | '>' | '>=' | '==' | '!=') expr)?
+    fn comparison(&mut self) -> Result<Node> {
+        let lhs = self.expr()?;
+        let op = match self.peek() {
+            Some(c @ (b'<' | b'>' | b'=' | b'!')) => {
+                self.pos += 1;
+                let or_equal = self.src.get(self.pos) == Some(&b'=');
+                if or_equal {
+                    self.pos += 1;
+                }
+                match (c, or_equal) {
+                    (b'<', false) => '<',
+                    (b'<', true) => '≤',
+                    (b'>', false) => '>',
+                    (b'>', true) => '≥',
+                    (b'=', true) => '=',
+                    (b'!', true) => '≠',
+                    _ => return Err(self.error("expected a comparison")),
+                }
+            }
+            _ => return Ok(lhs),
+        };
+        Ok(Node::Bin(op, Box::new(lhs), Box::new(self.expr()?)))
+    }
+
+    // expr := term (('+' | '-') term)*
+    fn expr(&mut self) -> Result<Node> {
+        let mut lhs = self.term()?;
+        while let Some(op @ (b'+' | b'-')) = self.peek() {
+            self.pos += 1;
+            lhs = Node::Bin(op as char, Box::new(lhs), Box::new(self.term()?));
+        }
+        Ok(lhs)
+    }
+
+    // term := unary (('*' | '/' | '%') unary)*
+    fn term(&mut self) -> Result<Node> {
+        let mut lhs = self.unary()?;
+        while let Some(op @ (b'*' | b'/' | b'%')) = self.peek() {
+            self.pos += 1;
+            lhs = Node::Bin(op as char, Box::new(lhs), Box::new(self.unary()?));
+        }
+        Ok(lhs)
+    }
+
+    // unary := ('-' | '+') unary | power
+    fn unary(&mut self) -> Result<Node> {
+        if self.eat(b'-') {
+            return Ok(Node::Neg(Box::new(self.unary()?)));
+        }
+        if self.eat(b'+') {
+            return self.unary();
+        }
+        self.power()
+    }
+
+    // power := atom ('^' unary)?, right associative
+    fn power(&mut self) -> Result<Node> {
+        let base = self.atom()?;
+        if self.eat(b'^') {
+            return Ok(Node::Bin('^', Box::new(base), Box::new(self.unary()?)));
+        }
+        Ok(base)
+    }
+
+    fn atom(&mut self) -> Result<Node> {
+        match self.peek() {
+            Some(b'(') => {
+                self.pos += 1;
+                let inner = self.comparison()?;
+                if !self.eat(b')') {
+                    return Err(self.error("expected `)`"));
+                }
+                Ok(inner)
+            }
+            Some(c) if c.is_ascii_digit() || c == b'.' => self.number(),
+            Some(c) if c.is_ascii_alphabetic() || c == b'_' => self.ident(),
+            Some(_) => Err(self.error("unexpected character")),
+            None => Err(self.error("unexpected end of expression")),
+        }
+    }
+
+    fn number(&mut self) -> Result<Node> {
+        let start = self.pos;
+        while self.pos < self.src.len() {
+            let c = self.src[self.pos];
+            let exponent_sign = (c == b'+' || c == b'-')
+                && matches!(self.src[self.pos - 1], b'e' | b'E')
+                && self.pos - 1 > start;
+            if c.is_ascii_digit() || c == b'.' || c == b'e' || c == b'E' || exponent_sign {
+                self.pos += 1;
+            } else {
+                break;
+            }
+        }
+        let text = std::str::from_utf8(&self.src[start..self.pos]).unwrap();
+        text.parse()
+            .map(Node::Num)
+            .map_err(|_| self.error(&format!("invalid number `{text}`")))
+    }
+
+    fn ident(&mut self) -> Result<Node> {
+        let start = self.pos;
+        while self.pos < self.src.len()
+            && (self.src[self.pos].is_ascii_alphanumeric() || self.src[self.pos] == b'_')
+        {
+            self.pos += 1;
+        }
+        let name = std::str::from_utf8(&self.src[start..self.pos]).unwrap();
+
+        if self.eat(b'(') {
+            let func =
+                function(name).ok_or_else(|| self.error(&format!("unknown function `{name}`")))?;
+            let mut args = vec![self.expr()?];
+            while self.eat(b',') {
+                args.push(self.expr()?);
+            }
+            if !self.eat(b')') {
+                return Err(self.error("expected `)`"));
+            }
+            let arity = match func {
+                Func::One(_) => 1,
+                Func::Two(_) => 2,
+            };
+            if args.len() != arity {
+                return Err(self.error(&format!("`{name}` takes {arity} argument(s)")));
+            }
+            return Ok(Node::Call(func, args));
+        }
+
+        if let Some(k) = self.vars.iter().position(|v| *v == name) {
+            return Ok(Node::Var(k));
+        }
+        match name {
+            "pi" => Ok(Node::Num(std::f64::consts::PI)),
+            "e" => Ok(Node::Num(std::f64::consts::E)),
+            _ => Err(self.error(&format!("unknown variable `{name}`"))),
+        }
+    }
+}
@@ -0,0 +1,87 @@
+use rayon::prelude::*;
+use rustfft::{Fft, FftPlanner, num_complex::Complex64};
+use std::sync::Arc;
+
+/// In-place 3D complex FFT of an x-fastest `[nx, ny, nz]` buffer
+pub struct Fft3 {
+    n: [usize; 3],
+    fwd: [Arc<dyn Fft<f64>>; 3],
+    inv: [Arc<dyn Fft<f64>>; 3],
+}
+
+impl Fft3 {
+    pub fn new(n: [usize; 3]) -> Self {
+        let mut planner = FftPlanner::new();
+        Self {
+            n,
+            fwd: n.map(|len| planner.plan_fft_forward(len)),
+            inv: n.map(|len| planner.plan_fft_inverse(len)),
+        }
+    }
+
+    pub fn size(&self) -> [usize; 3] {
+        self.n
+    }
+
+    pub fn len(&self) -> usize {
+        self.n.iter().product()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn forward(&self, buf: &mut [Complex64]) {
+        self.transform(buf, &self.fwd);
+    }
+
+    /// Inverse transform, normalised so that `inverse(forward(x)) == x`
+    pub fn inverse(&self, buf: &mut [Complex64]) {
+        self.transform(buf, &self.inv);
+        let scale = 1.0 / self.len() as f64;
+        buf.par_iter_mut().for_each(|v| *v *= scale);
+    }
+
+    fn transform(&self, buf: &mut [Complex64], plans: &[Arc<dyn Fft<f64>>; 3]) {
+        let [nx, ny, nz] = self.n;
+        let slab = nx * ny;
+
+        // x: contiguous rows, one z-slab per task
+        if nx > 1 {
+            buf.par_chunks_mut(slab).for_each(|s| plans[0].process(s));
+        }
+
+        // y: strided lines inside each z-slab
+        if ny > 1 {
+            buf.par_chunks_mut(slab).for_each(|s| {
+                let mut line = vec![Complex64::default(); ny];
+                for x in 0..nx {
+                    for (y, v) in line.iter_mut().enumerate() {
+                        *v = s[x + nx * y];
+                    }
+                    plans[1].process(&mut line);
+                    for (y, v) in line.iter().enumerate() {
+                        s[x + nx * y] = *v;
+                    }
+                }
+            });
+        }
+
+        // z: gather every (x, y) line, transform, scatter back
+        if nz > 1 {
+            let lines: Vec<Vec<Complex64>> = (0..slab)
+                .into_par_iter()
+                .map(|xy| {
+                    let mut line: Vec<_> = (0..nz).map(|z| buf[xy + slab * z]).collect();
+                    plans[2].process(&mut line);
+                    line
+                })
+                .collect();
+            for (xy, line) in lines.iter().enumerate() {
+                for (z, v) in line.iter().enumerate() {
+                    buf[xy + slab * z] = *v;
+                }
+            }
+        }
+    }
+}
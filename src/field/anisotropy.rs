@@ -0,0 +1,209 @@
+use nalgebra::{Unit, Vector3};
+use rayon::prelude::*;
+
+use super::FieldTerm;
+use crate::{Mesh, Params, VectorField, maps::Spatial, parallel::chunk};
+
+/// Uniaxial magnetocrystalline anisotropy with energy density
+/// `-K1 (m·u)² - K2 (m·u)⁴`
+pub struct UniaxialAnisotropy {
+    /// first-order constant (J m⁻³)
+    pub k1: Spatial<f64>,
+    /// second-order constant (J m⁻³)
+    pub k2: Spatial<f64>,
+    /// easy axis
+    pub axis: Spatial<Unit<Vector3<f64>>>,
+}
+
+impl UniaxialAnisotropy {
+    pub fn new(k1: f64, k2: f64, axis: Vector3<f64>) -> Self {
+        Self::varying(
+            Spatial::uniform(k1),
+            Spatial::uniform(k2),
+            Spatial::uniform(axis),
+        )
+    }
+
+    /// Constants and axis varying in space
+    pub fn varying(k1: Spatial<f64>, k2: Spatial<f64>, axis: Spatial<Vector3<f64>>) -> Self {
+        Self {
+            k1,
+            k2,
+            axis: axis.map(Unit::new_normalize),
+        }
+    }
+
+    /// Energy density (J m⁻³) of cell `i` pointing along `m`
+    fn density(&self, i: usize, m: &Vector3<f64>) -> f64 {
+        let mu2 = m.dot(&self.axis.at(i)).powi(2);
+        -self.k1.at(i) * mu2 - self.k2.at(i) * mu2 * mu2
+    }
+}
+
+impl FieldTerm for UniaxialAnisotropy {
+    fn name(&self) -> &'static str {
+        "uniaxial"
+    }
+
+    fn add_field(&self, m: &VectorField, _t: f64, _mesh: &Mesh, p: &Params, h: &mut VectorField) {
+        h.par_add(|i| {
+            let ms = p.ms_at(i);
+            if ms == 0.0 {
+                return Vector3::zeros();
+            }
+            let u = self.axis.at(i).into_inner();
+            let mu = m.get(i).dot(&u);
+            (2.0 * self.k1.at(i) * mu + 4.0 * self.k2.at(i) * mu.powi(3)) / ms * u
+        });
+    }
+
+    fn energy(&self, m: &VectorField, _t: f64, mesh: &Mesh, _p: &Params) -> f64 {
+        let e: f64 = (0..m.len())
+            .into_par_iter()
+            .with_min_len(chunk())
+            .map(|i| self.density(i, &m.get(i)))
+            .sum();
+        e * mesh.cell_volume()
+    }
+
+    fn add_energy_density(
+        &self,
+        m: &VectorField,
+        _t: f64,
+        _mesh: &Mesh,
+        _p: &Params,
+        e: &mut [f64],
+    ) {
+        e.par_iter_mut()
+            .enumerate()
+            .with_min_len(chunk())
+            .for_each(|(i, e)| *e += self.density(i, &m.get(i)));
+    }
+
+    fn delta_energy(
+        &self,
+        m: &VectorField,
+        i: usize,
+        new: Vector3<f64>,
+        _t: f64,
+        mesh: &Mesh,
+        _p: &Params,
+    ) -> Option<f64> {
+        Some((self.density(i, &new) - self.density(i, &m.get(i))) * mesh.cell_volume())
+    }
+}
+
+/// Cubic magnetocrystalline anisotropy with energy density
+/// `Kc1 (a²b² + b²c² + c²a²) + Kc2 a²b²c² + Kc3 (a⁴b⁴ + b⁴c⁴ + c⁴a⁴)`,
+/// where a, b, c are the projections of m on the three cubic axes
+pub struct CubicAnisotropy {
+    /// constants Kc1, Kc2, Kc3 (J m⁻³)
+    pub k: [Spatial<f64>; 3],
+    /// orthonormal cubic axes
+    pub axes: Spatial<[Vector3<f64>; 3]>,
+}
+
+impl CubicAnisotropy {
+    /// `c1` and `c2` need not be orthonormal: `c2` is orthogonalised against
+    /// `c1` and the third axis is `c1 × c2`
+    pub fn new(k: [f64; 3], c1: Vector3<f64>, c2: Vector3<f64>) -> Self {
+        Self::varying(
+            k.map(Spatial::uniform),
+            Spatial::uniform(Self::axes(c1, c2)),
+        )
+    }
+
+    /// Constants and axes (see [`CubicAnisotropy::axes`]) varying in space
+    pub fn varying(k: [Spatial<f64>; 3], axes: Spatial<[Vector3<f64>; 3]>) -> Self {
+        Self { k, axes }
+    }
+
+    /// Orthonormal axes from `c1` and `c2`, as in [`CubicAnisotropy::new`]
+    pub fn axes(c1: Vector3<f64>, c2: Vector3<f64>) -> [Vector3<f64>; 3] {
+        let c1 = c1.normalize();
+        let c2 = (c2 - c2.dot(&c1) * c1).normalize();
+        [c1, c2, c1.cross(&c2)]
+    }
+
+    /// Kc1, Kc2, Kc3 in cell `i`
+    #[inline(always)]
+    fn constants(&self, i: usize) -> [f64; 3] {
+        self.k.each_ref().map(|k| k.at(i))
+    }
+
+    fn project(&self, i: usize, m: &Vector3<f64>) -> [f64; 3] {
+        self.axes.at(i).map(|c| m.dot(&c))
+    }
+
+    /// Energy density (J m⁻³) of cell `i` pointing along `m`
+    fn density(&self, i: usize, m: &Vector3<f64>) -> f64 {
+        let [k1, k2, k3] = self.constants(i);
+        let [a, b, c] = self.project(i, m);
+        let (a2, b2, c2) = (a * a, b * b, c * c);
+        k1 * (a2 * b2 + b2 * c2 + c2 * a2)
+            + k2 * a2 * b2 * c2
+            + k3 * (a2 * a2 * b2 * b2 + b2 * b2 * c2 * c2 + c2 * c2 * a2 * a2)
+    }
+}
+
+impl FieldTerm for CubicAnisotropy {
+    fn name(&self) -> &'static str {
+        "cubic"
+    }
+
+    fn add_field(&self, m: &VectorField, _t: f64, _mesh: &Mesh, p: &Params, h: &mut VectorField) {
+        h.par_add(|i| {
+            let ms = p.ms_at(i);
+            if ms == 0.0 {
+                return Vector3::zeros();
+            }
+            let [k1, k2, k3] = self.constants(i);
+            let axes = self.axes.at(i);
+            let [a, b, c] = self.project(i, &m.get(i));
+            let (a2, b2, c2) = (a * a, b * b, c * c);
+            // ∂E/∂a, ∂E/∂b, ∂E/∂c
+            let da =
+                2.0 * a * (k1 * (b2 + c2) + k2 * b2 * c2) + 4.0 * k3 * a * a2 * (b2 * b2 + c2 * c2);
+            let db =
+                2.0 * b * (k1 * (a2 + c2) + k2 * a2 * c2) + 4.0 * k3 * b * b2 * (a2 * a2 + c2 * c2);
+            let dc =
+                2.0 * c * (k1 * (a2 + b2) + k2 * a2 * b2) + 4.0 * k3 * c * c2 * (a2 * a2 + b2 * b2);
+            -(da * axes[0] + db * axes[1] + dc * axes[2]) / ms
+        });
+    }
+
+    fn energy(&self, m: &VectorField, _t: f64, mesh: &Mesh, _p: &Params) -> f64 {
+        let e: f64 = (0..m.len())
+            .into_par_iter()
+            .with_min_len(chunk())
+            .map(|i| self.density(i, &m.get(i)))
+            .sum();
+        e * mesh.cell_volume()
+    }
+
+    fn add_energy_density(
+        &self,
+        m: &VectorField,
+        _t: f64,
+        _mesh: &Mesh,
+        _p: &Params,
+        e: &mut [f64],
+    ) {
+        e.par_iter_mut()
+            .enumerate()
+            .with_min_len(chunk())
+            .for_each(|(i, e)| *e += self.density(i, &m.get(i)));
+    }
+
+    fn delta_energy(
+        &self,
+        m: &VectorField,
+        i: usize,
+        new: Vector3<f64>,
+        _t: f64,
+        mesh: &Mesh,
+        _p: &Params,
+    ) -> Option<f64> {
+        Some((self.density(i, &new) - self.density(i, &m.get(i))) * mesh.cell_volume())
+    }
+}
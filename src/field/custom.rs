@@ -0,0 +1,80 @@
+use super::FieldTerm;
+use crate::{Mesh, Params, VectorField};
+
+/// Field of every cell (T) given `m` and the time
+pub type FieldFn = Box<dyn Fn(&VectorField, f64) -> VectorField + Send + Sync>;
+/// Energy of the whole sample (J) given `m` and the time
+pub type EnergyFn = Box<dyn Fn(&VectorField, f64) -> f64 + Send + Sync>;
+
+/// A term defined by closures, for interactions the crate does not know,
+/// registered with [`FieldTerms::insert`](super::FieldTerms::insert) next
+/// to the built-in ones. Several may join, under different names; the name
+/// also heads its `E_` column in the table.
+///
+/// Without an energy closure the term counts no energy, and like every
+/// term without a local update it keeps Monte Carlo from running.
+///
+/// ```
+/// use nalgebra::Vector3;
+/// use nez::{Mesh, Params, Simulation, VectorField, field::Custom};
+///
+/// let mesh = Mesh::chain(16, 2e-9);
+/// let mut sim = Simulation::new(mesh, Params::default(), Vector3::x());
+/// // a field along z growing with mx, like a crude exchange bias
+/// let bias = Custom::new("bias", |m: &VectorField, _t| {
+///     VectorField::from_fn(m.len(), |i| Vector3::new(0.0, 0.0, 0.1 * m.get(i).x))
+/// });
+/// sim.terms.insert(bias);
+/// sim.run(10).unwrap();
+/// assert!(sim.terms.names().any(|(name, _)| name == "bias"));
+/// ```
+pub struct Custom {
+    name: &'static str,
+    field: FieldFn,
+    energy: Option<EnergyFn>,
+}
+
+impl Custom {
+    pub fn new(
+        name: &'static str,
+        field: impl Fn(&VectorField, f64) -> VectorField + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            name,
+            field: Box::new(field),
+            energy: None,
+        }
+    }
+
+    /// The term with its energy given by `energy`
+    pub fn with_energy(
+        mut self,
+        energy: impl Fn(&VectorField, f64) -> f64 + Send + Sync + 'static,
+    ) -> Self {
+        self.energy = Some(Box::new(energy));
+        self
+    }
+}
+
+impl FieldTerm for Custom {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn add_field(&self, m: &VectorField, t: f64, _mesh: &Mesh, _p: &Params, h: &mut VectorField) {
+        let field = (self.field)(m, t);
+        assert_eq!(
+            field.len(),
+            m.len(),
+            "field term `{}` returned a field of {} cells for {}",
+            self.name,
+            field.len(),
+            m.len()
+        );
+        h.axpy(1.0, &field);
+    }
+
+    fn energy(&self, m: &VectorField, t: f64, _mesh: &Mesh, _p: &Params) -> f64 {
+        self.energy.as_ref().map_or(0.0, |energy| energy(m, t))
+    }
+}
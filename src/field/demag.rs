@@ -0,0 +1,301 @@
+use nalgebra::Vector3;
+use rayon::prelude::*;
+use rustfft::num_complex::Complex64;
+use std::{f64::consts::PI, sync::Mutex};
+
+use super::FieldTerm;
+use crate::{Mesh, Params, VectorField, fft::Fft3, parallel::chunk};
+
+/// Beyond this distance (in units of the largest cell dimension) the Newell
+/// formulas lose too many digits to cancellation and the point-dipole
+/// approximation is used instead.
+const DIPOLE_CUTOFF: f64 = 32.0;
+
+/// Magnetostatic field via FFT convolution with the Newell demag tensor.
+///
+/// The kernel is evaluated once on a grid zero-padded to twice the mesh size
+/// along every free axis with more than one cell, and kept in Fourier space.
+/// Periodic axes are not padded, so the convolution wraps around, and the
+/// kernel sums over `mesh.pbc` images on each side.
+pub struct Demag {
+    mesh: Mesh,
+    fft: Fft3,
+    /// Fourier transforms of Nxx, Nyy, Nzz, Nxy, Nxz, Nyz (purely real, as the
+    /// tensor components are even or odd along every axis)
+    kernel: [Vec<f64>; 6],
+    /// padded mx, my, mz
+    scratch: Mutex<[Vec<Complex64>; 3]>,
+}
+
+impl Demag {
+    pub fn new(mesh: &Mesh) -> Self {
+        let padded: [usize; 3] = std::array::from_fn(|a| {
+            let n = mesh.size()[a];
+            if n > 1 && !mesh.periodic(a) { 2 * n } else { n }
+        });
+        let fft = Fft3::new(padded);
+        let [px, py, _] = padded;
+        let d = mesh.cell();
+
+        // N(r) for every displacement, stored at index r mod padded
+        let mut kernel: [Vec<Complex64>; 6] =
+            std::array::from_fn(|_| vec![Complex64::default(); fft.len()]);
+        let values: Vec<[f64; 6]> = (0..fft.len())
+            .into_par_iter()
+            .map(|i| {
+                let c = [i % px, (i / px) % py, i / (px * py)];
+                let shifts: [Vec<(f64, f64)>; 3] = std::array::from_fn(|a| shifts(mesh, a, c[a]));
+                let mut n = [0.0; 6];
+                for &(x, wx) in &shifts[0] {
+                    for &(y, wy) in &shifts[1] {
+                        for &(z, wz) in &shifts[2] {
+                            let t = tensor([x, y, z], d);
+                            for k in 0..6 {
+                                n[k] += wx * wy * wz * t[k];
+                            }
+                        }
+                    }
+                }
+                n
+            })
+            .collect();
+        for (i, v) in values.iter().enumerate() {
+            for (k, n) in kernel.iter_mut().enumerate() {
+                n[i] = Complex64::new(v[k], 0.0);
+            }
+        }
+
+        for n in kernel.iter_mut() {
+            fft.forward(n);
+        }
+
+        let len = fft.len();
+        Self {
+            mesh: *mesh,
+            fft,
+            kernel: kernel.map(|n| n.into_iter().map(|v| v.re).collect()),
+            scratch: Mutex::new(std::array::from_fn(|_| vec![Complex64::default(); len])),
+        }
+    }
+
+    /// Add B_demag = -μ₀Mₛ N * m to `h`
+    fn convolve(&self, m: &VectorField, p: &Params, h: &mut VectorField) {
+        // μ₀M of every cell, scaled by μ₀Mₛ afterwards when it is uniform
+        if p.is_uniform() {
+            self.convolve_moments(|i| m.get(i), p.mu0_ms, h);
+        } else {
+            self.convolve_moments(|i| p.mu0_ms_at(i) * m.get(i), 1.0, h);
+        }
+    }
+
+    /// Add the field of the net magnetization μ₀(M₁ + M₂) of two
+    /// sublattices `m` with parameters `p` to `h`
+    pub fn add_net_field(&self, m: [&VectorField; 2], p: [&Params; 2], h: &mut VectorField) {
+        self.convolve_moments(
+            |i| p[0].mu0_ms_at(i) * m[0].get(i) + p[1].mu0_ms_at(i) * m[1].get(i),
+            1.0,
+            h,
+        );
+    }
+
+    /// Add `-scale N * μ₀M` to `h`, with μ₀M of cell `i` given by `mu0_m(i)`
+    fn convolve_moments(
+        &self,
+        mu0_m: impl Fn(usize) -> Vector3<f64>,
+        scale: f64,
+        h: &mut VectorField,
+    ) {
+        let mut scratch = self.scratch.lock().unwrap();
+        let [mx, my, mz] = &mut *scratch;
+        let [px, py, _] = self.fft.size();
+        let mesh = &self.mesh;
+        let pad = |i: usize| {
+            let [x, y, z] = mesh.coords(i);
+            x + px * (y + py * z)
+        };
+
+        for buf in [&mut *mx, &mut *my, &mut *mz] {
+            buf.fill(Complex64::default());
+        }
+        for i in 0..h.len() {
+            let (j, v) = (pad(i), mu0_m(i));
+            mx[j].re = v.x;
+            my[j].re = v.y;
+            mz[j].re = v.z;
+        }
+
+        for buf in [&mut *mx, &mut *my, &mut *mz] {
+            self.fft.forward(buf);
+        }
+
+        let [nxx, nyy, nzz, nxy, nxz, nyz] = &self.kernel;
+        (mx.par_iter_mut(), my.par_iter_mut(), mz.par_iter_mut())
+            .into_par_iter()
+            .with_min_len(chunk())
+            .enumerate()
+            .for_each(|(k, (x, y, z))| {
+                let (a, b, c) = (*x, *y, *z);
+                *x = nxx[k] * a + nxy[k] * b + nxz[k] * c;
+                *y = nxy[k] * a + nyy[k] * b + nyz[k] * c;
+                *z = nxz[k] * a + nyz[k] * b + nzz[k] * c;
+            });
+
+        for buf in [&mut *mx, &mut *my, &mut *mz] {
+            self.fft.inverse(buf);
+        }
+
+        h.par_add(|i| {
+            let j = pad(i);
+            -scale * Vector3::new(mx[j].re, my[j].re, mz[j].re)
+        });
+    }
+}
+
+/// Displacements (m) and weights contributing to kernel index `c` along
+/// `axis`.
+///
+/// On a free axis index n (only present when padded) is the unused midpoint,
+/// so it gets no displacement at all. On a periodic axis every image within
+/// `mesh.pbc` periods is included. For even n the Nyquist index is split
+/// evenly between +n/2 and -n/2, which keeps the kernel symmetric.
+pub(super) fn shifts(mesh: &Mesh, axis: usize, c: usize) -> Vec<(f64, f64)> {
+    let n = mesh.size()[axis];
+    let d = mesh.cell()[axis];
+    if !mesh.periodic(axis) {
+        if n > 1 && c == n {
+            return Vec::new();
+        }
+        let s = if c < n {
+            c as f64
+        } else {
+            c as f64 - 2.0 * n as f64
+        };
+        return vec![(s * d, 1.0)];
+    }
+    let images = mesh.pbc[axis] as isize;
+    let centres: Vec<(isize, f64)> = if 2 * c == n {
+        vec![(c as isize, 0.5), (c as isize - n as isize, 0.5)]
+    } else if 2 * c < n {
+        vec![(c as isize, 1.0)]
+    } else {
+        vec![(c as isize - n as isize, 1.0)]
+    };
+    let mut out = Vec::new();
+    for (s, w) in centres {
+        for k in -images..=images {
+            out.push(((s + k * n as isize) as f64 * d, w));
+        }
+    }
+    out
+}
+
+impl FieldTerm for Demag {
+    fn name(&self) -> &'static str {
+        "demag"
+    }
+
+    fn add_field(&self, m: &VectorField, _t: f64, _mesh: &Mesh, p: &Params, h: &mut VectorField) {
+        self.convolve(m, p, h);
+    }
+
+    fn energy(&self, m: &VectorField, _t: f64, mesh: &Mesh, p: &Params) -> f64 {
+        let mut h = VectorField::zeros(m.len());
+        self.convolve(m, p, &mut h);
+        -0.5 * super::moment_dot(m, &h, mesh, p)
+    }
+}
+
+/// Demag tensor (Nxx, Nyy, Nzz, Nxy, Nxz, Nyz) between two cells of size `d`
+/// separated by `r`
+pub fn tensor(r: [f64; 3], d: [f64; 3]) -> [f64; 6] {
+    let [x, y, z] = r;
+    let [dx, dy, dz] = d;
+    let dist = (x * x + y * y + z * z).sqrt();
+    if dist > DIPOLE_CUTOFF * dx.max(dy).max(dz) {
+        return dipole(r, dx * dy * dz);
+    }
+    [
+        newell(f, [x, y, z], [dx, dy, dz]),
+        newell(f, [y, x, z], [dy, dx, dz]),
+        newell(f, [z, y, x], [dz, dy, dx]),
+        newell(g, [x, y, z], [dx, dy, dz]),
+        newell(g, [x, z, y], [dx, dz, dy]),
+        newell(g, [y, z, x], [dy, dz, dx]),
+    ]
+}
+
+/// Point-dipole tensor for a cell of volume `v`
+pub(super) fn dipole(r: [f64; 3], v: f64) -> [f64; 6] {
+    let [x, y, z] = r;
+    let r2 = x * x + y * y + z * z;
+    let r5 = r2 * r2 * r2.sqrt();
+    let pref = -v / (4.0 * PI * r5);
+    [
+        pref * (3.0 * x * x - r2),
+        pref * (3.0 * y * y - r2),
+        pref * (3.0 * z * z - r2),
+        pref * 3.0 * x * y,
+        pref * 3.0 * x * z,
+        pref * 3.0 * y * z,
+    ]
+}
+
+/// Newell's 27-point second difference of `func`, which turns the
+/// point-to-point potential into the cell-averaged tensor component
+fn newell(func: fn(f64, f64, f64) -> f64, r: [f64; 3], d: [f64; 3]) -> f64 {
+    const W: [(f64, f64); 3] = [(-1.0, -1.0), (0.0, 2.0), (1.0, -1.0)];
+    let mut sum = 0.0;
+    for (sx, wx) in W {
+        for (sy, wy) in W {
+            for (sz, wz) in W {
+                sum += wx * wy * wz * func(r[0] + sx * d[0], r[1] + sy * d[1], r[2] + sz * d[2]);
+            }
+        }
+    }
+    sum / (4.0 * PI * d[0] * d[1] * d[2])
+}
+
+/// Newell's f, generating the diagonal components
+fn f(x: f64, y: f64, z: f64) -> f64 {
+    let (x, y, z) = (x.abs(), y.abs(), z.abs());
+    let (xx, yy, zz) = (x * x, y * y, z * z);
+    let r = (xx + yy + zz).sqrt();
+    let mut res = (2.0 * xx - yy - zz) * r / 6.0;
+    if xx + zz > 0.0 {
+        res += 0.5 * y * (zz - xx) * (y / (xx + zz).sqrt()).asinh();
+    }
+    if xx + yy > 0.0 {
+        res += 0.5 * z * (yy - xx) * (z / (xx + yy).sqrt()).asinh();
+    }
+    if x * r > 0.0 {
+        res -= x * y * z * (y * z / (x * r)).atan();
+    }
+    res
+}
+
+/// Newell's g, generating the off-diagonal components
+fn g(x: f64, y: f64, z: f64) -> f64 {
+    let z = z.abs();
+    let (xx, yy, zz) = (x * x, y * y, z * z);
+    let r = (xx + yy + zz).sqrt();
+    let mut res = -x * y * r / 3.0;
+    if xx + yy > 0.0 {
+        res += x * y * z * (z / (xx + yy).sqrt()).asinh();
+    }
+    if yy + zz > 0.0 {
+        res += y / 6.0 * (3.0 * zz - yy) * (x / (yy + zz).sqrt()).asinh();
+    }
+    if xx + zz > 0.0 {
+        res += x / 6.0 * (3.0 * zz - xx) * (y / (xx + zz).sqrt()).asinh();
+    }
+    if z * r > 0.0 {
+        res -= z * zz / 6.0 * (x * y / (z * r)).atan();
+    }
+    if y * r != 0.0 {
+        res -= z * yy / 2.0 * (x * z / (y * r)).atan();
+    }
+    if x * r != 0.0 {
+        res -= z * xx / 2.0 * (y * z / (x * r)).atan();
+    }
+    res
+}
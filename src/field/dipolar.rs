@@ -0,0 +1,155 @@
+use nalgebra::Vector3;
+use rayon::prelude::*;
+
+use super::{
+    FieldTerm,
+    demag::{dipole, shifts},
+};
+use crate::{Mesh, Params, VectorField};
+
+/// Magnetostatic field summed directly over point dipoles, one per cell, for
+/// chains and small or sparse systems where the FFT convolution of
+/// [`Demag`](super::Demag) is overkill or its cell-averaged tensor
+/// inappropriate.
+///
+/// The tensor of every displacement on the mesh is tabulated once, summed
+/// over `mesh.pbc` images along periodic axes; displacements longer than the
+/// cutoff, if any, are dropped. Each cell then sums over the table, so the
+/// cost grows with the number of cells times the number of displacements
+/// within the cutoff.
+pub struct Dipolar {
+    mesh: Mesh,
+    /// displacement (cells) to another cell and its dipole tensor Nxx, Nyy,
+    /// Nzz, Nxy, Nxz, Nyz
+    stencil: Vec<([isize; 3], [f64; 6])>,
+    /// tensor of the periodic images of a cell itself
+    images: [f64; 6],
+}
+
+impl Dipolar {
+    /// Point dipoles on `mesh`, interacting up to `cutoff` (m) apart
+    pub fn new(mesh: &Mesh, cutoff: Option<f64>) -> Self {
+        let size = mesh.size();
+        // displacement index c along an axis, as in the demag kernel
+        let range = |a: usize| {
+            let n = size[a];
+            if n > 1 && !mesh.periodic(a) { 2 * n } else { n }
+        };
+        let offset = |a: usize, c: usize| {
+            if c < size[a] {
+                c as isize
+            } else {
+                c as isize - 2 * size[a] as isize
+            }
+        };
+        let cutoff = cutoff.unwrap_or(f64::INFINITY);
+        let v = mesh.cell_volume();
+        let [rx, ry, rz] = [0, 1, 2].map(range);
+        let mut stencil: Vec<_> = (0..rx * ry * rz)
+            .into_par_iter()
+            .filter_map(|k| {
+                let c = [k % rx, (k / rx) % ry, k / (rx * ry)];
+                let shifts: [Vec<(f64, f64)>; 3] = std::array::from_fn(|a| shifts(mesh, a, c[a]));
+                let mut n = [0.0; 6];
+                for &(x, wx) in &shifts[0] {
+                    for &(y, wy) in &shifts[1] {
+                        for &(z, wz) in &shifts[2] {
+                            let r = (x * x + y * y + z * z).sqrt();
+                            // no self-interaction, but images of the cell count
+                            if r == 0.0 || r > cutoff {
+                                continue;
+                            }
+                            let t = dipole([x, y, z], v);
+                            for k in 0..6 {
+                                n[k] += wx * wy * wz * t[k];
+                            }
+                        }
+                    }
+                }
+                (n != [0.0; 6]).then(|| ([0, 1, 2].map(|a| offset(a, c[a])), n))
+            })
+            .collect();
+        let images = match stencil.iter().position(|(s, _)| *s == [0; 3]) {
+            Some(k) => stencil.swap_remove(k).1,
+            None => [0.0; 6],
+        };
+        Self {
+            mesh: *mesh,
+            stencil,
+            images,
+        }
+    }
+
+    /// B_dip = -Σⱼ N(rᵢ - rⱼ) μ₀Mⱼ at cell `i`
+    fn field_at(&self, m: &VectorField, p: &Params, i: usize) -> Vector3<f64> {
+        self.others_at(m, p, i) - apply(&self.images, p.mu0_ms_at(i) * m.get(i))
+    }
+
+    /// [`field_at`](Self::field_at) without the images of cell `i` itself
+    fn others_at(&self, m: &VectorField, p: &Params, i: usize) -> Vector3<f64> {
+        let size = self.mesh.size();
+        let r = self.mesh.coords(i);
+        let mut b = Vector3::zeros();
+        'stencil: for (s, n) in &self.stencil {
+            let mut c = [0; 3];
+            for a in 0..3 {
+                let len = size[a] as isize;
+                let x = r[a] as isize + s[a];
+                c[a] = if self.mesh.periodic(a) {
+                    x.rem_euclid(len)
+                } else if (0..len).contains(&x) {
+                    x
+                } else {
+                    continue 'stencil;
+                } as usize;
+            }
+            let j = self.mesh.idx(c[0], c[1], c[2]);
+            b -= apply(n, p.mu0_ms_at(j) * m.get(j));
+        }
+        b
+    }
+}
+
+/// The symmetric tensor `n` applied to `v`
+#[inline(always)]
+fn apply(n: &[f64; 6], v: Vector3<f64>) -> Vector3<f64> {
+    let [nxx, nyy, nzz, nxy, nxz, nyz] = *n;
+    Vector3::new(
+        nxx * v.x + nxy * v.y + nxz * v.z,
+        nxy * v.x + nyy * v.y + nyz * v.z,
+        nxz * v.x + nyz * v.y + nzz * v.z,
+    )
+}
+
+impl FieldTerm for Dipolar {
+    fn name(&self) -> &'static str {
+        "dipolar"
+    }
+
+    fn add_field(&self, m: &VectorField, _t: f64, _mesh: &Mesh, p: &Params, h: &mut VectorField) {
+        h.par_add(|i| self.field_at(m, p, i));
+    }
+
+    fn energy(&self, m: &VectorField, _t: f64, mesh: &Mesh, p: &Params) -> f64 {
+        let mut h = VectorField::zeros(m.len());
+        self.add_field(m, 0.0, mesh, p, &mut h);
+        -0.5 * super::moment_dot(m, &h, mesh, p)
+    }
+
+    fn delta_energy(
+        &self,
+        m: &VectorField,
+        i: usize,
+        new: Vector3<f64>,
+        _t: f64,
+        mesh: &Mesh,
+        p: &Params,
+    ) -> Option<f64> {
+        // the energy of the other dipoles is linear in m[i], that of the
+        // images of cell i quadratic
+        let (old, mu0_ms) = (m.get(i), p.mu0_ms_at(i));
+        let own = |m: Vector3<f64>| 0.5 * mu0_ms * m.dot(&apply(&self.images, m));
+        let b = self.others_at(m, p, i);
+        Some((own(new) - own(old) - (new - old).dot(&b)) * p.ms_at(i) * mesh.cell_volume())
+    }
+}
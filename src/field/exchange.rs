@@ -0,0 +1,154 @@
+use nalgebra::Vector3;
+use rayon::prelude::*;
+
+use super::{FieldTerm, moment_dot};
+use crate::{Mesh, Params, VectorField, vector_field::Real};
+
+/// Add the exchange field `(2A/Mₛ) ∇²m` of every cell to `h`: 6-neighbour
+/// Laplacian, where a missing neighbour at a free boundary counts as the cell
+/// itself, i.e. dm/dn = 0.
+///
+/// Each component is swept one x row at a time, so the inner loop runs over
+/// contiguous memory. With material values varying from cell to cell, each
+/// pair of cells couples through the harmonic mean of their stiffnesses
+/// instead, weakened across grain boundaries by `grains.exchange`.
+pub fn add_exchange_field(m: &VectorField, mesh: &Mesh, p: &Params, h: &mut VectorField) {
+    if !p.is_uniform() {
+        return add_local_exchange_field(m, mesh, p, h);
+    }
+    let nx = mesh.nx;
+    let [cx, cy, cz] = mesh
+        .cell()
+        .map(|d| (2.0 * p.a_ex / (p.ms() * d * d)) as Real);
+    let shift = |r, axis, dir| shift_row(mesh, r, axis, dir);
+    for (src, dst) in [(&m.x, &mut h.x), (&m.y, &mut h.y), (&m.z, &mut h.z)] {
+        dst.par_chunks_mut(nx).enumerate().for_each(|(r, out)| {
+            let row = |r: usize| &src[r * nx..(r + 1) * nx];
+            let here = row(r);
+            let (ym, yp) = (row(shift(r, 1, -1)), row(shift(r, 1, 1)));
+            let (zm, zp) = (row(shift(r, 2, -1)), row(shift(r, 2, 1)));
+            let transverse = |x: usize| {
+                cy * (ym[x] + yp[x] - 2.0 * here[x]) + cz * (zm[x] + zp[x] - 2.0 * here[x])
+            };
+            for x in 1..nx.saturating_sub(1) {
+                out[x] += cx * (here[x - 1] + here[x + 1] - 2.0 * here[x]) + transverse(x);
+            }
+            // the two ends of the row, or its only cell
+            let last = nx - 1;
+            let (left, right) = if mesh.periodic(0) {
+                (here[last], here[0])
+            } else {
+                (here[0], here[last])
+            };
+            let after_first = if nx > 1 { here[1] } else { right };
+            out[0] += cx * (left + after_first - 2.0 * here[0]) + transverse(0);
+            if nx > 1 {
+                out[last] += cx * (here[last - 1] + right - 2.0 * here[last]) + transverse(last);
+            }
+        });
+    }
+}
+
+/// Row of x cells holding the neighbours one step `dir` along y (`axis`
+/// 1) or z (2) of row `r`: the row itself past a free edge
+#[inline(always)]
+pub(crate) fn shift_row(mesh: &Mesh, r: usize, axis: usize, dir: isize) -> usize {
+    let (c, n, stride) = if axis == 1 {
+        (r % mesh.ny, mesh.ny, 1)
+    } else {
+        (r / mesh.ny, mesh.nz, mesh.ny)
+    };
+    let next = c as isize + dir;
+    if next >= 0 && (next as usize) < n {
+        (r as isize + dir * stride as isize) as usize
+    } else if mesh.periodic(axis) {
+        (r as isize + dir * stride as isize - dir * (n * stride) as isize) as usize
+    } else {
+        r
+    }
+}
+
+/// The exchange interaction as a [`FieldTerm`]
+pub struct Exchange;
+
+impl FieldTerm for Exchange {
+    fn name(&self) -> &'static str {
+        "exchange"
+    }
+
+    fn add_field(&self, m: &VectorField, _t: f64, mesh: &Mesh, p: &Params, h: &mut VectorField) {
+        add_exchange_field(m, mesh, p, h);
+    }
+
+    fn energy(&self, m: &VectorField, _t: f64, mesh: &Mesh, p: &Params) -> f64 {
+        let mut h = VectorField::zeros(m.len());
+        add_exchange_field(m, mesh, p, &mut h);
+        -0.5 * moment_dot(m, &h, mesh, p)
+    }
+
+    fn delta_energy(
+        &self,
+        m: &VectorField,
+        i: usize,
+        new: Vector3<f64>,
+        _t: f64,
+        mesh: &Mesh,
+        p: &Params,
+    ) -> Option<f64> {
+        let dm = new - m.get(i);
+        Some(-dm.dot(&exchange_coupling(m, i, mesh, p)) * p.ms_at(i) * mesh.cell_volume())
+    }
+}
+
+/// Stiffness coupling cells `i` and `j` (J m⁻¹), the harmonic mean of theirs
+/// scaled at grain boundaries
+#[inline(always)]
+fn pair_stiffness(p: &Params, i: usize, j: usize) -> f64 {
+    let (a, b) = (p.a_ex_at(i), p.a_ex_at(j));
+    if a + b > 0.0 {
+        2.0 * a * b / (a + b) * p.exchange_scale(i, j)
+    } else {
+        0.0
+    }
+}
+
+/// [`add_exchange_field`] cell by cell: `(2/Mₛᵢ) Σⱼ Aᵢⱼ (mⱼ - mᵢ)/d²`
+fn add_local_exchange_field(m: &VectorField, mesh: &Mesh, p: &Params, h: &mut VectorField) {
+    h.par_add(|i| {
+        let ms = p.ms_at(i);
+        if ms == 0.0 {
+            return Vector3::zeros();
+        }
+        let m0 = m.get(i);
+        let mut sum = Vector3::zeros();
+        for (axis, d) in mesh.cell().into_iter().enumerate() {
+            for dir in [-1, 1] {
+                if let Some(j) = p.neighbor(mesh, i, axis, dir) {
+                    sum += pair_stiffness(p, i, j) / (d * d) * (m.get(j) - m0);
+                }
+            }
+        }
+        (2.0 / ms) * sum
+    });
+}
+
+/// Exchange field at cell *i* from its neighbours alone, `(2A/Mₛ) Σⱼ mⱼ/d²`.
+///
+/// Up to a constant the exchange energy is `-Mₛ V Σ mᵢ·` of this over pairs,
+/// so turning spin *i* by `δm` changes it by `-Mₛ V δm·` this coupling.
+pub fn exchange_coupling(m: &VectorField, i: usize, mesh: &Mesh, p: &Params) -> Vector3<f64> {
+    let ms = p.ms_at(i);
+    if ms == 0.0 {
+        return Vector3::zeros();
+    }
+    let mut sum = Vector3::zeros();
+    for (axis, d) in mesh.cell().into_iter().enumerate() {
+        for dir in [-1, 1] {
+            // a cell that is its own neighbour (one periodic cell) adds m_i·m_i = 1
+            if let Some(j) = p.neighbor(mesh, i, axis, dir).filter(|&j| j != i) {
+                sum += pair_stiffness(p, i, j) * m.get(j) / (d * d);
+            }
+        }
+    }
+    (2.0 / ms) * sum
+}
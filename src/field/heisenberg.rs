@@ -0,0 +1,183 @@
+use nalgebra::Vector3;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::FieldTerm;
+use crate::{Mesh, Params, Result, VectorField, parallel::chunk};
+
+/// Heisenberg exchange between the moments of the cells taken as atoms, with
+/// energy `-Σ Jᵢⱼ mᵢ·mⱼ` over pairs, and one constant per shell of
+/// neighbours:
+///
+/// ```toml
+/// [heisenberg]
+/// j = [1e-21, -0.4e-21]   # nearest and next-nearest neighbours
+/// ```
+///
+/// Shells are the distances between cell centres in increasing order: along
+/// a chain the first holds the two cells next to each other cell, the second
+/// those two cells away; on a square film the second holds the four
+/// diagonals. Negative constants couple antiferromagnetically, so competing
+/// shells can frustrate the lattice into a spiral. The term comes on top of
+/// the continuum exchange of `a_ex`, which `disable = ["exchange"]` drops.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HeisenbergConfig {
+    /// exchange constant of every shell (J), nearest first
+    pub j: Vec<f64>,
+}
+
+/// The other cell of a coupled pair, with the constant `j` (J)
+#[derive(Debug, Clone, Copy)]
+struct Link {
+    other: usize,
+    j: f64,
+}
+
+pub struct Heisenberg {
+    /// links of cell `i` at `links[start[i]..start[i + 1]]`
+    start: Vec<usize>,
+    links: Vec<Link>,
+}
+
+impl Heisenberg {
+    /// The couplings of `config` on `mesh`; fails if the mesh has fewer
+    /// shells than constants given
+    pub fn new(config: &HeisenbergConfig, mesh: &Mesh) -> Result<Self> {
+        let shells = shells(mesh, config.j.len());
+        if shells.len() < config.j.len() {
+            return Err(format!(
+                "`heisenberg.j` gives {} shells, the mesh has only {}",
+                config.j.len(),
+                shells.len()
+            )
+            .into());
+        }
+        let mut start = vec![0; mesh.len() + 1];
+        let mut links = Vec::new();
+        for i in 0..mesh.len() {
+            for (offsets, &j) in shells.iter().zip(&config.j) {
+                links.extend(
+                    offsets
+                        .iter()
+                        .filter_map(|&o| displaced(mesh, i, o))
+                        .filter(|&other| other != i)
+                        .map(|other| Link { other, j }),
+                );
+            }
+            start[i + 1] = links.len();
+        }
+        Ok(Self { start, links })
+    }
+
+    fn links(&self, i: usize) -> &[Link] {
+        &self.links[self.start[i]..self.start[i + 1]]
+    }
+
+    /// `Σⱼ Jᵢⱼ mⱼ` over the links of cell `i` (J)
+    fn coupling(&self, m: &VectorField, i: usize) -> Vector3<f64> {
+        self.links(i).iter().map(|l| l.j * m.get(l.other)).sum()
+    }
+}
+
+/// Cell `offset` cells away from cell `i`, wrapping along periodic axes, or
+/// `None` past a free edge
+fn displaced(mesh: &Mesh, i: usize, offset: [isize; 3]) -> Option<usize> {
+    let (coords, size) = (mesh.coords(i), mesh.size());
+    let mut c = [0; 3];
+    for a in 0..3 {
+        let n = size[a] as isize;
+        let k = coords[a] as isize + offset[a];
+        c[a] = if (0..n).contains(&k) {
+            k as usize
+        } else if mesh.periodic(a) {
+            k.rem_euclid(n) as usize
+        } else {
+            return None;
+        };
+    }
+    Some(mesh.idx(c[0], c[1], c[2]))
+}
+
+/// Offsets (in cells) of the first `count` shells of neighbours on `mesh`,
+/// or of all of them if it has fewer: the box of offsets searched doubles
+/// until it holds `count` distances, every offset within the radius of the
+/// box being in it
+fn shells(mesh: &Mesh, count: usize) -> Vec<Vec<[isize; 3]>> {
+    let (size, d) = (mesh.size(), mesh.cell());
+    // farther offsets reach no other cell
+    let reach = size.map(|n| n as isize - 1);
+    let Some(mut radius) = (0..3)
+        .filter(|&a| reach[a] > 0)
+        .map(|a| d[a])
+        .min_by(f64::total_cmp)
+    else {
+        return Vec::new();
+    };
+    loop {
+        let bound: [isize; 3] =
+            std::array::from_fn(|a| ((radius / d[a]).floor() as isize).min(reach[a]));
+        let mut offsets = Vec::new();
+        for x in -bound[0]..=bound[0] {
+            for y in -bound[1]..=bound[1] {
+                for z in -bound[2]..=bound[2] {
+                    let o = [x, y, z];
+                    let r2: f64 = (0..3).map(|a| (o[a] as f64 * d[a]).powi(2)).sum();
+                    if r2 > 0.0 && r2 <= radius * radius * (1.0 + 1e-9) {
+                        offsets.push((r2, o));
+                    }
+                }
+            }
+        }
+        offsets.sort_by(|a, b| a.0.total_cmp(&b.0));
+        let mut shells: Vec<(f64, Vec<[isize; 3]>)> = Vec::new();
+        for (r2, o) in offsets {
+            match shells.last_mut() {
+                Some((last, shell)) if r2 <= *last * (1.0 + 1e-9) => shell.push(o),
+                _ => shells.push((r2, vec![o])),
+            }
+        }
+        if shells.len() >= count || bound == reach {
+            return shells.into_iter().take(count).map(|(_, s)| s).collect();
+        }
+        radius *= 2.0;
+    }
+}
+
+impl FieldTerm for Heisenberg {
+    fn name(&self) -> &'static str {
+        "heisenberg"
+    }
+
+    fn add_field(&self, m: &VectorField, _t: f64, mesh: &Mesh, p: &Params, h: &mut VectorField) {
+        h.par_add(|i| {
+            let ms = p.ms_at(i);
+            if ms == 0.0 {
+                return Vector3::zeros();
+            }
+            self.coupling(m, i) / (ms * mesh.cell_volume())
+        });
+    }
+
+    fn energy(&self, m: &VectorField, _t: f64, _mesh: &Mesh, _p: &Params) -> f64 {
+        // every pair is linked both ways
+        let e: f64 = (0..m.len())
+            .into_par_iter()
+            .with_min_len(chunk())
+            .map(|i| -m.get(i).dot(&self.coupling(m, i)))
+            .sum();
+        0.5 * e
+    }
+
+    fn delta_energy(
+        &self,
+        m: &VectorField,
+        i: usize,
+        new: Vector3<f64>,
+        _t: f64,
+        _mesh: &Mesh,
+        _p: &Params,
+    ) -> Option<f64> {
+        Some(-(new - m.get(i)).dot(&self.coupling(m, i)))
+    }
+}
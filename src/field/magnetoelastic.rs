@@ -0,0 +1,145 @@
+use nalgebra::{Vector3, Vector6};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::{FieldTerm, Profile};
+use crate::{Mesh, Params, Result, VectorField, Waveform, parallel::chunk};
+
+/// One contribution to the strain, `waveform(t) × profile(r)`, with the
+/// tensor components in the order εxx, εyy, εzz, εyz, εxz, εxy (shear
+/// components are those of the tensor, half the engineering strains):
+///
+/// ```toml
+/// [[magnetoelastic.strain]]
+/// kind = "sine"
+/// amplitude = [1e-4, 0.0, 0.0, 0.0, 0.0, 0.0]
+/// frequency = 2e9
+/// profile = { kind = "expr", expr = "sin(2 * pi * x / 1.5e-6)" }
+/// ```
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct StrainSource {
+    #[serde(flatten)]
+    pub waveform: Waveform<Vector6<f64>>,
+    #[serde(default, skip_serializing_if = "Profile::is_uniform")]
+    pub profile: Profile,
+}
+
+struct Source {
+    waveform: Waveform<Vector6<f64>>,
+    /// per-cell weights, `None` when uniform
+    weights: Option<Vec<f64>>,
+}
+
+/// Magnetoelastic anisotropy of a cubic crystal under a prescribed strain,
+/// with energy density
+/// `B1 (εxx mx² + εyy my² + εzz mz²) + 2 B2 (εxy mx my + εyz my mz + εxz mx mz)`
+pub struct Magnetoelastic {
+    /// first and second magnetoelastic constants B1, B2 (J m⁻³)
+    pub b: [f64; 2],
+    sources: Vec<Source>,
+}
+
+impl Magnetoelastic {
+    pub fn new(b1: f64, b2: f64, strain: &[StrainSource], mesh: &Mesh) -> Result<Self> {
+        Ok(Self {
+            b: [b1, b2],
+            sources: strain
+                .iter()
+                .map(|s| {
+                    Ok(Source {
+                        waveform: s.waveform.clone(),
+                        weights: s.profile.weights(mesh)?,
+                    })
+                })
+                .collect::<Result<_>>()?,
+        })
+    }
+
+    /// The strain at time `t`
+    fn strain(&self, t: f64) -> Strain<'_> {
+        let mut uniform = Vector6::zeros();
+        let mut local = Vec::new();
+        for s in &self.sources {
+            match &s.weights {
+                None => uniform += s.waveform.eval(t),
+                Some(weights) => local.push((s.waveform.eval(t), weights.as_slice())),
+            }
+        }
+        Strain { uniform, local }
+    }
+
+    /// ∂e/∂m in a cell of strain `e` (J m⁻³)
+    #[inline(always)]
+    fn gradient(&self, e: &Vector6<f64>, m: &Vector3<f64>) -> Vector3<f64> {
+        let [b1, b2] = self.b;
+        2.0 * Vector3::new(
+            b1 * e[0] * m.x + b2 * (e[5] * m.y + e[4] * m.z),
+            b1 * e[1] * m.y + b2 * (e[5] * m.x + e[3] * m.z),
+            b1 * e[2] * m.z + b2 * (e[4] * m.x + e[3] * m.y),
+        )
+    }
+}
+
+/// The strain at one time: its uniform part, and the amplitudes of the
+/// sources with a profile alongside their weights
+struct Strain<'a> {
+    uniform: Vector6<f64>,
+    local: Vec<(Vector6<f64>, &'a [f64])>,
+}
+
+impl Strain<'_> {
+    /// Strain of cell `i`
+    #[inline(always)]
+    fn at(&self, i: usize) -> Vector6<f64> {
+        self.local
+            .iter()
+            .fold(self.uniform, |e, (amplitude, weights)| {
+                e + weights[i] * amplitude
+            })
+    }
+}
+
+impl FieldTerm for Magnetoelastic {
+    fn name(&self) -> &'static str {
+        "magnetoelastic"
+    }
+
+    fn add_field(&self, m: &VectorField, t: f64, _mesh: &Mesh, p: &Params, h: &mut VectorField) {
+        let strain = self.strain(t);
+        h.par_add(|i| {
+            let ms = p.ms_at(i);
+            if ms == 0.0 {
+                return Vector3::zeros();
+            }
+            -self.gradient(&strain.at(i), &m.get(i)) / ms
+        });
+    }
+
+    fn energy(&self, m: &VectorField, t: f64, mesh: &Mesh, _p: &Params) -> f64 {
+        let strain = self.strain(t);
+        // the energy density is quadratic in m: e = ½ m·∂e/∂m
+        let e: f64 = (0..m.len())
+            .into_par_iter()
+            .with_min_len(chunk())
+            .map(|i| {
+                let m = m.get(i);
+                0.5 * m.dot(&self.gradient(&strain.at(i), &m))
+            })
+            .sum();
+        e * mesh.cell_volume()
+    }
+
+    fn delta_energy(
+        &self,
+        m: &VectorField,
+        i: usize,
+        new: Vector3<f64>,
+        t: f64,
+        mesh: &Mesh,
+        _p: &Params,
+    ) -> Option<f64> {
+        let e = self.strain(t).at(i);
+        let density = |m: Vector3<f64>| 0.5 * m.dot(&self.gradient(&e, &m));
+        Some((density(new) - density(m.get(i))) * mesh.cell_volume())
+    }
+}
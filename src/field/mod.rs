@@ -0,0 +1,464 @@
+//! Effective-field contributions, all expressed in Tesla.
+
+mod anisotropy;
+mod custom;
+mod demag;
+mod dipolar;
+mod dmi;
+mod exchange;
+mod heisenberg;
+mod magnetoelastic;
+mod oersted;
+mod rkky;
+mod vcma;
+mod zeeman;
+
+pub use anisotropy::{CubicAnisotropy, UniaxialAnisotropy};
+pub use custom::{Custom, EnergyFn, FieldFn};
+pub use demag::{Demag, tensor as demag_tensor};
+pub use dipolar::Dipolar;
+pub use dmi::{Dmi, DmiKind};
+pub(crate) use exchange::shift_row;
+pub use exchange::{Exchange, add_exchange_field, exchange_coupling};
+pub use heisenberg::{Heisenberg, HeisenbergConfig};
+pub use magnetoelastic::{Magnetoelastic, StrainSource};
+pub use oersted::Oersted;
+pub use rkky::{Rkky, RkkyConfig};
+pub use vcma::Vcma;
+pub use zeeman::{Drive, Profile, Zeeman, ZeemanSource};
+
+use nalgebra::Vector3;
+use rayon::prelude::*;
+use std::any::Any;
+
+use crate::{
+    Config, Mesh, Params, Result, VectorField,
+    grains::Grains,
+    maps::{CubicMaps, Map, Spatial, UniaxialMaps, evaluate},
+    parallel::chunk,
+    profile::{self, Section},
+    regions::{RegionConfig, Regions},
+};
+
+/// A contribution to the effective field with an associated energy
+pub trait FieldTerm: Any + Send + Sync {
+    fn name(&self) -> &'static str;
+
+    /// Add this term's field (T) at time `t` to `h`
+    fn add_field(&self, m: &VectorField, t: f64, mesh: &Mesh, p: &Params, h: &mut VectorField);
+
+    /// Energy of the whole sample at time `t` (J)
+    fn energy(&self, m: &VectorField, t: f64, mesh: &Mesh, p: &Params) -> f64;
+
+    /// Add this term's energy density at time `t` (J/m³) to `e`, one value
+    /// per cell; by default that of a field linear in `m`, -½ Mₛ m·B, which
+    /// terms of another form override
+    fn add_energy_density(&self, m: &VectorField, t: f64, mesh: &Mesh, p: &Params, e: &mut [f64]) {
+        let mut h = VectorField::zeros(m.len());
+        self.add_field(m, t, mesh, p, &mut h);
+        add_moment_density(m, &h, p, -0.5, e);
+    }
+
+    /// Energy change (J) when spin `i` alone turns from `m[i]` to the unit
+    /// vector `new`, or `None` for terms without a cheap local update
+    fn delta_energy(
+        &self,
+        _m: &VectorField,
+        _i: usize,
+        _new: Vector3<f64>,
+        _t: f64,
+        _mesh: &Mesh,
+        _p: &Params,
+    ) -> Option<f64> {
+        None
+    }
+}
+
+/// A registered term and whether it contributes
+struct Entry {
+    term: Box<dyn FieldTerm>,
+    enabled: bool,
+}
+
+/// The terms of the effective field, in the order their fields are summed
+/// and their energies listed: the applied field and exchange, then those of
+/// the config in the order of [`FieldTerms::from_config`], then any added
+/// with [`FieldTerms::insert`]. Every term can be switched off, by name,
+/// which leaves it out of the field, the energies and the table alike.
+pub struct FieldTerms {
+    entries: Vec<Entry>,
+}
+
+impl Default for FieldTerms {
+    /// The applied field `Params::h_ext` and exchange
+    fn default() -> Self {
+        let mut terms = Self {
+            entries: Vec::new(),
+        };
+        terms.insert(Zeeman::default());
+        terms.insert(Exchange);
+        terms
+    }
+}
+
+impl dyn FieldTerm {
+    /// Whether the term is a `T`
+    pub fn is<T: FieldTerm>(&self) -> bool {
+        (self as &dyn Any).is::<T>()
+    }
+
+    /// The term as a `T`, if it is one
+    pub fn downcast_ref<T: FieldTerm>(&self) -> Option<&T> {
+        (self as &dyn Any).downcast_ref()
+    }
+}
+
+impl FieldTerms {
+    /// The terms of `config`, with the constants that `config.regions`
+    /// override looked up through `regions`, those of `config.maps`
+    /// evaluated on the mesh and the anisotropy axes turned in every grain
+    /// of `grains`, less those listed in `config.disable`
+    pub fn from_config(
+        config: &Config,
+        regions: Option<&Regions>,
+        grains: Option<&Grains>,
+    ) -> Result<Self> {
+        let (local, maps) = (&config.regions, &config.maps);
+        let map = |key, map: &Option<Map>| evaluate(key, map.as_ref(), &config.mesh);
+        let scalar = |base, map: &Option<Vec<f64>>, value: fn(&RegionConfig) -> Option<f64>| {
+            Spatial::new(regions, local, base, map.as_deref(), value)
+        };
+
+        let uniaxial = if config.uniaxial.is_some()
+            || local.iter().any(|r| !r.uniaxial.is_empty())
+            || maps.uniaxial != UniaxialMaps::default()
+        {
+            let c = config.uniaxial.clone().unwrap_or_default();
+            let k1 = map("uniaxial.k1", &maps.uniaxial.k1)?;
+            let k2 = map("uniaxial.k2", &maps.uniaxial.k2)?;
+            let axis = grains.and_then(|g| g.oriented(c.axis, |r| r * c.axis));
+            Some(UniaxialAnisotropy::varying(
+                scalar(c.k1, &k1, |r| r.uniaxial.k1),
+                scalar(c.k2, &k2, |r| r.uniaxial.k2),
+                Spatial::new(regions, local, c.axis, axis.as_deref(), |r| r.uniaxial.axis),
+            ))
+        } else {
+            None
+        };
+        let cubic = if config.cubic.is_some()
+            || local.iter().any(|r| !r.cubic.is_empty())
+            || maps.cubic != CubicMaps::default()
+        {
+            let c = config.cubic.clone().unwrap_or_default();
+            let k1 = map("cubic.k1", &maps.cubic.k1)?;
+            let k2 = map("cubic.k2", &maps.cubic.k2)?;
+            let k3 = map("cubic.k3", &maps.cubic.k3)?;
+            let base = CubicAnisotropy::axes(c.c1, c.c2);
+            let axes = grains.and_then(|g| g.oriented(base[0], |r| base.map(|a| r * a)));
+            Some(CubicAnisotropy::varying(
+                [
+                    scalar(c.k1, &k1, |r| r.cubic.k1),
+                    scalar(c.k2, &k2, |r| r.cubic.k2),
+                    scalar(c.k3, &k3, |r| r.cubic.k3),
+                ],
+                Spatial::new(regions, local, base, axes.as_deref(), |r| {
+                    let o = &r.cubic;
+                    (o.c1.is_some() || o.c2.is_some())
+                        .then(|| CubicAnisotropy::axes(o.c1.unwrap_or(c.c1), o.c2.unwrap_or(c.c2)))
+                }),
+            ))
+        } else {
+            None
+        };
+        let dmi = if config.dmi.is_some()
+            || local.iter().any(|r| !r.dmi.is_empty())
+            || maps.dmi.d.is_some()
+        {
+            let c = config.dmi.clone().unwrap_or_default();
+            let d = map("dmi.d", &maps.dmi.d)?;
+            Some(Dmi::varying(scalar(c.d, &d, |r| r.dmi.d), c.kind))
+        } else {
+            None
+        };
+        let mut terms = Self::default();
+        terms.insert(Zeeman::new(&config.zeeman, &config.mesh)?);
+        if config.demag.is_some() {
+            terms.insert(Demag::new(&config.mesh));
+        }
+        if let Some(d) = &config.dipolar {
+            terms.insert(Dipolar::new(&config.mesh, d.cutoff));
+        }
+        if let Some(u) = uniaxial {
+            terms.insert(u);
+        }
+        if let Some(c) = cubic {
+            terms.insert(c);
+        }
+        if let Some(d) = dmi {
+            terms.insert(d);
+        }
+        if !config.rkky.is_empty() {
+            terms.insert(Rkky::new(&config.rkky, &config.mesh, regions));
+        }
+        if let Some(c) = &config.heisenberg {
+            terms.insert(Heisenberg::new(c, &config.mesh)?);
+        }
+        if let Some(c) = &config.magnetoelastic {
+            terms.insert(Magnetoelastic::new(c.b1, c.b2, &c.strain, &config.mesh)?);
+        }
+        if let Some(c) = &config.oersted {
+            let inside = match &config.geometry {
+                Some(shape) => Some(shape.mask(&config.mesh)?),
+                None => None,
+            };
+            terms.insert(Oersted::new(c, &config.mesh, inside.as_deref())?);
+        }
+        if let Some(c) = &config.vcma {
+            terms.insert(Vcma::new(c, &config.mesh)?);
+        }
+        for name in &config.disable {
+            terms
+                .set_enabled(name, false)
+                .map_err(|e| format!("`disable`: {e}"))?;
+        }
+        Ok(terms)
+    }
+
+    /// Add `term` after the others, enabled, or in the place of the one of
+    /// the same type and name, which stays switched on or off
+    pub fn insert<T: FieldTerm>(&mut self, term: T) {
+        let term = Box::new(term);
+        let same = |e: &&mut Entry| e.term.is::<T>() && e.term.name() == term.name();
+        match self.entries.iter_mut().find(same) {
+            Some(e) => e.term = term,
+            None => self.entries.push(Entry {
+                term,
+                enabled: true,
+            }),
+        }
+        self.couple_dmi();
+    }
+
+    /// Switch the term called `name` on or off
+    pub fn set_enabled(&mut self, name: &str, enabled: bool) -> Result<()> {
+        match self.entries.iter_mut().find(|e| e.term.name() == name) {
+            Some(e) => {
+                e.enabled = enabled;
+                self.couple_dmi();
+                Ok(())
+            }
+            None => {
+                let names: Vec<_> = self.entries.iter().map(|e| e.term.name()).collect();
+                Err(format!("no term `{name}`, only {}", names.join(", ")).into())
+            }
+        }
+    }
+
+    /// Tell the DMI whether the exchange is on, which its free surfaces
+    /// depend on
+    fn couple_dmi(&mut self) {
+        let exchange = self
+            .entries
+            .iter()
+            .any(|e| e.enabled && e.term.is::<Exchange>());
+        if let Some(dmi) = self.get_mut::<Dmi>() {
+            dmi.exchange = exchange;
+        }
+    }
+
+    /// Name of every registered term, with whether it is enabled
+    pub fn names(&self) -> impl Iterator<Item = (&'static str, bool)> + '_ {
+        self.entries.iter().map(|e| (e.term.name(), e.enabled))
+    }
+
+    /// The enabled terms, in order
+    pub fn iter(&self) -> impl Iterator<Item = &dyn FieldTerm> {
+        self.entries
+            .iter()
+            .filter(|e| e.enabled)
+            .map(|e| e.term.as_ref())
+    }
+
+    /// The term of type `T`, if registered and enabled
+    pub fn get<T: FieldTerm>(&self) -> Option<&T> {
+        self.iter().find_map(|term| term.downcast_ref())
+    }
+
+    /// The term of type `T`, if registered, enabled or not, to reconfigure
+    /// it
+    pub fn get_mut<T: FieldTerm>(&mut self) -> Option<&mut T> {
+        self.entries
+            .iter_mut()
+            .find_map(|e| (e.term.as_mut() as &mut dyn Any).downcast_mut())
+    }
+
+    /// Total effective field B_eff for every cell at time `t`
+    pub fn effective_field(&self, m: &VectorField, t: f64, mesh: &Mesh, p: &Params) -> VectorField {
+        let mut h = VectorField::zeros(m.len());
+        self.effective_field_into(m, t, mesh, p, &mut h);
+        h
+    }
+
+    /// [`effective_field`](Self::effective_field) written over `h`, which
+    /// must hold one vector per cell
+    pub fn effective_field_into(
+        &self,
+        m: &VectorField,
+        t: f64,
+        mesh: &Mesh,
+        p: &Params,
+        h: &mut VectorField,
+    ) {
+        self.fields_into(m, t, mesh, p, h, true);
+    }
+
+    /// [`effective_field_into`](Self::effective_field_into) without the
+    /// demagnetizing field, which two sublattices share (see
+    /// [`crate::sublattice`])
+    pub(crate) fn local_field_into(
+        &self,
+        m: &VectorField,
+        t: f64,
+        mesh: &Mesh,
+        p: &Params,
+        h: &mut VectorField,
+    ) {
+        self.fields_into(m, t, mesh, p, h, false);
+    }
+
+    fn fields_into(
+        &self,
+        m: &VectorField,
+        t: f64,
+        mesh: &Mesh,
+        p: &Params,
+        h: &mut VectorField,
+        demag: bool,
+    ) {
+        h.par_set(|_| Vector3::zeros());
+        for term in self.terms(demag) {
+            let start = profile::start();
+            term.add_field(m, t, mesh, p, h);
+            profile::record(Section::Field, term.name(), start);
+        }
+        if let Some(g) = &p.geometry {
+            // the applied field, like every other, acts on the sample only
+            g.clear_outside(h);
+        }
+    }
+
+    /// Total energy at time `t` (J)
+    pub fn energy(&self, m: &VectorField, t: f64, mesh: &Mesh, p: &Params) -> f64 {
+        self.energies(m, t, mesh, p).iter().map(|(_, e)| e).sum()
+    }
+
+    /// Energy of every enabled term at time `t` (J), named as in the config
+    pub fn energies(
+        &self,
+        m: &VectorField,
+        t: f64,
+        mesh: &Mesh,
+        p: &Params,
+    ) -> Vec<(&'static str, f64)> {
+        self.energies_of(m, t, mesh, p, true)
+    }
+
+    /// [`energies`](Self::energies) but for the demagnetizing energy, see
+    /// [`local_field_into`](Self::local_field_into)
+    pub(crate) fn local_energies(
+        &self,
+        m: &VectorField,
+        t: f64,
+        mesh: &Mesh,
+        p: &Params,
+    ) -> Vec<(&'static str, f64)> {
+        self.energies_of(m, t, mesh, p, false)
+    }
+
+    fn energies_of(
+        &self,
+        m: &VectorField,
+        t: f64,
+        mesh: &Mesh,
+        p: &Params,
+        demag: bool,
+    ) -> Vec<(&'static str, f64)> {
+        self.terms(demag)
+            .map(|term| (term.name(), term.energy(m, t, mesh, p)))
+            .collect()
+    }
+
+    /// Energy density of all enabled terms at time `t` (J/m³), one value per
+    /// cell
+    pub fn energy_density(&self, m: &VectorField, t: f64, mesh: &Mesh, p: &Params) -> Vec<f64> {
+        let mut e = vec![0.0; m.len()];
+        for term in self.iter() {
+            term.add_energy_density(m, t, mesh, p, &mut e);
+        }
+        e
+    }
+
+    /// Energy change (J) when spin `i` alone turns from `m[i]` to the unit
+    /// vector `new`, or `None` if a term has no local update
+    pub fn delta_energy(
+        &self,
+        m: &VectorField,
+        i: usize,
+        new: Vector3<f64>,
+        t: f64,
+        mesh: &Mesh,
+        p: &Params,
+    ) -> Option<f64> {
+        self.iter().try_fold(0.0, |e, term| {
+            Some(e + term.delta_energy(m, i, new, t, mesh, p)?)
+        })
+    }
+
+    /// Name of the first enabled term without a local energy update (see
+    /// [`FieldTerm::delta_energy`]), if any
+    pub fn nonlocal_term(
+        &self,
+        m: &VectorField,
+        t: f64,
+        mesh: &Mesh,
+        p: &Params,
+    ) -> Option<&'static str> {
+        self.iter()
+            .find(|term| term.delta_energy(m, 0, m.get(0), t, mesh, p).is_none())
+            .map(|term| term.name())
+    }
+
+    /// The enabled terms, demag only if `demag`
+    fn terms(&self, demag: bool) -> impl Iterator<Item = &dyn FieldTerm> {
+        self.iter().filter(move |term| demag || !term.is::<Demag>())
+    }
+}
+
+/// Σᵢ Mₛᵢ V mᵢ·bᵢ (J), minus the energy of the moments in the field `b`
+pub(crate) fn moment_dot(m: &VectorField, b: &VectorField, mesh: &Mesh, p: &Params) -> f64 {
+    let sum = if p.is_uniform() {
+        p.ms() * m.dot(b)
+    } else {
+        (0..m.len())
+            .into_par_iter()
+            .with_min_len(chunk())
+            .map(|i| p.ms_at(i) * m.get(i).dot(&b.get(i)))
+            .sum()
+    };
+    sum * mesh.cell_volume()
+}
+
+/// Add `factor` Mₛᵢ mᵢ·bᵢ to every `eᵢ`, the energy density of the moments
+/// in the field `b` (J/m³) for `factor` -1
+pub(crate) fn add_moment_density(
+    m: &VectorField,
+    b: &VectorField,
+    p: &Params,
+    factor: f64,
+    e: &mut [f64],
+) {
+    e.par_iter_mut()
+        .enumerate()
+        .with_min_len(chunk())
+        .for_each(|(i, e)| *e += factor * p.ms_at(i) * m.get(i).dot(&b.get(i)));
+}
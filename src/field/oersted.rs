@@ -0,0 +1,199 @@
+use nalgebra::Vector3;
+use rayon::prelude::*;
+use rustfft::num_complex::Complex64;
+use std::f64::consts::PI;
+
+use super::{FieldTerm, demag::shifts};
+use crate::{
+    Mesh, Params, Result, VectorField, Waveform, config::OerstedConfig, fft::Fft3, params::MU0,
+};
+
+/// Within this distance (in units of the largest cell dimension) the
+/// Biot–Savart kernel is averaged over the source cell rather than taken at
+/// its centre.
+const NEAR: f64 = 8.0;
+
+/// Sample points per axis of that average, even so that none falls on the
+/// field point of the cell itself
+const SAMPLES: usize = 6;
+
+/// Oersted field of a current flowing through the sample, e.g. the field
+/// circling a nanowire that carries the current driving its domain walls.
+///
+/// The current density is fixed in space and scaled in time, so its field is
+/// summed once, by FFT convolution with the Biot–Savart kernel on the mesh
+/// padded as in [`Demag`](super::Demag), and only scaled afterwards.
+pub struct Oersted {
+    /// field (T) of the current density at a scale of 1
+    field: VectorField,
+    scale: Waveform<f64>,
+}
+
+impl Oersted {
+    /// The field of the current of `config` on `mesh`, flowing only through
+    /// the cells where `inside` holds, if given
+    pub fn new(config: &OerstedConfig, mesh: &Mesh, inside: Option<&[bool]>) -> Result<Self> {
+        let mut density: Vec<Vector3<f64>> = match (&config.file, config.density) {
+            (Some(file), _) => crate::ovf::read(file)?.interpolate(mesh),
+            (None, density) => vec![density.unwrap_or_default(); mesh.len()],
+        };
+        if let Some(weights) = config.profile.weights(mesh)? {
+            for (j, w) in density.iter_mut().zip(weights) {
+                *j *= w;
+            }
+        }
+        if let Some(inside) = inside {
+            for (j, inside) in density.iter_mut().zip(inside) {
+                if !inside {
+                    *j = Vector3::zeros();
+                }
+            }
+        }
+        if let Some(i) = density
+            .iter()
+            .position(|j| !j.iter().all(|c| c.is_finite()))
+        {
+            return Err(format!(
+                "`oersted` current density not finite in cell {:?}",
+                mesh.coords(i)
+            )
+            .into());
+        }
+        Ok(Self {
+            field: biot_savart(&density, mesh),
+            scale: config.scale.clone(),
+        })
+    }
+}
+
+/// B(rᵢ) = μ₀/4π Σⱼ Jⱼ × (rᵢ - rⱼ) / |rᵢ - rⱼ|³ V of the current density
+/// `j` on `mesh`
+fn biot_savart(j: &[Vector3<f64>], mesh: &Mesh) -> VectorField {
+    let padded: [usize; 3] = std::array::from_fn(|a| {
+        let n = mesh.size()[a];
+        if n > 1 && !mesh.periodic(a) { 2 * n } else { n }
+    });
+    let fft = Fft3::new(padded);
+    let [px, py, _] = padded;
+    let d = mesh.cell();
+    let pad = |i: usize| {
+        let [x, y, z] = mesh.coords(i);
+        x + px * (y + py * z)
+    };
+
+    // K(r) for every displacement, stored at index r mod padded
+    let values: Vec<Vector3<f64>> = (0..fft.len())
+        .into_par_iter()
+        .map(|i| {
+            let c = [i % px, (i / px) % py, i / (px * py)];
+            let shifts: [Vec<(f64, f64)>; 3] = std::array::from_fn(|a| shifts(mesh, a, c[a]));
+            let mut k = Vector3::zeros();
+            for &(x, wx) in &shifts[0] {
+                for &(y, wy) in &shifts[1] {
+                    for &(z, wz) in &shifts[2] {
+                        k += wx * wy * wz * cell_kernel(Vector3::new(x, y, z), d);
+                    }
+                }
+            }
+            k
+        })
+        .collect();
+    let mut kernel: [Vec<Complex64>; 3] =
+        std::array::from_fn(|a| values.iter().map(|k| Complex64::new(k[a], 0.0)).collect());
+    let mut current: [Vec<Complex64>; 3] =
+        std::array::from_fn(|_| vec![Complex64::default(); fft.len()]);
+    for (i, j) in j.iter().enumerate() {
+        for a in 0..3 {
+            current[a][pad(i)].re = j[a];
+        }
+    }
+    for buf in kernel.iter_mut().chain(current.iter_mut()) {
+        fft.forward(buf);
+    }
+
+    // B = J × K, convolved component by component
+    let [kx, ky, kz] = &kernel;
+    let [jx, jy, jz] = &mut current;
+    (jx.par_iter_mut(), jy.par_iter_mut(), jz.par_iter_mut())
+        .into_par_iter()
+        .enumerate()
+        .for_each(|(k, (x, y, z))| {
+            let (a, b, c) = (*x, *y, *z);
+            *x = b * kz[k] - c * ky[k];
+            *y = c * kx[k] - a * kz[k];
+            *z = a * ky[k] - b * kx[k];
+        });
+    for buf in current.iter_mut() {
+        fft.inverse(buf);
+    }
+
+    let [bx, by, bz] = &current;
+    (0..mesh.len())
+        .map(|i| {
+            let k = pad(i);
+            Vector3::new(bx[k].re, by[k].re, bz[k].re)
+        })
+        .collect()
+}
+
+/// μ₀V/4π r/|r|³, the field per unit current density of a cell of size `d`
+/// at displacement `r` from it, averaged over the cell when close by
+fn cell_kernel(r: Vector3<f64>, d: [f64; 3]) -> Vector3<f64> {
+    let point = |r: Vector3<f64>| {
+        let r2 = r.norm_squared();
+        if r2 == 0.0 {
+            Vector3::zeros()
+        } else {
+            r / (r2 * r2.sqrt())
+        }
+    };
+    let [dx, dy, dz] = d;
+    let k = if r.norm() > NEAR * dx.max(dy).max(dz) {
+        point(r)
+    } else {
+        let offset = |s: usize, d: f64| ((s as f64 + 0.5) / SAMPLES as f64 - 0.5) * d;
+        let mut sum = Vector3::zeros();
+        for sx in 0..SAMPLES {
+            for sy in 0..SAMPLES {
+                for sz in 0..SAMPLES {
+                    let s = Vector3::new(offset(sx, dx), offset(sy, dy), offset(sz, dz));
+                    sum += point(r - s);
+                }
+            }
+        }
+        sum / SAMPLES.pow(3) as f64
+    };
+    MU0 * dx * dy * dz / (4.0 * PI) * k
+}
+
+impl FieldTerm for Oersted {
+    fn name(&self) -> &'static str {
+        "oersted"
+    }
+
+    fn add_field(&self, _m: &VectorField, t: f64, _mesh: &Mesh, _p: &Params, h: &mut VectorField) {
+        let scale = self.scale.eval(t);
+        h.axpy(scale, &self.field);
+    }
+
+    fn energy(&self, m: &VectorField, t: f64, mesh: &Mesh, p: &Params) -> f64 {
+        -self.scale.eval(t) * super::moment_dot(m, &self.field, mesh, p)
+    }
+
+    fn add_energy_density(&self, m: &VectorField, t: f64, _mesh: &Mesh, p: &Params, e: &mut [f64]) {
+        super::add_moment_density(m, &self.field, p, -self.scale.eval(t), e);
+    }
+
+    fn delta_energy(
+        &self,
+        m: &VectorField,
+        i: usize,
+        new: Vector3<f64>,
+        t: f64,
+        mesh: &Mesh,
+        p: &Params,
+    ) -> Option<f64> {
+        let b = self.scale.eval(t) * self.field.get(i);
+        Some(-(new - m.get(i)).dot(&b) * p.ms_at(i) * mesh.cell_volume())
+    }
+}
@@ -0,0 +1,193 @@
+use nalgebra::Vector3;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::FieldTerm;
+use crate::{Mesh, Params, VectorField, parallel::chunk, regions::Regions};
+
+/// One interlayer exchange coupling across a spacer, with energy
+/// `-J1 m₁·m₂ - J2 (m₁·m₂)²` per unit area of facing cells:
+///
+/// ```toml
+/// [[rkky]]
+/// layers = [0, 2]   # or regions = [1, 2]
+/// j1 = -1e-3
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RkkyConfig {
+    /// the two coupled z layers (cell indices), every cell of one facing the
+    /// cell above or below it in the other
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub layers: Option<[usize; 2]>,
+    /// the two coupled regions (0 for the cells of no `[[regions]]` entry),
+    /// in every column the closest cells of the two facing each other
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub regions: Option<[usize; 2]>,
+    /// bilinear constant (J m⁻²), negative for antiparallel layers
+    pub j1: f64,
+    /// biquadratic constant (J m⁻²), negative for perpendicular layers
+    pub j2: f64,
+}
+
+/// The other cell of a coupled pair, with the constants `j1`, `j2` (J m⁻²)
+#[derive(Debug, Clone, Copy)]
+struct Link {
+    other: usize,
+    j1: f64,
+    j2: f64,
+}
+
+/// RKKY-like interlayer exchange between pairs of facing cells, as in
+/// synthetic antiferromagnets and spin valves
+pub struct Rkky {
+    /// links of cell `i` at `links[start[i]..start[i + 1]]`
+    start: Vec<usize>,
+    links: Vec<Link>,
+}
+
+impl Rkky {
+    /// The couplings of `config` on `mesh`, regions looked up in `regions`
+    pub fn new(config: &[RkkyConfig], mesh: &Mesh, regions: Option<&Regions>) -> Self {
+        let mut pairs = Vec::new();
+        for c in config {
+            let link = |other| Link {
+                other,
+                j1: c.j1,
+                j2: c.j2,
+            };
+            for (i, j) in facing(c, mesh, regions) {
+                pairs.push((i, link(j)));
+                pairs.push((j, link(i)));
+            }
+        }
+        pairs.sort_by_key(|&(i, _)| i);
+        let mut start = vec![0; mesh.len() + 1];
+        for &(i, _) in &pairs {
+            start[i + 1] += 1;
+        }
+        for i in 0..mesh.len() {
+            start[i + 1] += start[i];
+        }
+        Self {
+            start,
+            links: pairs.into_iter().map(|(_, l)| l).collect(),
+        }
+    }
+
+    fn links(&self, i: usize) -> &[Link] {
+        &self.links[self.start[i]..self.start[i + 1]]
+    }
+
+    /// Energy per unit area (J m⁻²) of cell `i` pointing along `mi`, in the
+    /// links of `i` alone
+    fn density(&self, m: &VectorField, i: usize, mi: &Vector3<f64>) -> f64 {
+        self.links(i)
+            .iter()
+            .map(|l| {
+                let d = mi.dot(&m.get(l.other));
+                -l.j1 * d - l.j2 * d * d
+            })
+            .sum()
+    }
+}
+
+/// Pairs of facing cells coupled by `config`, the lower one first
+fn facing(config: &RkkyConfig, mesh: &Mesh, regions: Option<&Regions>) -> Vec<(usize, usize)> {
+    let columns = mesh.nx * mesh.ny;
+    if let Some([a, b]) = config.layers {
+        let (a, b) = (a.min(b), a.max(b));
+        return (0..columns)
+            .map(|c| (c + a * columns, c + b * columns))
+            .collect();
+    }
+    let Some([a, b]) = config.regions else {
+        return Vec::new();
+    };
+    let region = |i: usize| regions.map_or(0, |r| r.index[i] as usize);
+    (0..columns)
+        .filter_map(|c| {
+            // closest pair of a cell of one region above one of the other
+            let mut last: Option<usize> = None;
+            let mut best: Option<(usize, usize)> = None;
+            for z in 0..mesh.nz {
+                let i = c + z * columns;
+                let r = region(i);
+                if r != a && r != b {
+                    continue;
+                }
+                if let Some(j) = last
+                    && region(j) != r
+                    && best.is_none_or(|(lo, hi)| i - j < hi - lo)
+                {
+                    best = Some((j, i));
+                }
+                last = Some(i);
+            }
+            best
+        })
+        .collect()
+}
+
+impl FieldTerm for Rkky {
+    fn name(&self) -> &'static str {
+        "rkky"
+    }
+
+    fn add_field(&self, m: &VectorField, _t: f64, mesh: &Mesh, p: &Params, h: &mut VectorField) {
+        h.par_add(|i| {
+            let ms = p.ms_at(i);
+            if ms == 0.0 {
+                return Vector3::zeros();
+            }
+            let mi = m.get(i);
+            let b: Vector3<f64> = self
+                .links(i)
+                .iter()
+                .map(|l| {
+                    let mj = m.get(l.other);
+                    (l.j1 + 2.0 * l.j2 * mi.dot(&mj)) * mj
+                })
+                .sum();
+            b / (ms * mesh.dz)
+        });
+    }
+
+    fn energy(&self, m: &VectorField, _t: f64, mesh: &Mesh, _p: &Params) -> f64 {
+        // every pair is linked both ways
+        let e: f64 = (0..m.len())
+            .into_par_iter()
+            .with_min_len(chunk())
+            .map(|i| self.density(m, i, &m.get(i)))
+            .sum();
+        0.5 * e * mesh.dx * mesh.dy
+    }
+
+    fn add_energy_density(
+        &self,
+        m: &VectorField,
+        _t: f64,
+        mesh: &Mesh,
+        _p: &Params,
+        e: &mut [f64],
+    ) {
+        // half of every pair in each of its cells, spread over their height
+        e.par_iter_mut()
+            .enumerate()
+            .with_min_len(chunk())
+            .for_each(|(i, e)| *e += 0.5 * self.density(m, i, &m.get(i)) / mesh.dz);
+    }
+
+    fn delta_energy(
+        &self,
+        m: &VectorField,
+        i: usize,
+        new: Vector3<f64>,
+        _t: f64,
+        mesh: &Mesh,
+        _p: &Params,
+    ) -> Option<f64> {
+        let de = self.density(m, i, &new) - self.density(m, i, &m.get(i));
+        Some(de * mesh.dx * mesh.dy)
+    }
+}
@@ -0,0 +1,107 @@
+use nalgebra::{Unit, Vector3};
+use rayon::prelude::*;
+
+use super::FieldTerm;
+use crate::{Mesh, Params, Result, VectorField, Waveform, config::VcmaConfig, parallel::chunk};
+
+/// Voltage-controlled magnetic anisotropy: a voltage V(t) across the oxide
+/// barrier on top of the film changes its interface anisotropy by ξ E, with
+/// E = V / t_ox the electric field in the barrier. Spread over the film
+/// thickness t_FM, that is a uniaxial anisotropy of energy density
+/// `-ξ V(t) / (t_ox t_FM) (m·u)²`, scaled per cell by the profile of the
+/// electrode.
+pub struct Vcma {
+    /// anisotropy constant per volt, ξ / (t_ox t_FM) (J m⁻³ V⁻¹)
+    pub k_per_volt: f64,
+    pub axis: Unit<Vector3<f64>>,
+    voltage: Waveform<f64>,
+    /// per-cell weights, `None` when uniform
+    weights: Option<Vec<f64>>,
+}
+
+impl Vcma {
+    pub fn new(config: &VcmaConfig, mesh: &Mesh) -> Result<Self> {
+        let thickness = config.thickness.unwrap_or(mesh.nz as f64 * mesh.dz);
+        Ok(Self {
+            k_per_volt: config.coefficient / (config.oxide * thickness),
+            axis: Unit::new_normalize(config.axis),
+            voltage: config.voltage.clone(),
+            weights: config.profile.weights(mesh)?,
+        })
+    }
+
+    /// Anisotropy constant (J m⁻³) at time `t`, before the profile
+    fn k(&self, t: f64) -> f64 {
+        self.k_per_volt * self.voltage.eval(t)
+    }
+
+    #[inline(always)]
+    fn weight(&self, i: usize) -> f64 {
+        self.weights.as_ref().map_or(1.0, |w| w[i])
+    }
+
+    /// Energy density (J m⁻³) of cell `i` pointing along `m`, for a constant
+    /// `k`
+    fn density(&self, k: f64, i: usize, m: &Vector3<f64>) -> f64 {
+        -k * self.weight(i) * m.dot(&self.axis).powi(2)
+    }
+}
+
+impl FieldTerm for Vcma {
+    fn name(&self) -> &'static str {
+        "vcma"
+    }
+
+    fn add_field(&self, m: &VectorField, t: f64, _mesh: &Mesh, p: &Params, h: &mut VectorField) {
+        let k = self.k(t);
+        if k == 0.0 {
+            return;
+        }
+        h.par_add(|i| {
+            let ms = p.ms_at(i);
+            if ms == 0.0 {
+                return Vector3::zeros();
+            }
+            let u = self.axis.into_inner();
+            2.0 * k * self.weight(i) * m.get(i).dot(&u) / ms * u
+        });
+    }
+
+    fn energy(&self, m: &VectorField, t: f64, mesh: &Mesh, _p: &Params) -> f64 {
+        let k = self.k(t);
+        let e: f64 = (0..m.len())
+            .into_par_iter()
+            .with_min_len(chunk())
+            .map(|i| self.density(k, i, &m.get(i)))
+            .sum();
+        e * mesh.cell_volume()
+    }
+
+    fn add_energy_density(
+        &self,
+        m: &VectorField,
+        t: f64,
+        _mesh: &Mesh,
+        _p: &Params,
+        e: &mut [f64],
+    ) {
+        let k = self.k(t);
+        e.par_iter_mut()
+            .enumerate()
+            .with_min_len(chunk())
+            .for_each(|(i, e)| *e += self.density(k, i, &m.get(i)));
+    }
+
+    fn delta_energy(
+        &self,
+        m: &VectorField,
+        i: usize,
+        new: Vector3<f64>,
+        t: f64,
+        mesh: &Mesh,
+        _p: &Params,
+    ) -> Option<f64> {
+        let k = self.k(t);
+        Some((self.density(k, i, &new) - self.density(k, i, &m.get(i))) * mesh.cell_volume())
+    }
+}
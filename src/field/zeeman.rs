@@ -0,0 +1,209 @@
+use nalgebra::Vector3;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::FieldTerm;
+use crate::{Mesh, Params, Result, VectorField, Waveform, expr::Expr};
+
+/// Spatial weight multiplying an applied-field waveform
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(tag = "kind", rename_all = "lowercase", deny_unknown_fields)]
+pub enum Profile {
+    #[default]
+    Uniform,
+    /// 1 for cells whose centre lies in the box `[min, max]` (m), 0 elsewhere,
+    /// e.g. the footprint of a stripline antenna
+    Box {
+        min: Vector3<f64>,
+        max: Vector3<f64>,
+    },
+    /// `1 + gradient · (r - origin)`, with `gradient` in m⁻¹
+    Gradient {
+        gradient: Vector3<f64>,
+        #[serde(default = "Vector3::zeros")]
+        origin: Vector3<f64>,
+    },
+    /// Scalar expression of the cell centre `x`, `y`, `z` (m)
+    Expr { expr: String },
+}
+
+impl Profile {
+    pub fn is_uniform(&self) -> bool {
+        matches!(self, Self::Uniform)
+    }
+
+    /// Per-cell weights, or `None` for a uniform profile
+    pub fn weights(&self, mesh: &Mesh) -> Result<Option<Vec<f64>>> {
+        let weight: Box<dyn Fn(Vector3<f64>) -> f64 + Sync> = match self {
+            Self::Uniform => return Ok(None),
+            Self::Box { min, max } => {
+                let (min, max) = (*min, *max);
+                Box::new(move |r| {
+                    let inside = (0..3).all(|a| r[a] >= min[a] && r[a] <= max[a]);
+                    if inside { 1.0 } else { 0.0 }
+                })
+            }
+            Self::Gradient { gradient, origin } => {
+                let (g, o) = (*gradient, *origin);
+                Box::new(move |r| 1.0 + g.dot(&(r - o)))
+            }
+            Self::Expr { expr } => {
+                let expr = Expr::parse(expr, &["x", "y", "z"])?;
+                Box::new(move |r| expr.eval(&[r.x, r.y, r.z]))
+            }
+        };
+        Ok(Some(
+            (0..mesh.len())
+                .into_par_iter()
+                .map(|i| weight(mesh.position(i)))
+                .collect(),
+        ))
+    }
+}
+
+/// One applied-field source, `waveform(t) × profile(r)`:
+///
+/// ```toml
+/// [[zeeman]]
+/// kind = "sine"
+/// amplitude = [0.01, 0.0, 0.0]
+/// frequency = 10e9
+/// profile = { kind = "box", min = [0.0, 0.0, 0.0], max = [50e-9, 1.0, 1.0] }
+/// ```
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct ZeemanSource {
+    #[serde(flatten)]
+    pub waveform: Waveform<Vector3<f64>>,
+    #[serde(default, skip_serializing_if = "Profile::is_uniform")]
+    pub profile: Profile,
+}
+
+/// A uniform applied field computed by the caller from the time, e.g. by a
+/// [`crate::script`]
+pub type Drive = Box<dyn Fn(f64) -> Vector3<f64> + Send + Sync>;
+
+/// Applied field: the static `Params::h_ext` plus every source
+#[derive(Default)]
+pub struct Zeeman {
+    sources: Vec<Source>,
+    drive: Option<Drive>,
+}
+
+struct Source {
+    waveform: Waveform<Vector3<f64>>,
+    /// per-cell weights, `None` when uniform
+    weights: Option<Vec<f64>>,
+}
+
+impl Zeeman {
+    pub fn new(sources: &[ZeemanSource], mesh: &Mesh) -> Result<Self> {
+        Ok(Self {
+            sources: sources
+                .iter()
+                .map(|s| {
+                    Ok(Source {
+                        waveform: s.waveform.clone(),
+                        weights: s.profile.weights(mesh)?,
+                    })
+                })
+                .collect::<Result<_>>()?,
+            drive: None,
+        })
+    }
+
+    /// Add `drive(t)` to the field, replacing any earlier drive
+    pub fn set_drive(&mut self, drive: Option<Drive>) {
+        self.drive = drive;
+    }
+
+    /// Whether the field is `Params::h_ext` alone
+    pub fn is_static(&self) -> bool {
+        self.sources.is_empty() && self.drive.is_none()
+    }
+
+    /// B_ext at time `t` if it is the same in every cell
+    pub fn uniform_at(&self, t: f64, p: &Params) -> Option<Vector3<f64>> {
+        self.sources
+            .iter()
+            .try_fold(self.bias(t, p), |h, s| match s.weights {
+                None => Some(h + s.waveform.eval(t)),
+                Some(_) => None,
+            })
+    }
+
+    /// `Params::h_ext` plus the drive at time `t`
+    fn bias(&self, t: f64, p: &Params) -> Vector3<f64> {
+        match &self.drive {
+            Some(drive) => p.h_ext + drive(t),
+            None => p.h_ext,
+        }
+    }
+
+    /// B_ext at time `t` as a function of the cell, with every waveform
+    /// evaluated once
+    fn at_time(&self, t: f64, p: &Params) -> impl Fn(usize) -> Vector3<f64> + Sync {
+        let mut uniform = self.bias(t, p);
+        let mut local = Vec::new();
+        for s in &self.sources {
+            match &s.weights {
+                None => uniform += s.waveform.eval(t),
+                Some(weights) => local.push((s.waveform.eval(t), weights)),
+            }
+        }
+        move |i| {
+            local
+                .iter()
+                .fold(uniform, |h, (b, weights)| h + weights[i] * b)
+        }
+    }
+
+    /// Write B_ext at time `t` into every cell of `h`
+    pub fn field(&self, t: f64, p: &Params, h: &mut VectorField) {
+        h.par_set(self.at_time(t, p));
+    }
+
+    /// B_ext at time `t` in cell `i` alone
+    pub fn field_at(&self, i: usize, t: f64, p: &Params) -> Vector3<f64> {
+        self.sources
+            .iter()
+            .fold(self.bias(t, p), |h, s| match &s.weights {
+                None => h + s.waveform.eval(t),
+                Some(weights) => h + weights[i] * s.waveform.eval(t),
+            })
+    }
+}
+
+impl FieldTerm for Zeeman {
+    fn name(&self) -> &'static str {
+        "zeeman"
+    }
+
+    fn add_field(&self, _m: &VectorField, t: f64, _mesh: &Mesh, p: &Params, h: &mut VectorField) {
+        h.par_add(self.at_time(t, p));
+    }
+
+    fn energy(&self, m: &VectorField, t: f64, mesh: &Mesh, p: &Params) -> f64 {
+        let mut b = VectorField::zeros(m.len());
+        self.field(t, p, &mut b);
+        -super::moment_dot(m, &b, mesh, p)
+    }
+
+    fn add_energy_density(&self, m: &VectorField, t: f64, _mesh: &Mesh, p: &Params, e: &mut [f64]) {
+        let mut b = VectorField::zeros(m.len());
+        self.field(t, p, &mut b);
+        super::add_moment_density(m, &b, p, -1.0, e);
+    }
+
+    fn delta_energy(
+        &self,
+        m: &VectorField,
+        i: usize,
+        new: Vector3<f64>,
+        t: f64,
+        mesh: &Mesh,
+        p: &Params,
+    ) -> Option<f64> {
+        let dm = new - m.get(i);
+        Some(-dm.dot(&self.field_at(i, t, p)) * p.ms_at(i) * mesh.cell_volume())
+    }
+}
@@ -0,0 +1,230 @@
+//! dm/dt of uniform samples in a single sweep over the cells.
+//!
+//! Summing the effective field term by term, then turning it into dm/dt,
+//! reads and writes every array once per term and once more for the cross
+//! products; with exchange swept component by component that is a dozen
+//! passes over memory per stage, four stages per RK4 step. When every cell
+//! has the same material values, the sweep here computes the exchange field
+//! from the neighbours of each cell, adds the applied field and uniaxial
+//! anisotropy when they are uniform, and writes m × B and m × (m × B) right
+//! away, reading m once and writing dm/dt once.
+//!
+//! The cells are taken x row by x row, so that the neighbours along x are
+//! adjacent in memory and those along y and z lie in rows read just before;
+//! rows longer than [`chunk`] cells, e.g. a long chain, are split across
+//! threads, shorter ones grouped. Any other term is summed into dm/dt
+//! first, and the sweep adds what it holds to the field of each cell.
+
+use rayon::prelude::*;
+
+use crate::{
+    Mesh, VectorField,
+    field::{Exchange, FieldTerm, UniaxialAnisotropy, Zeeman, shift_row},
+    llg::Llg,
+    parallel::chunk,
+    profile::{self, Section},
+    vector_field::Real,
+};
+
+/// The constants of one evaluation of the sweep
+pub(crate) struct Fused {
+    /// 2A / (Mₛ d²) along x, y and z (T), 0 without exchange
+    exchange: [Real; 3],
+    /// uniform applied field (T), when `zeeman`
+    bias: [Real; 3],
+    zeeman: bool,
+    /// easy axis, 2K₁/Mₛ and 4K₂/Mₛ (T), when `uniaxial`
+    axis: [Real; 3],
+    k1: Real,
+    k2: Real,
+    uniaxial: bool,
+    /// coefficients of m × B and m × (m × B)
+    coefs: [Real; 2],
+}
+
+impl Fused {
+    /// The sweep of `llg` at time `t`, or `None` where it does not apply:
+    /// two sublattices, the LLB equation or material values varying from
+    /// cell to cell
+    pub(crate) fn new(llg: &Llg, t: f64) -> Option<Self> {
+        let (mesh, p, terms) = (llg.mesh, llg.params, llg.terms);
+        if llg.sublattice.is_some() || llg.llb.is_some() || !p.is_uniform() || p.mu0_ms == 0.0 {
+            return None;
+        }
+        let ms = p.ms();
+        let exchange = match terms.get::<Exchange>() {
+            Some(_) => mesh.cell().map(|d| (2.0 * p.a_ex / (ms * d * d)) as Real),
+            None => [0.0; 3],
+        };
+        let bias = terms.get::<Zeeman>().and_then(|z| z.uniform_at(t, p));
+        let uniaxial = terms.get::<UniaxialAnisotropy>().and_then(|u| {
+            Some((
+                u.axis.as_uniform()?.into_inner(),
+                u.k1.as_uniform()?,
+                u.k2.as_uniform()?,
+            ))
+        });
+        let (axis, k1, k2) = uniaxial.unwrap_or_default();
+        let coefs = if llg.precession {
+            let pref = -p.gamma / (1.0 + p.alpha * p.alpha);
+            [pref, pref * p.alpha]
+        } else {
+            [0.0, -p.gamma]
+        };
+        Some(Self {
+            exchange,
+            bias: bias.unwrap_or_default().map(|b| b as Real).into(),
+            zeeman: bias.is_some(),
+            axis: axis.map(|a| a as Real).into(),
+            k1: (2.0 * k1 / ms) as Real,
+            k2: (4.0 * k2 / ms) as Real,
+            uniaxial: uniaxial.is_some(),
+            coefs: coefs.map(|c| c as Real),
+        })
+    }
+
+    /// Whether the sweep itself adds the field of `term`
+    fn handles(&self, term: &dyn FieldTerm) -> bool {
+        term.is::<Exchange>()
+            || (self.zeeman && term.is::<Zeeman>())
+            || (self.uniaxial && term.is::<UniaxialAnisotropy>())
+    }
+
+    /// dm/dt of `m` at time `t` without the spin torques, written over
+    /// `dmdt`, which holds one vector per cell
+    pub(crate) fn rhs_into(&self, llg: &Llg, m: &VectorField, t: f64, dmdt: &mut VectorField) {
+        let (mesh, p) = (llg.mesh, llg.params);
+        let rest = llg.noise.is_some() || llg.terms.iter().any(|term| !self.handles(term));
+        if rest {
+            dmdt.par_set(|_| nalgebra::Vector3::zeros());
+            for term in llg.terms.iter().filter(|&term| !self.handles(term)) {
+                let start = profile::start();
+                term.add_field(m, t, mesh, p, dmdt);
+                profile::record(Section::Field, term.name(), start);
+            }
+            if let Some(noise) = llg.noise {
+                dmdt.axpy(1.0, noise);
+            }
+        }
+        let start = profile::start();
+        let (nx, n) = (mesh.nx, chunk());
+        // whole rows per task, or parts of one row
+        let block = (n / nx).max(1);
+        let part = nx.min(n);
+        let [ox, oy, oz] = [&mut dmdt.x, &mut dmdt.y, &mut dmdt.z];
+        ox.par_chunks_mut(block * nx)
+            .zip(oy.par_chunks_mut(block * nx))
+            .zip(oz.par_chunks_mut(block * nx))
+            .enumerate()
+            .for_each(|(b, ((ox, oy), oz))| {
+                let rows = ox
+                    .chunks_mut(nx)
+                    .zip(oy.chunks_mut(nx))
+                    .zip(oz.chunks_mut(nx));
+                for (k, ((ox, oy), oz)) in rows.enumerate() {
+                    let r = b * block + k;
+                    if part < nx {
+                        ox.par_chunks_mut(part)
+                            .zip(oy.par_chunks_mut(part))
+                            .zip(oz.par_chunks_mut(part))
+                            .enumerate()
+                            .for_each(|(s, ((ox, oy), oz))| {
+                                self.sweep(mesh, m, r, s * part, [ox, oy, oz], rest)
+                            });
+                    } else {
+                        self.sweep(mesh, m, r, 0, [ox, oy, oz], rest);
+                    }
+                }
+            });
+        profile::record(Section::Field, "fused local terms", start);
+    }
+
+    /// dm/dt of cells `x0..` of row `r` written over `out`, which holds the
+    /// field of the other terms if `rest`: the exchange field first, one
+    /// component at a time so that the stencil vectorizes, then the local
+    /// terms and the cross products while the stretch is still in cache
+    #[inline(always)]
+    fn sweep(
+        &self,
+        mesh: &Mesh,
+        m: &VectorField,
+        r: usize,
+        x0: usize,
+        mut out: [&mut [Real]; 3],
+        rest: bool,
+    ) {
+        let (nx, n) = (mesh.nx, out[0].len());
+        let here = r * nx;
+        let [ym, yp, zm, zp] = [(1, -1), (1, 1), (2, -1), (2, 1)]
+            .map(|(axis, dir)| shift_row(mesh, r, axis, dir) * nx);
+        let [cx, cy, cz] = self.exchange;
+        let last = nx - 1;
+        let periodic = mesh.periodic(0);
+        let ends = [0, last];
+        let ends = &ends[..if last == 0 { 1 } else { 2 }];
+        for (src, out) in [&m.x, &m.y, &m.z].into_iter().zip(&mut out) {
+            if !rest {
+                out.fill(0.0);
+            }
+            let row = |r: usize| &src[r..r + nx];
+            let (c, ym, yp, zm, zp) = (row(here), row(ym), row(yp), row(zm), row(zp));
+            let transverse =
+                |x: usize| cy * (ym[x] + yp[x] - 2.0 * c[x]) + cz * (zm[x] + zp[x] - 2.0 * c[x]);
+            // the cells of the stretch with both x neighbours in the row
+            let (lo, hi) = (x0.max(1), (x0 + n).min(last));
+            if lo < hi {
+                let inner = out[lo - x0..hi - x0]
+                    .iter_mut()
+                    .zip(&c[lo - 1..hi - 1])
+                    .zip(&c[lo + 1..hi + 1])
+                    .zip(&c[lo..hi])
+                    .zip(&ym[lo..hi])
+                    .zip(&yp[lo..hi])
+                    .zip(&zm[lo..hi])
+                    .zip(&zp[lo..hi]);
+                for (((((((o, l), r), c), ym), yp), zm), zp) in inner {
+                    *o += cx * (l + r - 2.0 * c)
+                        + cy * (ym + yp - 2.0 * c)
+                        + cz * (zm + zp - 2.0 * c);
+                }
+            }
+            // the two ends of the row, or its only cell
+            for x in ends.iter().filter(|x| (x0..x0 + n).contains(x)).copied() {
+                let xm = if x > 0 {
+                    x - 1
+                } else if periodic {
+                    last
+                } else {
+                    0
+                };
+                let xp = if x < last {
+                    x + 1
+                } else if periodic {
+                    0
+                } else {
+                    last
+                };
+                out[x - x0] += cx * (c[xm] + c[xp] - 2.0 * c[x]) + transverse(x);
+            }
+        }
+        let [ux, uy, uz] = self.axis;
+        let [a1, a2] = self.coefs;
+        let [bx0, by0, bz0] = self.bias;
+        let cells = here + x0..here + x0 + n;
+        let (mx, my, mz) = (&m.x[cells.clone()], &m.y[cells.clone()], &m.z[cells]);
+        let [ox, oy, oz] = out;
+        let (ox, oy, oz) = (&mut ox[..n], &mut oy[..n], &mut oz[..n]);
+        for k in 0..n {
+            let (mx, my, mz) = (mx[k], my[k], mz[k]);
+            let mu = mx * ux + my * uy + mz * uz;
+            let s = (self.k1 + self.k2 * mu * mu) * mu;
+            let bx = ox[k] + bx0 + s * ux;
+            let by = oy[k] + by0 + s * uy;
+            let bz = oz[k] + bz0 + s * uz;
+            let (px, py, pz) = (my * bz - mz * by, mz * bx - mx * bz, mx * by - my * bx);
+            ox[k] = a1 * px + a2 * (my * pz - mz * py);
+            oy[k] = a1 * py + a2 * (mz * px - mx * pz);
+            oz[k] = a1 * pz + a2 * (mx * py - my * px);
+        }
+    }
+}
@@ -0,0 +1,225 @@
+//! Geometry: the shape of the sample inside the mesh, and the shapes that
+//! select regions.
+//!
+//! Shapes are built from primitives combined by union, intersection and
+//! difference. Cells outside the sample carry no moment: their Mₛ and
+//! magnetization are zero, so every field, torque and energy vanishes there
+//! and their neighbours see a free surface.
+
+use std::path::{Path, PathBuf};
+
+use nalgebra::Vector3;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{Mesh, Result, VectorField, bitmap, expr::Expr};
+
+/// A set of cells, selected by the position (m) of their centre or by their
+/// indices
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(tag = "kind", rename_all = "lowercase", deny_unknown_fields)]
+pub enum Shape {
+    /// the axis-aligned box `[min, max]`
+    #[serde(alias = "box")]
+    Cuboid {
+        min: Vector3<f64>,
+        max: Vector3<f64>,
+    },
+    /// a cylinder of `radius` around `axis` through `centre`, `height` long
+    /// or unbounded
+    Cylinder {
+        centre: Vector3<f64>,
+        radius: f64,
+        #[serde(default = "Vector3::z")]
+        axis: Vector3<f64>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        height: Option<f64>,
+    },
+    /// an axis-aligned ellipsoid with semi-axes `radii`
+    Ellipsoid {
+        centre: Vector3<f64>,
+        radii: Vector3<f64>,
+    },
+    /// the z layers `first..=last` (cell indices), e.g. one film of a
+    /// bilayer
+    Layers { first: usize, last: usize },
+    /// cells where an expression of `x`, `y`, `z` is positive
+    Expr { expr: String },
+    /// cells whose pixel in a PNG or TIFF image has a grey value (see
+    /// [`Bitmap::at`](crate::bitmap::Bitmap::at)) within `[min, max]`, either bound being optional.
+    /// The image spans the mesh in x and y, its top row at the largest y;
+    /// the pages of a multi-page TIFF are stacked along z, a single page
+    /// applies to every layer.
+    Image {
+        file: PathBuf,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        min: Option<f64>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        max: Option<f64>,
+    },
+    /// cells in any of `shapes`
+    Union { shapes: Vec<Shape> },
+    /// cells in all of `shapes`
+    Intersection { shapes: Vec<Shape> },
+    /// cells in the first of `shapes` but in none of the others
+    Difference { shapes: Vec<Shape> },
+}
+
+/// Membership test of a cell index
+type Inside<'a> = Box<dyn Fn(usize) -> bool + Sync + 'a>;
+
+impl Shape {
+    /// Whether each cell of `mesh` lies inside
+    pub fn mask(&self, mesh: &Mesh) -> Result<Vec<bool>> {
+        let inside = self.inside(mesh)?;
+        Ok((0..mesh.len()).into_par_iter().map(&inside).collect())
+    }
+
+    /// Look up the image files of this shape and its parts in `dir`
+    pub fn resolve_files(&mut self, dir: &Path) {
+        match self {
+            Self::Image { file, .. } => *file = dir.join(&file),
+            Self::Union { shapes }
+            | Self::Intersection { shapes }
+            | Self::Difference { shapes } => {
+                for s in shapes {
+                    s.resolve_files(dir);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn inside<'a>(&self, mesh: &'a Mesh) -> Result<Inside<'a>> {
+        let parts = |shapes: &[Shape]| -> Result<Vec<Inside<'a>>> {
+            shapes.iter().map(|s| s.inside(mesh)).collect()
+        };
+        Ok(match self {
+            Self::Cuboid { min, max } => {
+                let (min, max) = (*min, *max);
+                Box::new(move |i| {
+                    let r = mesh.position(i);
+                    (0..3).all(|a| r[a] >= min[a] && r[a] <= max[a])
+                })
+            }
+            Self::Cylinder {
+                centre,
+                radius,
+                axis,
+                height,
+            } => {
+                let (c, radius, u, height) = (*centre, *radius, axis.normalize(), *height);
+                Box::new(move |i| {
+                    let d = mesh.position(i) - c;
+                    let along = d.dot(&u);
+                    (d - along * u).norm() <= radius
+                        && height.is_none_or(|h| 2.0 * along.abs() <= h)
+                })
+            }
+            Self::Ellipsoid { centre, radii } => {
+                let (c, radii) = (*centre, *radii);
+                Box::new(move |i| {
+                    (mesh.position(i) - c).component_div(&radii).norm_squared() <= 1.0
+                })
+            }
+            Self::Layers { first, last } => {
+                let (first, last) = (*first, *last);
+                Box::new(move |i| (first..=last).contains(&mesh.coords(i)[2]))
+            }
+            Self::Expr { expr } => {
+                let expr = Expr::parse(expr, &["x", "y", "z"])?;
+                Box::new(move |i| {
+                    let r = mesh.position(i);
+                    expr.eval(&[r.x, r.y, r.z]) > 0.0
+                })
+            }
+            Self::Image { file, min, max } => {
+                let image = bitmap::read(file)?;
+                let (min, max) = (
+                    min.unwrap_or(f64::NEG_INFINITY),
+                    max.unwrap_or(f64::INFINITY),
+                );
+                // the pixel holding the centre of cell c of n, out of size
+                let pixel = |c: usize, n: usize, size: usize| {
+                    (((c as f64 + 0.5) * size as f64 / n as f64) as usize).min(size - 1)
+                };
+                Box::new(move |i| {
+                    let [x, y, z] = mesh.coords(i);
+                    let v = image.at(
+                        pixel(x, mesh.nx, image.width),
+                        image.height - 1 - pixel(y, mesh.ny, image.height),
+                        pixel(z, mesh.nz, image.pages),
+                    );
+                    (min..=max).contains(&v)
+                })
+            }
+            Self::Union { shapes } => {
+                let parts = parts(shapes)?;
+                Box::new(move |i| parts.iter().any(|p| p(i)))
+            }
+            Self::Intersection { shapes } => {
+                let parts = parts(shapes)?;
+                Box::new(move |i| parts.iter().all(|p| p(i)))
+            }
+            Self::Difference { shapes } => {
+                let parts = parts(shapes)?;
+                Box::new(move |i| {
+                    parts.first().is_some_and(|p| p(i)) && !parts[1..].iter().any(|p| p(i))
+                })
+            }
+        })
+    }
+}
+
+/// The cells of the mesh that belong to the sample
+#[derive(Debug, Clone, PartialEq)]
+pub struct Geometry {
+    inside: Vec<bool>,
+    /// number of cells inside
+    cells: usize,
+}
+
+impl Geometry {
+    /// The cells of `mesh` inside `shape`, which must hold at least one
+    pub fn new(shape: &Shape, mesh: &Mesh) -> Result<Self> {
+        let inside = shape.mask(mesh)?;
+        let cells = inside.iter().filter(|&&b| b).count();
+        if cells == 0 {
+            return Err("`geometry` contains no cell of the mesh".into());
+        }
+        Ok(Self { inside, cells })
+    }
+
+    /// Whether cell `i` belongs to the sample
+    #[inline(always)]
+    pub fn contains(&self, i: usize) -> bool {
+        self.inside[i]
+    }
+
+    /// Number of cells in the sample
+    pub fn len(&self) -> usize {
+        self.cells
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cells == 0
+    }
+
+    /// Zero the vectors of `v` outside the sample, `v` holding one or more
+    /// vectors per cell, mesh after mesh
+    pub fn clear_outside(&self, v: &mut VectorField) {
+        let n = self.inside.len();
+        v.par_update(|i, v| {
+            if self.inside[i % n] {
+                v
+            } else {
+                Vector3::zeros()
+            }
+        });
+    }
+
+    /// Magnetization averaged over the sample
+    pub fn mean(&self, m: &VectorField) -> Vector3<f64> {
+        m.sum() / self.cells as f64
+    }
+}
@@ -0,0 +1,252 @@
+//! Minimum energy paths with the geodesic nudged elastic band method
+//! (Bessarab, Uzdin & Jónsson, Comput. Phys. Commun. 196, 335 (2015)).
+
+use nalgebra::Vector3;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    Mesh, Params, VectorField, field::FieldTerms, llg::hold_frozen, parallel::chunk, rng::Rng,
+};
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GnebConfig {
+    /// number of images, including the two fixed end points
+    pub images: usize,
+    /// spring constant between neighbouring images (T rad⁻¹)
+    pub spring: f64,
+    /// pseudo time step of the velocity-projection optimiser (T^-1/2)
+    pub step: f64,
+    /// stop once the largest perpendicular force on any spin drops below
+    /// this (T)
+    pub tolerance: f64,
+    pub max_iterations: u64,
+    /// random tilt (rad) added to the interior images of the initial path, so
+    /// the band can leave symmetric paths such as coherent rotation
+    pub noise: f64,
+    /// seed of the random tilt
+    pub seed: u64,
+    /// let the highest image climb to the saddle point once the band is
+    /// within 10 × `tolerance` of convergence
+    pub climbing: bool,
+}
+
+impl Default for GnebConfig {
+    fn default() -> Self {
+        Self {
+            images: 20,
+            spring: 1.0,
+            step: 0.1,
+            tolerance: 1e-3,
+            max_iterations: 100_000,
+            noise: 1e-2,
+            seed: 0,
+            climbing: true,
+        }
+    }
+}
+
+/// A relaxed (or partially relaxed) band of images
+#[derive(Debug, Clone)]
+pub struct Band {
+    pub images: Vec<VectorField>,
+    /// energy of every image (J)
+    pub energies: Vec<f64>,
+    pub iterations: u64,
+    /// final largest force on any spin (T)
+    pub force: f64,
+    pub converged: bool,
+}
+
+impl Band {
+    /// Index of the highest-energy image, the saddle point estimate
+    pub fn saddle(&self) -> usize {
+        (0..self.energies.len())
+            .max_by(|&a, &b| self.energies[a].total_cmp(&self.energies[b]))
+            .unwrap_or(0)
+    }
+
+    /// Energy barrier seen from the first image (J)
+    pub fn barrier(&self) -> f64 {
+        self.energies[self.saddle()] - self.energies[0]
+    }
+
+    /// Cumulative geodesic distance along the band (rad)
+    pub fn reaction_coordinate(&self) -> Vec<f64> {
+        let mut s = vec![0.0];
+        for w in self.images.windows(2) {
+            s.push(s[s.len() - 1] + distance(&w[0], &w[1]));
+        }
+        s
+    }
+}
+
+/// Geodesic distance between two states: the root sum of squared angles
+pub fn distance(a: &VectorField, b: &VectorField) -> f64 {
+    (0..a.len())
+        .into_par_iter()
+        .with_min_len(chunk())
+        .map(|i| {
+            let (a, b) = (a.get(i), b.get(i));
+            a.cross(&b).norm().atan2(a.dot(&b)).powi(2)
+        })
+        .sum::<f64>()
+        .sqrt()
+}
+
+/// Rotate `a` towards `b` along the great circle by the fraction `s`
+fn slerp(a: Vector3<f64>, b: Vector3<f64>, s: f64) -> Vector3<f64> {
+    let axis = a.cross(&b);
+    let theta = axis.norm().atan2(a.dot(&b));
+    let axis = if axis.norm() > 1e-12 {
+        axis.normalize()
+    } else if theta > 1.0 {
+        // antiparallel: any perpendicular axis will do
+        let trial = if a.x.abs() < 0.9 {
+            Vector3::x()
+        } else {
+            Vector3::y()
+        };
+        a.cross(&trial).normalize()
+    } else {
+        return a;
+    };
+    let phi = s * theta;
+    a * phi.cos() + axis.cross(&a) * phi.sin() + axis * axis.dot(&a) * (1.0 - phi.cos())
+}
+
+/// Images evenly spaced along the geodesic from `start` to `end`
+pub fn interpolate(start: &VectorField, end: &VectorField, n: usize) -> Vec<VectorField> {
+    (0..n)
+        .map(|k| {
+            let s = k as f64 / (n - 1) as f64;
+            VectorField::from_fn(start.len(), |i| slerp(start.get(i), end.get(i), s))
+        })
+        .collect()
+}
+
+/// Project `v` onto the tangent planes at `m`
+fn project(m: &VectorField, v: &mut VectorField) {
+    v.par_update(|i, v| {
+        let m = m.get(i);
+        v - v.dot(&m) * m
+    });
+}
+
+/// Upwind tangent at image `k` from its neighbours and energies
+fn tangent(images: &[VectorField], e: &[f64], k: usize) -> VectorField {
+    let (prev, here, next) = (&images[k - 1], &images[k], &images[k + 1]);
+    let (e0, e1, e2) = (e[k - 1], e[k], e[k + 1]);
+    let (wp, wm) = if e2 > e1 && e1 > e0 {
+        (1.0, 0.0)
+    } else if e2 < e1 && e1 < e0 {
+        (0.0, 1.0)
+    } else {
+        let big = (e2 - e1).abs().max((e0 - e1).abs());
+        let small = (e2 - e1).abs().min((e0 - e1).abs());
+        if e2 > e0 { (big, small) } else { (small, big) }
+    };
+    let mut t = VectorField::from_fn(here.len(), |i| {
+        wp * (next.get(i) - here.get(i)) + wm * (here.get(i) - prev.get(i))
+    });
+    project(here, &mut t);
+    let norm = t.dot(&t).sqrt();
+    if norm > 0.0 {
+        t.par_update(|_, t| t / norm);
+    }
+    t
+}
+
+/// Relax the band between `start` and `end` (kept fixed) towards the minimum
+/// energy path of `terms` at time `t`
+pub fn gneb(
+    start: &VectorField,
+    end: &VectorField,
+    t: f64,
+    mesh: &Mesh,
+    p: &Params,
+    terms: &FieldTerms,
+    config: &GnebConfig,
+) -> Band {
+    let n = config.images.max(2);
+    let mut images = interpolate(start, end, n);
+    let mut rng = Rng::new(config.seed);
+    for image in images.iter_mut().take(n - 1).skip(1) {
+        for i in 0..image.len() {
+            let m = image.get(i);
+            if m == Vector3::zeros() {
+                // outside the sample
+                continue;
+            }
+            let r = Vector3::from_fn(|_, _| 2.0 * rng.uniform() - 1.0);
+            image.set(i, (m + config.noise * r).normalize());
+        }
+    }
+    let mut velocity = vec![VectorField::zeros(start.len()); n];
+    let energy = |m: &VectorField| terms.energy(m, t, mesh, p);
+    let mut energies: Vec<f64> = images.iter().map(energy).collect();
+    let mut climbing = false;
+    let mut iterations = 0;
+    let mut force = f64::INFINITY;
+    let dt = config.step;
+
+    while iterations < config.max_iterations {
+        // forces on the interior images
+        let mut forces = Vec::with_capacity(n);
+        force = 0.0;
+        let top = (1..n - 1)
+            .max_by(|&a, &b| energies[a].total_cmp(&energies[b]))
+            .unwrap_or(0);
+        for k in 1..n - 1 {
+            let m = &images[k];
+            // -∇(E / Mₛ V) on the spheres, i.e. the perpendicular part of B
+            let mut f = terms.effective_field(m, t, mesh, p);
+            project(m, &mut f);
+            hold_frozen(p, &mut f);
+            let tau = tangent(&images, &energies, k);
+            let along = f.dot(&tau);
+            let spring = if climbing && k == top {
+                // invert the force along the path, no springs
+                f.axpy(-2.0 * along, &tau);
+                0.0
+            } else {
+                f.axpy(-along, &tau);
+                config.spring * (distance(&images[k + 1], m) - distance(m, &images[k - 1]))
+            };
+            f.axpy(spring, &tau);
+            force = force.max(f.max_norm());
+            forces.push(f);
+        }
+        if force <= config.tolerance && (climbing || !config.climbing || n < 3) {
+            break;
+        }
+        if config.climbing && !climbing && force <= 10.0 * config.tolerance {
+            climbing = true;
+            continue;
+        }
+        iterations += 1;
+
+        // velocity projection: keep only the velocity along the force
+        for (k, f) in (1..n - 1).zip(&forces) {
+            let v = &mut velocity[k];
+            let vf = v.dot(f);
+            let ff = f.dot(f);
+            let keep = if vf > 0.0 && ff > 0.0 { vf / ff } else { 0.0 };
+            v.par_set(|i| (keep + dt) * f.get(i));
+            let m = &mut images[k];
+            m.axpy(dt, v);
+            m.normalize();
+            project(m, v);
+            energies[k] = energy(m);
+        }
+    }
+
+    Band {
+        images,
+        energies,
+        iterations,
+        force,
+        converged: force <= config.tolerance,
+    }
+}
@@ -0,0 +1,132 @@
+// LLG time stepping on the full field arrays, one invocation per cell.
+//
+// A step of an explicit Runge–Kutta scheme is a sequence of `stage` / `rhs`
+// dispatches, one pair per stage, followed by `update`. Vectors are stored
+// as vec4 (w unused) to satisfy the storage buffer alignment.
+
+struct Constants {
+    // cells along x, y, z and their total
+    size: vec4<u32>,
+    // 1 where neighbours wrap around along x, y, z
+    periodic: vec4<u32>,
+    // exchange coupling 2A / (Ms d²) along x, y, z (T)
+    exchange: vec4<f32>,
+    // uniform applied field (T)
+    h_ext: vec4<f32>,
+    // uniaxial easy axis and, in w, 2 K1 / Ms (T)
+    axis: vec4<f32>,
+    // x: 4 K2 / Ms (T); y, z: dm/dt = y m × B + z m × (m × B)
+    coefs: vec4<f32>,
+}
+
+struct Stage {
+    // dt-scaled weights of the stages summed by `stage` and `update`
+    weights: array<vec4<f32>, 2>,
+    // number of weights
+    count: u32,
+    // stage written by `rhs`
+    index: u32,
+}
+
+@group(0) @binding(0) var<uniform> c: Constants;
+@group(0) @binding(1) var<uniform> s: Stage;
+@group(0) @binding(2) var<storage, read_write> m: array<vec4<f32>>;
+@group(0) @binding(3) var<storage, read_write> tmp: array<vec4<f32>>;
+@group(0) @binding(4) var<storage, read_write> k: array<vec4<f32>>;
+
+const WORKGROUP: u32 = 64u;
+
+fn cell(gid: vec3<u32>, groups: vec3<u32>) -> u32 {
+    return gid.x + gid.y * groups.x * WORKGROUP;
+}
+
+fn weight(j: u32) -> f32 {
+    return s.weights[j / 4u][j % 4u];
+}
+
+// m[i] + Σ_j w_j k_j[i]
+fn combine(i: u32) -> vec3<f32> {
+    let n = c.size.w;
+    var v = m[i].xyz;
+    for (var j = 0u; j < s.count; j++) {
+        v += weight(j) * k[j * n + i].xyz;
+    }
+    return v;
+}
+
+// Neighbour of cell (x, y, z) one step `dir` along `axis`, or the cell itself
+// across a free boundary
+fn neighbor(p: vec3<u32>, axis: u32, dir: i32) -> u32 {
+    let n = c.size[axis];
+    var q = p;
+    if dir < 0 {
+        if p[axis] == 0u {
+            if c.periodic[axis] == 0u {
+                return p.x + c.size.x * (p.y + c.size.y * p.z);
+            }
+            q[axis] = n - 1u;
+        } else {
+            q[axis] = p[axis] - 1u;
+        }
+    } else {
+        if p[axis] + 1u == n {
+            if c.periodic[axis] == 0u {
+                return p.x + c.size.x * (p.y + c.size.y * p.z);
+            }
+            q[axis] = 0u;
+        } else {
+            q[axis] = p[axis] + 1u;
+        }
+    }
+    return q.x + c.size.x * (q.y + c.size.y * q.z);
+}
+
+// Effective field (T) at cell i of the state in `tmp`
+fn field(i: u32) -> vec3<f32> {
+    let nx = c.size.x;
+    let ny = c.size.y;
+    let p = vec3<u32>(i % nx, (i / nx) % ny, i / (nx * ny));
+    let m_i = tmp[i].xyz;
+    var b = c.h_ext.xyz;
+    for (var axis = 0u; axis < 3u; axis++) {
+        let lap = tmp[neighbor(p, axis, -1)].xyz + tmp[neighbor(p, axis, 1)].xyz - 2.0 * m_i;
+        b += c.exchange[axis] * lap;
+    }
+    let mu = dot(m_i, c.axis.xyz);
+    b += (c.axis.w * mu + c.coefs.x * mu * mu * mu) * c.axis.xyz;
+    return b;
+}
+
+// tmp = m + Σ_j w_j k_j, the state at which the next stage is evaluated
+@compute @workgroup_size(64)
+fn stage(@builtin(global_invocation_id) gid: vec3<u32>, @builtin(num_workgroups) groups: vec3<u32>) {
+    let i = cell(gid, groups);
+    if i >= c.size.w {
+        return;
+    }
+    tmp[i] = vec4<f32>(combine(i), 0.0);
+}
+
+// k_index = dm/dt at the state in `tmp`
+@compute @workgroup_size(64)
+fn rhs(@builtin(global_invocation_id) gid: vec3<u32>, @builtin(num_workgroups) groups: vec3<u32>) {
+    let i = cell(gid, groups);
+    let n = c.size.w;
+    if i >= n {
+        return;
+    }
+    let m_i = tmp[i].xyz;
+    let mxb = cross(m_i, field(i));
+    let dmdt = c.coefs.y * mxb + c.coefs.z * cross(m_i, mxb);
+    k[s.index * n + i] = vec4<f32>(dmdt, 0.0);
+}
+
+// m = normalize(m + Σ_j w_j k_j), the end of the step
+@compute @workgroup_size(64)
+fn update(@builtin(global_invocation_id) gid: vec3<u32>, @builtin(num_workgroups) groups: vec3<u32>) {
+    let i = cell(gid, groups);
+    if i >= c.size.w {
+        return;
+    }
+    m[i] = vec4<f32>(normalize(combine(i)), 0.0);
+}
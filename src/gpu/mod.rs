@@ -0,0 +1,530 @@
+//! Explicit Runge–Kutta time stepping in wgpu compute shaders.
+//!
+//! The magnetization stays on the device for a whole batch of fixed-size
+//! steps ([`Stepper::advance_fixed`]); the exchange stencil, the field terms
+//! and the LLG right-hand side are evaluated there for every cell, in single
+//! precision. Equations the shaders cannot evaluate (demag, DMI, cubic
+//! anisotropy, time-dependent fields, spin torques) run on the CPU instead.
+
+use bytemuck::{Pod, Zeroable};
+use nalgebra::Vector3;
+
+use crate::{
+    Result, VectorField,
+    field::{Exchange, FieldTerm, UniaxialAnisotropy, Zeeman},
+    llg::Llg,
+    profile::{self, Section},
+    stepper::{ExplicitRk, HEUN, Method, RK4, Stepper, Tableau},
+};
+
+/// Cells per workgroup, as in `llg.wgsl`
+const WORKGROUP: u32 = 64;
+/// Largest number of workgroups along one dispatch dimension
+const MAX_GROUPS: u32 = 65535;
+/// Stride of the [`StageParams`] records, the usual uniform offset alignment
+const STRIDE: u64 = 256;
+/// Steps encoded per submission
+const BATCH: u64 = 256;
+/// Largest number of stages of a scheme
+const MAX_STAGES: usize = 8;
+
+/// `method` on the GPU, or on the CPU if it is adaptive or no adapter is
+/// found
+pub fn stepper(method: Method) -> Box<dyn Stepper> {
+    let tableau = match method {
+        Method::Heun => &HEUN,
+        Method::Rk4 => &RK4,
+        _ => {
+            tracing::warn!(
+                "solver method `{}` does not run on the GPU, using the CPU",
+                method.stepper().name()
+            );
+            return method.stepper();
+        }
+    };
+    match Context::new(tableau) {
+        Ok(context) => Box::new(GpuRk {
+            tableau,
+            cpu: ExplicitRk::new(tableau),
+            context,
+            buffers: None,
+            warned: false,
+        }),
+        Err(e) => {
+            tracing::warn!("no usable GPU ({e}), using the CPU");
+            method.stepper()
+        }
+    }
+}
+
+/// Uniforms shared by every dispatch (`Constants` in `llg.wgsl`)
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct Constants {
+    size: [u32; 4],
+    periodic: [u32; 4],
+    exchange: [f32; 4],
+    h_ext: [f32; 4],
+    axis: [f32; 4],
+    coefs: [f32; 4],
+}
+
+/// Uniforms of one stage (`Stage` in `llg.wgsl`)
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct StageParams {
+    weights: [f32; MAX_STAGES],
+    count: u32,
+    index: u32,
+    _pad: [u32; 2],
+}
+
+/// Device, pipelines and uniform buffers
+struct Context {
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    layout: wgpu::BindGroupLayout,
+    stage: wgpu::ComputePipeline,
+    rhs: wgpu::ComputePipeline,
+    update: wgpu::ComputePipeline,
+    constants: wgpu::Buffer,
+    /// one [`StageParams`] per stage, then that of the final update
+    stages: wgpu::Buffer,
+}
+
+/// Field arrays for a given number of cells
+struct Buffers {
+    cells: usize,
+    m: wgpu::Buffer,
+    /// host-readable copy of `m`
+    staging: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+}
+
+impl Context {
+    fn new(tableau: &Tableau) -> Result<Self> {
+        let instance =
+            wgpu::Instance::new(wgpu::InstanceDescriptor::new_without_display_handle_from_env());
+        let adapter = pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
+            power_preference: wgpu::PowerPreference::HighPerformance,
+            ..Default::default()
+        }))?;
+        if !adapter
+            .get_downlevel_capabilities()
+            .flags
+            .contains(wgpu::DownlevelFlags::COMPUTE_SHADERS)
+        {
+            return Err(format!("{} has no compute shaders", adapter.get_info().name).into());
+        }
+        let (device, queue) =
+            pollster::block_on(adapter.request_device(&wgpu::DeviceDescriptor {
+                label: Some("nez"),
+                required_limits: adapter.limits(),
+                ..Default::default()
+            }))?;
+        let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("llg"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("llg.wgsl").into()),
+        });
+        let entry = |binding, ty| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::COMPUTE,
+            ty,
+            count: None,
+        };
+        let uniform = |dynamic| wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Uniform,
+            has_dynamic_offset: dynamic,
+            min_binding_size: None,
+        };
+        let storage = wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Storage { read_only: false },
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        };
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("llg"),
+            entries: &[
+                entry(0, uniform(false)),
+                entry(1, uniform(true)),
+                entry(2, storage),
+                entry(3, storage),
+                entry(4, storage),
+            ],
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("llg"),
+            bind_group_layouts: &[Some(&layout)],
+            immediate_size: 0,
+        });
+        let pipeline = |name| {
+            device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some(name),
+                layout: Some(&pipeline_layout),
+                module: &module,
+                entry_point: Some(name),
+                compilation_options: Default::default(),
+                cache: None,
+            })
+        };
+        let (stage, rhs, update) = (pipeline("stage"), pipeline("rhs"), pipeline("update"));
+        let uniform_buffer = |label, size| {
+            device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some(label),
+                size,
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            })
+        };
+        let constants = uniform_buffer("constants", size_of::<Constants>() as u64);
+        let stages = uniform_buffer("stages", STRIDE * (tableau.c.len() as u64 + 1));
+        Ok(Self {
+            device,
+            queue,
+            layout,
+            stage,
+            rhs,
+            update,
+            constants,
+            stages,
+        })
+    }
+
+    /// Arrays for `cells` cells and a scheme of `stages` stages, or why they
+    /// do not fit on the device
+    fn buffers(&self, cells: usize, stages: usize) -> std::result::Result<Buffers, String> {
+        let limits = self.device.limits();
+        let bytes = (cells * size_of::<[f32; 4]>()) as u64;
+        if bytes * stages as u64
+            > limits
+                .max_storage_buffer_binding_size
+                .min(limits.max_buffer_size)
+        {
+            return Err(format!("{cells} cells do not fit in GPU memory"));
+        }
+        let groups = (cells as u64).div_ceil(WORKGROUP as u64);
+        if groups > MAX_GROUPS as u64 * MAX_GROUPS as u64 {
+            return Err(format!("{cells} cells exceed the GPU dispatch size"));
+        }
+        let storage = |label, size| {
+            self.device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some(label),
+                size,
+                usage: wgpu::BufferUsages::STORAGE
+                    | wgpu::BufferUsages::COPY_DST
+                    | wgpu::BufferUsages::COPY_SRC,
+                mapped_at_creation: false,
+            })
+        };
+        let m = storage("m", bytes);
+        let tmp = storage("tmp", bytes);
+        let k = storage("k", bytes * stages as u64);
+        let staging = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("staging"),
+            size: bytes,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let bind_group = self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("llg"),
+            layout: &self.layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: self.constants.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                        buffer: &self.stages,
+                        offset: 0,
+                        size: wgpu::BufferSize::new(size_of::<StageParams>() as u64),
+                    }),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: m.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: tmp.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 4,
+                    resource: k.as_entire_binding(),
+                },
+            ],
+        });
+        Ok(Buffers {
+            cells,
+            m,
+            staging,
+            bind_group,
+        })
+    }
+
+    /// `n` steps of `tableau` with size `dt` from `m`, in place
+    fn run(
+        &self,
+        tableau: &Tableau,
+        buffers: &Buffers,
+        llg: &Llg,
+        m: &mut VectorField,
+        dt: f64,
+        n: u64,
+    ) {
+        if n == 0 {
+            return;
+        }
+        let data: Vec<[f32; 4]> = m
+            .iter()
+            .map(|v| [v.x as f32, v.y as f32, v.z as f32, 0.0])
+            .collect();
+        self.queue
+            .write_buffer(&buffers.m, 0, bytemuck::cast_slice(&data));
+        self.queue
+            .write_buffer(&self.constants, 0, bytemuck::bytes_of(&constants(llg)));
+        self.queue
+            .write_buffer(&self.stages, 0, &stage_params(tableau, dt));
+
+        let groups = (m.len() as u32).div_ceil(WORKGROUP);
+        let (gx, gy) = (groups.min(MAX_GROUPS), groups.div_ceil(MAX_GROUPS));
+        let stages = tableau.c.len() as u32;
+        let mut done = 0;
+        while done < n {
+            let steps = BATCH.min(n - done);
+            let mut encoder = self
+                .device
+                .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+            {
+                let mut pass = encoder.begin_compute_pass(&Default::default());
+                for _ in 0..steps {
+                    for s in 0..stages {
+                        pass.set_bind_group(0, &buffers.bind_group, &[s * STRIDE as u32]);
+                        pass.set_pipeline(&self.stage);
+                        pass.dispatch_workgroups(gx, gy, 1);
+                        pass.set_pipeline(&self.rhs);
+                        pass.dispatch_workgroups(gx, gy, 1);
+                    }
+                    pass.set_bind_group(0, &buffers.bind_group, &[stages * STRIDE as u32]);
+                    pass.set_pipeline(&self.update);
+                    pass.dispatch_workgroups(gx, gy, 1);
+                }
+            }
+            done += steps;
+            if done == n {
+                encoder.copy_buffer_to_buffer(&buffers.m, 0, &buffers.staging, 0, None);
+            }
+            self.queue.submit([encoder.finish()]);
+        }
+
+        let slice = buffers.staging.slice(..);
+        slice.map_async(wgpu::MapMode::Read, |r| r.expect("GPU readback failed"));
+        self.device
+            .poll(wgpu::PollType::wait_indefinitely())
+            .expect("GPU readback failed");
+        {
+            let view = slice.get_mapped_range().expect("GPU readback failed");
+            for (i, v) in bytemuck::cast_slice::<u8, [f32; 4]>(&view)
+                .iter()
+                .enumerate()
+            {
+                m.set(i, Vector3::new(v[0] as f64, v[1] as f64, v[2] as f64));
+            }
+        }
+        buffers.staging.unmap();
+    }
+}
+
+/// Why the shaders cannot evaluate the equation of motion of `llg`, if so
+fn unsupported(llg: &Llg) -> Option<&'static str> {
+    let terms = llg.terms;
+    // the shaders sum exactly these: exchange, the applied field and uniaxial
+    // anisotropy
+    let shaded = |term: &dyn FieldTerm| {
+        term.is::<Exchange>() || term.is::<Zeeman>() || term.is::<UniaxialAnisotropy>()
+    };
+    if llg.sublattice.is_some() {
+        Some("two sublattices")
+    } else if llg.llb.is_some() {
+        Some("the LLB equation")
+    } else if llg.noise.is_some() {
+        Some("thermal fluctuations")
+    } else if let Some(term) = terms.iter().find(|&term| !shaded(term)) {
+        Some(term.name())
+    } else if terms.get::<Exchange>().is_none() || terms.get::<Zeeman>().is_none() {
+        Some("switching exchange or the applied field off")
+    } else if terms.get::<Zeeman>().is_some_and(|z| !z.is_static()) {
+        Some("time-dependent fields")
+    } else if !llg.torques.is_empty() {
+        Some("spin torques")
+    } else if llg.params.frozen.is_some() {
+        Some("frozen regions")
+    } else if !llg.params.is_uniform()
+        || terms.get::<UniaxialAnisotropy>().is_some_and(|u| {
+            u.k1.as_uniform().is_none()
+                || u.k2.as_uniform().is_none()
+                || u.axis.as_uniform().is_none()
+        })
+    {
+        Some("spatially varying parameters")
+    } else {
+        None
+    }
+}
+
+/// The uniforms describing `llg`
+fn constants(llg: &Llg) -> Constants {
+    let (mesh, p) = (llg.mesh, llg.params);
+    let ms = p.ms();
+    let exchange = mesh.cell().map(|d| (2.0 * p.a_ex / (ms * d * d)) as f32);
+    let (axis, k1, k2) = match llg.terms.get::<UniaxialAnisotropy>() {
+        // uniform, see `unsupported`
+        Some(u) => (u.axis.at(0).into_inner(), u.k1.at(0), u.k2.at(0)),
+        None => (Vector3::z(), 0.0, 0.0),
+    };
+    let (precession, damping) = if llg.precession {
+        let pref = -p.gamma / (1.0 + p.alpha * p.alpha);
+        (pref, pref * p.alpha)
+    } else {
+        (0.0, -p.gamma)
+    };
+    let h = p.h_ext;
+    Constants {
+        size: [
+            mesh.nx as u32,
+            mesh.ny as u32,
+            mesh.nz as u32,
+            mesh.len() as u32,
+        ],
+        periodic: [
+            mesh.periodic(0) as u32,
+            mesh.periodic(1) as u32,
+            mesh.periodic(2) as u32,
+            0,
+        ],
+        exchange: [exchange[0], exchange[1], exchange[2], 0.0],
+        h_ext: [h.x as f32, h.y as f32, h.z as f32, 0.0],
+        axis: [
+            axis.x as f32,
+            axis.y as f32,
+            axis.z as f32,
+            (2.0 * k1 / ms) as f32,
+        ],
+        coefs: [
+            (4.0 * k2 / ms) as f32,
+            precession as f32,
+            damping as f32,
+            0.0,
+        ],
+    }
+}
+
+/// The [`StageParams`] records of a step of size `dt`, each padded to
+/// [`STRIDE`] bytes
+fn stage_params(tableau: &Tableau, dt: f64) -> Vec<u8> {
+    let record = |w: &[f64], index| {
+        let mut weights = [0.0; MAX_STAGES];
+        for (dst, w) in weights.iter_mut().zip(w) {
+            *dst = (dt * w) as f32;
+        }
+        StageParams {
+            weights,
+            count: w.len() as u32,
+            index,
+            _pad: [0; 2],
+        }
+    };
+    let records = tableau
+        .a
+        .iter()
+        .enumerate()
+        .map(|(s, a)| record(a, s as u32))
+        .chain([record(tableau.b, 0)]);
+    let mut bytes = Vec::new();
+    for r in records {
+        bytes.extend_from_slice(bytemuck::bytes_of(&r));
+        bytes.resize(bytes.len().next_multiple_of(STRIDE as usize), 0);
+    }
+    bytes
+}
+
+/// A fixed-step [`Tableau`] evaluated in compute shaders, with the CPU
+/// scheme as fallback
+pub struct GpuRk {
+    tableau: &'static Tableau,
+    cpu: ExplicitRk,
+    context: Context,
+    buffers: Option<Buffers>,
+    /// whether the fallback to the CPU has been reported
+    warned: bool,
+}
+
+impl GpuRk {
+    /// Allocate the arrays for `cells` cells unless already there
+    fn allocate(&mut self, cells: usize) -> std::result::Result<(), String> {
+        if self.buffers.as_ref().is_none_or(|b| b.cells != cells) {
+            self.buffers = None;
+            self.buffers = Some(self.context.buffers(cells, self.tableau.c.len())?);
+        }
+        Ok(())
+    }
+
+    /// Report once that the CPU takes over
+    fn fall_back(&mut self, reason: &str) {
+        if !self.warned {
+            tracing::warn!("{reason}, using the CPU");
+            self.warned = true;
+        }
+    }
+}
+
+impl Stepper for GpuRk {
+    fn name(&self) -> &'static str {
+        self.tableau.name
+    }
+
+    fn order(&self) -> Option<u32> {
+        None
+    }
+
+    fn advance(
+        &mut self,
+        llg: &Llg,
+        m: &VectorField,
+        t: f64,
+        dt: f64,
+        next: &mut VectorField,
+    ) -> f64 {
+        next.clone_from(m);
+        self.advance_fixed(llg, next, &mut VectorField::default(), t, dt, 1);
+        0.0
+    }
+
+    fn advance_fixed(
+        &mut self,
+        llg: &Llg,
+        m: &mut VectorField,
+        scratch: &mut VectorField,
+        t: f64,
+        dt: f64,
+        n: u64,
+    ) {
+        if let Some(term) = unsupported(llg) {
+            self.fall_back(&format!("{term} does not run on the GPU"));
+            return self.cpu.advance_fixed(llg, m, scratch, t, dt, n);
+        }
+        if let Err(reason) = self.allocate(m.len()) {
+            self.fall_back(&reason);
+            return self.cpu.advance_fixed(llg, m, scratch, t, dt, n);
+        }
+        let buffers = self.buffers.as_ref().expect("allocated above");
+        let start = profile::start();
+        self.context.run(self.tableau, buffers, llg, m, dt, n);
+        profile::record(
+            Section::Solver,
+            format_args!("{} steps on the GPU", self.name()),
+            start,
+        );
+    }
+}
@@ -0,0 +1,210 @@
+//! Granular media: the film tessellated into columnar Voronoi grains, as in
+//! recording media and polycrystalline films.
+//!
+//! Grain seeds are scattered uniformly over the xy extent of the mesh and
+//! every cell belongs to the grain of the nearest seed, through the whole
+//! thickness (periodic axes wrap). Each grain turns the anisotropy axes by
+//! its own random rotation, and the exchange across grain boundaries may be
+//! weakened.
+
+use std::f64::consts::{PI, TAU};
+
+use nalgebra::{Rotation3, Unit, Vector3};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{Mesh, rng::Rng};
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GrainsConfig {
+    /// mean grain diameter (m), that of a circle with the mean grain area
+    pub size: f64,
+    /// largest tilt (degrees) of the `[uniaxial]` axis and of the `[cubic]`
+    /// axis `c1` in a grain, spread uniformly over the cone; cubic grains
+    /// also turn about `c1` by up to as much either way. 180 orients the
+    /// grains at random.
+    pub cone: f64,
+    /// exchange stiffness across a grain boundary, relative to that inside
+    /// the grains
+    pub exchange: f64,
+    pub seed: u64,
+}
+
+impl Default for GrainsConfig {
+    fn default() -> Self {
+        Self {
+            size: 10e-9,
+            cone: 0.0,
+            exchange: 1.0,
+            seed: 0,
+        }
+    }
+}
+
+/// Grain of every cell, with the orientation of every grain
+#[derive(Debug, Clone, PartialEq)]
+pub struct Grains {
+    /// grain of every cell
+    pub index: Vec<u32>,
+    /// tilt, azimuth of the tilt and turn about the tilted axis (rad) of
+    /// every grain
+    orientation: Vec<[f64; 3]>,
+    /// exchange across grain boundaries relative to inside the grains
+    pub exchange: f64,
+}
+
+impl Grains {
+    /// Tessellate `mesh` as `config` describes
+    pub fn new(config: &GrainsConfig, mesh: &Mesh) -> Self {
+        let extent = [mesh.nx as f64 * mesh.dx, mesh.ny as f64 * mesh.dy];
+        let grain_area = PI / 4.0 * config.size * config.size;
+        let count = ((extent[0] * extent[1] / grain_area).round() as usize).max(1);
+        let mut rng = Rng::new(config.seed);
+        let seeds: Vec<[f64; 2]> = (0..count)
+            .map(|_| extent.map(|l| l * rng.uniform()))
+            .collect();
+        let cone = config.cone.to_radians();
+        let orientation = (0..count)
+            .map(|_| {
+                // cos θ uniform over the cap spreads the axes evenly
+                let tilt = (1.0 - rng.uniform() * (1.0 - cone.cos())).acos();
+                [
+                    tilt,
+                    TAU * rng.uniform(),
+                    cone * (2.0 * rng.uniform() - 1.0),
+                ]
+            })
+            .collect();
+        let columns = nearest(&seeds, mesh);
+        let index = (0..mesh.len())
+            .map(|i| columns[i % (mesh.nx * mesh.ny)])
+            .collect();
+        Self {
+            index,
+            orientation,
+            exchange: config.exchange,
+        }
+    }
+
+    /// Number of grains
+    pub fn len(&self) -> usize {
+        self.orientation.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.orientation.is_empty()
+    }
+
+    /// Whether cells `i` and `j` lie in different grains
+    #[inline(always)]
+    pub fn boundary(&self, i: usize, j: usize) -> bool {
+        self.index[i] != self.index[j]
+    }
+
+    /// `value` of the rotation of the grain of every cell, the tilt being
+    /// measured from `reference`; `None` when no grain is turned
+    pub fn oriented<T: Copy + Send + Sync>(
+        &self,
+        reference: Vector3<f64>,
+        value: impl Fn(&Rotation3<f64>) -> T,
+    ) -> Option<Vec<T>> {
+        if self
+            .orientation
+            .iter()
+            .all(|&[tilt, _, turn]| tilt == 0.0 && turn == 0.0)
+        {
+            return None;
+        }
+        let u = Unit::new_normalize(reference);
+        let other = if u.x.abs() < 0.9 {
+            Vector3::x()
+        } else {
+            Vector3::y()
+        };
+        let normal = u.cross(&other).normalize();
+        let values: Vec<T> = self
+            .orientation
+            .iter()
+            .map(|&[tilt, azimuth, turn]| {
+                let hinge = Unit::new_normalize(Rotation3::from_axis_angle(&u, azimuth) * normal);
+                value(
+                    &(Rotation3::from_axis_angle(&hinge, tilt)
+                        * Rotation3::from_axis_angle(&u, turn)),
+                )
+            })
+            .collect();
+        Some(self.index.par_iter().map(|&g| values[g as usize]).collect())
+    }
+}
+
+/// Index of the seed nearest to every column (x, y) of `mesh`, found among
+/// the seeds binned on a grid about one seed spacing wide
+fn nearest(seeds: &[[f64; 2]], mesh: &Mesh) -> Vec<u32> {
+    let (nx, ny) = (mesh.nx, mesh.ny);
+    let extent = [nx as f64 * mesh.dx, ny as f64 * mesh.dy];
+    let spacing = (extent[0] * extent[1] / seeds.len() as f64).sqrt();
+    let bins = extent.map(|l| ((l / spacing) as usize).max(1));
+    let width = [extent[0] / bins[0] as f64, extent[1] / bins[1] as f64];
+    let bin = |r: [f64; 2]| [0, 1].map(|a| ((r[a] / width[a]) as usize).min(bins[a] - 1));
+    let mut members = vec![Vec::new(); bins[0] * bins[1]];
+    for (k, &s) in seeds.iter().enumerate() {
+        let [bx, by] = bin(s);
+        members[bx + bins[0] * by].push(k);
+    }
+    // distance along `axis`, through the boundary if periodic
+    let along = |a: f64, b: f64, axis: usize| {
+        let d = (a - b).abs();
+        if mesh.periodic(axis) {
+            d.min(extent[axis] - d)
+        } else {
+            d
+        }
+    };
+    // bin `i` along `axis`, if it exists
+    let wrap = |i: isize, axis: usize| {
+        let n = bins[axis] as isize;
+        if (0..n).contains(&i) {
+            Some(i as usize)
+        } else if mesh.periodic(axis) {
+            Some(i.rem_euclid(n) as usize)
+        } else {
+            None
+        }
+    };
+    (0..nx * ny)
+        .into_par_iter()
+        .map(|c| {
+            let r = [(c % nx) as f64 + 0.5, (c / nx) as f64 + 0.5];
+            let r = [r[0] * mesh.dx, r[1] * mesh.dy];
+            let home = bin(r).map(|b| b as isize);
+            let mut best = (f64::INFINITY, 0);
+            // seeds in ring `ring + 1` of bins around home lie at least
+            // `ring` bin widths away
+            for ring in 0..=bins[0].max(bins[1]) as isize {
+                for dy in -ring..=ring {
+                    for dx in -ring..=ring {
+                        if dx.abs().max(dy.abs()) != ring {
+                            continue;
+                        }
+                        let (Some(bx), Some(by)) = (wrap(home[0] + dx, 0), wrap(home[1] + dy, 1))
+                        else {
+                            continue;
+                        };
+                        for &k in &members[bx + bins[0] * by] {
+                            let s = seeds[k];
+                            let d2 = along(r[0], s[0], 0).powi(2) + along(r[1], s[1], 1).powi(2);
+                            if d2 < best.0 || (d2 == best.0 && k < best.1) {
+                                best = (d2, k);
+                            }
+                        }
+                    }
+                }
+                if best.0.sqrt() <= ring as f64 * width[0].min(width[1]) {
+                    break;
+                }
+            }
+            best.1 as u32
+        })
+        .collect()
+}
@@ -0,0 +1,109 @@
+//! Quasi-static hysteresis loops: the applied field is stepped along a fixed
+//! direction and the magnetization relaxed at every step.
+
+use nalgebra::Vector3;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+use crate::{Config, Result, Simulation};
+
+/// How the state is brought to rest at every field
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Relaxation {
+    /// conjugate gradients with the `[minimize]` settings
+    #[default]
+    Minimize,
+    /// damping-only dynamics with the `[relax]` settings
+    Relax,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HysteresisConfig {
+    /// direction of the swept field, added to `params.h_ext`
+    pub direction: Vector3<f64>,
+    /// field at the start and end of the loop (T)
+    pub max: f64,
+    /// field at the turning point (T)
+    pub min: f64,
+    /// field steps per branch
+    pub steps: u64,
+    pub relaxation: Relaxation,
+    /// store the state at every field rather than only the final one
+    pub snapshots: bool,
+    /// tab-separated B–M table
+    pub table: PathBuf,
+}
+
+impl Default for HysteresisConfig {
+    fn default() -> Self {
+        Self {
+            direction: Vector3::z(),
+            max: 0.5,
+            min: -0.5,
+            steps: 100,
+            relaxation: Relaxation::default(),
+            snapshots: false,
+            table: "hysteresis.tsv".into(),
+        }
+    }
+}
+
+impl HysteresisConfig {
+    /// Swept field at every point of the loop, `max → min → max` (T)
+    pub fn fields(&self) -> Vec<f64> {
+        let n = self.steps as usize;
+        let at = |k: usize| self.max + (self.min - self.max) * k as f64 / n as f64;
+        (0..=n).chain((0..n).rev()).map(at).collect()
+    }
+}
+
+/// Relaxed state at one field of the loop
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct Point {
+    /// swept field along `direction` (T)
+    pub field: f64,
+    /// spatially averaged magnetization
+    pub m: Vector3<f64>,
+    /// ⟨m⟩ along `direction`
+    pub projection: f64,
+    /// (J)
+    pub energy: f64,
+    pub converged: bool,
+}
+
+/// Run the loop of `config.hysteresis` on `sim`, calling `each` after
+/// relaxing at every field. `params.h_ext` is restored afterwards.
+pub fn hysteresis(
+    sim: &mut Simulation,
+    config: &Config,
+    mut each: impl FnMut(&mut Simulation, &Point) -> Result<()>,
+) -> Result<Vec<Point>> {
+    let sweep = &config.hysteresis;
+    let u = sweep.direction.normalize();
+    let bias = sim.params.h_ext;
+    let mut points = Vec::new();
+    for field in sweep.fields() {
+        sim.params.h_ext = bias + field * u;
+        let relaxed = match sweep.relaxation {
+            Relaxation::Minimize => sim.minimize(&config.minimize),
+            Relaxation::Relax => sim.relax(&config.relax),
+        };
+        let m = sim.average();
+        let point = Point {
+            field,
+            m,
+            projection: m.dot(&u),
+            energy: sim.energy(),
+            converged: relaxed.converged,
+        };
+        if let Err(e) = each(sim, &point) {
+            sim.params.h_ext = bias;
+            return Err(e);
+        }
+        points.push(point);
+    }
+    sim.params.h_ext = bias;
+    Ok(points)
+}
@@ -0,0 +1,130 @@
+//! Ending a run cleanly on Ctrl+C, on the SIGTERM a cluster scheduler
+//! sends a job it preempts, or before the wall-time limit of the job.
+//!
+//! Once [`install`] has been called, the first SIGINT or SIGTERM only sets a
+//! flag, which [`Simulation::run`](crate::Simulation::run) checks between
+//! steps: the step under way is finished, the command writes a checkpoint
+//! to the store and exits with an error, and `nez resume` continues from
+//! there. A second signal ends the process at once. A [`Walltime`] budget,
+//! `--walltime 23h30m` on the command line, sets a deadline that ends the
+//! run the same way, a little ahead of the limit.
+
+use std::{
+    str::FromStr,
+    sync::{
+        OnceLock,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+/// Whether a signal asked the run to end
+static REQUESTED: AtomicBool = AtomicBool::new(false);
+
+/// Time at which the run is to end, if any
+static DEADLINE: OnceLock<Instant> = OnceLock::new();
+
+/// Whether SIGINT or SIGTERM was received since [`install`], or the
+/// deadline has passed
+pub fn requested() -> bool {
+    REQUESTED.load(Ordering::Relaxed) || out_of_time()
+}
+
+/// Whether the deadline set by [`Walltime::start`] has passed
+pub fn out_of_time() -> bool {
+    DEADLINE.get().is_some_and(|d| Instant::now() >= *d)
+}
+
+/// Wall-clock time a job may take, e.g. `23h30m`, `90m`, `1d`, `3600` (s)
+/// or `23:30:00` as given to the scheduler
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Walltime {
+    /// as written
+    pub text: String,
+    pub budget: Duration,
+}
+
+impl FromStr for Walltime {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, String> {
+        let invalid = || format!("invalid wall time `{text}`, expected e.g. 23h30m or 23:30:00");
+        let seconds = if text.contains(':') {
+            // [hh:]mm:ss
+            let parts: Vec<u64> = text
+                .split(':')
+                .map(|p| p.parse().map_err(|_| invalid()))
+                .collect::<Result<_, _>>()?;
+            if parts.len() > 3 {
+                return Err(invalid());
+            }
+            parts.iter().fold(0, |s, p| 60 * s + p)
+        } else {
+            let mut seconds = 0.0;
+            let mut number = String::new();
+            for c in text.chars() {
+                let unit = match c {
+                    'd' => 86400.0,
+                    'h' => 3600.0,
+                    'm' => 60.0,
+                    's' => 1.0,
+                    _ => {
+                        number.push(c);
+                        continue;
+                    }
+                };
+                seconds += unit * number.parse::<f64>().map_err(|_| invalid())?;
+                number.clear();
+            }
+            if !number.is_empty() {
+                seconds += number.parse::<f64>().map_err(|_| invalid())?;
+            }
+            seconds as u64
+        };
+        if seconds == 0 {
+            return Err(invalid());
+        }
+        Ok(Self {
+            text: text.to_string(),
+            budget: Duration::from_secs(seconds),
+        })
+    }
+}
+
+impl Walltime {
+    /// Set the deadline of a job started at `start`: five minutes before
+    /// the end of the budget, or a twentieth of it if shorter, to leave
+    /// time for the step under way and the checkpoint
+    pub fn start(&self, start: Instant) {
+        let margin = (self.budget / 20).min(Duration::from_secs(300));
+        let _ = DEADLINE.set(start + self.budget - margin);
+    }
+}
+
+/// Catch SIGINT and SIGTERM from now on (Linux only)
+#[cfg(target_os = "linux")]
+pub fn install() {
+    extern "C" fn handle(signal: libc::c_int) {
+        if REQUESTED.swap(true, Ordering::Relaxed) {
+            // SAFETY: `_exit` is async-signal-safe
+            unsafe { libc::_exit(128 + signal) };
+        }
+    }
+    for signal in [libc::SIGINT, libc::SIGTERM] {
+        // SAFETY: `action` is fully initialised and `handle` only touches an
+        // atomic before exiting
+        let done = unsafe {
+            let mut action: libc::sigaction = std::mem::zeroed();
+            action.sa_sigaction = handle as extern "C" fn(libc::c_int) as libc::sighandler_t;
+            action.sa_flags = libc::SA_RESTART;
+            libc::sigemptyset(&mut action.sa_mask);
+            libc::sigaction(signal, &action, std::ptr::null_mut()) == 0
+        };
+        if !done {
+            tracing::warn!(signal, "cannot catch a signal, it will end the run at once");
+        }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn install() {}
@@ -0,0 +1,101 @@
+//! Thermal fluctuations in the dynamics: Brown's stochastic field, at a
+//! temperature that may vary across the sample, and the spin-Seebeck torque
+//! of its gradient.
+//!
+//! Every step adds to the effective field of cell i a random field
+//!
+//! B_th = η √(2 α k_B T / (γ Mₛ V Δt)),
+//!
+//! with η a standard normal vector drawn anew each step and held over its
+//! stages, so that the moments sample the Boltzmann distribution of the
+//! energy at temperature T. The step must then be fixed: `heun` converges
+//! to the Stratonovich solution the field is meant for, `rk4` does as well
+//! with the field frozen over the step. The temperature T(x) is uniform or
+//! given cell by cell, like a map:
+//!
+//! ```toml
+//! [langevin]
+//! map = { expr = "300 + 100 * x / 2e-6" }
+//! seed = 1
+//! seebeck = 1e-6
+//! ```
+//!
+//! A gradient drives magnons from the hot side to the cold one already
+//! through the field alone. `seebeck` adds the torque these magnons exert on
+//! textures they cross, see [`crate::torque::SpinSeebeck`].
+
+use nalgebra::Vector3;
+use serde::{Deserialize, Serialize};
+
+use crate::{Mesh, Params, Result, VectorField, maps::Map, monte_carlo::K_B, rng::Rng};
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LangevinConfig {
+    /// temperature of the whole sample (K)
+    pub temperature: f64,
+    /// temperature of every cell (K) instead, from an expression of the
+    /// position or a file
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub map: Option<Map>,
+    /// seed of the random numbers
+    pub seed: u64,
+    /// drift velocity of the spin-Seebeck torque per unit of temperature
+    /// gradient (m² s⁻¹ K⁻¹), 0 for none
+    pub seebeck: f64,
+    /// non-adiabaticity β of that torque
+    pub beta: f64,
+}
+
+impl LangevinConfig {
+    /// Temperature of every cell of `mesh` (K)
+    pub fn temperatures(&self, mesh: &Mesh) -> Result<Vec<f64>> {
+        let temperature = match &self.map {
+            Some(map) => map
+                .values(mesh)
+                .map_err(|e| format!("`langevin.map`: {e}"))?,
+            None => vec![self.temperature; mesh.len()],
+        };
+        match temperature.iter().find(|t| **t < 0.0) {
+            Some(t) => {
+                Err(format!("`langevin` temperatures must be non-negative, got {t} K").into())
+            }
+            None => Ok(temperature),
+        }
+    }
+}
+
+/// The stochastic field of `[langevin]`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Langevin {
+    /// temperature of every cell (K)
+    pub temperature: Vec<f64>,
+    /// field of the step being taken (T)
+    pub field: VectorField,
+}
+
+impl Langevin {
+    pub fn new(config: &LangevinConfig, mesh: &Mesh) -> Result<Self> {
+        Ok(Self {
+            temperature: config.temperatures(mesh)?,
+            field: VectorField::zeros(mesh.len()),
+        })
+    }
+
+    /// Draw the field of a step of `dt` from `rng`, with parameters `p` on
+    /// `mesh`; cells without a moment, frozen or at 0 K get none
+    pub fn draw(&mut self, p: &Params, mesh: &Mesh, dt: f64, rng: &mut Rng) {
+        let volume = mesh.cell_volume();
+        for (i, &t) in self.temperature.iter().enumerate() {
+            let ms = p.ms_at(i);
+            let b = if t > 0.0 && ms > 0.0 && !p.is_frozen(i) {
+                let sigma =
+                    (2.0 * p.alpha_at(i) * K_B * t / (p.gamma_at(i) * ms * volume * dt)).sqrt();
+                sigma * rng.normal_vector()
+            } else {
+                Vector3::zeros()
+            };
+            self.field.set(i, b);
+        }
+    }
+}
@@ -0,0 +1,80 @@
+//! nez — a small micromagnetic / atomistic spin-dynamics solver.
+//!
+//! The solver integrates the Landau–Lifshitz–Gilbert equation with a
+//! pluggable [`stepper::Stepper`] and streams the magnetization to a Zarr
+//! store.
+
+pub mod absorbing;
+pub mod bitmap;
+pub mod config;
+pub mod dashboard;
+pub mod dispersion;
+pub mod ensemble;
+pub mod expr;
+pub mod fft;
+pub mod field;
+mod fused;
+pub mod geometry;
+pub mod gneb;
+#[cfg(feature = "gpu")]
+pub mod gpu;
+pub mod grains;
+pub mod hysteresis;
+pub mod interrupt;
+pub mod langevin;
+pub mod llb;
+pub mod llg;
+pub mod logging;
+pub mod maps;
+pub mod mesh;
+pub mod minimize;
+pub mod monte_carlo;
+pub mod mtj;
+pub mod mx3;
+pub mod output;
+pub mod ovf;
+pub mod parallel;
+pub mod params;
+pub mod presets;
+pub mod probe;
+pub mod profile;
+pub mod progress;
+pub mod regions;
+#[cfg(feature = "object-store")]
+mod remote;
+pub mod render;
+pub mod rng;
+pub mod script;
+pub mod serve;
+pub mod simulation;
+pub mod snapshot;
+pub mod spectrum;
+pub mod spin_pumping;
+pub mod stages;
+pub mod stepper;
+pub mod sublattice;
+pub mod sweep;
+pub mod table;
+pub mod temperature;
+pub mod texture;
+pub mod topology;
+pub mod torque;
+pub mod vector_field;
+pub mod vtk;
+pub mod wall;
+pub mod waveform;
+
+pub use config::Config;
+pub use field::FieldTerms;
+pub use mesh::Mesh;
+pub use output::{ZarrGroup, ZarrOutput};
+pub use params::Params;
+pub use simulation::Simulation;
+pub use torque::Torques;
+pub use vector_field::VectorField;
+pub use waveform::Waveform;
+
+/// Crate-wide error type
+pub type Error = Box<dyn std::error::Error + Send + Sync>;
+/// Crate-wide result type
+pub type Result<T> = std::result::Result<T, Error>;
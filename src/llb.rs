@@ -0,0 +1,107 @@
+//! The Landau–Lifshitz–Bloch equation, for dynamics near and above the
+//! Curie temperature: ultrafast demagnetization, heat-assisted recording.
+//!
+//! With `[llb]` set, the magnetization m = M / Mₛ(0) of every cell is no
+//! longer a unit vector: besides precessing and relaxing transversally as in
+//! the LLG equation, it relaxes longitudinally towards its equilibrium
+//! length at the temperature T(t) of the sample,
+//!
+//! dm/dt = γ′ [-m × B + α∥ (m·(B + B∥)) m / m² - α⊥ m × (m × B) / m²],
+//!
+//! with γ′ = γ / (1 + α²), α∥ = 2αT / 3T_c and α⊥ = α (1 - T / 3T_c) below
+//! T_c, α⊥ = α∥ above. The longitudinal field comes from the Landau
+//! expansion of the mean-field free energy,
+//! B∥ = (1 - T / T_c - m²) m / 2χ₀: below T_c it drives |m| to
+//! m_e = √(1 - T / T_c) with the susceptibility χ∥ = χ₀ T_c / (T_c - T),
+//! above it to 0. At T = 0 the equation is the LLG equation again.
+//!
+//! Mₛ and `a_ex` keep their zero-temperature values, and the temperature is
+//! the same everywhere; a laser pulse heating the sample is a waveform of
+//! it:
+//!
+//! ```toml
+//! [llb]
+//! curie = 850.0
+//! susceptibility = 1e-3
+//! temperature = { kind = "gaussian", amplitude = 1000.0, t0 = 1e-12, sigma = 2e-13 }
+//! ```
+
+use nalgebra::Vector3;
+use serde::{Deserialize, Serialize};
+
+use crate::{Params, VectorField, Waveform};
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LlbConfig {
+    /// Curie temperature T_c (K)
+    pub curie: f64,
+    /// χ₀ (T⁻¹), the longitudinal susceptibility χ∥ times (T_c - T) / T_c
+    pub susceptibility: f64,
+    /// temperature of the sample (K)
+    pub temperature: Waveform<f64>,
+}
+
+impl Default for LlbConfig {
+    /// permalloy at room temperature
+    fn default() -> Self {
+        Self {
+            curie: 850.0,
+            susceptibility: 1e-3,
+            temperature: Waveform::Constant { value: 300.0 },
+        }
+    }
+}
+
+/// The LLB equation of motion of `[llb]`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Llb {
+    pub config: LlbConfig,
+}
+
+impl Llb {
+    pub fn new(config: &LlbConfig) -> Self {
+        Self {
+            config: config.clone(),
+        }
+    }
+
+    /// Temperature of the sample at time `t` (K), never negative
+    pub fn temperature(&self, t: f64) -> f64 {
+        self.config.temperature.eval(t).max(0.0)
+    }
+
+    /// Equilibrium length of m at time `t`
+    pub fn equilibrium(&self, t: f64) -> f64 {
+        (1.0 - self.temperature(t) / self.config.curie)
+            .max(0.0)
+            .sqrt()
+    }
+
+    /// Overwrite the effective field `h` of `m` at time `t`, with
+    /// parameters `p`, by dm/dt
+    pub fn field_to_rhs(&self, m: &VectorField, t: f64, p: &Params, h: &mut VectorField) {
+        let tc = self.config.curie;
+        let reduced = self.temperature(t) / tc;
+        let chi = self.config.susceptibility;
+        h.par_update(|i, b| {
+            let m = m.get(i);
+            let m2 = m.norm_squared();
+            if m2 == 0.0 {
+                return Vector3::zeros();
+            }
+            let (gamma, alpha) = (p.gamma_at(i), p.alpha_at(i));
+            let parallel = 2.0 * alpha * reduced / 3.0;
+            let perpendicular = if reduced < 1.0 {
+                alpha * (1.0 - reduced / 3.0)
+            } else {
+                parallel
+            };
+            let longitudinal = (1.0 - reduced - m2) / (2.0 * chi) * m;
+            let mxb = m.cross(&b);
+            -gamma / (1.0 + alpha * alpha)
+                * (mxb - parallel * m.dot(&(b + longitudinal)) / m2 * m
+                    + perpendicular * m.cross(&mxb) / m2)
+        });
+    }
+}
@@ -0,0 +1,151 @@
+use nalgebra::Vector3;
+use rayon::prelude::*;
+
+use crate::{
+    Mesh, Params, Torques, VectorField,
+    field::FieldTerms,
+    fused::Fused,
+    llb::Llb,
+    parallel::chunk,
+    profile::{self, Section},
+    sublattice::Sublattice,
+    vector_field::Real,
+};
+
+/// LLG right-hand side for a single spin
+#[inline(always)]
+pub fn llg_rhs(m: &Vector3<f64>, h_eff: &Vector3<f64>, p: &Params) -> Vector3<f64> {
+    let mxh = m.cross(h_eff);
+    let mxmxh = m.cross(&mxh);
+    let pref = -p.gamma / (1.0 + p.alpha * p.alpha);
+    pref * (mxh + p.alpha * mxmxh)
+}
+
+/// Largest torque max|m × B| (T) on the moments that are free to turn
+pub fn max_torque(m: &VectorField, h: &VectorField, p: &Params) -> f64 {
+    (0..m.len())
+        .into_par_iter()
+        .with_min_len(chunk())
+        .filter(|&i| !p.is_frozen(i))
+        .map(|i| m.get(i).cross(&h.get(i)).norm())
+        .reduce(|| 0.0, f64::max)
+}
+
+/// Zero `v` on the frozen cells of `p`
+pub fn hold_frozen(p: &Params, v: &mut VectorField) {
+    if p.frozen.is_some() {
+        v.par_update(|i, v| if p.is_frozen(i) { Vector3::zeros() } else { v });
+    }
+}
+
+/// Overwrite `b` by `a₁ m × b + a₂ m × (m × b)`, cell by cell, with
+/// `(a₁, a₂)` of cell `i` given by `coefs(i)`
+fn cross_terms(m: &VectorField, coefs: impl Fn(usize) -> (f64, f64) + Sync, b: &mut VectorField) {
+    b.par_chunks_mut().for_each(|(start, [bx, by, bz])| {
+        let end = start + bx.len();
+        let (mx, my, mz) = (&m.x[start..end], &m.y[start..end], &m.z[start..end]);
+        for k in 0..bx.len() {
+            let (a1, a2) = coefs(start + k);
+            let (a1, a2) = (a1 as Real, a2 as Real);
+            let cx = my[k] * bz[k] - mz[k] * by[k];
+            let cy = mz[k] * bx[k] - mx[k] * bz[k];
+            let cz = mx[k] * by[k] - my[k] * bx[k];
+            let dx = my[k] * cz - mz[k] * cy;
+            let dy = mz[k] * cx - mx[k] * cz;
+            let dz = mx[k] * cy - my[k] * cx;
+            bx[k] = a1 * cx + a2 * dx;
+            by[k] = a1 * cy + a2 * dy;
+            bz[k] = a1 * cz + a2 * dz;
+        }
+    });
+}
+
+/// The equation of motion: everything needed to evaluate dm/dt
+#[derive(Clone, Copy)]
+pub struct Llg<'a> {
+    pub mesh: &'a Mesh,
+    pub params: &'a Params,
+    pub terms: &'a FieldTerms,
+    pub torques: &'a Torques,
+    /// `false` keeps only the damping term, -γ m × (m × B), which relaxes
+    /// the magnetization without spin waves
+    pub precession: bool,
+    /// second sublattice, whose moments follow those of the first in the
+    /// state
+    pub sublattice: Option<&'a Sublattice>,
+    /// the Landau–Lifshitz–Bloch equation instead, under which |m| changes
+    pub llb: Option<&'a Llb>,
+    /// stochastic field of the step being taken, added to the effective
+    /// field, see [`crate::langevin`]
+    pub noise: Option<&'a VectorField>,
+}
+
+impl Llg<'_> {
+    /// dm/dt for every cell at time `t`
+    pub fn rhs(&self, m: &VectorField, t: f64) -> VectorField {
+        let mut dmdt = VectorField::zeros(m.len());
+        self.rhs_into(m, t, &mut dmdt);
+        dmdt
+    }
+
+    /// [`rhs`](Self::rhs) written over `dmdt`, resized to `m` if needed
+    pub fn rhs_into(&self, m: &VectorField, t: f64, dmdt: &mut VectorField) {
+        if let Some(s) = self.sublattice {
+            return s.rhs_into(self, m, t, dmdt);
+        }
+        dmdt.resize(m.len());
+        if let Some(fused) = Fused::new(self, t) {
+            fused.rhs_into(self, m, t, dmdt);
+            return self.add_torques(m, t, self.params, dmdt);
+        }
+        self.terms
+            .effective_field_into(m, t, self.mesh, self.params, dmdt);
+        self.field_to_rhs(m, t, self.params, dmdt);
+    }
+
+    /// Effective field B_eff of every moment at time `t`
+    pub fn effective_field(&self, m: &VectorField, t: f64) -> VectorField {
+        let mut h = VectorField::zeros(m.len());
+        match self.sublattice {
+            Some(s) => s.effective_field_into(self.terms, m, t, self.mesh, self.params, &mut h),
+            None => self
+                .terms
+                .effective_field_into(m, t, self.mesh, self.params, &mut h),
+        }
+        h
+    }
+
+    /// Turn the effective field `h` of `m`, with parameters `p`, into dm/dt
+    /// in place
+    pub(crate) fn field_to_rhs(&self, m: &VectorField, t: f64, p: &Params, h: &mut VectorField) {
+        let precession = self.precession;
+        let coefs = move |gamma: f64, alpha: f64| {
+            if precession {
+                let pref = -gamma / (1.0 + alpha * alpha);
+                (pref, pref * alpha)
+            } else {
+                (0.0, -gamma)
+            }
+        };
+        if let Some(noise) = self.noise {
+            h.axpy(1.0, noise);
+        }
+        if let Some(llb) = self.llb {
+            llb.field_to_rhs(m, t, p, h);
+        } else if p.is_uniform() {
+            let c = coefs(p.gamma, p.alpha);
+            cross_terms(m, |_| c, h);
+        } else {
+            cross_terms(m, |i| coefs(p.gamma_at(i), p.alpha_at(i)), h);
+        }
+        self.add_torques(m, t, p, h);
+    }
+
+    /// Add the spin torques to `dmdt`, then hold the frozen cells
+    fn add_torques(&self, m: &VectorField, t: f64, p: &Params, dmdt: &mut VectorField) {
+        let start = profile::start();
+        self.torques.add_torque(m, t, self.mesh, p, dmdt);
+        profile::record(Section::Field, "spin torques", start);
+        hold_frozen(p, dmdt);
+    }
+}
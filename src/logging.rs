@@ -0,0 +1,77 @@
+//! Log messages through `tracing`: stage timings, step-size changes, I/O
+//! durations and warnings about the accuracy of a run. They go to stderr
+//! and, once [`log_to`] names one, to a file besides the store, e.g.
+//! `run.log` next to `run.zarr` with `output.log = true`.
+//!
+//! Only warnings are shown by default; [`init`] raises the level, and the
+//! `RUST_LOG` variable overrides it with filter directives such as
+//! `nez=debug`.
+
+use std::{
+    fs::{File, OpenOptions},
+    io::{self, IsTerminal, Write},
+    path::Path,
+    sync::Mutex,
+};
+
+use tracing_subscriber::{EnvFilter, fmt, prelude::*};
+
+use crate::Result;
+
+/// Log file of the current run, if any
+static FILE: Mutex<Option<File>> = Mutex::new(None);
+
+/// Install the logger: warnings for `verbosity` 0, then info, debug and
+/// trace messages of nez as it grows
+pub fn init(verbosity: u8) {
+    let level = match verbosity {
+        0 => "warn",
+        1 => "info",
+        2 => "debug",
+        _ => "trace",
+    };
+    let filter = EnvFilter::try_from_default_env()
+        .unwrap_or_else(|_| EnvFilter::new(format!("warn,nez={level}")));
+    let stderr = fmt::layer()
+        .with_ansi(io::stderr().is_terminal())
+        .with_writer(io::stderr);
+    let file = fmt::layer().with_ansi(false).with_writer(|| LogFile);
+    // a second call keeps the first logger
+    let _ = tracing_subscriber::registry()
+        .with(filter)
+        .with(stderr)
+        .with(file)
+        .try_init();
+}
+
+/// Also write the log to `path`, after what it holds if `append`
+pub fn log_to(path: &Path, append: bool) -> Result<()> {
+    let file = OpenOptions::new()
+        .create(true)
+        .write(true)
+        .append(append)
+        .truncate(!append)
+        .open(path)
+        .map_err(|e| format!("cannot open {}: {e}", path.display()))?;
+    *FILE.lock().unwrap_or_else(|e| e.into_inner()) = Some(file);
+    Ok(())
+}
+
+/// Writer of the file layer, discarding the messages while there is no file
+struct LogFile;
+
+impl Write for LogFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match FILE.lock().unwrap_or_else(|e| e.into_inner()).as_mut() {
+            Some(file) => file.write(buf),
+            None => Ok(buf.len()),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match FILE.lock().unwrap_or_else(|e| e.into_inner()).as_mut() {
+            Some(file) => file.flush(),
+            None => Ok(()),
+        }
+    }
+}
@@ -1,173 +1,1424 @@
-use nalgebra::Vector3;
+use clap::{Parser, Subcommand};
+use std::{
+    io::Write,
+    path::{Path, PathBuf},
+    process::ExitCode,
+    sync::{
+        Mutex,
+        atomic::{AtomicUsize, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
 use rayon::prelude::*;
-use std::{fs, sync::Arc};
 
-// ---- Zarr stuff -----------------------------------------------------------
-use zarrs::{
-    array::{
-        ArrayBuilder, DataType, FillValue, codec::array_to_bytes::sharding::ShardingCodecBuilder,
-        codec::bytes_to_bytes::gzip::GzipCodec,
-    },
-    array_subset::ArraySubset,
-    filesystem::FilesystemStore,
-    group::GroupBuilder,
-    storage::ReadableWritableListableStorage, // <- correct path
+use nez::{
+    Config, Simulation, VectorField, ZarrGroup, ZarrOutput,
+    config::RunConfig,
+    dispersion::{Axis, Component},
+    ensemble::Statistics,
+    interrupt::{self, Walltime},
+    minimize::Relaxed,
+    monte_carlo::MonteCarlo,
+    output::Precision,
+    parallel::{self, ParallelConfig},
+    profile,
+    progress::{Progress, Target},
+    script::Script,
+    snapshot::{self, Encoding, Format},
+    spectrum::{Spectrum, Window},
+    stages::StageKind,
 };
-// ---------------------------------------------------------------------------
-
-/// ---------------- Simulation parameters ----------------
-const N_SPINS: usize = 128; // chain length
-const D: f64 = 2.5e-9; // spacing (m)
-const GAMMA: f64 = 1.760_859e11; // rad s⁻¹ T⁻¹
-const ALPHA: f64 = 0.2; // damping
-const A_EX: f64 = 1.3e-11; // exchange stiffness (J m⁻¹)
-const MU0_MS: f64 = 4.0 * std::f64::consts::PI * 1.0e5; // μ₀Mₛ (≈ 1 T)
-
-const DT: f64 = 1e-14; // time-step (s)
-const N_STEPS: u64 = 50; // #time-steps
-
-/// external field (constant here)
-const H_EXT: Vector3<f64> = Vector3::new(0.0, 0.0, 1.0); // Tesla
-
-/// LLG right-hand side for a single spin
-#[inline(always)]
-fn llg_rhs(m: &Vector3<f64>, h_eff: &Vector3<f64>) -> Vector3<f64> {
-    let mxh = m.cross(h_eff);
-    let mxmxh = m.cross(&mxh);
-    let pref = -GAMMA / (1.0 + ALPHA * ALPHA);
-    pref * (mxh + ALPHA * mxmxh)
-}
-
-/// Exchange field at site *i* (free boundaries)
-fn exchange_field(chain: &[Vector3<f64>], i: usize) -> Vector3<f64> {
-    let m_i = chain[i];
-    let m_ip1 = if i + 1 < chain.len() {
-        chain[i + 1]
+
+/// Spin-dynamics solver writing Zarr output
+#[derive(Parser)]
+#[command(version, about)]
+struct Cli {
+    /// Number of worker threads (default: `parallel.threads` of the config,
+    /// one per available core)
+    #[arg(short = 'j', long, global = true)]
+    threads: Option<usize>,
+
+    /// Print neither the rows every command writes as it goes nor the
+    /// progress bar, e.g. in batch jobs
+    #[arg(short, long, global = true)]
+    quiet: bool,
+
+    /// Log more: -v for timings, -vv for I/O and rejected steps, -vvv for
+    /// every change of step size (see `nez::logging`)
+    #[arg(short, long, global = true, action = clap::ArgAction::Count)]
+    verbose: u8,
+
+    /// Print the wall time spent in every field term, solver stage and kind
+    /// of output at the end (see `nez::profile`)
+    #[arg(long, global = true)]
+    profile: bool,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Run a simulation described by a TOML config
+    Run {
+        config: PathBuf,
+        /// Override `output.path`
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        /// Override `run.steps`
+        #[arg(short = 'n', long)]
+        steps: Option<u64>,
+        /// Plot the average magnetization and the energy on the terminal as
+        /// the run goes
+        #[arg(long, conflicts_with = "quiet")]
+        dashboard: bool,
+        /// Serve a live view of the run to browsers at this address (see
+        /// `nez::serve`)
+        #[arg(long, value_name = "ADDR", num_args = 0..=1,
+              default_missing_value = "127.0.0.1:35367")]
+        serve: Option<String>,
+        /// Wall-clock budget of the job, e.g. 23h30m or 23:30:00: checkpoint
+        /// and exit shortly before it runs out, printing the command that
+        /// continues the run
+        #[arg(long)]
+        walltime: Option<Walltime>,
+        /// Check the config and report the size of the mesh, the memory and
+        /// output the run would take and its speed over a few steps, writing
+        /// nothing
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Drive the simulation of a TOML config with a control script (see
+    /// `nez::script`) instead of `run.steps`
+    Script {
+        config: PathBuf,
+        script: PathBuf,
+        /// Override `output.path`
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Print the config of a standard problem, or list them without a name
+    /// (see `nez::presets`)
+    Preset { name: Option<String> },
+    /// Translate a mumax3 input script into a TOML config and a control
+    /// script for `nez script`, written next to it (see `nez::mx3`)
+    Import { script: PathBuf },
+    /// Relax the initial state of a TOML config to an energy minimum and
+    /// store both states
+    Minimize {
+        config: PathBuf,
+        /// Override `output.path`
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Relax the initial state of a TOML config with damping-only dynamics
+    /// and store both states
+    Relax {
+        config: PathBuf,
+        /// Override `output.path`
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Find the minimum energy path between two stored states with the
+    /// geodesic nudged elastic band method
+    Gneb {
+        config: PathBuf,
+        /// Store holding the initial state
+        start: PathBuf,
+        /// Store holding the final state
+        end: PathBuf,
+        /// Snapshot of `start` to use (default: the last one)
+        #[arg(long)]
+        start_frame: Option<u64>,
+        /// Snapshot of `end` to use (default: the last one)
+        #[arg(long)]
+        end_frame: Option<u64>,
+        /// Override `output.path`
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Sweep the applied field through a hysteresis loop, relaxing at every
+    /// field, and write the B–M table to `hysteresis.table`
+    Hysteresis {
+        config: PathBuf,
+        /// Override `output.path`
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Sample thermal equilibrium at `monte_carlo.temperatures` with
+    /// Metropolis Monte Carlo, storing the final state at each temperature
+    #[command(alias = "mc")]
+    MonteCarlo {
+        config: PathBuf,
+        /// Override `output.path`
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Export the snapshots of a store to other file formats, one file per
+    /// snapshot
+    Convert {
+        store: PathBuf,
+        #[arg(long, value_enum, default_value_t = Format::Ovf)]
+        format: Format,
+        /// Encoding of the data
+        #[arg(long, value_enum, default_value_t = Encoding::Binary4)]
+        data: Encoding,
+        /// Convert only this snapshot (default: all)
+        #[arg(long)]
+        frame: Option<u64>,
+        /// Directory for the files (default: the store path without extension,
+        /// as for `output.snapshots`)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Continue an interrupted run from its latest checkpoint or snapshot
+    Resume {
+        store: PathBuf,
+        /// Run until this step instead of the recorded `run.steps`, e.g. to
+        /// extend a finished run
+        #[arg(short = 'n', long)]
+        steps: Option<u64>,
+        /// Wall-clock budget of the job, as for `nez run`
+        #[arg(long)]
+        walltime: Option<Walltime>,
+    },
+    /// Run a TOML config once for every combination of the values of
+    /// `sweep.parameters`, each case into its own group of one store (see
+    /// `nez::sweep`)
+    Sweep {
+        config: PathBuf,
+        /// Override `output.path`
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Run a TOML config with thermal noise once per seed of an ensemble of
+    /// realizations, into groups of one store with the statistics of their
+    /// tables (see `nez::ensemble`)
+    Ensemble {
+        config: PathBuf,
+        /// Override `output.path`
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Print a summary of a Zarr output
+    Info { store: PathBuf },
+    /// Analyse the snapshots of a store
+    Analyze {
+        #[command(subcommand)]
+        analysis: Analysis,
+    },
+}
+
+#[derive(Subcommand)]
+enum Analysis {
+    /// Spin-wave dispersion |m(k, f)|², stored in the group `dispersion` of
+    /// the store (see `nez::dispersion`)
+    Dispersion {
+        store: PathBuf,
+        /// Direction of propagation
+        #[arg(long, value_enum, default_value_t = Axis::X)]
+        axis: Axis,
+        /// Component of m
+        #[arg(long, value_enum, default_value_t = Component::Z)]
+        component: Component,
+        /// Also write the map as a PNG image
+        #[arg(long)]
+        image: Option<PathBuf>,
+    },
+    /// FMR absorption spectra of a ringdown, of ⟨m⟩ and of every cell,
+    /// stored in the group `fmr` of the store and the first printed (see
+    /// `nez::spectrum`)
+    Fmr {
+        store: PathBuf,
+        /// Window applied before the Fourier transform
+        #[arg(long, value_enum, default_value_t = Window::Hann)]
+        window: Window,
+        /// Skip the spectra of every cell
+        #[arg(long)]
+        no_cells: bool,
+    },
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    match run_cli(cli) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+impl Command {
+    /// The config this command runs, if it takes one
+    fn config(&self) -> Option<&Path> {
+        match self {
+            Self::Run { config, .. }
+            | Self::Script { config, .. }
+            | Self::Minimize { config, .. }
+            | Self::Relax { config, .. }
+            | Self::Gneb { config, .. }
+            | Self::Hysteresis { config, .. }
+            | Self::MonteCarlo { config, .. }
+            | Self::Sweep { config, .. }
+            | Self::Ensemble { config, .. } => Some(config),
+            _ => None,
+        }
+    }
+
+    /// The `[parallel]` settings of the config of this command, that
+    /// recorded in the store when resuming
+    fn parallel(&self) -> nez::Result<ParallelConfig> {
+        if let Some(config) = self.config() {
+            return Ok(Config::load(config)?.parallel);
+        }
+        match self {
+            Self::Resume { store, .. } => Ok(ZarrOutput::open(store)?.config()?.parallel),
+            _ => Ok(ParallelConfig::default()),
+        }
+    }
+}
+
+fn run_cli(cli: Cli) -> nez::Result<()> {
+    nez::logging::init(cli.verbose);
+    let settings = cli.command.parallel()?;
+    parallel::set_chunk(settings.chunk);
+    if cli.profile {
+        profile::enable();
+    }
+    let result = settings.pool(cli.threads)?.install(|| run_command(cli));
+    if let Some(report) = profile::report() {
+        eprint!("{report}");
+    }
+    result
+}
+
+/// Carry out the command of `cli` in the current thread pool
+fn run_command(cli: Cli) -> nez::Result<()> {
+    let mut progress = Progress::new(cli.quiet);
+
+    match cli.command {
+        Command::Run {
+            config,
+            output,
+            steps,
+            dashboard,
+            serve,
+            walltime,
+            dry_run,
+        } => {
+            if let Some(walltime) = &walltime {
+                walltime.start(Instant::now());
+            }
+            let mut config = Config::load(config)?;
+            if let Some(path) = output {
+                config.output.path = path;
+            }
+            if let Some(n) = steps {
+                if !config.stages.is_empty() {
+                    return Err("`--steps` does not apply to a run in `[[stages]]`".into());
+                }
+                config.run.steps = n;
+            }
+            if dry_run {
+                return estimate(&config);
+            }
+            let mut sim = Simulation::from_config(&config)?;
+            sim.start_table(&config.table)?;
+            sim.autosave()?;
+            if dashboard {
+                progress.show_dashboard()?;
+            }
+            if let Some(addr) = serve {
+                progress.serve(&addr)?;
+            }
+            print_header(&mut progress);
+            print_status(&sim, &mut progress);
+            interrupt::install();
+            if config.stages.is_empty() {
+                time_loop(&mut sim, &config, &mut progress)?;
+                interrupted(&sim, &config, &config.output.path, walltime)
+            } else {
+                // runs in stages cannot be resumed
+                stages(&mut sim, &config, &mut progress)?;
+                interrupted(&sim, &config, &config.output.path, None)
+            }
+        }
+        Command::Script {
+            config,
+            script,
+            output,
+        } => {
+            let mut config = Config::load(config)?;
+            if let Some(path) = output {
+                config.output.path = path;
+            }
+            let script = Script::load(script)?;
+            let mut sim = Simulation::from_config(&config)?;
+            sim.start_table(&config.table)?;
+            sim.autosave()?;
+            if progress.is_quiet() {
+                script.run(&mut sim, &config, &mut std::io::sink())?;
+            } else {
+                script.run(&mut sim, &config, &mut std::io::stdout())?;
+            }
+            sim.checkpoint()
+        }
+        Command::Import { script } => import(&script),
+        Command::Minimize { config, output } => {
+            relax_initial(config, output, &mut progress, |sim, config| {
+                sim.minimize(&config.minimize)
+            })
+        }
+        Command::Relax { config, output } => {
+            relax_initial(config, output, &mut progress, |sim, config| {
+                sim.relax(&config.relax)
+            })
+        }
+        Command::Gneb {
+            config,
+            start,
+            end,
+            start_frame,
+            end_frame,
+            output,
+        } => {
+            let mut config = Config::load(config)?;
+            if let Some(path) = output {
+                config.output.path = path;
+            }
+            gneb(
+                &config,
+                (&start, start_frame),
+                (&end, end_frame),
+                &mut progress,
+            )
+        }
+        Command::Hysteresis { config, output } => {
+            let mut config = Config::load(config)?;
+            if let Some(path) = output {
+                config.output.path = path;
+            }
+            hysteresis(&config, &mut progress)
+        }
+        Command::MonteCarlo { config, output } => {
+            let mut config = Config::load(config)?;
+            if let Some(path) = output {
+                config.output.path = path;
+            }
+            monte_carlo(&config, &mut progress)
+        }
+        Command::Resume {
+            store,
+            steps,
+            walltime,
+        } => {
+            if let Some(walltime) = &walltime {
+                walltime.start(Instant::now());
+            }
+            let (mut sim, mut config) = Simulation::resume(&store)?;
+            if !config.stages.is_empty() {
+                return Err("`nez resume` does not support `[[stages]]`".into());
+            }
+            if let Some(n) = steps {
+                config.run.steps = n;
+            }
+            progress.println(format!(
+                "resuming {} at step {}/{}",
+                store.display(),
+                sim.step,
+                config.run.steps
+            ));
+            sim.start_table(&config.table)?;
+            print_header(&mut progress);
+            interrupt::install();
+            time_loop(&mut sim, &config, &mut progress)?;
+            interrupted(&sim, &config, &store, walltime)
+        }
+        Command::Sweep { config, output } => sweep(&config, output, &mut progress),
+        Command::Ensemble { config, output } => ensemble(&config, output, &mut progress),
+        Command::Convert {
+            store,
+            format,
+            data,
+            frame,
+            output,
+        } => {
+            let output = output.unwrap_or_else(|| snapshot::default_dir(&store));
+            convert(&store, format, data, frame, &output)
+        }
+        Command::Info { store } => info(&store),
+        Command::Preset { name } => preset(name.as_deref()),
+        Command::Analyze {
+            analysis:
+                Analysis::Dispersion {
+                    store,
+                    axis,
+                    component,
+                    image,
+                },
+        } => dispersion(&store, axis, component, image.as_deref()),
+        Command::Analyze {
+            analysis:
+                Analysis::Fmr {
+                    store,
+                    window,
+                    no_cells,
+                },
+        } => fmr(&store, window, !no_cells),
+    }
+}
+
+/// Print the TOML of preset `name`, or the names and descriptions of all
+fn preset(name: Option<&str>) -> nez::Result<()> {
+    match name {
+        Some(name) => {
+            let toml = nez::presets::get(name).ok_or_else(|| format!("no preset `{name}`"))?;
+            print!("{toml}");
+        }
+        None => {
+            for (name, about, _) in nez::presets::PRESETS {
+                println!("{name:<8}{about}");
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Write `script.toml` and `script.nez` translated from the mumax3 `script`
+fn import(script: &std::path::Path) -> nez::Result<()> {
+    let text = std::fs::read_to_string(script)
+        .map_err(|e| format!("cannot read {}: {e}", script.display()))?;
+    let import = nez::mx3::translate(&text).map_err(|e| format!("{}: {e}", script.display()))?;
+    let mut config = import.config;
+    let name = script.file_stem().unwrap_or_default();
+    config.output.path = PathBuf::from(name).with_extension("zarr");
+    let (toml, control) = (script.with_extension("toml"), script.with_extension("nez"));
+    std::fs::write(&toml, toml::to_string(&config)?)?;
+    std::fs::write(&control, import.script)?;
+    for note in &import.notes {
+        eprintln!("note: {note}");
+    }
+    println!(
+        "wrote {} and {}, run with `nez script {0} {1}`",
+        toml.display(),
+        control.display()
+    );
+    Ok(())
+}
+
+/// Bring the initial state of `config` to rest with `relax`, storing the
+/// state before and after
+fn relax_initial(
+    config: PathBuf,
+    output: Option<PathBuf>,
+    progress: &mut Progress,
+    relax: impl FnOnce(&mut Simulation, &Config) -> Relaxed,
+) -> nez::Result<()> {
+    let mut config = Config::load(config)?;
+    if let Some(path) = output {
+        config.output.path = path;
+    }
+    let mut sim = Simulation::from_config(&config)?;
+    sim.save()?;
+    print_header(progress);
+    print_status(&sim, progress);
+    let start = Instant::now();
+    let result = relax(&mut sim, &config);
+    tracing::info!(
+        iterations = result.iterations,
+        elapsed = ?start.elapsed(),
+        "relaxation finished"
+    );
+    sim.save()?;
+    print_status(&sim, progress);
+    println!(
+        "{} after {} iterations, max torque {:.3e} T",
+        if result.converged {
+            "converged"
+        } else {
+            "not converged"
+        },
+        result.iterations,
+        result.torque
+    );
+    Ok(())
+}
+
+/// Steps timed by `nez run --dry-run`, after one that sets up the solver
+const DRY_RUN_STEPS: u64 = 20;
+
+/// Report what the run of `config` would take, from a simulation built
+/// without output and a few steps of it: the mesh, the memory, the size of
+/// the store and files and the speed
+fn estimate(config: &Config) -> nez::Result<()> {
+    let mut quiet = config.clone();
+    quiet.output.snapshots = None;
+    let mut sim = Simulation::build(&quiet)?;
+    let m = &config.mesh;
+    println!("config: valid, writing to {}", config.output.path.display());
+    println!(
+        "mesh:   {} x {} x {} = {} cells of {:e} x {:e} x {:e} m",
+        m.nx,
+        m.ny,
+        m.nz,
+        m.len(),
+        m.dx,
+        m.dy,
+        m.dz
+    );
+
+    // steps of the run, unknown for stages of a set duration
+    let steps = if config.stages.is_empty() {
+        Some(config.run.steps)
     } else {
-        chain[i]
+        config
+            .stages
+            .iter()
+            .map(|s| match s.kind {
+                StageKind::Run => s.steps,
+                _ => Some(0),
+            })
+            .sum::<Option<u64>>()
     };
-    let m_im1 = if i > 0 { chain[i - 1] } else { chain[i] };
-    let lap = m_ip1 - 2.0 * m_i + m_im1;
-    (2.0 * A_EX / MU0_MS) * lap / (D * D)
-}
-
-/// One RK4 step for the whole chain
-fn rk4_step(chain: &[Vector3<f64>]) -> Vec<Vector3<f64>> {
-    // k1
-    let k1: Vec<_> = chain
-        .par_iter()
-        .enumerate()
-        .map(|(i, m)| llg_rhs(m, &(H_EXT + exchange_field(chain, i))))
-        .collect();
+    let values = 3 * sim.m.len() as u64;
+    let frame = values
+        * match config.output.precision {
+            Precision::F64 => 8,
+            Precision::F32 => 4,
+            Precision::I16 => 2,
+        };
+    match steps {
+        Some(steps) => {
+            let frames = steps / config.output.every + 1 + config.stages.len() as u64;
+            let rows = steps / config.table.every + 1;
+            let table = if config.table.zarr {
+                rows * 8 * sim.observables().len() as u64
+            } else {
+                0
+            };
+            // both slots of the checkpoint, always in f64
+            let checkpoint = 2 * values * 8;
+            println!(
+                "store:  {frames} frames of {} and a table of {rows} rows, {} before \
+                 compression",
+                bytes(frame),
+                bytes(frames * frame + table + checkpoint)
+            );
+            if let Some(s) = &config.output.snapshots {
+                let file = values
+                    * match s.data {
+                        Encoding::Binary4 => 4,
+                        Encoding::Binary8 => 8,
+                        Encoding::Text => 20,
+                    };
+                let files = steps / s.every + 1;
+                println!(
+                    "files:  {files} {} snapshots, about {}",
+                    format!("{:?}", s.format).to_lowercase(),
+                    bytes(files * file)
+                );
+            }
+            if let Some(f) = &config.output.fields {
+                let size = match config.output.precision {
+                    Precision::F64 => 8,
+                    Precision::F32 | Precision::I16 => 4,
+                };
+                let arrays = f.arrays();
+                let frame: u64 = arrays
+                    .iter()
+                    .map(|(_, vectors)| if *vectors { 3 } else { 1 } * m.len() as u64 * size)
+                    .sum();
+                let names: Vec<_> = arrays.iter().map(|(name, _)| name.as_str()).collect();
+                let frames = steps / f.every + 1;
+                println!(
+                    "fields: {frames} frames of {}, {} before compression",
+                    names.join(", "),
+                    bytes(frames * frame)
+                );
+            }
+        }
+        None => println!(
+            "store:  {} per frame of m, stages of a set duration take an unknown number",
+            bytes(frame)
+        ),
+    }
+
+    sim.run(1)?;
+    let start = Instant::now();
+    let mut timed = 0;
+    while timed < DRY_RUN_STEPS && start.elapsed().as_secs_f64() < 2.0 {
+        sim.run(1)?;
+        timed += 1;
+    }
+    let rate = timed as f64 / start.elapsed().as_secs_f64();
+    match peak_memory() {
+        Some(peak) => println!(
+            "memory: m {}, {} at most in all",
+            bytes(values * size_of::<nez::vector_field::Real>() as u64),
+            bytes(peak)
+        ),
+        None => println!(
+            "memory: m {}",
+            bytes(values * size_of::<nez::vector_field::Real>() as u64)
+        ),
+    }
+    let mut speed = format!(
+        "speed:  {rate:.1} steps/s with {}, dt = {:.3e} s",
+        sim.stepper.name(),
+        sim.params.dt
+    );
+    if let Some(steps) = steps {
+        let time = Duration::from_secs_f64(steps as f64 / rate);
+        speed += &format!(", {steps} steps in about {time:.1?}");
+    }
+    println!("{speed}");
+    Ok(())
+}
+
+/// Largest resident memory of the process so far (Linux only)
+fn peak_memory() -> Option<u64> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|l| l.starts_with("VmHWM:"))?;
+    let kib: u64 = line.split_whitespace().nth(1)?.parse().ok()?;
+    Some(kib * 1024)
+}
+
+/// `n` bytes in B, KiB, MiB, GiB or TiB
+fn bytes(n: u64) -> String {
+    let mut size = n as f64;
+    for unit in ["B", "KiB", "MiB", "GiB"] {
+        if size < 1024.0 {
+            return format!("{size:.1} {unit}");
+        }
+        size /= 1024.0;
+    }
+    format!("{size:.1} TiB")
+}
+
+/// Run every case of the sweep of the config at `path` into a group of the
+/// store at `output.path`, `sweep.parallel` at a time, printing a line as
+/// each ends. A failed case does not stop the others.
+fn sweep(path: &Path, output: Option<PathBuf>, progress: &mut Progress) -> nez::Result<()> {
+    let mut config = Config::load(path)?;
+    if let Some(path) = output {
+        config.output.path = path;
+    }
+    if config.sweep.parameters.is_empty() {
+        return Err("`nez sweep` needs at least one `[[sweep.parameters]]`".into());
+    }
+    let cases = nez::sweep::cases(path, &config)?;
+    let keys: Vec<_> = config.sweep.parameters.iter().map(|p| &p.key).collect();
+    let names: Vec<_> = cases.iter().map(|c| &c.name).collect();
+    let values: Vec<_> = cases.iter().map(|c| &c.values).collect();
+    let mut attributes = serde_json::Map::new();
+    attributes.insert("parameters".into(), serde_json::to_value(&keys)?);
+    attributes.insert("cases".into(), serde_json::to_value(&names)?);
+    attributes.insert("values".into(), serde_json::to_value(&values)?);
+    let store = ZarrGroup::create(&config.output.path, attributes)?;
+    let (columns, index) = nez::sweep::index(&config.sweep, &cases);
+    let mut attributes = serde_json::Map::new();
+    attributes.insert("columns".into(), serde_json::to_value(&columns)?);
+    store.write_dataset(
+        "/index",
+        &[cases.len() as u64, columns.len() as u64],
+        &["case", "column"],
+        &index,
+        attributes,
+    )?;
 
-    // k2
-    let tmp: Vec<_> = chain
+    progress.println(format!(
+        "# {} cases into {}, {} at a time",
+        cases.len(),
+        config.output.path.display(),
+        config.sweep.parallel
+    ));
+    let jobs: Vec<_> = cases
         .iter()
-        .zip(&k1)
-        .map(|(m, k)| m + 0.5 * DT * (*k))
-        .collect();
-    let k2: Vec<_> = tmp
-        .par_iter()
-        .enumerate()
-        .map(|(i, m)| llg_rhs(m, &(H_EXT + exchange_field(&tmp, i))))
+        .map(|c| Job {
+            name: &c.name,
+            label: c.label(&config.sweep),
+            config: &c.config,
+        })
         .collect();
+    let outcomes = run_jobs(&jobs, config.sweep.parallel, progress);
+    check_jobs(&outcomes, "cases").map(drop)
+}
+
+/// Run the realizations of the ensemble of the config at `path` into groups
+/// of the store at `output.path`, `ensemble.parallel` at a time, and store
+/// the statistics of their tables (see `nez::ensemble`)
+fn ensemble(path: &Path, output: Option<PathBuf>, progress: &mut Progress) -> nez::Result<()> {
+    let mut config = Config::load(path)?;
+    if let Some(path) = output {
+        config.output.path = path;
+    }
+    let runs = nez::ensemble::realizations(&config)?;
+    let seeds: Vec<_> = runs.iter().map(|r| r.seed).collect();
+    let mut attributes = serde_json::Map::new();
+    attributes.insert("realizations".into(), runs.len().into());
+    attributes.insert("seeds".into(), serde_json::to_value(&seeds)?);
+    let store = ZarrGroup::create(&config.output.path, attributes)?;
 
-    // k3
-    let tmp: Vec<_> = chain
+    progress.println(format!(
+        "# {} realizations into {}, {} at a time",
+        runs.len(),
+        config.output.path.display(),
+        config.ensemble.parallel
+    ));
+    let jobs: Vec<_> = runs
         .iter()
-        .zip(&k2)
-        .map(|(m, k)| m + 0.5 * DT * (*k))
-        .collect();
-    let k3: Vec<_> = tmp
-        .par_iter()
-        .enumerate()
-        .map(|(i, m)| llg_rhs(m, &(H_EXT + exchange_field(&tmp, i))))
+        .map(|r| Job {
+            name: &r.name,
+            label: format!("seed {}", r.seed),
+            config: &r.config,
+        })
         .collect();
+    let outcomes = run_jobs(&jobs, config.ensemble.parallel, progress);
+    let finished = check_jobs(&outcomes, "realizations")?;
 
-    // k4
-    let tmp: Vec<_> = chain.iter().zip(&k3).map(|(m, k)| m + DT * (*k)).collect();
-    let k4: Vec<_> = tmp
-        .par_iter()
-        .enumerate()
-        .map(|(i, m)| llg_rhs(m, &(H_EXT + exchange_field(&tmp, i))))
+    let mut tables = Vec::with_capacity(runs.len());
+    let mut names = Vec::new();
+    for run in &runs {
+        let output = ZarrOutput::open(&run.config.output.path)?;
+        names = output.table_columns()?.unwrap_or_default();
+        let columns = (0..names.len())
+            .map(|c| output.table_column(c))
+            .collect::<nez::Result<Vec<_>>>()?;
+        tables.push(columns);
+    }
+    let statistics = Statistics::of(&tables, names.len());
+    let (rows, columns) = (statistics.rows as u64, names.len() as u64);
+    let labelled = || -> nez::Result<_> {
+        let mut attributes = serde_json::Map::new();
+        attributes.insert("columns".into(), serde_json::to_value(&names)?);
+        Ok(attributes)
+    };
+    let dims = ["row", "column"];
+    store.write_dataset(
+        "/mean",
+        &[rows, columns],
+        &dims,
+        &statistics.mean,
+        labelled()?,
+    )?;
+    store.write_dataset(
+        "/std",
+        &[rows, columns],
+        &dims,
+        &statistics.std,
+        labelled()?,
+    )?;
+    store.write_dataset(
+        "/count",
+        &[rows],
+        &["row"],
+        &statistics.count,
+        Default::default(),
+    )?;
+    let last: Vec<f64> = tables
+        .iter()
+        .flat_map(|t| t.iter().map(|c| c.last().copied().unwrap_or(f64::NAN)))
         .collect();
+    store.write_dataset(
+        "/final",
+        &[runs.len() as u64, columns],
+        &["realization", "column"],
+        &last,
+        labelled()?,
+    )?;
 
-    // final update + renormalise
-    chain
-        .iter()
-        .zip(&k1)
-        .zip(&k2)
-        .zip(&k3)
-        .zip(&k4)
-        .map(|((((m, k1), k2), k3), k4)| {
-            let next = *m + (DT / 6.0) * (*k1 + 2.0 * (*k2) + 2.0 * (*k3) + *k4);
-            next.normalize()
-        })
-        .collect()
+    let stopped: Vec<_> = finished.iter().map(|f| f.stopped.clone()).collect();
+    let fraction = stopped.iter().flatten().count() as f64 / runs.len() as f64;
+    store.write_attribute("stopped", serde_json::to_value(&stopped)?)?;
+    store.write_attribute("stopped_fraction", fraction.into())?;
+    progress.println(format!(
+        "# {} of {} realizations stopped early, a fraction of {fraction:.3}",
+        stopped.iter().flatten().count(),
+        runs.len()
+    ));
+    Ok(())
+}
+
+/// A run of `nez sweep` or `nez ensemble`
+struct Job<'a> {
+    /// name of its group in the store
+    name: &'a str,
+    /// what sets it apart, printed as it ends
+    label: String,
+    config: &'a Config,
 }
 
-fn main() -> Result<(), Box<dyn std::error::Error>> {
-    // ---------- initial state: small tilt ----------
-    let tilt = 10f64.to_radians();
-    let mut chain = vec![Vector3::new(tilt.sin(), 0.0, tilt.cos()); N_SPINS];
+/// How a [`Job`] ended
+struct Finished {
+    step: u64,
+    t: f64,
+    /// the `run.stop_*` condition on the state that ended it early, if any
+    stopped: Option<String>,
+}
 
-    // ---------- create Zarr store + dataset ----------
-    let store_path = "magnetization.zarr";
+/// Run `jobs` as `nez run` would, `parallel` at a time, printing a line as
+/// each ends. A failed job does not stop the others. Returns how each
+/// ended, none for those a signal kept from starting.
+fn run_jobs(
+    jobs: &[Job],
+    parallel: usize,
+    progress: &mut Progress,
+) -> Vec<Option<Result<Finished, String>>> {
+    interrupt::install();
+    let next = AtomicUsize::new(0);
+    let outcomes = Mutex::new((0..jobs.len()).map(|_| None).collect::<Vec<_>>());
+    let progress = Mutex::new(progress);
+    (0..parallel.min(jobs.len())).into_par_iter().for_each(|_| {
+        while !interrupt::requested() {
+            let k = next.fetch_add(1, Ordering::Relaxed);
+            let Some(job) = jobs.get(k) else {
+                break;
+            };
+            let start = Instant::now();
+            let outcome = run_job(job.config).map_err(|e| e.to_string());
+            let line = match &outcome {
+                Ok(f) => format!(
+                    "{}: {}: {} steps, t = {:.3e} s, in {:.1?}{}",
+                    job.name,
+                    job.label,
+                    f.step,
+                    f.t,
+                    start.elapsed(),
+                    f.stopped
+                        .as_ref()
+                        .map_or(String::new(), |r| format!(", stopped: {r}"))
+                ),
+                Err(e) => format!("{}: {}: failed: {e}", job.name, job.label),
+            };
+            if let Ok(mut progress) = progress.lock() {
+                progress.println(line);
+            }
+            if let Ok(mut outcomes) = outcomes.lock() {
+                outcomes[k] = Some(outcome);
+            }
+        }
+    });
+    outcomes.into_inner().unwrap_or_default()
+}
 
-    // If the folder already exists, delete it first
-    if std::path::Path::new(store_path).exists() {
-        fs::remove_dir_all(store_path)?;
+/// The ends of jobs run by [`run_jobs`], or an error if a signal stopped
+/// them or any failed
+fn check_jobs<'a>(
+    outcomes: &'a [Option<Result<Finished, String>>],
+    what: &str,
+) -> nez::Result<Vec<&'a Finished>> {
+    let started = outcomes.iter().flatten().count();
+    if interrupt::requested() {
+        return Err(format!(
+            "interrupted with {started} of {} {what} started, after writing their checkpoints",
+            outcomes.len()
+        )
+        .into());
+    }
+    let failed = outcomes.iter().flatten().filter(|o| o.is_err()).count();
+    if failed > 0 {
+        return Err(format!("{failed} of {} {what} failed", outcomes.len()).into());
     }
+    Ok(outcomes.iter().flatten().flatten().collect())
+}
 
-    let store: ReadableWritableListableStorage = Arc::new(FilesystemStore::new(store_path)?);
+/// Run the config of a job as `nez run` would, printing nothing
+fn run_job(config: &Config) -> nez::Result<Finished> {
+    let mut progress = Progress::new(true);
+    let start = Instant::now();
+    let mut sim = Simulation::from_config(config)?;
+    sim.start_table(&config.table)?;
+    sim.autosave()?;
+    if config.stages.is_empty() {
+        time_loop(&mut sim, config, &mut progress)?;
+    } else {
+        stages(&mut sim, config, &mut progress)?;
+    }
+    // only the conditions on the state, not on the time taken
+    let state = RunConfig {
+        stop_time: None,
+        stop_wall: None,
+        ..config.run.clone()
+    };
+    Ok(Finished {
+        step: sim.step,
+        t: sim.t,
+        stopped: sim.stop_reached(&state, start)?,
+    })
+}
 
-    // root group
-    GroupBuilder::new()
-        .build(store.clone(), "/")?
-        .store_metadata()?;
+/// Step until `run.steps`, printing the main observables every
+/// `run.print_every` steps, and checkpoint at the end; stop early once a
+/// `run.stop_*` condition is met
+fn time_loop(sim: &mut Simulation, config: &Config, progress: &mut Progress) -> nez::Result<()> {
+    let steps = config.run.steps;
+    let (start, first) = (Instant::now(), sim.step);
+    progress.begin(sim, Target::Step(steps));
+    let mut done = stopped(sim, config, start, progress)?;
+    while sim.step < steps && !done {
+        advance(sim, config, config.run.print_every.min(steps - sim.step))?;
+        print_status(sim, progress);
+        done = stopped(sim, config, start, progress)?;
+    }
+    progress.finish();
+    tracing::info!(
+        steps = sim.step - first,
+        t = sim.t,
+        elapsed = ?start.elapsed(),
+        "run finished"
+    );
+    sim.checkpoint()
+}
 
-    // shape: (time, z, y, x, vec)  →  (N_STEPS+1, N_SPINS, 1, 1, 3)
-    let shape = vec![(N_STEPS + 1) as u64, 1, 1, N_SPINS as u64, 3];
-    let chunk = vec![1, 1, 1, N_SPINS as u64, 3].try_into().unwrap();
+/// Carry out `config.stages` in order, printing as [`time_loop`] does, and
+/// checkpoint at the end. Every stage ends with a frame of its final state.
+fn stages(sim: &mut Simulation, config: &Config, progress: &mut Progress) -> nez::Result<()> {
+    let print_every = config.run.print_every;
+    let begun = Instant::now();
+    for (k, stage) in config.stages.iter().enumerate() {
+        if interrupt::requested() {
+            break;
+        }
+        stage.apply(sim, config)?;
+        progress.println(format!("# stage {k}: {}", stage.kind.name()));
+        let (start, first) = (Instant::now(), sim.step);
+        match stage.kind {
+            StageKind::Run => {
+                let mut done = stopped(sim, config, begun, progress)?;
+                if let Some(n) = stage.steps {
+                    let end = sim.step + n;
+                    progress.begin(sim, Target::Step(end));
+                    while sim.step < end && !done {
+                        advance(sim, config, print_every.min(end - sim.step))?;
+                        print_status(sim, progress);
+                        done = stopped(sim, config, begun, progress)?;
+                    }
+                } else if let Some(duration) = stage.duration {
+                    let end = sim.t + duration;
+                    let end = config.run.stop_time.map_or(end, |t| end.min(t));
+                    progress.begin(sim, Target::Time(end));
+                    while !done {
+                        let reached = sim.run_until(end, print_every)?;
+                        print_status(sim, progress);
+                        done = stopped(sim, config, begun, progress)? || reached;
+                    }
+                }
+                progress.finish();
+                if !sim.step.is_multiple_of(sim.save_every) {
+                    sim.save()?;
+                }
+            }
+            StageKind::Relax | StageKind::Minimize => {
+                let relaxed = if stage.kind == StageKind::Relax {
+                    sim.relax(&config.relax)
+                } else {
+                    sim.minimize(&config.minimize)
+                };
+                sim.save()?;
+                print_status(sim, progress);
+                progress.println(format!(
+                    "# {} after {} iterations, max torque {:.3e} T",
+                    if relaxed.converged {
+                        "converged"
+                    } else {
+                        "not converged"
+                    },
+                    relaxed.iterations,
+                    relaxed.torque
+                ));
+            }
+        }
+        tracing::info!(
+            stage = k,
+            kind = stage.kind.name(),
+            steps = sim.step - first,
+            t = sim.t,
+            elapsed = ?start.elapsed(),
+            "stage finished"
+        );
+    }
+    sim.checkpoint()
+}
 
-    let mut sharding_codec_builder = ShardingCodecBuilder::new(
-        vec![1, 1, 1, N_SPINS as u64, 3].try_into()?, // inner chunk shape
-    );
-    sharding_codec_builder.bytes_to_bytes_codecs(vec![Arc::new(GzipCodec::new(5)?)]);
+/// An error telling where the run of `sim` was stopped if a signal or the
+/// wall-time budget of the job asked it to end, once its checkpoint is
+/// written. With a budget, `walltime`, the error gives the `nez resume`
+/// command continuing the run of `config` in `store`, so that it reaches
+/// the log of the job even when run with `--quiet`.
+fn interrupted(
+    sim: &Simulation,
+    config: &Config,
+    store: &Path,
+    walltime: Option<Walltime>,
+) -> nez::Result<()> {
+    if !interrupt::requested() {
+        return Ok(());
+    }
+    match walltime.filter(|_| interrupt::out_of_time()) {
+        Some(walltime) => Err(format!(
+            "wall-time budget of {} used up at step {}, after writing a checkpoint\n\
+             # continue with: nez resume {} -n {} --walltime {}",
+            walltime.text,
+            sim.step,
+            store.display(),
+            config.run.steps,
+            walltime.text
+        )
+        .into()),
+        None => Err(format!(
+            "interrupted at step {}, t = {:.3e} s, after writing a checkpoint",
+            sim.step, sim.t
+        )
+        .into()),
+    }
+}
 
-    let array = ArrayBuilder::new(shape, DataType::Float64, chunk, FillValue::from(0.0f64))
-        .array_to_bytes_codec(sharding_codec_builder.build_arc())
-        .build(store.clone(), "/m")?;
+/// Advance `sim` by `n` steps, or fewer to land on `run.stop_time`
+fn advance(sim: &mut Simulation, config: &Config, n: u64) -> nez::Result<()> {
+    match config.run.stop_time {
+        Some(t_end) => sim.run_until(t_end, n).map(drop),
+        None => sim.run(n),
+    }
+}
 
-    array.store_metadata()?; // write metadata once
+/// Whether a signal asked the run to end or a `run.stop_*` condition of
+/// `config` is met in a run started at `start`, which is then reported
+/// through `progress`
+fn stopped(
+    sim: &Simulation,
+    config: &Config,
+    start: Instant,
+    progress: &mut Progress,
+) -> nez::Result<bool> {
+    if interrupt::requested() {
+        return Ok(true);
+    }
+    Ok(match sim.stop_reached(&config.run, start)? {
+        Some(reason) => {
+            progress.println(format!("# stopped: {reason}"));
+            true
+        }
+        None => false,
+    })
+}
 
-    // ---------- time loop ----------
-    for step in 0..=N_STEPS {
-        let t = step as f64 * DT;
+/// Columns of [`Simulation::observables`] printed by [`print_status`]
+const PRINTED: [&str; 7] = ["t", "mx", "my", "mz", "E_total", "max_torque", "dt"];
 
-        // ---- write one time slice to Zarr ----
-        let mut flat = Vec::<f64>::with_capacity(N_SPINS * 3);
-        for m in &chain {
-            flat.extend_from_slice(&[m.x, m.y, m.z]); // x, y, z
+fn print_header(progress: &mut Progress) {
+    progress.println(PRINTED.join("\t"));
+}
+
+/// Print a row of the main observables and redraw the progress bar
+fn print_status(sim: &Simulation, progress: &mut Progress) {
+    // a quiet run computes the observables only for the browsers
+    let observables = if progress.is_quiet() && !progress.is_serving() {
+        Vec::new()
+    } else {
+        sim.observables()
+    };
+    if !progress.is_quiet() {
+        let line: Vec<_> = observables
+            .iter()
+            .filter(|(k, _)| PRINTED.contains(&k.as_str()))
+            .map(|(k, v)| match k.as_str() {
+                "mx" | "my" | "mz" => format!("{v:.6}"),
+                "E_total" => format!("{v:.6e}"),
+                _ => format!("{v:.3e}"),
+            })
+            .collect();
+        progress.println(line.join("\t"));
+    }
+    progress.update(sim, &observables);
+}
+
+/// Snapshot `frame` (default: the last) of the store at `path`
+fn snapshot(path: &PathBuf, frame: Option<u64>) -> nez::Result<VectorField> {
+    let store = ZarrOutput::open(path)?;
+    let frame = match frame {
+        Some(f) => f,
+        None => store
+            .frames()?
+            .checked_sub(1)
+            .ok_or_else(|| format!("{} holds no snapshots", path.display()))?,
+    };
+    store.read(frame)
+}
+
+fn gneb(
+    config: &Config,
+    start: (&PathBuf, Option<u64>),
+    end: (&PathBuf, Option<u64>),
+    progress: &mut Progress,
+) -> nez::Result<()> {
+    if config.sublattice.is_some() {
+        return Err("`nez gneb` does not support a `[sublattice]`".into());
+    }
+    let (start, end) = (snapshot(start.0, start.1)?, snapshot(end.0, end.1)?);
+    if start.len() != config.mesh.len() || end.len() != config.mesh.len() {
+        return Err("end-point states do not match the mesh of the config".into());
+    }
+    let mut sim = Simulation::from_config(config)?;
+    let band = nez::gneb::gneb(
+        &start,
+        &end,
+        sim.t,
+        &sim.mesh,
+        &sim.params,
+        &sim.terms,
+        &config.gneb,
+    );
+    let s = band.reaction_coordinate();
+    progress.println("image\tdistance\tenergy");
+    for (k, (s, e)) in s.iter().zip(&band.energies).enumerate() {
+        progress.println(format!("{k}\t{s:.6e}\t{e:.6e}"));
+    }
+    let (saddle, barrier) = (band.saddle(), band.barrier());
+    progress.println(format!(
+        "{} after {} iterations, max force {:.3e} T",
+        if band.converged {
+            "converged"
+        } else {
+            "not converged"
+        },
+        band.iterations,
+        band.force
+    ));
+    progress.println(format!(
+        "saddle at image {saddle}, barrier {barrier:.6e} J ({:.4} eV)",
+        barrier / 1.602_176_634e-19
+    ));
+
+    if let Some(out) = &mut sim.output {
+        for (k, image) in band.images.iter().enumerate() {
+            out.write(k as u64, sim.t, image)?;
         }
+        out.write_attribute(
+            "gneb",
+            serde_json::json!({
+                "distance": s,
+                "energy": band.energies,
+                "saddle": saddle,
+                "barrier": barrier,
+                "converged": band.converged,
+            }),
+        )?;
+    }
+    Ok(())
+}
 
-        let subset = ArraySubset::new_with_ranges(&[
-            step as u64..step as u64 + 1, // time
-            0..N_SPINS as u64,            // z
-            0..1,                         // y
-            0..1,                         // x
-            0..3,                         // vec
-        ]);
+fn hysteresis(config: &Config, progress: &mut Progress) -> nez::Result<()> {
+    let snapshots = config.hysteresis.snapshots;
+    let mut sim = Simulation::from_config(config)?;
+    let mut table = std::io::BufWriter::new(std::fs::File::create(&config.hysteresis.table)?);
+    let header = "B\tm\tmx\tmy\tmz\tenergy";
+    progress.println(header);
+    writeln!(table, "{header}")?;
+    let points = nez::hysteresis::hysteresis(&mut sim, config, |sim, p| {
+        let line = format!(
+            "{:.6e}\t{:.6}\t{:.6}\t{:.6}\t{:.6}\t{:.6e}{}",
+            p.field,
+            p.projection,
+            p.m.x,
+            p.m.y,
+            p.m.z,
+            p.energy,
+            if p.converged { "" } else { "\t# not converged" }
+        );
+        writeln!(table, "{line}")?;
+        progress.println(line);
+        if snapshots {
+            sim.save()?;
+        }
+        Ok(())
+    })?;
+    table.flush()?;
+    if !snapshots {
+        sim.save()?;
+    }
+    if let Some(out) = &sim.output {
+        out.write_attribute("hysteresis", serde_json::to_value(&points)?)?;
+    }
+    Ok(())
+}
 
-        array.store_array_subset_elements(&subset, &flat)?;
+fn monte_carlo(config: &Config, progress: &mut Progress) -> nez::Result<()> {
+    if config.sublattice.is_some() {
+        return Err("`nez monte-carlo` does not support a `[sublattice]`".into());
+    }
+    let temperatures = &config.monte_carlo.temperatures;
+    let mut sim = Simulation::from_config(config)?;
+    let mut mc = MonteCarlo::new(
+        &sim.m,
+        sim.t,
+        &sim.mesh,
+        &sim.params,
+        &sim.terms,
+        &config.monte_carlo,
+    )?;
+    progress
+        .println("T\t|m|\tmx\tmy\tmz\tenergy\theat_capacity\tsusceptibility\tbinder\tacceptance");
+    let mut samples = Vec::with_capacity(temperatures.len());
+    for (k, &temperature) in temperatures.iter().enumerate() {
+        let s = mc.sample(&mut sim.m, temperature);
+        progress.println(format!(
+            "{:.3}\t{:.6}\t{:.6}\t{:.6}\t{:.6}\t{:.6e}\t{:.6e}\t{:.6e}\t{:.6}\t{:.3}",
+            s.temperature,
+            s.magnetization,
+            s.m.x,
+            s.m.y,
+            s.m.z,
+            s.energy,
+            s.heat_capacity,
+            s.susceptibility,
+            s.binder,
+            s.acceptance
+        ));
+        if let Some(out) = &mut sim.output {
+            out.write(k as u64, sim.t, &sim.m)?;
+        }
+        samples.push(s);
+    }
+    if let Some(out) = &sim.output {
+        out.write_attribute("monte_carlo", serde_json::to_value(&samples)?)?;
+    }
+    Ok(())
+}
 
-        if step % 50 == 0 {
-            let m_avg_z = chain.iter().map(|m| m.z).sum::<f64>() / N_SPINS as f64;
-            println!("{:.3e}\t{:.6e}", t, m_avg_z);
+fn convert(
+    path: &PathBuf,
+    format: Format,
+    data: Encoding,
+    frame: Option<u64>,
+    dir: &PathBuf,
+) -> nez::Result<()> {
+    let store = ZarrOutput::open(path)?;
+    let mesh = store.config()?.mesh;
+    let frames = match frame {
+        Some(f) if f < store.frames()? => f..f + 1,
+        Some(f) => return Err(format!("{} has no snapshot {f}", path.display()).into()),
+        None => 0..store.frames()?,
+    };
+    if dir == path {
+        return Err("output directory must differ from the store".into());
+    }
+    std::fs::create_dir_all(dir)?;
+    for f in frames {
+        let (m, t) = (store.read(f)?, store.time(f)?);
+        for file in snapshot::write(dir, f, &mesh, &m, t, format, data)? {
+            println!("{}", file.display());
         }
+    }
+    Ok(())
+}
 
-        chain = rk4_step(&chain);
+/// Compute the dispersion of the store at `path` and write it back, and to
+/// `image` if given
+fn dispersion(
+    path: &PathBuf,
+    axis: Axis,
+    component: Component,
+    image: Option<&std::path::Path>,
+) -> nez::Result<()> {
+    let out = ZarrOutput::open(path)?;
+    let map = nez::dispersion::compute(&out, axis, component)?;
+    map.store(&out, axis, component)?;
+    let (k, f) = (map.k[map.k.len() - 1], map.f[map.f.len() - 1]);
+    println!(
+        "dispersion: {} x {} (f, k) in {}/dispersion, k up to {:.4e} rad/m, f up to {:.4e} Hz",
+        map.f.len(),
+        map.k.len(),
+        path.display(),
+        k,
+        f
+    );
+    if let Some(image) = image {
+        map.write_png(image)?;
+        println!("image:      {}", image.display());
     }
+    Ok(())
+}
 
+/// Compute the FMR spectra of the store at `path`, of every cell too if
+/// `cells`, write them back and print that of ⟨m⟩
+fn fmr(path: &PathBuf, window: Window, cells: bool) -> nez::Result<()> {
+    use nez::spectrum;
+    let out = ZarrOutput::open(path)?;
+    let (source, (times, samples)) = match spectrum::table_average(&out)? {
+        Some(table) => ("table", table),
+        None => ("snapshots", spectrum::snapshots(&out, true)?),
+    };
+    let average = Spectrum::compute(&times, &samples, window)?;
+    let about = |units: &str, source: &str| {
+        let mut attributes = serde_json::Map::new();
+        attributes.insert("units".into(), units.into());
+        attributes.insert("source".into(), source.into());
+        attributes.insert("window".into(), format!("{window:?}").to_lowercase().into());
+        attributes
+    };
+    let nf = average.f.len() as u64;
+    out.write_dataset("/fmr/f", &[nf], &["f"], &average.f, about("Hz", source))?;
+    let dims = ["f", "vec"];
+    let power = &average.power;
+    out.write_dataset("/fmr/power", &[nf, 3], &dims, power, about("1", source))?;
+    if cells && out.frames()? >= 4 {
+        let (times, samples) = spectrum::snapshots(&out, false)?;
+        let spectrum = Spectrum::compute(&times, &samples, window)?;
+        let nf = spectrum.f.len() as u64;
+        let f = &spectrum.f;
+        out.write_dataset("/fmr/cells/f", &[nf], &["f"], f, about("Hz", "snapshots"))?;
+        let mut shape = out.shape().to_vec();
+        shape[0] = nf;
+        let dims = ["f", "z", "y", "x", "vec"];
+        let power = &spectrum.power;
+        let about = about("1", "snapshots");
+        out.write_dataset("/fmr/cells/power", &shape, &dims, power, about)?;
+    }
+    println!("f\tmx\tmy\tmz");
+    for (j, f) in average.f.iter().enumerate() {
+        let [x, y, z] = [0, 1, 2].map(|c| average.at(j, c));
+        println!("{f:.6e}\t{x:.6e}\t{y:.6e}\t{z:.6e}");
+    }
+    Ok(())
+}
+
+fn info(path: &PathBuf) -> nez::Result<()> {
+    let out = ZarrOutput::open(path)?;
+    let shape = out.shape();
+    println!("store:  {}", path.display());
+    println!("m:      shape {shape:?} (time, z, y, x, vec)");
+    println!("frames: {} / {} written", out.frames()?, out.capacity());
+    match out.config() {
+        Ok(config) => {
+            let m = &config.mesh;
+            let p = &config.params;
+            println!(
+                "mesh:   {} x {} x {} cells of {:e} x {:e} x {:e} m",
+                m.nx, m.ny, m.nz, m.dx, m.dy, m.dz
+            );
+            println!("dt:     {:e} s, {} steps", p.dt, config.run.steps);
+            println!("alpha:  {}", p.alpha);
+            println!("h_ext:  {:?} T", p.h_ext.as_slice());
+        }
+        Err(e) => println!("config: unavailable ({e})"),
+    }
+    if let (Some(columns), rows @ 1..) = (out.table_columns()?, out.table_rows()) {
+        // the energy of every term in the last row, as a check of the physics
+        let row = out.table_row(rows - 1)?;
+        let energies: Vec<_> = columns
+            .iter()
+            .zip(&row)
+            .filter_map(|(c, v)| Some((c.strip_prefix("E_")?, v)))
+            .collect();
+        if let Some(t) = columns.iter().position(|c| c == "t") {
+            println!("table:  {rows} rows, last at t = {:.4e} s", row[t]);
+        }
+        for (term, e) in energies {
+            println!("  E_{term:<12} {:>13.6e} J", e + 0.0);
+        }
+    }
     Ok(())
 }
@@ -0,0 +1,278 @@
+//! Maps: material parameters given cell by cell, from an expression of the
+//! position or from a file, e.g. a graded anisotropy or a damping profile
+//! that absorbs spin waves at the edges.
+//!
+//! A map replaces the value of the main section in every cell. Regions that
+//! override the same parameter still win in their own cells.
+
+use std::{
+    iter,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    Mesh, Result,
+    expr::Expr,
+    regions::{RegionConfig, Regions},
+};
+
+/// Source of the values of one parameter
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Map {
+    /// expression of the cell centre `x`, `y`, `z` (m)
+    Expr(String),
+    /// OVF file with one value per node, as mumax3 saves scalar quantities,
+    /// stretched over the mesh and interpolated if the grids differ
+    File(PathBuf),
+}
+
+impl Map {
+    /// The value in every cell of `mesh`
+    pub fn values(&self, mesh: &Mesh) -> Result<Vec<f64>> {
+        let values: Vec<f64> = match self {
+            Self::Expr(expr) => {
+                let expr = Expr::parse(expr, &["x", "y", "z"])?;
+                (0..mesh.len())
+                    .into_par_iter()
+                    .map(|i| {
+                        let r = mesh.position(i);
+                        expr.eval(&[r.x, r.y, r.z])
+                    })
+                    .collect()
+            }
+            Self::File(file) => crate::ovf::read_scalar(file)?.interpolate(mesh),
+        };
+        match values.iter().position(|v| !v.is_finite()) {
+            Some(i) => Err(format!("not finite in cell {:?}", mesh.coords(i)).into()),
+            None => Ok(values),
+        }
+    }
+}
+
+/// Maps of `[params]` values
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ParamsMaps {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gamma: Option<Map>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub alpha: Option<Map>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub a_ex: Option<Map>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mu0_ms: Option<Map>,
+}
+
+/// Maps of `[uniaxial]` constants
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct UniaxialMaps {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub k1: Option<Map>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub k2: Option<Map>,
+}
+
+/// Maps of `[cubic]` constants
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CubicMaps {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub k1: Option<Map>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub k2: Option<Map>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub k3: Option<Map>,
+}
+
+/// Maps of `[dmi]` constants
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DmiMaps {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub d: Option<Map>,
+}
+
+/// The `[maps]` section, laid out like the sections it replaces values of:
+///
+/// ```toml
+/// [maps]
+/// params.alpha = { expr = "0.01 + 0.5 * step(abs(x - 320e-9) - 300e-9)" }
+/// uniaxial.k1 = { file = "ku1.ovf" }
+/// ```
+///
+/// Mapping a term that has no section of its own enables it, with the
+/// defaults of that section for the other constants.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MapsConfig {
+    pub params: ParamsMaps,
+    pub uniaxial: UniaxialMaps,
+    pub cubic: CubicMaps,
+    pub dmi: DmiMaps,
+}
+
+impl MapsConfig {
+    /// Every parameter that can be mapped, by key, e.g. `params.alpha`
+    pub fn entries(&self) -> [(&'static str, Option<&Map>); 10] {
+        [
+            ("params.gamma", self.params.gamma.as_ref()),
+            ("params.alpha", self.params.alpha.as_ref()),
+            ("params.a_ex", self.params.a_ex.as_ref()),
+            ("params.mu0_ms", self.params.mu0_ms.as_ref()),
+            ("uniaxial.k1", self.uniaxial.k1.as_ref()),
+            ("uniaxial.k2", self.uniaxial.k2.as_ref()),
+            ("cubic.k1", self.cubic.k1.as_ref()),
+            ("cubic.k2", self.cubic.k2.as_ref()),
+            ("cubic.k3", self.cubic.k3.as_ref()),
+            ("dmi.d", self.dmi.d.as_ref()),
+        ]
+    }
+
+    /// Look up the files of all maps in `dir`
+    pub fn resolve_files(&mut self, dir: &Path) {
+        for map in [
+            &mut self.params.gamma,
+            &mut self.params.alpha,
+            &mut self.params.a_ex,
+            &mut self.params.mu0_ms,
+            &mut self.uniaxial.k1,
+            &mut self.uniaxial.k2,
+            &mut self.cubic.k1,
+            &mut self.cubic.k2,
+            &mut self.cubic.k3,
+            &mut self.dmi.d,
+        ] {
+            if let Some(Map::File(file)) = map {
+                *file = dir.join(&file);
+            }
+        }
+    }
+}
+
+/// The values of the map at `key` of `[maps]` on `mesh`, if it is set
+pub fn evaluate(key: &str, map: Option<&Map>, mesh: &Mesh) -> Result<Option<Vec<f64>>> {
+    map.map(|m| m.values(mesh))
+        .transpose()
+        .map_err(|e| format!("`maps.{key}`: {e}").into())
+}
+
+/// Per-cell values of `[params]` from `[maps.params]`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CellParams {
+    pub gamma: Option<Vec<f64>>,
+    pub alpha: Option<Vec<f64>>,
+    pub a_ex: Option<Vec<f64>>,
+    pub mu0_ms: Option<Vec<f64>>,
+}
+
+impl CellParams {
+    /// Evaluate `maps` on `mesh`, or `None` without any
+    pub fn new(maps: &ParamsMaps, mesh: &Mesh) -> Result<Option<Self>> {
+        if *maps == ParamsMaps::default() {
+            return Ok(None);
+        }
+        let load = |key: &str, map: &Option<Map>| -> Result<Option<Vec<f64>>> {
+            let values = evaluate(key, map.as_ref(), mesh)?;
+            if let Some(v) = values.iter().flatten().find(|v| **v < 0.0) {
+                return Err(format!("`maps.{key}` must be non-negative, got {v}").into());
+            }
+            Ok(values)
+        };
+        Ok(Some(Self {
+            gamma: load("params.gamma", &maps.gamma)?,
+            alpha: load("params.alpha", &maps.alpha)?,
+            a_ex: load("params.a_ex", &maps.a_ex)?,
+            mu0_ms: load("params.mu0_ms", &maps.mu0_ms)?,
+        }))
+    }
+}
+
+/// A parameter that may vary in space, looked up cell by cell
+#[derive(Debug, Clone, PartialEq)]
+pub struct Spatial<T>(Values<T>);
+
+#[derive(Debug, Clone, PartialEq)]
+enum Values<T> {
+    Uniform(T),
+    /// region of every cell and the value of every region
+    Regions {
+        index: Arc<[u8]>,
+        values: Vec<T>,
+    },
+    Cells(Arc<[T]>),
+}
+
+impl<T: Copy + PartialEq> Spatial<T> {
+    pub fn uniform(value: T) -> Self {
+        Self(Values::Uniform(value))
+    }
+
+    /// `base`, or the value of `map` in each cell if given, except in the
+    /// regions of `config` for which `value` yields an override
+    pub fn new(
+        regions: Option<&Regions>,
+        config: &[RegionConfig],
+        base: T,
+        map: Option<&[T]>,
+        value: impl Fn(&RegionConfig) -> Option<T>,
+    ) -> Self {
+        let overrides: Vec<Option<T>> = iter::once(None).chain(config.iter().map(value)).collect();
+        match (regions, map) {
+            (None, None) => Self::uniform(base),
+            (None, Some(map)) => Self(Values::Cells(map.into())),
+            (Some(regions), Some(map)) => Self(Values::Cells(
+                regions
+                    .index
+                    .iter()
+                    .zip(map)
+                    .map(|(r, v)| overrides[*r as usize].unwrap_or(*v))
+                    .collect(),
+            )),
+            (Some(regions), None) => {
+                if overrides.iter().flatten().all(|v| *v == base) {
+                    return Self::uniform(base);
+                }
+                Self(Values::Regions {
+                    index: regions.index.clone(),
+                    values: overrides.iter().map(|v| v.unwrap_or(base)).collect(),
+                })
+            }
+        }
+    }
+
+    /// Value in cell `i`
+    #[inline(always)]
+    pub fn at(&self, i: usize) -> T {
+        match &self.0 {
+            Values::Uniform(v) => *v,
+            Values::Regions { index, values } => values[index[i] as usize],
+            Values::Cells(values) => values[i],
+        }
+    }
+
+    /// The value shared by all cells, if any
+    pub fn as_uniform(&self) -> Option<T> {
+        match self.0 {
+            Values::Uniform(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    /// The same lookup with every value mapped through `f`
+    pub fn map<U: Copy + PartialEq>(&self, f: impl Fn(T) -> U) -> Spatial<U> {
+        Spatial(match &self.0 {
+            Values::Uniform(v) => Values::Uniform(f(*v)),
+            Values::Regions { index, values } => Values::Regions {
+                index: index.clone(),
+                values: values.iter().map(|v| f(*v)).collect(),
+            },
+            Values::Cells(values) => Values::Cells(values.iter().map(|v| f(*v)).collect()),
+        })
+    }
+}
@@ -0,0 +1,128 @@
+use nalgebra::Vector3;
+use serde::{Deserialize, Serialize};
+
+/// Regular finite-difference grid of `nx × ny × nz` cells.
+///
+/// Cells are stored x-fastest, i.e. `i = x + nx * (y + ny * z)`, which matches
+/// the (z, y, x) axis order of the Zarr output.
+///
+/// Axes with a non-zero `pbc` entry are periodic: neighbours wrap around, and
+/// the demag field includes that many images of the sample on each side.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Mesh {
+    pub nx: usize,
+    pub ny: usize,
+    pub nz: usize,
+    /// cell size (m)
+    pub dx: f64,
+    pub dy: f64,
+    pub dz: f64,
+    /// periodic images along x, y, z (0 for free boundaries)
+    pub pbc: [usize; 3],
+}
+
+impl Mesh {
+    pub fn new(n: [usize; 3], d: [f64; 3]) -> Self {
+        Self {
+            nx: n[0],
+            ny: n[1],
+            nz: n[2],
+            dx: d[0],
+            dy: d[1],
+            dz: d[2],
+            pbc: [0; 3],
+        }
+    }
+
+    /// Same mesh with `pbc[axis]` periodic images along each axis
+    pub fn with_pbc(mut self, pbc: [usize; 3]) -> Self {
+        self.pbc = pbc;
+        self
+    }
+
+    /// Whether neighbours wrap around along `axis`
+    #[inline(always)]
+    pub fn periodic(&self, axis: usize) -> bool {
+        self.pbc[axis] > 0
+    }
+
+    /// 1D chain of `n` cells along x with spacing `dx`
+    pub fn chain(n: usize, dx: f64) -> Self {
+        Self::new([n, 1, 1], [dx, dx, dx])
+    }
+
+    /// Total number of cells
+    pub fn len(&self) -> usize {
+        self.nx * self.ny * self.nz
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Cell counts as `[nx, ny, nz]`
+    pub fn size(&self) -> [usize; 3] {
+        [self.nx, self.ny, self.nz]
+    }
+
+    /// Cell sizes as `[dx, dy, dz]`
+    pub fn cell(&self) -> [f64; 3] {
+        [self.dx, self.dy, self.dz]
+    }
+
+    /// Cell volume (m³)
+    pub fn cell_volume(&self) -> f64 {
+        self.dx * self.dy * self.dz
+    }
+
+    /// Linear index of cell (x, y, z)
+    #[inline(always)]
+    pub fn idx(&self, x: usize, y: usize, z: usize) -> usize {
+        x + self.nx * (y + self.ny * z)
+    }
+
+    /// Cell coordinates of linear index `i`
+    #[inline(always)]
+    pub fn coords(&self, i: usize) -> [usize; 3] {
+        [
+            i % self.nx,
+            (i / self.nx) % self.ny,
+            i / (self.nx * self.ny),
+        ]
+    }
+
+    /// Index of the neighbour of cell `i` one step along `axis` (0, 1, 2 for
+    /// x, y, z) in direction `dir` (±1), or `None` past the edge of the mesh.
+    /// Along periodic axes the index wraps around instead.
+    #[inline(always)]
+    pub fn neighbor(&self, i: usize, axis: usize, dir: isize) -> Option<usize> {
+        let c0 = self.coords(i)[axis] as isize;
+        let n = self.size()[axis] as isize;
+        let mut c = c0 + dir;
+        if c < 0 || c >= n {
+            if !self.periodic(axis) {
+                return None;
+            }
+            c = c.rem_euclid(n);
+        }
+        let stride = [1, self.nx, self.nx * self.ny][axis] as isize;
+        Some((i as isize + (c - c0) * stride) as usize)
+    }
+
+    /// Position of the centre of cell `i` (m), origin at the corner of cell 0
+    pub fn position(&self, i: usize) -> Vector3<f64> {
+        let [x, y, z] = self.coords(i);
+        Vector3::new(
+            (x as f64 + 0.5) * self.dx,
+            (y as f64 + 0.5) * self.dy,
+            (z as f64 + 0.5) * self.dz,
+        )
+    }
+}
+
+impl Default for Mesh {
+    fn default() -> Self {
+        Self::chain(128, 2.5e-9)
+    }
+}
@@ -0,0 +1,190 @@
+//! Static energy minimization with nonlinear conjugate gradients.
+
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    VectorField,
+    llg::{Llg, hold_frozen, max_torque},
+    parallel::chunk,
+};
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MinimizeConfig {
+    /// stop once max|m × B_eff| drops below this (T)
+    pub tolerance: f64,
+    /// give up after this many conjugate-gradient iterations
+    pub max_iterations: u64,
+}
+
+impl Default for MinimizeConfig {
+    fn default() -> Self {
+        Self {
+            tolerance: 1e-5,
+            max_iterations: 100_000,
+        }
+    }
+}
+
+/// Outcome of [`minimize`] or [`Simulation::relax`](crate::Simulation::relax)
+#[derive(Debug, Clone, Copy)]
+pub struct Relaxed {
+    /// conjugate-gradient iterations or time steps taken
+    pub iterations: u64,
+    /// final max|m × B_eff| (T)
+    pub torque: f64,
+    pub converged: bool,
+}
+
+/// The state at one point of the search, with its gradient
+struct Point {
+    m: VectorField,
+    /// gradient of E / (Mₛ V) on the sphere, -B_eff projected onto the
+    /// tangent plane (T)
+    g: VectorField,
+    torque: f64,
+}
+
+impl Point {
+    fn new(m: VectorField, t: f64, llg: &Llg) -> Self {
+        let h = llg.effective_field(&m, t);
+        let mut g = VectorField::from_fn(m.len(), |i| {
+            let (m, h) = (m.get(i), h.get(i));
+            m.dot(&h) * m - h
+        });
+        // frozen moments have no gradient, so no search direction moves them
+        hold_frozen(llg.params, &mut g);
+        let torque = max_torque(&m, &h, llg.params);
+        Self { m, g, torque }
+    }
+}
+
+/// Project `v` onto the tangent planes at `m`
+fn project(m: &VectorField, v: &mut VectorField) {
+    v.par_update(|i, v| {
+        let m = m.get(i);
+        v - v.dot(&m) * m
+    });
+}
+
+/// `-v`
+fn negated(v: &VectorField) -> VectorField {
+    VectorField::from_fn(v.len(), |i| -v.get(i))
+}
+
+/// Minimize the energy of the effective field of `llg` at fixed time `t`,
+/// starting from and updating `m` (torques and damping play no part).
+///
+/// Nonlinear conjugate gradients (Polak–Ribière+) on the product of unit
+/// spheres: steps are retracted by renormalising, and search directions are
+/// carried along by projecting onto the new tangent planes. The line search
+/// only looks at the directional derivative, which stays accurate long after
+/// energy differences have drowned in round-off.
+pub fn minimize(m: &mut VectorField, llg: &Llg, t: f64, config: &MinimizeConfig) -> Relaxed {
+    let eval = |m: VectorField| Point::new(m, t, llg);
+    let mut x = eval(m.clone());
+    let mut d = negated(&x.g);
+    // step length in rad per T of direction, rescaled on every line search
+    let mut s = 0.0;
+    let mut iterations = 0;
+
+    while x.torque > config.tolerance && iterations < config.max_iterations {
+        iterations += 1;
+        let mut slope = x.g.dot(&d);
+        if slope >= 0.0 {
+            // not a descent direction: restart along the steepest descent
+            d = negated(&x.g);
+            slope = x.g.dot(&d);
+        }
+        if s == 0.0 {
+            s = 0.05 / d.max_norm();
+        }
+        let next = line_search(&x, &d, slope, &mut s, &eval);
+
+        // Polak–Ribière+ update with directions transported to the new point
+        let mut g_old = x.g;
+        project(&next.m, &mut g_old);
+        project(&next.m, &mut d);
+        let mut y = next.g.clone();
+        y.axpy(-1.0, &g_old);
+        let beta = (next.g.dot(&y) / g_old.dot(&g_old)).max(0.0);
+        let beta = if beta.is_finite() { beta } else { 0.0 };
+        d.par_update(|i, d| -next.g.get(i) + beta * d);
+        x = next;
+    }
+
+    *m = x.m;
+    Relaxed {
+        iterations,
+        torque: x.torque,
+        converged: x.torque <= config.tolerance,
+    }
+}
+
+/// Find an approximate minimum of the energy along `d` from `x`, whose
+/// directional derivative is `slope` < 0. `s` is the first trial step and is
+/// updated to the accepted one.
+fn line_search(
+    x: &Point,
+    d: &VectorField,
+    slope: f64,
+    s: &mut f64,
+    eval: &impl Fn(VectorField) -> Point,
+) -> Point {
+    let n = d.len();
+    let retract = |s: f64| -> (VectorField, Vec<f64>) {
+        // cells without a moment stay at zero
+        let norms: Vec<f64> = (0..n)
+            .into_par_iter()
+            .with_min_len(chunk())
+            .map(|i| (x.m.get(i) + s * d.get(i)).norm())
+            .map(|norm| if norm > 0.0 { norm } else { 1.0 })
+            .collect();
+        let m = VectorField::from_fn(n, |i| (x.m.get(i) + s * d.get(i)) / norms[i]);
+        (m, norms)
+    };
+    // derivative of the energy along the retracted curve at step s
+    let derivative = |pt: &Point, norms: &[f64]| -> f64 {
+        (0..n)
+            .into_par_iter()
+            .with_min_len(chunk())
+            .map(|i| {
+                let (g, d, m) = (pt.g.get(i), d.get(i), pt.m.get(i));
+                g.dot(&(d - d.dot(&m) * m)) / norms[i]
+            })
+            .sum()
+    };
+
+    // the retracted curve saturates at d̂, where its derivative vanishes
+    // without a minimum, so no spin turns by more than 45° per search
+    let s_max = 1.0 / d.max_norm();
+    let (mut lo, mut dlo) = (0.0, slope);
+    let mut hi: Option<(f64, f64)> = None;
+    let mut trial = s.min(s_max);
+    let mut best = None;
+    for _ in 0..20 {
+        let (m, norms) = retract(trial);
+        let pt = eval(m);
+        let dphi = derivative(&pt, &norms);
+        let done = dphi.abs() <= 0.5 * slope.abs() || (dphi < 0.0 && trial >= s_max);
+        if dphi < 0.0 {
+            (lo, dlo) = (trial, dphi);
+        } else {
+            hi = Some((trial, dphi));
+        }
+        best = Some((trial, pt));
+        if done {
+            break;
+        }
+        trial = match hi {
+            // secant between the bracketing steps
+            Some((h, dh)) => lo + (h - lo) * dlo / (dlo - dh),
+            // still descending: expand
+            None => (4.0 * trial).min(s_max),
+        };
+    }
+    let (trial, pt) = best.expect("at least one line-search step");
+    *s = trial;
+    pt
+}
@@ -0,0 +1,217 @@
+//! Equilibrium sampling with single-spin Metropolis Monte Carlo.
+//!
+//! Every cell is one classical moment Mₛ V with the same Hamiltonian as the
+//! dynamics. Trial moves are Gaussian displacements `m + σ g` projected back
+//! on the sphere, whose width σ is adapted during equilibration to reach the
+//! target acceptance rate (Alzate-Cardona et al., J. Phys.: Condens. Matter
+//! 31, 095802 (2019)).
+
+use nalgebra::Vector3;
+use serde::{Deserialize, Serialize};
+
+use crate::{Mesh, Params, Result, VectorField, field::FieldTerms, rng::Rng};
+
+/// Boltzmann constant (J K⁻¹)
+pub const K_B: f64 = 1.380_649e-23;
+
+/// Cone widths beyond this sample the sphere almost uniformly
+const MAX_CONE: f64 = 60.0;
+
+/// Trial moves between two adaptations of the cone width
+const ADAPT_TRIALS: usize = 1000;
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MonteCarloConfig {
+    /// temperatures to sample (K), in order, each starting from the final
+    /// state of the previous one
+    pub temperatures: Vec<f64>,
+    /// sweeps discarded at every temperature before sampling
+    pub equilibration: u64,
+    /// sweeps sampled at every temperature
+    pub sweeps: u64,
+    /// initial width σ of the trial moves
+    pub cone: f64,
+    /// acceptance rate the cone width is adapted to during equilibration
+    pub acceptance: f64,
+    pub seed: u64,
+}
+
+impl Default for MonteCarloConfig {
+    fn default() -> Self {
+        Self {
+            temperatures: vec![300.0],
+            equilibration: 1000,
+            sweeps: 10_000,
+            cone: 0.5,
+            acceptance: 0.5,
+            seed: 0,
+        }
+    }
+}
+
+/// Thermal averages at one temperature, one measurement per sweep
+#[derive(Debug, Clone, Serialize)]
+pub struct Sample {
+    /// (K)
+    pub temperature: f64,
+    /// mean of the spatially averaged magnetization
+    pub m: Vector3<f64>,
+    /// mean of its length, the order parameter
+    pub magnetization: f64,
+    /// mean energy (J)
+    pub energy: f64,
+    /// (⟨E²⟩ − ⟨E⟩²) / (k_B T²) (J K⁻¹)
+    pub heat_capacity: f64,
+    /// N Mₛ V (⟨|m|²⟩ − ⟨|m|⟩²) / (k_B T) (T⁻¹)
+    pub susceptibility: f64,
+    /// Binder cumulant 1 − ⟨|m|⁴⟩ / (3 ⟨|m|²⟩²)
+    pub binder: f64,
+    /// fraction of accepted moves while sampling
+    pub acceptance: f64,
+    /// cone width σ after equilibration
+    pub cone: f64,
+}
+
+/// Metropolis sampler over the field terms of a simulation, at frozen time
+pub struct MonteCarlo<'a> {
+    mesh: &'a Mesh,
+    params: &'a Params,
+    terms: &'a FieldTerms,
+    t: f64,
+    config: &'a MonteCarloConfig,
+    rng: Rng,
+    cone: f64,
+}
+
+impl<'a> MonteCarlo<'a> {
+    /// Fails if a term (such as demag) has no local energy update
+    pub fn new(
+        m: &VectorField,
+        t: f64,
+        mesh: &'a Mesh,
+        params: &'a Params,
+        terms: &'a FieldTerms,
+        config: &'a MonteCarloConfig,
+    ) -> Result<Self> {
+        if let Some(name) = terms.nonlocal_term(m, t, mesh, params) {
+            return Err(format!("Monte Carlo does not support the `{name}` term").into());
+        }
+        Ok(Self {
+            mesh,
+            params,
+            terms,
+            t,
+            config,
+            rng: Rng::new(config.seed),
+            cone: config.cone,
+        })
+    }
+
+    /// One Metropolis trial per cell of the sample, in storage order, at
+    /// `temperature` (K). Accepted moves are added to `energy`; returns the
+    /// acceptance rate.
+    pub fn sweep(&mut self, m: &mut VectorField, temperature: f64, energy: &mut f64) -> f64 {
+        let kt = K_B * temperature;
+        let (mut accepted, mut trials) = (0, 0);
+        for i in 0..m.len() {
+            if self
+                .params
+                .geometry
+                .as_ref()
+                .is_some_and(|g| !g.contains(i))
+                || self.params.is_frozen(i)
+            {
+                continue;
+            }
+            trials += 1;
+            let new = (m.get(i) + self.cone * self.rng.normal_vector()).normalize();
+            let de = self
+                .terms
+                .delta_energy(m, i, new, self.t, self.mesh, self.params)
+                .expect("checked in MonteCarlo::new");
+            if de <= 0.0 || self.rng.uniform() < (-de / kt).exp() {
+                m.set(i, new);
+                *energy += de;
+                accepted += 1;
+            }
+        }
+        accepted as f64 / trials as f64
+    }
+
+    /// Equilibrate `m` at `temperature` (K), then sample it
+    pub fn sample(&mut self, m: &mut VectorField, temperature: f64) -> Sample {
+        let target = self.config.acceptance;
+        let mut energy = self.terms.energy(m, self.t, self.mesh, self.params);
+        let (mut accepted, mut trials) = (0.0, 0);
+        for _ in 0..self.config.equilibration {
+            accepted += self.sweep(m, temperature, &mut energy) * m.len() as f64;
+            trials += m.len();
+            if trials >= ADAPT_TRIALS {
+                // widen the cone when too many moves pass, narrow it otherwise
+                let rate = accepted / trials as f64;
+                let factor = ((1.0 - target) / (1.0 - rate).max(1e-3)).clamp(0.5, 2.0);
+                self.cone = (self.cone * factor).clamp(f64::EPSILON, MAX_CONE);
+                (accepted, trials) = (0.0, 0);
+            }
+        }
+
+        // energies are accumulated relative to the first one to avoid
+        // cancellation in the variance
+        let e0 = energy;
+        let mut sum = Sums::default();
+        for _ in 0..self.config.sweeps {
+            sum.acceptance += self.sweep(m, temperature, &mut energy);
+            let avg = match &self.params.geometry {
+                Some(g) => g.mean(m),
+                None => m.mean(),
+            };
+            let (norm, de) = (avg.norm(), energy - e0);
+            sum.m += avg;
+            sum.norm += norm;
+            sum.norm2 += norm * norm;
+            sum.norm4 += norm.powi(4);
+            sum.energy += de;
+            sum.energy2 += de * de;
+        }
+
+        let n = self.config.sweeps as f64;
+        let (norm, norm2) = (sum.norm / n, sum.norm2 / n);
+        let (de, de2) = (sum.energy / n, sum.energy2 / n);
+        let kt = K_B * temperature;
+        // total moment of the sample (A m²)
+        let moment: f64 =
+            (0..m.len()).map(|i| self.params.ms_at(i)).sum::<f64>() * self.mesh.cell_volume();
+        let (heat_capacity, susceptibility) = if kt > 0.0 {
+            (
+                (de2 - de * de) / (kt * temperature),
+                moment * (norm2 - norm * norm) / kt,
+            )
+        } else {
+            (0.0, 0.0)
+        };
+        Sample {
+            temperature,
+            m: sum.m / n,
+            magnetization: norm,
+            energy: e0 + de,
+            heat_capacity,
+            susceptibility,
+            binder: 1.0 - sum.norm4 / n / (3.0 * norm2 * norm2),
+            acceptance: sum.acceptance / n,
+            cone: self.cone,
+        }
+    }
+}
+
+/// Running sums over the sampling sweeps
+#[derive(Default)]
+struct Sums {
+    m: Vector3<f64>,
+    norm: f64,
+    norm2: f64,
+    norm4: f64,
+    energy: f64,
+    energy2: f64,
+    acceptance: f64,
+}
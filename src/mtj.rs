@@ -0,0 +1,91 @@
+//! Magnetic tunnel junctions: the free layer of the mesh over the fixed
+//! polarizer of `[slonczewski]`, across a tunnel barrier whose resistance
+//! follows the angle between the two and joins the observables:
+//!
+//! ```toml
+//! [slonczewski]
+//! polarization = 0.6
+//! fixed = [1.0, 0.0, 0.0]   # polarizer magnetization
+//! current = { kind = "ramp", from = 0.0, to = 2e11, start = 0.0, end = 20e-9 }
+//!
+//! [mtj]
+//! ra = 1e-11                # resistance-area product, parallel state (Ω m²)
+//! tmr = 1.0                 # (R_AP - R_P) / R_P
+//! ```
+//!
+//! Each column of the pillar conducts as `G(θ) = (G_P + G_AP)/2 +
+//! (G_P - G_AP)/2 cos θ` (Jullière, Slonczewski), θ the angle between `m`
+//! and the polarizer, so that the conductance of the junction follows
+//! ⟨m⟩·p over the sample. The table gains the columns `current_density`,
+//! the `[slonczewski]` current (A m⁻²), `resistance` (Ω) and
+//! `magnetoresistance`, `(R - R_P) / R_P`, from 0 to `tmr`: a slow current
+//! sweep traces the switching loop R(I).
+
+use nalgebra::Vector3;
+use serde::{Deserialize, Serialize};
+
+use crate::{Mesh, Params, Result};
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MtjConfig {
+    /// resistance-area product in the parallel state (Ω m²)
+    pub ra: f64,
+    /// tunnel magnetoresistance ratio (R_AP - R_P) / R_P
+    pub tmr: f64,
+}
+
+impl Default for MtjConfig {
+    /// a CoFeB/MgO junction
+    fn default() -> Self {
+        Self {
+            ra: 1e-11,
+            tmr: 1.0,
+        }
+    }
+}
+
+impl MtjConfig {
+    /// Check that the junction conducts in both states
+    pub fn validate(&self) -> Result<()> {
+        if !(self.ra.is_finite() && self.ra > 0.0) {
+            return Err(format!("`mtj.ra` must be positive, got {}", self.ra).into());
+        }
+        if !(self.tmr.is_finite() && self.tmr > -1.0) {
+            return Err(format!("`mtj.tmr` must be above -1, got {}", self.tmr).into());
+        }
+        Ok(())
+    }
+}
+
+/// The junction of `[mtj]` over the sample of a mesh
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mtj {
+    /// resistance in the parallel state (Ω)
+    pub r_p: f64,
+    pub tmr: f64,
+}
+
+impl Mtj {
+    /// The junction over the cross-section of the sample of `p`, its cells
+    /// with a moment per z layer
+    pub fn new(config: &MtjConfig, mesh: &Mesh, p: &Params) -> Result<Self> {
+        let cells = (0..mesh.len()).filter(|&i| p.mu0_ms_at(i) > 0.0).count();
+        if cells == 0 {
+            return Err("`mtj`: the sample holds no cell".into());
+        }
+        let area = cells as f64 / mesh.nz as f64 * mesh.dx * mesh.dy;
+        Ok(Self {
+            r_p: config.ra / area,
+            tmr: config.tmr,
+        })
+    }
+
+    /// Resistance (Ω) with the magnetization `average` over the sample and
+    /// the polarizer along `fixed`
+    pub fn resistance(&self, average: &Vector3<f64>, fixed: &Vector3<f64>) -> f64 {
+        let (g_p, g_ap) = (1.0 / self.r_p, 1.0 / (self.r_p * (1.0 + self.tmr)));
+        let cos = average.dot(&fixed.normalize());
+        1.0 / ((g_p + g_ap) / 2.0 + (g_p - g_ap) / 2.0 * cos)
+    }
+}
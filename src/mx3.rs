@@ -0,0 +1,670 @@
+//! Import of mumax3 input scripts: `nez import file.mx3` translates a common
+//! subset into `file.toml` and a control script `file.nez` (see
+//! [`crate::script`]), to be run with `nez script file.toml file.nez`.
+//!
+//! - `SetGridSize`, `SetCellSize`, `SetPBC` and `SetMesh` set the mesh;
+//! - `Msat`, `Aex`, `alpha` and `B_ext` go to the config before the first
+//!   command and to the script after it; a `B_ext` depending on `t` becomes a
+//!   script `field`;
+//! - `Ku1`, `Ku2`, `AnisU`, `Kc1`, `Kc2`, `Kc3`, `AnisC1`, `AnisC2`, `Dind`,
+//!   `Dbulk`, `EnableDemag`, `SetSolver`, `FixDt`, `MinDt`, `MaxDt`,
+//!   `MaxErr`, `m = Uniform(…)`, `Vortex(…)`, `RandomMag()`,
+//!   `RandomMagSeed(…)`, `m.LoadFile(…)` and `ext_centerWall` go to the
+//!   config, so only before the first command;
+//! - `Run`, `Steps`, `Relax`, `Minimize`, `Save(m)`, `TableSave()` and
+//!   `Print` become script commands, a `Run` saving `m` and printing a table
+//!   row at the `AutoSave(m, …)` and `TableAutoSave(…)` intervals;
+//! - variables (`f := 10e9`) are kept, and evaluated where the config needs
+//!   them.
+//!
+//! Names are case-insensitive, as in mumax3. Anything else, e.g. regions,
+//! shapes, loops or other quantities, is left out with a note and copied into
+//! the script as a comment. As in mumax3, the demagnetizing field is on and
+//! `alpha` and `B_ext` are 0 unless set; unlike it, frames of `m` are only
+//! written by `Save` and `AutoSave`, besides the initial one.
+
+use nalgebra::Vector3;
+
+use crate::{
+    Config, Result,
+    config::{DemagConfig, DmiConfig},
+    expr::Expr,
+    field::DmiKind,
+    script::{STATE, split},
+    stepper::Method,
+    texture::Texture,
+    wall::{Direction, WallConfig},
+};
+
+/// A translated mumax3 script
+#[derive(Debug, Clone)]
+pub struct Import {
+    pub config: Config,
+    /// text of the control script
+    pub script: String,
+    /// statements left out or translated loosely, one per line
+    pub notes: Vec<String>,
+}
+
+/// Translate the text of a mumax3 script
+pub fn translate(text: &str) -> Result<Import> {
+    let mut t = Translator::default();
+    for (line, statement) in statements(text) {
+        if let Err(e) = t.statement(&statement) {
+            t.skip(line, &statement, &e.to_string());
+        }
+    }
+    t.finish()
+}
+
+/// The statements of `text` with their line numbers, comments removed and a
+/// `for`, `if` or `func` block kept whole
+fn statements(text: &str) -> Vec<(usize, String)> {
+    let mut out = Vec::new();
+    let mut current = String::new();
+    let (mut line, mut start) = (1, 1);
+    let (mut braces, mut parens, mut string) = (0, 0, false);
+    let mut flush = |current: &mut String, start| {
+        let statement = current.trim();
+        if !statement.is_empty() {
+            out.push((start, statement.to_string()));
+        }
+        current.clear();
+    };
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == '/' && !string && chars.peek() == Some(&'/') {
+            while chars.next_if(|&c| c != '\n').is_some() {}
+            continue;
+        }
+        if c == '/' && !string && chars.peek() == Some(&'*') {
+            chars.next();
+            let mut last = ' ';
+            for c in chars.by_ref() {
+                if c == '\n' {
+                    line += 1;
+                }
+                if last == '*' && c == '/' {
+                    break;
+                }
+                last = c;
+            }
+            current.push(' ');
+            continue;
+        }
+        match c {
+            '"' => string = !string,
+            _ if string => {}
+            '{' => braces += 1,
+            '}' => braces -= 1,
+            '(' => parens += 1,
+            ')' => parens -= 1,
+            _ => {}
+        }
+        let header = ["for ", "if ", "func ", "else"]
+            .iter()
+            .any(|k| current.trim_start().starts_with(k));
+        let top = braces == 0 && parens == 0 && !string;
+        if matches!(c, '\n' | ';') && top && !header {
+            flush(&mut current, start);
+        } else {
+            if current.trim().is_empty() && !c.is_whitespace() {
+                start = line;
+            }
+            current.push(c);
+            if c == '}' && top {
+                flush(&mut current, start);
+            }
+        }
+        if c == '\n' {
+            line += 1;
+        }
+    }
+    flush(&mut current, start);
+    out
+}
+
+/// State of a translation
+struct Translator {
+    config: Config,
+    script: Vec<String>,
+    notes: Vec<String>,
+    /// variables whose value is known, usable in the config
+    constants: Vec<(String, f64)>,
+    /// variables set by the script
+    names: Vec<String>,
+    /// whether a command has run, after which parameters change in the
+    /// script
+    started: bool,
+    /// whether the static field may not vanish
+    bias: bool,
+    /// whether the script applies a `field`
+    driven: bool,
+    /// `SetSolver` type and `FixDt` step, resolved at the end
+    solver: i64,
+    fixed: f64,
+    /// `AutoSave(m, …)` and `TableAutoSave(…)` intervals
+    autosave: Option<String>,
+    table: Option<String>,
+    /// whether `SetGridSize` and `SetCellSize` were seen
+    grid: bool,
+    cell: bool,
+}
+
+impl Default for Translator {
+    fn default() -> Self {
+        let mut config = Config::default();
+        config.params.alpha = 0.0;
+        config.params.h_ext = Vector3::zeros();
+        config.demag = Some(DemagConfig {});
+        config.output.every = u32::MAX.into();
+        Self {
+            config,
+            script: Vec::new(),
+            notes: Vec::new(),
+            constants: Vec::new(),
+            names: Vec::new(),
+            started: false,
+            bias: false,
+            driven: false,
+            solver: 5,
+            fixed: 0.0,
+            autosave: None,
+            table: None,
+            grid: false,
+            cell: false,
+        }
+    }
+}
+
+impl Translator {
+    fn statement(&mut self, text: &str) -> Result<()> {
+        if text.contains('{') {
+            return Err("blocks are not translated".into());
+        }
+        if let Some((name, declare, value)) = assignment(text) {
+            self.assign(&name.to_lowercase(), declare, &lowercase(value))
+        } else if let Some((name, args)) = text.strip_suffix(')').and_then(|t| t.split_once('(')) {
+            let args = if args.trim().is_empty() {
+                Vec::new()
+            } else {
+                split(args)
+            };
+            self.call(&name.trim().to_lowercase(), &args)
+        } else {
+            Err("not understood".into())
+        }
+    }
+
+    /// Leave out `text` at `line`, with a note saying `why`
+    fn skip(&mut self, line: usize, text: &str, why: &str) {
+        self.notes
+            .push(format!("line {line}: `{text}` left out: {why}"));
+        for l in text.lines() {
+            self.script.push(format!("# {l}"));
+        }
+    }
+
+    fn assign(&mut self, name: &str, declare: bool, value: &str) -> Result<()> {
+        match name {
+            "msat" => self.param("mu0_ms", &format!("4e-7 * pi * ({value})"))?,
+            "aex" => self.param("a_ex", value)?,
+            "alpha" => self.param("alpha", value)?,
+            "b_ext" => self.b_ext(value)?,
+            "m" => {
+                self.before("initial state")?;
+                if let Ok(args) = call(value, "vortex", 2) {
+                    self.config.initial.texture = Some(Texture::Vortex {
+                        centre: None,
+                        circulation: self.value(&args[0])?,
+                        polarity: self.value(&args[1])?,
+                        core: 10e-9,
+                    });
+                } else if value.replace(' ', "") == "randommag()" {
+                    self.config.initial.texture = Some(Texture::Random { seed: 0 });
+                } else if let Ok(args) = call(value, "randommagseed", 1) {
+                    let seed = self.value(&args[0])? as u64;
+                    self.config.initial.texture = Some(Texture::Random { seed });
+                } else {
+                    let args = call(value, "uniform", 3).map_err(|_| {
+                        "expected `Uniform(…)`, `Vortex(…)`, `RandomMag()` or `RandomMagSeed(…)`"
+                    })?;
+                    self.config.initial.m = self.vector(&args)?;
+                }
+            }
+            "ku1" | "ku2" | "anisu" => {
+                self.before("anisotropy")?;
+                let mut u = self.config.uniaxial.clone().unwrap_or_default();
+                match name {
+                    "ku1" => u.k1 = self.value(value)?,
+                    "ku2" => u.k2 = self.value(value)?,
+                    _ => u.axis = self.vector(&call(value, "vector", 3)?)?,
+                }
+                self.config.uniaxial = Some(u);
+            }
+            "kc1" | "kc2" | "kc3" | "anisc1" | "anisc2" => {
+                self.before("anisotropy")?;
+                let mut c = self.config.cubic.clone().unwrap_or_default();
+                match name {
+                    "kc1" => c.k1 = self.value(value)?,
+                    "kc2" => c.k2 = self.value(value)?,
+                    "kc3" => c.k3 = self.value(value)?,
+                    "anisc1" => c.c1 = self.vector(&call(value, "vector", 3)?)?,
+                    _ => c.c2 = self.vector(&call(value, "vector", 3)?)?,
+                }
+                self.config.cubic = Some(c);
+            }
+            "dind" | "dbulk" => {
+                self.before("DMI")?;
+                let kind = if name == "dind" {
+                    DmiKind::Interfacial
+                } else {
+                    DmiKind::Bulk
+                };
+                let d = self.value(value)?;
+                if let Some(dmi) = &self.config.dmi
+                    && dmi.kind != kind
+                    && dmi.d != 0.0
+                {
+                    return Err("nez has either interfacial or bulk DMI".into());
+                }
+                self.config.dmi = Some(DmiConfig { d, kind });
+            }
+            "enabledemag" => {
+                self.before("demagnetizing field")?;
+                self.config.demag = match value {
+                    "true" => Some(DemagConfig {}),
+                    "false" => None,
+                    _ => return Err("expected `true` or `false`".into()),
+                };
+            }
+            "fixdt" => {
+                self.before("solver")?;
+                self.fixed = self.value(value)?;
+                if self.fixed > 0.0 {
+                    self.config.params.dt = self.fixed;
+                }
+            }
+            "mindt" => {
+                self.before("solver")?;
+                self.config.solver.dt_min = self.value(value)?;
+            }
+            "maxdt" => {
+                self.before("solver")?;
+                let dt = self.value(value)?;
+                self.config.solver.dt_max = (dt > 0.0).then_some(dt);
+            }
+            "maxerr" => {
+                self.before("solver")?;
+                self.config.solver.tolerance = self.value(value)?;
+            }
+            _ if declare || self.names.iter().any(|n| n == name) => self.variable(name, value)?,
+            _ => return Err(format!("`{name}` is not supported").into()),
+        }
+        Ok(())
+    }
+
+    fn call(&mut self, name: &str, args: &[String]) -> Result<()> {
+        let arity = match name {
+            "setgridsize" | "setcellsize" | "setpbc" => 3,
+            "setmesh" => 9,
+            "run" | "steps" | "save" | "tableautosave" | "setsolver" | "m.loadfile"
+            | "ext_centerwall" => 1,
+            "saveas" | "autosave" => 2,
+            "relax" | "minimize" | "tablesave" => 0,
+            "print" => args.len(),
+            _ => return Err(format!("`{name}` is not supported").into()),
+        };
+        if args.len() != arity {
+            return Err(format!("`{name}` takes {arity} argument(s)").into());
+        }
+        let args: Vec<String> = args.iter().map(|a| lowercase(a)).collect();
+        match name {
+            "setgridsize" => self.grid_size(&args)?,
+            "setcellsize" => self.cell_size(&args)?,
+            "setpbc" => self.pbc(&args)?,
+            "setmesh" => {
+                self.grid_size(&args[..3])?;
+                self.cell_size(&args[3..6])?;
+                self.pbc(&args[6..])?;
+            }
+            "setsolver" => {
+                self.before("solver")?;
+                self.solver = self.value(&args[0])?.round() as i64;
+                if !(1..=6).contains(&self.solver) {
+                    return Err(format!("solver type {} is not supported", self.solver).into());
+                }
+            }
+            "m.loadfile" => {
+                self.before("initial state")?;
+                let file = args[0].trim_matches('"');
+                self.config.initial.file = Some(file.into());
+            }
+            "ext_centerwall" => {
+                self.before("moving window")?;
+                let component = match self.value(&args[0])?.round() as i64 {
+                    0 => Direction::X,
+                    1 => Direction::Y,
+                    2 => Direction::Z,
+                    c => return Err(format!("no component {c}").into()),
+                };
+                self.config.wall = Some(WallConfig {
+                    component,
+                    axis: Direction::X,
+                    center: true,
+                });
+            }
+            "autosave" => {
+                saved(&args[0])?;
+                self.autosave = self.interval(&args[1])?;
+            }
+            "tableautosave" => self.table = self.interval(&args[0])?,
+            "run" => self.run(&args[0])?,
+            "steps" => self.command(format!("steps({})", args[0]), &[&args[0]])?,
+            "relax" | "minimize" => self.command(format!("{name}()"), &[])?,
+            "save" | "saveas" => {
+                saved(&args[0])?;
+                self.command("save()".into(), &[])?;
+            }
+            "tablesave" => self.command("print(t, mx, my, mz)".into(), &[])?,
+            _ => {
+                let args: Vec<&str> = args
+                    .iter()
+                    .filter(|a| !a.starts_with('"'))
+                    .map(String::as_str)
+                    .collect();
+                if args.is_empty() {
+                    return Err("only numbers are printed".into());
+                }
+                self.emit(format!("print({})", args.join(", ")), &args)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Set parameter `name` of the config, or of the script once started
+    fn param(&mut self, name: &str, value: &str) -> Result<()> {
+        if self.started {
+            return self.emit(format!("{name} = {value}"), &[value]);
+        }
+        let v = self.value(value)?;
+        let p = &mut self.config.params;
+        match name {
+            "mu0_ms" => p.mu0_ms = v,
+            "a_ex" => p.a_ex = v,
+            _ => p.alpha = v,
+        }
+        Ok(())
+    }
+
+    fn b_ext(&mut self, value: &str) -> Result<()> {
+        let args = call(value, "vector", 3)?;
+        let parts: Vec<&str> = args.iter().map(String::as_str).collect();
+        let timed = parts
+            .iter()
+            .map(|p| self.parse(p))
+            .collect::<Result<Vec<_>>>()?
+            .iter()
+            .any(|e| e.uses(0));
+        let vector = format!("[{}]", args.join(", "));
+        if timed {
+            if self.bias {
+                self.emit("h_ext = [0, 0, 0]".into(), &[])?;
+                self.bias = false;
+            }
+            self.emit(format!("field = {vector}"), &parts)?;
+            self.driven = true;
+        } else if self.started || self.driven {
+            self.emit(format!("h_ext = {vector}"), &parts)?;
+            self.bias = true;
+            if self.driven {
+                self.emit("field = [0, 0, 0]".into(), &[])?;
+                self.driven = false;
+            }
+        } else {
+            self.config.params.h_ext = self.vector(&args)?;
+            self.bias = true;
+        }
+        Ok(())
+    }
+
+    fn variable(&mut self, name: &str, value: &str) -> Result<()> {
+        if STATE.contains(&name) {
+            return Err(format!("`{name}` is reserved by nez scripts").into());
+        }
+        self.emit(format!("{name} = {value}"), &[value])?;
+        if !self.names.iter().any(|n| n == name) {
+            self.names.push(name.to_string());
+        }
+        self.constants.retain(|(n, _)| n != name);
+        if let Ok(v) = self.value(value) {
+            self.constants.push((name.to_string(), v));
+        }
+        Ok(())
+    }
+
+    /// `Run(duration)`, saving and printing at the autosave intervals
+    fn run(&mut self, duration: &str) -> Result<()> {
+        let Some(every) = self.autosave.clone().or_else(|| self.table.clone()) else {
+            return self.command(format!("run({duration})"), &[duration]);
+        };
+        if let (Some(a), Some(t)) = (&self.autosave, &self.table)
+            && a != t
+        {
+            self.notes.push(format!(
+                "`Run({duration})`: table rows are printed at the `AutoSave` interval {a}"
+            ));
+        }
+        self.parse(duration)?;
+        self.parse(&every)?;
+        let count = match (self.value(duration), self.value(&every)) {
+            (Ok(d), Ok(e)) => {
+                let n = d / e;
+                if (n - n.round()).abs() < 1e-6 {
+                    format!("{}", n.round())
+                } else {
+                    format!("{}", n.floor())
+                }
+            }
+            _ => format!("floor(({duration}) / ({every}) + 1e-6)"),
+        };
+        self.started = true;
+        self.script.push(format!("for _k = 1, {count}"));
+        self.script.push(format!("    run({every})"));
+        if self.autosave.is_some() {
+            self.script.push("    save()".into());
+        }
+        if self.table.is_some() {
+            self.script.push("    print(t, mx, my, mz)".into());
+        }
+        self.script.push("end".into());
+        if let (Ok(d), Ok(e)) = (self.value(duration), self.value(&every)) {
+            let rest = d - (d / e + 1e-6).floor() * e;
+            if rest > 1e-6 * e {
+                self.script.push(format!("run({rest:e})"));
+            }
+        }
+        Ok(())
+    }
+
+    /// Append a command acting on the simulation
+    fn command(&mut self, line: String, exprs: &[&str]) -> Result<()> {
+        self.emit(line, exprs)?;
+        self.started = true;
+        Ok(())
+    }
+
+    /// Append `line` to the script, once every expression of `exprs` parses
+    fn emit(&mut self, line: String, exprs: &[&str]) -> Result<()> {
+        for e in exprs {
+            self.parse(e)?;
+        }
+        self.script.push(line);
+        Ok(())
+    }
+
+    /// Parse `expr` over the names readable in a script
+    fn parse(&self, expr: &str) -> Result<Expr> {
+        let names: Vec<&str> = STATE
+            .iter()
+            .copied()
+            .chain(self.names.iter().map(String::as_str))
+            .collect();
+        Expr::parse(expr, &names)
+    }
+
+    /// Value of `expr` over the constants so far
+    fn value(&self, expr: &str) -> Result<f64> {
+        let names: Vec<&str> = self.constants.iter().map(|(n, _)| n.as_str()).collect();
+        let values: Vec<f64> = self.constants.iter().map(|(_, v)| *v).collect();
+        let parsed =
+            Expr::parse(expr, &names).map_err(|e| format!("`{expr}` is not a constant: {e}"))?;
+        Ok(parsed.eval(&values))
+    }
+
+    fn vector(&self, args: &[String]) -> Result<Vector3<f64>> {
+        Ok(Vector3::new(
+            self.value(&args[0])?,
+            self.value(&args[1])?,
+            self.value(&args[2])?,
+        ))
+    }
+
+    /// Counts of `args`, which must be whole numbers
+    fn counts(&self, args: &[String]) -> Result<[usize; 3]> {
+        let mut n = [0; 3];
+        for (n, a) in n.iter_mut().zip(args) {
+            let v = self.value(a)?;
+            if !(v >= 0.0 && v.fract() == 0.0) {
+                return Err(format!("`{a}` is not a count").into());
+            }
+            *n = v as usize;
+        }
+        Ok(n)
+    }
+
+    fn grid_size(&mut self, args: &[String]) -> Result<()> {
+        self.before("mesh")?;
+        let [nx, ny, nz] = self.counts(args)?;
+        let mesh = &mut self.config.mesh;
+        (mesh.nx, mesh.ny, mesh.nz) = (nx, ny, nz);
+        self.grid = true;
+        Ok(())
+    }
+
+    fn cell_size(&mut self, args: &[String]) -> Result<()> {
+        self.before("mesh")?;
+        let d = self.vector(args)?;
+        let mesh = &mut self.config.mesh;
+        (mesh.dx, mesh.dy, mesh.dz) = (d.x, d.y, d.z);
+        self.cell = true;
+        Ok(())
+    }
+
+    fn pbc(&mut self, args: &[String]) -> Result<()> {
+        self.before("mesh")?;
+        self.config.mesh.pbc = self.counts(args)?;
+        Ok(())
+    }
+
+    /// Interval of an autosave, `None` when not positive
+    fn interval(&self, every: &str) -> Result<Option<String>> {
+        match self.value(every) {
+            Ok(v) if v <= 0.0 => Ok(None),
+            Ok(_) => Ok(Some(every.to_string())),
+            Err(_) => self.parse(every).map(|_| Some(every.to_string())),
+        }
+    }
+
+    /// Fail unless `what`, part of the config, is set before the first command
+    fn before(&self, what: &str) -> Result<()> {
+        if self.started {
+            Err(format!("the {what} is set in the config, so only before the first command").into())
+        } else {
+            Ok(())
+        }
+    }
+
+    fn finish(mut self) -> Result<Import> {
+        if !(self.grid && self.cell) {
+            return Err("the script must call `SetGridSize` and `SetCellSize`".into());
+        }
+        let fixed = self.fixed > 0.0;
+        let method = match (self.solver, fixed) {
+            (1 | 2, true) => Method::Heun,
+            (_, true) => Method::Rk4,
+            (1..=3, false) => Method::Rk23,
+            _ => Method::Rk45,
+        };
+        let exact = matches!(
+            (self.solver, fixed),
+            (2, true) | (4, true) | (3, false) | (5, false)
+        );
+        if !exact {
+            self.notes.push(format!(
+                "solver type {} {} runs as `{}`",
+                self.solver,
+                if fixed { "with FixDt" } else { "without FixDt" },
+                format!("{method:?}").to_lowercase()
+            ));
+        }
+        self.config.solver.method = method;
+        self.config.validate()?;
+        let mut script = self.script.join("\n");
+        script.push('\n');
+        Ok(Import {
+            config: self.config,
+            script,
+            notes: self.notes,
+        })
+    }
+}
+
+/// Name, whether declared with `:=`, and value of an assignment
+fn assignment(text: &str) -> Option<(&str, bool, &str)> {
+    let (name, value, declare) = match text.split_once(":=") {
+        Some((n, v)) => (n, v, true),
+        None => {
+            let (n, v) = text.split_once('=')?;
+            (n, v, false)
+        }
+    };
+    let name = name.trim();
+    let valid = name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') && !name.is_empty();
+    (valid && !value.starts_with('=')).then(|| (name, declare, value.trim()))
+}
+
+/// Arguments of `value`, a call of `name` with `arity` arguments
+fn call(value: &str, name: &str, arity: usize) -> Result<Vec<String>> {
+    let args = value
+        .strip_prefix(name)
+        .and_then(|v| v.trim().strip_prefix('('))
+        .and_then(|v| v.strip_suffix(')'))
+        .map(split)
+        .filter(|a| a.len() == arity)
+        .ok_or_else(|| format!("expected `{name}(…)` with {arity} arguments"))?;
+    Ok(args)
+}
+
+/// Fail unless `quantity` is the magnetization
+fn saved(quantity: &str) -> Result<()> {
+    if quantity == "m" {
+        Ok(())
+    } else {
+        Err("only `m` is saved".into())
+    }
+}
+
+/// `text` with names folded to lower case, as mumax3 ignores their case,
+/// but not strings
+fn lowercase(text: &str) -> String {
+    let mut string = false;
+    text.chars()
+        .map(|c| {
+            if c == '"' {
+                string = !string;
+            }
+            if string { c } else { c.to_ascii_lowercase() }
+        })
+        .collect()
+}
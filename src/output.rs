@@ -0,0 +1,1355 @@
+use nalgebra::Vector3;
+use std::{
+    collections::{HashMap, hash_map::Entry},
+    fs,
+    ops::Range,
+    path::Path,
+    sync::{
+        Arc, LazyLock, Mutex,
+        mpsc::{Receiver, SyncSender, sync_channel},
+    },
+    thread::JoinHandle,
+    time::Instant,
+};
+
+// ---- Zarr stuff -----------------------------------------------------------
+use zarrs::{
+    array::{
+        Array, ArrayBuilder, DataType, FillValue, codec::BytesToBytesCodecTraits,
+        codec::array_to_bytes::sharding::ShardingCodecBuilder,
+        codec::bytes_to_bytes::gzip::GzipCodec,
+    },
+    array_subset::ArraySubset,
+    filesystem::FilesystemStore,
+    group::{Group, GroupBuilder},
+    storage::{ReadableWritableListableStorage, StorePrefix, store::MemoryStore},
+};
+// ---------------------------------------------------------------------------
+
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    Config, Mesh, Result, VectorField,
+    config::{FieldsConfig, OutputConfig},
+    parallel::chunk,
+    profile::{self, Section},
+    vector_field::wide,
+};
+
+/// Number of rows per chunk of `t` and `table`
+const ROW_CHUNK: u64 = 1024;
+
+type StoreArray = Array<dyn zarrs::storage::ReadableWritableListableStorageTraits>;
+
+/// Compression of the chunks of `m`. Zstd, Blosc and LZ4 need nez to be
+/// built with the `zstd` or `blosc` feature.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Codec {
+    None,
+    /// levels 0–9
+    #[default]
+    Gzip,
+    /// levels -7–22
+    Zstd,
+    /// Blosc with its Zstd compressor and byte shuffling, levels 0–9
+    Blosc,
+    /// Blosc with its LZ4 compressor and byte shuffling, levels 0–9
+    Lz4,
+}
+
+/// Type `m` and `m2` are stored as, whatever the precision of the
+/// computation (cargo feature `f32`, see [`crate::vector_field::Real`]);
+/// checkpoints are always written in full
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Precision {
+    #[default]
+    F64,
+    /// half the size, with about 7 significant digits
+    F32,
+    /// a quarter of the size: integers `round(I16_SCALE m)`, a resolution
+    /// of 3e-5, with the `scale_factor` attribute that decodes them in
+    /// xarray and netCDF tools
+    I16,
+}
+
+/// Stored value of a component of 1 with [`Precision::I16`]
+const I16_SCALE: f64 = i16::MAX as f64;
+
+impl Precision {
+    fn data_type(self) -> DataType {
+        match self {
+            Precision::F64 => DataType::Float64,
+            Precision::F32 => DataType::Float32,
+            Precision::I16 => DataType::Int16,
+        }
+    }
+
+    fn fill_value(self) -> FillValue {
+        match self {
+            Precision::F64 => FillValue::from(0.0f64),
+            Precision::F32 => FillValue::from(0.0f32),
+            Precision::I16 => FillValue::from(0i16),
+        }
+    }
+}
+
+impl Codec {
+    /// Level used when none is given
+    pub fn default_level(self) -> i32 {
+        match self {
+            Codec::None => 0,
+            Codec::Gzip | Codec::Blosc | Codec::Lz4 => 5,
+            Codec::Zstd => 3,
+        }
+    }
+
+    /// Check that the codec is built in and accepts `level`
+    pub fn check(self, level: i32) -> Result<()> {
+        let (range, feature, built) = match self {
+            Codec::None => return Ok(()),
+            Codec::Gzip => (0..=9, "", true),
+            Codec::Zstd => (-7..=22, "zstd", cfg!(feature = "zstd")),
+            Codec::Blosc | Codec::Lz4 => (0..=9, "blosc", cfg!(feature = "blosc")),
+        };
+        let name = format!("{self:?}").to_lowercase();
+        if !built {
+            return Err(format!(
+                "`{name}` compression needs nez built with the `{feature}` feature"
+            )
+            .into());
+        }
+        if !range.contains(&level) {
+            return Err(format!(
+                "`{name}` compression level must lie in {}..={}, got {level}",
+                range.start(),
+                range.end()
+            )
+            .into());
+        }
+        Ok(())
+    }
+
+    /// The bytes-to-bytes codecs applied to every inner chunk of values
+    /// stored with `precision`
+    fn codecs(
+        self,
+        level: i32,
+        // the element size Blosc shuffles by
+        #[cfg_attr(not(feature = "blosc"), allow(unused_variables))] precision: Precision,
+    ) -> Result<Vec<Arc<dyn BytesToBytesCodecTraits>>> {
+        self.check(level)?;
+        Ok(match self {
+            Codec::None => vec![],
+            Codec::Gzip => vec![Arc::new(GzipCodec::new(level as u32)?)],
+            #[cfg(feature = "zstd")]
+            Codec::Zstd => vec![Arc::new(
+                zarrs::array::codec::bytes_to_bytes::zstd::ZstdCodec::new(level, false),
+            )],
+            #[cfg(feature = "blosc")]
+            Codec::Blosc | Codec::Lz4 => {
+                use zarrs::array::codec::bytes_to_bytes::blosc::{
+                    BloscCodec, BloscCompressionLevel, BloscCompressor, BloscShuffleMode,
+                };
+                let compressor = match self {
+                    Codec::Lz4 => BloscCompressor::LZ4,
+                    _ => BloscCompressor::Zstd,
+                };
+                let level = BloscCompressionLevel::try_from(level as u8)
+                    .map_err(|l| format!("invalid Blosc level {l}"))?;
+                vec![Arc::new(BloscCodec::new(
+                    compressor,
+                    level,
+                    None,
+                    BloscShuffleMode::Shuffle,
+                    Some(match precision {
+                        Precision::F64 => size_of::<f64>(),
+                        Precision::F32 => size_of::<f32>(),
+                        Precision::I16 => size_of::<i16>(),
+                    }),
+                )?)]
+            }
+            #[allow(unreachable_patterns)] // codecs left out of the build
+            _ => unreachable!("checked above"),
+        })
+    }
+}
+
+/// Solver state beyond the magnetization needed to continue a run exactly
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct Checkpoint {
+    /// steps taken
+    pub step: u64,
+    /// simulated time (s)
+    pub t: f64,
+    /// size of the next time step (s)
+    pub dt: f64,
+    /// snapshots written
+    pub frame: u64,
+    /// state of the random number generator, for stochastic runs
+    pub rng: Option<[u64; 4]>,
+    /// distance the window has moved to follow a domain wall (m)
+    #[serde(default)]
+    pub wall_shift: f64,
+}
+
+/// A checkpoint as recorded in the store
+#[derive(Deserialize, Serialize)]
+struct Stored {
+    #[serde(flatten)]
+    checkpoint: Checkpoint,
+    /// FNV-1a hash of the `config` attribute it belongs to
+    config_hash: u64,
+    /// index of the `checkpoint` slot holding its magnetization
+    slot: u64,
+}
+
+/// Arrangement of the table and attributes of a store
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Layout {
+    /// `table` as a single (row, column) array
+    #[default]
+    Nez,
+    /// as written by amumax: `table/<column>` as one array per column, named
+    /// as in amumax (`t`, `mx`, `E_total`, `E_exch`, `maxTorque`, ...), and
+    /// the root attributes `Nx`, `dx`, `Tx`, `PBC`, ... besides ours
+    Amumax,
+}
+
+/// Name of the amumax table column holding the nez column `column`
+fn amumax_column(column: &str) -> &str {
+    match column {
+        "E_exchange" => "E_exch",
+        "E_zeeman" => "E_Zeeman",
+        "E_uniaxial" => "E_anis",
+        "E_cubic" => "E_anis_cubic",
+        "max_torque" => "maxTorque",
+        "topological_charge" => "ext_topologicalchargelattice",
+        other => other,
+    }
+}
+
+/// Arrays of the observables table
+enum TableArrays {
+    /// [`Layout::Nez`]: one (row, column) array
+    Matrix(Box<StoreArray>),
+    /// [`Layout::Amumax`]: one array per column
+    Columns(Vec<StoreArray>),
+}
+
+/// Zarr store holding the magnetization as `m` with shape (time, z, y, x, vec),
+/// the simulated time of every snapshot as `t`, and optionally a `table` of
+/// scalar observables, arranged as set by its [`Layout`].
+///
+/// The latest [`Checkpoint`] goes to the root attribute `checkpoint`, with its
+/// magnetization in one of the two slots of the `checkpoint` array, written
+/// alternately so that an interrupted write leaves the previous one intact.
+///
+/// Snapshots are compressed and stored by a background thread while the
+/// simulation goes on: [`write`](Self::write) only copies `m`, and waits
+/// only if the frame before the last is still being written. Reading the
+/// store, writing a checkpoint and dropping the output wait for the frames
+/// under way; a failed write is reported by the next call.
+pub struct ZarrOutput {
+    store: ReadableWritableListableStorage,
+    array: StoreArray,
+    /// `m2`, the magnetization of a second sublattice, if simulated
+    sublattice: Option<StoreArray>,
+    /// absent in stores written before `t` was recorded
+    time: Option<StoreArray>,
+    /// observables, one row per record, see [`ZarrOutput::create_table`]
+    table: Option<TableArrays>,
+    /// arrays of the group `fields` written to so far, by name, see
+    /// [`ZarrOutput::write_fields`]
+    fields: HashMap<String, StoreArray>,
+    layout: Layout,
+    /// (nz, ny, nx)
+    dims: [u64; 3],
+    writer: Writer,
+}
+
+/// FNV-1a hash of `bytes`, stable across platforms and releases
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xCBF2_9CE4_8422_2325, |h, &b| {
+        (h ^ b as u64).wrapping_mul(0x0100_0000_01B3)
+    })
+}
+
+impl ZarrOutput {
+    /// Create a fresh store at `config.path` holding no snapshots yet; the
+    /// time axis grows as they are written. `m` is compressed and chunked as
+    /// set in `config`. An existing store at the same path is deleted first.
+    pub fn create(config: &OutputConfig, mesh: &Mesh) -> Result<Self> {
+        let path = &config.path;
+        let level = config.level.unwrap_or(config.codec.default_level());
+        let codecs = config.codec.codecs(level, config.precision)?;
+        let store = fresh_storage(path)?;
+
+        // root group
+        GroupBuilder::new()
+            .build(store.clone(), "/")?
+            .store_metadata()?;
+
+        // shape: (time, z, y, x, vec)  →  (0, nz, ny, nx, 3); each shard holds
+        // `config.shard` whole frames
+        let [nz, ny, nx] = [mesh.nz, mesh.ny, mesh.nx].map(|n| n as u64);
+        let shape = vec![0, nz, ny, nx, 3];
+        let chunk = vec![config.shard, nz, ny, nx, 3].try_into()?;
+
+        let [cz, cy, cx] = config.chunk.unwrap_or([nz, ny, nx]);
+        let mut sharding_codec_builder = ShardingCodecBuilder::new(
+            vec![1, cz, cy, cx, 3].try_into()?, // inner chunk shape
+        );
+        sharding_codec_builder.bytes_to_bytes_codecs(codecs);
+
+        let precision = config.precision;
+        let mut array =
+            ArrayBuilder::new(shape, precision.data_type(), chunk, precision.fill_value())
+                .array_to_bytes_codec(sharding_codec_builder.build_arc())
+                .dimension_names(Some(["t", "z", "y", "x", "comp"]))
+                .build(store.clone(), "/m")?;
+        if precision == Precision::I16 {
+            array
+                .attributes_mut()
+                .insert("scale_factor".into(), (1.0 / I16_SCALE).into());
+        }
+
+        array.store_metadata()?; // write metadata once
+
+        let time = ArrayBuilder::new(
+            vec![0],
+            DataType::Float64,
+            vec![ROW_CHUNK].try_into()?,
+            FillValue::from(0.0f64),
+        )
+        .dimension_names(Some(["t"]))
+        .build(store.clone(), "/t")?;
+        time.store_metadata()?;
+
+        let output = Self {
+            store,
+            array,
+            sublattice: None,
+            time: Some(time),
+            table: None,
+            fields: HashMap::new(),
+            layout: config.layout,
+            dims: [nz, ny, nx],
+            writer: Writer::spawn()?,
+        };
+        output.hand_over()?;
+        Ok(output)
+    }
+
+    /// Open an existing store for reading and further writes
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        if !is_remote(path) && !path.exists() {
+            return Err(format!("{} does not exist", path.display()).into());
+        }
+        let store = storage(path)?;
+        let array = Array::open(store.clone(), "/m")
+            .map_err(|e| format!("cannot open `m` in {}: {e}", path.display()))?;
+        let shape = array.shape();
+        if shape.len() != 5 || shape[4] != 3 {
+            return Err(format!("`m` has unexpected shape {shape:?}").into());
+        }
+        let dims = [shape[1], shape[2], shape[3]];
+        let sublattice = Array::open(store.clone(), "/m2").ok();
+        let time = Array::open(store.clone(), "/t").ok();
+        let (table, layout) = match Array::open(store.clone(), "/table") {
+            Ok(table) => (Some(TableArrays::Matrix(Box::new(table))), Layout::Nez),
+            Err(_) => match Group::open(store.clone(), "/table") {
+                Ok(group) => {
+                    let columns: Vec<String> = serde_json::from_value(
+                        group
+                            .attributes()
+                            .get("columns")
+                            .ok_or("`table` has no `columns` attribute")?
+                            .clone(),
+                    )?;
+                    let arrays = columns
+                        .iter()
+                        .map(|c| {
+                            Array::open(store.clone(), &format!("/table/{}", amumax_column(c)))
+                        })
+                        .collect::<std::result::Result<_, _>>()?;
+                    (Some(TableArrays::Columns(arrays)), Layout::Amumax)
+                }
+                Err(_) => (None, Layout::Nez),
+            },
+        };
+        let output = Self {
+            store,
+            array,
+            sublattice,
+            time,
+            table,
+            fields: HashMap::new(),
+            layout,
+            dims,
+            writer: Writer::spawn()?,
+        };
+        output.hand_over()?;
+        Ok(output)
+    }
+
+    /// Give the writer thread handles of its own on `m`, `m2` and `t`, with
+    /// their metadata as it is now, to store the frames after in
+    fn hand_over(&self) -> Result<()> {
+        let copy = |array: &StoreArray| {
+            Array::new_with_metadata(
+                self.store.clone(),
+                array.path().as_str(),
+                array.metadata().clone(),
+            )
+        };
+        let arrays = std::iter::once(&self.array)
+            .chain(&self.sublattice)
+            .map(copy)
+            .collect::<std::result::Result<_, _>>()?;
+        let time = self.time.as_ref().map(copy).transpose()?;
+        self.writer
+            .send(Task::Arrays(Box::new(Handles { arrays, time })))
+    }
+
+    /// Shape of `m`: (time, z, y, x, vec)
+    pub fn shape(&self) -> &[u64] {
+        self.array.shape()
+    }
+
+    /// Length of the time axis. Stores written before it could grow were
+    /// preallocated, so it may exceed the number of snapshots written.
+    pub fn capacity(&self) -> u64 {
+        self.array.shape()[0]
+    }
+
+    /// Number of leading snapshots actually written
+    pub fn frames(&self) -> Result<u64> {
+        self.flush()?;
+        let capacity = self.capacity();
+        if capacity == 0 {
+            return Ok(0);
+        }
+        // a shard may hold several frames; the partly written last one of a
+        // growing store is cut off by its shape
+        let per_shard = self.array.chunk_shape(&[0; 5])?[0].get();
+        let mut shards = 0;
+        while shards * per_shard < capacity
+            && self
+                .array
+                .retrieve_chunk_if_exists(&[shards, 0, 0, 0, 0])?
+                .is_some()
+        {
+            shards += 1;
+        }
+        Ok((shards * per_shard).min(capacity))
+    }
+
+    /// Root group attributes
+    pub fn attributes(&self) -> Result<serde_json::Map<String, serde_json::Value>> {
+        Ok(Group::open(self.store.clone(), "/")?.attributes().clone())
+    }
+
+    /// Set the root group attribute `key`
+    pub fn write_attribute(&self, key: &str, value: serde_json::Value) -> Result<()> {
+        let mut group = Group::open(self.store.clone(), "/")?;
+        group.attributes_mut().insert(key.into(), value);
+        group.store_metadata()?;
+        Ok(())
+    }
+
+    /// Record the run configuration on the root group as `config`, and the
+    /// quantities needed to interpret the data without it as separate
+    /// attributes: the mesh, cell size, time step, material parameters and
+    /// solver settings on the root group, and the cell size and units on `m`
+    /// and `t`. A second sublattice gets its own array `m2`, shaped and
+    /// encoded like `m`, and `output.fields` the group `fields`, see
+    /// [`ZarrOutput::write_fields`].
+    pub fn write_config(&mut self, config: &Config) -> Result<()> {
+        let mesh = &config.mesh;
+        let [dx, dy, dz] = mesh.cell();
+        let mut group = Group::open(self.store.clone(), "/")?;
+        let attributes = group.attributes_mut();
+        for (key, value) in [
+            ("config", serde_json::to_value(config)?),
+            ("nez_version", env!("CARGO_PKG_VERSION").into()),
+            ("mesh", serde_json::to_value(mesh)?),
+            ("dx", dx.into()),
+            ("dy", dy.into()),
+            ("dz", dz.into()),
+            ("dt", config.params.dt.into()),
+            ("params", serde_json::to_value(&config.params)?),
+            ("solver", serde_json::to_value(&config.solver)?),
+        ] {
+            attributes.insert(key.into(), value);
+        }
+        if self.layout == Layout::Amumax {
+            let size = [mesh.nx, mesh.ny, mesh.nz];
+            let cell = [dx, dy, dz];
+            for (k, axis) in ["x", "y", "z"].into_iter().enumerate() {
+                attributes.insert(format!("N{axis}"), size[k].into());
+                attributes.insert(format!("T{axis}"), (size[k] as f64 * cell[k]).into());
+            }
+            attributes.insert("PBC".into(), serde_json::to_value(mesh.pbc)?);
+        }
+        group.store_metadata()?;
+
+        let attributes = self.array.attributes_mut();
+        attributes.insert("dx".into(), dx.into());
+        attributes.insert("dy".into(), dy.into());
+        attributes.insert("dz".into(), dz.into());
+        attributes.insert("unit".into(), "1".into());
+        attributes.insert(
+            "description".into(),
+            "unit magnetization m = M / Ms per cell".into(),
+        );
+        self.array.store_metadata()?;
+        if config.sublattice.is_some() {
+            let mut m2 =
+                Array::new_with_metadata(self.store.clone(), "/m2", self.array.metadata().clone())?;
+            m2.attributes_mut().insert(
+                "description".into(),
+                "unit magnetization of the second sublattice per cell".into(),
+            );
+            m2.store_metadata()?;
+            self.sublattice = Some(m2);
+        }
+        if let Some(time) = &mut self.time {
+            time.attributes_mut().insert("unit".into(), "s".into());
+            time.store_metadata()?;
+        }
+        self.hand_over()?;
+        if let Some(fields) = &config.output.fields {
+            self.create_fields(fields, &config.output)?;
+        }
+        Ok(())
+    }
+
+    /// Create the group `fields` holding the arrays of `fields`, with no
+    /// frames yet, compressed like `m`, in single precision unless `m` is in
+    /// double, and their own time axis `t`
+    fn create_fields(&mut self, fields: &FieldsConfig, output: &OutputConfig) -> Result<()> {
+        let precision = match output.precision {
+            Precision::F64 => Precision::F64,
+            // the fields are no unit vectors
+            Precision::F32 | Precision::I16 => Precision::F32,
+        };
+        let level = output.level.unwrap_or(output.codec.default_level());
+        let codecs = output.codec.codecs(level, precision)?;
+        let mut group = GroupBuilder::new().build(self.store.clone(), "/fields")?;
+        group
+            .attributes_mut()
+            .insert("every".into(), fields.every.into());
+        group.store_metadata()?;
+        let [nz, ny, nx] = self.dims;
+        for (name, vectors) in fields.arrays() {
+            let (shape, dimensions, description) = if vectors {
+                let shape = vec![0, nz, ny, nx, 3];
+                (shape, vec!["t", "z", "y", "x", "comp"], "field per cell")
+            } else {
+                (
+                    vec![0, nz, ny, nx],
+                    vec!["t", "z", "y", "x"],
+                    "density per cell",
+                )
+            };
+            let mut chunk = shape.clone();
+            chunk[0] = 1;
+            let mut array = ArrayBuilder::new(
+                shape,
+                precision.data_type(),
+                chunk.try_into()?,
+                precision.fill_value(),
+            )
+            .bytes_to_bytes_codecs(codecs.clone())
+            .dimension_names(Some(dimensions))
+            .build(self.store.clone(), &format!("/fields/{name}"))?;
+            let unit = if vectors { "T" } else { "J/m3" };
+            let attributes = array.attributes_mut();
+            attributes.insert("unit".into(), unit.into());
+            attributes.insert("description".into(), format!("{name} {description}").into());
+            array.store_metadata()?;
+            self.fields.insert(name, array);
+        }
+        let mut time = ArrayBuilder::new(
+            vec![0],
+            DataType::Float64,
+            vec![ROW_CHUNK].try_into()?,
+            FillValue::from(0.0f64),
+        )
+        .dimension_names(Some(["t"]))
+        .build(self.store.clone(), "/fields/t")?;
+        time.attributes_mut().insert("unit".into(), "s".into());
+        time.store_metadata()?;
+        self.fields.insert("t".into(), time);
+        Ok(())
+    }
+
+    /// Write frame `frame` of the arrays of the group `fields`, each named
+    /// as in [`FieldsConfig::arrays`], at time `t`, overwriting an earlier
+    /// one or appending right after the last
+    pub fn write_fields(
+        &mut self,
+        frame: u64,
+        t: f64,
+        values: &[(String, FieldFrame)],
+    ) -> Result<()> {
+        let time = self.field_array("t")?;
+        let grown = grow(time, frame)?;
+        time.store_array_subset_elements(&time_subset(frame)?, &[t])?;
+        if grown {
+            time.store_metadata()?;
+        }
+        for (name, value) in values {
+            let array = self.field_array(name)?;
+            let grown = grow(array, frame)?;
+            let subset = frame_subset(array.shape(), frame);
+            match value {
+                FieldFrame::Vectors(v) => {
+                    let mut staged = Staging::new(array.data_type());
+                    staged.fill(v);
+                    staged.store(array, &subset, 0..3 * v.len())?;
+                }
+                FieldFrame::Scalars(e) if *array.data_type() == DataType::Float32 => {
+                    let single: Vec<f32> = e.iter().map(|&v| v as f32).collect();
+                    array.store_array_subset_elements(&subset, &single)?;
+                }
+                FieldFrame::Scalars(e) => array.store_array_subset_elements(&subset, e)?,
+            }
+            if grown {
+                array.store_metadata()?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Frame `frame` of the array `name` of the group `fields`, components
+    /// cell after cell for vectors
+    pub fn read_field(&self, name: &str, frame: u64) -> Result<Vec<f64>> {
+        let array = Array::open(self.store.clone(), &format!("/fields/{name}"))
+            .map_err(|e| format!("cannot open `fields/{name}`: {e}"))?;
+        if frame >= array.shape()[0] {
+            return Err(format!("`fields/{name}` has no frame {frame}").into());
+        }
+        retrieve_floats(&array, &frame_subset(array.shape(), frame))
+    }
+
+    /// The array `name` of the group `fields`, opened on first use
+    fn field_array(&mut self, name: &str) -> Result<&mut StoreArray> {
+        Ok(match self.fields.entry(name.to_string()) {
+            Entry::Occupied(array) => array.into_mut(),
+            Entry::Vacant(slot) => slot.insert(
+                Array::open(self.store.clone(), &format!("/fields/{name}"))
+                    .map_err(|e| format!("cannot open `fields/{name}`: {e}"))?,
+            ),
+        })
+    }
+
+    /// Configuration recorded by [`ZarrOutput::write_config`]
+    pub fn config(&self) -> Result<Config> {
+        let value = self
+            .attributes()?
+            .remove("config")
+            .ok_or("store has no `config` attribute")?;
+        let config: Config = serde_json::from_value(value)?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Record `checkpoint` with its magnetization `m`, replacing the previous
+    /// one. Both sublattices of `m` are stacked along z.
+    pub fn write_checkpoint(&self, checkpoint: &Checkpoint, m: &VectorField) -> Result<()> {
+        // the frames it counts are in the store first
+        self.flush()?;
+        let attributes = self.attributes()?;
+        let config = attributes
+            .get("config")
+            .ok_or("store has no `config` attribute")?;
+        let slot = match attributes.get("checkpoint") {
+            Some(old) => 1 - serde_json::from_value::<Stored>(old.clone())?.slot,
+            None => 0,
+        };
+        let array = match Array::open(self.store.clone(), "/checkpoint") {
+            Ok(array) => array,
+            Err(_) => {
+                let [_, ny, nx] = self.dims;
+                let nz = m.len() as u64 / (ny * nx);
+                let array = ArrayBuilder::new(
+                    vec![2, nz, ny, nx, 3],
+                    DataType::Float64,
+                    vec![1, nz, ny, nx, 3].try_into()?,
+                    FillValue::from(0.0f64),
+                )
+                .build(self.store.clone(), "/checkpoint")?;
+                array.store_metadata()?;
+                array
+            }
+        };
+        array.store_array_subset_elements(&frame_subset(array.shape(), slot), &flatten(m))?;
+        let stored = Stored {
+            checkpoint: checkpoint.clone(),
+            config_hash: fnv1a(config.to_string().as_bytes()),
+            slot,
+        };
+        self.write_attribute("checkpoint", serde_json::to_value(stored)?)
+    }
+
+    /// The latest checkpoint and its magnetization, if any. Fails if it was
+    /// written for a different config than the one recorded now.
+    pub fn checkpoint(&self) -> Result<Option<(Checkpoint, VectorField)>> {
+        let attributes = self.attributes()?;
+        let Some(value) = attributes.get("checkpoint") else {
+            return Ok(None);
+        };
+        let stored: Stored = serde_json::from_value(value.clone())?;
+        let config = attributes
+            .get("config")
+            .ok_or("store has no `config` attribute")?;
+        if fnv1a(config.to_string().as_bytes()) != stored.config_hash {
+            return Err("checkpoint does not belong to the recorded config".into());
+        }
+        let array = Array::open(self.store.clone(), "/checkpoint")?;
+        let flat: Vec<f64> =
+            array.retrieve_array_subset_elements(&frame_subset(array.shape(), stored.slot))?;
+        Ok(Some((stored.checkpoint, unflatten(&flat))))
+    }
+
+    /// Store `data` as the array `name`, of `shape` with the axes `dims`, in
+    /// a single chunk, replacing an earlier array of that name, in a group
+    /// created as needed. Used for the results of an analysis of the
+    /// snapshots.
+    pub fn write_dataset(
+        &self,
+        name: &str,
+        shape: &[u64],
+        dims: &[&str],
+        data: &[f64],
+        attributes: serde_json::Map<String, serde_json::Value>,
+    ) -> Result<()> {
+        write_dataset(&self.store, name, shape, dims, data, attributes)
+    }
+
+    /// Read the snapshot at index `frame`, followed by that of the second
+    /// sublattice if the store has one
+    pub fn read(&self, frame: u64) -> Result<VectorField> {
+        self.flush()?;
+        let mut flat = retrieve_floats(&self.array, &self.subset(frame))?;
+        if let Some(m2) = &self.sublattice {
+            flat.extend(retrieve_floats(m2, &self.subset(frame))?);
+        }
+        Ok(unflatten(&flat))
+    }
+
+    /// Simulated time of snapshot `frame` (s), if the store records it
+    pub fn time(&self, frame: u64) -> Result<Option<f64>> {
+        let Some(time) = &self.time else {
+            return Ok(None);
+        };
+        self.flush()?;
+        let t: Vec<f64> = time.retrieve_array_subset_elements(&time_subset(frame)?)?;
+        Ok(Some(t[0]))
+    }
+
+    fn subset(&self, frame: u64) -> ArraySubset {
+        let [nz, ny, nx] = self.dims;
+        frame_subset(&[frame, nz, ny, nx, 3], frame)
+    }
+
+    /// Write the snapshot at index `frame`, taken at time `t`, overwriting an
+    /// earlier one or appending right after the last, in the background. The
+    /// moments past the mesh, of a second sublattice, go to `m2`.
+    pub fn write(&mut self, frame: u64, t: f64, m: &VectorField) -> Result<()> {
+        let subset = self.subset(frame);
+        let cells = self.dims.iter().product::<u64>() as usize;
+        // the writer grows its own handles likewise
+        grow(&mut self.array, frame)?;
+        if let Some(m2) = &mut self.sublattice {
+            grow(m2, frame)?;
+        }
+        if let Some(time) = &mut self.time {
+            grow(time, frame)?;
+        }
+        let mut values = self.writer.buffer(self.array.data_type());
+        values.fill(m);
+        self.writer.send(Task::Frame(Box::new(Frame {
+            values,
+            t,
+            frame,
+            subset,
+            cells,
+        })))
+    }
+
+    /// Wait for the snapshots under way to be written
+    pub fn flush(&self) -> Result<()> {
+        let (done, wait) = sync_channel(1);
+        self.writer.send(Task::Flush(done))?;
+        let _ = wait.recv();
+        self.writer.failure()
+    }
+
+    /// Create an empty table with one column per name in `columns`,
+    /// replacing any previous one: a `table` array, or a `table` group of
+    /// one array per column in the amumax layout
+    pub fn create_table(&mut self, columns: &[String]) -> Result<()> {
+        self.store.erase_prefix(&StorePrefix::new("table/")?)?;
+        let names = serde_json::to_value(columns)?;
+        let table = match self.layout {
+            Layout::Nez => {
+                let mut table = ArrayBuilder::new(
+                    vec![0, columns.len() as u64],
+                    DataType::Float64,
+                    vec![ROW_CHUNK, columns.len() as u64].try_into()?,
+                    FillValue::from(f64::NAN),
+                )
+                .dimension_names(Some(["row", "column"]))
+                .build(self.store.clone(), "/table")?;
+                table.attributes_mut().insert("columns".into(), names);
+                table.store_metadata()?;
+                TableArrays::Matrix(Box::new(table))
+            }
+            Layout::Amumax => {
+                let mut group = GroupBuilder::new().build(self.store.clone(), "/table")?;
+                group.attributes_mut().insert("columns".into(), names);
+                group.store_metadata()?;
+                let arrays = columns
+                    .iter()
+                    .map(|c| -> Result<StoreArray> {
+                        let array = ArrayBuilder::new(
+                            vec![0],
+                            DataType::Float64,
+                            vec![ROW_CHUNK].try_into()?,
+                            FillValue::from(f64::NAN),
+                        )
+                        .dimension_names(Some(["row"]))
+                        .build(self.store.clone(), &format!("/table/{}", amumax_column(c)))?;
+                        array.store_metadata()?;
+                        Ok(array)
+                    })
+                    .collect::<Result<_>>()?;
+                TableArrays::Columns(arrays)
+            }
+        };
+        self.table = Some(table);
+        Ok(())
+    }
+
+    /// Column names of the table, if the store has one
+    pub fn table_columns(&self) -> Result<Option<Vec<String>>> {
+        let attributes = match &self.table {
+            None => return Ok(None),
+            Some(TableArrays::Matrix(table)) => table.attributes().clone(),
+            Some(TableArrays::Columns(_)) => Group::open(self.store.clone(), "/table")?
+                .attributes()
+                .clone(),
+        };
+        let columns = attributes
+            .get("columns")
+            .ok_or("`table` has no `columns` attribute")?;
+        Ok(Some(serde_json::from_value(columns.clone())?))
+    }
+
+    /// Number of rows of the table, 0 if the store has none
+    pub fn table_rows(&self) -> u64 {
+        match &self.table {
+            None => 0,
+            Some(TableArrays::Matrix(table)) => table.shape()[0],
+            Some(TableArrays::Columns(arrays)) => arrays.first().map_or(0, |a| a.shape()[0]),
+        }
+    }
+
+    /// Row `row` of the table, in the order of [`table_columns`](Self::table_columns)
+    pub fn table_row(&self, row: u64) -> Result<Vec<f64>> {
+        match self.table.as_ref().ok_or("store has no `table`")? {
+            TableArrays::Matrix(table) => {
+                let columns = table.shape()[1];
+                let subset = ArraySubset::new_with_start_shape(vec![row, 0], vec![1, columns])?;
+                Ok(table.retrieve_array_subset_elements(&subset)?)
+            }
+            TableArrays::Columns(arrays) => arrays
+                .iter()
+                .map(|a| Ok(a.retrieve_array_subset_elements::<f64>(&time_subset(row)?)?[0]))
+                .collect(),
+        }
+    }
+
+    /// Column `column` of the table, every row
+    pub fn table_column(&self, column: usize) -> Result<Vec<f64>> {
+        let rows = self.table_rows();
+        match self.table.as_ref().ok_or("store has no `table`")? {
+            TableArrays::Matrix(table) => {
+                let subset =
+                    ArraySubset::new_with_ranges(&[0..rows, column as u64..column as u64 + 1]);
+                Ok(table.retrieve_array_subset_elements(&subset)?)
+            }
+            TableArrays::Columns(arrays) => {
+                let array = arrays.get(column).ok_or("no such table column")?;
+                Ok(array
+                    .retrieve_array_subset_elements(&ArraySubset::new_with_shape(vec![rows]))?)
+            }
+        }
+    }
+
+    /// Write `values` as row `row` of the table, overwriting an earlier row
+    /// or appending; rows skipped over read as NaN
+    pub fn write_row(&mut self, row: u64, values: &[f64]) -> Result<()> {
+        match self.table.as_mut().ok_or("store has no `table`")? {
+            TableArrays::Matrix(table) => {
+                let columns = table.shape()[1];
+                if values.len() as u64 != columns {
+                    return Err(
+                        format!("{} values for {columns} table columns", values.len()).into(),
+                    );
+                }
+                let grown = row >= table.shape()[0];
+                if grown {
+                    table.set_shape(vec![row + 1, columns]);
+                }
+                let subset = ArraySubset::new_with_start_shape(vec![row, 0], vec![1, columns])?;
+                table.store_array_subset_elements(&subset, values)?;
+                if grown {
+                    table.store_metadata()?;
+                }
+            }
+            TableArrays::Columns(arrays) => {
+                if values.len() != arrays.len() {
+                    return Err(format!(
+                        "{} values for {} table columns",
+                        values.len(),
+                        arrays.len()
+                    )
+                    .into());
+                }
+                for (array, &value) in arrays.iter_mut().zip(values) {
+                    let grown = row >= array.shape()[0];
+                    if grown {
+                        array.set_shape(vec![row + 1]);
+                    }
+                    array.store_array_subset_elements(&time_subset(row)?, &[value])?;
+                    if grown {
+                        array.store_metadata()?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Frames queued besides the one being written, at most; `write` waits
+/// beyond that, so that at most two staged copies of `m` are held
+const QUEUE: usize = 1;
+
+/// Thread writing the snapshots of a [`ZarrOutput`] in the background
+struct Writer {
+    tasks: SyncSender<Task>,
+    /// staged frames once written, reused for the next ones
+    spare: Mutex<Receiver<Staging>>,
+    /// the first error of the thread, after which it writes no more
+    failure: Arc<Mutex<Option<String>>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+enum Task {
+    /// the handles the frames after are stored in
+    Arrays(Box<Handles>),
+    Frame(Box<Frame>),
+    /// signalled once the frames before are written
+    Flush(SyncSender<()>),
+}
+
+/// A snapshot to write
+struct Frame {
+    values: Staging,
+    t: f64,
+    frame: u64,
+    subset: ArraySubset,
+    /// cells of the mesh, past which `values` holds the second sublattice
+    cells: usize,
+}
+
+/// The writer's own handles on the arrays of the snapshots, copied from
+/// those of [`ZarrOutput`] once they are created
+struct Handles {
+    /// `m`, and `m2` if simulated
+    arrays: Vec<StoreArray>,
+    /// absent in stores written before `t` was recorded
+    time: Option<StoreArray>,
+}
+
+impl Frame {
+    /// Store the frame in `handles`, grown to hold it, then the metadata of
+    /// the arrays it grew, so that an interrupted append leaves a consistent
+    /// store
+    fn store(&self, handles: &mut Handles) -> Result<()> {
+        let n = 3 * self.cells;
+        let mut grown = Vec::with_capacity(handles.arrays.len());
+        for (k, array) in handles.arrays.iter_mut().enumerate() {
+            grown.push(grow(array, self.frame)?);
+            self.values.store(array, &self.subset, k * n..(k + 1) * n)?;
+        }
+        if let Some(time) = &mut handles.time {
+            let grown = grow(time, self.frame)?;
+            time.store_array_subset_elements(&time_subset(self.frame)?, &[self.t])?;
+            if grown {
+                time.store_metadata()?;
+            }
+        }
+        // `m` last
+        for (grown, array) in grown.into_iter().zip(&handles.arrays).rev() {
+            if grown {
+                array.store_metadata()?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Writer {
+    fn spawn() -> Result<Self> {
+        let (tasks, queue) = sync_channel::<Task>(QUEUE);
+        let (recycle, spare) = sync_channel(QUEUE + 1);
+        let failure = Arc::new(Mutex::new(None));
+        let failed = failure.clone();
+        let thread = std::thread::Builder::new()
+            .name("nez-writer".into())
+            .spawn(move || {
+                let mut handles = None;
+                for task in queue {
+                    match task {
+                        Task::Arrays(arrays) => handles = Some(*arrays),
+                        Task::Frame(frame) => {
+                            // after a failure, the frames are dropped
+                            if failed.lock().is_ok_and(|f| f.is_none()) {
+                                let start = Instant::now();
+                                let stored = match &mut handles {
+                                    Some(handles) => frame.store(handles),
+                                    None => Err("no arrays to store it in".into()),
+                                };
+                                match stored {
+                                    Ok(()) => profile::record(
+                                        Section::Output,
+                                        "frame of m, in the background",
+                                        start,
+                                    ),
+                                    Err(e) => {
+                                        let mut failed =
+                                            failed.lock().unwrap_or_else(|e| e.into_inner());
+                                        *failed = Some(format!("frame {}: {e}", frame.frame));
+                                    }
+                                }
+                            }
+                            let _ = recycle.try_send(frame.values);
+                        }
+                        Task::Flush(done) => {
+                            let _ = done.send(());
+                        }
+                    }
+                }
+            })?;
+        Ok(Self {
+            tasks,
+            spare: Mutex::new(spare),
+            failure,
+            thread: Some(thread),
+        })
+    }
+
+    /// A frame written already, to stage the next one of `data_type` into
+    fn buffer(&self, data_type: &DataType) -> Staging {
+        let spare = self.spare.lock().unwrap_or_else(|e| e.into_inner());
+        spare.try_recv().unwrap_or_else(|_| Staging::new(data_type))
+    }
+
+    fn send(&self, task: Task) -> Result<()> {
+        self.failure()?;
+        self.tasks
+            .send(task)
+            .map_err(|_| "the snapshot writer has stopped".into())
+    }
+
+    /// The error that stopped the writer, if any
+    fn failure(&self) -> Result<()> {
+        match &*self.failure.lock().unwrap_or_else(|e| e.into_inner()) {
+            Some(e) => Err(format!("writing a snapshot failed: {e}").into()),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for Writer {
+    fn drop(&mut self) {
+        // closing the queue ends the thread once the frames in it are written
+        let (tasks, _) = sync_channel(0);
+        drop(std::mem::replace(&mut self.tasks, tasks));
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+        if let Err(e) = self.failure() {
+            tracing::error!("{e}");
+        }
+    }
+}
+
+/// A frame of an array of the group `fields`
+pub enum FieldFrame {
+    /// one vector per cell
+    Vectors(VectorField),
+    /// one value per cell
+    Scalars(Vec<f64>),
+}
+
+/// Components of `m` as written to the store: cell after cell in (x, y, z)
+/// order, converted to the type of the arrays. Staged straight from the
+/// components of the state and kept from frame to frame, so that writing a
+/// frame allocates nothing and copies `m` once.
+enum Staging {
+    F64(Vec<f64>),
+    F32(Vec<f32>),
+    I16(Vec<i16>),
+}
+
+impl Staging {
+    /// Empty, for arrays of `data_type`
+    fn new(data_type: &DataType) -> Self {
+        match data_type {
+            DataType::Float32 => Self::F32(Vec::new()),
+            DataType::Int16 => Self::I16(Vec::new()),
+            _ => Self::F64(Vec::new()),
+        }
+    }
+
+    /// Overwrite with the components of `m`, rounded to the [`Precision`]
+    /// of the arrays
+    fn fill(&mut self, m: &VectorField) {
+        match self {
+            Self::F64(values) => interleave(m, values, |v| v),
+            Self::F32(values) => interleave(m, values, |v| v as f32),
+            Self::I16(values) => interleave(m, values, |v| {
+                (v.clamp(-1.0, 1.0) * I16_SCALE).round() as i16
+            }),
+        }
+    }
+
+    /// Store the values `range` over `subset` of `array`
+    fn store(&self, array: &StoreArray, subset: &ArraySubset, range: Range<usize>) -> Result<()> {
+        match self {
+            Self::F64(values) => array.store_array_subset_elements(subset, &values[range])?,
+            Self::F32(values) => array.store_array_subset_elements(subset, &values[range])?,
+            Self::I16(values) => array.store_array_subset_elements(subset, &values[range])?,
+        }
+        Ok(())
+    }
+}
+
+/// Overwrite `out` with the components of `m` in (x, y, z) order, cell
+/// after cell, each passed through `convert`, keeping the allocation
+fn interleave<T: Copy + Default + Send>(
+    m: &VectorField,
+    out: &mut Vec<T>,
+    convert: impl Fn(f64) -> T + Sync,
+) {
+    out.resize(3 * m.len(), T::default());
+    let n = chunk();
+    out.par_chunks_mut(3 * n).enumerate().for_each(|(b, out)| {
+        for (k, v) in out.chunks_exact_mut(3).enumerate() {
+            let i = b * n + k;
+            v[0] = convert(wide(m.x[i]));
+            v[1] = convert(wide(m.y[i]));
+            v[2] = convert(wide(m.z[i]));
+        }
+    });
+}
+
+/// Zarr store holding only a root group with attributes and arrays, whose
+/// subgroups are written as stores of their own, e.g. the cases of `nez
+/// sweep` (see [`crate::sweep`]) and the realizations of `nez ensemble`
+pub struct ZarrGroup {
+    store: ReadableWritableListableStorage,
+}
+
+impl ZarrGroup {
+    /// Create a fresh store at `path` holding a root group with
+    /// `attributes`. An existing store at the same path is deleted first.
+    pub fn create(
+        path: &Path,
+        attributes: serde_json::Map<String, serde_json::Value>,
+    ) -> Result<Self> {
+        let store = fresh_storage(path)?;
+        let mut group = GroupBuilder::new().build(store.clone(), "/")?;
+        group.attributes_mut().extend(attributes);
+        group.store_metadata()?;
+        Ok(Self { store })
+    }
+
+    /// Set the root group attribute `key`
+    pub fn write_attribute(&self, key: &str, value: serde_json::Value) -> Result<()> {
+        let mut group = Group::open(self.store.clone(), "/")?;
+        group.attributes_mut().insert(key.into(), value);
+        group.store_metadata()?;
+        Ok(())
+    }
+
+    /// Store `data` as the array `name`, as [`ZarrOutput::write_dataset`]
+    /// does
+    pub fn write_dataset(
+        &self,
+        name: &str,
+        shape: &[u64],
+        dims: &[&str],
+        data: &[f64],
+        attributes: serde_json::Map<String, serde_json::Value>,
+    ) -> Result<()> {
+        write_dataset(&self.store, name, shape, dims, data, attributes)
+    }
+}
+
+/// Whether `path` is a URL such as `s3://bucket/run.zarr` or
+/// `memory://run` rather than a directory
+pub fn is_remote(path: &Path) -> bool {
+    path.to_str().is_some_and(|p| p.contains("://"))
+}
+
+/// Stores held in memory, by the name following `memory://`. They live as
+/// long as the process, so that one written by a simulation can be opened
+/// again by the same path, and vanish with it: tests and benchmarks use
+/// them to leave nothing on disk.
+static MEMORY: LazyLock<Mutex<HashMap<String, ReadableWritableListableStorage>>> =
+    LazyLock::new(Mutex::default);
+
+/// Storage for a store at `path`: a directory, a store in memory for
+/// `memory://<name>`, or an object store URL when built with the
+/// `object-store` feature
+fn storage(path: &Path) -> Result<ReadableWritableListableStorage> {
+    if !is_remote(path) {
+        return Ok(Arc::new(FilesystemStore::new(path)?));
+    }
+    let url = path.to_str().unwrap_or_default();
+    if let Some(name) = url.strip_prefix("memory://") {
+        let mut stores = MEMORY.lock().map_err(|_| "in-memory stores poisoned")?;
+        let store = stores
+            .entry(name.into())
+            .or_insert_with(|| Arc::new(MemoryStore::new()));
+        return Ok(store.clone());
+    }
+    #[cfg(feature = "object-store")]
+    return crate::remote::storage(url).map_err(|e| format!("{url}: {e}").into());
+    #[cfg(not(feature = "object-store"))]
+    Err(format!("{url}: object stores need nez built with the `object-store` feature").into())
+}
+
+/// Store `data` as the array `name` of `store`, see
+/// [`ZarrOutput::write_dataset`]
+fn write_dataset(
+    store: &ReadableWritableListableStorage,
+    name: &str,
+    shape: &[u64],
+    dims: &[&str],
+    data: &[f64],
+    attributes: serde_json::Map<String, serde_json::Value>,
+) -> Result<()> {
+    if let Some((group, _)) = name.rsplit_once('/')
+        && !group.is_empty()
+        && Group::open(store.clone(), group).is_err()
+    {
+        GroupBuilder::new()
+            .build(store.clone(), group)?
+            .store_metadata()?;
+    }
+    let mut array = ArrayBuilder::new(
+        shape.to_vec(),
+        DataType::Float64,
+        shape
+            .iter()
+            .map(|&n| n.max(1))
+            .collect::<Vec<_>>()
+            .try_into()?,
+        FillValue::from(f64::NAN),
+    )
+    .dimension_names(Some(dims.to_vec()))
+    .build(store.clone(), name)?;
+    array.attributes_mut().extend(attributes);
+    array.store_metadata()?;
+    array.store_array_subset_elements(&ArraySubset::new_with_shape(shape.to_vec()), data)?;
+    Ok(())
+}
+
+/// Empty storage at `path`, deleting what was there
+fn fresh_storage(path: &Path) -> Result<ReadableWritableListableStorage> {
+    if is_remote(path) {
+        let store = storage(path)?;
+        store.erase_prefix(&StorePrefix::root())?;
+        Ok(store)
+    } else {
+        if path.exists() {
+            fs::remove_dir_all(path)?;
+        }
+        storage(path)
+    }
+}
+
+/// Extend the time axis of `array` to include `frame`, which may lie at most
+/// one past its end. Returns whether it grew, in which case the metadata must
+/// be stored once the data is in, so that an interrupted append leaves a
+/// consistent store.
+fn grow(array: &mut StoreArray, frame: u64) -> Result<bool> {
+    let mut shape = array.shape().to_vec();
+    if frame > shape[0] {
+        return Err(format!("frame {frame} would leave a gap after {} frames", shape[0]).into());
+    }
+    if frame < shape[0] {
+        return Ok(false);
+    }
+    shape[0] = frame + 1;
+    array.set_shape(shape);
+    Ok(true)
+}
+
+/// Frame `frame` of an array of (time, z, y, x) or (time, z, y, x, vec)
+/// `shape`
+fn frame_subset(shape: &[u64], frame: u64) -> ArraySubset {
+    // the time axis, then the whole of (z, y, x) and the components if any
+    let ranges: Vec<_> = std::iter::once(frame..frame + 1)
+        .chain(shape[1..].iter().map(|&n| 0..n))
+        .collect();
+    ArraySubset::new_with_ranges(&ranges)
+}
+
+/// The single element of `t` at index `frame`
+fn time_subset(frame: u64) -> Result<ArraySubset> {
+    Ok(ArraySubset::new_with_start_shape(vec![frame], vec![1])?)
+}
+
+/// Components of `m` in (x, y, z) order, cell after cell
+fn flatten(m: &VectorField) -> Vec<f64> {
+    let mut flat = Vec::new();
+    interleave(m, &mut flat, |v| v);
+    flat
+}
+
+/// The values of `subset` of `array`, of any [`Precision`]
+fn retrieve_floats(array: &StoreArray, subset: &ArraySubset) -> Result<Vec<f64>> {
+    Ok(match array.data_type() {
+        DataType::Float32 => array
+            .retrieve_array_subset_elements::<f32>(subset)?
+            .into_iter()
+            .map(f64::from)
+            .collect(),
+        DataType::Int16 => array
+            .retrieve_array_subset_elements::<i16>(subset)?
+            .into_iter()
+            .map(|v| f64::from(v) / I16_SCALE)
+            .collect(),
+        _ => array.retrieve_array_subset_elements(subset)?,
+    })
+}
+
+fn unflatten(flat: &[f64]) -> VectorField {
+    flat.chunks_exact(3)
+        .map(|v| Vector3::new(v[0], v[1], v[2]))
+        .collect()
+}
@@ -0,0 +1,316 @@
+//! OOMMF OVF files, as used by OOMMF, mumax3 and mumax-view. Version 2.0 is
+//! written; rectangular meshes in versions 1.0 and 2.0 can be read back,
+//! with vector or scalar data.
+
+use nalgebra::Vector3;
+use std::{
+    fs::{self, File},
+    io::{BufWriter, Write},
+    ops::{Add, Mul},
+    path::Path,
+};
+
+use crate::{Mesh, Result, VectorField};
+
+/// Encoding of the data block
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OvfData {
+    /// little-endian f32, as written by mumax3
+    #[default]
+    Binary4,
+    /// little-endian f64
+    Binary8,
+    Text,
+}
+
+impl OvfData {
+    fn label(self) -> &'static str {
+        match self {
+            OvfData::Binary4 => "Binary 4",
+            OvfData::Binary8 => "Binary 8",
+            OvfData::Text => "Text",
+        }
+    }
+}
+
+/// Write the magnetization `m` on `mesh` as a single-segment OVF 2.0 file,
+/// recording the simulated time `t` (s) when known
+pub fn write(
+    path: impl AsRef<Path>,
+    mesh: &Mesh,
+    m: &VectorField,
+    t: Option<f64>,
+    data: OvfData,
+) -> Result<()> {
+    if m.len() != mesh.len() {
+        return Err(format!(
+            "{} cells do not fit the {} of the mesh",
+            m.len(),
+            mesh.len()
+        )
+        .into());
+    }
+    let mut w = BufWriter::new(File::create(path)?);
+    let [nx, ny, nz] = mesh.size();
+    let [dx, dy, dz] = mesh.cell();
+    writeln!(w, "# OOMMF OVF 2.0")?;
+    writeln!(w, "# Segment count: 1")?;
+    writeln!(w, "# Begin: Segment")?;
+    writeln!(w, "# Begin: Header")?;
+    writeln!(w, "# Title: m")?;
+    writeln!(w, "# meshtype: rectangular")?;
+    writeln!(w, "# meshunit: m")?;
+    let axes = [("x", nx, dx), ("y", ny, dy), ("z", nz, dz)];
+    for (axis, _, _) in axes {
+        writeln!(w, "# {axis}min: 0")?;
+    }
+    for (axis, n, d) in axes {
+        writeln!(w, "# {axis}max: {:e}", n as f64 * d)?;
+    }
+    writeln!(w, "# valuedim: 3")?;
+    writeln!(w, "# valuelabels: m_x m_y m_z")?;
+    writeln!(w, "# valueunits: 1 1 1")?;
+    if let Some(t) = t {
+        writeln!(w, "# Desc: Total simulation time: {t:e} s")?;
+    }
+    for (axis, _, d) in axes {
+        writeln!(w, "# {axis}base: {:e}", d / 2.0)?;
+    }
+    for (axis, n, _) in axes {
+        writeln!(w, "# {axis}nodes: {n}")?;
+    }
+    for (axis, _, d) in axes {
+        writeln!(w, "# {axis}stepsize: {d:e}")?;
+    }
+    writeln!(w, "# End: Header")?;
+    writeln!(w, "# Begin: Data {}", data.label())?;
+    // nodes run x fastest, then y, then z: the storage order of the mesh
+    match data {
+        OvfData::Binary4 => {
+            w.write_all(&1_234_567.0f32.to_le_bytes())?;
+            for v in m.iter().flat_map(|v| [v.x, v.y, v.z]) {
+                w.write_all(&(v as f32).to_le_bytes())?;
+            }
+            writeln!(w)?;
+        }
+        OvfData::Binary8 => {
+            w.write_all(&123_456_789_012_345.0f64.to_le_bytes())?;
+            for v in m.iter().flat_map(|v| [v.x, v.y, v.z]) {
+                w.write_all(&v.to_le_bytes())?;
+            }
+            writeln!(w)?;
+        }
+        OvfData::Text => {
+            for v in m.iter() {
+                writeln!(w, "{:e} {:e} {:e}", v.x, v.y, v.z)?;
+            }
+        }
+    }
+    writeln!(w, "# End: Data {}", data.label())?;
+    writeln!(w, "# End: Segment")?;
+    w.flush()?;
+    Ok(())
+}
+
+/// Vector (or, with `T = f64`, scalar) field read from a rectangular-mesh OVF
+/// file
+#[derive(Debug, Clone)]
+pub struct Ovf<T = Vector3<f64>> {
+    /// node counts along x, y, z
+    pub nodes: [usize; 3],
+    /// node spacing along x, y, z (in `meshunit`)
+    pub step: [f64; 3],
+    /// one value per node, x fastest
+    pub data: Vec<T>,
+}
+
+/// Read the first segment of an OVF 1.0 or 2.0 file with a rectangular mesh
+/// and three values per node
+pub fn read(path: impl AsRef<Path>) -> Result<Ovf> {
+    let ovf = read_values(path.as_ref(), 3)?;
+    Ok(Ovf {
+        nodes: ovf.nodes,
+        step: ovf.step,
+        data: ovf
+            .data
+            .chunks_exact(3)
+            .map(|v| Vector3::new(v[0], v[1], v[2]))
+            .collect(),
+    })
+}
+
+/// Read the first segment of an OVF 1.0 or 2.0 file with a rectangular mesh
+/// and one value per node, as mumax3 saves scalar quantities
+pub fn read_scalar(path: impl AsRef<Path>) -> Result<Ovf<f64>> {
+    read_values(path.as_ref(), 1)
+}
+
+fn read_values(path: &Path, dim: usize) -> Result<Ovf<f64>> {
+    let bytes = fs::read(path).map_err(|e| format!("cannot read {}: {e}", path.display()))?;
+    parse(&bytes, dim).map_err(|e| format!("{}: {e}", path.display()).into())
+}
+
+/// The `dim` values of every node, flattened
+fn parse(bytes: &[u8], dim: usize) -> Result<Ovf<f64>> {
+    let mut version = None;
+    let mut nodes = [0; 3];
+    let mut step = [0.0; 3];
+    let mut valuedim = 3;
+    let mut pos = 0;
+    let data = loop {
+        let end = bytes[pos..]
+            .iter()
+            .position(|&b| b == b'\n')
+            .map_or(bytes.len(), |n| pos + n + 1);
+        if pos == end {
+            return Err("no data block".into());
+        }
+        let line = String::from_utf8_lossy(&bytes[pos..end]);
+        pos = end;
+        let line = line.trim();
+        if version.is_none() {
+            version = match line {
+                l if l.starts_with("# OOMMF OVF 2.0") => Some(2),
+                l if l.starts_with("# OOMMF: rectangular mesh v1.0") => Some(1),
+                _ => return Err("not an OVF 1.0 or 2.0 file".into()),
+            };
+            continue;
+        }
+        let Some((key, value)) = line.trim_start_matches('#').split_once(':') else {
+            continue;
+        };
+        let (key, value) = (key.trim().to_lowercase(), value.trim());
+        let number = || -> Result<f64> {
+            value
+                .parse()
+                .map_err(|_| format!("bad value `{value}` for `{key}`").into())
+        };
+        match key.as_str() {
+            "meshtype" if value != "rectangular" => {
+                return Err(format!("unsupported meshtype `{value}`").into());
+            }
+            "valuedim" => valuedim = number()? as usize,
+            "xnodes" | "ynodes" | "znodes" => nodes[axis(&key)] = number()? as usize,
+            "xstepsize" | "ystepsize" | "zstepsize" => step[axis(&key)] = number()?,
+            "begin" if value.to_lowercase().starts_with("data") => break value.to_lowercase(),
+            _ => {}
+        }
+    };
+    if valuedim != dim {
+        return Err(format!("expected {dim} values per node, got {valuedim}").into());
+    }
+    if nodes.contains(&0) {
+        return Err("missing node counts".into());
+    }
+    let n = nodes.iter().product::<usize>() * dim;
+    let big_endian = version == Some(1);
+    let values: Vec<f64> = match data.as_str() {
+        "data binary 4" => {
+            let raw = binary(&bytes[pos..], n + 1, 4)?;
+            let word = |b: &[u8]| {
+                let b = b.try_into().expect("4 bytes");
+                if big_endian {
+                    f32::from_be_bytes(b)
+                } else {
+                    f32::from_le_bytes(b)
+                }
+            };
+            if word(&raw[..4]) != 1_234_567.0 {
+                return Err("bad Binary 4 check value".into());
+            }
+            raw[4..].chunks_exact(4).map(|b| word(b) as f64).collect()
+        }
+        "data binary 8" => {
+            let raw = binary(&bytes[pos..], n + 1, 8)?;
+            let word = |b: &[u8]| {
+                let b = b.try_into().expect("8 bytes");
+                if big_endian {
+                    f64::from_be_bytes(b)
+                } else {
+                    f64::from_le_bytes(b)
+                }
+            };
+            if word(&raw[..8]) != 123_456_789_012_345.0 {
+                return Err("bad Binary 8 check value".into());
+            }
+            raw[8..].chunks_exact(8).map(word).collect()
+        }
+        "data text" => String::from_utf8_lossy(&bytes[pos..])
+            .lines()
+            .take_while(|l| !l.starts_with('#'))
+            .flat_map(str::split_whitespace)
+            .take(n)
+            .map(|v| v.parse().map_err(|_| format!("bad number `{v}`")))
+            .collect::<std::result::Result<_, _>>()?,
+        other => return Err(format!("unsupported `{other}` block").into()),
+    };
+    if values.len() != n {
+        return Err(format!("expected {n} values, found {}", values.len()).into());
+    }
+    Ok(Ovf {
+        nodes,
+        step,
+        data: values,
+    })
+}
+
+/// Index of the axis a `x…`, `y…` or `z…` key refers to
+fn axis(key: &str) -> usize {
+    match key.as_bytes()[0] {
+        b'x' => 0,
+        b'y' => 1,
+        _ => 2,
+    }
+}
+
+/// The first `words` words of `size` bytes of a binary block
+fn binary(bytes: &[u8], words: usize, size: usize) -> Result<&[u8]> {
+    bytes
+        .get(..words * size)
+        .ok_or_else(|| "data block is truncated".into())
+}
+
+impl<T: Copy + Add<Output = T> + Mul<f64, Output = T>> Ovf<T> {
+    /// Values at the cell centres of `mesh`, trilinearly interpolated when
+    /// the node counts differ. The file is stretched over the whole mesh.
+    pub fn interpolate(&self, mesh: &Mesh) -> Vec<T> {
+        let [sx, sy, sz] = self.nodes;
+        let at = |x: usize, y: usize, z: usize| self.data[x + sx * (y + sy * z)];
+        // source index coordinate of the centre of target cell c, its lower
+        // node and the weight of the upper one
+        let map = |c: usize, n: usize, s: usize| {
+            let u = ((c as f64 + 0.5) * s as f64 / n as f64 - 0.5).clamp(0.0, (s - 1) as f64);
+            let lo = (u.floor() as usize).min(s - 1);
+            (lo, (lo + 1).min(s - 1), u - lo as f64)
+        };
+        (0..mesh.len())
+            .map(|i| {
+                let [x, y, z] = mesh.coords(i);
+                let (x0, x1, fx) = map(x, mesh.nx, sx);
+                let (y0, y1, fy) = map(y, mesh.ny, sy);
+                let (z0, z1, fz) = map(z, mesh.nz, sz);
+                let lerp = |a: T, b: T, f: f64| a * (1.0 - f) + b * f;
+                let plane = |z| {
+                    lerp(
+                        lerp(at(x0, y0, z), at(x1, y0, z), fx),
+                        lerp(at(x0, y1, z), at(x1, y1, z), fx),
+                        fy,
+                    )
+                };
+                lerp(plane(z0), plane(z1), fz)
+            })
+            .collect()
+    }
+}
+
+impl Ovf {
+    /// Directions on `mesh`, interpolated as in [`Ovf::interpolate`] and
+    /// normalised; cells where they vanish point along `fallback`.
+    pub fn resample(&self, mesh: &Mesh, fallback: Vector3<f64>) -> VectorField {
+        let fallback = fallback.normalize();
+        self.interpolate(mesh)
+            .into_iter()
+            .map(|v| v.try_normalize(1e-12).unwrap_or(fallback))
+            .collect()
+    }
+}
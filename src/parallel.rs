@@ -0,0 +1,118 @@
+//! Threads of the computation: how many there are, which cores they run
+//! on, and how finely the loops over cells are split between them.
+//!
+//! The command line runs every command in a pool of its own, built from
+//! `[parallel]` of the config, with `--threads` taking precedence:
+//!
+//! ```toml
+//! [parallel]
+//! threads = 4       # 0: one per core the process may use
+//! chunk = 512       # cells per parallel task
+//! pin = true        # bind every thread to one of those cores
+//! ```
+//!
+//! Every parallel loop over cells hands out tasks of at least `chunk`
+//! cells, so that a chain of a few hundred spins is not split into
+//! per-cell tasks whose scheduling costs more than their work. On a shared
+//! cluster node, the threads default to the cores the process is allowed
+//! to run on rather than to every core of the node; pinning them there
+//! keeps each one on its core and its caches.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use rayon::{ThreadPool, ThreadPoolBuilder};
+use serde::{Deserialize, Serialize};
+
+use crate::Result;
+
+/// Cells per parallel task unless set otherwise
+pub const CHUNK: usize = 4096;
+
+/// Cells per parallel task of the loops over cells
+static CHUNK_SIZE: AtomicUsize = AtomicUsize::new(CHUNK);
+
+/// Cells per parallel task of the loops over cells
+#[inline(always)]
+pub fn chunk() -> usize {
+    CHUNK_SIZE.load(Ordering::Relaxed)
+}
+
+/// Split the loops over cells into tasks of `cells` cells from now on
+pub fn set_chunk(cells: usize) {
+    CHUNK_SIZE.store(cells.max(1), Ordering::Relaxed);
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ParallelConfig {
+    /// number of worker threads, 0 for one per available core
+    pub threads: usize,
+    /// cells per parallel task
+    pub chunk: usize,
+    /// bind every worker thread to a core (Linux only)
+    pub pin: bool,
+}
+
+impl Default for ParallelConfig {
+    fn default() -> Self {
+        Self {
+            threads: 0,
+            chunk: CHUNK,
+            pin: false,
+        }
+    }
+}
+
+impl ParallelConfig {
+    /// A dedicated pool of these threads, or of `threads` if given, e.g. on
+    /// the command line
+    pub fn pool(&self, threads: Option<usize>) -> Result<ThreadPool> {
+        let mut builder = ThreadPoolBuilder::new().num_threads(threads.unwrap_or(self.threads));
+        if self.pin {
+            let cores = allowed_cores();
+            if cores.is_empty() {
+                tracing::warn!("cannot pin threads on this platform, `parallel.pin` is ignored");
+            } else {
+                builder = builder.start_handler(move |k| pin(cores[k % cores.len()]));
+            }
+        }
+        Ok(builder.build()?)
+    }
+}
+
+/// Cores the process may run on, in increasing order
+#[cfg(target_os = "linux")]
+fn allowed_cores() -> Vec<usize> {
+    // SAFETY: `set` is a plain bit mask of the size given
+    unsafe {
+        let mut set: libc::cpu_set_t = std::mem::zeroed();
+        if libc::sched_getaffinity(0, size_of::<libc::cpu_set_t>(), &mut set) != 0 {
+            return Vec::new();
+        }
+        (0..libc::CPU_SETSIZE as usize)
+            .filter(|&c| libc::CPU_ISSET(c, &set))
+            .collect()
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn allowed_cores() -> Vec<usize> {
+    Vec::new()
+}
+
+/// Bind the calling thread to `core`
+#[cfg(target_os = "linux")]
+fn pin(core: usize) {
+    // SAFETY: as above
+    let done = unsafe {
+        let mut set: libc::cpu_set_t = std::mem::zeroed();
+        libc::CPU_SET(core, &mut set);
+        libc::sched_setaffinity(0, size_of::<libc::cpu_set_t>(), &set) == 0
+    };
+    if !done {
+        tracing::warn!(core, "cannot pin a worker thread");
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn pin(_core: usize) {}
@@ -0,0 +1,181 @@
+use std::sync::Arc;
+
+use nalgebra::Vector3;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    Mesh,
+    geometry::Geometry,
+    grains::Grains,
+    maps::CellParams,
+    regions::{ParamsOverride, Regions},
+    temperature::Thermal,
+};
+
+/// Vacuum permeability (T m A⁻¹)
+pub const MU0: f64 = 4.0 * std::f64::consts::PI * 1e-7;
+
+/// Material and solver parameters. The material values apply everywhere
+/// except where a map replaces them or a region overrides them; kernels read
+/// them cell by cell through the `*_at` methods.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Params {
+    /// gyromagnetic ratio (rad s⁻¹ T⁻¹)
+    pub gamma: f64,
+    /// Gilbert damping
+    pub alpha: f64,
+    /// exchange stiffness (J m⁻¹)
+    pub a_ex: f64,
+    /// μ₀Mₛ (T)
+    pub mu0_ms: f64,
+    /// time-step (s)
+    pub dt: f64,
+    /// external field (T)
+    pub h_ext: Vector3<f64>,
+    /// cells of the sample, from `[geometry]`; Mₛ is zero elsewhere
+    #[serde(skip)]
+    pub geometry: Option<Arc<Geometry>>,
+    /// region of every cell and the values that differ there, from
+    /// `[[regions]]`
+    #[serde(skip)]
+    pub regions: Option<Arc<Regions>>,
+    /// values of every cell, from `[maps.params]`
+    #[serde(skip)]
+    pub maps: Option<Arc<CellParams>>,
+    /// grain of every cell, from `[grains]`
+    #[serde(skip)]
+    pub grains: Option<Arc<Grains>>,
+    /// damping added in every cell, from `[absorbing]`
+    #[serde(skip)]
+    pub absorbing: Option<Arc<[f64]>>,
+    /// cells whose moments stay fixed, from `[[regions]]` with `frozen`
+    #[serde(skip)]
+    pub frozen: Option<Arc<[bool]>>,
+    /// factors of Mₛ and `a_ex` in every cell, from `[temperature]`
+    #[serde(skip)]
+    pub thermal: Option<Arc<Thermal>>,
+}
+
+impl Default for Params {
+    fn default() -> Self {
+        Self {
+            gamma: 1.760_859e11,
+            alpha: 0.2,
+            a_ex: 1.3e-11,
+            mu0_ms: MU0 * 8.0e5, // ≈ 1 T
+            dt: 1e-14,
+            h_ext: Vector3::new(0.0, 0.0, 1.0),
+            geometry: None,
+            regions: None,
+            maps: None,
+            grains: None,
+            absorbing: None,
+            frozen: None,
+            thermal: None,
+        }
+    }
+}
+
+impl Params {
+    /// Saturation magnetization Mₛ (A m⁻¹)
+    pub fn ms(&self) -> f64 {
+        self.mu0_ms / MU0
+    }
+
+    /// Whether every cell has the same material values and couples alike to
+    /// its neighbours
+    pub fn is_uniform(&self) -> bool {
+        self.geometry.is_none()
+            && self.maps.is_none()
+            && self.absorbing.is_none()
+            && self.thermal.is_none()
+            && self.grains.as_ref().is_none_or(|g| g.exchange == 1.0)
+            && self.regions.as_ref().is_none_or(|r| {
+                r.params.iter().all(ParamsOverride::is_empty) && r.exchange.is_none()
+            })
+    }
+
+    /// The override picked by `region` in the region of cell `i`, else the
+    /// value of the map picked by `map`, if any
+    #[inline(always)]
+    fn local(
+        &self,
+        i: usize,
+        region: impl Fn(&ParamsOverride) -> Option<f64>,
+        map: impl Fn(&CellParams) -> &Option<Vec<f64>>,
+    ) -> Option<f64> {
+        self.regions
+            .as_ref()
+            .and_then(|r| region(&r.params[r.index[i] as usize]))
+            .or_else(|| Some(map(self.maps.as_ref()?).as_ref()?[i]))
+    }
+
+    #[inline(always)]
+    pub fn gamma_at(&self, i: usize) -> f64 {
+        self.local(i, |o| o.gamma, |c| &c.gamma)
+            .unwrap_or(self.gamma)
+    }
+
+    #[inline(always)]
+    pub fn alpha_at(&self, i: usize) -> f64 {
+        let alpha = self
+            .local(i, |o| o.alpha, |c| &c.alpha)
+            .unwrap_or(self.alpha);
+        alpha + self.absorbing.as_ref().map_or(0.0, |a| a[i])
+    }
+
+    #[inline(always)]
+    pub fn a_ex_at(&self, i: usize) -> f64 {
+        let a_ex = self.local(i, |o| o.a_ex, |c| &c.a_ex).unwrap_or(self.a_ex);
+        self.thermal.as_ref().map_or(a_ex, |t| a_ex * t.a_ex[i])
+    }
+
+    #[inline(always)]
+    pub fn mu0_ms_at(&self, i: usize) -> f64 {
+        if self.geometry.as_ref().is_some_and(|g| !g.contains(i)) {
+            return 0.0;
+        }
+        let mu0_ms = self
+            .local(i, |o| o.mu0_ms, |c| &c.mu0_ms)
+            .unwrap_or(self.mu0_ms);
+        self.thermal.as_ref().map_or(mu0_ms, |t| mu0_ms * t.ms[i])
+    }
+
+    /// Factor on the exchange between neighbours `i` and `j`: that of
+    /// `[grains]` across a grain boundary, times that of
+    /// `[[exchange_scale]]` between their regions
+    #[inline(always)]
+    pub fn exchange_scale(&self, i: usize, j: usize) -> f64 {
+        let grains = match &self.grains {
+            Some(g) if g.boundary(i, j) => g.exchange,
+            _ => 1.0,
+        };
+        grains
+            * self
+                .regions
+                .as_ref()
+                .map_or(1.0, |r| r.exchange_scale(i, j))
+    }
+
+    /// Whether the moment of cell `i`, of either sublattice, stays fixed
+    #[inline(always)]
+    pub fn is_frozen(&self, i: usize) -> bool {
+        self.frozen.as_ref().is_some_and(|f| f[i % f.len()])
+    }
+
+    /// Mₛ (A m⁻¹) in cell `i`
+    #[inline(always)]
+    pub fn ms_at(&self, i: usize) -> f64 {
+        self.mu0_ms_at(i) / MU0
+    }
+
+    /// Neighbour of cell `i` as [`Mesh::neighbor`], or `None` when that cell
+    /// carries no moment, which makes it a free surface like the edge of
+    /// the mesh
+    #[inline(always)]
+    pub fn neighbor(&self, mesh: &Mesh, i: usize, axis: usize, dir: isize) -> Option<usize> {
+        mesh.neighbor(i, axis, dir)
+            .filter(|&j| self.mu0_ms_at(j) > 0.0)
+    }
+}
@@ -0,0 +1,24 @@
+//! Built-in configs of standard problems, printed by `nez preset NAME` as a
+//! starting point: `nez preset sp4 > sp4.toml && nez run sp4.toml`.
+
+/// Name, description and TOML of every preset
+pub const PRESETS: &[(&str, &str, &str)] = &[
+    (
+        "sp4",
+        "µMAG standard problem 4, field 1: switching of a permalloy film",
+        include_str!("presets/sp4.toml"),
+    ),
+    (
+        "mtj",
+        "current-induced switching of an in-plane tunnel junction, R(I) loop",
+        include_str!("presets/mtj.toml"),
+    ),
+];
+
+/// The TOML of preset `name`
+pub fn get(name: &str) -> Option<&'static str> {
+    PRESETS
+        .iter()
+        .find(|(n, _, _)| n.eq_ignore_ascii_case(name))
+        .map(|(_, _, toml)| *toml)
+}
@@ -0,0 +1,136 @@
+//! Probes: named groups of cells whose average magnetization joins the
+//! observables as the columns `<name>_mx`, `<name>_my` and `<name>_mz`,
+//! recorded with every row of the table, so that the signal of an antenna,
+//! a detector or a layer is followed at the full time resolution without
+//! saving frames of `m`.
+//!
+//! ```toml
+//! [[probes]]
+//! name = "detector"
+//! cell = [120, 32, 0]             # (x, y, z) indices of a single cell
+//!
+//! [[probes]]
+//! name = "strip"
+//! shape = { kind = "cuboid", min = [0.0, 0.0, 0.0], max = [10e-9, 40e-9, 1e-9] }
+//!
+//! [[probes]]
+//! name = "free"
+//! region = 2                      # cells of `regions[1]`
+//! ```
+//!
+//! Cells outside the sample of `geometry` are left out of the average, and
+//! with a second sublattice the probes follow the first.
+
+use nalgebra::Vector3;
+use serde::{Deserialize, Serialize};
+
+use crate::{Mesh, Result, VectorField, geometry::Shape, params::Params};
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ProbeConfig {
+    /// prefix of the columns
+    pub name: String,
+    /// (x, y, z) indices of a single cell
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cell: Option<[usize; 3]>,
+    /// the cells of a shape, as for `geometry`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub shape: Option<Shape>,
+    /// the cells of a region, 0 for those of no `[[regions]]` entry
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub region: Option<usize>,
+}
+
+impl ProbeConfig {
+    /// Check the name and that exactly one selection is given, within
+    /// `mesh` and `regions` regions besides region 0
+    pub fn validate(&self, k: usize, mesh: &Mesh, regions: usize) -> Result<()> {
+        let key = format!("probes[{k}]");
+        let ok = |c: char| c.is_ascii_alphanumeric() || c == '_';
+        if self.name.is_empty() || !self.name.chars().all(ok) {
+            return Err(format!(
+                "`{key}.name` must be letters, digits and underscores, got {:?}",
+                self.name
+            )
+            .into());
+        }
+        let given = [
+            self.cell.is_some(),
+            self.shape.is_some(),
+            self.region.is_some(),
+        ];
+        if given.iter().filter(|&&g| g).count() != 1 {
+            return Err(
+                format!("`{key}` needs exactly one of `cell`, `shape` and `region`").into(),
+            );
+        }
+        if let Some(cell) = self.cell
+            && cell.iter().zip(mesh.size()).any(|(&c, n)| c >= n)
+        {
+            return Err(format!(
+                "`{key}.cell` {cell:?} is outside the mesh of {:?} cells",
+                mesh.size()
+            )
+            .into());
+        }
+        if let Some(r) = self.region.filter(|&r| r > regions) {
+            return Err(format!(
+                "`{key}.region` is {r}, but there are only regions 0 to {regions}"
+            )
+            .into());
+        }
+        Ok(())
+    }
+}
+
+/// A probe of the mesh, with the cells it averages over
+#[derive(Debug, Clone, PartialEq)]
+pub struct Probe {
+    pub name: String,
+    cells: Vec<usize>,
+}
+
+impl Probe {
+    /// The cells of `config` on `mesh` that belong to the sample of `p`,
+    /// which must hold at least one
+    pub fn new(config: &ProbeConfig, mesh: &Mesh, p: &Params) -> Result<Self> {
+        let selected: Vec<usize> = if let Some([x, y, z]) = config.cell {
+            vec![mesh.idx(x, y, z)]
+        } else if let Some(shape) = &config.shape {
+            let mask = shape.mask(mesh)?;
+            (0..mesh.len()).filter(|&i| mask[i]).collect()
+        } else {
+            let region = config.region.unwrap_or(0);
+            (0..mesh.len())
+                .filter(|&i| p.regions.as_ref().map_or(0, |r| r.index[i] as usize) == region)
+                .collect()
+        };
+        let cells: Vec<usize> = selected
+            .into_iter()
+            .filter(|&i| p.geometry.as_ref().is_none_or(|g| g.contains(i)))
+            .collect();
+        if cells.is_empty() {
+            return Err(format!("probe `{}` holds no cell of the sample", config.name).into());
+        }
+        Ok(Self {
+            name: config.name.clone(),
+            cells,
+        })
+    }
+
+    /// Magnetization averaged over the cells of the probe
+    pub fn mean(&self, m: &VectorField) -> Vector3<f64> {
+        let sum: Vector3<f64> = self.cells.iter().map(|&i| m.get(i)).sum();
+        sum / self.cells.len() as f64
+    }
+
+    /// Number of cells averaged over
+    pub fn len(&self) -> usize {
+        self.cells.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cells.is_empty()
+    }
+}
@@ -0,0 +1,137 @@
+//! Where the wall time of a run goes, with `nez --profile`: the time spent
+//! in every field term, in every stage of the solver and in every kind of
+//! output is added up, and [`report`] breaks it down at the end, e.g. to
+//! tell whether the demagnetizing field, the exchange field or the
+//! compression of the frames dominates.
+//!
+//! The solver stages include the field terms they evaluate, which are also
+//! evaluated for the table, so that the time of a term counts that of the
+//! observables as well. For uniform parameters, the exchange field is
+//! computed in one sweep with the Zeeman and uniaxial terms and the torque,
+//! timed as `fused local terms`. Off by default, profiling costs an atomic
+//! load per measured call.
+
+use std::{
+    fmt::{Display, Write},
+    sync::{
+        Mutex, OnceLock,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+/// Whether time is being measured
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// When profiling began
+static STARTED: OnceLock<Instant> = OnceLock::new();
+
+/// Time spent so far, in the order first measured
+static ENTRIES: Mutex<Vec<Entry>> = Mutex::new(Vec::new());
+
+/// Part of a run whose time is measured
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Section {
+    /// a field term, by name, or the spin torques
+    Field,
+    /// a step of the solver, and every stage of the Runge–Kutta schemes
+    Solver,
+    /// writes to the store and files
+    Output,
+}
+
+impl Section {
+    const ALL: [Self; 3] = [Self::Field, Self::Solver, Self::Output];
+
+    fn title(self) -> &'static str {
+        match self {
+            Self::Field => "field terms and torques",
+            Self::Solver => "solver",
+            Self::Output => "output",
+        }
+    }
+}
+
+struct Entry {
+    section: Section,
+    name: String,
+    total: Duration,
+    calls: u64,
+}
+
+/// Measure time from now on
+pub fn enable() {
+    STARTED.get_or_init(Instant::now);
+    ENABLED.store(true, Ordering::Relaxed);
+}
+
+/// The time a measured call starts, none while profiling is off
+pub fn start() -> Option<Instant> {
+    ENABLED.load(Ordering::Relaxed).then(Instant::now)
+}
+
+/// Add the time since `start` to the part `name` of `section`, if
+/// profiling is on
+pub fn record(section: Section, name: impl Display, start: impl Into<Option<Instant>>) {
+    let Some(start) = start.into() else {
+        return;
+    };
+    if !ENABLED.load(Ordering::Relaxed) {
+        return;
+    }
+    let elapsed = start.elapsed();
+    let name = name.to_string();
+    let mut entries = ENTRIES.lock().unwrap_or_else(|e| e.into_inner());
+    match entries
+        .iter_mut()
+        .find(|e| e.section == section && e.name == name)
+    {
+        Some(e) => {
+            e.total += elapsed;
+            e.calls += 1;
+        }
+        None => entries.push(Entry {
+            section,
+            name,
+            total: elapsed,
+            calls: 1,
+        }),
+    }
+}
+
+/// The time spent in every part measured, as a share of the wall time
+/// since [`enable`], slowest first in every section; none if profiling is
+/// off
+pub fn report() -> Option<String> {
+    let wall = STARTED.get()?.elapsed();
+    let entries = ENTRIES.lock().unwrap_or_else(|e| e.into_inner());
+    let mut out = format!("# profile of {wall:.2?} of wall time\n");
+    for section in Section::ALL {
+        let mut parts: Vec<_> = entries.iter().filter(|e| e.section == section).collect();
+        if parts.is_empty() {
+            continue;
+        }
+        parts.sort_by_key(|e| std::cmp::Reverse(e.total));
+        let _ = writeln!(
+            out,
+            "# {:<28}{:>12}{:>8}{:>10}{:>12}",
+            section.title(),
+            "time",
+            "share",
+            "calls",
+            "per call"
+        );
+        for e in parts {
+            let _ = writeln!(
+                out,
+                "#   {:<26}{:>12}{:>7.1}%{:>10}{:>12}",
+                e.name,
+                format!("{:.3?}", e.total),
+                100.0 * e.total.as_secs_f64() / wall.as_secs_f64(),
+                e.calls,
+                format!("{:.2?}", e.total.div_f64(e.calls as f64)),
+            );
+        }
+    }
+    Some(out)
+}
@@ -0,0 +1,200 @@
+//! Progress of a run on the terminal: a line at the bottom with a bar, the
+//! step, the simulated time, the rate and the estimated time left, with the
+//! printed rows scrolling above it.
+//!
+//! The bar goes to stderr, and only when it is a terminal, so that logs of
+//! batch jobs hold the rows alone. With a [`Dashboard`], the bar, the rows
+//! and plots of the run share the screen instead, and a [`Server`] shows
+//! them in the browser.
+//!
+//! Every command writes its rows through [`Progress::println`], so that
+//! `--quiet` silences them all. The bar is an indicatif one, hidden while
+//! the rows are printed.
+
+use indicatif::{FormattedDuration, ProgressBar, ProgressDrawTarget, ProgressStyle};
+use std::{fmt::Display, io::Write, time::Instant};
+
+use crate::{Result, Simulation, dashboard::Dashboard, serve::Server};
+
+/// Layout of the bar; its message holds the step, time and rate
+const TEMPLATE: &str = "[{bar:30}] {percent:>3}%  {msg}  ETA {eta_precise}";
+/// Positions along the bar, which follows the fraction of the run done
+const LENGTH: u64 = 10_000;
+
+/// Where a run ends
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Target {
+    /// at this step
+    Step(u64),
+    /// at this simulated time (s)
+    Time(f64),
+}
+
+impl Target {
+    /// Position of `sim` along this target's axis
+    fn position(self, sim: &Simulation) -> f64 {
+        match self {
+            Self::Step(_) => sim.step as f64,
+            Self::Time(_) => sim.t,
+        }
+    }
+
+    fn end(self) -> f64 {
+        match self {
+            Self::Step(n) => n as f64,
+            Self::Time(t) => t,
+        }
+    }
+}
+
+/// Printed rows and the progress bar of the current run
+pub struct Progress {
+    quiet: bool,
+    run: Option<Run>,
+    dashboard: Option<Dashboard>,
+    server: Option<Server>,
+}
+
+/// A run the bar follows
+struct Run {
+    target: Target,
+    /// position and step at its start
+    from: f64,
+    step: u64,
+    start: Instant,
+    bar: ProgressBar,
+}
+
+impl Progress {
+    /// Print nothing at all when `quiet`
+    pub fn new(quiet: bool) -> Self {
+        Self {
+            quiet,
+            run: None,
+            dashboard: None,
+            server: None,
+        }
+    }
+
+    /// Show the run on a [`Dashboard`] from now on, instead of printing to
+    /// the terminal
+    pub fn show_dashboard(&mut self) -> Result<()> {
+        self.dashboard = Some(Dashboard::open()?);
+        Ok(())
+    }
+
+    pub fn is_quiet(&self) -> bool {
+        self.quiet
+    }
+
+    /// Follow a run of `sim` from now until `target`
+    pub fn begin(&mut self, sim: &Simulation, target: Target) {
+        // on stderr, which indicatif leaves alone unless it is a terminal
+        let draw = if self.quiet || self.dashboard.is_some() {
+            ProgressDrawTarget::hidden()
+        } else {
+            ProgressDrawTarget::stderr()
+        };
+        let bar = ProgressBar::with_draw_target(Some(LENGTH), draw).with_style(
+            ProgressStyle::with_template(TEMPLATE)
+                .expect("valid template")
+                .progress_chars("#-"),
+        );
+        self.run = Some(Run {
+            target,
+            from: target.position(sim),
+            step: sim.step,
+            start: Instant::now(),
+            bar,
+        });
+    }
+
+    /// Also send the run to browsers connecting to `addr`
+    pub fn serve(&mut self, addr: &str) -> Result<()> {
+        self.server = Some(Server::start(addr)?);
+        Ok(())
+    }
+
+    /// Whether the run is sent to browsers, which takes its observables
+    pub fn is_serving(&self) -> bool {
+        self.server.is_some()
+    }
+
+    /// Redraw the bar, or the dashboard, for the state of `sim`, and send it
+    /// with its `observables` to the browsers
+    pub fn update(&mut self, sim: &Simulation, observables: &[(String, f64)]) {
+        if let Some(server) = &mut self.server {
+            server.publish(sim, observables);
+        }
+        if let Some(run) = &self.run {
+            run.bar.set_position((run.done(sim) * LENGTH as f64) as u64);
+            run.bar.set_message(run.figures(sim));
+        }
+        if let Some(dashboard) = &mut self.dashboard {
+            dashboard.sample(sim);
+            match &self.run {
+                Some(run) => dashboard.update(run.done(sim), run.status(sim)),
+                None => dashboard.update(0.0, String::new()),
+            }
+        }
+    }
+
+    /// Print `line` to stdout above the bar
+    pub fn println(&mut self, line: impl Display) {
+        if self.quiet {
+            return;
+        }
+        if let Some(dashboard) = &mut self.dashboard {
+            dashboard.row(line.to_string());
+            return;
+        }
+        let print = || {
+            println!("{line}");
+            let _ = std::io::stdout().flush();
+        };
+        match &self.run {
+            Some(run) => run.bar.suspend(print),
+            None => print(),
+        }
+    }
+
+    /// Stop following the run, leaving its last bar on the terminal
+    pub fn finish(&mut self) {
+        if let Some(dashboard) = &mut self.dashboard {
+            dashboard.draw();
+        }
+        if let Some(run) = self.run.take() {
+            run.bar.abandon();
+        }
+    }
+}
+
+impl Run {
+    /// Fraction of the run done at the state of `sim`
+    fn done(&self, sim: &Simulation) -> f64 {
+        let span = self.target.end() - self.from;
+        if span > 0.0 {
+            ((self.target.position(sim) - self.from) / span).clamp(0.0, 1.0)
+        } else {
+            1.0
+        }
+    }
+
+    /// The step, simulated time and rate of the run at the state of `sim`
+    fn figures(&self, sim: &Simulation) -> String {
+        let elapsed = self.start.elapsed().as_secs_f64();
+        let rate = (sim.step - self.step) as f64 / elapsed.max(1e-9);
+        format!("step {}  t {:.3e} s  {rate:.3e} steps/s", sim.step, sim.t)
+    }
+
+    /// The line of the dashboard over its gauge: the fraction done, the
+    /// figures and the time left
+    fn status(&self, sim: &Simulation) -> String {
+        format!(
+            "{:3.0}%  {}  ETA {}",
+            100.0 * self.done(sim),
+            self.figures(sim),
+            FormattedDuration(self.bar.eta())
+        )
+    }
+}
@@ -0,0 +1,237 @@
+//! Regions: groups of cells with their own material parameters, as in
+//! mumax3.
+//!
+//! Every cell carries a region index. Region 0 holds the cells no
+//! `[[regions]]` entry claims and takes the parameters of the main sections;
+//! entry `k` defines region `k + 1`, overriding some of them. Where shapes
+//! overlap the later entry wins.
+//!
+//! A region may also be `frozen`: its moments keep their initial direction
+//! through dynamics, relaxation and Monte Carlo, while still acting on their
+//! neighbours through exchange, demag and every other field, as a pinned
+//! layer of an exchange-bias stack or the fixed polarizer of a spin valve.
+//!
+//! The exchange between two regions, or within one, may be scaled by
+//! `[[exchange_scale]]` entries, as `ext_ScaleExchange` of mumax3: weakened
+//! at the interface of an exchange spring, or turned negative to couple the
+//! layers of a synthetic antiferromagnet antiparallel.
+
+use std::sync::Arc;
+
+use nalgebra::Vector3;
+use serde::{Deserialize, Serialize};
+
+use crate::{Mesh, Result, geometry::Shape};
+
+/// Most regions besides region 0, as the index is stored in a byte
+pub const MAX_REGIONS: usize = u8::MAX as usize;
+
+/// Values of `[params]` that differ in a region
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ParamsOverride {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gamma: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub alpha: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub a_ex: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mu0_ms: Option<f64>,
+}
+
+impl ParamsOverride {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// Values of `[uniaxial]` that differ in a region
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct UniaxialOverride {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub k1: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub k2: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub axis: Option<Vector3<f64>>,
+}
+
+impl UniaxialOverride {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// Values of `[cubic]` that differ in a region
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CubicOverride {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub k1: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub k2: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub k3: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub c1: Option<Vector3<f64>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub c2: Option<Vector3<f64>>,
+}
+
+impl CubicOverride {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// Values of `[dmi]` that differ in a region
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DmiOverride {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub d: Option<f64>,
+}
+
+impl DmiOverride {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// One `[[regions]]` entry, the cells of `shape` and the parameters that
+/// differ there:
+///
+/// ```toml
+/// [[regions]]
+/// shape = { kind = "layers", first = 2, last = 3 }
+/// params = { mu0_ms = 1.5, a_ex = 2e-11 }
+/// uniaxial = { k1 = 5e5, axis = [0.0, 0.0, 1.0] }
+/// frozen = true
+/// ```
+///
+/// Overriding a term that has no section of its own enables it, with zero
+/// constants outside the region.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct RegionConfig {
+    pub shape: Shape,
+    #[serde(default, skip_serializing_if = "ParamsOverride::is_empty")]
+    pub params: ParamsOverride,
+    #[serde(default, skip_serializing_if = "UniaxialOverride::is_empty")]
+    pub uniaxial: UniaxialOverride,
+    #[serde(default, skip_serializing_if = "CubicOverride::is_empty")]
+    pub cubic: CubicOverride,
+    #[serde(default, skip_serializing_if = "DmiOverride::is_empty")]
+    pub dmi: DmiOverride,
+    /// the moments of the region stay fixed
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub frozen: bool,
+}
+
+/// A factor on the stiffness coupling neighbouring cells of two regions:
+///
+/// ```toml
+/// [[exchange_scale]]
+/// regions = [1, 2]   # 0 for the cells of no `[[regions]]` entry
+/// scale = -0.5
+/// ```
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct ExchangeScale {
+    /// the two regions, the same twice for the exchange within one
+    pub regions: [usize; 2],
+    pub scale: f64,
+}
+
+/// Region of every cell, with the `[params]` overrides of each region
+#[derive(Debug, Clone, PartialEq)]
+pub struct Regions {
+    /// region of every cell
+    pub index: Arc<[u8]>,
+    /// overrides of every region, none for region 0
+    pub params: Vec<ParamsOverride>,
+    /// whether the moments of every region stay fixed, never those of
+    /// region 0
+    pub frozen: Vec<bool>,
+    /// factor on the exchange between every pair of regions, `len()` by
+    /// `len()`, if `[[exchange_scale]]` sets any
+    pub exchange: Option<Vec<f64>>,
+}
+
+impl Regions {
+    /// Paint the regions of `config` onto `mesh` in order, with the
+    /// exchange between them scaled by `scales`, or `None` without either
+    pub fn new(
+        config: &[RegionConfig],
+        scales: &[ExchangeScale],
+        mesh: &Mesh,
+    ) -> Result<Option<Self>> {
+        if config.is_empty() && scales.is_empty() {
+            return Ok(None);
+        }
+        if config.len() > MAX_REGIONS {
+            return Err(format!("at most {MAX_REGIONS} `regions` are supported").into());
+        }
+        let mut index = vec![0u8; mesh.len()];
+        for (k, region) in config.iter().enumerate() {
+            let mask = region.shape.mask(mesh)?;
+            for (r, inside) in index.iter_mut().zip(mask) {
+                if inside {
+                    *r = k as u8 + 1;
+                }
+            }
+        }
+        let params = std::iter::once(ParamsOverride::default())
+            .chain(config.iter().map(|r| r.params.clone()))
+            .collect();
+        let frozen = std::iter::once(false)
+            .chain(config.iter().map(|r| r.frozen))
+            .collect();
+        let n = config.len() + 1;
+        let exchange = (!scales.is_empty()).then(|| {
+            let mut exchange = vec![1.0; n * n];
+            for s in scales {
+                let [a, b] = s.regions;
+                exchange[a * n + b] = s.scale;
+                exchange[b * n + a] = s.scale;
+            }
+            exchange
+        });
+        Ok(Some(Self {
+            index: index.into(),
+            params,
+            frozen,
+            exchange,
+        }))
+    }
+
+    /// Whether every cell stays fixed, or `None` if no region is frozen
+    pub fn frozen_cells(&self) -> Option<Arc<[bool]>> {
+        self.frozen.contains(&true).then(|| {
+            self.index
+                .iter()
+                .map(|&r| self.frozen[r as usize])
+                .collect()
+        })
+    }
+
+    /// Factor on the exchange between neighbouring cells `i` and `j`
+    #[inline(always)]
+    pub fn exchange_scale(&self, i: usize, j: usize) -> f64 {
+        self.exchange.as_ref().map_or(1.0, |e| {
+            let n = self.len();
+            e[self.index[i] as usize * n + self.index[j] as usize]
+        })
+    }
+
+    /// Number of regions, region 0 included
+    pub fn len(&self) -> usize {
+        self.params.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.params.is_empty()
+    }
+}
@@ -0,0 +1,49 @@
+//! Zarr stores in object storage, addressed as `s3://bucket/prefix` or
+//! `gs://bucket/prefix`. Credentials and region come from the usual
+//! environment variables (`AWS_ACCESS_KEY_ID`, `AWS_REGION`,
+//! `GOOGLE_APPLICATION_CREDENTIALS`, ...).
+
+use object_store::{
+    ObjectStore, aws::AmazonS3Builder, gcp::GoogleCloudStorageBuilder, prefix::PrefixStore,
+};
+use std::sync::Arc;
+use zarrs::storage::ReadableWritableListableStorage;
+use zarrs_object_store::AsyncObjectStore;
+use zarrs_storage::storage_adapter::async_to_sync::{
+    AsyncToSyncBlockOn, AsyncToSyncStorageAdapter,
+};
+
+use crate::Result;
+
+/// Runs the requests of the async object store to completion
+struct BlockOn(tokio::runtime::Runtime);
+
+impl AsyncToSyncBlockOn for BlockOn {
+    fn block_on<F: core::future::Future>(&self, future: F) -> F::Output {
+        self.0.block_on(future)
+    }
+}
+
+/// Synchronous storage at `url`
+pub fn storage(url: &str) -> Result<ReadableWritableListableStorage> {
+    let (scheme, rest) = url.split_once("://").ok_or("not a URL")?;
+    let (bucket, prefix) = rest.split_once('/').unwrap_or((rest, ""));
+    let bucket_url = format!("{scheme}://{bucket}");
+    let store: Arc<dyn ObjectStore> = match scheme {
+        "s3" => Arc::new(AmazonS3Builder::from_env().with_url(bucket_url).build()?),
+        "gs" | "gcs" => Arc::new(
+            GoogleCloudStorageBuilder::from_env()
+                .with_url(bucket_url)
+                .build()?,
+        ),
+        _ => return Err(format!("unsupported object store `{scheme}://`").into()),
+    };
+    let store = PrefixStore::new(store, prefix.trim_end_matches('/'));
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()?;
+    Ok(Arc::new(AsyncToSyncStorageAdapter::new(
+        Arc::new(AsyncObjectStore::new(store)),
+        BlockOn(runtime),
+    )))
+}
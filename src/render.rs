@@ -0,0 +1,70 @@
+//! Colour images of the magnetization, as drawn by mumax3: the hue gives
+//! the in-plane angle (red along +x, then yellow, green along +y, …) and the
+//! lightness mz, white for +z and black for -z. The layers of the mesh are
+//! averaged into one image; cells without a moment are transparent.
+
+use nalgebra::Vector3;
+use std::{fs::File, io::BufWriter, path::Path};
+
+use crate::{Mesh, Result, VectorField};
+
+/// Longest side of the images of small meshes, in pixels, drawn with
+/// several pixels per cell
+const SIDE: usize = 512;
+
+/// RGB colour of the direction `m`
+pub fn colour(m: Vector3<f64>) -> [u8; 3] {
+    let hue = m.y.atan2(m.x).to_degrees().rem_euclid(360.0);
+    let lightness = (0.5 + 0.5 * m.z).clamp(0.0, 1.0);
+    let a = lightness.min(1.0 - lightness);
+    let f = |n: f64| {
+        let k = (n + hue / 30.0) % 12.0;
+        let v = lightness - a * (k - 3.0).min(9.0 - k).clamp(-1.0, 1.0);
+        (255.0 * v).round() as u8
+    };
+    [f(0.0), f(8.0), f(4.0)]
+}
+
+/// RGBA pixels of `m` on `mesh`, row by row from the top (largest y), each
+/// cell `scale` pixels wide
+pub fn image(mesh: &Mesh, m: &VectorField, scale: usize) -> Vec<u8> {
+    let (nx, ny) = (mesh.nx, mesh.ny);
+    let mut sum = vec![Vector3::zeros(); nx * ny];
+    for i in 0..mesh.len() {
+        let [x, y, _] = mesh.coords(i);
+        sum[x + nx * y] += m.get(i);
+    }
+    let width = nx * scale;
+    let mut pixels = vec![0; 4 * width * ny * scale];
+    for (k, s) in sum.iter().enumerate() {
+        let (x, y) = (k % nx, k / nx);
+        let rgba = match s.try_normalize(0.0) {
+            Some(m) => {
+                let [r, g, b] = colour(m);
+                [r, g, b, 255]
+            }
+            None => [0; 4],
+        };
+        for py in (ny - 1 - y) * scale..(ny - y) * scale {
+            for px in x * scale..(x + 1) * scale {
+                let o = 4 * (px + width * py);
+                pixels[o..o + 4].copy_from_slice(&rgba);
+            }
+        }
+    }
+    pixels
+}
+
+/// Write `m` on `mesh` as a PNG image
+pub fn write_png(path: &Path, mesh: &Mesh, m: &VectorField) -> Result<()> {
+    let scale = (SIDE / mesh.nx.max(mesh.ny)).max(1);
+    let file = File::create(path).map_err(|e| format!("cannot write {}: {e}", path.display()))?;
+    let (width, height) = ((mesh.nx * scale) as u32, (mesh.ny * scale) as u32);
+    let mut encoder = png::Encoder::new(BufWriter::new(file), width, height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(&image(mesh, m, scale))?;
+    writer.finish()?;
+    Ok(())
+}
@@ -0,0 +1,64 @@
+//! Small seedable pseudo-random number generator (xoshiro256**), so that
+//! stochastic runs are reproducible from a single `seed`.
+
+use nalgebra::Vector3;
+
+#[derive(Debug, Clone)]
+pub struct Rng {
+    s: [u64; 4],
+}
+
+impl Rng {
+    /// Generator whose state is expanded from `seed` with SplitMix64
+    pub fn new(seed: u64) -> Self {
+        let mut z = seed;
+        let s = [(); 4].map(|_| {
+            z = z.wrapping_add(0x9E37_79B9_7F4A_7C15);
+            let mut x = z;
+            x = (x ^ (x >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+            x = (x ^ (x >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+            x ^ (x >> 31)
+        });
+        Self { s }
+    }
+
+    /// Generator continuing from a state saved with [`Rng::state`]
+    pub fn from_state(s: [u64; 4]) -> Self {
+        Self { s }
+    }
+
+    /// Internal state, e.g. for checkpoints
+    pub fn state(&self) -> [u64; 4] {
+        self.s
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        let s = &mut self.s;
+        let result = s[1].wrapping_mul(5).rotate_left(7).wrapping_mul(9);
+        let t = s[1] << 17;
+        s[2] ^= s[0];
+        s[3] ^= s[1];
+        s[1] ^= s[2];
+        s[0] ^= s[3];
+        s[2] ^= t;
+        s[3] = s[3].rotate_left(45);
+        result
+    }
+
+    /// Uniform number in [0, 1)
+    pub fn uniform(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Standard normal number (Box–Muller)
+    pub fn normal(&mut self) -> f64 {
+        let u = 1.0 - self.uniform(); // (0, 1], keeps ln finite
+        let v = self.uniform();
+        (-2.0 * u.ln()).sqrt() * (std::f64::consts::TAU * v).cos()
+    }
+
+    /// Vector of three independent standard normal components
+    pub fn normal_vector(&mut self) -> Vector3<f64> {
+        Vector3::new(self.normal(), self.normal(), self.normal())
+    }
+}
@@ -0,0 +1,546 @@
+//! Control scripts: a short program driving a simulation set up by a config,
+//! in the spirit of mumax3 scripts, run with `nez script config.toml file`:
+//!
+//! ```text
+//! # relax in a bias field, then let it ring after a pulse
+//! h_ext = [0, 0, 0.2]
+//! relax()
+//! save()
+//! f = 20e9
+//! field = [0.01 * sinc(2 * pi * f * (t - 1e-9)), 0, 0]
+//! run(5e-9)
+//! field = [0, 0, 0]
+//!
+//! # then sweep the bias down
+//! for b = 0.2, 0, -0.05
+//!     h_ext = [0, 0, b]
+//!     minimize()
+//!     print(b, mz, energy)
+//!     if mz < 0
+//!         save()
+//!     end
+//! end
+//! ```
+//!
+//! Every line holds one statement, `#` starts a comment:
+//!
+//! - `name = expr` sets a variable, or one of the parameters `alpha`,
+//!   `gamma`, `a_ex`, `mu0_ms` and `dt`;
+//! - `h_ext = [x, y, z]` sets the static applied field (T), and
+//!   `field = [x, y, z]` a uniform field on top of it, evaluated anew as the
+//!   time `t` advances;
+//! - `run(duration)`, `steps(n)`, `relax()`, `minimize()`, `save()` and
+//!   `print(expr, …)` act on the simulation, relaxing with the `[relax]` and
+//!   `[minimize]` settings of the config;
+//! - `for name = start, stop, step` (the step defaulting to 1) and `if expr`
+//!   open blocks closed by `end`; `if` runs its block when `expr` is not 0.
+//!
+//! Expressions are those of [`crate::expr`], over the variables of the
+//! script, the parameters and the current state: `t` (s), `step`, the
+//! averaged `mx`, `my`, `mz` and the total `energy` (J). They are parsed
+//! with the script, once, and a variable read before it is set stops the
+//! run. A `field` sees every variable as it was when the field was set,
+//! except `t`.
+
+use nalgebra::Vector3;
+use std::{fs, io::Write, path::Path};
+
+use crate::{Config, Result, Simulation, expr::Expr, field::Zeeman};
+
+/// Names readable in every expression, in the order of [`Env::state`]
+pub(crate) const STATE: [&str; 11] = [
+    "t", "step", "mx", "my", "mz", "energy", "alpha", "gamma", "a_ex", "mu0_ms", "dt",
+];
+
+/// A parsed script
+#[derive(Debug, Clone)]
+pub struct Script {
+    /// variables the script sets, read after the names of [`STATE`]
+    vars: Vec<String>,
+    body: Vec<Statement>,
+}
+
+/// A statement, with its expressions as text `E = String` until every
+/// variable of the script is known, then parsed
+#[derive(Debug, Clone)]
+struct Statement<E = Expr> {
+    /// line number, from 1
+    line: usize,
+    kind: Kind<E>,
+}
+
+#[derive(Debug, Clone)]
+enum Kind<E> {
+    Assign(String, Value<E>),
+    Call(String, Vec<E>),
+    For {
+        var: String,
+        /// start, stop and step
+        range: Vec<E>,
+        body: Vec<Statement<E>>,
+    },
+    If(E, Vec<Statement<E>>),
+}
+
+#[derive(Debug, Clone)]
+enum Value<E> {
+    Scalar(E),
+    Vector([E; 3]),
+}
+
+impl Script {
+    /// Read and parse the script at `path`
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let text =
+            fs::read_to_string(path).map_err(|e| format!("cannot read {}: {e}", path.display()))?;
+        Self::parse(&text).map_err(|e| format!("{}: {e}", path.display()).into())
+    }
+
+    /// Parse the text of a script and every expression in it
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut lines = text
+            .lines()
+            .enumerate()
+            .map(|(k, l)| (k + 1, l.split('#').next().unwrap_or("").trim()))
+            .filter(|(_, l)| !l.is_empty());
+        let body = block(&mut lines, None)?;
+        let mut vars = Vec::new();
+        variables(&body, &mut vars);
+        let names: Vec<&str> = STATE
+            .iter()
+            .copied()
+            .chain(vars.iter().map(String::as_str))
+            .collect();
+        let body = body
+            .into_iter()
+            .map(|s| s.parse(&names))
+            .collect::<Result<_>>()?;
+        Ok(Self { vars, body })
+    }
+
+    /// Run the script on `sim`, relaxing with the settings of `config` and
+    /// writing the output of `print` to `out`
+    pub fn run(&self, sim: &mut Simulation, config: &Config, out: &mut impl Write) -> Result<()> {
+        let mut env = Env {
+            vars: &self.vars,
+            values: vec![None; self.vars.len()],
+        };
+        env.block(&self.body, sim, config, out)
+    }
+}
+
+/// Add the names `body` assigns to, but those of [`STATE`], to `vars`
+fn variables(body: &[Statement<String>], vars: &mut Vec<String>) {
+    for s in body {
+        let name = match &s.kind {
+            Kind::Assign(name, Value::Scalar(_)) | Kind::For { var: name, .. } => Some(name),
+            _ => None,
+        };
+        if let Some(name) = name
+            && !STATE.contains(&name.as_str())
+            && !vars.contains(name)
+        {
+            vars.push(name.clone());
+        }
+        if let Kind::For { body, .. } | Kind::If(_, body) = &s.kind {
+            variables(body, vars);
+        }
+    }
+}
+
+impl Statement<String> {
+    /// The statement with its expressions parsed over `names`
+    fn parse(self, names: &[&str]) -> Result<Statement> {
+        let line = self.line;
+        let parse = |expr: String| {
+            Expr::parse(&expr, names).map_err(|e| crate::Error::from(format!("line {line}: {e}")))
+        };
+        let all = |exprs: Vec<String>| exprs.into_iter().map(parse).collect::<Result<Vec<_>>>();
+        let block = |body: Vec<Statement<String>>| {
+            body.into_iter()
+                .map(|s| s.parse(names))
+                .collect::<Result<Vec<_>>>()
+        };
+        let kind = match self.kind {
+            Kind::Assign(name, Value::Scalar(expr)) => {
+                Kind::Assign(name, Value::Scalar(parse(expr)?))
+            }
+            Kind::Assign(name, Value::Vector([x, y, z])) => {
+                Kind::Assign(name, Value::Vector([parse(x)?, parse(y)?, parse(z)?]))
+            }
+            Kind::Call(name, args) => Kind::Call(name, all(args)?),
+            Kind::For { var, range, body } => Kind::For {
+                var,
+                range: all(range)?,
+                body: block(body)?,
+            },
+            Kind::If(cond, body) => Kind::If(parse(cond)?, block(body)?),
+        };
+        Ok(Statement { line, kind })
+    }
+}
+
+/// Statements up to the `end` of the block opened at line `open`, or to the
+/// end of the text if `None`
+fn block<'a>(
+    lines: &mut impl Iterator<Item = (usize, &'a str)>,
+    open: Option<usize>,
+) -> Result<Vec<Statement<String>>> {
+    let mut body = Vec::new();
+    while let Some((line, text)) = lines.next() {
+        let error = |what: String| format!("line {line}: {what}");
+        let kind = if text == "end" {
+            return match open {
+                Some(_) => Ok(body),
+                None => Err(error("`end` outside a block".into()).into()),
+            };
+        } else if let Some(rest) = keyword(text, "for") {
+            let (var, range) = rest
+                .split_once('=')
+                .ok_or_else(|| error("expected `for name = start, stop, step`".into()))?;
+            let var = identifier(var.trim()).map_err(|e| error(e.to_string()))?;
+            let range = split(range);
+            if !(2..=3).contains(&range.len()) {
+                return Err(error("expected `for name = start, stop, step`".into()).into());
+            }
+            Kind::For {
+                var,
+                range,
+                body: block(lines, Some(line))?,
+            }
+        } else if let Some(cond) = keyword(text, "if") {
+            Kind::If(cond.to_string(), block(lines, Some(line))?)
+        } else if let Some((name, value)) = assignment(text) {
+            let name = identifier(name).map_err(|e| error(e.to_string()))?;
+            let value = match value.strip_prefix('[') {
+                Some(inner) => {
+                    let inner = inner
+                        .strip_suffix(']')
+                        .ok_or_else(|| error("expected `]`".into()))?;
+                    let v: [String; 3] = split(inner)
+                        .try_into()
+                        .map_err(|_| error("a vector has three components".into()))?;
+                    Value::Vector(v)
+                }
+                None => Value::Scalar(value.to_string()),
+            };
+            Kind::Assign(name, value)
+        } else if let Some((name, args)) = text.split_once('(') {
+            let args = args
+                .strip_suffix(')')
+                .ok_or_else(|| error("expected `)`".into()))?;
+            let name = identifier(name.trim()).map_err(|e| error(e.to_string()))?;
+            let args = if args.trim().is_empty() {
+                Vec::new()
+            } else {
+                split(args)
+            };
+            Kind::Call(name, args)
+        } else {
+            return Err(error(format!("cannot understand `{text}`")).into());
+        };
+        body.push(Statement { line, kind });
+    }
+    match open {
+        Some(line) => Err(format!("line {line}: block has no `end`").into()),
+        None => Ok(body),
+    }
+}
+
+/// The rest of `text` after the keyword `word` and a space, if it starts so
+fn keyword<'a>(text: &'a str, word: &str) -> Option<&'a str> {
+    let rest = text.strip_prefix(word)?;
+    rest.starts_with(char::is_whitespace).then(|| rest.trim())
+}
+
+/// Name and value of `name = value`, but not of a comparison `a == b`
+fn assignment(text: &str) -> Option<(&str, &str)> {
+    let (name, value) = text.split_once('=')?;
+    let name = name.trim();
+    (!name.ends_with(['<', '>', '!']) && !value.starts_with('=')).then(|| (name, value.trim()))
+}
+
+fn identifier(name: &str) -> Result<String> {
+    let mut chars = name.chars();
+    let valid = chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if valid {
+        Ok(name.to_string())
+    } else {
+        Err(format!("`{name}` is not a valid name").into())
+    }
+}
+
+/// `text` split at the commas outside parentheses, each part trimmed
+pub(crate) fn split(text: &str) -> Vec<String> {
+    let mut parts = vec![String::new()];
+    let mut depth = 0;
+    for c in text.chars() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            ',' if depth == 0 => {
+                parts.push(String::new());
+                continue;
+            }
+            _ => {}
+        }
+        parts.last_mut().unwrap().push(c);
+    }
+    parts.iter().map(|p| p.trim().to_string()).collect()
+}
+
+/// Variables of a running script, `None` until set
+struct Env<'a> {
+    vars: &'a [String],
+    values: Vec<Option<f64>>,
+}
+
+impl Env<'_> {
+    fn block(
+        &mut self,
+        body: &[Statement],
+        sim: &mut Simulation,
+        config: &Config,
+        out: &mut impl Write,
+    ) -> Result<()> {
+        for s in body {
+            self.statement(s, sim, config, out)?;
+        }
+        Ok(())
+    }
+
+    /// Run statement `s`; its errors name its line, those of the statements
+    /// of a block the lines of those
+    fn statement(
+        &mut self,
+        s: &Statement,
+        sim: &mut Simulation,
+        config: &Config,
+        out: &mut impl Write,
+    ) -> Result<()> {
+        let at = |e: crate::Error| format!("line {}: {e}", s.line);
+        match &s.kind {
+            Kind::For { var, range, body } => {
+                let mut r = Vec::with_capacity(3);
+                for expr in range {
+                    r.push(self.eval(expr, sim).map_err(at)?);
+                }
+                let (start, stop, step) = (r[0], r[1], r.get(2).copied().unwrap_or(1.0));
+                finite("for", r.iter().copied()).map_err(at)?;
+                if step == 0.0 {
+                    return Err(at("`for` step must not be 0".into()).into());
+                }
+                // the stop value is included despite rounding
+                let count = ((stop - start) / step + 1e-9).floor();
+                for k in 0..=(count.max(-1.0) as i64) {
+                    self.assign(var, start + k as f64 * step, sim).map_err(at)?;
+                    self.block(body, sim, config, out)?;
+                }
+            }
+            Kind::If(cond, body) => {
+                if self.eval(cond, sim).map_err(at)? != 0.0 {
+                    self.block(body, sim, config, out)?;
+                }
+            }
+            _ => self.simple(s, sim, config, out).map_err(at)?,
+        }
+        Ok(())
+    }
+
+    /// Run statement `s`, which opens no block
+    fn simple(
+        &mut self,
+        s: &Statement,
+        sim: &mut Simulation,
+        config: &Config,
+        out: &mut impl Write,
+    ) -> Result<()> {
+        match &s.kind {
+            Kind::Assign(name, Value::Scalar(expr)) => {
+                let v = self.eval(expr, sim)?;
+                self.assign(name, v, sim)?;
+            }
+            Kind::Assign(name, Value::Vector(exprs)) => match name.as_str() {
+                "h_ext" => {
+                    let [x, y, z] = exprs;
+                    let h =
+                        Vector3::new(self.eval(x, sim)?, self.eval(y, sim)?, self.eval(z, sim)?);
+                    finite("h_ext", h.iter().copied())?;
+                    sim.params.h_ext = h;
+                }
+                "field" => self.field(exprs, sim)?,
+                _ => {
+                    return Err(
+                        format!("only `h_ext` and `field` take a vector, not `{name}`").into(),
+                    );
+                }
+            },
+            Kind::Call(name, args) => self.call(name, args, sim, config, out)?,
+            Kind::For { .. } | Kind::If(..) => unreachable!("blocks run in `statement`"),
+        }
+        Ok(())
+    }
+
+    fn call(
+        &mut self,
+        name: &str,
+        args: &[Expr],
+        sim: &mut Simulation,
+        config: &Config,
+        out: &mut impl Write,
+    ) -> Result<()> {
+        let arity = match name {
+            "run" | "steps" => 1,
+            "relax" | "minimize" | "save" => 0,
+            "print" => args.len(),
+            _ => return Err(format!("unknown command `{name}`").into()),
+        };
+        if args.len() != arity {
+            return Err(format!("`{name}` takes {arity} argument(s)").into());
+        }
+        match name {
+            "run" => {
+                let duration = self.eval(&args[0], sim)?;
+                if !(duration.is_finite() && duration >= 0.0) {
+                    return Err(format!("cannot run for {duration} s").into());
+                }
+                sim.run_until(sim.t + duration, u64::MAX)?;
+            }
+            "steps" => {
+                let n = self.eval(&args[0], sim)?;
+                if !(n.is_finite() && n >= 0.0) {
+                    return Err(format!("cannot take {n} steps").into());
+                }
+                sim.run(n.round() as u64)?;
+            }
+            "relax" | "minimize" => {
+                let relaxed = if name == "relax" {
+                    sim.relax(&config.relax)
+                } else {
+                    sim.minimize(&config.minimize)
+                };
+                if !relaxed.converged {
+                    writeln!(
+                        out,
+                        "# {name}: not converged after {} iterations, max torque {:.3e} T",
+                        relaxed.iterations, relaxed.torque
+                    )?;
+                }
+            }
+            "save" => sim.save()?,
+            _ => {
+                let mut line = Vec::with_capacity(args.len());
+                for expr in args {
+                    line.push(format!("{:.6e}", self.eval(expr, sim)?));
+                }
+                writeln!(out, "{}", line.join("\t"))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Set variable or parameter `name` to `v`
+    fn assign(&mut self, name: &str, v: f64, sim: &mut Simulation) -> Result<()> {
+        finite(name, [v])?;
+        let p = &mut sim.params;
+        let positive = |v: f64| {
+            if v > 0.0 {
+                Ok(v)
+            } else {
+                Err(format!("`{name}` must be positive, got {v}"))
+            }
+        };
+        match name {
+            "alpha" if v < 0.0 => {
+                return Err(format!("`alpha` must not be negative, got {v}").into());
+            }
+            "alpha" => p.alpha = v,
+            "a_ex" if v < 0.0 => return Err(format!("`a_ex` must not be negative, got {v}").into()),
+            "a_ex" => p.a_ex = v,
+            "gamma" => p.gamma = positive(v)?,
+            "mu0_ms" => p.mu0_ms = positive(v)?,
+            "dt" => {
+                p.dt = positive(v)?;
+                sim.dt = v;
+            }
+            _ if STATE.contains(&name) => return Err(format!("`{name}` cannot be set").into()),
+            _ => {
+                let k = self.vars.iter().position(|n| n == name);
+                self.values[k.expect("assigned names are variables")] = Some(v);
+            }
+        }
+        Ok(())
+    }
+
+    /// Values of the state and the variables, in the order of the names
+    /// the script is parsed over, the energy only if one of `exprs` needs
+    /// it; fails if one of them reads a variable not set yet
+    fn state(&self, exprs: &[&Expr], sim: &Simulation) -> Result<Vec<f64>> {
+        for (k, value) in self.values.iter().enumerate() {
+            if value.is_none() && exprs.iter().any(|e| e.uses(STATE.len() + k)) {
+                return Err(format!("`{}` is read before it is set", self.vars[k]).into());
+            }
+        }
+        let m = sim.average();
+        let energy = if exprs.iter().any(|e| e.uses(5)) {
+            sim.energy()
+        } else {
+            0.0
+        };
+        let p = &sim.params;
+        let state = [
+            sim.t,
+            sim.step as f64,
+            m.x,
+            m.y,
+            m.z,
+            energy,
+            p.alpha,
+            p.gamma,
+            p.a_ex,
+            p.mu0_ms,
+            p.dt,
+        ];
+        Ok(state
+            .into_iter()
+            .chain(self.values.iter().map(|v| v.unwrap_or(f64::NAN)))
+            .collect())
+    }
+
+    fn eval(&self, expr: &Expr, sim: &Simulation) -> Result<f64> {
+        Ok(expr.eval(&self.state(&[expr], sim)?))
+    }
+
+    /// Apply the uniform field `[x, y, z]`, as a function of time
+    fn field(&self, exprs: &[Expr; 3], sim: &mut Simulation) -> Result<()> {
+        let values = self.state(&exprs.each_ref(), sim)?;
+        let constant = exprs.iter().all(|e| !e.uses(0) && e.eval(&values) == 0.0);
+        let zeeman = sim
+            .terms
+            .get_mut::<Zeeman>()
+            .ok_or("no applied field term")?;
+        if constant {
+            zeeman.set_drive(None);
+            return Ok(());
+        }
+        let [x, y, z] = exprs.clone();
+        zeeman.set_drive(Some(Box::new(move |t| {
+            let mut values = values.clone();
+            values[0] = t;
+            Vector3::new(x.eval(&values), y.eval(&values), z.eval(&values))
+        })));
+        Ok(())
+    }
+}
+
+fn finite(name: &str, values: impl IntoIterator<Item = f64>) -> Result<()> {
+    if values.into_iter().all(f64::is_finite) {
+        Ok(())
+    } else {
+        Err(format!("`{name}` must be finite").into())
+    }
+}
@@ -0,0 +1,296 @@
+//! Live view of a running simulation in the browser, `nez run --serve`, in
+//! the spirit of the mumax3 web GUI. A small HTTP server answers `/` with a
+//! page drawing the magnetization and plotting the table, which it receives
+//! over a WebSocket at `/ws`:
+//!
+//! - a text message `{"columns": [...], "rows": [[...], ...]}` with the
+//!   table rows so far, when a browser connects, then one per new row;
+//! - a binary message with a frame of `m`: the sizes `nx`, `ny` as
+//!   little-endian u32, then `mx, my, mz` of every cell as f32, x fastest.
+//!   Frames are averaged through the thickness and over blocks of cells to
+//!   at most [`SIDE`] cells a side, and sent at most every [`INTERVAL`].
+//!
+//! The server runs on its own threads, one per connection, each browser
+//! with its own queue of [`QUEUE`] messages: one that does not keep up is
+//! dropped once its queue is full rather than slowing down the run. The
+//! WebSocket itself is spoken by `tungstenite`, which answers pings and
+//! closes; a request not sent in full within [`HANDSHAKE`], or a message
+//! from the browser longer than [`RECEIVED`] bytes, ends the connection.
+
+use std::{
+    collections::VecDeque,
+    io::{BufRead, BufReader, ErrorKind, Read, Write},
+    net::{SocketAddr, TcpListener, TcpStream},
+    sync::{
+        Arc, Mutex,
+        mpsc::{self, Receiver, SyncSender, TryRecvError},
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use tungstenite::{
+    Message, WebSocket,
+    handshake::derive_accept_key,
+    protocol::{CloseFrame, Role, WebSocketConfig, frame::coding::CloseCode},
+};
+
+use crate::{Result, Simulation};
+
+/// Largest number of cells along a side of the frames sent
+pub const SIDE: usize = 128;
+/// Shortest time between two frames sent
+pub const INTERVAL: Duration = Duration::from_millis(200);
+/// Table rows kept for browsers connecting later
+const HISTORY: usize = 10_000;
+/// Messages waiting for a browser before it is dropped
+pub const QUEUE: usize = 64;
+/// Longest time a browser may take to send its request
+pub const HANDSHAKE: Duration = Duration::from_secs(5);
+/// Largest request, and largest message, accepted from a browser
+pub const RECEIVED: usize = 8192;
+/// How often a connection looks for messages to send between reads
+const POLL: Duration = Duration::from_millis(20);
+
+const PAGE: &str = include_str!("serve.html");
+
+/// The live-view server of a run
+pub struct Server {
+    shared: Arc<Mutex<Shared>>,
+    sent: Option<Instant>,
+    addr: SocketAddr,
+}
+
+/// What the connection threads and the run share
+#[derive(Default)]
+struct Shared {
+    /// queues of the connection threads writing to the browsers
+    clients: Vec<SyncSender<Message>>,
+    columns: Vec<String>,
+    rows: VecDeque<Vec<f64>>,
+    /// the last frame, as sent
+    frame: Option<Message>,
+}
+
+impl Server {
+    /// Listen on `addr`, e.g. `127.0.0.1:35367`, or on a free port with
+    /// port 0
+    pub fn start(addr: &str) -> Result<Self> {
+        let listener =
+            TcpListener::bind(addr).map_err(|e| format!("cannot listen on {addr}: {e}"))?;
+        let addr = listener.local_addr()?;
+        let shared = Arc::new(Mutex::new(Shared::default()));
+        let accept = Arc::clone(&shared);
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let shared = Arc::clone(&accept);
+                thread::spawn(move || {
+                    if let Err(e) = connect(stream, &shared) {
+                        tracing::debug!("live view connection failed: {e}");
+                    }
+                });
+            }
+        });
+        tracing::info!("live view at http://{addr}/");
+        Ok(Self {
+            shared,
+            sent: None,
+            addr,
+        })
+    }
+
+    /// Address the server listens on
+    pub fn local_addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Send the table row of `sim`, its `observables`, and a frame unless
+    /// one went out very recently
+    pub fn publish(&mut self, sim: &Simulation, observables: &[(String, f64)]) {
+        let row: Vec<f64> = observables.iter().map(|(_, v)| *v).collect();
+        let frame = if self.sent.is_none_or(|s| s.elapsed() >= INTERVAL) {
+            self.sent = Some(Instant::now());
+            Some(frame(sim))
+        } else {
+            None
+        };
+        let mut shared = self.shared.lock().unwrap_or_else(|e| e.into_inner());
+        if shared.columns.is_empty() {
+            shared.columns = observables.iter().map(|(k, _)| k.clone()).collect();
+        }
+        let text = serde_json::json!({ "columns": shared.columns, "rows": [&row] }).to_string();
+        shared.rows.push_back(row);
+        if shared.rows.len() > HISTORY {
+            shared.rows.pop_front();
+        }
+        let mut messages = vec![Message::text(text)];
+        if let Some(frame) = frame {
+            let frame = Message::binary(frame);
+            messages.push(frame.clone());
+            shared.frame = Some(frame);
+        }
+        // a client whose queue is full, or whose connection closed, is dropped
+        shared
+            .clients
+            .retain(|c| messages.iter().all(|m| c.try_send(m.clone()).is_ok()));
+    }
+}
+
+/// Serve the page, or upgrade the connection to a WebSocket, add it to the
+/// clients and write it their messages until it is dropped
+fn connect(mut stream: TcpStream, shared: &Mutex<Shared>) -> Result<()> {
+    stream.set_write_timeout(Some(Duration::from_secs(1)))?;
+    let (request, key, read) = {
+        let until = Instant::now() + HANDSHAKE;
+        let deadline = Deadline {
+            stream: &stream,
+            until,
+        };
+        let mut reader = BufReader::new(deadline.take(RECEIVED as u64));
+        let mut request = String::new();
+        reader.read_line(&mut request)?;
+        let mut key = None;
+        loop {
+            let mut line = String::new();
+            if reader.read_line(&mut line)? == 0 {
+                return Err(format!("request cut short or longer than {RECEIVED} bytes").into());
+            }
+            if line.trim().is_empty() {
+                break;
+            }
+            if let Some((name, value)) = line.split_once(':')
+                && name.trim().eq_ignore_ascii_case("sec-websocket-key")
+            {
+                key = Some(value.trim().to_string());
+            }
+        }
+        // whatever the browser sent past its request
+        (request, key, reader.buffer().to_vec())
+    };
+    let path = request.split_whitespace().nth(1).unwrap_or("/");
+    match (path, key) {
+        ("/ws", Some(key)) => {
+            let accept = derive_accept_key(key.as_bytes());
+            let (sender, queue) = mpsc::sync_channel(QUEUE);
+            let mut history = Vec::new();
+            {
+                let mut shared = shared.lock().unwrap_or_else(|e| e.into_inner());
+                if !shared.columns.is_empty() {
+                    let text =
+                        serde_json::json!({ "columns": shared.columns, "rows": shared.rows });
+                    history.push(Message::text(text.to_string()));
+                }
+                history.extend(shared.frame.clone());
+                shared.clients.push(sender);
+            }
+            // answered once taken on, so that nothing published after the
+            // browser sees the upgrade is missed
+            write!(
+                stream,
+                "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\n\
+                 Connection: Upgrade\r\nSec-WebSocket-Accept: {accept}\r\n\r\n"
+            )?;
+            stream.set_read_timeout(Some(POLL))?;
+            let config = WebSocketConfig::default()
+                .max_message_size(Some(RECEIVED))
+                .max_frame_size(Some(RECEIVED));
+            let mut ws = WebSocket::from_partially_read(stream, read, Role::Server, Some(config));
+            for m in history {
+                ws.write(m)?;
+            }
+            talk(&mut ws, &queue)?;
+        }
+        ("/", _) => {
+            write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Type: text/html; charset=utf-8\r\n\
+                 Content-Length: {}\r\nConnection: close\r\n\r\n{PAGE}",
+                PAGE.len()
+            )?;
+        }
+        _ => {
+            stream.write_all(
+                b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/// Write the messages of `queue` to the browser as they come, reading what
+/// it sends in between, until either end closes
+fn talk(ws: &mut WebSocket<TcpStream>, queue: &Receiver<Message>) -> Result<()> {
+    loop {
+        loop {
+            match queue.try_recv() {
+                Ok(m) => ws.write(m)?,
+                Err(TryRecvError::Empty) => break,
+                // dropped for not keeping up
+                Err(TryRecvError::Disconnected) => return Ok(()),
+            }
+        }
+        ws.flush()?;
+        // pings are answered by tungstenite, and a close echoed once flushed
+        match ws.read() {
+            Ok(Message::Close(_)) => return Ok(ws.flush()?),
+            Ok(_) => {}
+            Err(tungstenite::Error::Io(e))
+                if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {}
+            Err(tungstenite::Error::ConnectionClosed) => return Ok(()),
+            Err(e @ tungstenite::Error::Capacity(_)) => {
+                let frame = CloseFrame {
+                    code: CloseCode::Size,
+                    reason: "message too long".into(),
+                };
+                ws.close(Some(frame))?;
+                return Err(e.into());
+            }
+            Err(e) => return Err(e.into()),
+        }
+    }
+}
+
+/// The stream of a connection, read until a deadline after which reads fail
+struct Deadline<'a> {
+    stream: &'a TcpStream,
+    until: Instant,
+}
+
+impl Read for Deadline<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let left = self.until.saturating_duration_since(Instant::now());
+        if left.is_zero() {
+            return Err(ErrorKind::TimedOut.into());
+        }
+        self.stream.set_read_timeout(Some(left))?;
+        let mut stream = self.stream;
+        stream.read(buf)
+    }
+}
+
+/// Frame of `m` in the layout of the binary messages
+fn frame(sim: &Simulation) -> Vec<u8> {
+    let mesh = &sim.mesh;
+    let block = mesh.nx.max(mesh.ny).div_ceil(SIDE);
+    let (nx, ny) = (mesh.nx.div_ceil(block), mesh.ny.div_ceil(block));
+    let mut sum = vec![[0.0f64; 3]; nx * ny];
+    for i in 0..mesh.len() {
+        let [x, y, _] = mesh.coords(i);
+        let m = sim.m.get(i);
+        let s = &mut sum[x / block + nx * (y / block)];
+        for c in 0..3 {
+            s[c] += m[c];
+        }
+    }
+    let mut bytes = Vec::with_capacity(8 + 12 * nx * ny);
+    bytes.extend((nx as u32).to_le_bytes());
+    bytes.extend((ny as u32).to_le_bytes());
+    for s in sum {
+        let norm = (s[0] * s[0] + s[1] * s[1] + s[2] * s[2]).sqrt();
+        for c in s {
+            let v = if norm > 0.0 { c / norm } else { 0.0 };
+            bytes.extend((v as f32).to_le_bytes());
+        }
+    }
+    bytes
+}
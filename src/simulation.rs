@@ -0,0 +1,941 @@
+use nalgebra::Vector3;
+use std::{fs, path::Path, sync::Arc, time::Instant};
+
+use crate::{
+    Config, FieldTerms, Mesh, Params, Result, Torques, VectorField, ZarrOutput,
+    config::{FieldsConfig, RelaxConfig, RunConfig, SnapshotConfig, SolverConfig},
+    geometry::Geometry,
+    grains::Grains,
+    interrupt,
+    langevin::Langevin,
+    llb::Llb,
+    llg::{Llg, hold_frozen, max_torque},
+    logging,
+    maps::CellParams,
+    minimize::{self, MinimizeConfig, Relaxed},
+    mtj::Mtj,
+    output::{Checkpoint, FieldFrame, is_remote},
+    probe::Probe,
+    profile::{self, Section},
+    regions::Regions,
+    rng::Rng,
+    snapshot,
+    spin_pumping::SpinPumping,
+    stepper::Stepper,
+    sublattice::Sublattice,
+    table::{Table, TableConfig},
+    temperature::Thermal,
+    topology,
+    wall::WallConfig,
+};
+
+/// Most steps taken in one batch, so that a signal or the deadline ends the
+/// run soon after however far apart its outputs are
+const BATCH: u64 = 1000;
+
+/// A magnetic sample together with its parameters, state and output
+pub struct Simulation {
+    pub mesh: Mesh,
+    pub params: Params,
+    pub terms: FieldTerms,
+    pub torques: Torques,
+    pub solver: SolverConfig,
+    pub stepper: Box<dyn Stepper>,
+    /// second sublattice of an antiferromagnet or ferrimagnet, if any
+    pub sublattice: Option<Sublattice>,
+    /// Landau–Lifshitz–Bloch equation of motion replacing the LLG equation,
+    /// if any
+    pub llb: Option<Llb>,
+    /// stochastic thermal field, if any, drawn from `rng` every step
+    pub langevin: Option<Langevin>,
+    /// magnetization, one unit vector per cell (shorter under the LLB
+    /// equation), followed by that of the
+    /// second sublattice if any
+    pub m: VectorField,
+    /// number of steps taken so far
+    pub step: u64,
+    /// simulated time (s)
+    pub t: f64,
+    /// current time step (s): `params.dt` for fixed-step methods, adapted
+    /// after every step otherwise
+    pub dt: f64,
+    pub output: Option<ZarrOutput>,
+    /// number of snapshots written to `output`
+    pub frame: u64,
+    /// steps between frames written by [`Simulation::run`]
+    pub save_every: u64,
+    /// snapshot files written by [`Simulation::run`], with `dir` set
+    pub snapshots: Option<SnapshotConfig>,
+    /// fields written to `output` by [`Simulation::run`]
+    pub fields: Option<FieldsConfig>,
+    /// steps between checkpoints written by [`Simulation::run`] (0 for none)
+    pub checkpoint_every: u64,
+    /// random number generator of stochastic terms, if any
+    pub rng: Option<Rng>,
+    /// per-step observables, recorded by [`Simulation::run`] once started
+    pub table: Option<Table>,
+    /// domain wall followed in the observables, if any
+    pub wall: Option<WallConfig>,
+    /// groups of cells whose average `m` joins the observables
+    pub probes: Vec<Probe>,
+    /// interface pumping spin current into a normal metal, see
+    /// [`crate::spin_pumping`]
+    pub spin_pumping: Option<SpinPumping>,
+    /// tunnel junction over the `[slonczewski]` polarizer, see
+    /// [`crate::mtj`]
+    pub mtj: Option<Mtj>,
+    /// distance `m` has been shifted by to keep that wall centred (m)
+    pub wall_shift: f64,
+    /// candidate state of the step being taken, kept between steps so that
+    /// stepping allocates nothing
+    next: VectorField,
+}
+
+impl Simulation {
+    /// New simulation with every spin along `m0` and no output attached
+    pub fn new(mesh: Mesh, params: Params, m0: Vector3<f64>) -> Self {
+        Self {
+            m: VectorField::uniform(mesh.len(), m0.normalize()),
+            dt: params.dt,
+            mesh,
+            params,
+            terms: FieldTerms::default(),
+            torques: Torques::default(),
+            solver: SolverConfig::default(),
+            stepper: SolverConfig::default().method.stepper(),
+            sublattice: None,
+            llb: None,
+            langevin: None,
+            step: 0,
+            t: 0.0,
+            output: None,
+            frame: 0,
+            save_every: 1,
+            snapshots: None,
+            fields: None,
+            checkpoint_every: 0,
+            rng: None,
+            table: None,
+            wall: None,
+            probes: Vec::new(),
+            spin_pumping: None,
+            mtj: None,
+            wall_shift: 0.0,
+            next: VectorField::default(),
+        }
+    }
+
+    /// Build a simulation from a config, creating its (empty) Zarr store and
+    /// the log file besides it with `output.log`
+    pub fn from_config(config: &Config) -> Result<Self> {
+        let sim = Self::build(config)?;
+        let mut output = ZarrOutput::create(&config.output, &config.mesh)?;
+        output.write_config(config)?;
+        if config.output.log {
+            logging::log_to(&config.output.path.with_extension("log"), false)?;
+        }
+        Ok(sim.with_output(output))
+    }
+
+    /// Build a simulation from a config without attaching any output
+    pub fn build(config: &Config) -> Result<Self> {
+        let mut m = config.initial.state(&config.mesh)?;
+        if let Some(s) = &config.sublattice {
+            m = s.state(&m);
+        }
+        let mut sim = Self::new(config.mesh, config.params.clone(), config.initial.m);
+        sim.m = m;
+        sim.configure(config)?;
+        if let Some(g) = &sim.params.geometry {
+            g.clear_outside(&mut sim.m);
+        }
+        if let Some(t) = &sim.params.thermal {
+            t.clear_paramagnetic(&mut sim.m);
+        }
+        Ok(sim)
+    }
+
+    /// Reopen a store written by [`Simulation::from_config`] and continue
+    /// from its latest checkpoint, or from its last snapshot if that is more
+    /// recent. Returns the recorded config alongside.
+    pub fn resume(path: impl AsRef<Path>) -> Result<(Self, Config)> {
+        let path = path.as_ref();
+        let output = ZarrOutput::open(path)?;
+        let config = output.config()?;
+        if config.output.log && !is_remote(path) {
+            logging::log_to(&path.with_extension("log"), true)?;
+        }
+        let frames = output.frames()?;
+        let mut sim = Self::new(config.mesh, config.params.clone(), config.initial.m);
+        sim.configure(&config)?;
+        match output.checkpoint()? {
+            // snapshots written after the checkpoint are simply redone
+            Some((checkpoint, m)) if checkpoint.frame >= frames => sim.restore(&checkpoint, m),
+            _ if frames == 0 => {
+                return Err("store contains no snapshots to resume from".into());
+            }
+            _ => {
+                sim.m = output.read(frames - 1)?;
+                // frame 0 is the initial state
+                sim.step = (frames - 1) * sim.save_every;
+                sim.t = match output.time(frames - 1)? {
+                    Some(t) => t,
+                    None => sim.step as f64 * sim.params.dt,
+                };
+                sim.frame = frames;
+            }
+        }
+        Ok((sim.with_output(output), config))
+    }
+
+    /// Take the terms, torques, solver and output cadences of `config`
+    fn configure(&mut self, config: &Config) -> Result<()> {
+        self.params.geometry = match &config.geometry {
+            Some(shape) => Some(Arc::new(Geometry::new(shape, &config.mesh)?)),
+            None => None,
+        };
+        let regions =
+            Regions::new(&config.regions, &config.exchange_scale, &config.mesh)?.map(Arc::new);
+        let grains = config
+            .grains
+            .as_ref()
+            .map(|g| Arc::new(Grains::new(g, &config.mesh)));
+        self.terms = FieldTerms::from_config(config, regions.as_deref(), grains.as_deref())?;
+        self.params.frozen = regions.as_ref().and_then(|r| r.frozen_cells());
+        self.params.regions = regions;
+        self.params.grains = grains;
+        self.params.maps = CellParams::new(&config.maps.params, &config.mesh)?.map(Arc::new);
+        self.params.thermal = match &config.temperature {
+            Some(t) => Some(Arc::new(Thermal::new(t, &config.mesh)?)),
+            None => None,
+        };
+        self.params.absorbing = config
+            .absorbing
+            .as_ref()
+            .map(|a| a.damping(&config.mesh).into());
+        self.torques = Torques::from_config(config)?;
+        self.sublattice = config
+            .sublattice
+            .as_ref()
+            .map(|s| Sublattice::new(s, &config.params));
+        self.llb = config.llb.as_ref().map(Llb::new);
+        self.langevin = match &config.langevin {
+            Some(l) => Some(Langevin::new(l, &config.mesh)?),
+            None => None,
+        };
+        self.rng = config.langevin.as_ref().map(|l| Rng::new(l.seed));
+        self.set_solver(config.solver.clone());
+        self.save_every = config.output.every;
+        self.checkpoint_every = config.run.checkpoint_every;
+        self.wall = config.wall;
+        self.probes = config
+            .probes
+            .iter()
+            .map(|p| Probe::new(p, &self.mesh, &self.params))
+            .collect::<Result<_>>()?;
+        self.spin_pumping = match &config.spin_pumping {
+            Some(s) => Some(SpinPumping::new(s, &self.mesh, &self.params)?),
+            None => None,
+        };
+        self.mtj = match &config.mtj {
+            Some(c) => Some(Mtj::new(c, &self.mesh, &self.params)?),
+            None => None,
+        };
+        self.snapshots = match &config.output.snapshots {
+            Some(s) => {
+                let dir = config.output.snapshot_dir();
+                fs::create_dir_all(&dir)
+                    .map_err(|e| format!("cannot create {}: {e}", dir.display()))?;
+                Some(SnapshotConfig {
+                    dir: Some(dir),
+                    ..s.clone()
+                })
+            }
+            None => None,
+        };
+        if let Some(fields) = &config.output.fields {
+            for name in &fields.terms {
+                if !self.terms.iter().any(|t| t.name() == name) {
+                    let names: Vec<_> = self.terms.iter().map(|t| t.name()).collect();
+                    return Err(format!(
+                        "`output.fields.terms`: no enabled term `{name}`, only {}",
+                        names.join(", ")
+                    )
+                    .into());
+                }
+            }
+        }
+        self.fields = config.output.fields.clone();
+        Ok(())
+    }
+
+    /// Continue from `checkpoint` with magnetization `m`
+    pub fn restore(&mut self, checkpoint: &Checkpoint, m: VectorField) {
+        self.m = m;
+        self.step = checkpoint.step;
+        self.t = checkpoint.t;
+        self.dt = checkpoint.dt;
+        self.frame = checkpoint.frame;
+        self.rng = checkpoint.rng.map(Rng::from_state);
+        self.wall_shift = checkpoint.wall_shift;
+    }
+
+    /// Solver state beyond the magnetization
+    pub fn state(&self) -> Checkpoint {
+        Checkpoint {
+            step: self.step,
+            t: self.t,
+            dt: self.dt,
+            frame: self.frame,
+            rng: self.rng.as_ref().map(Rng::state),
+            wall_shift: self.wall_shift,
+        }
+    }
+
+    /// Record a checkpoint in the output (no-op without output)
+    pub fn checkpoint(&self) -> Result<()> {
+        if let Some(out) = &self.output {
+            let start = Instant::now();
+            out.write_checkpoint(&self.state(), &self.m)?;
+            tracing::debug!(step = self.step, elapsed = ?start.elapsed(), "wrote checkpoint");
+            profile::record(Section::Output, "checkpoint", start);
+        }
+        Ok(())
+    }
+
+    /// Switch to the scheme and tolerances of `solver`
+    pub fn set_solver(&mut self, solver: SolverConfig) {
+        self.stepper = solver.stepper();
+        self.solver = solver;
+    }
+
+    pub fn with_output(mut self, output: ZarrOutput) -> Self {
+        self.output = Some(output);
+        self
+    }
+
+    /// Advance by one time step. Adaptive methods retry with a smaller step
+    /// until the error estimate is within `solver.tolerance`.
+    pub fn step(&mut self) {
+        if let (Some(langevin), Some(rng)) = (&mut self.langevin, &mut self.rng) {
+            langevin.draw(&self.params, &self.mesh, self.dt, rng);
+        }
+        let llg = Llg {
+            mesh: &self.mesh,
+            params: &self.params,
+            terms: &self.terms,
+            torques: &self.torques,
+            precession: true,
+            sublattice: self.sublattice.as_ref(),
+            llb: self.llb.as_ref(),
+            noise: self.langevin.as_ref().map(|l| &l.field),
+        };
+        let dt = advance(
+            self.stepper.as_mut(),
+            &self.solver,
+            &llg,
+            &mut self.m,
+            &mut self.next,
+            self.t,
+            &mut self.dt,
+        );
+        self.t += dt;
+        self.step += 1;
+    }
+
+    /// Advance by `n` steps, autosaving after each one and checkpointing
+    /// every `checkpoint_every` steps. Fixed-step schemes take the steps
+    /// between two outputs in batches of up to a thousand, unless thermal
+    /// fluctuations need a new field every step or the window follows a
+    /// wall, which it checks after every step. Returns early, after the
+    /// step or batch under way, once a signal asks to end (see
+    /// [`interrupt`]).
+    pub fn run(&mut self, n: u64) -> Result<()> {
+        let end = self.step + n;
+        while self.step < end && !interrupt::requested() {
+            let centered = self.wall.filter(|w| w.center);
+            // the stochastic field is drawn anew every step
+            if self.stepper.order().is_some() || self.langevin.is_some() || centered.is_some() {
+                self.step();
+            } else {
+                self.steps(self.until_output().min(end - self.step).min(BATCH));
+            }
+            if let Some(wall) = centered {
+                self.center_wall(wall)?;
+            }
+            self.autosave()?;
+            if self.checkpoint_every > 0 && self.step.is_multiple_of(self.checkpoint_every) {
+                self.checkpoint()?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Advance as [`Simulation::run`] does until time `t_end`, but by at most
+    /// `n` steps; returns whether `t_end` was reached. Adaptive schemes
+    /// shorten the last step to land on it, fixed-step schemes take the
+    /// nearest whole number of steps.
+    pub fn run_until(&mut self, t_end: f64, n: u64) -> Result<bool> {
+        if self.stepper.order().is_none() {
+            let left = ((t_end - self.t) / self.dt).round().max(0.0) as u64;
+            self.run(left.min(n))?;
+            return Ok(left <= n);
+        }
+        for _ in 0..n {
+            if self.reached(t_end) {
+                return Ok(true);
+            }
+            if interrupt::requested() {
+                return Ok(false);
+            }
+            let left = t_end - self.t;
+            if self.dt < left {
+                self.run(1)?;
+            } else {
+                // keep the step size for what follows
+                let dt = self.dt;
+                self.dt = left;
+                self.run(1)?;
+                self.dt = dt;
+            }
+        }
+        Ok(self.reached(t_end))
+    }
+
+    /// Whether the time has reached `t_end`: up to rounding, or to half a
+    /// step for fixed-step schemes, as [`Simulation::run_until`] lands
+    fn reached(&self, t_end: f64) -> bool {
+        if self.stepper.order().is_none() {
+            t_end - self.t < 0.5 * self.dt
+        } else {
+            t_end - self.t <= 4.0 * f64::EPSILON * t_end.abs()
+        }
+    }
+
+    /// Shift `m` to bring `wall` back to the centre of the mesh once it is
+    /// a cell or more away; an error once there is no wall to bring back
+    fn center_wall(&mut self, wall: WallConfig) -> Result<()> {
+        let Some(by) = wall.offset(&self.mesh, &self.m) else {
+            return Err(format!(
+                "lost the wall followed by `wall.center` at step {}, t = {:.3e} s",
+                self.step, self.t
+            )
+            .into());
+        };
+        if by != 0 {
+            wall.shift(&self.mesh, &mut self.m, by);
+            self.wall_shift += by as f64 * self.mesh.cell()[wall.axis as usize];
+            tracing::trace!(step = self.step, cells = by, "shifted m to centre the wall");
+        }
+        Ok(())
+    }
+
+    /// Advance by `n` steps of the fixed size `dt`
+    fn steps(&mut self, n: u64) {
+        let llg = Llg {
+            mesh: &self.mesh,
+            params: &self.params,
+            terms: &self.terms,
+            torques: &self.torques,
+            precession: true,
+            sublattice: self.sublattice.as_ref(),
+            llb: self.llb.as_ref(),
+            noise: None,
+        };
+        self.stepper
+            .advance_fixed(&llg, &mut self.m, &mut self.next, self.t, self.dt, n);
+        for _ in 0..n {
+            self.t += self.dt;
+        }
+        self.step += n;
+    }
+
+    /// Steps until the next frame, table row, snapshot file, frame of the
+    /// fields or checkpoint
+    fn until_output(&self) -> u64 {
+        let mut every = vec![self.save_every];
+        every.extend(self.table.as_ref().map(|t| t.every));
+        every.extend(self.snapshots.as_ref().map(|s| s.every));
+        every.extend(self.fields.as_ref().map(|f| f.every));
+        if self.checkpoint_every > 0 {
+            every.push(self.checkpoint_every);
+        }
+        every
+            .into_iter()
+            .map(|e| e - self.step % e)
+            .min()
+            .unwrap_or(1)
+    }
+
+    /// Relax to the nearest energy minimum without time integration (spin
+    /// torques are ignored)
+    pub fn minimize(&mut self, config: &MinimizeConfig) -> Relaxed {
+        let no_torques = Torques::default();
+        let llg = Llg {
+            mesh: &self.mesh,
+            params: &self.params,
+            terms: &self.terms,
+            torques: &no_torques,
+            precession: true,
+            sublattice: self.sublattice.as_ref(),
+            llb: None,
+            noise: None,
+        };
+        minimize::minimize(&mut self.m, &llg, self.t, config)
+    }
+
+    /// Relax by integrating the LLG equation without precession at frozen
+    /// time, until max|m × B_eff| drops below `config.tolerance`. Spin torques
+    /// are ignored; `t`, `step` and `dt` are left untouched.
+    ///
+    /// Adaptive schemes tend to settle at the stability limit of the stiffest
+    /// mode, where the error tolerance sets a floor on the torque, so their
+    /// tolerance is halved whenever the torque stops decreasing.
+    pub fn relax(&mut self, config: &RelaxConfig) -> Relaxed {
+        let no_torques = Torques::default();
+        let llg = Llg {
+            mesh: &self.mesh,
+            params: &self.params,
+            terms: &self.terms,
+            torques: &no_torques,
+            precession: false,
+            sublattice: self.sublattice.as_ref(),
+            llb: None,
+            noise: None,
+        };
+        let torque = |m: &VectorField| max_torque(m, &llg.effective_field(m, self.t), llg.params);
+        let mut solver = self.solver.clone();
+        let mut dt = self.dt;
+        let mut steps = 0;
+        let mut last = f64::INFINITY;
+        while steps < config.max_steps {
+            if steps % config.check_every == 0 {
+                let now = torque(&self.m);
+                if now <= config.tolerance {
+                    break;
+                }
+                if now >= last {
+                    solver.tolerance = (solver.tolerance / 2.0).max(f64::EPSILON);
+                }
+                last = now;
+            }
+            advance(
+                self.stepper.as_mut(),
+                &solver,
+                &llg,
+                &mut self.m,
+                &mut self.next,
+                self.t,
+                &mut dt,
+            );
+            steps += 1;
+        }
+        let torque = torque(&self.m);
+        Relaxed {
+            iterations: steps,
+            torque,
+            converged: torque <= config.tolerance,
+        }
+    }
+
+    /// Write the current state to the output (no-op without output)
+    pub fn save(&mut self) -> Result<()> {
+        if let Some(out) = &mut self.output {
+            let start = Instant::now();
+            out.write(self.frame, self.t, &self.m)?;
+            tracing::debug!(frame = self.frame, elapsed = ?start.elapsed(), "wrote frame");
+            profile::record(Section::Output, "frame of m", start);
+            self.frame += 1;
+        }
+        Ok(())
+    }
+
+    /// Wait for the frames of `m` under way to be written to the output,
+    /// e.g. before reading the store from another handle
+    pub fn flush(&self) -> Result<()> {
+        match &self.output {
+            Some(out) => out.flush(),
+            None => Ok(()),
+        }
+    }
+
+    /// Write what is due at the current step: a frame of `m` every
+    /// `save_every` steps, a table row every `table.every`, a snapshot
+    /// file every `snapshots.every` and a frame of the fields every
+    /// `fields.every`. Frame, row and file `k` thus hold step `k × every`.
+    pub fn autosave(&mut self) -> Result<()> {
+        if self.step.is_multiple_of(self.save_every) {
+            self.save()?;
+        }
+        if let Some(table) = &self.table
+            && self.step.is_multiple_of(table.every)
+        {
+            self.record()?;
+        }
+        if let Some(s) = &self.snapshots
+            && self.step.is_multiple_of(s.every)
+        {
+            let start = Instant::now();
+            snapshot::write(
+                s.dir.as_deref().unwrap_or(Path::new(".")),
+                self.step / s.every,
+                &self.mesh,
+                &self.m,
+                Some(self.t),
+                s.format,
+                s.data,
+            )?;
+            tracing::debug!(step = self.step, elapsed = ?start.elapsed(), "wrote snapshot file");
+            profile::record(Section::Output, "snapshot file", start);
+        }
+        if let Some(f) = &self.fields
+            && self.step.is_multiple_of(f.every)
+        {
+            self.save_fields()?;
+        }
+        Ok(())
+    }
+
+    /// Write the fields of `output.fields` at the current step to the
+    /// output (no-op without either)
+    pub fn save_fields(&mut self) -> Result<()> {
+        let (Some(fields), Some(_)) = (&self.fields, &self.output) else {
+            return Ok(());
+        };
+        let start = Instant::now();
+        let (m, t, mesh, p) = (&self.m, self.t, &self.mesh, &self.params);
+        let mut values = Vec::new();
+        if fields.b_eff || fields.torque {
+            let h = self.equation(&Torques::default()).effective_field(m, t);
+            if fields.torque {
+                let mut torque = VectorField::zeros(m.len());
+                torque.par_add(|i| m.get(i).cross(&h.get(i)));
+                hold_frozen(p, &mut torque);
+                values.push(("torque".to_string(), FieldFrame::Vectors(torque)));
+            }
+            if fields.b_eff {
+                values.push(("B_eff".to_string(), FieldFrame::Vectors(h)));
+            }
+        }
+        for name in &fields.terms {
+            let mut b = VectorField::zeros(m.len());
+            if let Some(term) = self.terms.iter().find(|term| term.name() == name) {
+                term.add_field(m, t, mesh, p, &mut b);
+            }
+            values.push((format!("B_{name}"), FieldFrame::Vectors(b)));
+        }
+        if fields.energy_density {
+            let e = self.terms.energy_density(m, t, mesh, p);
+            values.push(("energy_density".to_string(), FieldFrame::Scalars(e)));
+        }
+        let frame = self.step / fields.every;
+        if let Some(out) = &mut self.output {
+            out.write_fields(frame, t, &values)?;
+        }
+        tracing::debug!(frame, elapsed = ?start.elapsed(), "wrote fields");
+        profile::record(Section::Output, "frame of the fields", start);
+        Ok(())
+    }
+
+    /// Start recording [`Simulation::observables`] as configured, continuing
+    /// after the rows already written up to the current step
+    pub fn start_table(&mut self, config: &TableConfig) -> Result<()> {
+        if !config.enabled() {
+            self.table = None;
+            return Ok(());
+        }
+        let columns = self.observables().into_iter().map(|(k, _)| k).collect();
+        let rows = if self.step == 0 {
+            0
+        } else {
+            self.step / config.every + 1
+        };
+        self.table = Some(Table::open(config, columns, rows, self.output.as_mut())?);
+        Ok(())
+    }
+
+    /// Append the current observables to the table (no-op without table)
+    pub fn record(&mut self) -> Result<()> {
+        if self.table.is_none() {
+            return Ok(());
+        }
+        let start = profile::start();
+        let values: Vec<_> = self.observables().into_iter().map(|(_, v)| v).collect();
+        profile::record(Section::Output, "table observables", start);
+        let start = profile::start();
+        if let Some(table) = &mut self.table {
+            table.write(&values, self.output.as_mut())?;
+        }
+        profile::record(Section::Output, "table row", start);
+        Ok(())
+    }
+
+    /// Named scalars describing the current state: step, time (s), ⟨m⟩ (and
+    /// ⟨m₂⟩ of a second sublattice), the total energy and that of every term
+    /// (J), the topological charge of films (see [`topology`]), the position
+    /// (m, counting the shifts of a moving window) and velocity (m/s) of a
+    /// domain wall (see [`crate::wall`]), ⟨m⟩ over every probe (see
+    /// [`crate::probe`]), the pumped spin current (J m⁻²) and ISHE voltage
+    /// (V) of `[spin_pumping]` (see [`crate::spin_pumping`]), the current
+    /// density (A m⁻²), resistance (Ω) and magnetoresistance of `[mtj]` (see
+    /// [`crate::mtj`]),
+    /// max|m × B_eff| (T), max|dm/dt| (s⁻¹) and the time step (s)
+    pub fn observables(&self) -> Vec<(String, f64)> {
+        let no_torques = Torques::default();
+        let llg = Llg {
+            mesh: &self.mesh,
+            params: &self.params,
+            terms: &self.terms,
+            torques: &no_torques,
+            precession: true,
+            sublattice: self.sublattice.as_ref(),
+            llb: self.llb.as_ref(),
+            noise: None,
+        };
+        let (m, t) = (&self.m, self.t);
+        let energies = self.energies();
+        let h = llg.effective_field(m, t);
+        let avg = self.average();
+        let mut values = vec![
+            ("step".to_string(), self.step as f64),
+            ("t".to_string(), t),
+            ("mx".to_string(), avg.x),
+            ("my".to_string(), avg.y),
+            ("mz".to_string(), avg.z),
+        ];
+        if self.sublattice.is_some() {
+            let avg = self.sublattice_average(1);
+            values.push(("m2x".to_string(), avg.x));
+            values.push(("m2y".to_string(), avg.y));
+            values.push(("m2z".to_string(), avg.z));
+        }
+        values.push(("E_total".to_string(), energies.iter().map(|(_, e)| e).sum()));
+        values.extend(energies.iter().map(|(k, e)| (format!("E_{k}"), *e)));
+        if topology::applies(&self.mesh) {
+            let n = self.mesh.len();
+            let charge = if self.sublattice.is_some() {
+                let mut m = VectorField::default();
+                m.copy_range(&self.m, 0..n);
+                topology::charge(&self.mesh, &m)
+            } else {
+                topology::charge(&self.mesh, m)
+            };
+            values.push(("topological_charge".to_string(), charge));
+        }
+        let torques = Llg {
+            torques: &self.torques,
+            ..llg
+        };
+        let dmdt = if self.sublattice.is_some() {
+            torques.rhs(m, t)
+        } else {
+            let mut dmdt = h.clone();
+            torques.field_to_rhs(m, t, &self.params, &mut dmdt);
+            dmdt
+        };
+        if let Some(wall) = &self.wall {
+            let mesh = &self.mesh;
+            let position = wall.position(mesh, m) + self.wall_shift;
+            values.push(("wall_position".to_string(), position));
+            values.push(("wall_velocity".to_string(), wall.velocity(mesh, m, &dmdt)));
+        }
+        for probe in &self.probes {
+            let avg = probe.mean(m);
+            values.push((format!("{}_mx", probe.name), avg.x));
+            values.push((format!("{}_my", probe.name), avg.y));
+            values.push((format!("{}_mz", probe.name), avg.z));
+        }
+        if let Some(pumping) = &self.spin_pumping {
+            let js = pumping.spin_current(m, &dmdt);
+            values.push(("spin_current_x".to_string(), js.x));
+            values.push(("spin_current_y".to_string(), js.y));
+            values.push(("spin_current_z".to_string(), js.z));
+            values.push(("ishe_voltage".to_string(), pumping.voltage(&js)));
+        }
+        if let Some(mtj) = &self.mtj
+            && let Some(torque) = &self.torques.slonczewski
+        {
+            let r = mtj.resistance(&avg, &torque.fixed);
+            values.push(("current_density".to_string(), torque.current.eval(t)));
+            values.push(("resistance".to_string(), r));
+            values.push(("magnetoresistance".to_string(), r / mtj.r_p - 1.0));
+        }
+        values.push(("max_torque".to_string(), max_torque(m, &h, &self.params)));
+        values.push(("max_dmdt".to_string(), dmdt.max_norm()));
+        values.push(("dt".to_string(), self.dt));
+        values
+    }
+
+    /// max|m × B_eff| over the cells that are not frozen (T)
+    pub fn max_torque(&self) -> f64 {
+        let h = self
+            .equation(&Torques::default())
+            .effective_field(&self.m, self.t);
+        max_torque(&self.m, &h, &self.params)
+    }
+
+    /// max|dm/dt| over the cells, spin torques included (s⁻¹)
+    pub fn max_dmdt(&self) -> f64 {
+        self.equation(&self.torques).rhs(&self.m, self.t).max_norm()
+    }
+
+    /// The condition of `run` that ends a run met by the current state, if
+    /// any, as shown to the user, for a run started at `start`; a condition
+    /// on a column missing from the table is an error
+    pub fn stop_reached(&self, run: &RunConfig, start: Instant) -> Result<Option<String>> {
+        if let Some(t_end) = run.stop_time
+            && self.reached(t_end)
+        {
+            return Ok(Some(format!("t = {t_end:e} s reached")));
+        }
+        if let Some(stop) = run.stop_wall {
+            let elapsed = start.elapsed().as_secs_f64();
+            if elapsed >= stop {
+                return Ok(Some(format!("{elapsed:.0} s of wall-clock time")));
+            }
+        }
+        if let Some(stop) = run.stop_torque {
+            let torque = self.max_torque();
+            if torque < stop {
+                return Ok(Some(format!("max torque {torque:.3e} T below {stop:e} T")));
+            }
+        }
+        if let Some(stop) = run.stop_dmdt {
+            let dmdt = self.max_dmdt();
+            if dmdt < stop {
+                return Ok(Some(format!("max |dm/dt| {dmdt:.3e} /s below {stop:e} /s")));
+            }
+        }
+        if run.stop_when.is_empty() {
+            return Ok(None);
+        }
+        let values = self.observables();
+        for stop in &run.stop_when {
+            let name = &stop.column;
+            let Some(&(_, v)) = values.iter().find(|(k, _)| k == name) else {
+                let columns: Vec<_> = values.iter().map(|(k, _)| k.as_str()).collect();
+                return Err(format!(
+                    "`run.stop_when`: no column `{name}` in the table, which has {}",
+                    columns.join(", ")
+                )
+                .into());
+            };
+            if let Some(below) = stop.below.filter(|&b| v < b) {
+                return Ok(Some(format!("{name} = {v:.3e} below {below}")));
+            }
+            if let Some(above) = stop.above.filter(|&a| v > a) {
+                return Ok(Some(format!("{name} = {v:.3e} above {above}")));
+            }
+        }
+        Ok(None)
+    }
+
+    /// The equation of motion with `torques`, without thermal noise
+    fn equation<'a>(&'a self, torques: &'a Torques) -> Llg<'a> {
+        Llg {
+            mesh: &self.mesh,
+            params: &self.params,
+            terms: &self.terms,
+            torques,
+            precession: true,
+            sublattice: self.sublattice.as_ref(),
+            llb: self.llb.as_ref(),
+            noise: None,
+        }
+    }
+
+    /// Energy of every active term (J), with the inter-sublattice exchange
+    /// last if there is a second sublattice
+    pub fn energies(&self) -> Vec<(&'static str, f64)> {
+        let (m, t, mesh, p) = (&self.m, self.t, &self.mesh, &self.params);
+        match &self.sublattice {
+            Some(s) => s.energies(&self.terms, m, t, mesh, p),
+            None => self.terms.energies(m, t, mesh, p),
+        }
+    }
+
+    /// Total energy (J)
+    pub fn energy(&self) -> f64 {
+        self.energies().iter().map(|(_, e)| e).sum()
+    }
+
+    /// Magnetization averaged over the sample, that of the first sublattice
+    /// if there are two
+    pub fn average(&self) -> Vector3<f64> {
+        self.sublattice_average(0)
+    }
+
+    /// Magnetization of sublattice `k` (0 or 1) averaged over the sample
+    pub fn sublattice_average(&self, k: usize) -> Vector3<f64> {
+        let n = self.mesh.len();
+        let mut m = VectorField::default();
+        let m = if self.sublattice.is_none() {
+            &self.m
+        } else {
+            m.copy_range(&self.m, k * n..(k + 1) * n);
+            &m
+        };
+        match &self.params.geometry {
+            Some(g) => g.mean(m),
+            None => m.mean(),
+        }
+    }
+}
+
+/// Take one accepted step of `stepper` from `m` at time `t`, starting from
+/// step size `dt`, at most `solver.dt_max`, and leaving there the size for
+/// the next step. The new
+/// state replaces `m`, with `next` as scratch; returns the step actually
+/// taken.
+fn advance(
+    stepper: &mut dyn Stepper,
+    solver: &SolverConfig,
+    llg: &Llg,
+    m: &mut VectorField,
+    next: &mut VectorField,
+    t: f64,
+    dt: &mut f64,
+) -> f64 {
+    if stepper.order().is_some() {
+        *dt = dt.min(solver.dt_max.unwrap_or(f64::INFINITY));
+    }
+    loop {
+        let h = *dt;
+        let start = profile::start();
+        let err = stepper.advance(llg, m, t, h, next);
+        profile::record(
+            Section::Solver,
+            format_args!("{} step", stepper.name()),
+            start,
+        );
+        let accept = match stepper.order() {
+            Some(order) => {
+                let accept = err <= solver.tolerance || h <= solver.dt_min;
+                *dt = adapt(solver, h, err, order);
+                if !accept {
+                    tracing::debug!(t, dt = h, err, next = *dt, "step rejected");
+                } else if *dt != h {
+                    tracing::trace!(t, dt = h, err, next = *dt, "step size changed");
+                }
+                accept
+            }
+            None => true,
+        };
+        if accept {
+            std::mem::swap(m, next);
+            return h;
+        }
+    }
+}
+
+/// Next step size after a step of `dt` with error `err` from a scheme whose
+/// error estimate is of order `order`
+fn adapt(solver: &SolverConfig, dt: f64, err: f64, order: u32) -> f64 {
+    let factor = if err.is_nan() {
+        0.2
+    } else {
+        (0.9 * (solver.tolerance / err).powf(1.0 / order as f64)).clamp(0.2, 5.0)
+    };
+    let dt_max = solver.dt_max.unwrap_or(f64::INFINITY);
+    (dt * factor).clamp(solver.dt_min, dt_max)
+}
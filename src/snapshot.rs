@@ -0,0 +1,134 @@
+//! Single-state files written alongside the store, for tools that do not
+//! read Zarr: OVF for OOMMF and mumax3 tooling, VTK for ParaView, and PNG
+//! images for a quick look.
+
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use crate::{Mesh, Result, VectorField, ovf::OvfData, vtk::VtkData};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Format {
+    /// OOMMF OVF 2.0
+    #[default]
+    Ovf,
+    /// legacy VTK structured points
+    Vtk,
+    /// VTK XML image data
+    Vti,
+    /// colour image, see [`crate::render`]; the encoding does not apply
+    Png,
+}
+
+/// Encoding of the data
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Encoding {
+    /// binary single precision
+    #[default]
+    Binary4,
+    /// binary double precision
+    Binary8,
+    Text,
+}
+
+impl From<Encoding> for OvfData {
+    fn from(e: Encoding) -> Self {
+        match e {
+            Encoding::Binary4 => OvfData::Binary4,
+            Encoding::Binary8 => OvfData::Binary8,
+            Encoding::Text => OvfData::Text,
+        }
+    }
+}
+
+impl From<Encoding> for VtkData {
+    fn from(e: Encoding) -> Self {
+        match e {
+            Encoding::Binary4 => VtkData::Float32,
+            Encoding::Binary8 => VtkData::Float64,
+            Encoding::Text => VtkData::Ascii,
+        }
+    }
+}
+
+/// Directory for the files of the store at `store`: its path without
+/// extension, or with `.snapshots` if it has none. Files of object stores go
+/// to the working directory.
+pub fn default_dir(store: &Path) -> PathBuf {
+    if crate::output::is_remote(store) {
+        let name = store.file_name().map(Path::new).unwrap_or(Path::new("m"));
+        return default_dir(name);
+    }
+    match store.extension() {
+        Some(_) => store.with_extension(""),
+        None => store.with_extension("snapshots"),
+    }
+}
+
+/// Write `m` on `mesh` as file number `index` in `dir`, e.g. `m000042.ovf`,
+/// and return its path. A second sublattice, the moments of `m` past the
+/// mesh, goes to a file of its own, e.g. `m2_000042.ovf`, returned second.
+pub fn write(
+    dir: &Path,
+    index: u64,
+    mesh: &Mesh,
+    m: &VectorField,
+    t: Option<f64>,
+    format: Format,
+    data: Encoding,
+) -> Result<Vec<PathBuf>> {
+    if m.len() == mesh.len() {
+        return Ok(vec![write_file(
+            &dir.join(format!("m{index:06}")),
+            mesh,
+            m,
+            t,
+            format,
+            data,
+        )?]);
+    }
+    let n = mesh.len();
+    let mut files = Vec::new();
+    let mut half = VectorField::default();
+    for (k, name) in [(0, format!("m{index:06}")), (1, format!("m2_{index:06}"))] {
+        half.copy_range(m, k * n..(k + 1) * n);
+        files.push(write_file(&dir.join(name), mesh, &half, t, format, data)?);
+    }
+    Ok(files)
+}
+
+/// Write `m` to `file` with the extension of `format` and return its path
+fn write_file(
+    file: &Path,
+    mesh: &Mesh,
+    m: &VectorField,
+    t: Option<f64>,
+    format: Format,
+    data: Encoding,
+) -> Result<PathBuf> {
+    match format {
+        Format::Ovf => {
+            let file = file.with_extension("ovf");
+            crate::ovf::write(&file, mesh, m, t, data.into())?;
+            Ok(file)
+        }
+        Format::Vtk => {
+            let file = file.with_extension("vtk");
+            crate::vtk::write_legacy(&file, mesh, m, t, data.into())?;
+            Ok(file)
+        }
+        Format::Vti => {
+            let file = file.with_extension("vti");
+            crate::vtk::write_xml(&file, mesh, m, t, data.into())?;
+            Ok(file)
+        }
+        Format::Png => {
+            let file = file.with_extension("png");
+            crate::render::write_png(&file, mesh, m)?;
+            Ok(file)
+        }
+    }
+}
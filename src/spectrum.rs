@@ -0,0 +1,188 @@
+//! Absorption spectra of ringdown FMR experiments, `nez analyze fmr`: after
+//! a short pulse the magnetization rings down at the frequencies of its
+//! modes, and the power of its Fourier transform over time gives them.
+//!
+//! The spectrum of ⟨m⟩ comes from the table when the store holds one, as
+//! its rows are usually far more frequent than the snapshots, else from the
+//! snapshots; the spatially resolved one, of every cell, from the
+//! snapshots. Every series has its mean removed and is windowed before the
+//! transform, and samples unevenly spaced in time, as written by adaptive
+//! solvers, are interpolated onto an even grid first.
+
+use clap::ValueEnum;
+use rayon::prelude::*;
+use rustfft::{FftPlanner, num_complex::Complex64};
+use std::f64::consts::PI;
+
+use crate::{Result, ZarrOutput};
+
+/// Window applied to the series before the transform
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum Window {
+    /// sharp peaks, little leakage between them
+    #[default]
+    Hann,
+    Hamming,
+    /// no window: the narrowest peaks, but leakage from the cut ends
+    #[value(name = "none")]
+    Rectangular,
+}
+
+impl Window {
+    /// Weights of `n` samples
+    fn weights(self, n: usize) -> Vec<f64> {
+        let phase = |k: usize| 2.0 * PI * k as f64 / (n - 1).max(1) as f64;
+        (0..n)
+            .map(|k| match self {
+                Window::Hann => 0.5 - 0.5 * phase(k).cos(),
+                Window::Hamming => 0.54 - 0.46 * phase(k).cos(),
+                Window::Rectangular => 1.0,
+            })
+            .collect()
+    }
+}
+
+/// Times and, at each, the values of every series
+pub type Samples = (Vec<f64>, Vec<Vec<f64>>);
+
+/// Power spectra of several series sampled together
+pub struct Spectrum {
+    /// frequencies (Hz), from 0 to the Nyquist frequency
+    pub f: Vec<f64>,
+    /// power of every series at each frequency in turn, normalized by the
+    /// window so that a sine of amplitude a peaks near a²/4
+    pub power: Vec<f64>,
+    /// number of series
+    pub series: usize,
+}
+
+impl Spectrum {
+    /// Spectra of the series of `samples`, one vector of all series per
+    /// time in `times`
+    pub fn compute(times: &[f64], samples: &[Vec<f64>], window: Window) -> Result<Self> {
+        let (dt, samples) = resample(times, samples)?;
+        let (nt, series) = (samples.len(), samples[0].len());
+        let weights = window.weights(nt);
+        let norm = weights.iter().sum::<f64>().powi(2);
+        let fft = FftPlanner::new().plan_fft_forward(nt);
+        let nf = nt / 2 + 1;
+        let columns: Vec<Vec<f64>> = (0..series)
+            .into_par_iter()
+            .map(|s| {
+                let mean = samples.iter().map(|v| v[s]).sum::<f64>() / nt as f64;
+                let mut buf: Vec<Complex64> = samples
+                    .iter()
+                    .zip(&weights)
+                    .map(|(v, w)| Complex64::new(w * (v[s] - mean), 0.0))
+                    .collect();
+                fft.process(&mut buf);
+                buf[..nf].iter().map(|c| c.norm_sqr() / norm).collect()
+            })
+            .collect();
+        let power = (0..nf)
+            .flat_map(|j| columns.iter().map(move |c| c[j]))
+            .collect();
+        let f = (0..nf).map(|j| j as f64 / (nt as f64 * dt)).collect();
+        Ok(Self { f, power, series })
+    }
+
+    /// Power of series `s` at frequency index `j`
+    pub fn at(&self, j: usize, s: usize) -> f64 {
+        self.power[j * self.series + s]
+    }
+}
+
+/// Times and ⟨m⟩ of the rows of the table of `out`, if it has `t`, `mx`,
+/// `my` and `mz` columns
+pub fn table_average(out: &ZarrOutput) -> Result<Option<Samples>> {
+    let Some(columns) = out.table_columns()? else {
+        return Ok(None);
+    };
+    let find = |name: &str| columns.iter().position(|c| c == name);
+    let (Some(t), Some(x), Some(y), Some(z)) = (find("t"), find("mx"), find("my"), find("mz"))
+    else {
+        return Ok(None);
+    };
+    let [t, x, y, z] = [t, x, y, z].map(|c| out.table_column(c));
+    let (mut times, mut samples) = (Vec::new(), Vec::new());
+    for (k, t) in t?.into_iter().enumerate() {
+        // rows left unwritten read as NaN
+        if t.is_finite() && times.last().is_none_or(|&last| t > last) {
+            times.push(t);
+            samples.push(k);
+        }
+    }
+    let (x, y, z) = (x?, y?, z?);
+    let samples = samples
+        .into_iter()
+        .map(|k| vec![x[k], y[k], z[k]])
+        .collect();
+    Ok(Some((times, samples)))
+}
+
+/// Times and `m` of every snapshot of `out`, the components of every cell
+/// in the order of the mesh, or their averages over the sample only
+pub fn snapshots(out: &ZarrOutput, average: bool) -> Result<Samples> {
+    let frames = out.frames()?;
+    let (mut times, mut samples) = (Vec::new(), Vec::new());
+    for frame in 0..frames {
+        let t = out
+            .time(frame)?
+            .ok_or("the store records no times of its snapshots")?;
+        let m = out.read(frame)?;
+        let cells = out.shape()[1..4].iter().product::<u64>() as usize;
+        let sample = if average {
+            let mut sum = [0.0; 3];
+            for i in 0..cells {
+                let v = m.get(i);
+                (0..3).for_each(|c| sum[c] += v[c]);
+            }
+            // cells outside the sample hold zeros and leave the sum alone
+            let moments = (0..cells).filter(|&i| m.get(i).norm() > 0.0).count();
+            sum.map(|s| s / moments.max(1) as f64).to_vec()
+        } else {
+            (0..cells)
+                .flat_map(|i| <[f64; 3]>::from(m.get(i)))
+                .collect()
+        };
+        times.push(t);
+        samples.push(sample);
+    }
+    Ok((times, samples))
+}
+
+/// The samples at `times`, one vector per time, interpolated linearly onto
+/// as many evenly spaced times over the same span; returns the spacing
+pub fn resample(times: &[f64], samples: &[Vec<f64>]) -> Result<(f64, Vec<Vec<f64>>)> {
+    let nt = times.len();
+    if nt < 4 {
+        return Err(format!("{nt} samples are too few for a spectrum").into());
+    }
+    let dt = (times[nt - 1] - times[0]) / (nt - 1) as f64;
+    if dt.is_nan() || dt <= 0.0 {
+        return Err("the samples do not advance in time".into());
+    }
+    let uneven = times
+        .windows(2)
+        .any(|w| ((w[1] - w[0]) - dt).abs() > 1e-3 * dt);
+    if !uneven {
+        return Ok((dt, samples.to_vec()));
+    }
+    tracing::info!("samples unevenly spaced in time, interpolated every {dt:e} s");
+    let mut j = 0;
+    let even = (0..nt)
+        .map(|s| {
+            let t = times[0] + s as f64 * dt;
+            while j + 2 < nt && times[j + 1] < t {
+                j += 1;
+            }
+            let w = ((t - times[j]) / (times[j + 1] - times[j])).clamp(0.0, 1.0);
+            samples[j]
+                .iter()
+                .zip(&samples[j + 1])
+                .map(|(a, b)| (1.0 - w) * a + w * b)
+                .collect()
+        })
+        .collect();
+    Ok((dt, even))
+}
@@ -0,0 +1,159 @@
+//! Spin pumping into a normal metal beside the magnet, and the voltage the
+//! inverse spin Hall effect (ISHE) turns it into, as observables:
+//!
+//! ```toml
+//! [spin_pumping]
+//! side = "bottom"            # interface: bottom (z = 0) or top layer of cells
+//! g_mix = 1e19               # real part of the spin-mixing conductance (m⁻²)
+//! theta_sh = 0.1             # spin Hall angle of the normal metal
+//! resistivity = 2e-7         # of the normal metal (Ω m)
+//! thickness = 5e-9           # of the normal metal (m)
+//! diffusion_length = 1.5e-9  # spin diffusion length of the normal metal (m)
+//! direction = "x"            # voltage between the two ends of the mesh along x
+//! ```
+//!
+//! The magnetization precessing in the cells of the interface layer pumps
+//! the spin current density `j_s = (ħ/4π) g_mix ⟨m × dm/dt⟩` (J m⁻²) across
+//! it, flowing along the normal `n` into the metal. There the ISHE drives
+//! the charge current density `j_c = θ_SH (2e/ħ) (λ/t) tanh(t/2λ) n × j_s`,
+//! averaged over the thickness `t` of the metal as the spin current decays
+//! over `λ`, and the open strip develops `V = ρ L j_c·u` between its ends a
+//! length `L` apart along `u`, neglecting the shunt through the magnet.
+//!
+//! Both are instantaneous, joining the table as `spin_current_x`, `_y`, `_z`
+//! and `ishe_voltage`; the DC voltage of a resonance experiment is their time
+//! average over whole periods. With a second sublattice the first pumps.
+
+use nalgebra::Vector3;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    Mesh, Params, Result, VectorField,
+    torque::{HBAR, QE},
+    wall::Direction,
+};
+
+/// Layer of cells facing the normal metal
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Side {
+    /// z layer 0, the metal below, as the heavy metal of `[sot]`
+    #[default]
+    Bottom,
+    /// the last z layer, the metal above
+    Top,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SpinPumpingConfig {
+    pub side: Side,
+    /// real part of the spin-mixing conductance per area (m⁻²)
+    pub g_mix: f64,
+    /// spin Hall angle θ_SH of the normal metal
+    pub theta_sh: f64,
+    /// resistivity ρ of the normal metal (Ω m)
+    pub resistivity: f64,
+    /// thickness t of the normal metal (m)
+    pub thickness: f64,
+    /// spin diffusion length λ of the normal metal (m)
+    pub diffusion_length: f64,
+    /// in-plane axis between the two voltage contacts
+    pub direction: Direction,
+}
+
+impl Default for SpinPumpingConfig {
+    /// a platinum layer below the magnet
+    fn default() -> Self {
+        Self {
+            side: Side::Bottom,
+            g_mix: 1e19,
+            theta_sh: 0.1,
+            resistivity: 2e-7,
+            thickness: 5e-9,
+            diffusion_length: 1.5e-9,
+            direction: Direction::X,
+        }
+    }
+}
+
+impl SpinPumpingConfig {
+    /// Check the constants and that the voltage is taken in the plane
+    pub fn validate(&self) -> Result<()> {
+        for (name, v) in [
+            ("g_mix", self.g_mix),
+            ("resistivity", self.resistivity),
+            ("thickness", self.thickness),
+            ("diffusion_length", self.diffusion_length),
+        ] {
+            if !(v.is_finite() && v > 0.0) {
+                return Err(format!("`spin_pumping.{name}` must be positive, got {v}").into());
+            }
+        }
+        if !self.theta_sh.is_finite() {
+            return Err("`spin_pumping.theta_sh` must be finite".into());
+        }
+        if self.direction == Direction::Z {
+            return Err("`spin_pumping.direction` must be \"x\" or \"y\", in the plane".into());
+        }
+        Ok(())
+    }
+}
+
+/// The interface of `[spin_pumping]` on a mesh
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpinPumping {
+    config: SpinPumpingConfig,
+    /// cells of the interface layer that carry a moment
+    cells: Vec<usize>,
+    /// normal pointing into the metal
+    normal: Vector3<f64>,
+    /// distance between the contacts (m)
+    length: f64,
+}
+
+impl SpinPumping {
+    /// The cells of the interface layer of `mesh` inside the sample of `p`
+    pub fn new(config: &SpinPumpingConfig, mesh: &Mesh, p: &Params) -> Result<Self> {
+        let (z, normal) = match config.side {
+            Side::Bottom => (0, -Vector3::z()),
+            Side::Top => (mesh.nz - 1, Vector3::z()),
+        };
+        let layer = mesh.nx * mesh.ny;
+        let cells: Vec<usize> = (z * layer..(z + 1) * layer)
+            .filter(|&i| p.mu0_ms_at(i) > 0.0)
+            .collect();
+        if cells.is_empty() {
+            return Err("`spin_pumping`: no cell of the sample at the interface".into());
+        }
+        let a = config.direction as usize;
+        Ok(Self {
+            config: config.clone(),
+            cells,
+            normal,
+            length: mesh.size()[a] as f64 * mesh.cell()[a],
+        })
+    }
+
+    /// Spin current density pumped into the metal (J m⁻²), from the
+    /// magnetization `m` turning at `dmdt` (s⁻¹)
+    pub fn spin_current(&self, m: &VectorField, dmdt: &VectorField) -> Vector3<f64> {
+        let sum: Vector3<f64> = self
+            .cells
+            .iter()
+            .map(|&i| m.get(i).cross(&dmdt.get(i)))
+            .sum();
+        let c = &self.config;
+        HBAR / (4.0 * std::f64::consts::PI) * c.g_mix * sum / self.cells.len() as f64
+    }
+
+    /// ISHE voltage between the contacts (V) of the spin current density
+    /// `js` (J m⁻²)
+    pub fn voltage(&self, js: &Vector3<f64>) -> f64 {
+        let c = &self.config;
+        let decay =
+            c.diffusion_length / c.thickness * (c.thickness / (2.0 * c.diffusion_length)).tanh();
+        let jc = c.theta_sh * (2.0 * QE / HBAR) * decay * self.normal.cross(js);
+        c.resistivity * self.length * jc[c.direction as usize]
+    }
+}
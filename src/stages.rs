@@ -0,0 +1,101 @@
+//! Multi-stage runs: an ordered list of stages carried out one after the
+//! other on the same state and store, e.g. relax, then a field pulse, then
+//! free ringing at constant field:
+//!
+//! ```toml
+//! [[stages]]
+//! kind = "relax"
+//!
+//! [[stages]]
+//! duration = 5e-9
+//! [[stages.zeeman]]
+//! kind = "sinc"
+//! amplitude = [0.0, 0.01, 0.0]
+//! cutoff = 20e9
+//! t0 = 1e-9
+//!
+//! [[stages]]
+//! duration = 20e-9
+//! h_ext = [0.0, 0.0, 0.2]
+//! every = 100
+//! ```
+//!
+//! Every stage starts from the main sections and replaces only the keys it
+//! sets, so a field applied in one stage is gone in the next unless repeated.
+
+use nalgebra::Vector3;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    Config, Result, Simulation,
+    config::SolverConfig,
+    field::{Zeeman, ZeemanSource},
+};
+
+/// What a stage does with the state
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StageKind {
+    /// time integration for `duration` or `steps`
+    #[default]
+    Run,
+    /// damping-only dynamics with the `[relax]` settings, at frozen time
+    Relax,
+    /// conjugate gradients with the `[minimize]` settings, at frozen time
+    Minimize,
+}
+
+impl StageKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Run => "run",
+            Self::Relax => "relax",
+            Self::Minimize => "minimize",
+        }
+    }
+}
+
+/// One entry of `[[stages]]`
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Stage {
+    pub kind: StageKind,
+    /// simulated time to run for (s)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub duration: Option<f64>,
+    /// number of steps to run for, instead of `duration`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub steps: Option<u64>,
+    /// static applied field (T), replacing `params.h_ext`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub h_ext: Option<Vector3<f64>>,
+    /// applied-field sources replacing `[[zeeman]]`; their waveforms see the
+    /// time of the whole run, not of the stage
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub zeeman: Option<Vec<ZeemanSource>>,
+    /// replacing `[solver]`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub solver: Option<SolverConfig>,
+    /// steps between frames, replacing `output.every`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub every: Option<u64>,
+}
+
+impl Stage {
+    /// Set up `sim` for this stage: the field, solver and output cadence of
+    /// `config`, with those this stage sets instead
+    pub fn apply(&self, sim: &mut Simulation, config: &Config) -> Result<()> {
+        sim.params.h_ext = self.h_ext.unwrap_or(config.params.h_ext);
+        let sources = self.zeeman.as_deref().unwrap_or(&config.zeeman);
+        sim.terms.insert(Zeeman::new(sources, &config.mesh)?);
+        let solver = self.solver.as_ref().unwrap_or(&config.solver);
+        if *solver != sim.solver {
+            sim.set_solver(solver.clone());
+            if sim.stepper.order().is_none() {
+                sim.dt = config.params.dt;
+            }
+        }
+        sim.save_every = self.every.unwrap_or(config.output.every);
+        Ok(())
+    }
+}
@@ -0,0 +1,729 @@
+//! Time integration schemes.
+
+use nalgebra::Vector3;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    VectorField,
+    llg::Llg,
+    profile::{self, Section},
+    vector_field::{Real, wide},
+};
+
+/// Change of |m| by the renormalisation of one step above which the step is
+/// reported as too large
+const DRIFT: f64 = 1e-3;
+
+/// A time integration scheme for the LLG equation
+pub trait Stepper: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    /// Order of the local error estimate, or `None` for fixed-step schemes
+    fn order(&self) -> Option<u32>;
+
+    /// Attempt a step of size `dt` from `m` at time `t`. Writes the
+    /// normalised candidate state over `next` (resized to `m` if needed) and
+    /// returns its estimated error in |dm| (0 for fixed-step schemes); the
+    /// caller decides whether to accept it.
+    fn advance(
+        &mut self,
+        llg: &Llg,
+        m: &VectorField,
+        t: f64,
+        dt: f64,
+        next: &mut VectorField,
+    ) -> f64;
+
+    /// Take `n` steps of fixed size `dt` from `m` at time `t`, leaving the
+    /// final state in `m`; `scratch` is overwritten. Schemes running on
+    /// another device override this to keep the state there between steps.
+    fn advance_fixed(
+        &mut self,
+        llg: &Llg,
+        m: &mut VectorField,
+        scratch: &mut VectorField,
+        t: f64,
+        dt: f64,
+        n: u64,
+    ) {
+        let mut t = t;
+        for _ in 0..n {
+            let start = profile::start();
+            self.advance(llg, m, t, dt, scratch);
+            profile::record(Section::Solver, format_args!("{} step", self.name()), start);
+            std::mem::swap(m, scratch);
+            t += dt;
+        }
+    }
+}
+
+/// Time integration scheme, selected by `solver.method`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Method {
+    /// Euler–Heun (2nd order), fixed step
+    Heun,
+    /// Bogacki–Shampine 3(2), adaptive step
+    Rk23,
+    /// classic 4th-order Runge–Kutta, fixed step
+    #[default]
+    Rk4,
+    /// Dormand–Prince 5(4), adaptive step
+    Rk45,
+    /// Cayley-transform midpoint (2nd order), fixed step, keeps |m| = 1
+    /// without renormalisation
+    Cayley,
+    /// low-storage 4th-order Runge–Kutta, fixed step, two buffers instead
+    /// of five
+    Lsrk4,
+    /// implicit midpoint (2nd order), fixed step, stable at steps far
+    /// beyond those of the explicit schemes
+    Implicit,
+}
+
+/// Where the time integration runs, selected by `solver.device`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Device {
+    /// rayon threads, double precision
+    #[default]
+    Cpu,
+    /// wgpu compute shaders, single precision (cargo feature `gpu`)
+    Gpu,
+}
+
+impl Method {
+    pub fn stepper(self) -> Box<dyn Stepper> {
+        match self {
+            Self::Heun => Box::new(ExplicitRk::new(&HEUN)),
+            Self::Rk23 => Box::new(ExplicitRk::new(&RK23)),
+            Self::Rk4 => Box::new(ExplicitRk::new(&RK4)),
+            Self::Rk45 => Box::new(ExplicitRk::new(&RK45)),
+            Self::Cayley => Box::new(Cayley::default()),
+            Self::Lsrk4 => Box::new(LowStorageRk::default()),
+            Self::Implicit => Box::new(ImplicitMidpoint::default()),
+        }
+    }
+}
+
+/// Butcher tableau of an explicit Runge–Kutta scheme
+pub struct Tableau {
+    pub name: &'static str,
+    /// nodes
+    pub c: &'static [f64],
+    /// coupling coefficients, row `s` holding the weights of stages `0..s`
+    pub a: &'static [&'static [f64]],
+    /// solution weights
+    pub b: &'static [f64],
+    /// difference to the embedded lower-order weights, empty without one
+    pub e: &'static [f64],
+    /// order of the error estimate
+    pub order: u32,
+}
+
+pub const HEUN: Tableau = Tableau {
+    name: "heun",
+    c: &[0.0, 1.0],
+    a: &[&[], &[1.0]],
+    b: &[0.5, 0.5],
+    e: &[],
+    order: 2,
+};
+
+pub const RK23: Tableau = Tableau {
+    name: "rk23",
+    c: &[0.0, 0.5, 0.75, 1.0],
+    a: &[
+        &[],
+        &[0.5],
+        &[0.0, 0.75],
+        &[2.0 / 9.0, 1.0 / 3.0, 4.0 / 9.0],
+    ],
+    b: &[2.0 / 9.0, 1.0 / 3.0, 4.0 / 9.0, 0.0],
+    e: &[-5.0 / 72.0, 1.0 / 12.0, 1.0 / 9.0, -1.0 / 8.0],
+    order: 3,
+};
+
+pub const RK4: Tableau = Tableau {
+    name: "rk4",
+    c: &[0.0, 0.5, 0.5, 1.0],
+    a: &[&[], &[0.5], &[0.0, 0.5], &[0.0, 0.0, 1.0]],
+    b: &[1.0 / 6.0, 1.0 / 3.0, 1.0 / 3.0, 1.0 / 6.0],
+    e: &[],
+    order: 4,
+};
+
+pub const RK45: Tableau = Tableau {
+    name: "rk45",
+    c: &[0.0, 1.0 / 5.0, 3.0 / 10.0, 4.0 / 5.0, 8.0 / 9.0, 1.0, 1.0],
+    a: &[
+        &[],
+        &[1.0 / 5.0],
+        &[3.0 / 40.0, 9.0 / 40.0],
+        &[44.0 / 45.0, -56.0 / 15.0, 32.0 / 9.0],
+        &[
+            19372.0 / 6561.0,
+            -25360.0 / 2187.0,
+            64448.0 / 6561.0,
+            -212.0 / 729.0,
+        ],
+        &[
+            9017.0 / 3168.0,
+            -355.0 / 33.0,
+            46732.0 / 5247.0,
+            49.0 / 176.0,
+            -5103.0 / 18656.0,
+        ],
+        &[
+            35.0 / 384.0,
+            0.0,
+            500.0 / 1113.0,
+            125.0 / 192.0,
+            -2187.0 / 6784.0,
+            11.0 / 84.0,
+        ],
+    ],
+    b: &[
+        35.0 / 384.0,
+        0.0,
+        500.0 / 1113.0,
+        125.0 / 192.0,
+        -2187.0 / 6784.0,
+        11.0 / 84.0,
+        0.0,
+    ],
+    e: &[
+        71.0 / 57600.0,
+        0.0,
+        -71.0 / 16695.0,
+        71.0 / 1920.0,
+        -17253.0 / 339200.0,
+        22.0 / 525.0,
+        -1.0 / 40.0,
+    ],
+    order: 5,
+};
+
+/// Explicit Runge–Kutta scheme driven by a [`Tableau`]. Spins are
+/// renormalised after every step.
+///
+/// The stage derivatives and the state they are evaluated at live in
+/// buffers kept between steps, so a step allocates nothing once the first
+/// one has sized them.
+pub struct ExplicitRk {
+    tableau: &'static Tableau,
+    /// dm/dt of every stage
+    k: Vec<VectorField>,
+    /// state at which the next stage is evaluated
+    stage: VectorField,
+    /// whether a large renormalisation was reported
+    warned: bool,
+}
+
+impl ExplicitRk {
+    pub fn new(tableau: &'static Tableau) -> Self {
+        Self {
+            tableau,
+            k: vec![VectorField::default(); tableau.c.len()],
+            stage: VectorField::default(),
+            warned: false,
+        }
+    }
+}
+
+/// Write `base + dt Σ w_j k_j` over `out` for every cell, with a zero base
+/// for `None`
+fn combine(
+    base: Option<&VectorField>,
+    k: &[VectorField],
+    w: &[f64],
+    dt: f64,
+    out: &mut VectorField,
+) {
+    let terms = || w.iter().zip(k).filter(|(w, _)| **w != 0.0);
+    out.par_chunks_mut().for_each(|(start, out)| {
+        for (c, out) in out.into_iter().enumerate() {
+            let range = start..start + out.len();
+            match base {
+                Some(base) => out.copy_from_slice(&base.components()[c][range.clone()]),
+                None => out.fill(0.0),
+            }
+            for (w, k) in terms() {
+                let a = (dt * w) as Real;
+                for (o, k) in out.iter_mut().zip(&k.components()[c][range.clone()]) {
+                    *o += a * k;
+                }
+            }
+        }
+    });
+}
+
+impl Stepper for ExplicitRk {
+    fn name(&self) -> &'static str {
+        self.tableau.name
+    }
+
+    fn order(&self) -> Option<u32> {
+        let tb = self.tableau;
+        (!tb.e.is_empty()).then_some(tb.order)
+    }
+
+    fn advance(
+        &mut self,
+        llg: &Llg,
+        m: &VectorField,
+        t: f64,
+        dt: f64,
+        next: &mut VectorField,
+    ) -> f64 {
+        let tb = self.tableau;
+        self.stage.resize(m.len());
+        next.resize(m.len());
+        for (s, (c, a)) in tb.c.iter().zip(tb.a).enumerate() {
+            let start = profile::start();
+            let (done, rest) = self.k.split_at_mut(s);
+            if a.is_empty() {
+                llg.rhs_into(m, t + c * dt, &mut rest[0]);
+            } else {
+                combine(Some(m), done, a, dt, &mut self.stage);
+                llg.rhs_into(&self.stage, t + c * dt, &mut rest[0]);
+            }
+            profile::record(
+                Section::Solver,
+                format_args!("{} stage {}", tb.name, s + 1),
+                start,
+            );
+        }
+        let err = if tb.e.is_empty() {
+            0.0
+        } else {
+            combine(None, &self.k, tb.e, dt, &mut self.stage);
+            self.stage.max_norm()
+        };
+        combine(Some(m), &self.k, tb.b, dt, next);
+        // the LLB equation sets the length of m itself
+        if llg.llb.is_some() {
+            return err;
+        }
+        renormalise(next, dt, tb.name, &mut self.warned);
+        err
+    }
+}
+
+/// Renormalise every spin of `next`, the state after a step of `dt` by
+/// `scheme`, warning once through `warned` if that moved |m| much
+fn renormalise(next: &mut VectorField, dt: f64, scheme: &str, warned: &mut bool) {
+    let drift = next.normalize();
+    if drift > DRIFT && !*warned {
+        tracing::warn!(
+            drift,
+            dt,
+            "{scheme}: renormalisation changed |m| by more than {DRIFT:e} in one step, the step \
+             may be too large",
+        );
+        *warned = true;
+    }
+}
+
+/// Stage coefficients (A, B, c) of the five-stage fourth-order 2N-storage
+/// scheme of Carpenter & Kennedy (1994), RK4(3)5[2N]
+const LSRK4: [[f64; 3]; 5] = [
+    [0.0, 1432997174477.0 / 9575080441755.0, 0.0],
+    [
+        -567301805773.0 / 1357537059087.0,
+        5161836677717.0 / 13612068292357.0,
+        1432997174477.0 / 9575080441755.0,
+    ],
+    [
+        -2404267990393.0 / 2016746695238.0,
+        1720146321549.0 / 2090206949498.0,
+        2526269341429.0 / 6820363962896.0,
+    ],
+    [
+        -3550918686646.0 / 2091501179385.0,
+        3134564353537.0 / 4481467310338.0,
+        2006345519317.0 / 3224310063776.0,
+    ],
+    [
+        -1275806237668.0 / 842570457699.0,
+        2277821191437.0 / 14882151754819.0,
+        2802321613138.0 / 2924317926251.0,
+    ],
+];
+
+/// Low-storage Runge–Kutta scheme of fourth order. Each stage updates the
+/// state in place,
+///
+/// q ← A_s q + dt f(t + c_s dt, m),  m ← m + B_s q,
+///
+/// so that besides the state itself only q and the derivative f are kept,
+/// against the four stage derivatives and the stage state of `rk4`. It
+/// takes five evaluations of dm/dt per step rather than four, for a
+/// somewhat smaller error. Spins are renormalised after every step.
+#[derive(Default)]
+pub struct LowStorageRk {
+    /// dm/dt of the current stage
+    k: VectorField,
+    /// accumulated increment q
+    q: VectorField,
+    /// whether a large renormalisation was reported
+    warned: bool,
+}
+
+impl Stepper for LowStorageRk {
+    fn name(&self) -> &'static str {
+        "lsrk4"
+    }
+
+    fn order(&self) -> Option<u32> {
+        None
+    }
+
+    fn advance(
+        &mut self,
+        llg: &Llg,
+        m: &VectorField,
+        t: f64,
+        dt: f64,
+        next: &mut VectorField,
+    ) -> f64 {
+        // A₁ = 0: q need not be cleared between steps
+        self.q.resize(m.len());
+        next.clone_from(m);
+        for (s, [a, b, c]) in LSRK4.into_iter().enumerate() {
+            let start = profile::start();
+            llg.rhs_into(next, t + c * dt, &mut self.k);
+            let (a, b, dt) = (a as Real, b as Real, dt as Real);
+            let (k, q) = (&self.k, &mut self.q);
+            q.par_chunks_mut()
+                .zip(next.par_chunks_mut())
+                .for_each(|((start, q), (_, out))| {
+                    for (c, (q, out)) in q.into_iter().zip(out).enumerate() {
+                        let k = &k.components()[c][start..start + q.len()];
+                        for ((q, out), k) in q.iter_mut().zip(out).zip(k) {
+                            *q = a * *q + dt * k;
+                            *out += b * *q;
+                        }
+                    }
+                });
+            profile::record(
+                Section::Solver,
+                format_args!("lsrk4 stage {}", s + 1),
+                start,
+            );
+        }
+        // the LLB equation sets the length of m itself
+        if llg.llb.is_none() {
+            renormalise(next, dt, "lsrk4", &mut self.warned);
+        }
+        0.0
+    }
+}
+
+/// Geometric midpoint scheme on the sphere. Writing the equation of motion
+/// as dm/dt = Ω × m with Ω = m × dm/dt, every spin is rotated by the Cayley
+/// transform of Ω evaluated at the midpoint, an exact rotation, so |m| is
+/// preserved to round-off and no renormalisation bias builds up.
+#[derive(Default)]
+pub struct Cayley {
+    /// dm/dt at the start, then at the midpoint
+    k: VectorField,
+    /// state at the midpoint
+    mid: VectorField,
+}
+
+/// Rotate `m` by cay(dt Ω / 2) = (1 - A)⁻¹(1 + A) with A = [dt Ω / 2]×
+#[inline(always)]
+fn cayley(m: Vector3<f64>, omega: Vector3<f64>, dt: f64) -> Vector3<f64> {
+    let a = 0.5 * dt * omega;
+    let axm = a.cross(&m);
+    m + (2.0 / (1.0 + a.norm_squared())) * (axm + a.cross(&axm))
+}
+
+/// Write over `out` every spin of `m` rotated along the precession vector
+/// `at × dmdt`
+fn rotate(m: &VectorField, at: &VectorField, dmdt: &VectorField, dt: f64, out: &mut VectorField) {
+    out.resize(m.len());
+    out.par_set(|i| cayley(m.get(i), at.get(i).cross(&dmdt.get(i)), dt));
+}
+
+impl Stepper for Cayley {
+    fn name(&self) -> &'static str {
+        "cayley"
+    }
+
+    fn order(&self) -> Option<u32> {
+        None
+    }
+
+    fn advance(
+        &mut self,
+        llg: &Llg,
+        m: &VectorField,
+        t: f64,
+        dt: f64,
+        next: &mut VectorField,
+    ) -> f64 {
+        llg.rhs_into(m, t, &mut self.k);
+        rotate(m, m, &self.k, 0.5 * dt, &mut self.mid);
+        llg.rhs_into(&self.mid, t + 0.5 * dt, &mut self.k);
+        rotate(m, &self.mid, &self.k, dt, next);
+        0.0
+    }
+}
+
+/// Residual of the implicit equation, in |dm| per cell, below which a step
+/// is converged
+const NEWTON_TOL: f64 = 100.0 * wide(Real::EPSILON);
+/// Newton iterations per step before giving up on convergence
+const NEWTON_ITERATIONS: usize = 12;
+/// Reduction of the Newton residual the linear solve aims for
+const FORCING: f64 = 1e-4;
+/// Dimension of the Krylov space before GMRES restarts
+const KRYLOV: usize = 20;
+/// Restarts of GMRES per Newton iteration
+const RESTARTS: usize = 4;
+
+/// Implicit midpoint scheme,
+///
+/// m' = m + dt f(t + dt/2, (m + m') / 2),
+///
+/// A-stable, so that the step is bounded by the accuracy wanted rather than
+/// by the stiffness of strong anisotropy or fine cells, and, like the
+/// Cayley scheme, keeps |m| and, without damping, the energy of a uniform
+/// precession. The equation is solved for m' by Newton's method starting
+/// from an Euler step; each correction solves
+///
+/// (1 - dt/2 ∂f/∂m) δ = -r
+///
+/// by restarted GMRES, with the products of the Jacobian ∂f/∂m taken by
+/// finite differences of dm/dt, so that no matrix is ever formed. Besides
+/// the state and a few work arrays, it holds a Krylov basis of up to
+/// [`KRYLOV`] + 1 vector fields.
+#[derive(Default)]
+pub struct ImplicitMidpoint {
+    /// state at the midpoint
+    mid: VectorField,
+    /// dm/dt at the midpoint
+    f: VectorField,
+    /// residual of the implicit equation
+    res: VectorField,
+    /// Newton correction
+    delta: VectorField,
+    /// orthonormal basis of the Krylov space
+    basis: Vec<VectorField>,
+    /// product of the Jacobian with the last basis vector
+    w: VectorField,
+    /// perturbed midpoint and its dm/dt
+    pert: VectorField,
+    fpert: VectorField,
+    /// whether a step that did not converge was reported
+    stalled: bool,
+    /// whether a large renormalisation was reported
+    warned: bool,
+}
+
+/// Write `Σ a_i x_i` over `out` for every cell
+fn lincomb(out: &mut VectorField, terms: &[(f64, &VectorField)]) {
+    out.par_chunks_mut().for_each(|(start, out)| {
+        for (c, out) in out.into_iter().enumerate() {
+            let range = start..start + out.len();
+            out.fill(0.0);
+            for &(a, x) in terms {
+                let a = a as Real;
+                for (o, x) in out.iter_mut().zip(&x.components()[c][range.clone()]) {
+                    *o += a * x;
+                }
+            }
+        }
+    });
+}
+
+/// The operator 1 - dt/2 ∂f/∂m of a Newton correction, at the midpoint
+/// `mid` where dm/dt is `f`
+struct Jacobian<'a> {
+    llg: &'a Llg<'a>,
+    mid: &'a VectorField,
+    f: &'a VectorField,
+    t: f64,
+    dt: f64,
+    /// finite-difference step along a unit vector
+    eps: f64,
+}
+
+impl Jacobian<'_> {
+    /// Write the operator applied to `v` over `out`
+    fn apply(
+        &self,
+        v: &VectorField,
+        pert: &mut VectorField,
+        fpert: &mut VectorField,
+        out: &mut VectorField,
+    ) {
+        let norm = v.dot(v).sqrt();
+        if norm == 0.0 {
+            return out.par_set(|_| Vector3::zeros());
+        }
+        let eps = self.eps / norm;
+        lincomb(pert, &[(1.0, self.mid), (eps, v)]);
+        self.llg.rhs_into(pert, self.t, fpert);
+        let a = 0.5 * self.dt / eps;
+        lincomb(out, &[(1.0, v), (-a, fpert), (a, self.f)]);
+    }
+}
+
+impl ImplicitMidpoint {
+    /// Solve for the Newton correction `delta` of the residual `res` by
+    /// restarted GMRES, to a relative residual of [`FORCING`] at best
+    fn solve(&mut self, jac: &Jacobian) {
+        let n = self.res.len();
+        self.basis.resize_with(KRYLOV + 1, VectorField::default);
+        let work = [
+            &mut self.delta,
+            &mut self.w,
+            &mut self.pert,
+            &mut self.fpert,
+        ];
+        for v in self.basis.iter_mut().chain(work) {
+            v.resize(n);
+        }
+        self.delta.par_set(|_| Vector3::zeros());
+        let target = FORCING * self.res.dot(&self.res).sqrt();
+        for restart in 0..=RESTARTS {
+            // the residual of the linear system, -res - J δ
+            let (first, _) = self.basis.split_at_mut(1);
+            if restart == 0 {
+                lincomb(&mut first[0], &[(-1.0, &self.res)]);
+            } else {
+                jac.apply(&self.delta, &mut self.pert, &mut self.fpert, &mut self.w);
+                lincomb(&mut first[0], &[(-1.0, &self.res), (-1.0, &self.w)]);
+            }
+            let beta = first[0].dot(&first[0]).sqrt();
+            if beta <= target || beta == 0.0 {
+                return;
+            }
+            first[0].par_update(|_, v| v / beta);
+            // Arnoldi with modified Gram–Schmidt, the Hessenberg matrix
+            // reduced to triangular by Givens rotations as it grows
+            let mut h = [[0.0; KRYLOV]; KRYLOV + 1];
+            let mut rotations = [(0.0, 0.0); KRYLOV];
+            let mut g = [0.0; KRYLOV + 1];
+            g[0] = beta;
+            let mut k = 0;
+            while k < KRYLOV {
+                jac.apply(&self.basis[k], &mut self.pert, &mut self.fpert, &mut self.w);
+                for (i, v) in self.basis[..=k].iter().enumerate() {
+                    h[i][k] = self.w.dot(v);
+                    self.w.axpy(-h[i][k], v);
+                }
+                let norm = self.w.dot(&self.w).sqrt();
+                for (i, &(c, s)) in rotations[..k].iter().enumerate() {
+                    let (a, b) = (h[i][k], h[i + 1][k]);
+                    h[i][k] = c * a + s * b;
+                    h[i + 1][k] = c * b - s * a;
+                }
+                let r = h[k][k].hypot(norm);
+                let (c, s) = if r == 0.0 {
+                    (1.0, 0.0)
+                } else {
+                    (h[k][k] / r, norm / r)
+                };
+                rotations[k] = (c, s);
+                h[k][k] = r;
+                g[k + 1] = -s * g[k];
+                g[k] *= c;
+                k += 1;
+                if g[k].abs() <= target || norm == 0.0 {
+                    break;
+                }
+                let next = &mut self.basis[k];
+                next.clone_from(&self.w);
+                next.par_update(|_, v| v / norm);
+            }
+            // the coefficients of the basis, by back substitution
+            let mut y = [0.0; KRYLOV];
+            for i in (0..k).rev() {
+                let sum: f64 = (i + 1..k).map(|j| h[i][j] * y[j]).sum();
+                y[i] = if h[i][i] == 0.0 {
+                    0.0
+                } else {
+                    (g[i] - sum) / h[i][i]
+                };
+            }
+            for (y, v) in y[..k].iter().zip(&self.basis) {
+                self.delta.axpy(*y, v);
+            }
+            if g[k].abs() <= target {
+                return;
+            }
+        }
+    }
+}
+
+impl Stepper for ImplicitMidpoint {
+    fn name(&self) -> &'static str {
+        "implicit"
+    }
+
+    fn order(&self) -> Option<u32> {
+        None
+    }
+
+    fn advance(
+        &mut self,
+        llg: &Llg,
+        m: &VectorField,
+        t: f64,
+        dt: f64,
+        next: &mut VectorField,
+    ) -> f64 {
+        let n = m.len();
+        for v in [&mut self.mid, &mut self.f, &mut self.res] {
+            v.resize(n);
+        }
+        next.resize(n);
+        // an Euler step to start from
+        llg.rhs_into(m, t, &mut self.f);
+        lincomb(next, &[(1.0, m), (dt, &self.f)]);
+        let tm = t + 0.5 * dt;
+        let mut residual = f64::INFINITY;
+        for _ in 0..NEWTON_ITERATIONS {
+            lincomb(&mut self.mid, &[(0.5, m), (0.5, next)]);
+            llg.rhs_into(&self.mid, tm, &mut self.f);
+            lincomb(&mut self.res, &[(1.0, next), (-1.0, m), (-dt, &self.f)]);
+            residual = self.res.max_norm();
+            if residual <= NEWTON_TOL {
+                break;
+            }
+            let mid = std::mem::take(&mut self.mid);
+            let f = std::mem::take(&mut self.f);
+            let size = mid.dot(&mid).sqrt();
+            let jac = Jacobian {
+                llg,
+                mid: &mid,
+                f: &f,
+                t: tm,
+                dt,
+                eps: wide(Real::EPSILON).sqrt() * (1.0 + size),
+            };
+            self.solve(&jac);
+            (self.mid, self.f) = (mid, f);
+            next.axpy(1.0, &self.delta);
+        }
+        if residual > NEWTON_TOL && !self.stalled {
+            tracing::warn!(
+                residual,
+                dt,
+                "implicit: Newton's method did not converge in {NEWTON_ITERATIONS} iterations, \
+                 the step may be too large"
+            );
+            self.stalled = true;
+        }
+        // the LLB equation sets the length of m itself
+        if llg.llb.is_none() {
+            renormalise(next, dt, "implicit", &mut self.warned);
+        }
+        0.0
+    }
+}
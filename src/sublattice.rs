@@ -0,0 +1,229 @@
+//! Two-sublattice antiferromagnets and ferrimagnets.
+//!
+//! With a `[sublattice]` section every cell holds a second moment m₂ with
+//! its own γ, α, Mₛ and exchange stiffness, coupled to the first through
+//! the homogeneous inter-sublattice exchange of energy density `-J m₁·m₂`.
+//! Both sublattices feel the applied field, anisotropy, DMI and spin torques
+//! of the main sections, and the demagnetizing field of their net
+//! magnetization M₁ + M₂. Sublattices with different Mₛ/γ make a
+//! ferrimagnet, whose net angular momentum vanishes where the two balance.
+//!
+//! The state holds the moments of the first sublattice followed by those of
+//! the second. Regions and maps set the values of the first sublattice only.
+
+use std::sync::Mutex;
+
+use nalgebra::Vector3;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    Mesh, Params, VectorField,
+    field::{Demag, FieldTerms, moment_dot},
+    llg::Llg,
+};
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SublatticeConfig {
+    /// gyromagnetic ratio (rad s⁻¹ T⁻¹)
+    pub gamma: f64,
+    /// Gilbert damping
+    pub alpha: f64,
+    /// μ₀Mₛ (T)
+    pub mu0_ms: f64,
+    /// exchange stiffness within the sublattice (J m⁻¹), that of `[params]`
+    /// when absent
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub a_ex: Option<f64>,
+    /// inter-sublattice exchange J (J m⁻³), negative for antiparallel
+    /// sublattices
+    pub exchange: f64,
+    /// uniform initial direction (normalised on load), opposite to the first
+    /// sublattice when absent
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub m: Option<Vector3<f64>>,
+}
+
+impl Default for SublatticeConfig {
+    fn default() -> Self {
+        let p = Params::default();
+        Self {
+            gamma: p.gamma,
+            alpha: p.alpha,
+            mu0_ms: p.mu0_ms,
+            a_ex: None,
+            exchange: 0.0,
+            m: None,
+        }
+    }
+}
+
+impl SublatticeConfig {
+    /// State of both sublattices, the first one being `first`
+    pub fn state(&self, first: &VectorField) -> VectorField {
+        let mut m = first.clone();
+        match self.m {
+            Some(d) => m.append(&VectorField::uniform(first.len(), d.normalize())),
+            None => m.append(&VectorField::from_fn(first.len(), |i| -first.get(i))),
+        }
+        m
+    }
+}
+
+/// The second sublattice and its coupling to the first
+pub struct Sublattice {
+    pub gamma: f64,
+    pub alpha: f64,
+    pub mu0_ms: f64,
+    pub a_ex: f64,
+    /// inter-sublattice exchange J (J m⁻³)
+    pub exchange: f64,
+    /// both halves of the state and of the field, and the demagnetizing
+    /// field they share
+    scratch: Mutex<[VectorField; 5]>,
+}
+
+impl Sublattice {
+    /// The sublattice of `config`, next to one with parameters `params`
+    pub fn new(config: &SublatticeConfig, params: &Params) -> Self {
+        Self {
+            gamma: config.gamma,
+            alpha: config.alpha,
+            mu0_ms: config.mu0_ms,
+            a_ex: config.a_ex.unwrap_or(params.a_ex),
+            exchange: config.exchange,
+            scratch: Mutex::default(),
+        }
+    }
+
+    /// Parameters of this sublattice: those of the first one, `p`, with
+    /// their own material values
+    pub fn params(&self, p: &Params) -> Params {
+        Params {
+            gamma: self.gamma,
+            alpha: self.alpha,
+            a_ex: self.a_ex,
+            mu0_ms: self.mu0_ms,
+            regions: None,
+            maps: None,
+            ..p.clone()
+        }
+    }
+
+    /// Effective field of both sublattices of `m` at time `t`, written over
+    /// `h` in the same layout
+    pub fn effective_field_into(
+        &self,
+        terms: &FieldTerms,
+        m: &VectorField,
+        t: f64,
+        mesh: &Mesh,
+        p: &Params,
+        h: &mut VectorField,
+    ) {
+        let p2 = self.params(p);
+        let mut scratch = self.scratch.lock().unwrap();
+        self.fields(terms, m, t, mesh, [p, &p2], &mut scratch);
+        let [_, _, h1, h2, _] = &*scratch;
+        h.resize(m.len());
+        h.copy_at(0, h1);
+        h.copy_at(h1.len(), h2);
+    }
+
+    /// dm/dt of both sublattices of `m` at time `t`, written over `dmdt`
+    pub fn rhs_into(&self, llg: &Llg, m: &VectorField, t: f64, dmdt: &mut VectorField) {
+        let (mesh, p) = (llg.mesh, llg.params);
+        let p2 = self.params(p);
+        let mut scratch = self.scratch.lock().unwrap();
+        self.fields(llg.terms, m, t, mesh, [p, &p2], &mut scratch);
+        let [m1, m2, h1, h2, _] = &mut *scratch;
+        llg.field_to_rhs(m1, t, p, h1);
+        llg.field_to_rhs(m2, t, &p2, h2);
+        dmdt.resize(m.len());
+        dmdt.copy_at(0, h1);
+        dmdt.copy_at(h1.len(), h2);
+    }
+
+    /// Energy of every active term at time `t` (J), both sublattices
+    /// together, followed by the inter-sublattice exchange as `sublattice`
+    pub fn energies(
+        &self,
+        terms: &FieldTerms,
+        m: &VectorField,
+        t: f64,
+        mesh: &Mesh,
+        p: &Params,
+    ) -> Vec<(&'static str, f64)> {
+        let p2 = self.params(p);
+        let n = mesh.len();
+        let (mut m1, mut m2) = (VectorField::default(), VectorField::default());
+        m1.copy_range(m, 0..n);
+        m2.copy_range(m, n..2 * n);
+        let mut energies = terms.local_energies(&m1, t, mesh, p);
+        for (e, (_, e2)) in energies
+            .iter_mut()
+            .zip(terms.local_energies(&m2, t, mesh, &p2))
+        {
+            e.1 += e2;
+        }
+        if let Some(demag) = terms.get::<Demag>() {
+            let mut h = VectorField::zeros(n);
+            demag.add_net_field([&m1, &m2], [p, &p2], &mut h);
+            let e = -0.5 * (moment_dot(&m1, &h, mesh, p) + moment_dot(&m2, &h, mesh, &p2));
+            // in the place it takes for a single lattice
+            let at = terms.iter().position(|term| term.is::<Demag>());
+            energies.insert(at.unwrap_or(energies.len()), ("demag", e));
+        }
+        energies.push((
+            "sublattice",
+            -self.exchange * m1.dot(&m2) * mesh.cell_volume(),
+        ));
+        energies
+    }
+
+    /// Split `state` into the halves m₁, m₂ of `scratch` and set the fields
+    /// B₁, B₂ after them, with parameters `p`
+    fn fields(
+        &self,
+        terms: &FieldTerms,
+        state: &VectorField,
+        t: f64,
+        mesh: &Mesh,
+        p: [&Params; 2],
+        scratch: &mut [VectorField; 5],
+    ) {
+        let n = mesh.len();
+        let [m1, m2, h1, h2, demag] = scratch;
+        let mut h = [h1, h2];
+        m1.copy_range(state, 0..n);
+        m2.copy_range(state, n..2 * n);
+        let m = [&*m1, &*m2];
+        for k in 0..2 {
+            h[k].resize(n);
+            terms.local_field_into(m[k], t, mesh, p[k], h[k]);
+        }
+        if let Some(d) = terms.get::<Demag>() {
+            demag.resize(n);
+            demag.par_set(|_| Vector3::zeros());
+            d.add_net_field(m, p, demag);
+            if let Some(g) = &p[0].geometry {
+                g.clear_outside(demag);
+            }
+            for h in &mut h {
+                h.axpy(1.0, demag);
+            }
+        }
+        // B₁ = J m₂ / Mₛ₁ and B₂ = J m₁ / Mₛ₂
+        for k in 0..2 {
+            let (p, other) = (p[k], m[1 - k]);
+            h[k].par_add(|i| {
+                let ms = p.ms_at(i);
+                if ms == 0.0 {
+                    Vector3::zeros()
+                } else {
+                    self.exchange / ms * other.get(i)
+                }
+            });
+        }
+    }
+}
@@ -0,0 +1,293 @@
+//! Parameter sweeps, `nez sweep config.toml`: the config is run once for
+//! every combination of the values listed for a few of its keys.
+//!
+//! ```toml
+//! [sweep]
+//! parallel = 2                # cases run at the same time
+//!
+//! [[sweep.parameters]]
+//! key = "params.alpha"        # dotted path of a key of the config
+//! values = [0.01, 0.02, 0.05]
+//!
+//! [[sweep.parameters]]
+//! key = "zeeman[0].amplitude" # `[k]` picks an entry of an array of tables
+//! values = [[0.01, 0.0, 0.0], [0.02, 0.0, 0.0]]
+//!
+//! [[sweep.parameters]]
+//! key = "dmi.d"
+//! range = [0.0, 3e-3, 1e-3]   # start, stop, step: 0, 1e-3, 2e-3, 3e-3
+//! ```
+//!
+//! Every case is the config with these keys set, the sections they name
+//! created if missing, as if written so in the file, and is run as `nez
+//! run` would. Its store is the group `case_0000`, `case_0001`... of the
+//! store at `output.path`, whose root group lists the parameters and the
+//! values of every case, with the numeric ones as the `index` array of one
+//! row per case and a column per key, or per component of vectors. The
+//! last parameter varies fastest.
+
+use serde::{Deserialize, Serialize};
+use std::{fs, path::Path};
+
+use crate::{Config, Result};
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SweepConfig {
+    /// keys set case by case, with their values
+    pub parameters: Vec<SweepParameter>,
+    /// cases run at the same time, sharing the worker threads
+    pub parallel: usize,
+}
+
+impl Default for SweepConfig {
+    fn default() -> Self {
+        Self {
+            parameters: Vec::new(),
+            parallel: 1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SweepParameter {
+    /// dotted path of the key, e.g. `params.alpha` or `zeeman[0].amplitude`
+    pub key: String,
+    /// values taken in turn, of any TOML type the key accepts
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub values: Vec<toml::Value>,
+    /// start, stop and step of evenly spaced numbers instead, stop included
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub range: Option<[f64; 3]>,
+}
+
+impl SweepParameter {
+    /// The values taken in turn
+    pub fn values(&self) -> Vec<toml::Value> {
+        match self.range {
+            Some([start, stop, step]) => {
+                // a whole number of steps, up to rounding
+                let n = ((stop - start) / step + 1e-9).floor() as usize;
+                (0..=n)
+                    .map(|k| toml::Value::Float(start + k as f64 * step))
+                    .collect()
+            }
+            None => self.values.clone(),
+        }
+    }
+
+    /// Check the key and the values
+    pub fn validate(&self, k: usize) -> Result<()> {
+        let name = format!("sweep.parameters[{k}]");
+        if self.key.is_empty() {
+            return Err(format!("`{name}.key` is empty").into());
+        }
+        match self.range {
+            Some(_) if !self.values.is_empty() => {
+                Err(format!("`{name}` takes either `values` or `range`, not both").into())
+            }
+            Some([start, stop, step]) => {
+                let ok = [start, stop, step].iter().all(|v| v.is_finite())
+                    && step != 0.0
+                    && (stop - start) * step >= 0.0;
+                if ok {
+                    Ok(())
+                } else {
+                    Err(format!(
+                        "`{name}.range` must be finite [start, stop, step] with a step \
+                         towards stop, got [{start}, {stop}, {step}]"
+                    )
+                    .into())
+                }
+            }
+            None if self.values.is_empty() => {
+                Err(format!("`{name}` needs `values` or a `range`").into())
+            }
+            None => Ok(()),
+        }
+    }
+}
+
+/// One run of a sweep
+#[derive(Debug, Clone)]
+pub struct Case {
+    /// name of its group in the store, e.g. `case_0003`
+    pub name: String,
+    /// value of every swept key, in the order of `sweep.parameters`
+    pub values: Vec<toml::Value>,
+    pub config: Config,
+}
+
+impl Case {
+    /// `key = value` for every swept key, as shown to the user
+    pub fn label(&self, sweep: &SweepConfig) -> String {
+        label(sweep, &self.values)
+    }
+}
+
+/// Every case of the sweep of the config file at `path`, whose own config
+/// is `config`: the Cartesian product of the values of its parameters,
+/// the last one varying fastest
+pub fn cases(path: &Path, config: &Config) -> Result<Vec<Case>> {
+    let text =
+        fs::read_to_string(path).map_err(|e| format!("cannot read {}: {e}", path.display()))?;
+    let table: toml::Table = toml::from_str(&text)?;
+    let dir = path.parent().unwrap_or(Path::new("."));
+    let lists: Vec<_> = config
+        .sweep
+        .parameters
+        .iter()
+        .map(SweepParameter::values)
+        .collect();
+    let count: usize = lists.iter().map(Vec::len).product();
+    let root = &config.output.path;
+    (0..count)
+        .map(|k| {
+            // the digits of k in the mixed radix of the list lengths
+            let mut rest = k;
+            let mut values = vec![toml::Value::Boolean(false); lists.len()];
+            for (list, value) in lists.iter().zip(&mut values).rev() {
+                *value = list[rest % list.len()].clone();
+                rest /= list.len();
+            }
+            let mut table = table.clone();
+            for (p, value) in config.sweep.parameters.iter().zip(&values) {
+                set(&mut table, &p.key, value.clone())
+                    .map_err(|e| format!("`sweep`: `{}`: {e}", p.key))?;
+            }
+            let name = format!("case_{k:04}");
+            let mut case = Config::parse_in(&toml::to_string(&table)?, dir).map_err(|e| {
+                let label = label(&config.sweep, &values);
+                format!("`sweep`: {name} ({label}): {e}")
+            })?;
+            place(&mut case, config, root, &name);
+            Ok(Case {
+                name,
+                values,
+                config: case,
+            })
+        })
+        .collect()
+}
+
+/// `key = value` of every parameter of `sweep`
+fn label(sweep: &SweepConfig, values: &[toml::Value]) -> String {
+    let pairs: Vec<_> = sweep
+        .parameters
+        .iter()
+        .zip(values)
+        .map(|(p, v)| format!("{} = {v}", p.key))
+        .collect();
+    pairs.join(", ")
+}
+
+/// Send the output of the case `name` of the sweep of `config`, or of a
+/// realization of its ensemble, to its own
+/// group of the store at `root`, and its snapshot files and CSV table to
+/// their own directory and file
+pub(crate) fn place(case: &mut Config, config: &Config, root: &Path, name: &str) {
+    case.output.path = root.join(name);
+    if let Some(snapshots) = &mut case.output.snapshots {
+        snapshots.dir = Some(config.output.snapshot_dir().join(name));
+    }
+    if let Some(csv) = &mut case.table.csv {
+        let stem = csv.file_stem().unwrap_or_default().to_string_lossy();
+        let file = match csv.extension() {
+            Some(ext) => format!("{stem}_{name}.{}", ext.to_string_lossy()),
+            None => format!("{stem}_{name}"),
+        };
+        *csv = csv.with_file_name(file);
+    }
+}
+
+/// Set the value at the dotted path `key` of `table`, creating the tables
+/// on the way
+fn set(table: &mut toml::Table, key: &str, value: toml::Value) -> Result<()> {
+    let (head, rest) = match key.split_once('.') {
+        Some((head, rest)) => (head, Some(rest)),
+        None => (key, None),
+    };
+    let (name, index) = match head.split_once('[') {
+        Some((name, index)) => {
+            let index = index
+                .strip_suffix(']')
+                .and_then(|i| i.parse::<usize>().ok())
+                .ok_or_else(|| format!("invalid index in `{head}`"))?;
+            (name, Some(index))
+        }
+        None => (head, None),
+    };
+    let entry = match index {
+        None => table
+            .entry(name)
+            .or_insert_with(|| toml::Value::Table(toml::Table::new())),
+        Some(i) => {
+            let array = table
+                .get_mut(name)
+                .and_then(toml::Value::as_array_mut)
+                .ok_or_else(|| format!("no array of tables `{name}`"))?;
+            let len = array.len();
+            array
+                .get_mut(i)
+                .ok_or_else(|| format!("`{name}` has {len} entries, no [{i}]"))?
+        }
+    };
+    match rest {
+        None => {
+            *entry = value;
+            Ok(())
+        }
+        Some(rest) => match entry.as_table_mut() {
+            Some(table) => set(table, rest, value),
+            None => Err(format!("`{head}` is not a table").into()),
+        },
+    }
+}
+
+/// Columns and rows of the `index` array of a sweep: a column per swept
+/// key, or per component of those holding arrays, e.g. `zeeman[0].value[1]`,
+/// a row per case, with NaN for values that are not numbers
+pub fn index(sweep: &SweepConfig, cases: &[Case]) -> (Vec<String>, Vec<f64>) {
+    // components of every key, none for scalars
+    let widths: Vec<_> = (0..sweep.parameters.len())
+        .map(|j| {
+            cases
+                .iter()
+                .filter_map(|c| c.values[j].as_array().map(Vec::len))
+                .max()
+        })
+        .collect();
+    let mut columns = Vec::new();
+    for (p, width) in sweep.parameters.iter().zip(&widths) {
+        match width {
+            Some(n) => columns.extend((0..*n).map(|i| format!("{}[{i}]", p.key))),
+            None => columns.push(p.key.clone()),
+        }
+    }
+    let mut rows = Vec::with_capacity(cases.len() * columns.len());
+    for case in cases {
+        for (value, width) in case.values.iter().zip(&widths) {
+            match width {
+                Some(n) => rows.extend((0..*n).map(|i| {
+                    value
+                        .as_array()
+                        .and_then(|a| a.get(i))
+                        .map_or(f64::NAN, numeric)
+                })),
+                None => rows.push(numeric(value)),
+            }
+        }
+    }
+    (columns, rows)
+}
+
+/// The numeric value of `v`, NaN for other types
+fn numeric(v: &toml::Value) -> f64 {
+    match v {
+        toml::Value::Integer(i) => *i as f64,
+        toml::Value::Float(f) => *f,
+        toml::Value::Boolean(b) => f64::from(u8::from(*b)),
+        _ => f64::NAN,
+    }
+}
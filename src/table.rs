@@ -0,0 +1,112 @@
+//! Scalar observables recorded during a run, one row per step, to the
+//! `table` array of the store and/or a CSV file.
+
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{BufWriter, Write},
+    path::PathBuf,
+};
+
+use crate::{Result, ZarrOutput};
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TableConfig {
+    /// record the `table` array in the Zarr store
+    pub zarr: bool,
+    /// also write the rows to this CSV file
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub csv: Option<PathBuf>,
+    /// steps between rows
+    pub every: u64,
+}
+
+impl Default for TableConfig {
+    fn default() -> Self {
+        Self {
+            zarr: true,
+            csv: None,
+            every: 1,
+        }
+    }
+}
+
+impl TableConfig {
+    pub fn enabled(&self) -> bool {
+        self.zarr || self.csv.is_some()
+    }
+}
+
+/// Destinations of the rows of a run
+pub struct Table {
+    pub columns: Vec<String>,
+    /// steps between rows
+    pub every: u64,
+    zarr: bool,
+    csv: Option<BufWriter<File>>,
+    /// rows written so far
+    pub rows: u64,
+}
+
+impl Table {
+    /// Continue a table after its first `rows` rows, or start a new one when
+    /// `rows` is 0. Later rows left in the CSV file by an interrupted run are
+    /// dropped, and rows missing from the store are left to be overwritten.
+    pub fn open(
+        config: &TableConfig,
+        columns: Vec<String>,
+        rows: u64,
+        output: Option<&mut ZarrOutput>,
+    ) -> Result<Self> {
+        let zarr = config.zarr && output.is_some();
+        if let Some(out) = output.filter(|_| zarr)
+            && (rows == 0 || out.table_columns()?.as_ref() != Some(&columns))
+        {
+            out.create_table(&columns)?;
+        }
+        let csv = match &config.csv {
+            Some(path) => {
+                let header = columns.join(",");
+                let text = match fs::read_to_string(path) {
+                    Ok(text) if rows > 0 && text.lines().next() == Some(header.as_str()) => text,
+                    _ => header,
+                };
+                let mut csv = BufWriter::new(
+                    OpenOptions::new()
+                        .write(true)
+                        .create(true)
+                        .truncate(true)
+                        .open(path)
+                        .map_err(|e| format!("cannot write {}: {e}", path.display()))?,
+                );
+                for line in text.lines().take(rows as usize + 1) {
+                    writeln!(csv, "{line}")?;
+                }
+                Some(csv)
+            }
+            None => None,
+        };
+        Ok(Self {
+            columns,
+            every: config.every,
+            zarr,
+            csv,
+            rows,
+        })
+    }
+
+    /// Append one row, whose values follow `columns`
+    pub fn write(&mut self, values: &[f64], output: Option<&mut ZarrOutput>) -> Result<()> {
+        if let Some(out) = output.filter(|_| self.zarr) {
+            out.write_row(self.rows, values)?;
+        }
+        if let Some(csv) = &mut self.csv {
+            let line: Vec<_> = values.iter().map(|v| format!("{v:e}")).collect();
+            writeln!(csv, "{}", line.join(","))?;
+            csv.flush()?;
+        }
+        self.rows += 1;
+        Ok(())
+    }
+}
@@ -0,0 +1,180 @@
+//! Material values scaled by a static temperature, for heating that is slow
+//! next to the magnetization dynamics, e.g. the hot spot of a laser or of a
+//! current, without the longitudinal relaxation of an LLB solver.
+//!
+//! The temperature is uniform or given cell by cell, like a map. Mₛ and
+//! `a_ex` of every cell, after regions and maps, are multiplied by their
+//! factors at the temperature of the cell:
+//!
+//! ```toml
+//! [temperature]
+//! map = { expr = "300 + 400 * exp(-((x - 160e-9) / 40e-9)^2)" }
+//! ms = { kind = "curie", curie = 800.0, exponent = 0.5 }
+//! a_ex = { kind = "ms", exponent = 2.0 }
+//! ```
+//!
+//! Cells at or above the Curie temperature lose their moment: they start
+//! with m = 0 and act like holes of `[geometry]`.
+
+use nalgebra::Vector3;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use crate::{
+    Mesh, Result, VectorField, Waveform,
+    maps::Map,
+    waveform::{interpolate, read_table},
+};
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TemperatureConfig {
+    /// temperature of the whole sample (K)
+    pub value: f64,
+    /// temperature of every cell (K) instead, from an expression of the
+    /// position or a file
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub map: Option<Map>,
+    /// Mₛ(T) / Mₛ(0)
+    pub ms: Scaling,
+    /// a_ex(T) / a_ex(0)
+    pub a_ex: Scaling,
+}
+
+impl Default for TemperatureConfig {
+    /// mean-field laws with the Curie temperature of permalloy
+    fn default() -> Self {
+        Self {
+            value: 0.0,
+            map: None,
+            ms: Scaling::Curie {
+                curie: 850.0,
+                exponent: 0.5,
+            },
+            a_ex: Scaling::Ms { exponent: 2.0 },
+        }
+    }
+}
+
+/// A factor depending on the temperature T (K), selected by its `kind`
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(tag = "kind", rename_all = "lowercase", deny_unknown_fields)]
+pub enum Scaling {
+    /// `(1 - T / curie)^exponent` below the Curie temperature, 0 above
+    Curie { curie: f64, exponent: f64 },
+    /// `(Mₛ(T) / Mₛ(0))^exponent`, for `a_ex`: 2 in mean field, about 1.7
+    /// as measured in thin films
+    Ms { exponent: f64 },
+    /// Piecewise-linear interpolation between `(T, factor)` points, constant
+    /// beyond the first and last, or read from a `T factor` file
+    Table {
+        #[serde(default)]
+        points: Vec<(f64, f64)>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        file: Option<PathBuf>,
+    },
+}
+
+impl Scaling {
+    /// The factor at `temperature`, given that of Mₛ as `ms`
+    fn at(&self, temperature: f64, ms: f64) -> f64 {
+        match self {
+            Self::Curie { curie, exponent } => (1.0 - temperature / curie).max(0.0).powf(*exponent),
+            Self::Ms { exponent } => ms.powf(*exponent),
+            Self::Table { points, .. } => interpolate(points, temperature),
+        }
+    }
+
+    /// Read the points of a file-backed table, resolving relative paths
+    /// against `dir`. Tables that already hold points are left untouched.
+    pub fn load_file(&mut self, dir: &Path) -> Result<()> {
+        if let Self::Table {
+            points,
+            file: Some(file),
+        } = self
+            && points.is_empty()
+        {
+            *points = read_table(&dir.join(file))?;
+        }
+        Ok(())
+    }
+
+    /// Check the parameters, naming the entry as `key`; `ms` tells whether
+    /// this is the factor of Mₛ, which cannot depend on itself
+    pub fn validate(&self, key: &str, ms: bool) -> Result<()> {
+        match self {
+            Self::Curie { curie, exponent } => {
+                if !(curie.is_finite() && *curie > 0.0) {
+                    return Err(format!("`{key}.curie` must be positive, got {curie}").into());
+                }
+                if !(exponent.is_finite() && *exponent > 0.0) {
+                    return Err(format!("`{key}.exponent` must be positive, got {exponent}").into());
+                }
+                Ok(())
+            }
+            Self::Ms { .. } if ms => Err(format!("`{key}` cannot scale with Mₛ itself").into()),
+            Self::Ms { exponent } if !exponent.is_finite() => {
+                Err(format!("`{key}.exponent` must be finite").into())
+            }
+            Self::Ms { .. } => Ok(()),
+            Self::Table { points, file } => {
+                Waveform::Table {
+                    points: points.clone(),
+                    file: file.clone(),
+                    columns: Vec::new(),
+                    time_scale: 1.0,
+                    scale: 1.0,
+                }
+                .validate(key)?;
+                match points.iter().find(|(_, f)| !(f.is_finite() && *f >= 0.0)) {
+                    Some((t, f)) => {
+                        Err(format!("`{key}` must be non-negative, got {f} at {t} K").into())
+                    }
+                    None => Ok(()),
+                }
+            }
+        }
+    }
+}
+
+/// Factors of Mₛ and `a_ex` in every cell, from `[temperature]`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Thermal {
+    pub ms: Vec<f64>,
+    pub a_ex: Vec<f64>,
+}
+
+impl Thermal {
+    /// The factors of `config` at its temperatures on `mesh`
+    pub fn new(config: &TemperatureConfig, mesh: &Mesh) -> Result<Self> {
+        let temperature = match &config.map {
+            Some(map) => map
+                .values(mesh)
+                .map_err(|e| format!("`temperature.map`: {e}"))?,
+            None => vec![config.value; mesh.len()],
+        };
+        if let Some(t) = temperature.iter().find(|t| **t < 0.0) {
+            return Err(format!("`temperature` must be non-negative, got {t} K").into());
+        }
+        let ms: Vec<f64> = temperature.iter().map(|&t| config.ms.at(t, 1.0)).collect();
+        let a_ex = temperature
+            .iter()
+            .zip(&ms)
+            .map(|(&t, &ms)| config.a_ex.at(t, ms))
+            .collect();
+        Ok(Self { ms, a_ex })
+    }
+
+    /// Zero the vectors of `v` in the cells without a moment, at or above
+    /// the Curie temperature, `v` holding one or more vectors per cell
+    pub fn clear_paramagnetic(&self, v: &mut VectorField) {
+        let n = self.ms.len();
+        v.par_update(|i, v| {
+            if self.ms[i % n] > 0.0 {
+                v
+            } else {
+                Vector3::zeros()
+            }
+        });
+    }
+}
@@ -0,0 +1,315 @@
+//! Initial magnetic textures, set by `initial.texture` in place of the
+//! uniform `initial.m`:
+//!
+//! ```toml
+//! [initial]
+//! texture = { kind = "vortex", circulation = 1, polarity = -1, core = 10e-9 }
+//! # { kind = "neel", axis = "x", position = 100e-9, width = 5e-9 }
+//! # { kind = "bloch" }, the same keys
+//! # { kind = "skyrmion", radius = 20e-9, width = 3e-9, helicity = 0.0 }
+//! # { kind = "helix", axis = "x", pitch = 60e-9 }
+//! # { kind = "random", seed = 1 }
+//! # { kind = "expr", mx = "1 / cosh((x - 50e-9) / 5e-9)", mz = "-tanh((x - 50e-9) / 5e-9)" }
+//! ```
+//!
+//! Centres and positions are those of cell centres (m), the middle of the
+//! mesh by default. Walls separate a domain along +z, before `position`
+//! along their axis, from one along -z after it, turning through the axis
+//! (Néel) or across it (Bloch) with the profile θ = 2 atan(exp(x / width)).
+//! A skyrmion has its core along `polarity` z in a background along the
+//! other way, turning over the profile of two such walls a `radius` from the
+//! centre, radially for `helicity` 0 (Néel) and circling for π/2 (Bloch).
+//! A helix turns in the plane across its axis, one turn per `pitch`. An
+//! `expr` texture gives each component as an expression of the cell centre
+//! `x`, `y`, `z` (m), 0 when left out, normalized in every cell.
+
+use nalgebra::Vector3;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{Mesh, Result, VectorField, expr::Expr, rng::Rng, wall::Direction};
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(tag = "kind", rename_all = "lowercase", deny_unknown_fields)]
+pub enum Texture {
+    /// an in-plane curl around an out-of-plane core
+    Vortex {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        centre: Option<Vector3<f64>>,
+        /// 1 counterclockwise seen from +z, -1 clockwise
+        #[serde(default = "one")]
+        circulation: f64,
+        /// sign of mz in the core
+        #[serde(default = "one")]
+        polarity: f64,
+        /// radius over which the core turns in-plane (m)
+        #[serde(default = "core")]
+        core: f64,
+    },
+    /// a Néel wall, turning through its axis
+    Neel {
+        #[serde(default = "x")]
+        axis: Direction,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        position: Option<f64>,
+        #[serde(default = "width")]
+        width: f64,
+    },
+    /// a Bloch wall, turning across its axis
+    Bloch {
+        #[serde(default = "x")]
+        axis: Direction,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        position: Option<f64>,
+        #[serde(default = "width")]
+        width: f64,
+    },
+    Skyrmion {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        centre: Option<Vector3<f64>>,
+        /// where mz vanishes (m)
+        #[serde(default = "radius")]
+        radius: f64,
+        /// of its wall (m)
+        #[serde(default = "width")]
+        width: f64,
+        /// sign of mz in the core
+        #[serde(default = "minus_one")]
+        polarity: f64,
+        /// angle of the in-plane component from the radial direction (rad)
+        #[serde(default)]
+        helicity: f64,
+    },
+    Helix {
+        #[serde(default = "x")]
+        axis: Direction,
+        /// length of one turn (m)
+        pitch: f64,
+    },
+    /// independent directions, uniform on the sphere
+    Random {
+        #[serde(default)]
+        seed: u64,
+    },
+    /// components as expressions of the cell centre `x`, `y`, `z` (m)
+    Expr {
+        #[serde(default = "zero")]
+        mx: String,
+        #[serde(default = "zero")]
+        my: String,
+        #[serde(default = "zero")]
+        mz: String,
+    },
+}
+
+fn zero() -> String {
+    "0".into()
+}
+
+fn one() -> f64 {
+    1.0
+}
+
+fn minus_one() -> f64 {
+    -1.0
+}
+
+fn core() -> f64 {
+    10e-9
+}
+
+fn radius() -> f64 {
+    20e-9
+}
+
+fn width() -> f64 {
+    5e-9
+}
+
+fn x() -> Direction {
+    Direction::X
+}
+
+impl Texture {
+    /// Check lengths and signs, `key` naming the texture
+    pub fn validate(&self, key: &str) -> Result<()> {
+        let positive = |name: &str, v: f64| -> Result<()> {
+            if v.is_finite() && v > 0.0 {
+                Ok(())
+            } else {
+                Err(format!("`{key}.{name}` must be positive, got {v}").into())
+            }
+        };
+        let sign = |name: &str, v: f64| -> Result<()> {
+            if v == 1.0 || v == -1.0 {
+                Ok(())
+            } else {
+                Err(format!("`{key}.{name}` must be 1 or -1, got {v}").into())
+            }
+        };
+        match self {
+            Self::Vortex {
+                circulation,
+                polarity,
+                core,
+                ..
+            } => {
+                sign("circulation", *circulation)?;
+                sign("polarity", *polarity)?;
+                positive("core", *core)
+            }
+            Self::Neel { axis, width, .. } | Self::Bloch { axis, width, .. } => {
+                if *axis == Direction::Z {
+                    return Err(format!(
+                        "`{key}.axis` must be \"x\" or \"y\": the domains lie along ±z"
+                    )
+                    .into());
+                }
+                positive("width", *width)
+            }
+            Self::Skyrmion {
+                radius,
+                width,
+                polarity,
+                helicity,
+                ..
+            } => {
+                positive("radius", *radius)?;
+                positive("width", *width)?;
+                sign("polarity", *polarity)?;
+                if helicity.is_finite() {
+                    Ok(())
+                } else {
+                    Err(format!("`{key}.helicity` must be finite").into())
+                }
+            }
+            Self::Helix { pitch, .. } => positive("pitch", *pitch),
+            Self::Random { .. } => Ok(()),
+            Self::Expr { mx, my, mz } => {
+                for (name, expr) in [("mx", mx), ("my", my), ("mz", mz)] {
+                    Expr::parse(expr, &["x", "y", "z"])
+                        .map_err(|e| format!("`{key}.{name}`: {e}"))?;
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// The magnetization of the texture on `mesh`, failing only where an
+    /// `expr` texture does not give a direction
+    pub fn state(&self, mesh: &Mesh) -> Result<VectorField> {
+        let middle = Vector3::new(
+            mesh.nx as f64 * mesh.dx,
+            mesh.ny as f64 * mesh.dy,
+            mesh.nz as f64 * mesh.dz,
+        ) / 2.0;
+        if let Self::Random { seed } = self {
+            let mut rng = Rng::new(*seed);
+            return Ok((0..mesh.len())
+                .map(|_| rng.normal_vector().normalize())
+                .collect());
+        }
+        if let Self::Expr { mx, my, mz } = self {
+            let parsed = [mx, my, mz]
+                .map(|expr| Expr::parse(expr, &["x", "y", "z"]))
+                .into_iter()
+                .collect::<Result<Vec<_>>>()?;
+            let m: Vec<Vector3<f64>> = (0..mesh.len())
+                .into_par_iter()
+                .map(|i| {
+                    let r = mesh.position(i);
+                    let values = [r.x, r.y, r.z];
+                    Vector3::from_iterator(parsed.iter().map(|e| e.eval(&values)))
+                })
+                .collect();
+            return match m
+                .iter()
+                .position(|v| !(v.norm() > 0.0 && v.norm().is_finite()))
+            {
+                Some(i) => Err(format!(
+                    "the `expr` texture gives no direction in cell {:?}: {:?}",
+                    mesh.coords(i),
+                    m[i].as_slice()
+                )
+                .into()),
+                None => Ok(m.into_iter().map(|v| v.normalize()).collect()),
+            };
+        }
+        Ok((0..mesh.len())
+            .map(|i| self.at(mesh.position(i), &middle))
+            .collect())
+    }
+
+    /// Direction at the position `r`, in a mesh whose middle is `middle`
+    fn at(&self, r: Vector3<f64>, middle: &Vector3<f64>) -> Vector3<f64> {
+        match *self {
+            Self::Vortex {
+                centre,
+                circulation,
+                polarity,
+                core,
+            } => {
+                let d = r - centre.unwrap_or(*middle);
+                let rho = d.x.hypot(d.y);
+                let mz = polarity * (-(rho / core).powi(2)).exp();
+                if rho == 0.0 {
+                    return Vector3::new(0.0, 0.0, polarity);
+                }
+                let curl = circulation * (1.0 - mz * mz).sqrt() / rho;
+                Vector3::new(-curl * d.y, curl * d.x, mz)
+            }
+            Self::Neel {
+                axis,
+                position,
+                width,
+            }
+            | Self::Bloch {
+                axis,
+                position,
+                width,
+            } => {
+                let a = axis as usize;
+                let theta = 2.0
+                    * ((r[a] - position.unwrap_or(middle[a])) / width)
+                        .exp()
+                        .atan();
+                let mut along = Vector3::zeros();
+                along[a] = 1.0;
+                if matches!(self, Self::Bloch { .. }) {
+                    along = Vector3::z().cross(&along);
+                }
+                along * theta.sin() + Vector3::z() * theta.cos()
+            }
+            Self::Skyrmion {
+                centre,
+                radius,
+                width,
+                polarity,
+                helicity,
+            } => {
+                let d = r - centre.unwrap_or(*middle);
+                let rho = d.x.hypot(d.y);
+                if rho == 0.0 {
+                    return Vector3::new(0.0, 0.0, polarity);
+                }
+                // π at the centre, π/2 at `radius`, 0 far away
+                let theta = 2.0 * ((radius / width).sinh() / (rho / width).sinh()).atan();
+                let (s, c) = helicity.sin_cos();
+                let (ux, uy) = (d.x / rho, d.y / rho);
+                let inplane = Vector3::new(c * ux - s * uy, s * ux + c * uy, 0.0);
+                inplane * theta.sin() - Vector3::z() * (polarity * theta.cos())
+            }
+            Self::Helix { axis, pitch } => {
+                let a = axis as usize;
+                let phase = std::f64::consts::TAU * r[a] / pitch;
+                let (mut u, mut v) = (Vector3::zeros(), Vector3::zeros());
+                u[(a + 1) % 3] = 1.0;
+                v[(a + 2) % 3] = 1.0;
+                u * phase.cos() + v * phase.sin()
+            }
+            Self::Random { .. } | Self::Expr { .. } => {
+                unreachable!("filled in `Texture::state`")
+            }
+        }
+    }
+}
@@ -0,0 +1,56 @@
+//! Topological charge (skyrmion number) of the magnetization of a film.
+//!
+//! On the lattice the charge is that of Berg and Lüscher: every square of
+//! four neighbouring cells in a layer is split into two triangles, and the
+//! solid angle Ω spanned by the moments at the corners of each adds Ω / 4π.
+//! Unlike the discretized integral of m · (∂x m × ∂y m) / 4π, the sum is an
+//! integer for any configuration without zero moments, up to the open edges
+//! of the mesh, so a skyrmion appearing or vanishing shows as a step of ±1.
+
+use nalgebra::Vector3;
+use rayon::prelude::*;
+use std::f64::consts::PI;
+
+use crate::{Mesh, VectorField, parallel::chunk};
+
+/// Whether the charge means anything on `mesh`: more than one cell along x
+/// and y
+pub fn applies(mesh: &Mesh) -> bool {
+    mesh.nx > 1 && mesh.ny > 1
+}
+
+/// Charge of the squares whose lower-left corner is each cell of `mesh`, 0
+/// where there is no such square, past the edge of a free boundary
+pub fn charge_density(mesh: &Mesh, m: &VectorField) -> Vec<f64> {
+    (0..mesh.len())
+        .into_par_iter()
+        .with_min_len(chunk())
+        .map(|i| {
+            let Some(right) = mesh.neighbor(i, 0, 1) else {
+                return 0.0;
+            };
+            let (Some(up), Some(corner)) = (mesh.neighbor(i, 1, 1), mesh.neighbor(right, 1, 1))
+            else {
+                return 0.0;
+            };
+            let [a, b, c, d] = [i, right, corner, up].map(|k| m.get(k));
+            (solid_angle(a, b, c) + solid_angle(a, c, d)) / (4.0 * PI)
+        })
+        .collect()
+}
+
+/// Total charge of `m` on `mesh`, averaged over the layers
+pub fn charge(mesh: &Mesh, m: &VectorField) -> f64 {
+    charge_density(mesh, m).iter().sum::<f64>() / mesh.nz as f64
+}
+
+/// Signed solid angle of the spherical triangle `a`, `b`, `c`, 0 if one of
+/// them is a missing moment
+fn solid_angle(a: Vector3<f64>, b: Vector3<f64>, c: Vector3<f64>) -> f64 {
+    if [a, b, c].iter().any(|v| v.norm_squared() == 0.0) {
+        return 0.0;
+    }
+    let numerator = a.dot(&b.cross(&c));
+    let denominator = 1.0 + a.dot(&b) + b.dot(&c) + c.dot(&a);
+    2.0 * numerator.atan2(denominator)
+}
@@ -0,0 +1,127 @@
+//! Current-driven torques entering dm/dt directly rather than through the
+//! effective field.
+
+mod seebeck;
+mod slonczewski;
+mod sot;
+mod zhang_li;
+
+pub use seebeck::SpinSeebeck;
+pub use slonczewski::Slonczewski;
+pub use sot::SpinOrbit;
+pub use zhang_li::ZhangLi;
+
+use nalgebra::Vector3;
+
+use crate::{Config, Mesh, Params, Result, VectorField};
+
+/// Bohr magneton (J T⁻¹)
+pub const MU_B: f64 = 9.274_010_078_3e-24;
+/// Elementary charge (C)
+pub const QE: f64 = 1.602_176_634e-19;
+/// Reduced Planck constant (J s)
+pub const HBAR: f64 = 1.054_571_817e-34;
+
+/// A contribution to dm/dt that is not the gradient of an energy
+pub trait Torque: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    /// Add this torque (s⁻¹) at time `t` to `dmdt`
+    fn add_torque(&self, m: &VectorField, t: f64, mesh: &Mesh, p: &Params, dmdt: &mut VectorField);
+}
+
+/// Damping-like (`a`) and field-like (`b`) torque around `p`, in T
+#[inline(always)]
+pub(crate) fn spin_torque(
+    m: Vector3<f64>,
+    p: Vector3<f64>,
+    a: f64,
+    b: f64,
+    alpha: f64,
+) -> Vector3<f64> {
+    let pxm = p.cross(&m);
+    let mxpxm = m.cross(&pxm);
+    ((a + alpha * b) * mxpxm + (b - alpha * a) * pxm) / (1.0 + alpha * alpha)
+}
+
+/// Optional spin torques, all off by default
+#[derive(Default)]
+pub struct Torques {
+    pub zhang_li: Option<ZhangLi>,
+    pub slonczewski: Option<Slonczewski>,
+    pub sot: Option<SpinOrbit>,
+    pub seebeck: Option<SpinSeebeck>,
+}
+
+impl Torques {
+    pub fn from_config(config: &Config) -> Result<Self> {
+        let seebeck = match &config.langevin {
+            Some(l) if l.seebeck != 0.0 => Some(SpinSeebeck::new(
+                l.seebeck,
+                l.beta,
+                &l.temperatures(&config.mesh)?,
+                &config.mesh,
+            )),
+            _ => None,
+        };
+        Ok(Self {
+            zhang_li: config
+                .zhang_li
+                .as_ref()
+                .map(|c| ZhangLi::new(c.polarization, c.xi, c.current.clone())),
+            slonczewski: config.slonczewski.as_ref().map(|c| {
+                Slonczewski::new(
+                    c.polarization,
+                    c.lambda,
+                    c.epsilon_prime,
+                    c.fixed,
+                    c.thickness
+                        .unwrap_or(config.mesh.nz as f64 * config.mesh.dz),
+                    c.current.clone(),
+                )
+            }),
+            sot: config.sot.as_ref().map(|c| {
+                SpinOrbit::new(
+                    c.theta_sh,
+                    c.damping_like,
+                    c.field_like,
+                    c.thickness
+                        .unwrap_or(config.mesh.nz as f64 * config.mesh.dz),
+                    c.current.clone(),
+                )
+            }),
+            seebeck,
+        })
+    }
+
+    /// Whether no torque is active
+    pub fn is_empty(&self) -> bool {
+        self.active().next().is_none()
+    }
+
+    /// Add every active torque at time `t` to `dmdt`
+    pub fn add_torque(
+        &self,
+        m: &VectorField,
+        t: f64,
+        mesh: &Mesh,
+        p: &Params,
+        dmdt: &mut VectorField,
+    ) {
+        for torque in self.active() {
+            torque.add_torque(m, t, mesh, p, dmdt);
+        }
+    }
+
+    fn active(&self) -> impl Iterator<Item = &dyn Torque> {
+        let zhang_li = self.zhang_li.as_ref().map(|t| t as &dyn Torque);
+        let slonczewski = self.slonczewski.as_ref().map(|t| t as &dyn Torque);
+        let sot = self.sot.as_ref().map(|t| t as &dyn Torque);
+        let seebeck = self.seebeck.as_ref().map(|t| t as &dyn Torque);
+        zhang_li
+            .into_iter()
+            .chain(slonczewski)
+            .chain(sot)
+            .chain(seebeck)
+    }
+}
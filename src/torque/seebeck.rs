@@ -0,0 +1,74 @@
+use nalgebra::Vector3;
+
+use super::{Torque, zhang_li::convective};
+use crate::{Mesh, Params, VectorField};
+
+/// Magnonic spin-Seebeck torque: thermal magnons flowing down a temperature
+/// gradient carry angular momentum, which they hand over to the textures
+/// they cross like the electrons of a current do. It takes the Zhang–Li
+/// form
+///
+/// dm/dt += -1/(1+α²) [(1+βα) m×(m×(u·∇)m) + (β-α) m×(u·∇)m]
+///
+/// with the drift velocity u = -S ∇T of the magnon current. Domain walls
+/// thus move against it, towards the hot side for S > 0.
+pub struct SpinSeebeck {
+    /// non-adiabaticity β
+    pub beta: f64,
+    /// drift velocity u of every cell (m s⁻¹)
+    velocity: Vec<Vector3<f64>>,
+}
+
+impl SpinSeebeck {
+    /// The torque of coefficient `seebeck` (m² s⁻¹ K⁻¹) in the temperatures
+    /// `temperature` (K) of the cells of `mesh`
+    pub fn new(seebeck: f64, beta: f64, temperature: &[f64], mesh: &Mesh) -> Self {
+        let velocity = (0..mesh.len())
+            .map(|i| -seebeck * gradient(temperature, i, mesh))
+            .collect();
+        Self { beta, velocity }
+    }
+}
+
+/// ∇T at cell `i` by central differences, one-sided at free edges
+fn gradient(temperature: &[f64], i: usize, mesh: &Mesh) -> Vector3<f64> {
+    let mut g = Vector3::zeros();
+    for (axis, h) in mesh.cell().into_iter().enumerate() {
+        let next = mesh.neighbor(i, axis, 1);
+        let prev = mesh.neighbor(i, axis, -1);
+        let steps = next.is_some() as u8 + prev.is_some() as u8;
+        if steps > 0 {
+            let t = |j: Option<usize>| temperature[j.unwrap_or(i)];
+            g[axis] = (t(next) - t(prev)) / (f64::from(steps) * h);
+        }
+    }
+    g
+}
+
+impl Torque for SpinSeebeck {
+    fn name(&self) -> &'static str {
+        "seebeck"
+    }
+
+    fn add_torque(
+        &self,
+        m: &VectorField,
+        _t: f64,
+        mesh: &Mesh,
+        p: &Params,
+        dmdt: &mut VectorField,
+    ) {
+        let beta = self.beta;
+        dmdt.par_add(|i| {
+            let u = self.velocity[i];
+            if u == Vector3::zeros() || p.ms_at(i) == 0.0 {
+                return Vector3::zeros();
+            }
+            let alpha = p.alpha_at(i);
+            let pref = -1.0 / (1.0 + alpha * alpha);
+            let (m_i, du) = (m.get(i), convective(m, i, mesh, p, u));
+            let mxdu = m_i.cross(&du);
+            pref * ((1.0 + beta * alpha) * m_i.cross(&mxdu) + (beta - alpha) * mxdu)
+        });
+    }
+}
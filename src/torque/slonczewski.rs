@@ -0,0 +1,79 @@
+use nalgebra::Vector3;
+
+use super::{HBAR, QE, Torque, spin_torque};
+use crate::{Mesh, Params, VectorField, Waveform};
+
+/// Slonczewski spin-transfer torque of a current flowing perpendicular to
+/// the plane through a fixed layer with magnetization `p`:
+///
+/// dm/dt += γ/(1+α²) [(a + αb) m×(p×m) + (b - αa) p×m]
+///
+/// with a = β ε, b = β ε′, β = ħJ / (e Mₛ t) and
+/// ε = P Λ² / ((Λ²+1) + (Λ²-1) m·p). A positive current pulls m towards `p`.
+pub struct Slonczewski {
+    /// spin polarization P
+    pub polarization: f64,
+    /// Slonczewski asymmetry parameter Λ
+    pub lambda: f64,
+    /// secondary, field-like efficiency ε′
+    pub epsilon_prime: f64,
+    /// fixed-layer magnetization (unit vector)
+    pub fixed: Vector3<f64>,
+    /// free-layer thickness (m)
+    pub thickness: f64,
+    /// current density J(t) along z (A m⁻²)
+    pub current: Waveform<f64>,
+}
+
+impl Slonczewski {
+    pub fn new(
+        polarization: f64,
+        lambda: f64,
+        epsilon_prime: f64,
+        fixed: Vector3<f64>,
+        thickness: f64,
+        current: Waveform<f64>,
+    ) -> Self {
+        Self {
+            polarization,
+            lambda,
+            epsilon_prime,
+            fixed: fixed.normalize(),
+            thickness,
+            current,
+        }
+    }
+}
+
+impl Torque for Slonczewski {
+    fn name(&self) -> &'static str {
+        "slonczewski"
+    }
+
+    fn add_torque(
+        &self,
+        m: &VectorField,
+        t: f64,
+        _mesh: &Mesh,
+        p: &Params,
+        dmdt: &mut VectorField,
+    ) {
+        let j = self.current.eval(t);
+        if j == 0.0 {
+            return;
+        }
+        let l2 = self.lambda * self.lambda;
+        let fixed = self.fixed;
+        dmdt.par_add(|i| {
+            let ms = p.ms_at(i);
+            if ms == 0.0 {
+                return Vector3::zeros();
+            }
+            let beta = HBAR * j / (QE * ms * self.thickness);
+            let m = m.get(i);
+            let eps = self.polarization * l2 / ((l2 + 1.0) + (l2 - 1.0) * m.dot(&fixed));
+            let (a, b) = (beta * eps, beta * self.epsilon_prime);
+            p.gamma_at(i) * spin_torque(m, fixed, a, b, p.alpha_at(i))
+        });
+    }
+}
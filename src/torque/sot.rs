@@ -0,0 +1,74 @@
+use nalgebra::Vector3;
+
+use super::{HBAR, QE, Torque, spin_torque};
+use crate::{Mesh, Params, VectorField, Waveform};
+
+/// Spin-orbit torque from a charge current J flowing in the plane of a heavy
+/// metal underneath the magnet. The spin Hall effect injects spins along
+/// σ = Ĵ × ẑ, giving
+///
+/// dm/dt += γ/(1+α²) [(a + αb) m×(σ×m) + (b - αa) σ×m]
+///
+/// with a = c_DL θ_SH ħ|J| / (2e Mₛ t) and b = c_FL θ_SH ħ|J| / (2e Mₛ t).
+pub struct SpinOrbit {
+    /// spin Hall angle θ_SH
+    pub theta_sh: f64,
+    /// damping-like efficiency c_DL
+    pub damping_like: f64,
+    /// field-like efficiency c_FL
+    pub field_like: f64,
+    /// ferromagnet thickness (m)
+    pub thickness: f64,
+    /// in-plane current density J(t) (A m⁻²)
+    pub current: Waveform<Vector3<f64>>,
+}
+
+impl SpinOrbit {
+    pub fn new(
+        theta_sh: f64,
+        damping_like: f64,
+        field_like: f64,
+        thickness: f64,
+        current: Waveform<Vector3<f64>>,
+    ) -> Self {
+        Self {
+            theta_sh,
+            damping_like,
+            field_like,
+            thickness,
+            current,
+        }
+    }
+}
+
+impl Torque for SpinOrbit {
+    fn name(&self) -> &'static str {
+        "sot"
+    }
+
+    fn add_torque(
+        &self,
+        m: &VectorField,
+        t: f64,
+        _mesh: &Mesh,
+        p: &Params,
+        dmdt: &mut VectorField,
+    ) {
+        let j = self.current.eval(t);
+        let sigma = j.cross(&Vector3::z());
+        let magnitude = sigma.norm();
+        if magnitude == 0.0 {
+            return;
+        }
+        let sigma = sigma / magnitude;
+        let pref = self.theta_sh * HBAR * magnitude / (2.0 * QE * self.thickness);
+        dmdt.par_add(|i| {
+            let ms = p.ms_at(i);
+            if ms == 0.0 {
+                return Vector3::zeros();
+            }
+            let (a, b) = (self.damping_like * pref / ms, self.field_like * pref / ms);
+            p.gamma_at(i) * spin_torque(m.get(i), sigma, a, b, p.alpha_at(i))
+        });
+    }
+}
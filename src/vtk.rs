@@ -0,0 +1,165 @@
+//! VTK image data for ParaView: the legacy `.vtk` format and XML `.vti`.
+//!
+//! The magnetization is stored as cell data named `m` on an image of
+//! `(nx + 1) × (ny + 1) × (nz + 1)` points, and the simulated time, when
+//! known, as the `TimeValue` field ParaView uses for animations.
+
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+};
+
+use crate::{Mesh, Result, VectorField};
+
+/// Encoding of the data arrays
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum VtkData {
+    #[default]
+    Float32,
+    Float64,
+    Ascii,
+}
+
+impl VtkData {
+    /// Binary values of `m`, x fastest, in the given byte order
+    fn bytes(self, m: &VectorField, big_endian: bool) -> Vec<u8> {
+        let values = m.iter().flat_map(|v| [v.x, v.y, v.z]);
+        match (self, big_endian) {
+            (VtkData::Float32, false) => values.flat_map(|v| (v as f32).to_le_bytes()).collect(),
+            (VtkData::Float32, true) => values.flat_map(|v| (v as f32).to_be_bytes()).collect(),
+            (_, false) => values.flat_map(f64::to_le_bytes).collect(),
+            (_, true) => values.flat_map(f64::to_be_bytes).collect(),
+        }
+    }
+}
+
+fn check(mesh: &Mesh, m: &VectorField) -> Result<()> {
+    if m.len() != mesh.len() {
+        return Err(format!(
+            "{} cells do not fit the {} of the mesh",
+            m.len(),
+            mesh.len()
+        )
+        .into());
+    }
+    Ok(())
+}
+
+/// Write `m` on `mesh` in the legacy VTK format (binary data is big-endian)
+pub fn write_legacy(
+    path: impl AsRef<Path>,
+    mesh: &Mesh,
+    m: &VectorField,
+    t: Option<f64>,
+    data: VtkData,
+) -> Result<()> {
+    check(mesh, m)?;
+    let mut w = BufWriter::new(File::create(path)?);
+    let [nx, ny, nz] = mesh.size();
+    let [dx, dy, dz] = mesh.cell();
+    writeln!(w, "# vtk DataFile Version 3.0")?;
+    match t {
+        Some(t) => writeln!(w, "m at t = {t:e} s")?,
+        None => writeln!(w, "m")?,
+    }
+    let ascii = data == VtkData::Ascii;
+    writeln!(w, "{}", if ascii { "ASCII" } else { "BINARY" })?;
+    writeln!(w, "DATASET STRUCTURED_POINTS")?;
+    writeln!(w, "DIMENSIONS {} {} {}", nx + 1, ny + 1, nz + 1)?;
+    writeln!(w, "ORIGIN 0 0 0")?;
+    writeln!(w, "SPACING {dx:e} {dy:e} {dz:e}")?;
+    if let Some(t) = t {
+        writeln!(w, "FIELD FieldData 1")?;
+        writeln!(w, "TimeValue 1 1 double")?;
+        writeln!(w, "{t:e}")?;
+    }
+    writeln!(w, "CELL_DATA {}", mesh.len())?;
+    let kind = if data == VtkData::Float32 {
+        "float"
+    } else {
+        "double"
+    };
+    writeln!(w, "VECTORS m {kind}")?;
+    if ascii {
+        for v in m.iter() {
+            writeln!(w, "{:e} {:e} {:e}", v.x, v.y, v.z)?;
+        }
+    } else {
+        w.write_all(&data.bytes(m, true))?;
+        writeln!(w)?;
+    }
+    w.flush()?;
+    Ok(())
+}
+
+/// Write `m` on `mesh` as XML image data (`.vti`); binary data goes to a raw
+/// little-endian appended block
+pub fn write_xml(
+    path: impl AsRef<Path>,
+    mesh: &Mesh,
+    m: &VectorField,
+    t: Option<f64>,
+    data: VtkData,
+) -> Result<()> {
+    check(mesh, m)?;
+    let mut w = BufWriter::new(File::create(path)?);
+    let [nx, ny, nz] = mesh.size();
+    let [dx, dy, dz] = mesh.cell();
+    let extent = format!("0 {nx} 0 {ny} 0 {nz}");
+    writeln!(
+        w,
+        r#"<?xml version="1.0"?>
+<VTKFile type="ImageData" version="1.0" byte_order="LittleEndian" header_type="UInt64">
+  <ImageData WholeExtent="{extent}" Origin="0 0 0" Spacing="{dx:e} {dy:e} {dz:e}">"#
+    )?;
+    if let Some(t) = t {
+        writeln!(
+            w,
+            r#"    <FieldData>
+      <DataArray type="Float64" Name="TimeValue" NumberOfTuples="1" format="ascii">{t:e}</DataArray>
+    </FieldData>"#
+        )?;
+    }
+    writeln!(
+        w,
+        r#"    <Piece Extent="{extent}">
+      <CellData Vectors="m">"#
+    )?;
+    let kind = if data == VtkData::Float32 {
+        "Float32"
+    } else {
+        "Float64"
+    };
+    if data == VtkData::Ascii {
+        writeln!(
+            w,
+            r#"        <DataArray type="{kind}" Name="m" NumberOfComponents="3" format="ascii">"#
+        )?;
+        for v in m.iter() {
+            writeln!(w, "          {:e} {:e} {:e}", v.x, v.y, v.z)?;
+        }
+        writeln!(w, "        </DataArray>")?;
+    } else {
+        writeln!(
+            w,
+            r#"        <DataArray type="{kind}" Name="m" NumberOfComponents="3" format="appended" offset="0"/>"#
+        )?;
+    }
+    writeln!(
+        w,
+        r#"      </CellData>
+    </Piece>
+  </ImageData>"#
+    )?;
+    if data != VtkData::Ascii {
+        let bytes = data.bytes(m, false);
+        write!(w, "  <AppendedData encoding=\"raw\">\n   _")?;
+        w.write_all(&(bytes.len() as u64).to_le_bytes())?;
+        w.write_all(&bytes)?;
+        writeln!(w, "\n  </AppendedData>")?;
+    }
+    writeln!(w, "</VTKFile>")?;
+    w.flush()?;
+    Ok(())
+}
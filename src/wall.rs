@@ -0,0 +1,138 @@
+//! Position and velocity of a domain wall, among the observables when
+//! `[wall]` is set, and a window moving with it.
+//!
+//! The chosen component of `m`, averaged over the planes across the chosen
+//! axis, gives a profile along it; the wall sits where the profile first
+//! changes sign, interpolated linearly between the two cell centres. The
+//! velocity follows from dm/dt rather than from successive positions: a
+//! profile p(x - X(t)) moving rigidly gives ∫ ∂p/∂t dx = -Ẋ Δp, with Δp the
+//! change of p from one end to the other.
+//!
+//! With `center`, the magnetization is shifted along the axis by whole cells
+//! whenever the wall drifts a cell or more from the centre of the mesh, as
+//! `ext_centerWall` of mumax3 does: the planes entering at one end copy the
+//! last plane there, feeding in fresh domain, and those leaving at the other
+//! end are dropped. A short mesh then follows a wall over long distances;
+//! the position recorded includes the shifts. The window is checked after
+//! every step, and a wall that leaves it all the same ends the run with an
+//! error.
+
+use serde::{Deserialize, Serialize};
+
+use crate::{Mesh, VectorField};
+
+/// Which wall to follow
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WallConfig {
+    /// component of `m` changing sign across the wall
+    pub component: Direction,
+    /// axis along which the wall moves
+    pub axis: Direction,
+    /// shift `m` to keep the wall at the centre of the mesh
+    pub center: bool,
+}
+
+impl Default for WallConfig {
+    /// the wall between up and down domains of a film, moving along x
+    fn default() -> Self {
+        Self {
+            component: Direction::Z,
+            axis: Direction::X,
+            center: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Direction {
+    X,
+    Y,
+    Z,
+}
+
+impl WallConfig {
+    /// Average of `component` of `v` over each plane across `axis` holding
+    /// moments in `m`, with the index of the plane
+    fn profile(&self, mesh: &Mesh, m: &VectorField, v: &VectorField) -> Vec<(usize, f64)> {
+        let (a, c) = (self.axis as usize, self.component as usize);
+        let n = mesh.size()[a];
+        let (mut sum, mut count) = (vec![0.0; n], vec![0usize; n]);
+        for i in 0..mesh.len() {
+            if m.get(i).norm_squared() > 0.0 {
+                let k = mesh.coords(i)[a];
+                sum[k] += v.get(i)[c];
+                count[k] += 1;
+            }
+        }
+        (0..n)
+            .filter(|&k| count[k] > 0)
+            .map(|k| (k, sum[k] / count[k] as f64))
+            .collect()
+    }
+
+    /// Position of the wall along the axis (m) from the corner of the mesh,
+    /// NaN without a sign change
+    pub fn position(&self, mesh: &Mesh, m: &VectorField) -> f64 {
+        let d = mesh.cell()[self.axis as usize];
+        let p = self.profile(mesh, m, m);
+        p.windows(2)
+            .find(|w| (w[0].1 <= 0.0) != (w[1].1 <= 0.0))
+            .map_or(f64::NAN, |w| {
+                let [(k0, p0), (k1, p1)] = [w[0], w[1]];
+                let k = k0 as f64 + (k1 - k0) as f64 * p0 / (p0 - p1);
+                (k + 0.5) * d
+            })
+    }
+
+    /// Velocity of the wall along the axis (m/s) given dm/dt, NaN when the
+    /// component hardly changes across the sample
+    pub fn velocity(&self, mesh: &Mesh, m: &VectorField, dmdt: &VectorField) -> f64 {
+        let p = self.profile(mesh, m, m);
+        let (Some(first), Some(last)) = (p.first(), p.last()) else {
+            return f64::NAN;
+        };
+        let change = last.1 - first.1;
+        if change.abs() < 0.5 {
+            return f64::NAN;
+        }
+        let d = mesh.cell()[self.axis as usize];
+        let flow: f64 = self.profile(mesh, m, dmdt).iter().map(|(_, v)| v).sum();
+        -flow * d / change
+    }
+
+    /// Cells by which to shift `m` to bring the wall back to the centre of
+    /// the mesh, 0 while it is less than a cell away, `None` if there is no
+    /// wall left to follow
+    pub fn offset(&self, mesh: &Mesh, m: &VectorField) -> Option<isize> {
+        let a = self.axis as usize;
+        let x = self.position(mesh, m) / mesh.cell()[a];
+        if x.is_nan() {
+            return None;
+        }
+        let away = x - mesh.size()[a] as f64 / 2.0;
+        Some(if away.abs() >= 1.0 {
+            away.round() as isize
+        } else {
+            0
+        })
+    }
+
+    /// Move `m`, of one or two sublattices, `by` cells towards the start of
+    /// the axis, the planes entering at the end copying the last one
+    pub fn shift(&self, mesh: &Mesh, m: &mut VectorField, by: isize) {
+        let a = self.axis as usize;
+        let n = mesh.size()[a] as isize;
+        // in place: every cell is read from further along the shift than
+        // those written before it, in the order of the cells or its reverse
+        let len = m.len();
+        for j in 0..len {
+            let i = if by > 0 { j } else { len - 1 - j };
+            let (block, cell) = (i / mesh.len(), i % mesh.len());
+            let mut c = mesh.coords(cell);
+            c[a] = (c[a] as isize + by).clamp(0, n - 1) as usize;
+            m.set(i, m.get(block * mesh.len() + mesh.idx(c[0], c[1], c[2])));
+        }
+    }
+}
@@ -0,0 +1,372 @@
+//! Time-dependent drive signals (fields, currents, voltages).
+
+use nalgebra::{Vector3, Vector6};
+use serde::{Deserialize, Serialize};
+use std::{
+    f64::consts::PI,
+    fs,
+    ops::{Add, Mul, Sub},
+    path::{Path, PathBuf},
+};
+
+use crate::Result;
+
+/// A quantity that can follow a waveform: scalars, vectors and strains
+pub trait Amplitude:
+    Copy + Add<Output = Self> + Sub<Output = Self> + Mul<f64, Output = Self> + Send + Sync
+{
+    /// number of columns per sample in a waveform file
+    const COMPONENTS: usize;
+    fn zero() -> Self;
+    fn from_slice(v: &[f64]) -> Self;
+}
+
+impl Amplitude for f64 {
+    const COMPONENTS: usize = 1;
+    fn zero() -> Self {
+        0.0
+    }
+    fn from_slice(v: &[f64]) -> Self {
+        v[0]
+    }
+}
+
+impl Amplitude for Vector3<f64> {
+    const COMPONENTS: usize = 3;
+    fn zero() -> Self {
+        Vector3::zeros()
+    }
+    fn from_slice(v: &[f64]) -> Self {
+        Vector3::new(v[0], v[1], v[2])
+    }
+}
+
+impl Amplitude for Vector6<f64> {
+    const COMPONENTS: usize = 6;
+    fn zero() -> Self {
+        Vector6::zeros()
+    }
+    fn from_slice(v: &[f64]) -> Self {
+        Vector6::from_column_slice(&v[..6])
+    }
+}
+
+/// A signal `f(t)`, selected in TOML by its `kind`:
+///
+/// ```toml
+/// [[zeeman]]
+/// kind = "sinc"
+/// amplitude = [0.0, 0.01, 0.0]
+/// cutoff = 50e9
+/// t0 = 1e-10
+/// ```
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(tag = "kind", rename_all = "lowercase", deny_unknown_fields)]
+pub enum Waveform<T> {
+    Constant {
+        value: T,
+    },
+    /// `A sin(2π f t + φ)`
+    Sine {
+        amplitude: T,
+        /// Hz
+        frequency: f64,
+        /// rad
+        #[serde(default)]
+        phase: f64,
+    },
+    /// `A sinc(2π f_c (t - t0))`, a band-limited pulse exciting everything
+    /// below the cutoff frequency
+    Sinc {
+        amplitude: T,
+        /// Hz
+        cutoff: f64,
+        /// s
+        #[serde(default)]
+        t0: f64,
+    },
+    /// `A exp(-(t - t0)² / 2σ²)`
+    Gaussian {
+        amplitude: T,
+        /// s
+        t0: f64,
+        /// s
+        sigma: f64,
+    },
+    /// Linear ramp from `from` at `start` to `to` at `end`, constant outside
+    Ramp {
+        from: T,
+        to: T,
+        /// s
+        start: f64,
+        /// s
+        end: f64,
+    },
+    /// Piecewise-linear interpolation between `(t, value)` samples, constant
+    /// before the first and after the last. The samples can instead be read
+    /// from a whitespace-, comma- or semicolon-separated `t value…` file,
+    /// such as the CSV export of an oscilloscope, see [`read_columns`].
+    Table {
+        #[serde(default)]
+        points: Vec<(f64, T)>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        file: Option<PathBuf>,
+        /// columns of `file` holding t and the components, by index from 0
+        /// or header name; all of them in order when empty
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        columns: Vec<Column>,
+        /// factor turning the times of `file` into s, e.g. 1e-9 for ns
+        #[serde(default = "one", skip_serializing_if = "is_one")]
+        time_scale: f64,
+        /// factor on the values of `file`, e.g. 1e-3 for mT
+        #[serde(default = "one", skip_serializing_if = "is_one")]
+        scale: f64,
+    },
+}
+
+/// A column of a waveform file, by index from 0 or by its name in the
+/// header line
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum Column {
+    Index(usize),
+    Name(String),
+}
+
+fn one() -> f64 {
+    1.0
+}
+
+fn is_one(v: &f64) -> bool {
+    *v == 1.0
+}
+
+impl<T: Amplitude> Waveform<T> {
+    pub fn eval(&self, t: f64) -> T {
+        match self {
+            Self::Constant { value } => *value,
+            Self::Sine {
+                amplitude,
+                frequency,
+                phase,
+            } => *amplitude * (2.0 * PI * frequency * t + phase).sin(),
+            Self::Sinc {
+                amplitude,
+                cutoff,
+                t0,
+            } => {
+                let x = 2.0 * PI * cutoff * (t - t0);
+                *amplitude * if x == 0.0 { 1.0 } else { x.sin() / x }
+            }
+            Self::Gaussian {
+                amplitude,
+                t0,
+                sigma,
+            } => *amplitude * (-(t - t0).powi(2) / (2.0 * sigma * sigma)).exp(),
+            Self::Ramp {
+                from,
+                to,
+                start,
+                end,
+            } => {
+                let s = if end > start {
+                    ((t - start) / (end - start)).clamp(0.0, 1.0)
+                } else if t < *start {
+                    0.0
+                } else {
+                    1.0
+                };
+                *from + (*to - *from) * s
+            }
+            Self::Table { points, .. } => interpolate(points, t),
+        }
+    }
+
+    /// Read the samples of a file-backed table, resolving relative paths
+    /// against `dir`. Tables that already hold samples are left untouched.
+    pub fn load_file(&mut self, dir: &Path) -> Result<()> {
+        if let Self::Table {
+            points,
+            file: Some(file),
+            columns,
+            time_scale,
+            scale,
+        } = self
+            && points.is_empty()
+        {
+            *points = read_columns::<T>(&dir.join(file), columns)?
+                .into_iter()
+                .map(|(t, v)| (t * *time_scale, v * *scale))
+                .collect();
+        }
+        Ok(())
+    }
+
+    /// Check the parameters, naming the offending entry as `key`
+    pub fn validate(&self, key: &str) -> Result<()> {
+        let bad = |what: &str| -> Result<()> { Err(format!("`{key}`: {what}").into()) };
+        match self {
+            Self::Sine { frequency, .. } if !frequency.is_finite() => bad("bad frequency"),
+            Self::Sinc { cutoff, .. } if !(cutoff.is_finite() && *cutoff > 0.0) => {
+                bad("`cutoff` must be positive")
+            }
+            Self::Gaussian { sigma, .. } if !(sigma.is_finite() && *sigma > 0.0) => {
+                bad("`sigma` must be positive")
+            }
+            Self::Ramp { start, end, .. } if end < start => bad("`end` is before `start`"),
+            Self::Table {
+                points,
+                file,
+                columns,
+                time_scale,
+                scale,
+            } => {
+                if points.is_empty() && file.is_none() {
+                    return bad("table needs `points` or a `file`");
+                }
+                if !columns.is_empty() && columns.len() != 1 + T::COMPONENTS {
+                    return bad(&format!(
+                        "`columns` must name t and {} components, got {}",
+                        T::COMPONENTS,
+                        columns.len()
+                    ));
+                }
+                if !(time_scale.is_finite() && *time_scale > 0.0) {
+                    return bad("`time_scale` must be positive");
+                }
+                if !scale.is_finite() {
+                    return bad("`scale` must be finite");
+                }
+                if points.iter().any(|(t, _)| !t.is_finite()) {
+                    return bad("table times must be finite");
+                }
+                if points.windows(2).any(|w| w[1].0 < w[0].0) {
+                    return bad("table times must be increasing");
+                }
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+pub(crate) fn interpolate<T: Amplitude>(points: &[(f64, T)], t: f64) -> T {
+    let Some(first) = points.first() else {
+        return T::zero();
+    };
+    let last = points[points.len() - 1];
+    if t <= first.0 {
+        return first.1;
+    }
+    if t >= last.0 {
+        return last.1;
+    }
+    // first sample strictly after t
+    let k = points.partition_point(|(tk, _)| *tk <= t);
+    let (t0, v0) = points[k - 1];
+    let (t1, v1) = points[k];
+    v0 + (v1 - v0) * ((t - t0) / (t1 - t0))
+}
+
+/// Parse `t v…` rows; blank lines and lines starting with `#` are skipped
+pub(crate) fn read_table<T: Amplitude>(path: &Path) -> Result<Vec<(f64, T)>> {
+    read_columns(path, &[])
+}
+
+/// Parse the rows of `t v…` picked by `columns` from a file, or all of its
+/// columns in order if empty. Fields are separated by commas, else by
+/// semicolons, else by whitespace; blank lines and lines starting with `#`
+/// are skipped, and a first line that is not numeric names the columns.
+/// Every value must be finite and the times must not decrease.
+pub fn read_columns<T: Amplitude>(path: &Path, columns: &[Column]) -> Result<Vec<(f64, T)>> {
+    let text = fs::read_to_string(path)
+        .map_err(|e| format!("cannot read waveform {}: {e}", path.display()))?;
+    let at = |n: usize, e: String| format!("{}:{}: {e}", path.display(), n + 1);
+    let mut header: Option<Vec<String>> = None;
+    let mut picked: Option<Vec<usize>> = None;
+    let mut points = Vec::new();
+    let rows = text
+        .lines()
+        .map(|line| line.trim_start_matches('\u{feff}').trim())
+        .enumerate()
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'));
+    for (n, line) in rows {
+        let fields = split_fields(line);
+        if points.is_empty() && header.is_none() && fields.iter().any(|f| f.parse::<f64>().is_err())
+        {
+            let name = |f: &str| f.trim_matches('"').to_string();
+            header = Some(fields.iter().map(|f| name(f)).collect());
+            continue;
+        }
+        let picked = match &picked {
+            Some(picked) => picked,
+            None => picked.insert(if columns.is_empty() {
+                if fields.len() != 1 + T::COMPONENTS {
+                    return Err(at(
+                        n,
+                        format!(
+                            "expected {} columns, found {}",
+                            1 + T::COMPONENTS,
+                            fields.len()
+                        ),
+                    )
+                    .into());
+                }
+                (0..fields.len()).collect()
+            } else {
+                columns
+                    .iter()
+                    .map(|c| match c {
+                        Column::Index(i) => Ok(*i),
+                        Column::Name(name) => header
+                            .as_ref()
+                            .and_then(|h| h.iter().position(|h| h == name))
+                            .ok_or_else(|| format!("no column named {name:?}")),
+                    })
+                    .collect::<std::result::Result<_, _>>()
+                    .map_err(|e| at(n, e))?
+            }),
+        };
+        let values = picked
+            .iter()
+            .map(|&i| {
+                let field = fields
+                    .get(i)
+                    .ok_or_else(|| format!("no column {i} among {}", fields.len()))?;
+                match field.parse::<f64>() {
+                    Ok(v) if v.is_finite() => Ok(v),
+                    Ok(_) => Err(format!("{field:?} is not a finite number")),
+                    Err(e) => Err(format!("{field:?}: {e}")),
+                }
+            })
+            .collect::<std::result::Result<Vec<f64>, _>>()
+            .map_err(|e| at(n, e))?;
+        if values.len() != 1 + T::COMPONENTS {
+            return Err(at(
+                n,
+                format!(
+                    "expected {} columns, found {}",
+                    1 + T::COMPONENTS,
+                    values.len()
+                ),
+            )
+            .into());
+        }
+        if let Some(&(before, _)) = points.last()
+            && values[0] < before
+        {
+            let e = format!("time {} is before {before} of the row above", values[0]);
+            return Err(at(n, e).into());
+        }
+        points.push((values[0], T::from_slice(&values[1..])));
+    }
+    Ok(points)
+}
+
+/// Fields of a line of a waveform file
+fn split_fields(line: &str) -> Vec<&str> {
+    match [',', ';'].into_iter().find(|&c| line.contains(c)) {
+        Some(c) => line.split(c).map(str::trim).collect(),
+        None => line.split_whitespace().collect(),
+    }
+}
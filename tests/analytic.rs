@@ -0,0 +1,541 @@
+//! The field terms and time stepping against analytic solutions of the LLG
+//! equation: a macrospin precessing and relaxing in a constant field, spin
+//! waves on an exchange-coupled chain, the profile of a domain wall, the
+//! canting of the edges of a strip with DMI and the switching field of a
+//! Stoner–Wohlfarth particle.
+
+use nalgebra::Vector3;
+use std::f64::consts::PI;
+
+use nez::{
+    Config, Mesh, Params, Simulation,
+    config::SolverConfig,
+    hysteresis::hysteresis,
+    spin_pumping::{SpinPumping, SpinPumpingConfig},
+    stepper::Method,
+    torque::{HBAR, QE},
+    wall::Direction,
+};
+
+const GAMMA: f64 = 1.760859e11;
+const B: f64 = 0.1;
+/// Error of the state allowed after many steps, mostly rounding in single
+/// precision (cargo feature `f32`)
+const ROUNDING: f64 = if cfg!(feature = "f32") { 1e-5 } else { 1e-8 };
+
+/// A single spin at angle `theta0` from a field `B` along z
+fn macrospin(alpha: f64, theta0: f64) -> Simulation {
+    let params = Params {
+        gamma: GAMMA,
+        alpha,
+        dt: 1e-13,
+        h_ext: Vector3::new(0.0, 0.0, B),
+        ..Params::default()
+    };
+    let m0 = Vector3::new(theta0.sin(), 0.0, theta0.cos());
+    Simulation::new(Mesh::chain(1, 2.5e-9), params, m0)
+}
+
+#[test]
+fn macrospin_precesses_at_the_larmor_frequency() {
+    let mut sim = macrospin(0.0, 0.5);
+    // times at which mx changes sign downwards, one per period
+    let mut crossings = Vec::new();
+    let mut last = sim.m.get(0).x;
+    while crossings.len() < 4 {
+        sim.step();
+        let mx = sim.m.get(0).x;
+        if last > 0.0 && mx <= 0.0 {
+            // interpolated between the two steps
+            crossings.push(sim.t - sim.dt * mx / (mx - last));
+        }
+        last = mx;
+    }
+    let period = (crossings[3] - crossings[0]) / 3.0;
+    let larmor = GAMMA * B / (2.0 * PI);
+    let f = 1.0 / period;
+    assert!(
+        (f - larmor).abs() < 1e-6 * larmor,
+        "f = {f:e} Hz, expected {larmor:e} Hz"
+    );
+    // without damping the cone angle stays
+    let tolerance = if cfg!(feature = "f32") {
+        ROUNDING
+    } else {
+        1e-9
+    };
+    assert!((sim.m.get(0).z - 0.5f64.cos()).abs() < tolerance);
+}
+
+#[test]
+fn macrospin_follows_the_damped_solution() {
+    let (alpha, theta0) = (0.1, 1.2);
+    let mut sim = macrospin(alpha, theta0);
+    let rate = GAMMA * B / (1.0 + alpha * alpha);
+    for _ in 0..10 {
+        sim.run(1_000).unwrap();
+        let t = sim.t;
+        let theta = 2.0 * ((theta0 / 2.0).tan() * (-alpha * rate * t).exp()).atan();
+        let phi = rate * t;
+        let expected = Vector3::new(
+            theta.sin() * phi.cos(),
+            theta.sin() * phi.sin(),
+            theta.cos(),
+        );
+        let m = sim.m.get(0);
+        assert!(
+            (m - expected).amax() < ROUNDING,
+            "m = {m:?} at t = {t:e} s, expected {expected:?}"
+        );
+    }
+}
+
+#[test]
+fn macrospin_torque_and_dmdt_diagnostics() {
+    let (alpha, theta0) = (0.1, 1.2);
+    let sim = macrospin(alpha, theta0);
+    let torque = B * theta0.sin();
+    let dmdt = GAMMA * torque / (1.0 + alpha * alpha).sqrt();
+    assert!((sim.max_torque() - torque).abs() < ROUNDING * torque);
+    assert!((sim.max_dmdt() - dmdt).abs() < ROUNDING * dmdt);
+    let values = sim.observables();
+    let column = |name: &str| values.iter().find(|(k, _)| k == name).unwrap().1;
+    assert_eq!(column("max_torque"), sim.max_torque());
+    assert_eq!(column("max_dmdt"), sim.max_dmdt());
+}
+
+#[test]
+fn precessing_macrospin_pumps_a_spin_current_along_the_field() {
+    // m × dm/dt = γ/(1+α²) [B - (m·B) m + α m × B], along B on average
+    let (alpha, theta0) = (0.1, 0.3);
+    let mut sim = macrospin(alpha, theta0);
+    sim.params.h_ext = Vector3::new(B, 0.0, 0.0);
+    sim.m = nez::VectorField::uniform(1, Vector3::new(theta0.cos(), theta0.sin(), 0.0));
+    let config = SpinPumpingConfig {
+        direction: Direction::Y,
+        ..Default::default()
+    };
+    sim.spin_pumping = Some(SpinPumping::new(&config, &sim.mesh, &sim.params).unwrap());
+    let values = sim.observables();
+    let column = |name: &str| values.iter().find(|(k, _)| k == name).unwrap().1;
+    let js =
+        HBAR / (4.0 * PI) * config.g_mix * GAMMA * B * theta0.sin().powi(2) / (1.0 + alpha * alpha);
+    assert!((column("spin_current_x") - js).abs() < ROUNDING * js);
+    // spins along x flowing down, -z, drive a charge current along -y
+    let (t, l) = (config.thickness, config.diffusion_length);
+    let jc = config.theta_sh * 2.0 * QE / HBAR * l / t * (t / (2.0 * l)).tanh() * js;
+    let v = -config.resistivity * 2.5e-9 * jc;
+    assert!((column("ishe_voltage") - v).abs() < ROUNDING * v.abs());
+}
+
+#[test]
+fn junction_resistance_follows_the_angle_to_the_polarizer() {
+    // G(θ) = (G_P + G_AP)/2 + (G_P - G_AP)/2 cos θ over the ellipse of the preset
+    let config = Config::parse(nez::presets::get("mtj").unwrap()).unwrap();
+    let mut sim = Simulation::build(&config).unwrap();
+    let (ra, tmr) = (1e-11, 1.0);
+    let cells = (0..sim.mesh.len())
+        .filter(|&i| sim.params.mu0_ms_at(i) > 0.0)
+        .count();
+    let r_p = ra / (cells as f64 * 2.5e-9 * 2.5e-9);
+    for (m, r) in [
+        (Vector3::x(), r_p),
+        (-Vector3::x(), r_p * (1.0 + tmr)),
+        (Vector3::y(), 2.0 / (1.0 / r_p + 1.0 / (r_p * (1.0 + tmr)))),
+    ] {
+        sim.m = nez::VectorField::uniform(sim.mesh.len(), m);
+        sim.params
+            .geometry
+            .as_ref()
+            .unwrap()
+            .clear_outside(&mut sim.m);
+        let values = sim.observables();
+        let column = |name: &str| values.iter().find(|(k, _)| k == name).unwrap().1;
+        assert!((column("resistance") - r).abs() < 1e-12 * r, "{m:?}");
+        assert!((column("magnetoresistance") - (r / r_p - 1.0)).abs() < 1e-12);
+        assert_eq!(column("current_density"), 0.0);
+    }
+}
+
+#[test]
+fn low_storage_rk4_follows_the_damped_solution() {
+    let (alpha, theta0) = (0.1, 1.2);
+    let mut sim = macrospin(alpha, theta0);
+    sim.set_solver(SolverConfig {
+        method: Method::Lsrk4,
+        ..SolverConfig::default()
+    });
+    let rate = GAMMA * B / (1.0 + alpha * alpha);
+    sim.run(10_000).unwrap();
+    let t = sim.t;
+    let theta = 2.0 * ((theta0 / 2.0).tan() * (-alpha * rate * t).exp()).atan();
+    let phi = rate * t;
+    let expected = Vector3::new(
+        theta.sin() * phi.cos(),
+        theta.sin() * phi.sin(),
+        theta.cos(),
+    );
+    let m = sim.m.get(0);
+    assert!(
+        (m - expected).amax() < ROUNDING,
+        "m = {m:?} at t = {t:e} s, expected {expected:?}"
+    );
+}
+
+/// The damped solution of [`macrospin`] at time `t`
+fn damped(alpha: f64, theta0: f64, t: f64) -> Vector3<f64> {
+    let rate = GAMMA * B / (1.0 + alpha * alpha);
+    let theta = 2.0 * ((theta0 / 2.0).tan() * (-alpha * rate * t).exp()).atan();
+    let phi = rate * t;
+    Vector3::new(
+        theta.sin() * phi.cos(),
+        theta.sin() * phi.sin(),
+        theta.cos(),
+    )
+}
+
+#[test]
+fn adaptive_steps_follow_the_damped_solution_within_the_tolerance() {
+    let (alpha, theta0) = (0.1, 1.2);
+    for method in [Method::Rk23, Method::Rk45] {
+        let tolerance = 1e-6;
+        let mut sim = macrospin(alpha, theta0);
+        sim.set_solver(SolverConfig {
+            method,
+            tolerance,
+            ..SolverConfig::default()
+        });
+        // a first step of a turn and a half is rejected and retried shorter
+        sim.dt = 1.5 * 2.0 * PI / (GAMMA * B);
+        sim.step();
+        assert!(
+            sim.t < 1.5 * 2.0 * PI / (GAMMA * B),
+            "{method:?}: no step rejected"
+        );
+        let t_end = 1e-9;
+        assert!(sim.run_until(t_end, u64::MAX).unwrap());
+        assert!(
+            (sim.t - t_end).abs() < 1e-20,
+            "{method:?}: t = {:e} s",
+            sim.t
+        );
+        let expected = damped(alpha, theta0, sim.t);
+        let m = sim.m.get(0);
+        // the local errors, one per step, add up at worst
+        let bound = (sim.step as f64 * tolerance).max(ROUNDING);
+        assert!(
+            (m - expected).amax() < bound,
+            "{method:?}: m = {m:?} after {} steps, expected {expected:?}",
+            sim.step
+        );
+    }
+}
+
+#[test]
+fn adaptive_steps_stay_between_dt_min_and_dt_max() {
+    let (alpha, theta0) = (0.1, 1.2);
+    // a loose tolerance would take steps far beyond 10 fs
+    let dt_max = 1e-14;
+    let mut sim = macrospin(alpha, theta0);
+    sim.set_solver(SolverConfig {
+        method: Method::Rk45,
+        tolerance: 1e-3,
+        dt_max: Some(dt_max),
+        ..SolverConfig::default()
+    });
+    // starting from params.dt, ten times longer
+    sim.run(100).unwrap();
+    assert!(sim.dt <= dt_max);
+    assert!(
+        (sim.t - 100.0 * dt_max).abs() < 1e-3 * sim.t,
+        "t = {:e} s",
+        sim.t
+    );
+    // a step no shorter than dt_min is accepted whatever its error
+    let too_long = 2.0 / (GAMMA * B);
+    let mut sim = macrospin(alpha, theta0);
+    sim.set_solver(SolverConfig {
+        method: Method::Rk23,
+        tolerance: 1e-12,
+        dt_min: too_long,
+        ..SolverConfig::default()
+    });
+    sim.dt = too_long;
+    sim.step();
+    assert_eq!(sim.t, too_long);
+    assert_eq!(sim.dt, too_long);
+}
+
+#[test]
+fn implicit_midpoint_turns_by_the_cayley_angle_at_large_steps() {
+    let theta0: f64 = 0.5;
+    let mut sim = macrospin(0.0, theta0);
+    sim.set_solver(SolverConfig {
+        method: Method::Implicit,
+        ..SolverConfig::default()
+    });
+    // eight steps per turn, far beyond the stability of rk4
+    let omega = GAMMA * B;
+    sim.params.dt = 2.0 * PI / (8.0 * omega);
+    sim.dt = sim.params.dt;
+    let steps = 100;
+    let mut phase = 0.0;
+    let mut last = sim.m.get(0);
+    for _ in 0..steps {
+        sim.step();
+        let m = sim.m.get(0);
+        phase += (m.y.atan2(m.x) - last.y.atan2(last.x)).rem_euclid(2.0 * PI);
+        last = m;
+    }
+    // the midpoint rule turns a uniform precession by 2 atan(ω dt / 2)
+    let expected = steps as f64 * 2.0 * (0.5 * omega * sim.dt).atan();
+    assert!(
+        (phase - expected).abs() < ROUNDING * expected,
+        "turned by {phase} rad, expected {expected} rad"
+    );
+    assert!((sim.m.get(0).z - theta0.cos()).abs() < ROUNDING);
+}
+
+#[test]
+fn implicit_midpoint_relaxes_with_steps_beyond_explicit_stability() {
+    let mut sim = macrospin(1.0, 3.0);
+    sim.set_solver(SolverConfig {
+        method: Method::Implicit,
+        ..SolverConfig::default()
+    });
+    // rk4 diverges beyond γ B dt ≈ 2.8
+    sim.params.dt = 5.0 / (GAMMA * B);
+    sim.dt = sim.params.dt;
+    sim.run(100).unwrap();
+    let m = sim.m.get(0);
+    assert!((m - Vector3::z()).amax() < ROUNDING, "m = {m:?}");
+}
+
+#[test]
+fn chain_spin_waves_follow_the_exchange_dispersion() {
+    let (n, dx) = (64, 2.5e-9);
+    let mesh = Mesh::chain(n, dx).with_pbc([1, 0, 0]);
+    let params = Params {
+        gamma: GAMMA,
+        alpha: 0.0,
+        a_ex: 1.3e-11,
+        mu0_ms: 1.0,
+        h_ext: Vector3::new(0.0, 0.0, B),
+        ..Params::default()
+    };
+    let ms = params.mu0_ms / (4e-7 * PI);
+    let cone: f64 = 0.01;
+    for waves in [0, 1, 4, 16, 32] {
+        let k = 2.0 * PI * waves as f64 / (n as f64 * dx);
+        // the discrete Laplacian of the helix, 2 (cos k dx - 1) / dx² m⊥
+        let stiffness = 2.0 * params.a_ex / ms * 2.0 * (1.0 - (k * dx).cos()) / (dx * dx);
+        let omega = GAMMA * (B + stiffness * (1.0 - cone * cone).sqrt());
+        // a hundred steps per radian
+        let dt = 0.01 / omega;
+        let mut sim = Simulation::new(
+            mesh,
+            Params {
+                dt,
+                ..params.clone()
+            },
+            Vector3::z(),
+        );
+        // a helix of constant cone angle is an exact mode of the chain
+        for i in 0..n {
+            let phase = k * (i as f64 + 0.5) * dx;
+            let m = Vector3::new(
+                cone * phase.cos(),
+                cone * phase.sin(),
+                (1.0 - cone * cone).sqrt(),
+            );
+            sim.m.set(i, m);
+        }
+        let phase0 = sim.m.get(0).y.atan2(sim.m.get(0).x);
+        // a quarter of a turn, well within one
+        sim.run(157).unwrap();
+        let m = sim.m.get(0);
+        let turned = (m.y.atan2(m.x) - phase0).rem_euclid(2.0 * PI);
+        let measured = turned / sim.t;
+        assert!(
+            (measured - omega).abs() < 1e-6 * omega,
+            "k = {k:e} rad/m: ω = {measured:e} rad/s, expected {omega:e} rad/s"
+        );
+    }
+}
+
+#[test]
+fn neel_wall_texture_of_the_exchange_length_is_at_rest() {
+    // θ = 2 atan(exp(x / Δ)) balances exchange and anisotropy for Δ = √(A/K)
+    let (a_ex, k1): (f64, f64) = (1.3e-11, 5e5);
+    let wall = |width: f64| {
+        let config = Config::parse(&format!(
+            r#"
+            [mesh]
+            nx = 200
+            dx = 1e-9
+            [params]
+            a_ex = {a_ex}
+            h_ext = [0.0, 0.0, 0.0]
+            [uniaxial]
+            k1 = {k1}
+            axis = [0.0, 0.0, 1.0]
+            [initial]
+            texture = {{ kind = "neel", width = {width} }}
+            "#
+        ))
+        .unwrap();
+        Simulation::build(&config).unwrap()
+    };
+    let delta = (a_ex / k1).sqrt();
+    let sim = wall(delta);
+    let mz = |i: usize| sim.m.get(i).z;
+    assert!(mz(0) > 0.999 && mz(199) < -0.999);
+    assert!(mz(99) > 0.0 && mz(100) < 0.0);
+    let (at_rest, off) = (sim.max_torque(), wall(2.0 * delta).max_torque());
+    assert!(at_rest < 0.05 * off, "{at_rest:e} T against {off:e} T");
+
+    // the same wall written out as expressions
+    let config = Config::parse(&format!(
+        r#"
+        [mesh]
+        nx = 200
+        dx = 1e-9
+        [initial]
+        texture = {{ kind = "expr", mx = "1 / cosh((x - 100e-9) / {delta})", mz = "-tanh((x - 100e-9) / {delta})" }}
+        "#
+    ))
+    .unwrap();
+    let expr = Simulation::build(&config).unwrap();
+    for i in 0..200 {
+        assert!((expr.m.get(i) - sim.m.get(i)).norm() < 1e-9, "cell {i}");
+    }
+}
+
+#[test]
+fn exchange_scale_multiplies_the_energy_across_the_interface() {
+    // two antiparallel cells, one per region: E = 2 A s (|Δm| / dz)² V / 2
+    let energy = |scale: f64| {
+        let config = Config::parse(&format!(
+            r#"
+            [mesh]
+            nx = 1
+            nz = 2
+            dx = 1e-9
+            dy = 1e-9
+            dz = 1e-9
+            [params]
+            a_ex = 1e-11
+            [[regions]]
+            shape = {{ kind = "layers", first = 1, last = 1 }}
+            [[exchange_scale]]
+            regions = [0, 1]
+            scale = {scale}
+            [initial]
+            texture = {{ kind = "expr", mx = "1 - 2 * step(z - 1e-9)" }}
+            "#
+        ))
+        .unwrap();
+        let sim = Simulation::build(&config).unwrap();
+        let exchange = sim.energies().into_iter().find(|(k, _)| *k == "exchange");
+        exchange.unwrap().1
+    };
+    let (dz, volume) = (1e-9, 1e-27);
+    let full = energy(1.0);
+    assert!((full - 1e-11 * 4.0 / (dz * dz) * volume).abs() < 1e-6 * full);
+    assert!((energy(-0.5) + 0.5 * full).abs() < 1e-9 * full);
+}
+
+#[test]
+fn dmi_cants_the_edges_of_a_strip_by_the_rado_weertman_angle() {
+    // at a free edge 2A θ' = D, and in the bulk A θ'² = K sin²θ, so the edge
+    // tilts by sin θ₀ = D / (2√(AK)) and tan(θ/2) decays as exp(-x/Δ), in
+    // the xz plane with interfacial DMI and the yz plane with bulk DMI; the
+    // periodic y and z keep the strip from canting at its other surfaces
+    let (a_ex, k1, d, dx): (f64, f64, f64, f64) = (1.3e-11, 5e5, 1e-3, 0.25e-9);
+    for kind in ["interfacial", "bulk"] {
+        let config = Config::parse(&format!(
+            r#"
+            [mesh]
+            nx = 200
+            dx = {dx}
+            pbc = [0, 1, 1]
+            [params]
+            a_ex = {a_ex}
+            h_ext = [0.0, 0.0, 0.0]
+            [uniaxial]
+            k1 = {k1}
+            axis = [0.0, 0.0, 1.0]
+            [dmi]
+            d = {d}
+            kind = "{kind}"
+            [initial]
+            m = [0.0, 0.0, 1.0]
+            "#
+        ))
+        .unwrap();
+        let mut sim = Simulation::build(&config).unwrap();
+        assert!(sim.minimize(&Default::default()).converged);
+        let theta0 = (d / (2.0 * (a_ex * k1).sqrt())).asin();
+        let theta = 2.0 * ((theta0 / 2.0).tan() * (-0.5 * dx / (a_ex / k1).sqrt()).exp()).atan();
+        for i in [0, 199] {
+            let m = sim.m.get(i);
+            let tilt = m.x.hypot(m.y).asin();
+            assert!(
+                (tilt - theta).abs() < 0.01 * theta,
+                "{kind}: {tilt} rad against {theta} rad"
+            );
+            let plane = if kind == "bulk" { m.x } else { m.y };
+            assert!(plane.abs() < 1e-3 * theta, "{kind}: m = {m:?}");
+        }
+    }
+}
+
+#[test]
+fn macrospin_switches_at_the_stoner_wohlfarth_field() {
+    // a field at ψ to the easy axis reverses a uniaxial macrospin at
+    // B_sw = B_K / (cos^⅔ψ + sin^⅔ψ)^{3/2}, B_K = 2K/Mₛ = 0.1 T here; ψ is
+    // kept small so that the sweep does not balance on the hard direction
+    let (k1, psi) = (4e4, 0.1f64.to_radians());
+    let config = Config::parse(&format!(
+        r#"
+        [mesh]
+        nx = 1
+        [params]
+        h_ext = [0.0, 0.0, 0.0]
+        [uniaxial]
+        k1 = {k1}
+        axis = [0.0, 0.0, 1.0]
+        [initial]
+        m = [0.0, 0.0, 1.0]
+        [hysteresis]
+        direction = [{}, 0.0, {}]
+        max = 0.2
+        min = -0.2
+        steps = 400
+        "#,
+        psi.sin(),
+        psi.cos()
+    ))
+    .unwrap();
+    let mut sim = Simulation::build(&config).unwrap();
+    let b_k = 2.0 * k1 / sim.params.ms();
+    let b_sw = b_k / (psi.cos().powf(2.0 / 3.0) + psi.sin().powf(2.0 / 3.0)).powf(1.5);
+    let points = hysteresis(&mut sim, &config, |_, _| Ok(())).unwrap();
+    assert!(points.iter().all(|p| p.converged));
+    // down from +0.2 T, then back up, in steps of 1 mT
+    let (down, up) = points.split_at(401);
+    let reversed = down.iter().find(|p| p.projection < 0.0).unwrap();
+    assert!(
+        (reversed.field + b_sw).abs() <= 1e-3 + 1e-9,
+        "down at {} T, expected -{b_sw} T",
+        reversed.field
+    );
+    let restored = up.iter().find(|p| p.projection > 0.0).unwrap();
+    assert!(
+        (restored.field - b_sw).abs() <= 1e-3 + 1e-9,
+        "up at {} T, expected {b_sw} T",
+        restored.field
+    );
+    // and h_ext is given back
+    assert_eq!(sim.params.h_ext, Vector3::zeros());
+}
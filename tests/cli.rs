@@ -0,0 +1,338 @@
+//! The `nez` command line, run as a batch job would run it.
+
+use std::{fs, path::Path, process::Command};
+
+use nez::ZarrOutput;
+
+#[test]
+fn quiet_run_out_of_walltime_tells_how_to_resume() {
+    let dir = std::env::temp_dir().join(format!("nez-cli-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let config = dir.join("long.toml");
+    let store = dir.join("long.zarr");
+    fs::write(
+        &config,
+        r#"
+        [mesh]
+        nx = 16
+        [initial]
+        m = [1.0, 0.2, 0.0]
+        [run]
+        steps = 1000000000
+        "#,
+    )
+    .unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_nez"))
+        .arg("-q")
+        .arg("run")
+        .arg(&config)
+        .arg("-o")
+        .arg(&store)
+        .args(["--walltime", "1"])
+        .output()
+        .unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(!output.status.success(), "{stderr}");
+    let resume = format!(
+        "# continue with: nez resume {} -n 1000000000 --walltime 1",
+        store.display()
+    );
+    assert!(stderr.contains(&resume), "{stderr}");
+}
+
+#[test]
+fn quiet_silences_the_rows_of_every_command() {
+    let dir = std::env::temp_dir().join(format!("nez-cli-quiet-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let config = dir.join("loop.toml");
+    fs::write(
+        &config,
+        format!(
+            r#"
+            [mesh]
+            nx = 4
+            [initial]
+            m = [0.0, 0.0, 1.0]
+            [hysteresis]
+            steps = 2
+            table = "{}"
+            [monte_carlo]
+            temperatures = [10.0]
+            equilibration = 2
+            sweeps = 2
+            "#,
+            dir.join("loop.tsv").display()
+        ),
+    )
+    .unwrap();
+    for command in ["hysteresis", "monte-carlo"] {
+        let run = |quiet: bool| {
+            let mut nez = Command::new(env!("CARGO_BIN_EXE_nez"));
+            if quiet {
+                nez.arg("-q");
+            }
+            let store = dir.join(format!("{command}-{quiet}.zarr"));
+            let output = nez
+                .arg(command)
+                .arg(&config)
+                .arg("-o")
+                .arg(&store)
+                .output()
+                .unwrap();
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            assert!(output.status.success(), "{command}: {stderr}");
+            String::from_utf8(output.stdout).unwrap()
+        };
+        let rows = run(false);
+        assert!(rows.lines().count() > 1, "{command}: {rows}");
+        assert_eq!(run(true), "", "{command}");
+    }
+    // the table of the loop is written all the same
+    let table = fs::read_to_string(dir.join("loop.tsv")).unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(table.lines().count(), 6, "{table}");
+}
+
+/// Attributes of the root group of the store at `path`
+fn attributes(path: &Path) -> serde_json::Value {
+    let json = fs::read_to_string(path.join("zarr.json")).unwrap();
+    serde_json::from_str::<serde_json::Value>(&json).unwrap()["attributes"].clone()
+}
+
+#[test]
+fn sweep_runs_every_case_into_its_own_group() {
+    let dir = std::env::temp_dir().join(format!("nez-cli-sweep-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let config = dir.join("sweep.toml");
+    let store = dir.join("sweep.zarr");
+    fs::write(
+        &config,
+        r#"
+        [mesh]
+        nx = 4
+        [initial]
+        m = [1.0, 0.2, 0.0]
+        [run]
+        steps = 20
+        [output]
+        every = 10
+        [sweep]
+        parallel = 2
+        [[sweep.parameters]]
+        key = "params.alpha"
+        values = [0.01, 0.5]
+        "#,
+    )
+    .unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_nez"))
+        .arg("-q")
+        .arg("sweep")
+        .arg(&config)
+        .arg("-o")
+        .arg(&store)
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success(), "{stderr}");
+    let root = attributes(&store);
+    assert_eq!(root["parameters"], serde_json::json!(["params.alpha"]));
+    assert_eq!(root["cases"], serde_json::json!(["case_0000", "case_0001"]));
+    assert_eq!(root["values"], serde_json::json!([[0.01], [0.5]]));
+    assert!(store.join("index").join("zarr.json").exists());
+    // every case with its own value, run to the end in its own group
+    let cases: Vec<_> = ["case_0000", "case_0001"]
+        .iter()
+        .map(|name| ZarrOutput::open(store.join(name)).unwrap())
+        .collect();
+    for (case, alpha) in cases.iter().zip([0.01, 0.5]) {
+        assert_eq!(case.config().unwrap().params.alpha, alpha);
+        assert_eq!(case.frames().unwrap(), 3);
+    }
+    let last = |case: &ZarrOutput| case.read(2).unwrap().get(0);
+    let (slow, fast) = (last(&cases[0]), last(&cases[1]));
+    fs::remove_dir_all(&dir).unwrap();
+    assert_ne!(slow, fast);
+}
+
+#[test]
+fn ensemble_runs_every_seed_into_its_own_group() {
+    let dir = std::env::temp_dir().join(format!("nez-cli-ensemble-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let config = dir.join("ensemble.toml");
+    let store = dir.join("ensemble.zarr");
+    fs::write(
+        &config,
+        r#"
+        [mesh]
+        nx = 4
+        [params]
+        dt = 1e-13
+        [solver]
+        method = "heun"
+        [initial]
+        m = [1.0, 0.2, 0.0]
+        [run]
+        steps = 20
+        [output]
+        every = 10
+        [table]
+        zarr = true
+        [langevin]
+        temperature = 300.0
+        seed = 5
+        [ensemble]
+        realizations = 2
+        parallel = 2
+        "#,
+    )
+    .unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_nez"))
+        .arg("-q")
+        .arg("ensemble")
+        .arg(&config)
+        .arg("-o")
+        .arg(&store)
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success(), "{stderr}");
+    let root = attributes(&store);
+    assert_eq!(root["realizations"], 2);
+    assert_eq!(root["seeds"], serde_json::json!([5, 6]));
+    assert_eq!(root["stopped_fraction"], 0.0);
+    for statistic in ["mean", "std", "count", "final"] {
+        assert!(
+            store.join(statistic).join("zarr.json").exists(),
+            "{statistic}"
+        );
+    }
+    // every realization with its own seed and table, in its own group
+    let runs: Vec<_> = ["run_0000", "run_0001"]
+        .iter()
+        .map(|name| ZarrOutput::open(store.join(name)).unwrap())
+        .collect();
+    for (run, seed) in runs.iter().zip([5, 6]) {
+        assert_eq!(run.config().unwrap().langevin.unwrap().seed, seed);
+        assert_eq!(run.frames().unwrap(), 3);
+        assert!(run.table_rows() > 0);
+    }
+    let last = |run: &ZarrOutput| run.read(2).unwrap().get(0);
+    let (first, second) = (last(&runs[0]), last(&runs[1]));
+    fs::remove_dir_all(&dir).unwrap();
+    // and so its own noise
+    assert_ne!(first, second);
+}
+
+#[test]
+fn dry_run_estimates_without_writing_anything() {
+    let dir = std::env::temp_dir().join(format!("nez-cli-dry-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let config = dir.join("dry.toml");
+    let store = dir.join("dry.zarr");
+    fs::write(
+        &config,
+        format!(
+            r#"
+            [mesh]
+            nx = 16
+            ny = 8
+            [run]
+            steps = 100
+            [output]
+            path = "{}"
+            every = 10
+            log = true
+            [output.snapshots]
+            every = 50
+            "#,
+            store.display()
+        ),
+    )
+    .unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_nez"))
+        .arg("run")
+        .arg(&config)
+        .arg("--dry-run")
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    // 128 cells of 3 f64 a frame, 11 frames
+    for line in [
+        "config: valid",
+        "mesh:   16 x 8 x 1 = 128 cells",
+        "store:  11 frames of 3.0 KiB",
+        "files:  3 ovf snapshots",
+        "steps/s",
+    ] {
+        assert!(stdout.contains(line), "no {line:?} in\n{stdout}");
+    }
+    // nor store, snapshots or log
+    let left: Vec<_> = fs::read_dir(&dir)
+        .unwrap()
+        .map(|e| e.unwrap().file_name())
+        .collect();
+    fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(left, ["dry.toml"]);
+}
+
+#[test]
+fn profile_times_every_registered_term() {
+    let dir = std::env::temp_dir().join(format!("nez-cli-profile-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let config = dir.join("profile.toml");
+    fs::write(
+        &config,
+        format!(
+            r#"
+            [mesh]
+            nx = 16
+            ny = 16
+            [run]
+            steps = 20
+            [output]
+            path = "{}"
+            every = 10
+            [demag]
+            [dmi]
+            d = 1e-3
+            [cubic]
+            k1 = 1e4
+            "#,
+            dir.join("profile.zarr").display()
+        ),
+    )
+    .unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_nez"))
+        .args(["-q", "--profile", "run"])
+        .arg(&config)
+        .output()
+        .unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success(), "{stderr}");
+    let report = &stderr[stderr.find("# profile of").expect(&stderr)..];
+    // every term, stepped and written, under its section
+    let (fields, rest) = report.split_once("# solver").expect(report);
+    let (solver, written) = rest.split_once("# output").expect(report);
+    for term in ["demag", "dmi", "cubic"] {
+        let row = fields
+            .lines()
+            .find(|l| l.trim_start_matches("#   ").starts_with(term));
+        let calls: u64 = row
+            .unwrap_or_else(|| panic!("no {term} in\n{report}"))
+            .split_whitespace()
+            .nth_back(1)
+            .unwrap()
+            .parse()
+            .unwrap();
+        assert!(calls >= 20, "{term} called {calls} times");
+    }
+    assert!(solver.contains("step"), "{report}");
+    assert!(written.contains("frame of m"), "{report}");
+}
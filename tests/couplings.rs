@@ -0,0 +1,287 @@
+//! Interactions beyond those of a single ferromagnet against closed forms:
+//! the resonance of an antiferromagnet, the interlayer exchange, the
+//! anisotropy of a strained crystal, the field of point dipoles and the
+//! Oersted field of a current sheet.
+
+use nalgebra::Vector3;
+use std::f64::consts::PI;
+
+use nez::{
+    Config, Mesh, Params, Simulation, VectorField,
+    field::{Dipolar, FieldTerm, Magnetoelastic, Oersted, Rkky, UniaxialAnisotropy},
+};
+
+const GAMMA: f64 = 1.760859e11;
+
+/// Times at which `value` of `sim` changes sign downwards, `count` of them
+fn crossings(sim: &mut Simulation, count: usize, value: impl Fn(&Simulation) -> f64) -> Vec<f64> {
+    let mut crossings = Vec::new();
+    let mut last = value(sim);
+    while crossings.len() < count {
+        sim.step();
+        let v = value(sim);
+        if last > 0.0 && v <= 0.0 {
+            crossings.push(sim.t - sim.dt * v / (v - last));
+        }
+        last = v;
+    }
+    crossings
+}
+
+#[test]
+fn antiferromagnet_resonates_at_the_exchange_enhanced_frequency() {
+    // ω = γ √(B_A (2 B_E + B_A)), with B_A = 2K/Mₛ and B_E = |J|/Mₛ
+    let (k1, j, mu0_ms) = (1e5, -1e7, 1.0);
+    let config = Config::parse(&format!(
+        r#"
+        [mesh]
+        nx = 1
+        [params]
+        gamma = {GAMMA}
+        alpha = 0.0
+        mu0_ms = {mu0_ms}
+        dt = 1e-15
+        h_ext = [0.0, 0.0, 0.0]
+        [uniaxial]
+        k1 = {k1}
+        axis = [0.0, 0.0, 1.0]
+        [sublattice]
+        gamma = {GAMMA}
+        alpha = 0.0
+        mu0_ms = {mu0_ms}
+        exchange = {j}
+        [initial]
+        m = [0.01, 0.0, 1.0]
+        "#
+    ))
+    .unwrap();
+    let mut sim = Simulation::build(&config).unwrap();
+    // the second sublattice antiparallel to the first
+    assert_eq!(sim.m.len(), 2);
+    assert_eq!(sim.m.get(1), -sim.m.get(0));
+    let crossings = crossings(&mut sim, 4, |sim| sim.m.get(0).x);
+    let f = 3.0 / (crossings[3] - crossings[0]);
+    let ms = mu0_ms / nez::params::MU0;
+    let (b_a, b_e) = (2.0 * k1 / ms, -j / ms);
+    let expected = GAMMA * (b_a * (2.0 * b_e + b_a)).sqrt() / (2.0 * PI);
+    assert!(
+        (f - expected).abs() < 1e-3 * expected,
+        "f = {f:e} Hz, expected {expected:e} Hz"
+    );
+    // the sublattices stay nearly antiparallel
+    let net = sim.m.get(0) + sim.m.get(1);
+    assert!(net.norm() < 0.02, "m₁ + m₂ = {net:?}");
+}
+
+#[test]
+fn interlayer_exchange_couples_facing_layers_per_unit_area() {
+    // E = -(J1 m₁·m₂ + J2 (m₁·m₂)²) A over the area A of the layers, and
+    // B₁ = (J1 + 2 J2 m₁·m₂) m₂ / (Mₛ dz) on the first
+    let (j1, j2, mu0_ms) = (-1e-3, -2e-4, 1.0);
+    let config = Config::parse(&format!(
+        r#"
+        [mesh]
+        nx = 4
+        ny = 2
+        nz = 3
+        dx = 2e-9
+        dy = 3e-9
+        dz = 1e-9
+        [params]
+        mu0_ms = {mu0_ms}
+        [[rkky]]
+        layers = [0, 2]
+        j1 = {j1}
+        j2 = {j2}
+        "#
+    ))
+    .unwrap();
+    let mut sim = Simulation::build(&config).unwrap();
+    let rkky = sim.terms.get::<Rkky>().unwrap();
+    let area = 4.0 * 2e-9 * 2.0 * 3e-9;
+    let ms = mu0_ms / nez::params::MU0;
+    for phi in [0.0, 1.0, PI / 2.0, 2.5, PI] {
+        let m2 = Vector3::new(phi.cos(), phi.sin(), 0.0);
+        // the middle layer is not coupled
+        let m = VectorField::from_fn(sim.mesh.len(), |i| match sim.mesh.coords(i)[2] {
+            0 => Vector3::x(),
+            1 => Vector3::z(),
+            _ => m2,
+        });
+        let d = phi.cos();
+        let energy = rkky.energy(&m, 0.0, &sim.mesh, &sim.params);
+        let expected = -(j1 * d + j2 * d * d) * area;
+        assert!(
+            (energy - expected).abs() < 1e-12 * area * j1.abs(),
+            "E = {energy:e} J at {phi} rad, expected {expected:e} J"
+        );
+        let mut h = VectorField::zeros(m.len());
+        rkky.add_field(&m, 0.0, &sim.mesh, &sim.params, &mut h);
+        for i in 0..m.len() {
+            let expected = match sim.mesh.coords(i)[2] {
+                0 => (j1 + 2.0 * j2 * d) * m2 / (ms * 1e-9),
+                1 => Vector3::zeros(),
+                _ => (j1 + 2.0 * j2 * d) * Vector3::x() / (ms * 1e-9),
+            };
+            assert!(
+                (h.get(i) - expected).amax() < 1e-12,
+                "cell {i} at {phi} rad"
+            );
+        }
+    }
+    // antiparallel layers are the lowest state with J1 < 0
+    sim.m = VectorField::from_fn(sim.mesh.len(), |i| match sim.mesh.coords(i)[2] {
+        2 => -Vector3::x(),
+        _ => Vector3::x(),
+    });
+    let antiparallel = sim.energies().into_iter().find(|(k, _)| *k == "rkky");
+    assert!((antiparallel.unwrap().1 - (j1 - j2) * area).abs() < 1e-12 * area);
+}
+
+#[test]
+fn uniform_strain_acts_as_a_uniaxial_anisotropy() {
+    // B1 εxx mx² is the anisotropy of constant K = -B1 εxx along x, and the
+    // shear 2 B2 εxy mx my = B2 εxy ((m·u)² - (m·v)²) those of ∓B2 εxy
+    // along the diagonals u and v
+    let (b1, b2, strain, mu0_ms) = (-5e6, 8e6, 1e-3, 1.0);
+    let config = Config::parse(&format!(
+        r#"
+        [mesh]
+        nx = 3
+        [params]
+        mu0_ms = {mu0_ms}
+        [magnetoelastic]
+        b1 = {b1}
+        b2 = {b2}
+        [[magnetoelastic.strain]]
+        kind = "constant"
+        value = [{strain}, 0.0, 0.0, 0.0, 0.0, {strain}]
+        "#
+    ))
+    .unwrap();
+    let sim = Simulation::build(&config).unwrap();
+    let magnetoelastic = sim.terms.get::<Magnetoelastic>().unwrap();
+    let tensile = UniaxialAnisotropy::new(-b1 * strain, 0.0, Vector3::x());
+    let shear = [
+        UniaxialAnisotropy::new(-b2 * strain, 0.0, Vector3::new(1.0, 1.0, 0.0)),
+        UniaxialAnisotropy::new(b2 * strain, 0.0, Vector3::new(1.0, -1.0, 0.0)),
+    ];
+    let (mesh, p) = (&sim.mesh, &sim.params);
+    let m = VectorField::from_fn(3, |i| {
+        Vector3::new(1.0 + i as f64, -0.5 * i as f64, 0.7).normalize()
+    });
+    let field = |term: &dyn FieldTerm| {
+        let mut h = VectorField::zeros(3);
+        term.add_field(&m, 0.0, mesh, p, &mut h);
+        h
+    };
+    let strained = field(magnetoelastic);
+    let anisotropies = [field(&tensile), field(&shear[0]), field(&shear[1])];
+    for i in 0..3 {
+        let expected: Vector3<f64> = anisotropies.iter().map(|h| h.get(i)).sum();
+        let b = strained.get(i);
+        assert!(
+            (b - expected).amax() < 1e-12,
+            "B = {b:?}, expected {expected:?}"
+        );
+        // and by hand: B = -∂e/∂m / Mₛ
+        let mi = m.get(i);
+        let by_hand = -2.0 / (mu0_ms / nez::params::MU0)
+            * strain
+            * Vector3::new(b1 * mi.x + b2 * mi.y, b2 * mi.x, 0.0);
+        assert!((b - by_hand).amax() < 1e-12);
+    }
+    let e = magnetoelastic.energy(&m, 0.0, mesh, p);
+    let by_hand: f64 = (0..3)
+        .map(|i| {
+            let mi = m.get(i);
+            (b1 * mi.x * mi.x + 2.0 * b2 * mi.x * mi.y) * strain * mesh.cell_volume()
+        })
+        .sum();
+    assert!((e - by_hand).abs() < 1e-12 * by_hand.abs(), "E = {e:e} J");
+}
+
+#[test]
+fn point_dipoles_fall_off_as_the_cube_of_their_distance() {
+    // B = μ₀/(4π r³) (3 (μ·r̂) r̂ - μ) of the moment μ = Mₛ V m of cell 0 at
+    // cell k of a chain, the cells between carrying no moment
+    let mesh = Mesh::chain(4, 2e-9);
+    let p = Params {
+        mu0_ms: 1.5,
+        ..Params::default()
+    };
+    let dipolar = Dipolar::new(&mesh, None);
+    let moment = p.ms() * mesh.cell_volume();
+    for k in 1..4 {
+        let r = k as f64 * 2e-9;
+        for (dir, along) in [
+            (Vector3::x(), 2.0),
+            (Vector3::y(), -1.0),
+            (Vector3::z(), -1.0),
+        ] {
+            let m = VectorField::from_fn(4, |i| if i == 0 { dir } else { Vector3::zeros() });
+            let mut h = VectorField::zeros(4);
+            dipolar.add_field(&m, 0.0, &mesh, &p, &mut h);
+            let expected = nez::params::MU0 / (4.0 * PI * r.powi(3)) * along * moment * dir;
+            let b = h.get(k);
+            assert!(
+                (b - expected).amax() < 1e-12 * expected.amax(),
+                "B = {b:?} at {k} cells, expected {expected:?}"
+            );
+            // a point dipole has no field of its own
+            assert_eq!(h.get(0), Vector3::zeros());
+        }
+    }
+    // two moments along the chain attract: E = -μ₀ 2 μ² / (4π r³)
+    let m = VectorField::from_fn(4, |i| {
+        if i < 2 {
+            Vector3::x()
+        } else {
+            Vector3::zeros()
+        }
+    });
+    let e = dipolar.energy(&m, 0.0, &mesh, &p);
+    let expected = -nez::params::MU0 * 2.0 * moment * moment / (4.0 * PI * 8e-27);
+    assert!(
+        (e - expected).abs() < 1e-12 * expected.abs(),
+        "E = {e:e} J, expected {expected:e} J"
+    );
+}
+
+#[test]
+fn current_slab_circulates_the_field_of_amperes_law() {
+    // a slab of thickness s around z₀ carrying J along x, periodic in the
+    // plane: B_y = -μ₀ J (z - z₀) inside and ∓μ₀ J s / 2 above and below
+    let (j, s, z0) = (1e12, 8e-9, 8e-9);
+    let config = Config::parse(&format!(
+        r#"
+        [mesh]
+        nx = 4
+        ny = 4
+        nz = 16
+        dx = 5e-9
+        dy = 5e-9
+        dz = 1e-9
+        pbc = [32, 32, 0]
+        [oersted]
+        density = [{j}, 0.0, 0.0]
+        profile = {{ kind = "box", min = [-1.0, -1.0, 4e-9], max = [1.0, 1.0, 12e-9] }}
+        "#
+    ))
+    .unwrap();
+    let sim = Simulation::build(&config).unwrap();
+    let oersted = sim.terms.get::<Oersted>().unwrap();
+    let mut h = VectorField::zeros(sim.mesh.len());
+    oersted.add_field(&sim.m, 0.0, &sim.mesh, &sim.params, &mut h);
+    let mu0 = nez::params::MU0;
+    for i in 0..sim.mesh.len() {
+        let z = sim.mesh.position(i).z - z0;
+        let expected = -mu0 * j * z.clamp(-s / 2.0, s / 2.0);
+        let b = h.get(i);
+        // the images beyond leave out a field of order s / (32 · 4 dx) of it
+        assert!(
+            (b - Vector3::new(0.0, expected, 0.0)).amax() < 0.02 * mu0 * j * s / 2.0,
+            "B = {b:?} at z - z₀ = {z:e} m, expected B_y = {expected:e} T"
+        );
+    }
+}
@@ -0,0 +1,80 @@
+//! The Newell demag tensor against the demagnetizing factors of a cube and
+//! of a thin plate, and the sum over periodic images against an infinite
+//! film.
+
+use nalgebra::Vector3;
+
+use nez::{
+    Mesh, Params, VectorField,
+    field::{Demag, FieldTerm, demag_tensor},
+};
+
+#[test]
+fn self_demag_factors_of_a_cube_add_up_to_one() {
+    let d = 2e-9;
+    let [nxx, nyy, nzz, nxy, nxz, nyz] = demag_tensor([0.0; 3], [d; 3]);
+    assert!(
+        (nxx + nyy + nzz - 1.0).abs() < 1e-12,
+        "trace {}",
+        nxx + nyy + nzz
+    );
+    for n in [nxx, nyy, nzz] {
+        assert!((n - 1.0 / 3.0).abs() < 1e-12, "N = {n}");
+    }
+    for n in [nxy, nxz, nyz] {
+        assert!(n.abs() < 1e-12, "off-diagonal N = {n}");
+    }
+    // any box: the trace stays 1
+    let [nxx, nyy, nzz, ..] = demag_tensor([0.0; 3], [3e-9, 2e-9, 0.5e-9]);
+    assert!((nxx + nyy + nzz - 1.0).abs() < 1e-12);
+    assert!(nzz > nyy && nyy > nxx);
+}
+
+#[test]
+fn self_demag_of_a_thin_plate_tends_to_nzz_one() {
+    let mut last = 0.0;
+    for aspect in [10.0, 100.0, 1000.0] {
+        let [nxx, nyy, nzz, ..] = demag_tensor([0.0; 3], [aspect * 1e-9, aspect * 1e-9, 1e-9]);
+        assert!(nzz > last, "Nzz = {nzz} at aspect {aspect}");
+        assert!((nxx - nyy).abs() < 1e-12);
+        // a square plate of side L and thickness t: 1 - Nzz ≈ 2t/(πL) ln(L/t)
+        let thin = 2.0 / (std::f64::consts::PI * aspect) * aspect.ln();
+        assert!(
+            (1.0 - nzz) < 2.0 * thin,
+            "1 - Nzz = {} at aspect {aspect}",
+            1.0 - nzz
+        );
+        last = nzz;
+    }
+    assert!(last > 0.99, "Nzz = {last}");
+}
+
+#[test]
+fn periodic_images_sum_to_the_field_of_an_infinite_film() {
+    // a 4 × 4 patch of a film ten times thinner than wide, repeated 64 times
+    // on each side in the plane: the images beyond leave out a field of order
+    // t / (64 · 4 dx) of the infinite film's B = -μ₀Mₛ mz ẑ
+    let mesh = Mesh::new([4, 4, 1], [5e-9, 5e-9, 0.5e-9]).with_pbc([64, 64, 0]);
+    let demag = Demag::new(&mesh);
+    let p = Params {
+        mu0_ms: 1.0,
+        ..Params::default()
+    };
+    let m = VectorField::uniform(mesh.len(), Vector3::z());
+    let mut h = VectorField::zeros(mesh.len());
+    demag.add_field(&m, 0.0, &mesh, &p, &mut h);
+    for i in 0..mesh.len() {
+        let b = h.get(i);
+        assert!(
+            (b - Vector3::new(0.0, 0.0, -1.0)).amax() < 1e-3,
+            "B = {b:?}"
+        );
+    }
+    // and no field at all in the plane
+    let m = VectorField::uniform(mesh.len(), Vector3::x());
+    let mut h = VectorField::zeros(mesh.len());
+    demag.add_field(&m, 0.0, &mesh, &p, &mut h);
+    for i in 0..mesh.len() {
+        assert!(h.get(i).amax() < 1e-3, "B = {:?}", h.get(i));
+    }
+}
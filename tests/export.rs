@@ -0,0 +1,188 @@
+//! Snapshots exported for other tools, decoded back: legacy VTK and `.vti`
+//! images in every encoding, and PNG images coloured by direction.
+
+use nalgebra::Vector3;
+use std::{fs, path::PathBuf};
+
+use nez::{
+    Mesh, VectorField, render,
+    vtk::{self, VtkData},
+};
+
+/// A fresh directory for the files of test `name`
+fn dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("nez-export-{name}-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// A magnetization different in every cell of `mesh`
+fn twisted(mesh: &Mesh) -> VectorField {
+    VectorField::from_fn(mesh.len(), |i| {
+        let r = mesh.position(i);
+        let (a, b) = (r.x / 7e-9 + r.z / 5e-9, r.y / 3e-9);
+        Vector3::new(a.cos() * b.sin(), a.sin() * b.sin(), b.cos())
+    })
+}
+
+/// The values of `bytes`, 4 or 8 wide, in either byte order
+fn floats(bytes: &[u8], width: usize, big_endian: bool) -> Vec<f64> {
+    bytes
+        .chunks_exact(width)
+        .map(|b| match (width, big_endian) {
+            (4, true) => f32::from_be_bytes(b.try_into().unwrap()) as f64,
+            (4, false) => f32::from_le_bytes(b.try_into().unwrap()) as f64,
+            (_, true) => f64::from_be_bytes(b.try_into().unwrap()),
+            (_, false) => f64::from_le_bytes(b.try_into().unwrap()),
+        })
+        .collect()
+}
+
+/// Position of the end of the first `marker` in `bytes`
+fn after(bytes: &[u8], marker: &str) -> usize {
+    let marker = marker.as_bytes();
+    bytes
+        .windows(marker.len())
+        .position(|w| w == marker)
+        .unwrap_or_else(|| panic!("no {:?}", String::from_utf8_lossy(marker)))
+        + marker.len()
+}
+
+/// Assert that the components `values` are those of `m` within `resolution`
+fn assert_reads_back(values: &[f64], m: &VectorField, resolution: f64, what: &str) {
+    assert_eq!(values.len(), 3 * m.len(), "{what}");
+    for (i, v) in values.chunks_exact(3).enumerate() {
+        let v = Vector3::new(v[0], v[1], v[2]);
+        assert!(
+            (v - m.get(i)).amax() <= resolution,
+            "{what}: cell {i} reads {v:?}, wrote {:?}",
+            m.get(i)
+        );
+    }
+}
+
+#[test]
+fn vtk_files_hold_the_image_and_its_cells() {
+    let dir = dir("vtk");
+    let mesh = Mesh::new([5, 4, 3], [2e-9, 3e-9, 4e-9]);
+    let m = twisted(&mesh);
+    for (data, width, resolution) in [
+        (VtkData::Float32, 4, 1e-7),
+        (VtkData::Float64, 8, 0.0),
+        (VtkData::Ascii, 0, 0.0),
+    ] {
+        // legacy: a text header, then big-endian values or a line per cell
+        let path = dir.join(format!("{data:?}.vtk"));
+        vtk::write_legacy(&path, &mesh, &m, Some(2e-9), data).unwrap();
+        let bytes = fs::read(&path).unwrap();
+        let kind = if width == 4 { "float" } else { "double" };
+        let start = after(&bytes, &format!("VECTORS m {kind}\n"));
+        let header = String::from_utf8(bytes[..start].to_vec()).unwrap();
+        for line in [
+            "# vtk DataFile Version 3.0",
+            "DATASET STRUCTURED_POINTS",
+            "DIMENSIONS 6 5 4",
+            "SPACING 2e-9 3e-9 4e-9",
+            "TimeValue 1 1 double\n2e-9",
+            "CELL_DATA 60",
+        ] {
+            assert!(header.contains(line), "{data:?}: no {line:?} in\n{header}");
+        }
+        let values = match data {
+            VtkData::Ascii => String::from_utf8(bytes[start..].to_vec())
+                .unwrap()
+                .split_whitespace()
+                .map(|v| v.parse().unwrap())
+                .collect(),
+            _ => floats(&bytes[start..start + 3 * 60 * width], width, true),
+        };
+        assert_reads_back(&values, &m, resolution, &format!("{data:?}.vtk"));
+
+        // XML: the extent of the points, then the little-endian values
+        // appended after their length in bytes
+        let path = dir.join(format!("{data:?}.vti"));
+        vtk::write_xml(&path, &mesh, &m, None, data).unwrap();
+        let bytes = fs::read(&path).unwrap();
+        let text = String::from_utf8_lossy(&bytes);
+        assert!(text.contains(r#"WholeExtent="0 5 0 4 0 3""#), "{text}");
+        assert!(!text.contains("TimeValue"));
+        let values: Vec<f64> = match data {
+            VtkData::Ascii => {
+                let start = after(&bytes, r#"format="ascii">"#);
+                let end = start + after(&bytes[start..], "</DataArray>") - "</DataArray>".len();
+                String::from_utf8(bytes[start..end].to_vec())
+                    .unwrap()
+                    .split_whitespace()
+                    .map(|v| v.parse().unwrap())
+                    .collect()
+            }
+            _ => {
+                let start = after(&bytes, "<AppendedData encoding=\"raw\">\n   _");
+                let len = u64::from_le_bytes(bytes[start..start + 8].try_into().unwrap());
+                assert_eq!(len as usize, 3 * 60 * width, "{data:?}");
+                floats(&bytes[start + 8..start + 8 + len as usize], width, false)
+            }
+        };
+        assert_reads_back(&values, &m, resolution, &format!("{data:?}.vti"));
+    }
+    // a field of another mesh is refused
+    let Err(err) = vtk::write_legacy(
+        dir.join("wrong.vtk"),
+        &mesh,
+        &VectorField::zeros(4),
+        None,
+        VtkData::Ascii,
+    ) else {
+        panic!("4 cells written on a mesh of 60")
+    };
+    assert!(
+        err.to_string().contains("4 cells do not fit the 60"),
+        "{err}"
+    );
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn png_is_coloured_cell_by_cell_from_the_top() {
+    // red along +x, cyan along -x, white up and black down
+    assert_eq!(render::colour(Vector3::x()), [255, 0, 0]);
+    assert_eq!(render::colour(-Vector3::x()), [0, 255, 255]);
+    assert_eq!(render::colour(Vector3::z()), [255, 255, 255]);
+    assert_eq!(render::colour(-Vector3::z()), [0, 0, 0]);
+
+    // 8 × 4 cells in two layers, drawn 64 pixels to a cell; the layers of
+    // the last column cancel, leaving it transparent
+    let dir = dir("png");
+    let mesh = Mesh::new([8, 4, 2], [1e-9; 3]);
+    let m = VectorField::from_fn(mesh.len(), |i| {
+        let [x, y, z] = mesh.coords(i);
+        match (x, z) {
+            (7, 0) => Vector3::x(),
+            (7, _) => -Vector3::x(),
+            _ => Vector3::new((x as f64).cos(), (x as f64).sin(), y as f64 / 4.0).normalize(),
+        }
+    });
+    let path = dir.join("m.png");
+    render::write_png(&path, &mesh, &m).unwrap();
+    let decoder = png::Decoder::new(std::io::BufReader::new(fs::File::open(&path).unwrap()));
+    let mut reader = decoder.read_info().unwrap();
+    let mut pixels = vec![0; reader.output_buffer_size().unwrap()];
+    let info = reader.next_frame(&mut pixels).unwrap();
+    assert_eq!((info.width, info.height), (512, 256));
+    assert_eq!(info.color_type, png::ColorType::Rgba);
+    let at = |px: usize, py: usize| &pixels[4 * (px + 512 * py)..4 * (px + 512 * py) + 4];
+    for (x, y) in [(0, 0), (3, 1), (6, 3)] {
+        let [r, g, b] = render::colour(m.get(mesh.idx(x, y, 0)));
+        // the corners of the cell, rows counted from the top
+        let (left, top) = (64 * x, 64 * (3 - y));
+        for (px, py) in [(left, top), (left + 63, top + 63)] {
+            assert_eq!(
+                at(px, py),
+                [r, g, b, 255],
+                "cell [{x}, {y}] at ({px}, {py})"
+            );
+        }
+    }
+    assert!((0..256).all(|py| at(7 * 64 + 10, py)[3] == 0));
+    fs::remove_dir_all(&dir).unwrap();
+}
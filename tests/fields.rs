@@ -0,0 +1,310 @@
+//! Field terms against their energies and closed forms: the default
+//! material, the exchange between two cells, the easy axes of cubic
+//! anisotropy, the free surfaces of DMI with and without exchange, the
+//! Heisenberg exchange of two shells, the anisotropy a
+//! voltage sets through VCMA, a term of closures driving the solver.
+
+use nalgebra::Vector3;
+
+use nez::{
+    Config, Mesh, Params, Simulation, VectorField, Waveform,
+    config::VcmaConfig,
+    field::{
+        CubicAnisotropy, Custom, Dmi, Exchange, FieldTerm, Heisenberg, HeisenbergConfig, Vcma,
+    },
+    params::MU0,
+};
+
+/// Assert that the field of `term` in every cell of `m` is -∂E/∂mᵢ / (Mₛ V),
+/// by central differences of the energy, within `tol` of its largest value
+fn assert_field_is_the_energy_gradient(
+    term: &dyn FieldTerm,
+    m: &VectorField,
+    mesh: &Mesh,
+    p: &Params,
+    tol: f64,
+) {
+    let mut h = VectorField::zeros(m.len());
+    term.add_field(m, 0.0, mesh, p, &mut h);
+    let largest = (0..m.len()).map(|i| h.get(i).amax()).fold(0.0, f64::max);
+    let eps = 1e-6;
+    for i in 0..m.len() {
+        let mut gradient = Vector3::zeros();
+        for k in 0..3 {
+            let turned = |by: f64| {
+                let mut m = m.clone();
+                let mut v = m.get(i);
+                v[k] += by;
+                m.set(i, v);
+                term.energy(&m, 0.0, mesh, p)
+            };
+            gradient[k] = (turned(eps) - turned(-eps)) / (2.0 * eps);
+        }
+        let expected = -gradient / (p.ms_at(i) * mesh.cell_volume());
+        let b = h.get(i);
+        assert!(
+            (b - expected).amax() <= tol * largest,
+            "{}: B = {b:?} in cell {i}, -∂E/∂m / (Mₛ V) = {expected:?}",
+            term.name()
+        );
+    }
+}
+
+#[test]
+fn default_saturation_is_about_one_tesla() {
+    let p = Params::default();
+    assert!((p.ms() - 8e5).abs() < 1e-6, "Mₛ = {} A/m", p.ms());
+    assert!(
+        (p.mu0_ms - MU0 * 8e5).abs() < 1e-12,
+        "μ₀Mₛ = {} T",
+        p.mu0_ms
+    );
+}
+
+#[test]
+fn exchange_of_two_cells_is_independent_of_the_saturation() {
+    // E = 2 A d (1 - cos θ) for two cubes of side d at an angle θ, and
+    // B₀ = 2A/Mₛ (m₁ - m₀) / d² on the first
+    let (a_ex, d) = (1.3e-11, 2e-9);
+    let mesh = Mesh::new([2, 1, 1], [d; 3]);
+    for mu0_ms in [0.5, 1.0, 2.0] {
+        let p = Params {
+            a_ex,
+            mu0_ms,
+            ..Params::default()
+        };
+        for theta in [0.1f64, 1.0, 2.5] {
+            let m1 = Vector3::new(theta.sin(), 0.0, theta.cos());
+            let m = VectorField::from_fn(2, |i| if i == 0 { Vector3::z() } else { m1 });
+            let e = Exchange.energy(&m, 0.0, &mesh, &p);
+            let expected = 2.0 * a_ex * d * (1.0 - theta.cos());
+            assert!(
+                (e - expected).abs() < 1e-12 * expected,
+                "E = {e:e} J at μ₀Mₛ = {mu0_ms} T, expected {expected:e} J"
+            );
+            let mut h = VectorField::zeros(2);
+            Exchange.add_field(&m, 0.0, &mesh, &p, &mut h);
+            let expected = 2.0 * a_ex / p.ms() * (m1 - Vector3::z()) / (d * d);
+            assert!((h.get(0) - expected).amax() < 1e-12 * expected.amax());
+        }
+    }
+}
+
+#[test]
+fn cubic_anisotropy_favours_the_cube_edges_or_diagonals() {
+    // E/V = K1 (a²b² + b²c² + c²a²) over the axes of a cube turned about z:
+    // 0 along its edges ⟨100⟩, K1/4 along the face diagonals ⟨110⟩ and
+    // K1/3 along the body diagonals ⟨111⟩
+    let mesh = Mesh::new([1, 1, 1], [2e-9; 3]);
+    let p = Params::default();
+    let (c1, c2) = (Vector3::new(1.0, 1.0, 0.0), Vector3::new(-1.0, 1.0, 0.0));
+    let energy = |cubic: &CubicAnisotropy, m: Vector3<f64>| {
+        cubic.energy(&VectorField::from_fn(1, |_| m.normalize()), 0.0, &mesh, &p)
+    };
+    // directions spread evenly over the sphere
+    let sphere: Vec<Vector3<f64>> = (0..4000)
+        .map(|k| {
+            let z = 1.0 - (2.0 * k as f64 + 1.0) / 4000.0;
+            let phi = k as f64 * std::f64::consts::PI * (3.0 - 5.0f64.sqrt());
+            let r = (1.0 - z * z).sqrt();
+            Vector3::new(r * phi.cos(), r * phi.sin(), z)
+        })
+        .collect();
+    for k1 in [4e4, -4e4] {
+        let cubic = CubicAnisotropy::new([k1, 0.0, 0.0], c1, c2);
+        let axes = CubicAnisotropy::axes(c1, c2);
+        let v = mesh.cell_volume();
+        let along =
+            |a: f64, b: f64, c: f64| energy(&cubic, a * axes[0] + b * axes[1] + c * axes[2]);
+        for (e, expected) in [
+            (along(1.0, 0.0, 0.0), 0.0),
+            (along(0.0, 0.0, -1.0), 0.0),
+            (along(1.0, -1.0, 0.0), k1 / 4.0),
+            (along(1.0, 1.0, -1.0), k1 / 3.0),
+        ] {
+            assert!(
+                (e - expected * v).abs() < 1e-12 * k1.abs() * v,
+                "E = {e:e} J"
+            );
+        }
+        // the lowest of all directions
+        let lowest = sphere
+            .iter()
+            .min_by(|a, b| energy(&cubic, **a).total_cmp(&energy(&cubic, **b)))
+            .unwrap();
+        let [a, b, c] = axes.map(|u| lowest.dot(&u).abs());
+        let largest = a.max(b).max(c);
+        if k1 > 0.0 {
+            assert!(largest > 0.99, "lowest along {lowest:?} for K1 = {k1}");
+        } else {
+            let diagonal = 1.0 / 3.0f64.sqrt();
+            assert!(
+                [a, b, c].iter().all(|x| (x - diagonal).abs() < 0.02),
+                "lowest along {lowest:?} for K1 = {k1}"
+            );
+        }
+    }
+    // and the field, with the higher orders, is the gradient of the energy
+    let cubic = CubicAnisotropy::new([4e4, -3e4, 2e4], c1, c2);
+    let mesh = Mesh::new([3, 1, 1], [2e-9; 3]);
+    let m = VectorField::from_fn(3, |i| Vector3::new(0.3 + i as f64, -0.5, 0.8).normalize());
+    assert_field_is_the_energy_gradient(&cubic, &m, &mesh, &p, 1e-6);
+}
+
+#[test]
+fn dmi_leaves_the_surfaces_alone_without_exchange() {
+    // a uniform state feels the DMI only at free surfaces, through the ghost
+    // cells of the exchange: none once it is disabled
+    let config = |disable: &str| {
+        Config::parse(&format!(
+            r#"
+            disable = [{disable}]
+            [mesh]
+            nx = 8
+            dx = 1e-9
+            [params]
+            h_ext = [0.0, 0.0, 0.0]
+            [dmi]
+            d = 1e-3
+            [initial]
+            m = [0.0, 0.0, 1.0]
+            "#
+        ))
+        .unwrap()
+    };
+    let edge_field = |sim: &Simulation| {
+        let h = sim
+            .terms
+            .effective_field(&sim.m, 0.0, &sim.mesh, &sim.params);
+        h.get(0).norm()
+    };
+    let mut sim = Simulation::build(&config(r#""exchange""#)).unwrap();
+    assert!(!sim.terms.get::<Dmi>().unwrap().exchange);
+    assert_eq!(edge_field(&sim), 0.0);
+    assert_eq!(sim.energies(), [("zeeman", 0.0), ("dmi", 0.0)]);
+    // switched back on, the edges cant, as they do when it was never off
+    sim.terms.set_enabled("exchange", true).unwrap();
+    let canting = edge_field(&sim);
+    assert!(canting > 0.0);
+    let sim = Simulation::build(&config("")).unwrap();
+    assert_eq!(edge_field(&sim), canting);
+}
+
+#[test]
+fn heisenberg_ferromagnet_counts_every_pair_of_both_shells() {
+    // on a free 4 × 4 square, 24 pairs of nearest neighbours and 18 pairs of
+    // diagonal ones: E = -(24 J₁ + 18 J₂) when all are parallel
+    let mesh = Mesh::new([4, 4, 1], [1e-9; 3]);
+    let p = Params::default();
+    let (j1, j2) = (2e-21, 0.5e-21);
+    let config = HeisenbergConfig { j: vec![j1, j2] };
+    let heisenberg = Heisenberg::new(&config, &mesh).unwrap();
+    let expected = -(24.0 * j1 + 18.0 * j2);
+    for direction in [Vector3::z(), Vector3::new(1.0, -2.0, 0.5).normalize()] {
+        let m = VectorField::from_fn(mesh.len(), |_| direction);
+        let e = heisenberg.energy(&m, 0.0, &mesh, &p);
+        assert!((e - expected).abs() < 1e-12 * expected.abs(), "E = {e:e} J");
+    }
+    // which is the lowest: any turned state lies above it
+    let turned = VectorField::from_fn(mesh.len(), |i| {
+        let angle = 0.3 * i as f64;
+        Vector3::new(angle.sin(), 0.0, angle.cos())
+    });
+    assert!(heisenberg.energy(&turned, 0.0, &mesh, &p) > expected);
+    assert_field_is_the_energy_gradient(&heisenberg, &turned, &mesh, &p, 1e-6);
+    // a chain of three cells holds two shells only
+    let config = HeisenbergConfig {
+        j: vec![j1, j2, j2],
+    };
+    let Err(err) = Heisenberg::new(&config, &Mesh::new([3, 1, 1], [1e-9; 3])) else {
+        panic!("three shells on a chain of three cells were accepted")
+    };
+    assert!(err.to_string().contains("the mesh has only 2"), "{err}");
+}
+
+#[test]
+fn vcma_anisotropy_is_linear_in_the_voltage() {
+    // K = ξ V / (t_ox t_FM): 100 fJ V⁻¹ m⁻¹ over 1 nm of oxide on a film of
+    // 2 nm is 5e4 J m⁻³ per volt, with E = -K V_cell Σ mz² and B = 2K mz / Mₛ ẑ
+    let mesh = Mesh::new([3, 1, 1], [2e-9; 3]);
+    let p = Params::default();
+    let m = VectorField::from_fn(3, |i| Vector3::new(0.3 + i as f64, -0.5, 0.8).normalize());
+    let mz2: f64 = (0..3).map(|i| m.get(i).z.powi(2)).sum();
+    let vcma = |voltage: f64| {
+        let config = VcmaConfig {
+            coefficient: 100e-15,
+            oxide: 1e-9,
+            voltage: Waveform::Constant { value: voltage },
+            ..VcmaConfig::default()
+        };
+        Vcma::new(&config, &mesh).unwrap()
+    };
+    for voltage in [-1.5, 0.5, 2.0] {
+        let vcma = vcma(voltage);
+        let k = 5e4 * voltage;
+        assert!((vcma.k_per_volt - 5e4).abs() < 1e-9);
+        let e = vcma.energy(&m, 0.0, &mesh, &p);
+        let expected = -k * mesh.cell_volume() * mz2;
+        assert!(
+            (e - expected).abs() < 1e-12 * expected.abs(),
+            "E = {e:e} J at {voltage} V"
+        );
+        let mut h = VectorField::zeros(3);
+        vcma.add_field(&m, 0.0, &mesh, &p, &mut h);
+        for i in 0..3 {
+            let expected = Vector3::new(0.0, 0.0, 2.0 * k * m.get(i).z / p.ms());
+            assert!((h.get(i) - expected).amax() < 1e-12 * expected.z.abs());
+        }
+        assert_field_is_the_energy_gradient(&vcma, &m, &mesh, &p, 1e-6);
+    }
+    // no voltage, no anisotropy
+    let vcma = vcma(0.0);
+    let mut h = VectorField::zeros(3);
+    vcma.add_field(&m, 0.0, &mesh, &p, &mut h);
+    assert_eq!(h, VectorField::zeros(3));
+    assert_eq!(vcma.energy(&m, 0.0, &mesh, &p), 0.0);
+}
+
+#[test]
+fn custom_term_reaches_the_field_the_energy_and_the_dynamics() {
+    // a uniaxial anisotropy of closures, B = b mz ẑ from E = -Mₛ V b Σ mz² / 2,
+    // on a macrospin left without damping nor applied field
+    let (b, d) = (0.1, 2e-9);
+    let mesh = Mesh::new([1, 1, 1], [d; 3]);
+    let p = Params {
+        alpha: 0.0,
+        h_ext: Vector3::zeros(),
+        ..Params::default()
+    };
+    let energy_scale = p.ms() * mesh.cell_volume() * b;
+    let easy = move || {
+        Custom::new("easy", move |m: &VectorField, _t| {
+            VectorField::from_fn(m.len(), |i| Vector3::new(0.0, 0.0, b * m.get(i).z))
+        })
+        .with_energy(move |m: &VectorField, _t| {
+            -energy_scale / 2.0 * (0..m.len()).map(|i| m.get(i).z.powi(2)).sum::<f64>()
+        })
+    };
+    let m0 = Vector3::new(0.8, 0.0, 0.6);
+    let m = VectorField::from_fn(1, |_| m0);
+    assert_field_is_the_energy_gradient(&easy(), &m, &mesh, &p, 1e-6);
+
+    let mut sim = Simulation::new(mesh, p, m0);
+    sim.terms.insert(easy());
+    let h = sim
+        .terms
+        .effective_field(&sim.m, 0.0, &sim.mesh, &sim.params);
+    assert!((h.get(0) - Vector3::new(0.0, 0.0, b * 0.6)).amax() < 1e-15);
+    let e = sim.energies().into_iter().find(|(name, _)| *name == "easy");
+    assert_eq!(e, Some(("easy", -energy_scale / 2.0 * 0.36)));
+    // dm/dt = -γ m × B: a precession about z at ω = γ b mz, mz kept
+    sim.run(1000).unwrap();
+    let phase = sim.params.gamma * b * 0.6 * sim.t;
+    let expected = Vector3::new(0.8 * phase.cos(), 0.8 * phase.sin(), 0.6);
+    let found = sim.m.get(0);
+    assert!(
+        (found - expected).amax() < 1e-6,
+        "m = {found:?} at {} s, expected {expected:?}",
+        sim.t
+    );
+}
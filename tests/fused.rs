@@ -0,0 +1,103 @@
+//! dm/dt of uniform samples, which the single sweep over the cells computes,
+//! against the effective field summed term by term and turned into dm/dt
+//! cell by cell.
+
+use nalgebra::Vector3;
+
+use nez::{Config, Simulation, VectorField, llg::llg_rhs, parallel, rng::Rng};
+
+/// Rounding of the sum taken in another order, relative to the largest dm/dt
+const ROUNDING: f64 = if cfg!(feature = "f32") { 1e-5 } else { 1e-12 };
+
+/// A block with exchange, uniaxial anisotropy and an applied field, plus
+/// `extra` config, periodic along x and y if `pbc`, in a random state
+fn sample(pbc: bool, extra: &str) -> Simulation {
+    let pbc = if pbc { "[1, 1, 0]" } else { "[0, 0, 0]" };
+    let config = Config::parse(&format!(
+        r#"
+        [mesh]
+        nx = 13
+        ny = 5
+        nz = 3
+        dx = 2e-9
+        dy = 3e-9
+        dz = 4e-9
+        pbc = {pbc}
+        [params]
+        alpha = 0.3
+        a_ex = 1.3e-11
+        h_ext = [0.02, -0.05, 0.1]
+        [uniaxial]
+        k1 = 5e5
+        k2 = -1e5
+        axis = [0.6, 0.0, 0.8]
+        {extra}
+        "#
+    ))
+    .unwrap();
+    let mut sim = Simulation::build(&config).unwrap();
+    let mut rng = Rng::new(7);
+    let m: Vec<Vector3<f64>> = (0..sim.mesh.len())
+        .map(|_| rng.normal_vector().normalize())
+        .collect();
+    sim.m = VectorField::from_fn(m.len(), |i| m[i]);
+    sim
+}
+
+/// The largest difference between dm/dt of the sweep and of the field
+/// summed term by term, relative to the largest dm/dt
+fn mismatch(sim: &Simulation) -> f64 {
+    let torques = Default::default();
+    let llg = nez::llg::Llg {
+        mesh: &sim.mesh,
+        params: &sim.params,
+        terms: &sim.terms,
+        torques: &torques,
+        precession: true,
+        sublattice: None,
+        llb: None,
+        noise: None,
+    };
+    let fused = llg.rhs(&sim.m, sim.t);
+    let h = llg.effective_field(&sim.m, sim.t);
+    let (mut worst, mut largest) = (0.0f64, 0.0f64);
+    for i in 0..sim.mesh.len() {
+        let expected = llg_rhs(&sim.m.get(i), &h.get(i), &sim.params);
+        worst = worst.max((fused.get(i) - expected).amax());
+        largest = largest.max(expected.amax());
+    }
+    assert!(largest > 0.0);
+    worst / largest
+}
+
+#[test]
+fn sweep_matches_the_field_summed_term_by_term() {
+    for pbc in [false, true] {
+        let sim = sample(pbc, "");
+        let off = mismatch(&sim);
+        assert!(off < ROUNDING, "pbc {pbc}: off by {off:e}");
+    }
+}
+
+#[test]
+fn sweep_adds_the_terms_it_does_not_hold() {
+    // demag is summed into dm/dt before the sweep adds the local terms
+    for pbc in [false, true] {
+        let sim = sample(pbc, "[demag]");
+        let off = mismatch(&sim);
+        assert!(off < ROUNDING, "pbc {pbc}: off by {off:e}");
+    }
+}
+
+#[test]
+fn sweep_splits_rows_longer_than_a_chunk() {
+    // rows of 13 cells taken 4 at a time, with the ends of the row in
+    // different parts
+    parallel::set_chunk(4);
+    for pbc in [false, true] {
+        let sim = sample(pbc, "");
+        let off = mismatch(&sim);
+        assert!(off < ROUNDING, "pbc {pbc}: off by {off:e}");
+    }
+    parallel::set_chunk(parallel::CHUNK);
+}
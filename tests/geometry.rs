@@ -0,0 +1,115 @@
+//! Shapes rasterised onto a mesh: the cells of primitives against their
+//! volume, and of their combinations against inclusion–exclusion.
+
+use nalgebra::Vector3;
+
+use nez::{Mesh, geometry::Shape};
+
+/// Number of cells of `mesh` inside `shape`
+fn count(shape: &Shape, mesh: &Mesh) -> usize {
+    shape.mask(mesh).unwrap().into_iter().filter(|&b| b).count()
+}
+
+/// A 64 × 64 × 8 mesh of 1 nm cells
+fn mesh() -> Mesh {
+    Mesh::new([64, 64, 8], [1e-9; 3])
+}
+
+fn cuboid(min: [f64; 3], max: [f64; 3]) -> Shape {
+    Shape::Cuboid {
+        min: Vector3::from(min) * 1e-9,
+        max: Vector3::from(max) * 1e-9,
+    }
+}
+
+fn cylinder(centre: [f64; 3], radius: f64) -> Shape {
+    Shape::Cylinder {
+        centre: Vector3::from(centre) * 1e-9,
+        radius: radius * 1e-9,
+        axis: Vector3::z(),
+        height: None,
+    }
+}
+
+#[test]
+fn cuboid_holds_the_cells_whose_centres_it_holds() {
+    let mesh = mesh();
+    assert_eq!(
+        count(&cuboid([10.0, 20.0, 0.0], [20.0, 40.0, 4.0]), &mesh),
+        10 * 20 * 4
+    );
+    // a box holding no centre is empty, one around the mesh holds it all
+    assert_eq!(
+        count(&cuboid([10.0, 10.0, 0.0], [10.4, 30.0, 8.0]), &mesh),
+        0
+    );
+    assert_eq!(count(&cuboid([-1.0; 3], [100.0; 3]), &mesh), mesh.len());
+}
+
+#[test]
+fn cylinder_holds_its_volume_in_cells() {
+    let mesh = mesh();
+    for radius in [5.0, 10.0, 20.0] {
+        let n = count(&cylinder([32.0, 32.0, 4.0], radius), &mesh) as f64;
+        let volume = std::f64::consts::PI * radius * radius * 8.0;
+        assert!(
+            (n - volume).abs() < 0.05 * volume,
+            "{n} cells for a volume of {volume} at radius {radius}"
+        );
+    }
+    // tilted along x and cut to a height: a disc of 16 columns of cells
+    let disc = Shape::Cylinder {
+        centre: Vector3::new(32.0, 32.0, 4.0) * 1e-9,
+        radius: 2.0e-9,
+        axis: Vector3::x(),
+        height: Some(16e-9),
+    };
+    let n = count(&disc, &mesh);
+    assert_eq!(n % 16, 0, "{n}");
+    assert!((9..=16).contains(&(n / 16)), "{} cells per column", n / 16);
+}
+
+#[test]
+fn combinations_count_as_sets_do() {
+    let mesh = mesh();
+    let a = cylinder([24.0, 32.0, 4.0], 12.0);
+    let b = cuboid([24.0, 0.0, 0.0], [64.0, 64.0, 2.0]);
+    let (na, nb) = (count(&a, &mesh), count(&b, &mesh));
+    let both = count(
+        &Shape::Intersection {
+            shapes: vec![a.clone(), b.clone()],
+        },
+        &mesh,
+    );
+    assert!(0 < both && both < na.min(nb));
+    let either = count(
+        &Shape::Union {
+            shapes: vec![a.clone(), b.clone()],
+        },
+        &mesh,
+    );
+    assert_eq!(either, na + nb - both);
+    let difference = count(
+        &Shape::Difference {
+            shapes: vec![a.clone(), b.clone()],
+        },
+        &mesh,
+    );
+    assert_eq!(difference, na - both);
+    // a ring: a disc less a smaller one, cell for cell
+    let ring = Shape::Difference {
+        shapes: vec![
+            cylinder([32.0, 32.0, 4.0], 20.0),
+            cylinder([32.0, 32.0, 4.0], 10.0),
+        ],
+    };
+    let outer = count(&cylinder([32.0, 32.0, 4.0], 20.0), &mesh);
+    let inner = count(&cylinder([32.0, 32.0, 4.0], 10.0), &mesh);
+    assert_eq!(count(&ring, &mesh), outer - inner);
+    // nested, and empty
+    let nested = Shape::Intersection {
+        shapes: vec![ring, Shape::Layers { first: 2, last: 3 }],
+    };
+    assert_eq!(count(&nested, &mesh), (outer - inner) / 4);
+    assert_eq!(count(&Shape::Union { shapes: vec![] }, &mesh), 0);
+}
@@ -0,0 +1,65 @@
+//! Minimum energy paths against the Stoner–Wohlfarth barrier of a
+//! macrospin.
+
+use nalgebra::Vector3;
+
+use nez::{
+    Config, Simulation, VectorField,
+    gneb::{GnebConfig, gneb},
+};
+
+#[test]
+fn macrospin_reversal_climbs_a_barrier_of_k_v_at_the_equator() {
+    let k1 = 5e5;
+    let config = Config::parse(&format!(
+        r#"
+        [mesh]
+        nx = 1
+        dx = 2e-9
+        [params]
+        h_ext = [0.0, 0.0, 0.0]
+        [uniaxial]
+        k1 = {k1}
+        axis = [0.0, 0.0, 1.0]
+        "#
+    ))
+    .unwrap();
+    let sim = Simulation::build(&config).unwrap();
+    let up = VectorField::uniform(1, Vector3::z());
+    let down = VectorField::uniform(1, -Vector3::z());
+    // an even number of images, so that none starts at the equator
+    let band = gneb(
+        &up,
+        &down,
+        0.0,
+        &sim.mesh,
+        &sim.params,
+        &sim.terms,
+        &GnebConfig {
+            images: 10,
+            tolerance: 1e-4,
+            ..GnebConfig::default()
+        },
+    );
+    assert!(band.converged, "largest force {:e} T", band.force);
+    let barrier = k1 * sim.mesh.cell_volume();
+    assert!(
+        (band.barrier() - barrier).abs() < 1e-6 * barrier,
+        "barrier {:e} J, expected {barrier:e} J",
+        band.barrier()
+    );
+    let saddle = band.images[band.saddle()].get(0);
+    assert!(saddle.z.abs() < 1e-4, "saddle at {saddle:?}");
+    // the ends stay, and the band runs downhill from the saddle both ways
+    assert_eq!(band.images[0].get(0), Vector3::z());
+    assert!((band.images[9].get(0) + Vector3::z()).amax() < 1e-12);
+    let s = band.saddle();
+    assert!(band.energies[..=s].windows(2).all(|w| w[0] <= w[1]));
+    assert!(band.energies[s..].windows(2).all(|w| w[0] >= w[1]));
+    let path = band.reaction_coordinate();
+    assert!(
+        (path[9] - std::f64::consts::PI).abs() < 1e-3,
+        "path {} rad",
+        path[9]
+    );
+}
@@ -0,0 +1,80 @@
+//! Voronoi grains: their number against the mean grain size, and the
+//! spread of their anisotropy axes over the cone.
+
+use nalgebra::Vector3;
+
+use nez::{
+    Mesh,
+    grains::{Grains, GrainsConfig},
+};
+
+/// Axis of every grain of a 256 × 256 nm film of 10 nm grains, turned from
+/// z within `cone` degrees, or `None` if none is
+fn axes(cone: f64) -> Option<Vec<Vector3<f64>>> {
+    let mesh = Mesh::new([256, 256, 1], [1e-9; 3]);
+    let grains = Grains::new(
+        &GrainsConfig {
+            cone,
+            seed: 7,
+            ..GrainsConfig::default()
+        },
+        &mesh,
+    );
+    let cells = grains.oriented(Vector3::z(), |r| r * Vector3::z())?;
+    let mut axes = vec![None; grains.len()];
+    for (&g, axis) in grains.index.iter().zip(cells) {
+        axes[g as usize] = Some(axis);
+    }
+    // seeds too close to another may hold no cell
+    Some(axes.into_iter().flatten().collect())
+}
+
+#[test]
+fn grains_have_the_mean_size() {
+    let mesh = Mesh::new([256, 256, 2], [1e-9; 3]);
+    let grains = Grains::new(&GrainsConfig::default(), &mesh);
+    // the film over the area of a disc 10 nm across
+    let expected = 256.0 * 256.0 / (std::f64::consts::PI / 4.0 * 100.0);
+    assert_eq!(grains.len(), expected.round() as usize);
+    // columnar: both layers alike
+    let (bottom, top) = grains.index.split_at(256 * 256);
+    assert_eq!(bottom, top);
+    let boundaries = (0..mesh.len())
+        .filter(|&i| {
+            mesh.neighbor(i, 0, 1)
+                .is_some_and(|j| grains.boundary(i, j))
+        })
+        .count();
+    assert!(boundaries > 0);
+}
+
+#[test]
+fn axes_spread_evenly_over_the_cone() {
+    assert!(axes(0.0).is_none(), "grains turned without a cone");
+    for cone in [10.0f64, 30.0, 90.0, 180.0] {
+        let axes = axes(cone).unwrap();
+        let limit = cone.to_radians().cos();
+        let tilts: Vec<f64> = axes.iter().map(|a| a.z).collect();
+        assert!(
+            tilts.iter().all(|&c| c >= limit - 1e-12),
+            "a grain tilted beyond {cone}°"
+        );
+        // cos θ uniform between cos(cone) and 1
+        let mean = tilts.iter().sum::<f64>() / tilts.len() as f64;
+        let expected = (1.0 + limit) / 2.0;
+        let spread = (1.0 - limit) / (12.0 * tilts.len() as f64).sqrt();
+        assert!(
+            (mean - expected).abs() < 4.0 * spread,
+            "⟨cos θ⟩ = {mean} within {cone}°, expected {expected}"
+        );
+        // and no azimuth preferred
+        let inplane = axes
+            .iter()
+            .map(|a| Vector3::new(a.x, a.y, 0.0))
+            .sum::<Vector3<f64>>();
+        assert!(
+            inplane.norm() / (axes.len() as f64) < 0.1 * (1.0 - limit).sqrt() + 1e-12,
+            "⟨m_xy⟩ = {inplane:?} within {cone}°"
+        );
+    }
+}
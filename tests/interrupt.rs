@@ -0,0 +1,37 @@
+//! Ending a run early: the wall-time deadline is honoured within a batch of
+//! steps, however far apart the outputs are. The deadline is global to the
+//! process, hence a test binary of its own.
+
+use std::time::{Duration, Instant};
+
+use nez::{Config, Simulation, interrupt::Walltime};
+
+#[test]
+fn deadline_ends_a_run_between_far_apart_outputs() {
+    let config = Config::parse(
+        r#"
+        [mesh]
+        nx = 1
+        [params]
+        dt = 1e-14
+        [solver]
+        method = "rk4"
+        [output]
+        every = 1000000000
+        [run]
+        checkpoint_every = 0
+        "#,
+    )
+    .unwrap();
+    let mut sim = Simulation::build(&config).unwrap();
+    // a budget of 1 s ends 50 ms ahead of its limit: 100 ms from now
+    let walltime: Walltime = "1".parse().unwrap();
+    walltime.start(Instant::now() - Duration::from_millis(850));
+    assert!(!nez::interrupt::requested());
+    // half a minute of steps, were they taken in one batch up to the first
+    // output
+    let n = 2_000_000;
+    sim.run(n).unwrap();
+    assert!(nez::interrupt::out_of_time());
+    assert!(sim.step > 0 && sim.step < n, "{} steps", sim.step);
+}
@@ -0,0 +1,88 @@
+//! Material parameters given cell by cell by expressions of the position,
+//! and the regions that still win over them.
+
+use nez::{
+    Config, Simulation,
+    field::{Dmi, UniaxialAnisotropy},
+};
+
+/// A 16-cell wire of 2 nm cells with `extra` sections
+fn wire(extra: &str) -> nez::Result<Simulation> {
+    let config = Config::parse(&format!(
+        r#"
+        [mesh]
+        nx = 16
+        dx = 2e-9
+        [params]
+        alpha = 0.01
+        {extra}
+        "#
+    ))?;
+    Simulation::build(&config)
+}
+
+#[test]
+fn expressions_are_evaluated_at_the_cell_centres() {
+    let sim = wire(
+        r#"
+        [maps]
+        params.alpha = { expr = "0.01 + 0.5 * step(abs(x - 16e-9) - 12e-9)" }
+        uniaxial.k1 = { expr = "1e5 * x / 32e-9" }
+        "#,
+    )
+    .unwrap();
+    let uniaxial = sim.terms.get::<UniaxialAnisotropy>().unwrap();
+    for i in 0..16 {
+        let x = (i as f64 + 0.5) * 2e-9;
+        // an absorbing margin of two cells on either end
+        let alpha = if (2..14).contains(&i) { 0.01 } else { 0.51 };
+        assert_eq!(sim.params.alpha_at(i), alpha, "cell {i}");
+        assert!(
+            (uniaxial.k1.at(i) - 1e5 * x / 32e-9).abs() < 1e-9,
+            "cell {i}"
+        );
+    }
+    // the term mapped had no section: enabled with the defaults for the rest
+    assert_eq!(uniaxial.k2.as_uniform(), Some(0.0));
+    assert!(sim.terms.get::<Dmi>().is_none());
+}
+
+#[test]
+fn regions_win_over_maps_in_their_cells() {
+    let sim = wire(
+        r#"
+        [maps]
+        params.alpha = { expr = "x / 32e-9" }
+        [[regions]]
+        shape = { kind = "cuboid", min = [0.0, 0.0, 0.0], max = [8e-9, 1.0, 1.0] }
+        params = { alpha = 0.3 }
+        "#,
+    )
+    .unwrap();
+    for i in 0..16 {
+        let x = (i as f64 + 0.5) * 2e-9;
+        let alpha = if i < 4 { 0.3 } else { x / 32e-9 };
+        assert!((sim.params.alpha_at(i) - alpha).abs() < 1e-15, "cell {i}");
+    }
+}
+
+#[test]
+fn maps_out_of_range_are_refused() {
+    for (map, message) in [
+        (
+            r#"params.alpha = { expr = "x - 16e-9" }"#,
+            "`maps.params.alpha` must be non-negative",
+        ),
+        (
+            r#"dmi.d = { expr = "sqrt(16e-9 - x)" }"#,
+            "`maps.dmi.d`: not finite in cell [8, 0, 0]",
+        ),
+        (r#"params.a_ex = { expr = "y +" }"#, "`maps.params.a_ex`"),
+    ] {
+        let Err(err) = wire(&format!("[maps]\n{map}")) else {
+            panic!("{map} was accepted")
+        };
+        let err = err.to_string();
+        assert!(err.contains(message), "{map}: {err}");
+    }
+}
@@ -0,0 +1,80 @@
+//! Metropolis sampling of an exchange-coupled film with uniaxial
+//! anisotropy.
+
+use nez::{
+    Config, Simulation,
+    monte_carlo::{MonteCarlo, MonteCarloConfig},
+};
+
+/// An 8 × 8 film of 2 nm cells magnetized along its easy axis z
+fn film() -> Simulation {
+    let config = Config::parse(
+        r#"
+        [mesh]
+        nx = 8
+        ny = 8
+        dx = 2e-9
+        dy = 2e-9
+        dz = 2e-9
+        pbc = [1, 1, 0]
+        [params]
+        a_ex = 1.3e-11
+        h_ext = [0.0, 0.0, 0.0]
+        [uniaxial]
+        k1 = 5e5
+        axis = [0.0, 0.0, 1.0]
+        [initial]
+        m = [0.0, 0.0, 1.0]
+        "#,
+    )
+    .unwrap();
+    Simulation::build(&config).unwrap()
+}
+
+#[test]
+fn film_stays_saturated_as_the_temperature_goes_to_zero() {
+    let mut sim = film();
+    let config = MonteCarloConfig {
+        equilibration: 200,
+        sweeps: 200,
+        ..MonteCarloConfig::default()
+    };
+    let mut mc = MonteCarlo::new(&sim.m, 0.0, &sim.mesh, &sim.params, &sim.terms, &config).unwrap();
+    let mut last = 0.0;
+    for temperature in [100.0, 10.0, 1e-3] {
+        let sample = mc.sample(&mut sim.m, temperature);
+        assert!(
+            sample.magnetization > last,
+            "|m| = {} at {temperature} K",
+            sample.magnetization
+        );
+        last = sample.magnetization;
+    }
+    assert!(1.0 - last < 1e-6, "|m| = {last} at 1 mK");
+    // along the easy axis it started from
+    let m = sim.average();
+    assert!(m.z > 1.0 - 1e-5, "⟨m⟩ = {m:?}");
+}
+
+#[test]
+fn cone_width_adapts_to_the_target_acceptance() {
+    for target in [0.2, 0.5, 0.8] {
+        let mut sim = film();
+        let config = MonteCarloConfig {
+            equilibration: 500,
+            sweeps: 500,
+            acceptance: target,
+            ..MonteCarloConfig::default()
+        };
+        let mut mc =
+            MonteCarlo::new(&sim.m, 0.0, &sim.mesh, &sim.params, &sim.terms, &config).unwrap();
+        let sample = mc.sample(&mut sim.m, 300.0);
+        assert!(
+            (sample.acceptance - target).abs() < 0.05,
+            "accepted {} against {target}, cone {}",
+            sample.acceptance,
+            sample.cone
+        );
+        assert_ne!(sample.cone, config.cone);
+    }
+}
@@ -0,0 +1,108 @@
+//! OVF files written and read back in every encoding.
+
+use nalgebra::Vector3;
+use std::{fs, path::PathBuf};
+
+use nez::{
+    Mesh, VectorField,
+    ovf::{self, OvfData},
+};
+
+/// A fresh directory for the files of test `name`
+fn dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("nez-ovf-{name}-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// A magnetization turning along every axis of `mesh`, different in every
+/// cell
+fn twisted(mesh: &Mesh) -> VectorField {
+    VectorField::from_fn(mesh.len(), |i| {
+        let r = mesh.position(i);
+        let (a, b) = (r.x / 7e-9 + r.z / 5e-9, r.y / 3e-9);
+        Vector3::new(a.cos() * b.sin(), a.sin() * b.sin(), b.cos())
+    })
+}
+
+#[test]
+fn every_encoding_reads_back_what_was_written() {
+    let dir = dir("round-trip");
+    let mesh = Mesh::new([5, 4, 3], [2e-9, 3e-9, 4e-9]);
+    let m = twisted(&mesh);
+    for (data, resolution) in [
+        (OvfData::Binary4, 1e-7),
+        (OvfData::Binary8, 0.0),
+        (OvfData::Text, 0.0),
+    ] {
+        let path = dir.join(format!("{data:?}.ovf"));
+        ovf::write(&path, &mesh, &m, Some(1e-9), data).unwrap();
+        let read = ovf::read(&path).unwrap();
+        assert_eq!(read.nodes, [5, 4, 3], "{data:?}");
+        assert_eq!(read.step, mesh.cell(), "{data:?}");
+        assert_eq!(read.data.len(), mesh.len(), "{data:?}");
+        for (i, v) in read.data.iter().enumerate() {
+            assert!(
+                (v - m.get(i)).amax() <= resolution,
+                "{data:?}: cell {i} reads {v:?}, wrote {:?}",
+                m.get(i)
+            );
+        }
+        // not scalar data
+        let Err(err) = ovf::read_scalar(&path) else {
+            panic!("{data:?}: vectors read as scalars")
+        };
+        assert!(
+            err.to_string().contains("expected 1 values per node"),
+            "{err}"
+        );
+    }
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn a_file_is_interpolated_onto_a_grid_twice_as_fine() {
+    // a ramp along every axis is reproduced exactly between the nodes, and
+    // held at its end value beyond the outer ones
+    let coarse = Mesh::new([4, 3, 2], [4e-9, 4e-9, 4e-9]);
+    let ramp = |[x, y, z]: [f64; 3]| 1.0 + x + 2.0 * y + 3.0 * z;
+    let scalar = ovf::Ovf {
+        nodes: coarse.size(),
+        step: coarse.cell(),
+        data: (0..coarse.len())
+            .map(|i| ramp(coarse.coords(i).map(|c| c as f64)))
+            .collect(),
+    };
+    let fine = Mesh::new([8, 6, 4], [2e-9, 2e-9, 2e-9]);
+    // source node coordinate of the centre of a fine cell
+    let node = |c: usize, s: usize| ((c as f64 + 0.5) / 2.0 - 0.5).clamp(0.0, (s - 1) as f64);
+    for (i, v) in scalar.interpolate(&fine).into_iter().enumerate() {
+        let [x, y, z] = fine.coords(i);
+        let expected = ramp([node(x, 4), node(y, 3), node(z, 2)]);
+        assert!(
+            (v - expected).abs() < 1e-12,
+            "cell {i}: {v}, expected {expected}"
+        );
+    }
+
+    // vectors from a file, interpolated then normalised
+    let dir = dir("resample");
+    let path = dir.join("ramp.ovf");
+    let m = VectorField::from_fn(coarse.len(), |i| {
+        Vector3::new(1.0, coarse.coords(i)[0] as f64, 0.0)
+    });
+    ovf::write(&path, &coarse, &m, None, OvfData::Binary8).unwrap();
+    let resampled = ovf::read(&path).unwrap().resample(&fine, Vector3::z());
+    for i in 0..fine.len() {
+        let expected = Vector3::new(1.0, node(fine.coords(i)[0], 4), 0.0).normalize();
+        assert!((resampled.get(i) - expected).amax() < 1e-7, "cell {i}");
+    }
+    // where nothing is left to normalise, the fallback
+    let zero = VectorField::zeros(coarse.len());
+    ovf::write(&path, &coarse, &zero, None, OvfData::Binary8).unwrap();
+    let resampled = ovf::read(&path)
+        .unwrap()
+        .resample(&fine, 2.0 * Vector3::z());
+    assert!(resampled.iter().all(|v| v == Vector3::z()));
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,126 @@
+//! Material parameters looked up region by region, the exchange between
+//! regions, and regions whose moments stay fixed.
+
+use nez::{Config, Simulation, field::UniaxialAnisotropy};
+
+/// Eight layers: region 1 over layers 2 to 5, region 2 over 4 to 7 painted
+/// on top of it
+fn layers() -> Simulation {
+    let config = Config::parse(
+        r#"
+        [mesh]
+        nx = 4
+        ny = 4
+        nz = 8
+        [params]
+        alpha = 0.01
+        a_ex = 1e-11
+        mu0_ms = 1.0
+        [uniaxial]
+        k1 = 1e5
+        [[regions]]
+        shape = { kind = "layers", first = 2, last = 5 }
+        params = { alpha = 0.1, mu0_ms = 0.5 }
+        uniaxial = { k1 = 3e5 }
+        [[regions]]
+        shape = { kind = "layers", first = 4, last = 7 }
+        params = { a_ex = 2e-11 }
+        [[exchange_scale]]
+        regions = [1, 2]
+        scale = -0.5
+        "#,
+    )
+    .unwrap();
+    Simulation::build(&config).unwrap()
+}
+
+#[test]
+fn later_regions_win_and_the_rest_keep_the_main_sections() {
+    let sim = layers();
+    let p = &sim.params;
+    let uniaxial = sim.terms.get::<UniaxialAnisotropy>().unwrap();
+    for i in 0..sim.mesh.len() {
+        let z = sim.mesh.coords(i)[2];
+        let region = p.regions.as_ref().unwrap().index[i];
+        let expected = match z {
+            0..2 => (0, 0.01, 1e-11, 1.0, 1e5),
+            2..4 => (1, 0.1, 1e-11, 0.5, 3e5),
+            // region 2 overrides a_ex alone, the main sections the rest
+            _ => (2, 0.01, 2e-11, 1.0, 1e5),
+        };
+        let found = (
+            region,
+            p.alpha_at(i),
+            p.a_ex_at(i),
+            p.mu0_ms_at(i),
+            uniaxial.k1.at(i),
+        );
+        assert_eq!(found, expected, "layer {z}");
+    }
+    assert!(!p.is_uniform());
+}
+
+#[test]
+fn exchange_is_scaled_between_the_regions_named() {
+    let sim = layers();
+    let p = &sim.params;
+    let at = |z| sim.mesh.idx(0, 0, z);
+    // across the interface of regions 1 and 2, both ways
+    assert_eq!(p.exchange_scale(at(3), at(4)), -0.5);
+    assert_eq!(p.exchange_scale(at(4), at(3)), -0.5);
+    // within a region, and between regions 0 and 1
+    for (a, b) in [(0, 1), (2, 3), (5, 6), (1, 2)] {
+        assert_eq!(p.exchange_scale(at(a), at(b)), 1.0, "{a}, {b}");
+    }
+}
+
+#[test]
+fn regions_beyond_the_last_are_refused() {
+    let mut config = String::from("[mesh]\nnx = 1\n");
+    for _ in 0..=nez::regions::MAX_REGIONS {
+        config.push_str("[[regions]]\nshape = { kind = \"layers\", first = 0, last = 0 }\n");
+    }
+    let err = Config::parse(&config).unwrap_err().to_string();
+    assert!(err.contains("at most 255 `regions`"), "{err}");
+}
+
+#[test]
+fn frozen_region_stays_put_while_its_neighbours_feel_it() {
+    // the first four cells along x, frozen, the rest along the field z
+    let config = Config::parse(
+        r#"
+        [mesh]
+        nx = 16
+        dx = 2e-9
+        [params]
+        alpha = 0.1
+        a_ex = 1.3e-11
+        h_ext = [0.0, 0.0, 0.05]
+        [[regions]]
+        shape = { kind = "cuboid", min = [0.0, 0.0, 0.0], max = [8e-9, 1.0, 1.0] }
+        frozen = true
+        [initial]
+        texture = { kind = "expr", mx = "1 - step(x - 8e-9)", mz = "step(x - 8e-9)" }
+        "#,
+    )
+    .unwrap();
+    let mut sim = Simulation::build(&config).unwrap();
+    let start = sim.m.clone();
+    sim.run(2000).unwrap();
+    for i in 0..4 {
+        assert!(sim.params.is_frozen(i));
+        // bit for bit
+        assert_eq!(sim.m.get(i), start.get(i), "frozen cell {i}");
+    }
+    assert!(!sim.params.is_frozen(4));
+    // the free cells next to them tilt towards x, less and less further on
+    let mx: Vec<f64> = (4..16).map(|i| sim.m.get(i).x).collect();
+    assert!(mx[0] > 0.1, "mx = {mx:?}");
+    assert!(mx.windows(2).all(|w| w[0] > w[1]), "mx = {mx:?}");
+    // relaxing and minimizing leave them as well
+    sim.relax(&Default::default());
+    sim.minimize(&Default::default());
+    for i in 0..4 {
+        assert_eq!(sim.m.get(i), start.get(i), "frozen cell {i}");
+    }
+}
@@ -0,0 +1,252 @@
+//! Control scripts parsed and run on a macrospin, one statement form at a
+//! time, and the errors of scripts that cannot run.
+
+use nalgebra::Vector3;
+
+use nez::{Config, Simulation, script::Script};
+
+/// A macrospin along x in a field along z, writing to the store `name` in
+/// memory
+fn macrospin(name: &str) -> (Simulation, Config) {
+    let mut config = Config::parse(
+        r#"
+        [mesh]
+        nx = 1
+        [params]
+        alpha = 0.5
+        h_ext = [0.0, 0.0, 0.1]
+        [initial]
+        m = [1.0, 0.0, 0.0]
+        "#,
+    )
+    .unwrap();
+    config.output.path = format!("memory://script-{name}").into();
+    (Simulation::from_config(&config).unwrap(), config)
+}
+
+/// The printed lines of `text` run on a fresh macrospin, and the macrospin
+fn run(name: &str, text: &str) -> (Vec<Vec<f64>>, Simulation) {
+    let (mut sim, config) = macrospin(name);
+    let mut out = Vec::new();
+    Script::parse(text)
+        .unwrap()
+        .run(&mut sim, &config, &mut out)
+        .unwrap();
+    let lines = String::from_utf8(out)
+        .unwrap()
+        .lines()
+        .map(|l| l.split('\t').map(|v| v.parse().unwrap()).collect())
+        .collect();
+    (lines, sim)
+}
+
+/// The message of the error `text` fails with, parsing or running
+fn error(text: &str) -> String {
+    let (mut sim, config) = macrospin("error");
+    let err = match Script::parse(text) {
+        Ok(script) => script
+            .run(&mut sim, &config, &mut Vec::new())
+            .expect_err("the script ran"),
+        Err(e) => e,
+    };
+    err.to_string()
+}
+
+#[test]
+fn variables_and_parameters_are_set_and_printed() {
+    let (lines, sim) = run(
+        "assign",
+        "
+        # a comment, then blank lines
+
+        a = 2
+        b = a * 3 # trailing comment
+        alpha = 0.1
+        dt = 2e-13
+        print(a, b, alpha, dt, t, step, mx, my, mz)
+        ",
+    );
+    assert_eq!(lines, [[2.0, 6.0, 0.1, 2e-13, 0.0, 0.0, 1.0, 0.0, 0.0]]);
+    assert_eq!(sim.params.alpha, 0.1);
+    assert_eq!((sim.params.dt, sim.dt), (2e-13, 2e-13));
+}
+
+#[test]
+fn h_ext_sets_the_static_field() {
+    let (lines, sim) = run("h_ext", "b = 0.5\nh_ext = [0, b / 2, b]\nprint(energy)");
+    assert_eq!(sim.params.h_ext, Vector3::new(0.0, 0.25, 0.5));
+    // m along x, perpendicular to the field
+    assert!(lines[0][0].abs() < 1e-30, "{lines:?}");
+}
+
+#[test]
+fn field_follows_the_time_and_the_variables_when_set() {
+    let (_, sim) = run(
+        "field",
+        "
+        f = 1e9
+        field = [0.01 * sin(2 * pi * f * t), 0, 0]
+        f = 2e9
+        ",
+    );
+    // a quarter period of the frequency when the field was set
+    let zeeman = sim.terms.get::<nez::field::Zeeman>().unwrap();
+    let b = zeeman.field_at(0, 0.25e-9, &sim.params) - sim.params.h_ext;
+    assert!((b - Vector3::new(0.01, 0.0, 0.0)).amax() < 1e-12, "{b:?}");
+    // a constant zero field removes the drive
+    let (_, sim) = run("no-field", "field = [sin(1), 0, 0]\nfield = [0, 0, 0]");
+    let zeeman = sim.terms.get::<nez::field::Zeeman>().unwrap();
+    assert_eq!(zeeman.field_at(0, 1e-9, &sim.params), sim.params.h_ext);
+}
+
+#[test]
+fn run_and_steps_advance_the_simulation() {
+    let (lines, sim) = run(
+        "run",
+        "dt = 1e-13\nrun(1e-11)\nprint(t, step)\nsteps(20)\nprint(step)",
+    );
+    assert!((lines[0][0] - 1e-11).abs() < 1e-20, "{lines:?}");
+    assert_eq!(lines[0][1], 100.0);
+    assert_eq!(lines[1], [120.0]);
+    assert_eq!(sim.step, 120);
+}
+
+#[test]
+fn relax_and_minimize_turn_m_along_the_field() {
+    for command in ["relax", "minimize"] {
+        let (lines, sim) = run(command, &format!("{command}()\nprint(mz)"));
+        assert!(lines[0][0] > 0.999, "{command}: {lines:?}");
+        assert!((sim.average().z - lines[0][0]).abs() < 1e-6);
+    }
+}
+
+#[test]
+fn save_writes_a_frame() {
+    let (_, sim) = run("save", "save()\nsave()");
+    assert_eq!(sim.frame, 2);
+}
+
+#[test]
+fn for_loops_include_the_stop_value() {
+    let (lines, _) = run(
+        "for",
+        "
+        for k = 1, 3
+            print(k)
+        end
+        for b = 0.2, 0, -0.05
+            print(b)
+        end
+        for k = 1, 0
+            print(k)
+        end
+        print(k)
+        ",
+    );
+    let flat: Vec<f64> = lines.into_iter().flatten().collect();
+    let expected = [1.0, 2.0, 3.0, 0.2, 0.15, 0.1, 0.05, 0.0, 3.0];
+    assert_eq!(flat.len(), expected.len(), "{flat:?}");
+    for (a, b) in flat.iter().zip(expected) {
+        assert!((a - b).abs() < 1e-12, "{flat:?}");
+    }
+}
+
+#[test]
+fn if_runs_its_block_when_not_zero() {
+    let (lines, _) = run(
+        "if",
+        "
+        for k = 0, 3
+            if k >= 2
+                print(k)
+            end
+            if k == 0
+                print(-1)
+            end
+        end
+        ",
+    );
+    assert_eq!(lines, [[-1.0], [2.0], [3.0]]);
+}
+
+#[test]
+fn malformed_scripts_are_refused_with_their_line() {
+    for (text, message) in [
+        ("end", "line 1: `end` outside a block"),
+        (
+            "a = 1\nfor k = 1, 2\nprint(k)",
+            "line 2: block has no `end`",
+        ),
+        ("if 1\nprint(1)", "line 1: block has no `end`"),
+        (
+            "for k 1, 2\nend",
+            "line 1: expected `for name = start, stop, step`",
+        ),
+        (
+            "for k = 1\nend",
+            "line 1: expected `for name = start, stop, step`",
+        ),
+        ("h_ext = [0, 1]", "line 1: a vector has three components"),
+        ("h_ext = [0, 1, 2", "line 1: expected `]`"),
+        ("print(1", "line 1: expected `)`"),
+        ("2a = 1", "line 1: `2a` is not a valid name"),
+        ("just words", "line 1: cannot understand `just words`"),
+    ] {
+        let err = error(text);
+        assert_eq!(err, message, "{text}");
+    }
+}
+
+#[test]
+fn statements_that_cannot_run_are_refused_with_their_line() {
+    for (text, message) in [
+        ("jump()", "line 1: unknown command `jump`"),
+        ("relax(1)", "line 1: `relax` takes 0 argument(s)"),
+        ("run()", "line 1: `run` takes 1 argument(s)"),
+        ("run(-1)", "line 1: cannot run for -1 s"),
+        ("steps(-1)", "line 1: cannot take -1 steps"),
+        ("t = 1", "line 1: `t` cannot be set"),
+        ("alpha = -1", "line 1: `alpha` must not be negative, got -1"),
+        ("dt = 0", "line 1: `dt` must be positive, got 0"),
+        ("a = 1 / 0", "line 1: `a` must be finite"),
+        (
+            "a = [1, 2, 3]",
+            "line 1: only `h_ext` and `field` take a vector, not `a`",
+        ),
+        ("for k = 1, 2, 0\nend", "line 1: `for` step must not be 0"),
+        // the line of the statement in the block, not of the block
+        ("\nfor k = 1, 2\nprint(k, nope)\nend", "line 3: "),
+        (
+            "if 1\nif 1\nsteps(-1)\nend\nend",
+            "line 3: cannot take -1 steps",
+        ),
+    ] {
+        let err = error(text);
+        assert!(err.starts_with(message), "{text}: {err}");
+    }
+}
+
+#[test]
+fn expressions_are_parsed_with_the_script() {
+    // an unknown name is refused before anything runs
+    let Err(err) = Script::parse("steps(10)\nprint(nope)") else {
+        panic!("an unknown name was parsed")
+    };
+    assert!(err.to_string().starts_with("line 2: "), "{err}");
+    // a variable of the script, read before it is set
+    for text in ["print(a)\na = 1", "if 0\na = 1\nend\nprint(a)"] {
+        let err = error(text);
+        assert!(
+            err.ends_with("`a` is read before it is set"),
+            "{text}: {err}"
+        );
+    }
+    // parsed once, the script runs again on another simulation
+    let script = Script::parse("for k = 1, 2\n  steps(10 * k)\nend\nprint(step)").unwrap();
+    for name in ["again-1", "again-2"] {
+        let (mut sim, config) = macrospin(name);
+        let mut out = Vec::new();
+        script.run(&mut sim, &config, &mut out).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap().trim(), "3.000000e1");
+    }
+}
@@ -0,0 +1,194 @@
+//! The live-view server spoken to as a browser would: the HTTP upgrade of
+//! RFC 6455, messages of every length encoding, and the closing handshake,
+//! and hung up on when sending too much or too slowly.
+
+use nalgebra::Vector3;
+use std::{
+    io::{BufRead, BufReader, Read, Write},
+    net::TcpStream,
+    time::{Duration, Instant},
+};
+
+use nez::{
+    Mesh, Params, Simulation,
+    serve::{HANDSHAKE, RECEIVED, Server},
+};
+
+/// Connect to `server` at `/ws` with the key of the example of RFC 6455,
+/// returning the stream past the response and its header lines
+fn upgrade(server: &Server) -> (BufReader<TcpStream>, Vec<String>) {
+    let mut stream = TcpStream::connect(server.local_addr()).unwrap();
+    stream
+        .set_read_timeout(Some(Duration::from_secs(10)))
+        .unwrap();
+    write!(
+        stream,
+        "GET /ws HTTP/1.1\r\nHost: localhost\r\nUpgrade: websocket\r\n\
+         Connection: Upgrade\r\nSec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\n\
+         Sec-WebSocket-Version: 13\r\n\r\n"
+    )
+    .unwrap();
+    let mut reader = BufReader::new(stream);
+    let mut lines = Vec::new();
+    loop {
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        if line.trim().is_empty() {
+            break;
+        }
+        lines.push(line.trim().to_string());
+    }
+    (reader, lines)
+}
+
+/// Opcode and payload of the next message from the server, which must not
+/// be masked, and how many bytes its length took
+fn read(reader: &mut impl Read) -> (u8, Vec<u8>, usize) {
+    let mut head = [0; 2];
+    reader.read_exact(&mut head).unwrap();
+    assert_eq!(head[0] & 0xF0, 0x80, "fragmented or with reserved bits");
+    assert_eq!(head[1] & 0x80, 0, "masked by the server");
+    let (len, extra) = match head[1] {
+        126 => {
+            let mut n = [0; 2];
+            reader.read_exact(&mut n).unwrap();
+            (u16::from_be_bytes(n) as usize, 2)
+        }
+        127 => {
+            let mut n = [0; 8];
+            reader.read_exact(&mut n).unwrap();
+            (u64::from_be_bytes(n) as usize, 8)
+        }
+        n => (n as usize, 0),
+    };
+    let mut payload = vec![0; len];
+    reader.read_exact(&mut payload).unwrap();
+    (head[0] & 0x0F, payload, extra)
+}
+
+/// A masked message from the browser
+fn send(stream: &mut TcpStream, opcode: u8, payload: &[u8]) {
+    assert!(payload.len() < 126);
+    let mask = [0x37, 0xfa, 0x21, 0x3d];
+    let mut frame = vec![0x80 | opcode, 0x80 | payload.len() as u8];
+    frame.extend(mask);
+    frame.extend(payload.iter().enumerate().map(|(k, b)| b ^ mask[k % 4]));
+    stream.write_all(&frame).unwrap();
+}
+
+/// A film of `nx × ny` cells along x
+fn film(nx: usize, ny: usize) -> Simulation {
+    let mesh = Mesh::new([nx, ny, 1], [1e-9; 3]);
+    Simulation::new(mesh, Params::default(), Vector3::x())
+}
+
+#[test]
+fn handshake_answers_the_key_of_rfc_6455() {
+    let server = Server::start("127.0.0.1:0").unwrap();
+    let (_, lines) = upgrade(&server);
+    assert_eq!(lines[0], "HTTP/1.1 101 Switching Protocols");
+    assert!(
+        lines.contains(&"Upgrade: websocket".to_string()),
+        "{lines:?}"
+    );
+    assert!(
+        lines.contains(&"Sec-WebSocket-Accept: s3pPLMBiTxaQ9kYGzzhZRbK+xOo=".to_string()),
+        "{lines:?}"
+    );
+}
+
+#[test]
+fn messages_take_every_length_encoding() {
+    // frames of 8 + 12 nx ny bytes: 20, then 392, then 98 312
+    for ((nx, ny), extra) in [((1, 1), 0), ((4, 8), 2), ((128, 64), 8)] {
+        // the 20 columns make a row of more than 125 bytes
+        let mut server = Server::start("127.0.0.1:0").unwrap();
+        let (mut reader, _) = upgrade(&server);
+        let sim = film(nx, ny);
+        let observables: Vec<(String, f64)> =
+            (0..20).map(|k| (format!("c{k}"), k as f64)).collect();
+        server.publish(&sim, &observables);
+        let (opcode, text, len) = read(&mut reader);
+        assert_eq!((opcode, len), (0x1, 2), "the table row");
+        let text = String::from_utf8(text).unwrap();
+        assert!(text.starts_with(r#"{"columns":["c0","#), "{text}");
+
+        let (opcode, frame, len) = read(&mut reader);
+        assert_eq!((opcode, len), (0x2, extra), "{nx} × {ny}");
+        assert_eq!(frame.len(), 8 + 12 * nx * ny);
+        assert_eq!(
+            frame[..8],
+            [(nx as u32).to_le_bytes(), (ny as u32).to_le_bytes()].concat()
+        );
+        let mx = f32::from_le_bytes(frame[8..12].try_into().unwrap());
+        assert_eq!(mx, 1.0);
+    }
+}
+
+#[test]
+fn close_and_ping_are_answered() {
+    let server = Server::start("127.0.0.1:0").unwrap();
+    let (mut reader, _) = upgrade(&server);
+    let mut stream = reader.get_ref().try_clone().unwrap();
+    send(&mut stream, 0x9, b"hello");
+    assert_eq!(read(&mut reader), (0xA, b"hello".to_vec(), 0));
+    // text from the browser is ignored
+    send(&mut stream, 0x1, b"anything");
+    // close, status 1000, echoed back before the server hangs up
+    send(&mut stream, 0x8, &1000u16.to_be_bytes());
+    assert_eq!(read(&mut reader), (0x8, 1000u16.to_be_bytes().to_vec(), 0));
+    let mut rest = Vec::new();
+    assert_eq!(reader.read_to_end(&mut rest).unwrap(), 0);
+}
+
+#[test]
+fn messages_too_long_end_the_connection() {
+    let server = Server::start("127.0.0.1:0").unwrap();
+    let (mut reader, _) = upgrade(&server);
+    let mut stream = reader.get_ref().try_clone().unwrap();
+    // a binary message announcing a terabyte: closed as too big, 1009,
+    // before its payload is read
+    let mut head = vec![0x82, 0x80 | 127];
+    head.extend((1u64 << 40).to_be_bytes());
+    head.extend([0x37, 0xfa, 0x21, 0x3d]);
+    stream.write_all(&head).unwrap();
+    let (opcode, payload, _) = read(&mut reader);
+    assert_eq!((opcode, &payload[..2]), (0x8, &1009u16.to_be_bytes()[..]));
+    let mut rest = Vec::new();
+    assert_eq!(reader.read_to_end(&mut rest).unwrap(), 0);
+}
+
+#[test]
+fn requests_too_long_or_too_slow_are_dropped() {
+    let server = Server::start("127.0.0.1:0").unwrap();
+    let connect = || {
+        let stream = TcpStream::connect(server.local_addr()).unwrap();
+        stream
+            .set_read_timeout(Some(HANDSHAKE + Duration::from_secs(5)))
+            .unwrap();
+        stream
+    };
+    // headers without end
+    let mut stream = connect();
+    write!(stream, "GET /ws HTTP/1.1\r\n").unwrap();
+    let header = format!("X-Padding: {}\r\n", "a".repeat(100));
+    for _ in 0..=RECEIVED / header.len() {
+        stream.write_all(header.as_bytes()).unwrap();
+    }
+    // hung up on, the rest of the request left unread
+    let mut rest = Vec::new();
+    match stream.read_to_end(&mut rest) {
+        Ok(n) => assert_eq!(n, 0),
+        Err(e) => assert_eq!(e.kind(), std::io::ErrorKind::ConnectionReset),
+    }
+    // a request started but never finished
+    let start = Instant::now();
+    let mut stream = connect();
+    write!(stream, "GET /ws HTTP/1.1\r\n").unwrap();
+    assert_eq!(stream.read_to_end(&mut rest).unwrap(), 0);
+    let waited = start.elapsed();
+    assert!(
+        waited >= HANDSHAKE && waited < HANDSHAKE + Duration::from_secs(2),
+        "hung up on after {waited:?}"
+    );
+}
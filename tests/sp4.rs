@@ -0,0 +1,105 @@
+//! µMAG standard problem 4 from the built-in preset, against the published
+//! solutions of field 1: the S state, the time ⟨mx⟩ first crosses zero and
+//! ⟨m⟩ after a nanosecond. A coarse mesh is checked on every `cargo test`;
+//! the preset itself takes about a minute in release mode, so it only runs
+//! on request: `cargo test --release --test sp4 -- --ignored`.
+
+use nalgebra::Vector3;
+
+use nez::{Config, Simulation, presets};
+
+/// Relaxed S state, as reached by mumax3 on the same 128 × 32 × 1 mesh
+/// (`test/standardproblem4.mx3` of its test suite)
+const S_STATE: [f64; 3] = [0.96697, 0.12527, 0.0];
+
+/// Time at which ⟨mx⟩ first crosses zero in field 1 in the published
+/// solutions (mumax3, OOMMF) at this cell size (s)
+const CROSSING: f64 = 0.138e-9;
+
+/// ⟨m⟩ of mumax3 after 1 ns in field 1, from the same test
+const AT_1NS: [f64; 3] = [-0.98461, 0.12604, 0.04327];
+
+fn config() -> Config {
+    Config::parse(presets::get("sp4").expect("preset `sp4`")).expect("valid preset")
+}
+
+/// The S state of `config` relaxed, then the time ⟨mx⟩ first crosses zero
+/// in field 1, interpolated between samples 1 ps apart; `sim` is left at the
+/// first sample past it
+fn switch(sim: &mut Simulation, config: &Config) -> (Vector3<f64>, f64) {
+    config.stages[0].apply(sim, config).unwrap();
+    assert!(sim.relax(&config.relax).converged);
+    let s = sim.average();
+    config.stages[1].apply(sim, config).unwrap();
+    let (mut t, mut mx) = (sim.t, s.x);
+    loop {
+        assert!(sim.run_until(t + 1e-12, u64::MAX).unwrap());
+        let next = sim.average().x;
+        if next < 0.0 {
+            return (s, t + (sim.t - t) * mx / (mx - next));
+        }
+        (t, mx) = (sim.t, next);
+        assert!(t < 1e-9, "⟨mx⟩ never crossed zero");
+    }
+}
+
+fn assert_close(m: Vector3<f64>, reference: [f64; 3], tolerance: f64, what: &str) {
+    assert!(
+        (m - Vector3::from(reference)).amax() < tolerance,
+        "{what}: ⟨m⟩ = {m:?}, published {reference:?}"
+    );
+}
+
+#[test]
+fn preset_is_standard_problem_4() {
+    let config = config();
+    let m = config.mesh;
+    assert_eq!([m.nx, m.ny, m.nz], [128, 32, 1]);
+    let size = [m.nx as f64 * m.dx, m.ny as f64 * m.dy, m.nz as f64 * m.dz];
+    for (a, b) in size.iter().zip([500e-9, 125e-9, 3e-9]) {
+        assert!((a - b).abs() < 1e-15, "sample size {size:?}");
+    }
+    assert!(config.demag.is_some());
+    assert_eq!(config.stages.len(), 2);
+    let field = config.stages[1].h_ext.expect("field of the second stage");
+    assert!(
+        (field.norm() - 0.025).abs() < 1e-4,
+        "|B| = {}",
+        field.norm()
+    );
+}
+
+/// The preset on cells four times as wide, 15.6 nm: the S state stiffens
+/// by about 0.01 and switching starts some 3 ps late
+#[test]
+fn coarse_mesh_switches_with_the_published_solutions() {
+    let mut config = config();
+    config.mesh.nx /= 4;
+    config.mesh.ny /= 4;
+    config.mesh.dx *= 4.0;
+    config.mesh.dy *= 4.0;
+    let mut sim = Simulation::build(&config).unwrap();
+    let (s, crossing) = switch(&mut sim, &config);
+    assert_close(s, S_STATE, 0.02, "S state");
+    assert!(
+        (crossing - CROSSING).abs() < 6e-12,
+        "⟨mx⟩ crosses zero at {crossing:e} s, published {CROSSING:e} s"
+    );
+}
+
+#[test]
+#[ignore = "about a minute in release mode"]
+fn switching_follows_the_published_solutions() {
+    let config = config();
+    let mut sim = Simulation::build(&config).unwrap();
+    let (s, crossing) = switch(&mut sim, &config);
+    assert_close(s, S_STATE, 1e-3, "S state");
+    assert!(
+        (crossing - CROSSING).abs() < 2e-12,
+        "⟨mx⟩ crosses zero at {crossing:e} s, published {CROSSING:e} s"
+    );
+    // ⟨my⟩ still rings at 1 ns, fast enough that timing differences between
+    // solvers of a few ps show at the 1e-2 level
+    assert!(sim.run_until(1e-9, u64::MAX).unwrap());
+    assert_close(sim.average(), AT_1NS, 1e-2, "at 1 ns");
+}
@@ -0,0 +1,130 @@
+//! Spectra of synthetic signals of known frequencies: the ringdown spectra
+//! of `nez analyze fmr` and the dispersion maps of `nez analyze
+//! dispersion`.
+
+use nalgebra::Vector3;
+use std::f64::consts::PI;
+
+use nez::{
+    Config, Mesh, Simulation, VectorField, ZarrOutput,
+    dispersion::{self, Axis, Component},
+    spectrum::{Spectrum, Window},
+};
+
+/// Frequency index of the highest power of series `s`
+fn peak(spectrum: &Spectrum, s: usize) -> usize {
+    (0..spectrum.f.len())
+        .max_by(|&a, &b| spectrum.at(a, s).total_cmp(&spectrum.at(b, s)))
+        .unwrap()
+}
+
+#[test]
+fn sines_peak_at_their_frequencies_with_their_power() {
+    // 1000 samples 1 ps apart, frequencies every 1 GHz: a sine of 10 GHz and
+    // amplitude 1 and one of 25 GHz and amplitude 2 on top of a constant
+    let (nt, dt) = (1000, 1e-12);
+    let times: Vec<f64> = (0..nt).map(|k| k as f64 * dt).collect();
+    let signal = |t: f64| {
+        vec![
+            0.3 + (2.0 * PI * 10e9 * t).sin(),
+            2.0 * (2.0 * PI * 25e9 * t + 1.0).sin(),
+        ]
+    };
+    let samples: Vec<Vec<f64>> = times.iter().map(|&t| signal(t)).collect();
+    for window in [Window::Hann, Window::Hamming, Window::Rectangular] {
+        let spectrum = Spectrum::compute(&times, &samples, window).unwrap();
+        assert_eq!(spectrum.series, 2);
+        assert_eq!(spectrum.f.len(), nt / 2 + 1);
+        assert!((spectrum.f[1] - 1e9).abs() < 1e-3);
+        for (s, f, power) in [(0, 10, 0.25), (1, 25, 1.0)] {
+            assert_eq!(peak(&spectrum, s), f, "{window:?}, series {s}");
+            // a²/4, within the leakage of a sine not quite periodic over the
+            // window
+            let found = spectrum.at(f, s);
+            assert!(
+                (found - power).abs() < 0.02 * power,
+                "{window:?}, series {s}: power {found} at its peak, expected {power}"
+            );
+        }
+        // the constant is removed
+        assert!(spectrum.at(0, 0) < 1e-3, "{window:?}");
+    }
+    // sampled unevenly, the series is interpolated onto the same grid
+    let jittered: Vec<f64> = times
+        .iter()
+        .enumerate()
+        .map(|(k, t)| {
+            if k == 0 || k == nt - 1 {
+                *t
+            } else {
+                t + 0.2 * dt * (k as f64).sin()
+            }
+        })
+        .collect();
+    let samples: Vec<Vec<f64>> = jittered.iter().map(|&t| signal(t)).collect();
+    let spectrum = Spectrum::compute(&jittered, &samples, Window::Hann).unwrap();
+    assert!((spectrum.f[1] - 1e9).abs() < 1e-3);
+    assert_eq!(peak(&spectrum, 0), 10);
+    assert_eq!(peak(&spectrum, 1), 25);
+}
+
+#[test]
+fn plane_waves_land_at_their_wave_number_and_frequency() {
+    // 64 snapshots 1 ps apart of 64 cells of 2 nm across two rows: bins of
+    // 15.625 GHz and 2π / 128 nm. A wave of 4 wave numbers and 8 frequency
+    // bins travels towards +x along the first axis, one of 6 and 3 bins
+    // towards -x
+    let (n, d, nt, dt) = (64, 2e-9, 64, 1e-12);
+    let (k, f) = (2.0 * PI / (n as f64 * d), 1.0 / (nt as f64 * dt));
+    for (axis, size) in [(Axis::X, [n, 2, 1]), (Axis::Y, [2, n, 1])] {
+        let path = format!("memory://dispersion-{axis:?}");
+        let config = Config::parse(&format!(
+            r#"
+            [mesh]
+            nx = {}
+            ny = {}
+            dx = {d}
+            dy = {d}
+            [output]
+            path = "{path}"
+            "#,
+            size[0], size[1]
+        ))
+        .unwrap();
+        drop(Simulation::from_config(&config).unwrap());
+        let mut out = ZarrOutput::open(&path).unwrap();
+        let mesh = Mesh::new(size, [d, d, 1e-9]);
+        for frame in 0..nt {
+            let t = frame as f64 * dt;
+            let m = VectorField::from_fn(mesh.len(), |i| {
+                let [x, y, _] = mesh.coords(i);
+                let r = (if axis == Axis::X { x } else { y }) as f64 * d;
+                let forward = 0.1 * (4.0 * k * r - 2.0 * PI * 8.0 * f * t).cos();
+                let backward = 0.05 * (6.0 * k * r + 2.0 * PI * 3.0 * f * t).sin();
+                Vector3::new(1.0, 0.0, forward + backward)
+            });
+            out.write(frame, t, &m).unwrap();
+        }
+        let map = dispersion::compute(&out, axis, Component::Z).unwrap();
+        assert_eq!((map.k.len(), map.f.len()), (n, nt as usize / 2 + 1));
+        assert!((map.k[n / 2 + 4] - 4.0 * k).abs() < 1e-6 * k);
+        assert!((map.f[8] - 8.0 * f).abs() < 1e-6 * f);
+        let power = |i: usize, j: usize| map.power[i + n * j];
+        // (N Nt a / 2)² at each peak, nothing elsewhere
+        let total: f64 = map.power.iter().sum();
+        let forward = (n as f64 * nt as f64 * 0.1 / 2.0).powi(2);
+        let backward = (n as f64 * nt as f64 * 0.05 / 2.0).powi(2);
+        assert!(
+            (power(n / 2 + 4, 8) - forward).abs() < 1e-9 * forward,
+            "{axis:?}"
+        );
+        assert!(
+            (power(n / 2 - 6, 3) - backward).abs() < 1e-9 * forward,
+            "{axis:?}"
+        );
+        assert!(
+            (total - forward - backward).abs() < 1e-9 * forward,
+            "{axis:?}: power off the peaks"
+        );
+    }
+}
@@ -0,0 +1,378 @@
+//! Zarr stores written by a simulation and read back, held in memory
+//! (`memory://` paths) so that nothing touches the disk but where the
+//! metadata on disk is looked at.
+
+use nalgebra::Vector3;
+
+use nez::{Config, Simulation, VectorField, ZarrOutput, output::Precision};
+
+/// A small film writing every 10 steps to the store `name` in memory
+fn config(name: &str, precision: Precision) -> Config {
+    let mut config = Config::parse(
+        r#"
+        [mesh]
+        nx = 16
+        ny = 8
+        [params]
+        alpha = 0.1
+        h_ext = [0.0, 0.0, 0.1]
+        [initial]
+        m = [1.0, 0.2, 0.1]
+        [output]
+        every = 10
+        "#,
+    )
+    .unwrap();
+    config.output.path = format!("memory://{name}").into();
+    config.output.precision = precision;
+    config
+}
+
+#[test]
+fn snapshots_read_back_at_every_precision() {
+    for (precision, resolution) in [
+        (Precision::F64, 0.0),
+        (Precision::F32, 1e-7),
+        (Precision::I16, 2e-5),
+    ] {
+        let name = format!("{precision:?}");
+        let config = config(&name, precision);
+        let mut sim = Simulation::from_config(&config).unwrap();
+        sim.run(30).unwrap();
+        sim.flush().unwrap();
+        let out = ZarrOutput::open(&config.output.path).unwrap();
+        assert_eq!(out.frames().unwrap(), 3, "{name}");
+        assert_eq!(out.config().unwrap().output.precision, precision, "{name}");
+        let m = out.read(2).unwrap();
+        let worst = (0..m.len())
+            .map(|i| (m.get(i) - sim.m.get(i)).amax())
+            .fold(0.0, f64::max);
+        assert!(worst <= resolution, "{name}: off by {worst:e}");
+        let t = out.time(2).unwrap().unwrap();
+        assert!((t - sim.t).abs() < 1e-20, "{name}: t = {t:e} s");
+    }
+}
+
+#[test]
+fn metadata_of_the_snapshots_keeps_their_attributes_as_they_grow() {
+    // the frames are stored through handles of the writer thread, which must
+    // see the attributes written after the arrays were created
+    let dir = std::env::temp_dir().join(format!("nez-store-{}", std::process::id()));
+    let mut config = config("", Precision::I16);
+    config.output.path = dir.join("grown.zarr");
+    let mut sim = Simulation::from_config(&config).unwrap();
+    sim.run(30).unwrap();
+    sim.flush().unwrap();
+    let metadata = |array: &str| {
+        let json = std::fs::read_to_string(config.output.path.join(array).join("zarr.json"));
+        serde_json::from_str::<serde_json::Value>(&json.unwrap()).unwrap()
+    };
+    let m = metadata("m");
+    assert_eq!(m["shape"], serde_json::json!([3, 1, 8, 16, 3]));
+    assert_eq!(m["attributes"]["dx"], config.mesh.dx);
+    assert_eq!(m["attributes"]["scale_factor"], 1.0 / 32767.0);
+    let t = metadata("t");
+    assert_eq!(t["shape"], serde_json::json!([3]));
+    assert_eq!(t["attributes"]["unit"], "s");
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn every_codec_reads_back_in_chunks_and_shards() {
+    // chunks of a quarter frame, two frames to a shard, the last one half
+    // full; codecs left out of the build are refused when parsing
+    for (codec, built) in [
+        ("none", true),
+        ("gzip", true),
+        ("zstd", cfg!(feature = "zstd")),
+        ("blosc", cfg!(feature = "blosc")),
+        ("lz4", cfg!(feature = "blosc")),
+    ] {
+        let text = format!(
+            r#"
+            [mesh]
+            nx = 16
+            ny = 8
+            [params]
+            alpha = 0.1
+            [initial]
+            m = [1.0, 0.2, 0.1]
+            [output]
+            path = "memory://codec-{codec}"
+            every = 10
+            codec = "{codec}"
+            precision = "f32"
+            chunk = [1, 4, 8]
+            shard = 2
+            "#
+        );
+        let config = match Config::parse(&text) {
+            Ok(config) => config,
+            Err(err) => {
+                assert!(!built, "{codec}: {err}");
+                assert!(err.to_string().contains("feature"), "{codec}: {err}");
+                continue;
+            }
+        };
+        assert!(built, "{codec} accepted without its feature");
+        let mut sim = Simulation::from_config(&config).unwrap();
+        sim.run(30).unwrap();
+        sim.flush().unwrap();
+        let out = ZarrOutput::open(&config.output.path).unwrap();
+        assert_eq!(out.frames().unwrap(), 3, "{codec}");
+        let m = out.read(2).unwrap();
+        let worst = (0..m.len())
+            .map(|i| (m.get(i) - sim.m.get(i)).amax())
+            .fold(0.0, f64::max);
+        assert!(worst <= 1e-7, "{codec}: off by {worst:e}");
+    }
+    // levels out of range, and chunks not dividing the mesh
+    for (output, error) in [
+        ("level = 12", "level must lie in 0..=9"),
+        (r#"chunk = [1, 3, 8]"#, "must divide the mesh size"),
+    ] {
+        let text = format!("[mesh]\nnx = 16\nny = 8\n[output]\n{output}");
+        let Err(err) = Config::parse(&text) else {
+            panic!("{output} accepted")
+        };
+        assert!(err.to_string().contains(error), "{err}");
+    }
+}
+
+#[test]
+fn run_resumes_from_a_store_in_memory() {
+    let config = config("resume", Precision::F64);
+    let mut sim = Simulation::from_config(&config).unwrap();
+    sim.checkpoint_every = 10;
+    sim.run(20).unwrap();
+    let (mut resumed, recorded) = Simulation::resume(&config.output.path).unwrap();
+    assert_eq!(recorded.mesh, config.mesh);
+    assert_eq!(resumed.step, 20);
+    sim.run(10).unwrap();
+    resumed.run(10).unwrap();
+    assert!((resumed.average() - sim.average()).amax() < 1e-15);
+    assert_ne!(sim.average(), Vector3::new(1.0, 0.2, 0.1).normalize());
+}
+
+#[test]
+fn thermal_run_resumed_from_a_checkpoint_repeats_the_uninterrupted_one() {
+    // the random numbers pick up where the checkpoint left them
+    let thermal = |name: &str| {
+        let mut config = config(name, Precision::F64);
+        let extra = Config::parse(
+            r#"
+            [langevin]
+            temperature = 300.0
+            seed = 11
+            "#,
+        )
+        .unwrap();
+        config.langevin = extra.langevin;
+        config
+    };
+    let whole = thermal("whole");
+    let mut sim = Simulation::from_config(&whole).unwrap();
+    sim.run(40).unwrap();
+    sim.flush().unwrap();
+
+    let halves = thermal("halves");
+    let mut first = Simulation::from_config(&halves).unwrap();
+    first.run(20).unwrap();
+    first.checkpoint().unwrap();
+    first.flush().unwrap();
+    drop(first);
+    let (mut resumed, _) = Simulation::resume(&halves.output.path).unwrap();
+    assert_eq!(resumed.step, 20);
+    resumed.run(20).unwrap();
+    resumed.flush().unwrap();
+
+    assert_eq!(resumed.step, sim.step);
+    assert_eq!(resumed.t, sim.t);
+    let state = |sim: &Simulation| sim.rng.as_ref().map(|r| r.state());
+    assert_eq!(state(&resumed), state(&sim));
+    assert!(state(&sim).is_some());
+    for i in 0..sim.mesh.len() {
+        assert_eq!(resumed.m.get(i), sim.m.get(i), "cell {i}");
+    }
+    let (whole, halves) = (
+        ZarrOutput::open(&whole.output.path).unwrap(),
+        ZarrOutput::open(&halves.output.path).unwrap(),
+    );
+    assert_eq!(halves.frames().unwrap(), whole.frames().unwrap());
+    assert_eq!(halves.frames().unwrap(), 4);
+    for frame in 0..4 {
+        assert_eq!(halves.time(frame).unwrap(), whole.time(frame).unwrap());
+    }
+    assert_ne!(sim.average(), Vector3::new(1.0, 0.2, 0.1).normalize());
+}
+
+#[test]
+fn fields_are_written_beside_m() {
+    let mut config = config("fields", Precision::F64);
+    let text = r#"
+        [demag]
+        [uniaxial]
+        k1 = 5e5
+        axis = [0.0, 0.0, 1.0]
+        [output.fields]
+        every = 10
+        b_eff = true
+        terms = ["demag", "uniaxial"]
+        energy_density = true
+        torque = true
+        "#;
+    let extra = Config::parse(&format!("[mesh]\nnx = 16\nny = 8\n{text}")).unwrap();
+    config.demag = extra.demag;
+    config.uniaxial = extra.uniaxial;
+    config.output.fields = extra.output.fields;
+    // frames at steps 0, 10, 20 and 30, as in `nez run`
+    let mut sim = Simulation::from_config(&config).unwrap();
+    sim.autosave().unwrap();
+    sim.run(30).unwrap();
+    sim.flush().unwrap();
+    let out = ZarrOutput::open(&config.output.path).unwrap();
+    let cells = sim.mesh.len();
+    for (name, values) in [
+        ("B_eff", 3),
+        ("B_demag", 3),
+        ("B_uniaxial", 3),
+        ("torque", 3),
+    ] {
+        assert_eq!(
+            out.read_field(name, 3).unwrap().len(),
+            values * cells,
+            "{name}"
+        );
+    }
+    assert!(out.read_field("B_eff", 4).is_err());
+
+    // the densities add up to the energy, and the torque is m × B_eff
+    let e = out.read_field("energy_density", 3).unwrap();
+    let total = e.iter().sum::<f64>() * sim.mesh.cell_volume();
+    let energy = sim.energy();
+    assert!(
+        (total - energy).abs() <= 1e-9 * energy.abs(),
+        "{total:e} J, {energy:e} J"
+    );
+    let b = out.read_field("B_eff", 3).unwrap();
+    let torque = out.read_field("torque", 3).unwrap();
+    for i in 0..cells {
+        let b = Vector3::new(b[3 * i], b[3 * i + 1], b[3 * i + 2]);
+        let expected = sim.m.get(i).cross(&b);
+        assert!((Vector3::from_column_slice(&torque[3 * i..3 * i + 3]) - expected).amax() < 1e-12);
+    }
+}
+
+#[test]
+fn probes_average_their_cells_into_the_table() {
+    let mut config = config("probes", Precision::F64);
+    let extra = Config::parse(
+        r#"
+        [mesh]
+        nx = 16
+        ny = 8
+        [[probes]]
+        name = "corner"
+        cell = [3, 2, 0]
+        [[probes]]
+        name = "left"
+        shape = { kind = "cuboid", min = [0.0, 0.0, 0.0], max = [10e-9, 20e-9, 1.0] }
+        "#,
+    )
+    .unwrap();
+    config.probes = extra.probes;
+    let mut sim = Simulation::from_config(&config).unwrap();
+    sim.run(20).unwrap();
+    let values = sim.observables();
+    let column = |name: &str| values.iter().find(|(k, _)| k == name).unwrap().1;
+    let corner = sim.m.get(sim.mesh.idx(3, 2, 0));
+    assert_eq!(column("corner_mx"), corner.x);
+    assert_eq!(column("corner_mz"), corner.z);
+    // the 4 x 8 cells whose centres lie within 10 nm of x = 0 and 20 nm of y = 0
+    let mut sum = Vector3::zeros();
+    for (x, y) in (0..4).flat_map(|x| (0..8).map(move |y| (x, y))) {
+        sum += sim.m.get(sim.mesh.idx(x, y, 0));
+    }
+    assert!((column("left_my") - sum.y / 32.0).abs() < 1e-15);
+}
+
+#[test]
+fn initial_state_is_taken_from_an_earlier_store() {
+    let config = config("earlier", Precision::F64);
+    let mut sim = Simulation::from_config(&config).unwrap();
+    sim.run(20).unwrap();
+    sim.flush().unwrap();
+    let stored = ZarrOutput::open(&config.output.path)
+        .unwrap()
+        .read(1)
+        .unwrap();
+    let mut next = Config::parse(
+        r#"
+        [mesh]
+        nx = 16
+        ny = 8
+        [initial]
+        store = "memory://earlier"
+        frame = 1
+        "#,
+    )
+    .unwrap();
+    let same = Simulation::build(&next).unwrap();
+    assert_eq!(same.m.get(37), stored.get(37));
+    // twice as fine: the average survives the interpolation
+    next.mesh.nx = 32;
+    next.mesh.dx /= 2.0;
+    let fine = Simulation::build(&next).unwrap();
+    assert!((fine.average() - same.average()).amax() < 1e-3);
+    next.initial.frame = Some(9);
+    let Err(err) = Simulation::build(&next) else {
+        panic!("a missing frame was read")
+    };
+    let err = err.to_string();
+    assert!(err.contains("no frame 9"), "{err}");
+}
+
+#[test]
+fn initial_state_is_cropped_or_padded_to_a_different_sample() {
+    let config = config("extent", Precision::F64);
+    let mut sim = Simulation::from_config(&config).unwrap();
+    // a direction of its own in every column along x
+    let column = |x: usize| Vector3::new(1.0, x as f64 / 8.0, 0.5).normalize();
+    sim.m = VectorField::from_fn(sim.mesh.len(), |i| column(sim.mesh.coords(i)[0]));
+    sim.save().unwrap();
+    sim.flush().unwrap();
+    let mut next = Config::parse(
+        r#"
+        [mesh]
+        nx = 8
+        ny = 8
+        [initial]
+        store = "memory://extent"
+        m = [0.0, 0.0, 1.0]
+        "#,
+    )
+    .unwrap();
+    // half as long: the middle half of the columns
+    let cropped = Simulation::build(&next).unwrap();
+    for i in 0..cropped.mesh.len() {
+        let [x, ..] = cropped.mesh.coords(i);
+        assert!((cropped.m.get(i) - column(x + 4)).amax() < 1e-12, "x = {x}");
+    }
+    // twice as long, at half the resolution: the columns, two by two, in
+    // the middle, and `m` on either side
+    next.mesh.nx = 16;
+    next.mesh.dx *= 2.0;
+    let padded = Simulation::build(&next).unwrap();
+    for i in 0..padded.mesh.len() {
+        let [x, ..] = padded.mesh.coords(i);
+        let expected = match x {
+            4..12 => (column(2 * (x - 4)) + column(2 * (x - 4) + 1)).normalize(),
+            _ => Vector3::z(),
+        };
+        assert!(
+            (padded.m.get(i) - expected).amax() < 1e-12,
+            "x = {x}: {:?}",
+            padded.m.get(i)
+        );
+    }
+}
@@ -0,0 +1,202 @@
+//! Initial textures and what is read from them: the topological charge of
+//! a skyrmion, the position of a domain wall, and the states of
+//! `initial.texture` against their closed forms or expressions.
+
+use nalgebra::Vector3;
+use std::f64::consts::{FRAC_PI_2, TAU};
+
+use nez::{
+    Config, Mesh, Simulation, VectorField,
+    texture::Texture,
+    topology,
+    wall::{Direction, WallConfig},
+};
+
+/// A film of 64 × 64 cells of 1 nm
+fn film() -> Mesh {
+    Mesh::new([64, 64, 1], [1e-9; 3])
+}
+
+#[test]
+fn skyrmion_has_the_charge_of_its_core() {
+    // Q = (1/4π) ∫ m · (∂x m × ∂y m) = (mz(0) - mz(∞)) / 2 for a winding of
+    // one: the polarity of the core, Néel or Bloch
+    let mesh = film();
+    for polarity in [1.0, -1.0] {
+        for helicity in [0.0, FRAC_PI_2] {
+            let m = Texture::Skyrmion {
+                centre: None,
+                radius: 10e-9,
+                width: 3e-9,
+                polarity,
+                helicity,
+            }
+            .state(&mesh)
+            .unwrap();
+            let q = topology::charge(&mesh, &m);
+            assert!(
+                (q - polarity).abs() < 1e-3,
+                "Q = {q} with polarity {polarity} and helicity {helicity}"
+            );
+        }
+    }
+}
+
+#[test]
+fn uniform_and_tilted_states_have_no_charge() {
+    let mesh = film();
+    for direction in [Vector3::z(), Vector3::new(1.0, -2.0, 0.5).normalize()] {
+        let m = VectorField::from_fn(mesh.len(), |_| direction);
+        assert_eq!(topology::charge(&mesh, &m), 0.0, "{direction:?}");
+    }
+    // nor a single layer of cells
+    assert!(!topology::applies(&Mesh::new([64, 1, 1], [1e-9; 3])));
+}
+
+#[test]
+fn wall_is_found_where_it_was_placed() {
+    // mz = -tanh((x - X) / width) changes sign at X, nearly linearly over
+    // a cell of a fifth of the width: within a hundredth of a cell
+    for axis in [Direction::X, Direction::Y] {
+        let mesh = match axis {
+            Direction::X => Mesh::new([64, 2, 1], [1e-9; 3]),
+            _ => Mesh::new([2, 64, 1], [1e-9; 3]),
+        };
+        let wall = WallConfig {
+            axis,
+            ..WallConfig::default()
+        };
+        let neel = |position: f64| {
+            Texture::Neel {
+                axis,
+                position: Some(position),
+                width: 5e-9,
+            }
+            .state(&mesh)
+            .unwrap()
+        };
+        for x in [20e-9, 31.5e-9, 32.3e-9, 50.05e-9] {
+            let found = wall.position(&mesh, &neel(x));
+            assert!(
+                (found - x).abs() < 1e-11,
+                "wall at {found:e} m, placed at {x:e} m"
+            );
+        }
+        // at or past the edges, no sign change is left to follow
+        for x in [0.0, -10e-9, 64e-9, 80e-9] {
+            let m = neel(x);
+            assert!(wall.position(&mesh, &m).is_nan(), "wall at {x:e} m");
+            assert_eq!(wall.offset(&mesh, &m), None);
+        }
+    }
+}
+
+/// The initial state of a 32 × 32 film of 1 nm cells from `texture`
+fn initial(texture: &str) -> (Mesh, VectorField) {
+    let config = Config::parse(&format!(
+        r#"
+        [mesh]
+        nx = 32
+        ny = 32
+        dx = 1e-9
+        dy = 1e-9
+        dz = 1e-9
+        [initial]
+        texture = {texture}
+        "#
+    ))
+    .unwrap();
+    let sim = Simulation::build(&config).unwrap();
+    (sim.mesh, sim.m)
+}
+
+/// Assert that `m` at the cell (`x`, `y`) of `mesh` is `expected`
+fn assert_at(mesh: &Mesh, m: &VectorField, [x, y]: [usize; 2], expected: Vector3<f64>, tol: f64) {
+    let found = m.get(mesh.idx(x, y, 0));
+    assert!(
+        (found - expected).amax() <= tol,
+        "m = {found:?} at [{x}, {y}], expected {expected:?}"
+    );
+}
+
+#[test]
+fn textures_turn_as_their_closed_forms() {
+    // walls about the middle of the mesh, x = 16 nm: (sech u, 0, -tanh u)
+    // through their axis, (0, sech u, -tanh u) across it, u = (x - 16 nm) / w
+    for (kind, across) in [("neel", false), ("bloch", true)] {
+        let (mesh, m) = initial(&format!(r#"{{ kind = "{kind}", width = 4e-9 }}"#));
+        for x in [0, 10, 15, 16, 25] {
+            let u = (x as f64 + 0.5 - 16.0) / 4.0;
+            let turned = 1.0 / u.cosh();
+            let expected = match across {
+                false => Vector3::new(turned, 0.0, -u.tanh()),
+                true => Vector3::new(0.0, turned, -u.tanh()),
+            };
+            assert_at(&mesh, &m, [x, 7], expected, 1e-12);
+        }
+    }
+    // a helix of a 16 nm pitch along y turning from z towards x
+    let (mesh, m) = initial(r#"{ kind = "helix", axis = "y", pitch = 16e-9 }"#);
+    for y in [0, 3, 8, 21] {
+        let phase = TAU * (y as f64 + 0.5) / 16.0;
+        let expected = Vector3::new(phase.sin(), 0.0, phase.cos());
+        assert_at(&mesh, &m, [5, y], expected, 1e-12);
+    }
+    // a vortex curling counterclockwise around an up core at the middle
+    let (mesh, m) = initial(r#"{ kind = "vortex", core = 4e-9 }"#);
+    assert!(m.get(mesh.idx(16, 16, 0)).z > 0.95);
+    for (x, y) in [(31, 16), (16, 0), (2, 29)] {
+        let (dx, dy) = (x as f64 - 15.5, y as f64 - 15.5);
+        let curl = Vector3::new(-dy, dx, 0.0).normalize();
+        assert_at(&mesh, &m, [x, y], curl, 1e-3);
+    }
+    // a skyrmion centred on a cell: down at its core, up far away, and
+    // in-plane a radius away, outwards for a Néel one, circling for Bloch
+    for (helicity, turned) in [(0.0, Vector3::x()), (FRAC_PI_2, Vector3::y())] {
+        let (mesh, m) = initial(&format!(
+            r#"{{ kind = "skyrmion", centre = [16.5e-9, 16.5e-9, 0.5e-9], radius = 8e-9, width = 2e-9, helicity = {helicity} }}"#
+        ));
+        assert_at(&mesh, &m, [16, 16], -Vector3::z(), 1e-12);
+        assert_at(&mesh, &m, [24, 16], turned, 1e-12);
+        assert_at(&mesh, &m, [0, 0], Vector3::z(), 1e-2);
+    }
+    // random: unit vectors, the same for the same seed
+    let (_, a) = initial(r#"{ kind = "random", seed = 4 }"#);
+    let (_, b) = initial(r#"{ kind = "random", seed = 4 }"#);
+    let (_, c) = initial(r#"{ kind = "random", seed = 5 }"#);
+    assert!((0..a.len()).all(|i| (a.get(i).norm() - 1.0).abs() < 1e-12));
+    assert_eq!(a, b);
+    assert_ne!(a, c);
+    let mean: Vector3<f64> = (0..a.len()).map(|i| a.get(i)).sum::<Vector3<f64>>() / a.len() as f64;
+    assert!(mean.norm() < 0.1, "⟨m⟩ = {mean:?}");
+}
+
+#[test]
+fn expression_texture_is_evaluated_at_the_cell_centres() {
+    // a cone about z, normalized: (x, y, 2 nm) / |..| from the corner
+    let (mesh, m) = initial(r#"{ kind = "expr", mx = "x", my = "y", mz = "2e-9" }"#);
+    for (x, y) in [(0, 0), (3, 1), (31, 20)] {
+        let r = Vector3::new(x as f64 + 0.5, y as f64 + 0.5, 2.0);
+        assert_at(&mesh, &m, [x, y], r.normalize(), 1e-12);
+    }
+    // components left out are 0
+    let (mesh, m) = initial(r#"{ kind = "expr", my = "x - 10e-9" }"#);
+    assert_at(&mesh, &m, [3, 3], -Vector3::y(), 0.0);
+    assert_at(&mesh, &m, [12, 3], Vector3::y(), 0.0);
+    // and cells without a direction are refused
+    let config = Config::parse(
+        r#"
+        [mesh]
+        nx = 4
+        dx = 1e-9
+        [initial]
+        texture = { kind = "expr", mz = "x - 2.5e-9" }
+        "#,
+    )
+    .unwrap();
+    let Err(err) = Simulation::build(&config) else {
+        panic!("a texture vanishing in a cell was accepted")
+    };
+    let err = err.to_string();
+    assert!(err.contains("no direction in cell [2, 0, 0]"), "{err}");
+}
@@ -0,0 +1,145 @@
+//! Temperature: Mₛ and `a_ex` scaled cell by cell by their laws, the
+//! longitudinal relaxation of the LLB equation, and the equilibrium that the
+//! thermal field of `[langevin]` samples.
+
+use nalgebra::Vector3;
+
+use nez::{Config, Simulation, monte_carlo::K_B, params::MU0};
+
+const GAMMA: f64 = 1.760859e11;
+
+#[test]
+fn material_values_follow_the_temperature_of_every_cell() {
+    // from 0 K at x = 0 to 1000 K at the far end, past the Curie temperature
+    let (curie, a_ex, mu0_ms) = (800.0, 1.3e-11, 1.0);
+    let config = Config::parse(&format!(
+        r#"
+        [mesh]
+        nx = 16
+        dx = 2e-9
+        [params]
+        a_ex = {a_ex}
+        mu0_ms = {mu0_ms}
+        [temperature]
+        map = {{ expr = "1000 * x / 32e-9" }}
+        ms = {{ kind = "curie", curie = {curie}, exponent = 0.5 }}
+        a_ex = {{ kind = "ms", exponent = 1.7 }}
+        "#
+    ))
+    .unwrap();
+    let mut sim = Simulation::build(&config).unwrap();
+    for i in 0..16 {
+        let t = 1000.0 * (i as f64 + 0.5) / 16.0;
+        let ms = (1.0 - t / curie).max(0.0).sqrt();
+        let p = &sim.params;
+        assert!((p.mu0_ms_at(i) - mu0_ms * ms).abs() < 1e-12, "cell {i}");
+        assert!(
+            (p.a_ex_at(i) - a_ex * ms.powf(1.7)).abs() < 1e-12 * a_ex,
+            "cell {i}"
+        );
+        // from 800 K on, no moment to start with
+        assert_eq!(sim.m.get(i).norm() == 0.0, t >= curie, "cell {i} at {t} K");
+    }
+    // nor one that the dynamics would give them
+    sim.run(100).unwrap();
+    assert!((13..16).all(|i| sim.m.get(i).norm() == 0.0));
+}
+
+#[test]
+fn llb_relaxes_the_length_of_m_to_its_equilibrium() {
+    // with no field, u = |m|² follows du/dt = k (m_e² - u) u, k = γ′α∥/χ₀,
+    // the logistic u = m_e² / (1 + (m_e² / u₀ - 1) exp(-k m_e² t)) below T_c,
+    // and decays to 0 above it
+    let (alpha, curie, chi) = (0.1, 800.0, 1e-3);
+    for temperature in [512.0f64, 960.0] {
+        let config = Config::parse(&format!(
+            r#"
+            [mesh]
+            nx = 1
+            [params]
+            gamma = {GAMMA}
+            alpha = {alpha}
+            dt = 1e-15
+            h_ext = [0.0, 0.0, 0.0]
+            [solver]
+            method = "rk4"
+            [llb]
+            curie = {curie}
+            susceptibility = {chi}
+            temperature = {{ kind = "constant", value = {temperature} }}
+            "#
+        ))
+        .unwrap();
+        let mut sim = Simulation::build(&config).unwrap();
+        let llb = sim.llb.clone().unwrap();
+        let me2 = 1.0 - temperature / curie;
+        assert_eq!(llb.equilibrium(0.0), me2.max(0.0).sqrt());
+        let k = GAMMA / (1.0 + alpha * alpha) * (2.0 * alpha * temperature / (3.0 * curie)) / chi;
+        let m0 = Vector3::new(0.6, 0.0, 0.8);
+        sim.m.set(0, m0);
+        for until in [0.2e-12, 0.5e-12, 2e-12] {
+            sim.run_until(until, u64::MAX).unwrap();
+            let u = me2 / (1.0 + (me2 - 1.0) * (-k * me2 * sim.t).exp());
+            let m = sim.m.get(0);
+            assert!(
+                (m.norm() - u.sqrt()).abs() < 1e-6,
+                "|m| = {} at {} s and {temperature} K, expected {}",
+                m.norm(),
+                sim.t,
+                u.sqrt()
+            );
+            // without a field, only the length changes
+            assert!((m.normalize() - m0).amax() < 1e-12);
+        }
+    }
+}
+
+#[test]
+fn thermal_field_gives_the_langevin_function_of_the_zeeman_energy() {
+    // uncoupled macrospins in B along z sample exp(x cos θ), x = Mₛ V B / k_B T,
+    // so that ⟨m_z⟩ = L(x) = coth x - 1/x
+    let (mu0_ms, b, volume) = (1.0, 0.5, 8e-27);
+    for x in [1.0f64, 4.0] {
+        let temperature = mu0_ms / MU0 * volume * b / (K_B * x);
+        let config = Config::parse(&format!(
+            r#"
+            [mesh]
+            nx = 16
+            ny = 16
+            dx = 2e-9
+            dy = 2e-9
+            dz = 2e-9
+            [params]
+            gamma = {GAMMA}
+            alpha = 0.5
+            a_ex = 0.0
+            mu0_ms = {mu0_ms}
+            dt = 5e-14
+            h_ext = [0.0, 0.0, {b}]
+            [solver]
+            method = "heun"
+            [langevin]
+            temperature = {temperature}
+            seed = 3
+            "#
+        ))
+        .unwrap();
+        let mut sim = Simulation::build(&config).unwrap();
+        // a few relaxation times (1+α²)/(αγB) to forget the start
+        sim.run(2000).unwrap();
+        let (mut sum, mut count) = (0.0, 0);
+        for _ in 0..400 {
+            sim.run(20).unwrap();
+            sum += (0..sim.m.len()).map(|i| sim.m.get(i).z).sum::<f64>();
+            count += sim.m.len();
+        }
+        let mean = sum / count as f64;
+        let expected = 1.0 / x.tanh() - 1.0 / x;
+        // 256 spins over some 14 relaxation times of 570 steps: a few
+        // thousand independent samples of a spread below 0.5
+        assert!(
+            (mean - expected).abs() < 0.01,
+            "⟨m_z⟩ = {mean} at x = {x}, expected {expected}"
+        );
+    }
+}
@@ -0,0 +1,201 @@
+//! Current-driven torques against closed forms: the steady velocity of a
+//! domain wall pushed by a Zhang–Li torque, also within a window following
+//! it, and a macrospin turned by the damping-like torque of a
+//! spin-polarized current.
+
+use nalgebra::Vector3;
+
+use nez::{
+    Config, Simulation,
+    params::MU0,
+    torque::{HBAR, QE},
+};
+
+const GAMMA: f64 = 1.760859e11;
+
+/// Follow a macrospin of `extra` sections, with no field, and compare it
+/// with the closed form of a damping-like torque of strength `a` (T) along
+/// `p`: m turns towards p as tan(θ/2) = tan(θ₀/2) exp(-γat/(1+α²)) while
+/// precessing about it at dφ/dt = -αγa/(1+α²)
+fn follows_the_damping_like_torque(extra: &str, p: Vector3<f64>, a: f64) {
+    let alpha = 0.05;
+    let config = Config::parse(&format!(
+        r#"
+        [mesh]
+        nx = 1
+        dx = 2e-9
+        dy = 2e-9
+        dz = 2e-9
+        [params]
+        gamma = {GAMMA}
+        alpha = {alpha}
+        mu0_ms = 1.0
+        dt = 1e-13
+        h_ext = [0.0, 0.0, 0.0]
+        [solver]
+        method = "rk4"
+        {extra}
+        "#
+    ))
+    .unwrap();
+    let mut sim = Simulation::build(&config).unwrap();
+    // a frame with p as its pole
+    let e1 = p.cross(&Vector3::new(0.3, 0.5, 0.7)).normalize();
+    let e2 = p.cross(&e1);
+    let (theta0, phi0) = (1.0f64, 0.0);
+    let angles = |m: Vector3<f64>| (m.dot(&p).acos(), m.dot(&e2).atan2(m.dot(&e1)));
+    sim.m.set(0, theta0.sin() * e1 + theta0.cos() * p);
+    sim.run_until(1e-9, u64::MAX).unwrap();
+    let rate = GAMMA * a / (1.0 + alpha * alpha);
+    let theta = 2.0 * ((theta0 / 2.0).tan() * (-rate * sim.t).exp()).atan();
+    let phi = phi0 - alpha * rate * sim.t;
+    let (found, turned) = angles(sim.m.get(0));
+    assert!(
+        (found - theta).abs() < 1e-6,
+        "θ = {found} rad, expected {theta} rad"
+    );
+    assert!(
+        (turned - phi).abs() < 1e-6,
+        "φ = {turned} rad, expected {phi} rad"
+    );
+    // towards p for a > 0, away from it otherwise
+    assert!((theta0 - theta) * a.signum() > 0.1, "θ = {theta} rad");
+}
+
+/// A Néel wall at `position` in a wire of `nx` cells of 1 nm, pushed by a
+/// Zhang–Li torque of a current along -x, with the window following it if
+/// `center`; with (α, ξ) = (0.3, 0.6)
+fn driven_wall(nx: usize, position: f64, center: bool) -> Simulation {
+    let (alpha, xi) = (0.3, 0.6);
+    let config = Config::parse(&format!(
+        r#"
+        [mesh]
+        nx = {nx}
+        dx = 1e-9
+        dy = 1e-9
+        dz = 1e-9
+        [params]
+        alpha = {alpha}
+        a_ex = 1.5e-11
+        mu0_ms = 1.0
+        dt = 1e-13
+        h_ext = [0.0, 0.0, 0.0]
+        [uniaxial]
+        k1 = 8e5
+        axis = [0.0, 0.0, 1.0]
+        [dmi]
+        d = -2e-3
+        [zhang_li]
+        polarization = 1.0
+        xi = {xi}
+        current = {{ kind = "constant", value = [-1e12, 0.0, 0.0] }}
+        [wall]
+        center = {center}
+        [solver]
+        method = "rk4"
+        [initial]
+        texture = {{ kind = "neel", position = {position}, width = 4e-9 }}
+        "#
+    ))
+    .unwrap();
+    Simulation::build(&config).unwrap()
+}
+
+/// The steady velocity -(ξ/α) u of the wall of [`driven_wall`]
+fn steady_velocity(sim: &Simulation) -> f64 {
+    let zhang_li = sim.torques.zhang_li.as_ref().unwrap();
+    -0.6 / 0.3 * zhang_li.velocity(0.0, sim.params.ms()).x
+}
+
+#[test]
+fn zhang_li_drives_a_rigid_wall_at_xi_over_alpha_times_u() {
+    // below Walker breakdown a wall moves at v = -(ξ/α) u, against u for a
+    // current along u; the DMI holds the wall Néel, of the chirality it favours
+    let mut sim = driven_wall(96, 32e-9, false);
+    let wall = sim.wall.unwrap();
+    let expected = steady_velocity(&sim);
+    // past the transient of the wall angle, a few (1+α²)/(αγB_D)
+    sim.run_until(0.1e-9, u64::MAX).unwrap();
+    let (x0, t0) = (wall.position(&sim.mesh, &sim.m), sim.t);
+    sim.run_until(0.3e-9, u64::MAX).unwrap();
+    let (x1, t1) = (wall.position(&sim.mesh, &sim.m), sim.t);
+    let v = (x1 - x0) / (t1 - t0);
+    // the differences of (u·∇)m over cells a quarter of the wall width
+    // lose about a percent
+    assert!(
+        (v - expected).abs() < 0.02 * expected.abs(),
+        "v = {v} m/s, expected {expected} m/s"
+    );
+}
+
+#[test]
+fn window_keeps_a_driven_wall_at_its_centre() {
+    // a window of 64 cells over a wall travelling some 50 nm in 5000 steps,
+    // out of it were it not followed
+    let mut sim = driven_wall(64, 32e-9, true);
+    let wall = sim.wall.unwrap();
+    let expected = steady_velocity(&sim);
+    let travelled = |sim: &Simulation| wall.position(&sim.mesh, &sim.m) + sim.wall_shift;
+    sim.run(1000).unwrap();
+    let (x0, t0) = (travelled(&sim), sim.t);
+    for _ in 0..40 {
+        sim.run(100).unwrap();
+        let x = wall.position(&sim.mesh, &sim.m);
+        assert!((x - 32e-9).abs() <= 1e-9, "wall at {x:e} m of the window");
+    }
+    let (x1, t1) = (travelled(&sim), sim.t);
+    let moved = (x1 - 32e-9).abs();
+    assert!(moved > 32e-9, "the wall moved by {moved:e} m only");
+    // as fast as on a long wire, the ends of the window eight wall widths
+    // further away taking a little more
+    let v = (x1 - x0) / (t1 - t0);
+    assert!(
+        (v - expected).abs() < 0.02 * expected.abs(),
+        "v = {v} m/s, expected {expected} m/s"
+    );
+    // with no wall left to follow, the run ends
+    sim.m = nez::VectorField::from_fn(sim.mesh.len(), |_| Vector3::z());
+    let err = sim.run(1).unwrap_err().to_string();
+    assert!(err.contains("lost the wall"), "{err}");
+}
+
+#[test]
+fn slonczewski_torque_pulls_m_towards_the_fixed_layer() {
+    // with Λ = 1, ε = P/2 at every angle: a = ħ J P / (2 e Mₛ t)
+    let (polarization, ms, t) = (0.5, 1.0 / MU0, 2e-9);
+    for j in [1e11, -1e11] {
+        let fixed = Vector3::new(0.0, 0.6, 0.8);
+        let extra = format!(
+            r#"
+            [slonczewski]
+            polarization = {polarization}
+            fixed = [0.0, 0.6, 0.8]
+            current = {{ kind = "constant", value = {j} }}
+            "#
+        );
+        let a = HBAR * j * polarization / (2.0 * QE * ms * t);
+        follows_the_damping_like_torque(&extra, fixed, a);
+    }
+}
+
+#[test]
+fn spin_orbit_torque_pulls_m_towards_j_cross_z() {
+    // spins σ = Ĵ × ẑ, a = θ_SH ħ |J| / (2 e Mₛ t)
+    let (theta_sh, ms, t) = (0.3, 1.0 / MU0, 2e-9);
+    for (current, sigma) in [
+        ([1e11, 0.0], -Vector3::y()),
+        ([0.0, 1e11], Vector3::x()),
+        ([-1e11, 0.0], Vector3::y()),
+    ] {
+        let extra = format!(
+            r#"
+            [sot]
+            theta_sh = {theta_sh}
+            current = {{ kind = "constant", value = [{}, {}, 0.0] }}
+            "#,
+            current[0], current[1]
+        );
+        let a = theta_sh * HBAR * 1e11 / (2.0 * QE * ms * t);
+        follows_the_damping_like_torque(&extra, sigma, a);
+    }
+}
@@ -0,0 +1,74 @@
+//! Drive signals read from files beside the configuration, as measured
+//! waveforms are exported by an oscilloscope.
+
+use nalgebra::Vector3;
+use std::fs;
+
+use nez::Config;
+
+#[test]
+fn csv_columns_drive_the_field_by_name_and_index() {
+    let dir = std::env::temp_dir().join(format!("nez-waveform-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(
+        dir.join("scope.csv"),
+        "\u{feff}# pulse measured on the stripline\n\
+         \"Index\",\"Time (ns)\",\"Bx (mT)\",\"By (mT)\"\n\
+         0,0.0,0.0,1.0\n\
+         1,1.0,2.0,-1.0\n\
+         2,3.0,4.0,\n",
+    )
+    .unwrap();
+    fs::write(
+        dir.join("pulse.toml"),
+        r#"
+        [[zeeman]]
+        kind = "table"
+        file = "scope.csv"
+        columns = ["Time (ns)", "Bx (mT)", 0, 0]
+        time_scale = 1e-9
+        scale = 1e-3
+        "#,
+    )
+    .unwrap();
+    let config = Config::load(dir.join("pulse.toml"));
+    fs::remove_dir_all(&dir).unwrap();
+    let waveform = &config.unwrap().zeeman[0].waveform;
+    // Bx interpolated between the rows at 1 and 3 ns, the index column as By
+    // and Bz; the empty By of the last row is never read
+    let b = waveform.eval(2e-9);
+    assert!(
+        (b - Vector3::new(3e-3, 1.5e-3, 1.5e-3)).norm() < 1e-12,
+        "{b}"
+    );
+    assert_eq!(waveform.eval(10e-9), Vector3::new(4e-3, 2e-3, 2e-3));
+}
+
+#[test]
+fn unsorted_or_non_finite_csv_rows_are_rejected_with_their_line() {
+    let dir = std::env::temp_dir().join(format!("nez-waveform-bad-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(
+        dir.join("pulse.toml"),
+        "[[zeeman]]\nkind = \"table\"\nfile = \"scope.csv\"\n",
+    )
+    .unwrap();
+    let load = |csv: &str| {
+        fs::write(dir.join("scope.csv"), csv).unwrap();
+        match Config::load(dir.join("pulse.toml")) {
+            Ok(_) => panic!("{csv:?} was accepted"),
+            Err(e) => e.to_string(),
+        }
+    };
+    let unsorted = load("t,bx,by,bz\n0,0,0,0\n2e-9,1,0,0\n1e-9,2,0,0\n");
+    let infinite = load("t,bx,by,bz\n0,0,0,0\n1e-9,inf,0,0\n");
+    fs::remove_dir_all(&dir).unwrap();
+    assert!(
+        unsorted.contains("scope.csv:4: time 0.000000001 is before 0.000000002"),
+        "{unsorted}"
+    );
+    assert!(
+        infinite.contains("scope.csv:3: \"inf\" is not a finite number"),
+        "{infinite}"
+    );
+}